@@ -50,6 +50,10 @@ echo ""
 echo "=== Configuration ==="
 read -p "DM Server URL [https://api.coreon.build]: " SERVER_URL
 SERVER_URL=${SERVER_URL:-https://api.coreon.build}
+case "$SERVER_URL" in
+    http://localhost*|http://127.*|http://\[::1\]*) ;;
+    http://*) echo "Warning: plain HTTP sends the API key unencrypted. Use https:// if the server supports it." ;;
+esac
 
 read -p "API Key: " API_KEY
 if [ -z "$API_KEY" ]; then
@@ -74,41 +78,112 @@ DM_API_KEY=$API_KEY
 DM_SERVICE_DIR=$SERVICE_DIR
 DM_RESTART_COMMAND=$RESTART_CMD
 DM_POLL_INTERVAL=$POLL_INTERVAL
+# 폴링 간격 무작위 편차 (±퍼센트, 0이면 편차 없음)
+# DM_POLL_JITTER_PCT=20
 DM_BACKUP_DIR=/var/backups/sam-dm
+DM_STAGING_DIR=/var/lib/sam-dm/staging
+DM_CONTROL_DIR=/var/lib/sam-dm/control
+# 아티팩트 추출 작업 디렉토리 (기본: 서비스 디렉토리 옆, 서비스와 같은 파일시스템이어야 rename으로 설치)
+# DM_WORK_DIR=/var/lib/sam-dm/work
+# 아티팩트를 받아 두는 디렉토리 (기본: 백업 디렉토리)
+# DM_DOWNLOAD_DIR=/var/lib/sam-dm/downloads
+# 다운로드 전 여유 공간 확인 (파일시스템마다 아티팩트 크기 × 배수 필요, DM_SKIP_DISK_CHECK=1이면 생략)
+# DM_DISK_SPACE_FACTOR=3
+# DM_SKIP_DISK_CHECK=1
+# 설치된 버전보다 낮은 버전 설치 허용 (기본은 거부, 서버 배포의 allow_downgrade로 건별 허용)
+# DM_ALLOW_DOWNGRADE=1
+# 아티팩트 ed25519 서명 검증용 공개 키 (base64, 설정하면 서명 없는 아티팩트도 거부)
+# DM_UPDATE_PUBLIC_KEY=
+# 업데이트 제한 시간 (초)
+# DM_UPDATE_TIMEOUT_SECS=7200
+# DM_DOWNLOAD_IDLE_TIMEOUT_SECS=60
+# 다운로드 중 서버의 타겟 변경 확인 주기 (0이면 확인 안 함)
+# DM_SUPERSEDE_CHECK_SECS=30
+# 큰 아티팩트 분할 다운로드 (동시 연결 수, 1이면 사용 안 함 / 이 크기(MB) 이상만)
+# DM_DOWNLOAD_CONNECTIONS=4
+# DM_DOWNLOAD_PARALLEL_MIN_MB=64
+# 연결 오류/시간 초과/5xx 재시도 (횟수, 첫 대기 시간 ms)
+# DM_HTTP_RETRIES=3
+# DM_HTTP_RETRY_BASE_MS=500
+# 업데이트 전/후 명령 (DM_FROM_VERSION, DM_TO_VERSION 전달)
+# DM_PRE_UPDATE_SCRIPT=
+# DM_POST_UPDATE_SCRIPT=
+# DM_COMMAND_TIMEOUT_SECS=300
+# 설치 상태 서명 키 (비우면 API Key 사용)
+# DM_STATE_SECRET=
+# 서버가 배포한 버전별 설치 스크립트 실행 허용
+# DM_ALLOW_REMOTE_SCRIPTS=true
+# 설치 전 백업 생략 (보존할 것이 없는 초기 프로비저닝용, 이후 롤백 불가)
+# DM_BACKUP_REQUIRED=false
+# 남길 백업 수 (새 백업을 만든 뒤 오래된 것부터 삭제, 0이면 모두 유지)
+# DM_BACKUP_KEEP=3
+# 백업을 tar.gz로 압축 (false면 디렉토리로 복사)
+# DM_BACKUP_COMPRESS=false
+# 백업 제외 (쉼표 구분 glob) 및 롤백 후 재생성
+# DM_BACKUP_EXCLUDE=node_modules,.next/cache
+# 업데이트와 롤백에서 그대로 둘 경로 (쉼표 구분, 서비스 디렉토리 기준 상대 경로)
+# DM_PRESERVE_PATHS=.env,data,uploads
+# DM_ROLLBACK_REGENERATE=true
+# DM_INSTALL_COMMAND=npm ci --omit=dev
+# 빈 장비 첫 설치 후 첫 재시작 전에 한 번 실행 (초기 데이터 생성 등)
+# DM_BOOTSTRAP_COMMAND=
+# 역할별 USB 번들 선택 (비우면 서버 지정 역할 사용)
+# DM_CLIENT_ROLE=pos
+# 설치할 아티팩트의 제품 (.dm-product와 다르면 설치 거부, 비우면 서버 지정 제품 사용)
+# DM_EXPECTED_PRODUCT=pos
+# 평문 HTTP 서버 URL과 서버가 내려준 평문 HTTP 아티팩트/미러 URL 거부 (loopback 제외)
+# DM_REQUIRE_TLS=true
+# 조건부 GET 캐시(DM_CONTROL_DIR/http-cache.json) 끄기 (디버깅용)
+# DM_NO_HTTP_CACHE=1
+# 보안 업데이트(urgent)를 받은 뒤 적용될 때까지의 폴링 간격 (초, 기본 5)
+# DM_URGENT_POLL_INTERVAL=5
+# 서버가 장애 중 요청한 대기 시간(next_poll_secs, Retry-After)의 하한/상한 (초, 기본 5/3600)
+# DM_POLL_HINT_MIN_SECS=5
+# DM_POLL_HINT_MAX_SECS=3600
+# 일시 중지(dm-client pause) 중에도 보안 업데이트 설치
+# DM_URGENT_DURING_PAUSE=true
+# USB 자동 적용: 이 마운트 루트 아래에 나타난 USB 번들을 데몬이 적용 (버전마다 한 번, 검사 주기 초)
+# DM_USB_WATCH_DIR=/media
+# DM_USB_WATCH_INTERVAL=5
+# HTTP 헬스 체크 (2xx면 정상)
+# DM_HEALTH_CHECK_URL=http://localhost:3000/healthz
+# 헬스 체크 시도 횟수, 시도 간격(초), 프로브 제한 시간(초)
+# DM_HEALTH_CHECK_ATTEMPTS=5
+# DM_HEALTH_CHECK_INTERVAL_SECS=3
+# DM_HEALTH_CHECK_TIMEOUT_SECS=10
+# 헬스 체크 프로브 (JSON 배열, 모두 통과해야 정상. 비우면 DM_HEALTH_CHECK_URL/DM_HEALTH_CHECK_COMMAND 또는 서버 지정 프로브 사용)
+# DM_HEALTH_CHECKS='[{"type":"tcp","host":"127.0.0.1","port":5000},{"type":"file_age","path":"/run/app/heartbeat","max_age_secs":60}]'
+# A/B 파티션 디스크 이미지 업데이트 (deploy_type=image 버전)
+# DM_AB_SLOTS=A=/dev/mmcblk0p2,B=/dev/mmcblk0p3
+# DM_AB_ACTIVE_SLOT_COMMAND=findmnt -no SOURCE /
+# DM_AB_SWITCH_COMMAND=grub-editenv /boot/grub/grubenv set next_slot={slot}
+# DM_AB_CONFIRM_COMMAND=grub-editenv /boot/grub/grubenv set default_slot={slot}
+# DM_AB_REBOOT_COMMAND=reboot
+# 서버 없이 정적 manifest를 Polling하려면:
+# DM_MODE=static
+# DM_MANIFEST_URL=https://host/updates/manifest.json
 RUST_LOG=info,dm_client=debug
 EOF
 
 # Create backup directory
 sudo mkdir -p /var/backups/sam-dm
 
-# Create systemd service
-sudo tee /etc/systemd/system/sam-dm-client.service > /dev/null << EOF
-[Unit]
-Description=Sam DM Client
-After=network.target
-
-[Service]
-Type=simple
-EnvironmentFile=/etc/sam-dm/config.env
-ExecStart=/usr/local/bin/dm-client
-Restart=always
-RestartSec=10
-
-[Install]
-WantedBy=multi-user.target
-EOF
+# Remove the unit written by earlier versions of this installer
+if [ -f /etc/systemd/system/sam-dm-client.service ]; then
+    sudo systemctl disable --now sam-dm-client || true
+    sudo rm -f /etc/systemd/system/sam-dm-client.service
+fi
 
-# Enable and start service
-sudo systemctl daemon-reload
-sudo systemctl enable sam-dm-client
-sudo systemctl start sam-dm-client
+# Create, enable and start the systemd service
+sudo /usr/local/bin/dm-client install-service --config $CONFIG_DIR/config.env --enable
 
 echo ""
 echo "✅ Sam DM Client installed successfully!"
 echo ""
 echo "Commands:"
-echo "  sudo systemctl status sam-dm-client  # Check status"
-echo "  sudo systemctl restart sam-dm-client # Restart"
-echo "  sudo journalctl -u sam-dm-client -f  # View logs"
+echo "  sudo systemctl status dm-client     # Check status"
+echo "  sudo systemctl restart dm-client    # Restart"
+echo "  sudo journalctl -u dm-client -f     # View logs"
+echo "  sudo dm-client uninstall-service    # Remove the service"
 echo ""
 echo "Config: /etc/sam-dm/config.env"
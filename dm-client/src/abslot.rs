@@ -0,0 +1,422 @@
+//! A/B 파티션 디스크 이미지 업데이트 (DM_AB_SLOTS)
+//!
+//! 비활성 슬롯에 이미지를 쓰고 부트 플래그를 바꾼 뒤 재부팅한다. 새 슬롯으로 부팅해
+//! 서버 체크인이 성공하면 확정하고, 이전 슬롯으로 돌아오면(워치독 폴백) 실패로 보고한다.
+//!
+//! 순서가 중요하다: 이미지 쓰기와 검증이 끝나기 전에는 부트 플래그를 건드리지 않고,
+//! 플래그를 바꾸기 전에 재부팅 전후를 잇는 기록(`PendingImage`)을 상태 파일에 먼저 남긴다.
+//! 어느 단계에서 멈추더라도 현재 부팅한 슬롯은 그대로 남는다.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::api::BuildInfo;
+use crate::config::{ConfigError, Env};
+use crate::deadline::UpdatePhase;
+use crate::progress::{Progress, Unit};
+use crate::updater::Updater;
+
+/// 이미지 쓰기/검증 단위
+const WRITE_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// gzip 매직 바이트
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// 풀지 않는 압축 형식의 시작 바이트 (그대로 쓰면 압축된 채로 슬롯에 남으므로 거부)
+const UNSUPPORTED_COMPRESSION: [(&[u8], &str); 5] = [
+    (&[0x28, 0xb5, 0x2f, 0xfd], "zstd"),
+    (&[0xfd, b'7', b'z', b'X', b'Z', 0x00], "xz"),
+    (b"BZh", "bzip2"),
+    (b"PK\x03\x04", "zip"),
+    (b"PK\x05\x06", "zip"),
+];
+
+/// 기본 부팅 ID 파일 (재부팅마다 바뀜)
+const DEFAULT_BOOT_ID_FILE: &str = "/proc/sys/kernel/random/boot_id";
+
+/// A/B 슬롯
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+    /// 슬롯 이름 (예: "A")
+    pub name: String,
+    /// 블록 장치 또는 (시뮬레이션용) 일반 파일 경로
+    pub device: String,
+}
+
+/// A/B 업데이트 설정 (DM_AB_SLOTS가 있을 때만)
+#[derive(Debug, Clone)]
+pub struct AbConfig {
+    /// 슬롯 목록 (DM_AB_SLOTS, 예: "A=/dev/mmcblk0p2,B=/dev/mmcblk0p3")
+    pub slots: Vec<Slot>,
+    /// 현재 부팅한 슬롯 이름 또는 장치를 출력하는 명령 (DM_AB_ACTIVE_SLOT_COMMAND)
+    pub active_slot_command: Option<String>,
+    /// 다음 부팅 슬롯 지정 명령, {slot}/{device} 치환 (DM_AB_SWITCH_COMMAND)
+    pub switch_command: Option<String>,
+    /// 새 슬롯 확정 명령, {slot}/{device} 치환 (DM_AB_CONFIRM_COMMAND, 선택)
+    pub confirm_command: Option<String>,
+    /// 재부팅 명령 (DM_AB_REBOOT_COMMAND, 기본 "reboot")
+    pub reboot_command: String,
+    /// 부팅 ID 파일 (DM_AB_BOOT_ID_FILE, 재부팅 여부 판단용)
+    pub boot_id_file: String,
+}
+
+impl AbConfig {
+    /// 환경 변수에서 읽기 (DM_AB_SLOTS가 없으면 None)
+    ///
+    /// DM_AB_SLOTS가 있으면 슬롯 형식과 필수 명령을 시작할 때 확인한다 (업데이트 때가 아니라).
+    pub(crate) fn from_env(env: &Env) -> Result<Option<Self>, ConfigError> {
+        let Some(slots) = env.non_empty("DM_AB_SLOTS")? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            slots: parse_slots(&slots)?,
+            active_slot_command: Some(env.required(
+                "DM_AB_ACTIVE_SLOT_COMMAND",
+                "a command printing the booted slot name or device when DM_AB_SLOTS is set",
+            )?),
+            switch_command: Some(env.required(
+                "DM_AB_SWITCH_COMMAND",
+                "a command selecting the next boot slot ({slot}/{device}) when DM_AB_SLOTS is set",
+            )?),
+            confirm_command: env.non_empty("DM_AB_CONFIRM_COMMAND")?,
+            reboot_command: env
+                .non_empty("DM_AB_REBOOT_COMMAND")?
+                .unwrap_or_else(|| "reboot".to_string()),
+            boot_id_file: env
+                .non_empty("DM_AB_BOOT_ID_FILE")?
+                .unwrap_or_else(|| DEFAULT_BOOT_ID_FILE.to_string()),
+        }))
+    }
+
+    /// 이미지 업데이트에 필요한 설정 확인
+    pub fn validate(&self) -> Result<()> {
+        if self.slots.len() != 2 {
+            anyhow::bail!(
+                "DM_AB_SLOTS must name exactly two slots as NAME=DEVICE (got {})",
+                self.slots.len()
+            );
+        }
+        if self.slots[0].name == self.slots[1].name || self.slots[0].device == self.slots[1].device {
+            anyhow::bail!("DM_AB_SLOTS slots must have distinct names and devices");
+        }
+        if self.active_slot_command.is_none() {
+            anyhow::bail!("DM_AB_ACTIVE_SLOT_COMMAND is required for image updates");
+        }
+        if self.switch_command.is_none() {
+            anyhow::bail!("DM_AB_SWITCH_COMMAND is required for image updates");
+        }
+        Ok(())
+    }
+
+    /// 슬롯 이름으로 찾기
+    pub fn slot(&self, name: &str) -> Result<&Slot> {
+        self.slots
+            .iter()
+            .find(|s| s.name == name)
+            .with_context(|| format!("Unknown A/B slot: {}", name))
+    }
+
+    /// 현재 부팅한 슬롯 (명령 출력이 슬롯 이름이나 장치 경로와 일치해야 함)
+    pub fn booted_slot(&self) -> Result<&Slot> {
+        let command = self
+            .active_slot_command
+            .as_deref()
+            .context("DM_AB_ACTIVE_SLOT_COMMAND is not set")?;
+        let output = Command::new("sh")
+            .args(["-c", command])
+            .output()
+            .with_context(|| format!("Failed to run active slot command: {}", command))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Active slot command failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let booted = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        self.slots
+            .iter()
+            .find(|s| s.name == booted || s.device == booted)
+            .with_context(|| format!("Active slot command printed {:?}, which is not in DM_AB_SLOTS", booted))
+    }
+
+    /// 현재 부팅한 슬롯이 아닌 슬롯 (업데이트 대상)
+    pub fn other_slot(&self, slot: &Slot) -> Result<&Slot> {
+        self.slots
+            .iter()
+            .find(|s| s.name != slot.name)
+            .context("DM_AB_SLOTS has no inactive slot")
+    }
+
+    /// 현재 부팅 ID (읽을 수 없으면 None)
+    pub fn boot_id(&self) -> Option<String> {
+        fs::read_to_string(&self.boot_id_file)
+            .ok()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+    }
+
+    /// 슬롯 명령 템플릿 치환 ({slot}, {device})
+    pub fn slot_command(template: &str, slot: &Slot) -> String {
+        template.replace("{slot}", &slot.name).replace("{device}", &slot.device)
+    }
+
+    /// 설정 보고용 요약
+    pub fn effective(&self) -> serde_json::Value {
+        serde_json::json!({
+            "slots": self
+                .slots
+                .iter()
+                .map(|s| format!("{}={}", s.name, s.device))
+                .collect::<Vec<_>>(),
+            "active_slot_command": self.active_slot_command,
+            "switch_command": self.switch_command,
+            "confirm_command": self.confirm_command,
+            "reboot_command": self.reboot_command,
+        })
+    }
+}
+
+/// 슬롯 명령 실행 (DM_COMMAND_TIMEOUT_SECS 적용, 0이 아닌 종료는 오류)
+pub fn run_slot_command(updater: &Updater, phase: UpdatePhase, template: &str, slot: &Slot) -> Result<()> {
+    let command = AbConfig::slot_command(template, slot);
+    tracing::info!("Running {} command: {}", phase, command);
+    let (status, stderr) = updater.run_command(&command, phase)?;
+    if !status.success() {
+        anyhow::bail!("{} command failed ({}): {}", phase, status, stderr.trim());
+    }
+    Ok(())
+}
+
+/// "A=/dev/sda2,B=/dev/sda3" 파싱 (이름과 장치가 서로 다른 슬롯 정확히 두 개)
+fn parse_slots(value: &str) -> Result<Vec<Slot>, ConfigError> {
+    let invalid = |reason: String| ConfigError::Invalid {
+        var: "DM_AB_SLOTS",
+        reason,
+    };
+    let slots = value
+        .split(',')
+        .map(|entry| {
+            let (name, device) = entry
+                .split_once('=')
+                .map(|(name, device)| (name.trim(), device.trim()))
+                .filter(|(name, device)| !name.is_empty() && !device.is_empty())
+                .ok_or_else(|| invalid(format!("expected NAME=DEVICE, got {:?}", entry.trim())))?;
+            Ok(Slot {
+                name: name.to_string(),
+                device: device.to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, ConfigError>>()?;
+
+    if slots.len() != 2 {
+        return Err(invalid(format!("expected exactly two slots, got {}", slots.len())));
+    }
+    if slots[0].name == slots[1].name || slots[0].device == slots[1].device {
+        return Err(invalid("slots must have distinct names and devices".to_string()));
+    }
+    Ok(slots)
+}
+
+/// 재부팅 전후를 잇는 이미지 업데이트 기록 (상태 파일에 저장)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingImage {
+    pub version: String,
+    pub artifact_checksum: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_info: Option<BuildInfo>,
+    /// 업데이트 전 버전 (폴백 시 그대로 유지)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_version: Option<String>,
+    /// 업데이트 전 부팅 슬롯
+    pub from_slot: String,
+    /// 이미지를 쓴 슬롯
+    pub to_slot: String,
+    /// 재부팅 요청 시점의 부팅 ID (같으면 아직 재부팅 전)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_id: Option<String>,
+    pub written_at: DateTime<Utc>,
+}
+
+/// 슬롯 장치 쓰기
+///
+/// 블록 장치와 일반 파일을 같은 방식으로 다루므로, 루프백 파일이나 `losetup` 장치로
+/// 실제 파티션 없이 전체 흐름을 시험할 수 있다. 블록 장치는 크기를 넘는 이미지를 거부하고,
+/// 일반 파일은 이미지 크기에 맞춰 잘라낸다.
+pub struct SlotDevice {
+    path: PathBuf,
+}
+
+/// 슬롯에 쓴 결과
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrittenImage {
+    pub bytes: u64,
+    /// 쓴 내용(압축 해제 후)의 SHA256
+    pub sha256: String,
+}
+
+impl SlotDevice {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// 이미지 쓰기 (gzip이면 압축 해제) 후 fsync, 다시 읽어 검증
+    ///
+    /// 그 밖의 압축 형식(zstd, xz 등)은 슬롯을 건드리기 전에 거부한다.
+    pub fn write_image(&self, image: &Path) -> Result<WrittenImage> {
+        let mut source = BufReader::new(
+            File::open(image).with_context(|| format!("Failed to open image {}", image.display()))?,
+        );
+        let head = source.fill_buf().context("Failed to read image")?;
+        if let Some((_, format)) = UNSUPPORTED_COMPRESSION.iter().find(|(magic, _)| head.starts_with(magic)) {
+            anyhow::bail!(
+                "Image {} is {} compressed; only raw and gzip images can be written",
+                image.display(),
+                format
+            );
+        }
+        let is_gzip = head.starts_with(&GZIP_MAGIC);
+        let (mut reader, total): (Box<dyn Read>, u64) = if is_gzip {
+            (Box::new(GzDecoder::new(source)), 0)
+        } else {
+            let len = fs::metadata(image)?.len();
+            (Box::new(source), len)
+        };
+
+        let block_device = is_block_device(&self.path);
+        let capacity = if block_device { Some(self.capacity()?) } else { None };
+        if let Some(capacity) = capacity.filter(|c| total > *c) {
+            anyhow::bail!(
+                "Image ({} bytes) does not fit slot device {} ({} bytes)",
+                total,
+                self.path.display(),
+                capacity
+            );
+        }
+
+        let mut device = OpenOptions::new()
+            .write(true)
+            .create(!block_device)
+            .truncate(!block_device)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open slot device {}", self.path.display()))?;
+
+        let mut hasher = Sha256::new();
+        let mut written = 0u64;
+        let mut buf = vec![0u8; WRITE_CHUNK_SIZE];
+        let mut progress = Progress::new("Writing", total, Unit::Bytes);
+        loop {
+            let n = reader.read(&mut buf).context("Failed to read image")?;
+            if n == 0 {
+                break;
+            }
+            written += n as u64;
+            if capacity.is_some_and(|c| written > c) {
+                anyhow::bail!(
+                    "Image does not fit slot device {} ({} bytes)",
+                    self.path.display(),
+                    capacity.unwrap_or_default()
+                );
+            }
+            device.write_all(&buf[..n])?;
+            hasher.update(&buf[..n]);
+            progress.inc(n as u64);
+        }
+        progress.finish();
+        device.sync_all()?;
+
+        let image = WrittenImage {
+            bytes: written,
+            sha256: format!("{:x}", hasher.finalize()),
+        };
+        self.verify(&image)?;
+        Ok(image)
+    }
+
+    /// 장치 앞부분을 다시 읽어 쓴 내용과 비교
+    pub fn verify(&self, image: &WrittenImage) -> Result<()> {
+        let mut device = File::open(&self.path)?;
+        let mut hasher = Sha256::new();
+        let mut remaining = image.bytes;
+        let mut buf = vec![0u8; WRITE_CHUNK_SIZE];
+        let mut progress = Progress::new("Verifying", image.bytes, Unit::Bytes);
+        while remaining > 0 {
+            let want = remaining.min(buf.len() as u64) as usize;
+            device.read_exact(&mut buf[..want]).with_context(|| {
+                format!("Slot device {} is shorter than the written image", self.path.display())
+            })?;
+            hasher.update(&buf[..want]);
+            remaining -= want as u64;
+            progress.inc(want as u64);
+        }
+        progress.finish();
+
+        let actual = format!("{:x}", hasher.finalize());
+        if actual != image.sha256 {
+            anyhow::bail!(
+                "Slot device {} read-back mismatch (wrote {}, read {})",
+                self.path.display(),
+                image.sha256,
+                actual
+            );
+        }
+        Ok(())
+    }
+
+    /// 장치 크기
+    fn capacity(&self) -> Result<u64> {
+        let mut device = File::open(&self.path)?;
+        Ok(device.seek(SeekFrom::End(0))?)
+    }
+}
+
+#[cfg(unix)]
+fn is_block_device(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata(path).is_ok_and(|m| m.file_type().is_block_device())
+}
+
+#[cfg(not(unix))]
+fn is_block_device(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_slots_reads_two_named_devices() {
+        let slots = parse_slots(" A = /dev/mmcblk0p2 ,B=/dev/mmcblk0p3").unwrap();
+        assert_eq!(
+            slots,
+            [
+                Slot { name: "A".to_string(), device: "/dev/mmcblk0p2".to_string() },
+                Slot { name: "B".to_string(), device: "/dev/mmcblk0p3".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_slots_rejects_malformed_lists() {
+        let error = |value| parse_slots(value).unwrap_err().to_string();
+        assert_eq!(error("A=/dev/sda2,=/dev/sda3"), r#"Invalid DM_AB_SLOTS: expected NAME=DEVICE, got "=/dev/sda3""#);
+        assert_eq!(error("A=/dev/sda2,B="), r#"Invalid DM_AB_SLOTS: expected NAME=DEVICE, got "B=""#);
+        assert_eq!(error("A=/dev/sda2"), "Invalid DM_AB_SLOTS: expected exactly two slots, got 1");
+        assert_eq!(
+            error("A=/dev/sda2,B=/dev/sda3,C=/dev/sda4"),
+            "Invalid DM_AB_SLOTS: expected exactly two slots, got 3"
+        );
+        assert_eq!(error("A=/dev/sda2,A=/dev/sda3"), "Invalid DM_AB_SLOTS: slots must have distinct names and devices");
+        assert_eq!(error("A=/dev/sda2,B=/dev/sda2"), "Invalid DM_AB_SLOTS: slots must have distinct names and devices");
+    }
+}
@@ -1,29 +1,714 @@
 use anyhow::Result;
-use reqwest::Client;
+use chrono::{DateTime, Utc};
+use reqwest::{header, Client, RequestBuilder, Response, StatusCode};
+use std::collections::VecDeque;
+use sha2::{Digest, Sha256};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+
+use crate::chunked::{DownloadJournal, ParallelDownload};
+use crate::config::Config;
+use crate::error::ClientError;
+use crate::health::{self, HealthProbe};
+use crate::httpcache::HttpCache;
+use crate::progress::{Progress, Unit};
+use crate::retry::{CircuitBreaker, RetryPolicy};
+use crate::scripts::InstallScripts;
+use crate::survey::SurveyReport;
+use crate::usb::UsbManifest;
 
 /// 체크인 요청
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub struct CheckinRequest {
     pub current_version: Option<String>,
     pub status: String,
+    /// 데몬 인스턴스 식별자 (다중 에이전트 감지용)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_started_at: Option<DateTime<Utc>>,
+    /// 스테이징되어 활성화 대기 중인 버전
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub staged_version: Option<String>,
+    /// 실제 적용 설정의 해시
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_config_hash: Option<String>,
+    /// 실제 적용 설정 (변경 시에만 전송)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_config: Option<serde_json::Value>,
+    /// 장비 역할/그룹 (DM_CLIENT_ROLE 또는 서버 지정값)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// 기대 제품 (DM_EXPECTED_PRODUCT 또는 서버 지정값)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product: Option<String>,
+    /// 설치 상태(.dm-version 등)가 dm-client 밖에서 수정됨
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub state_tampered: bool,
+    /// current_version으로 설치한 아티팩트의 SHA256 (설치 상태에 기록된 값, 모르면 생략)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installed_checksum: Option<String>,
+    /// 마지막으로 받아 저장한 서버 설정의 해시 (서버는 설정이 바뀌었을 때만 다시 보냄)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// 일시 중지 중이지만 보안 업데이트는 받음 (DM_URGENT_DURING_PAUSE)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub urgent_during_pause: bool,
+    /// 이 체크인 전에 실제로 기다린 시간 (초, 서버의 폴링 간격 분포용)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll_interval_secs: Option<u64>,
+}
+
+/// 버전 빌드 정보 (출처 추적용)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_time: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl BuildInfo {
+    pub fn is_empty(&self) -> bool {
+        self.git_commit.is_none() && self.build_time.is_none() && self.metadata.is_none()
+    }
 }
 
 /// 체크인 응답
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CheckinResponse {
-    pub action: String, // "none" or "update"
+    pub action: String, // "none", "update", "stage", "activate", "unstage", "restart", "rollback", "survey"
+    /// 재시작/롤백/조사 작업 ID (결과를 report_action_result 또는 report_survey_result로 보고)
+    #[serde(default)]
+    pub action_id: Option<String>,
     pub target_version: Option<String>,
     pub artifact_url: Option<String>,
+    /// 다운로드 시도 순서 (서버, 미러...). 미러는 API 키 없이 URL 그대로 요청
+    #[serde(default)]
+    pub artifact_urls: Vec<String>,
     pub checksum: Option<String>,
+    /// 아티팩트 크기 (bytes, 다운로드 전 여유 공간 확인용. 예전 서버는 보내지 않음)
+    #[serde(default)]
+    pub artifact_size: Option<u64>,
+    /// 아티팩트 ed25519 서명 (base64, DM_UPDATE_PUBLIC_KEY로 검증)
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// 타겟 버전의 빌드 정보
+    #[serde(default)]
+    pub build_info: Option<BuildInfo>,
+    /// 서버 안내 메시지 (예: 배포 취소 사유)
+    #[serde(default)]
+    pub note: Option<String>,
+    /// 서버 경고 (예: 동일 API Key로 여러 데몬 실행 중)
+    #[serde(default)]
+    pub warning: Option<String>,
+    /// 서버가 실제 적용 설정 전체를 다시 요청
+    #[serde(default)]
+    pub effective_config_requested: bool,
+    /// 동일한 아티팩트가 설치되어 있어도 다시 설치
+    #[serde(default)]
+    pub force_reinstall: bool,
+    /// 설치된 버전보다 낮은 타겟도 설치 (배포의 allow_downgrade, 의도한 되돌리기)
+    #[serde(default)]
+    pub allow_downgrade: bool,
+    /// 서버가 지정한 설정 (예: 역할)
+    #[serde(default)]
+    pub config: Option<PushedConfig>,
+    /// config의 해시 (다음 체크인에 보내면 바뀌기 전까지 config 생략)
+    #[serde(default)]
+    pub config_hash: Option<String>,
+    /// 타겟 버전 설치 스크립트의 SHA256 (내용은 스크립트 API로 받음)
+    #[serde(default)]
+    pub scripts: Option<InstallScripts>,
+    /// 배포 유형 ("image"면 A/B 슬롯에 쓰는 디스크 이미지, 없으면 애플리케이션 아카이브)
+    #[serde(default)]
+    pub deploy_type: Option<String>,
+    /// 보안 수정 버전 (다음 폴링 간격을 줄이고, 허용하면 일시 중지 중에도 설치)
+    #[serde(default)]
+    pub urgent: bool,
+    /// 조사 항목 (action이 "survey"일 때, 허용 목록에 있는 항목만 실행)
+    #[serde(default)]
+    pub survey: Vec<String>,
+    /// 서버가 요청한 다음 폴링까지의 대기 시간 (장애 중 부하 조절, 다음 대기 한 번에만 적용)
+    #[serde(default)]
+    pub next_poll_secs: Option<u64>,
+}
+
+impl CheckinResponse {
+    /// 디스크 이미지 배포 여부
+    pub fn is_image(&self) -> bool {
+        self.deploy_type.as_deref() == Some("image")
+    }
+
+    /// 아티팩트를 받을 URL 목록 (순서대로 시도)
+    pub fn artifact_sources(&self) -> Vec<String> {
+        if !self.artifact_urls.is_empty() {
+            return self.artifact_urls.clone();
+        }
+        self.artifact_url.iter().cloned().collect()
+    }
+}
+
+/// 서버가 체크인 응답으로 내려주는 설정
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PushedConfig {
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub product: Option<String>,
+    #[serde(default)]
+    pub backup_exclude: Option<Vec<String>>,
+    /// 설치와 롤백에서 그대로 둘 경로 (DM_PRESERVE_PATHS가 없을 때 사용)
+    #[serde(default)]
+    pub preserve_paths: Option<Vec<String>>,
+    #[serde(default)]
+    pub rollback_regenerate: Option<bool>,
+    /// 헬스 체크 프로브 목록 (해석은 `health_probes`에서, 모르는 종류가 있어도 체크인은 계속)
+    #[serde(default)]
+    pub health_checks: Option<Vec<serde_json::Value>>,
+    /// 기존 단일 헬스 체크 URL (health_checks가 없을 때 http 프로브로 사용)
+    #[serde(default)]
+    pub health_check_url: Option<String>,
+    #[serde(default)]
+    pub health_check_timeout: Option<i64>,
+    /// 설치 경로 (원격으로 바꾸지 않음, 로컬 설정과 다르면 경고만)
+    #[serde(default)]
+    pub service_dir: Option<String>,
+    #[serde(default)]
+    pub restart_command: Option<String>,
+    #[serde(default)]
+    pub pre_update_script: Option<String>,
+    #[serde(default)]
+    pub post_update_script: Option<String>,
+    #[serde(default)]
+    pub rollback_on_failure: Option<bool>,
+}
+
+impl PushedConfig {
+    /// 서버가 지정한 값을 환경 변수 기반 설정 위에 덮어씀. 적용한 항목 이름 반환
+    ///
+    /// 명령(restart_command, pre/post_update_script)은 서버가 준 코드를 실행하는 것이므로
+    /// DM_ALLOW_REMOTE_SCRIPTS=true일 때만 적용한다. 설치 경로는 상태 파일과 백업이 걸려 있어 바꾸지 않는다.
+    pub fn apply_to(&self, config: &mut Config) -> Vec<&'static str> {
+        let mut applied = Vec::new();
+        if let Some(dir) = self.service_dir.as_deref().filter(|dir| *dir != config.service_dir) {
+            tracing::warn!(
+                "Ignoring server service_dir {} (install location is set locally: {})",
+                dir,
+                config.service_dir
+            );
+        }
+
+        let allow_commands = config.allow_remote_scripts;
+        let mut command = |name: &'static str, value: &Option<String>| {
+            let value = value.as_deref().filter(|v| !v.trim().is_empty())?;
+            if !allow_commands {
+                tracing::warn!("Ignoring server {} (DM_ALLOW_REMOTE_SCRIPTS is not enabled)", name);
+                return None;
+            }
+            applied.push(name);
+            Some(value.to_string())
+        };
+        if let Some(restart_command) = command("restart_command", &self.restart_command) {
+            config.restart_command = restart_command;
+        }
+        if let Some(script) = command("pre_update_script", &self.pre_update_script) {
+            config.pre_update_script = Some(script);
+        }
+        if let Some(script) = command("post_update_script", &self.post_update_script) {
+            config.post_update_script = Some(script);
+        }
+
+        if let Some(rollback) = self.rollback_on_failure {
+            config.rollback_on_failure = Some(rollback);
+            applied.push("rollback_on_failure");
+        }
+        if let Some(timeout) = self.health_check_timeout.filter(|t| *t > 0) {
+            config.health_check_timeout_secs = Some(timeout as u64);
+            applied.push("health_check_timeout");
+        }
+        applied
+    }
+
+    /// 서버 지정 헬스 체크 프로브 (health_checks > health_check_url)
+    ///
+    /// 해석하거나 검증할 수 없는 목록은 경고 후 무시한다 (새 서버의 프로브 종류 등).
+    pub fn health_probes(&self) -> Option<Vec<HealthProbe>> {
+        if let Some(values) = &self.health_checks {
+            let parsed: Result<Vec<HealthProbe>, String> = values
+                .iter()
+                .map(|value| {
+                    let probe: HealthProbe = serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+                    probe.validate()?;
+                    Ok(probe)
+                })
+                .collect();
+            match parsed {
+                Ok(probes) => return Some(probes),
+                Err(e) => tracing::warn!("Ignoring server health checks: {}", e),
+            }
+        }
+        self.health_check_url
+            .as_deref()
+            .filter(|url| !url.is_empty())
+            .map(|url| vec![health::legacy_http(url, self.health_check_timeout)])
+    }
+}
+
+/// 배치 체크인 항목 (게이트웨이가 대신 체크인하는 장비)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchCheckinEntry {
+    pub api_key: String,
+    #[serde(default)]
+    pub current_version: Option<String>,
+    #[serde(default = "default_batch_status")]
+    pub status: String,
 }
 
-/// 업데이트 결과 보고
+fn default_batch_status() -> String {
+    "online".to_string()
+}
+
+/// 배치 체크인 항목별 결과
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchCheckinResult {
+    pub status: u16,
+    #[serde(default)]
+    pub response: Option<CheckinResponse>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// 재시작/롤백 결과 보고
+#[derive(Debug, Serialize)]
+pub struct ActionResultRequest {
+    pub action_id: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+}
+
+/// 조사 결과 업로드
 #[derive(Debug, Serialize)]
+pub struct SurveyResultRequest {
+    pub action_id: String,
+    #[serde(flatten)]
+    pub report: SurveyReport,
+}
+
+/// 업데이트 결과 보고 (보고하지 못한 결과는 `state::PendingResult`로 저장)
+#[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateResultRequest {
     pub version: String,
     pub success: bool,
     pub error_message: Option<String>,
+    /// 설치를 건너뛴 이유 (예: "already_installed")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped_reason: Option<String>,
+    /// 스테이징만 완료 (활성화 대기)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub staged: bool,
+    /// 분류된 실패 원인 (예: "fs_read_only", "disk_full", "io_error")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+    /// 설치 전에 직접 계산한 아티팩트 체크섬 (출처 추적용)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified_checksum: Option<String>,
+    /// 아티팩트를 받은 곳 (서버 경로 또는 미러 URL, 쿼리 제외)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact_source: Option<String>,
+    /// 단계별 시각 (서버 SLA 보고용)
+    #[serde(default, skip_serializing_if = "PhaseTimes::is_empty")]
+    pub phases: PhaseTimes,
+    /// 추출한 트리를 설치한 방법 ("rename" 또는 "copy", 설치 단계까지 가지 않았으면 생략)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_strategy: Option<String>,
+    /// 빈 장비의 첫 설치 (백업/롤백 없이 설치)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bootstrap: bool,
+}
+
+/// 업데이트 단계별 시각
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhaseTimes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_started_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_completed_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_started_at: Option<DateTime<Utc>>,
+    /// 서비스 재시작 완료 (헬스 체크 시작)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restarted_at: Option<DateTime<Utc>>,
+    /// 헬스 체크 통과
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub healthy_at: Option<DateTime<Utc>>,
+}
+
+impl PhaseTimes {
+    pub fn is_empty(&self) -> bool {
+        self.download_started_at.is_none()
+            && self.download_completed_at.is_none()
+            && self.install_started_at.is_none()
+            && self.restarted_at.is_none()
+            && self.healthy_at.is_none()
+    }
+}
+
+impl UpdateResultRequest {
+    pub fn success(version: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            success: true,
+            error_message: None,
+            skipped_reason: None,
+            staged: false,
+            failure_reason: None,
+            verified_checksum: None,
+            artifact_source: None,
+            phases: PhaseTimes::default(),
+            install_strategy: None,
+            bootstrap: false,
+        }
+    }
+
+    pub fn staged(version: &str) -> Self {
+        Self {
+            staged: true,
+            ..Self::success(version)
+        }
+    }
+
+    pub fn failure(version: &str, error_message: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            success: false,
+            error_message: Some(error_message.to_string()),
+            skipped_reason: None,
+            staged: false,
+            failure_reason: None,
+            verified_checksum: None,
+            artifact_source: None,
+            phases: PhaseTimes::default(),
+            install_strategy: None,
+            bootstrap: false,
+        }
+    }
+}
+
+/// 클라이언트 등록 응답
+#[derive(Debug, Deserialize)]
+pub struct RegisteredClient {
+    pub api_key: String,
+}
+
+/// 서버에 등록된 이 장비의 정보 (GET /api/clients/self)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub current_version: Option<String>,
+    #[serde(default)]
+    pub target_version: Option<String>,
+    #[serde(default)]
+    pub target_set_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub target_alias: Option<String>,
+    #[serde(default)]
+    pub target_staged: bool,
+    #[serde(default)]
+    pub update_pending: bool,
+    #[serde(default)]
+    pub pinned_version: Option<String>,
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// 서버 연결 통계 (GET /api/metrics/connections)
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ConnectionMetrics {
+    pub accepted_total: u64,
+    pub requests_total: u64,
+}
+
+/// 유휴 연결 유지 시간 = Polling 주기 + 이 값 (다음 체크인까지 연결이 살아 있도록)
+const POOL_IDLE_MARGIN: Duration = Duration::from_secs(15);
+
+/// TCP keepalive 프로브 시작 시간 (방화벽 conntrack 만료 전에 연결 유지)
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// Polling용 HTTP 클라이언트
+///
+/// 서버 하나에 연결 하나만 유지하고, 다음 체크인까지 닫히지 않도록 유휴 시간을 Polling 주기보다
+/// 길게 잡는다. 체크인마다 TCP/TLS 핸드셰이크를 새로 하지 않게 된다.
+pub fn polling_http_client(poll_interval: Duration) -> Client {
+    Client::builder()
+        .pool_idle_timeout(poll_interval + POOL_IDLE_MARGIN)
+        .pool_max_idle_per_host(1)
+        .tcp_keepalive(TCP_KEEPALIVE)
+        .build()
+        .unwrap_or_else(|_| Client::new())
+}
+
+/// 서버가 준 경로를 server_url 기준 URL로 변환
+///
+/// 서버가 리버스 프록시 접두사를 포함한 경로(`/dm/api/...`)를 주고 server_url도 그 접두사로
+/// 끝나면(`https://host/dm`) 접두사가 두 번 붙지 않도록 호스트 기준으로 합친다.
+fn server_relative_url(server_url: &str, path: &str) -> String {
+    if let Ok(base) = reqwest::Url::parse(server_url) {
+        let prefix = base.path().trim_end_matches('/');
+        if !prefix.is_empty() && path.starts_with(&format!("{}/", prefix)) {
+            if let Ok(url) = base.join(path) {
+                return url.to_string();
+            }
+        }
+    }
+    format!("{}{}", server_url, path)
+}
+
+/// 아티팩트 다운로드 대상
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactSource {
+    /// DM 서버 (또는 Static 모드 배포 위치)
+    Server,
+    /// 체크인 응답의 미러 (API 키 없이 URL 그대로 요청)
+    Mirror,
+}
+
+/// 받은 아티팩트 파일 이름 접두사
+const DOWNLOAD_PREFIX: &str = ".dm-download-";
+
+/// 디스크에 받은 아티팩트 (drop되면 파일 삭제)
+#[derive(Debug)]
+pub struct DownloadedArtifact {
+    pub path: tempfile::TempPath,
+    pub size: u64,
+    /// 받으면서 계산한 SHA256 (hex)
+    pub checksum: String,
+}
+
+impl DownloadedArtifact {
+    /// 다운로드 디렉토리에 빈 임시 파일 생성
+    pub fn create_in(dir: &Path) -> Result<tempfile::NamedTempFile> {
+        std::fs::create_dir_all(dir)?;
+        Ok(tempfile::Builder::new().prefix(DOWNLOAD_PREFIX).tempfile_in(dir)?)
+    }
+}
+
+/// 서명된 아티팩트 URL이 만료됨 (다시 체크인해 새 URL을 받아야 함)
+#[derive(Debug, thiserror::Error)]
+#[error("artifact URL expired")]
+pub struct ArtifactUrlExpired;
+
+/// 서버가 과부하로 체크인을 거부함 (429/503 + Retry-After, 재시도하지 않고 알려준 시간만큼 대기)
+#[derive(Debug, thiserror::Error)]
+#[error("server busy ({status}), retry after {}s", retry_after.as_secs())]
+pub struct ServerBusy {
+    pub status: StatusCode,
+    pub retry_after: Duration,
+}
+
+/// 과부하 응답(429/503)의 Retry-After (초 또는 HTTP 날짜, 없거나 읽을 수 없으면 None)
+fn retry_after(response: &Response) -> Option<Duration> {
+    if !matches!(
+        response.status(),
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        return None;
+    }
+    let value = response.headers().get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or_default())
+}
+
+/// 분할 다운로드 중 서버가 Range 헤더를 무시함 (200 응답, 단일 스트림으로 전환)
+#[derive(Debug, thiserror::Error)]
+#[error("server ignored the Range header")]
+struct RangeIgnored;
+
+/// 아티팩트 요청 대상
+#[derive(Debug, Clone)]
+struct ArtifactTarget {
+    url: String,
+    source: ArtifactSource,
+    /// 서명된 URL (토큰으로 인증하므로 API 키를 붙이지 않음, 만료되면 403)
+    signed: bool,
+}
+
+impl ArtifactTarget {
+    /// 아티팩트 GET 요청 (미러와 서명된 URL에는 API 키를 보내지 않음)
+    fn request(&self, client: &Client, api_key: &str) -> RequestBuilder {
+        let request = client.get(&self.url);
+        if self.source == ArtifactSource::Mirror || self.signed {
+            request
+        } else {
+            request.header("X-API-Key", api_key)
+        }
+    }
+
+    /// 2xx가 아닌 응답을 에러로 변환 (만료된 서명 URL은 ArtifactUrlExpired)
+    async fn failure(&self, response: Response) -> anyhow::Error {
+        let status = response.status();
+        if self.source == ArtifactSource::Mirror {
+            return ClientError::from_status("Download from mirror", status, "").into();
+        }
+        if self.signed && status == StatusCode::FORBIDDEN {
+            let text = response.text().await.unwrap_or_default();
+            if text.contains("expired") {
+                return ArtifactUrlExpired.into();
+            }
+        }
+        ClientError::from_status("Download", status, "").into()
+    }
+}
+
+/// Content-Range 헤더의 시작 위치와 전체 크기 (`bytes 0-99/1234`)
+fn content_range(response: &Response) -> Option<(u64, u64)> {
+    let value = response.headers().get(header::CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.parse().ok()?, total.parse().ok()?))
+}
+
+/// 분할 다운로드 작업들이 공유하는 상태
+struct ChunkQueue {
+    pending: Mutex<VecDeque<usize>>,
+    journal: Mutex<DownloadJournal>,
+    progress: Mutex<Progress>,
+    plan: ParallelDownload,
+}
+
+impl ChunkQueue {
+    /// 조각 완료 기록 (기록한 조각은 재시작 후 다시 받지 않음)
+    fn complete(&self, index: usize) -> Result<()> {
+        let mut journal = self.journal.lock().unwrap();
+        journal.done[index] = true;
+        journal.save(&self.plan.journal_path)
+    }
+}
+
+/// 조각을 받는 연결 하나 (작업마다 복제)
+#[derive(Clone)]
+struct RangeFetch {
+    client: Client,
+    api_key: String,
+    target: ArtifactTarget,
+    idle_timeout: Option<Duration>,
+    retry: RetryPolicy,
+}
+
+impl RangeFetch {
+    /// 남은 조각이 없을 때까지 하나씩 받음
+    async fn run(self, queue: Arc<ChunkQueue>) -> Result<()> {
+        loop {
+            let Some(index) = queue.pending.lock().unwrap().pop_front() else {
+                return Ok(());
+            };
+            let (start, end) = queue.journal.lock().unwrap().range(index);
+            self.fetch_chunk(&queue, start, end).await?;
+            queue.complete(index)?;
+        }
+    }
+
+    /// 조각 하나 받기 (실패하면 받은 위치부터 이 조각만 다시 요청)
+    async fn fetch_chunk(&self, queue: &ChunkQueue, start: u64, end: u64) -> Result<()> {
+        let mut offset = start;
+        let mut attempt = 0;
+        loop {
+            let error = match self.fetch_from(queue, &mut offset, end).await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            let retryable = !error.is::<RangeIgnored>()
+                && !error.is::<ArtifactUrlExpired>()
+                && matches!(ClientError::of(&error), None | Some(ClientError::Network(_)));
+            if !retryable || attempt >= self.retry.max_retries {
+                return Err(error);
+            }
+
+            let delay = self.retry.delay(attempt);
+            attempt += 1;
+            tracing::warn!(
+                "Chunk {}-{} failed at byte {}: {}; retrying in {}ms ({}/{})",
+                start,
+                end,
+                offset,
+                error,
+                delay.as_millis(),
+                attempt,
+                self.retry.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// `offset`부터 `end`까지 요청해 파일의 같은 위치에 씀 (받은 만큼 `offset` 전진)
+    async fn fetch_from(&self, queue: &ChunkQueue, offset: &mut u64, end: u64) -> Result<()> {
+        let mut response = self
+            .target
+            .request(&self.client, &self.api_key)
+            .header(header::RANGE, format!("bytes={}-{}", offset, end))
+            .send()
+            .await
+            .map_err(ClientError::from)?;
+        if response.status() == StatusCode::OK {
+            return Err(RangeIgnored.into());
+        }
+        if !response.status().is_success() {
+            return Err(self.target.failure(response).await);
+        }
+        if content_range(&response).map(|(start, _)| start) != Some(*offset) {
+            anyhow::bail!(ClientError::Network(format!(
+                "Unexpected Content-Range for bytes {}-{}",
+                offset, end
+            )));
+        }
+
+        let mut file = std::fs::OpenOptions::new().write(true).open(&queue.plan.part_path)?;
+        file.seek(SeekFrom::Start(*offset))?;
+        loop {
+            let chunk = match self.idle_timeout {
+                Some(idle) => tokio::time::timeout(idle, response.chunk())
+                    .await
+                    .map_err(|_| ClientError::Network(format!("Download stalled: no data for {}s", idle.as_secs())))?
+                    .map_err(ClientError::from)?,
+                None => response.chunk().await.map_err(ClientError::from)?,
+            };
+            let Some(chunk) = chunk else { break };
+            if *offset + chunk.len() as u64 > end + 1 {
+                anyhow::bail!(ClientError::Network(format!(
+                    "Server sent more than bytes {}-{}",
+                    offset, end
+                )));
+            }
+            file.write_all(&chunk)?;
+            *offset += chunk.len() as u64;
+            queue.progress.lock().unwrap().inc(chunk.len() as u64);
+        }
+        file.flush()?;
+
+        if *offset != end + 1 {
+            anyhow::bail!(ClientError::Network(format!(
+                "Chunk ended early at byte {} (expected {})",
+                offset,
+                end + 1
+            )));
+        }
+        Ok(())
+    }
 }
 
 /// DM Server API 클라이언트
@@ -31,89 +716,580 @@ pub struct DmApiClient {
     client: Client,
     server_url: String,
     api_key: String,
+    /// 체크인 시 전송할 인스턴스 정보 (데몬 전용)
+    instance: Option<(String, DateTime<Utc>)>,
+    retry: RetryPolicy,
+    breaker: CircuitBreaker,
+    /// 다운로드 중 데이터가 오지 않으면 중단하는 시간 (반쯤 끊긴 연결 대비)
+    download_idle_timeout: Option<Duration>,
+    /// 조건부 GET 캐시 (설정/스크립트처럼 자주 바뀌지 않는 조회용)
+    cache: Option<HttpCache>,
 }
 
 impl DmApiClient {
     pub fn new(server_url: &str, api_key: &str) -> Self {
+        Self::with_http_client(Client::new(), server_url, api_key)
+    }
+
+    /// 연결 풀을 공유하는 클라이언트 생성 (simulate 모드)
+    pub fn with_http_client(client: Client, server_url: &str, api_key: &str) -> Self {
         Self {
-            client: Client::new(),
+            client,
             server_url: server_url.trim_end_matches('/').to_string(),
             api_key: api_key.to_string(),
+            instance: None,
+            retry: RetryPolicy::default(),
+            breaker: CircuitBreaker::default(),
+            download_idle_timeout: None,
+            cache: None,
+        }
+    }
+
+    /// 서킷 브레이커 상태 (로그용)
+    pub fn breaker_summary(&self) -> String {
+        self.breaker.summary()
+    }
+
+    /// 재시도 정책을 적용해 요청 전송 (멱등 요청 전용)
+    ///
+    /// 5xx/연결 오류는 jitter 백오프로 재시도하고, 4xx는 그대로 반환한다.
+    /// 재시도 후에도 실패하면 서킷 브레이커에 실패로 기록한다.
+    /// Retry-After가 붙은 429/503은 서버가 알려준 시간을 따른다.
+    async fn send_with_retry<F>(&self, build: F) -> Result<Response>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        self.breaker.check()?;
+
+        let started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            let outcome = build().send().await;
+            // 과부하 응답의 Retry-After가 남은 예산 안이면 그만큼 기다려 재시도, 넘으면 그대로 반환
+            // (서버가 다시 올 시간을 알려준 것이므로 브레이커 실패로 세지 않음)
+            if let Some(wait) = outcome.as_ref().ok().and_then(retry_after) {
+                if attempt >= self.retry.max_retries || started.elapsed() + wait > self.retry.budget {
+                    return Ok(outcome?);
+                }
+                attempt += 1;
+                tracing::debug!(
+                    "Server busy, retrying in {}s as requested ({}/{})",
+                    wait.as_secs(),
+                    attempt,
+                    self.retry.max_retries
+                );
+                tokio::time::sleep(wait).await;
+                continue;
+            }
+            let cause = match &outcome {
+                Ok(response) if response.status().is_server_error() => response.status().to_string(),
+                Ok(_) => {
+                    self.breaker.record_success();
+                    return Ok(outcome.map_err(ClientError::from)?);
+                }
+                Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => e.to_string(),
+                Err(_) => return Ok(outcome.map_err(ClientError::from)?),
+            };
+
+            let delay = self.retry.delay(attempt);
+            if attempt >= self.retry.max_retries || started.elapsed() + delay > self.retry.budget {
+                self.breaker.record_failure();
+                return Ok(outcome.map_err(ClientError::from)?);
+            }
+
+            attempt += 1;
+            tracing::debug!(
+                "Transient server error ({}), retrying in {}ms ({}/{})",
+                cause,
+                delay.as_millis(),
+                attempt,
+                self.retry.max_retries
+            );
+            tokio::time::sleep(delay).await;
         }
     }
 
+    /// 체크인에 인스턴스 식별 정보 포함
+    pub fn with_instance(mut self, instance_id: &str, started_at: DateTime<Utc>) -> Self {
+        self.instance = Some((instance_id.to_string(), started_at));
+        self
+    }
+
+    /// 재시도 정책 설정 (DM_HTTP_RETRIES, DM_HTTP_RETRY_BASE_MS)
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 다운로드 유휴 제한 시간 설정
+    pub fn with_download_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.download_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// 조건부 GET 캐시 사용 (None이면 사용 안 함)
+    pub fn with_http_cache(mut self, path: Option<&std::path::Path>) -> Self {
+        self.cache = path.map(|path| HttpCache::open(path, &self.server_url, &self.api_key));
+        self
+    }
+
+    /// 인증된 GET (캐시가 있으면 If-None-Match/If-Modified-Since를 보내고 304면 캐시된 본문 반환)
+    async fn get_cached(&self, url: &str, what: &str) -> Result<String> {
+        let cached = self.cache.as_ref().and_then(|cache| cache.get(url));
+        let response = self
+            .send_with_retry(|| {
+                let mut request = self.client.get(url).header("X-API-Key", &self.api_key);
+                if let Some(entry) = &cached {
+                    if let Some(etag) = &entry.etag {
+                        request = request.header(header::IF_NONE_MATCH, etag);
+                    }
+                    if let Some(modified) = &entry.last_modified {
+                        request = request.header(header::IF_MODIFIED_SINCE, modified);
+                    }
+                }
+                request
+            })
+            .await?;
+
+        if let (StatusCode::NOT_MODIFIED, Some(cache), Some(entry)) = (response.status(), &self.cache, cached) {
+            tracing::debug!("{} not modified, using cached response ({})", what, url);
+            cache.touch(url);
+            return Ok(entry.body);
+        }
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!(ClientError::from_status(what, status, &text));
+        }
+
+        let validator = |name: header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = validator(header::ETAG);
+        let last_modified = validator(header::LAST_MODIFIED);
+        let body = response.text().await?;
+        if let Some(cache) = &self.cache {
+            cache.store(url, etag, last_modified, &body);
+        }
+        Ok(body)
+    }
+
     /// 서버에 체크인 (Polling)
-    pub async fn checkin(&self, current_version: Option<&str>, status: &str) -> Result<CheckinResponse> {
+    pub async fn checkin(&self, mut req: CheckinRequest) -> Result<CheckinResponse> {
         let url = format!("{}/api/checkin", self.server_url);
-        
-        let req = CheckinRequest {
-            current_version: current_version.map(|s| s.to_string()),
-            status: status.to_string(),
-        };
 
-        let response = self.client
-            .post(&url)
-            .header("X-API-Key", &self.api_key)
-            .json(&req)
-            .send()
+        if let Some((id, started)) = &self.instance {
+            req.instance_id = Some(id.clone());
+            req.instance_started_at = Some(*started);
+        }
+
+        let response = self
+            .send_with_retry(|| {
+                self.client
+                    .post(&url)
+                    .header("X-API-Key", &self.api_key)
+                    .json(&req)
+            })
             .await?;
 
+        if let Some(retry_after) = retry_after(&response) {
+            anyhow::bail!(ServerBusy {
+                status: response.status(),
+                retry_after,
+            });
+        }
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Checkin failed: {} - {}", status, text);
+            anyhow::bail!(ClientError::from_status("Checkin", status, &text));
         }
 
         let checkin_response: CheckinResponse = response.json().await?;
         Ok(checkin_response)
     }
 
-    /// 아티팩트 다운로드
-    pub async fn download_artifact(&self, artifact_url: &str) -> Result<Vec<u8>> {
+    /// 게이트웨이 배치 체크인 (각 항목의 API Key로 인증)
+    pub async fn checkin_batch(&self, entries: &[BatchCheckinEntry]) -> Result<Vec<BatchCheckinResult>> {
+        let url = format!("{}/api/checkin/batch", self.server_url);
+
+        let response = self
+            .send_with_retry(|| self.client.post(&url).json(entries))
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!(ClientError::from_status("Batch checkin", status, &text));
+        }
+
+        let results: Vec<BatchCheckinResult> = response.json().await?;
+        Ok(results)
+    }
+
+    /// 정적 manifest.json 조회 (static 모드)
+    pub async fn fetch_manifest(&self, manifest_url: &str) -> Result<UsbManifest> {
+        let response = self.send_with_retry(|| self.client.get(manifest_url)).await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(ClientError::from_status("Manifest fetch", response.status(), ""));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// 버전 설치 스크립트 조회
+    pub async fn fetch_scripts(&self, version: &str) -> Result<InstallScripts> {
+        let url = format!("{}/api/versions/{}/scripts", self.server_url, version);
+        let body = self.get_cached(&url, "Install script fetch").await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// 서버에 등록된 이 장비의 정보 조회
+    pub async fn fetch_self(&self) -> Result<ClientInfo> {
+        let url = format!("{}/api/clients/self", self.server_url);
+        let body = self.get_cached(&url, "Client info").await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// 클라이언트 등록 (관리 API)
+    pub async fn register_client(&self, name: &str) -> Result<RegisteredClient> {
+        let url = format!("{}/api/clients", self.server_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "name": name }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!(ClientError::from_status("Registration", status, &text));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// 서버 연결 통계 조회 (simulate 모드의 연결 재사용 측정용)
+    pub async fn connection_metrics(&self) -> Result<ConnectionMetrics> {
+        let url = format!("{}/api/metrics/connections", self.server_url);
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .and_then(Response::error_for_status)
+            .map_err(ClientError::from)?;
+        Ok(response.json().await?)
+    }
+
+    /// 아티팩트 요청 대상 (서버가 준 상대 경로는 server_url 기준으로)
+    fn artifact_target(&self, artifact_url: &str, source: ArtifactSource) -> ArtifactTarget {
         let url = if artifact_url.starts_with("http") {
             artifact_url.to_string()
         } else {
-            format!("{}{}", self.server_url, artifact_url)
+            server_relative_url(&self.server_url, artifact_url)
         };
+        // 서명된 URL은 토큰으로 인증 (CDN 등 캐시 앞단 대비)
+        let signed = source == ArtifactSource::Server
+            && reqwest::Url::parse(&url)
+                .map(|u| u.query_pairs().any(|(key, _)| key == "token"))
+                .unwrap_or(false);
+        ArtifactTarget { url, source, signed }
+    }
 
-        tracing::info!("Downloading artifact from {}", url);
+    /// 아티팩트 다운로드 요청 (응답 본문은 호출자가 읽음, `range`는 Range 헤더 값)
+    async fn artifact_response(
+        &self,
+        artifact_url: &str,
+        source: ArtifactSource,
+        range: Option<&str>,
+    ) -> Result<Response> {
+        let target = self.artifact_target(artifact_url, source);
+        tracing::info!("Downloading artifact from {}", target.url);
 
-        let response = self.client
-            .get(&url)
-            .header("X-API-Key", &self.api_key)
-            .send()
-            .await?;
+        let request = || {
+            let request = target.request(&self.client, &self.api_key);
+            match range {
+                Some(range) => request.header(header::RANGE, range),
+                None => request,
+            }
+        };
+        // 미러(정적 파일 서버)는 서버 장애 판단(재시도/브레이커)에서 제외
+        let response = match source {
+            ArtifactSource::Mirror => request().send().await?,
+            ArtifactSource::Server => self.send_with_retry(request).await?,
+        };
 
         if !response.status().is_success() {
-            let status = response.status();
-            anyhow::bail!("Download failed: {}", status);
+            return Err(target.failure(response).await);
+        }
+        Ok(response)
+    }
+
+    /// 아티팩트를 여러 연결로 나눠 `plan.part_path`에 받음. 받은 바이트 수 반환
+    ///
+    /// 마지막 1바이트를 Range로 요청해 지원 여부와 전체 크기를 확인한다. 서버가 Range를 무시하거나
+    /// (200 응답) 아티팩트가 기준보다 작으면 None을 반환하고 호출자가 단일 스트림으로 받는다.
+    /// 조각마다 완료를 기록하므로 중단되면 (데몬 재시작 포함) 남은 조각만 받는다.
+    pub async fn download_artifact_parallel(
+        &self,
+        artifact_url: &str,
+        source: ArtifactSource,
+        checksum: &str,
+        plan: &ParallelDownload,
+    ) -> Result<Option<u64>> {
+        let probe = self.artifact_response(artifact_url, source, Some("bytes=-1")).await?;
+        let size = match (probe.status(), content_range(&probe)) {
+            (StatusCode::PARTIAL_CONTENT, Some((_, size))) => size,
+            _ => {
+                tracing::info!("Artifact source does not support range requests; using a single stream");
+                return Ok(None);
+            }
+        };
+        drop(probe);
+        if size < plan.min_size {
+            return Ok(None);
+        }
+
+        let journal = plan.prepare(checksum, size)?;
+        let pending: VecDeque<usize> = journal.pending().into();
+        let chunks = journal.done.len();
+        let remaining: u64 = pending
+            .iter()
+            .map(|&i| {
+                let (start, end) = journal.range(i);
+                end - start + 1
+            })
+            .sum();
+        if pending.len() < chunks {
+            tracing::info!(
+                "Resuming download: {}/{} chunks already received",
+                chunks - pending.len(),
+                chunks
+            );
+        }
+        tracing::info!(
+            "Downloading {} bytes in {} chunks over {} connections",
+            size,
+            chunks,
+            plan.connections
+        );
+
+        let mut progress = Progress::new("Downloading", size, Unit::Bytes).logged_in_daemon();
+        progress.inc(size - remaining);
+        let connections = plan.connections.min(pending.len());
+        let queue = Arc::new(ChunkQueue {
+            pending: Mutex::new(pending),
+            journal: Mutex::new(journal),
+            progress: Mutex::new(progress),
+            plan: plan.clone(),
+        });
+        let fetch = RangeFetch {
+            client: self.client.clone(),
+            api_key: self.api_key.clone(),
+            target: self.artifact_target(artifact_url, source),
+            idle_timeout: self.download_idle_timeout,
+            retry: self.retry.clone(),
+        };
+
+        let mut workers = JoinSet::new();
+        for _ in 0..connections {
+            workers.spawn(fetch.clone().run(queue.clone()));
+        }
+        while let Some(joined) = workers.join_next().await {
+            match joined? {
+                Ok(()) => {}
+                Err(e) if e.is::<RangeIgnored>() => {
+                    workers.abort_all();
+                    tracing::warn!("Artifact source stopped honoring range requests; using a single stream");
+                    plan.discard();
+                    return Ok(None);
+                }
+                Err(e) => {
+                    workers.abort_all();
+                    return Err(e);
+                }
+            }
+        }
+        queue.progress.lock().unwrap().finish();
+
+        Ok(Some(size))
+    }
+
+    /// 아티팩트를 `dir`의 임시 파일로 받으며 SHA256 계산 (메모리에 모으지 않음)
+    pub async fn download_artifact(
+        &self,
+        artifact_url: &str,
+        source: ArtifactSource,
+        dir: &Path,
+    ) -> Result<DownloadedArtifact> {
+        let mut file = DownloadedArtifact::create_in(dir)?;
+        let mut hasher = Sha256::new();
+        let size = self
+            .stream_artifact(artifact_url, source, |chunk| {
+                hasher.update(chunk);
+                Ok(file.write_all(chunk)?)
+            })
+            .await?;
+        file.flush()?;
+        Ok(DownloadedArtifact {
+            path: file.into_temp_path(),
+            size,
+            checksum: format!("{:x}", hasher.finalize()),
+        })
+    }
+
+    /// 아티팩트를 받으며 조각마다 `sink` 호출 (유휴 제한 시간 적용). 받은 바이트 수 반환
+    ///
+    /// 받는 도중 연결이 끊기거나 멈추면 재시도 정책대로 기다린 뒤 받은 위치부터 Range로 이어 받는다.
+    /// `sink`에는 같은 바이트가 두 번 가지 않는다.
+    async fn stream_artifact(
+        &self,
+        artifact_url: &str,
+        source: ArtifactSource,
+        mut sink: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<u64> {
+        let mut response = self.artifact_response(artifact_url, source, None).await?;
+
+        let total = response.content_length().unwrap_or(0);
+        let mut progress = Progress::new("Downloading", total, Unit::Bytes).logged_in_daemon();
+        let mut received = 0;
+        // 이어 받기 요청에 서버가 Range를 무시하고 처음부터 보내면 이미 받은 만큼 버림
+        let mut skip = 0;
+        let mut attempt = 0;
+        loop {
+            let chunk = match self.download_idle_timeout {
+                Some(idle) => tokio::time::timeout(idle, response.chunk())
+                    .await
+                    .map_err(|_| ClientError::Network(format!("Download stalled: no data for {}s", idle.as_secs())))
+                    .and_then(|chunk| chunk.map_err(ClientError::from)),
+                None => response.chunk().await.map_err(ClientError::from),
+            };
+            let error = match chunk {
+                Ok(Some(chunk)) => {
+                    let dropped = skip.min(chunk.len() as u64);
+                    skip -= dropped;
+                    let chunk = &chunk[dropped as usize..];
+                    sink(chunk)?;
+                    received += chunk.len() as u64;
+                    progress.inc(chunk.len() as u64);
+                    continue;
+                }
+                Ok(None) => break,
+                Err(e) => e,
+            };
+            if attempt >= self.retry.max_retries {
+                return Err(error.into());
+            }
+
+            let delay = self.retry.delay(attempt);
+            attempt += 1;
+            tracing::warn!(
+                "Download interrupted at byte {}: {}; resuming in {}ms ({}/{})",
+                received,
+                error,
+                delay.as_millis(),
+                attempt,
+                self.retry.max_retries
+            );
+            tokio::time::sleep(delay).await;
+            response = self
+                .artifact_response(artifact_url, source, Some(&format!("bytes={}-", received)))
+                .await?;
+            skip = match (response.status(), content_range(&response)) {
+                (StatusCode::PARTIAL_CONTENT, Some((start, _))) if start == received => 0,
+                (StatusCode::PARTIAL_CONTENT, _) => {
+                    anyhow::bail!(ClientError::Network(format!(
+                        "Resumed download returned an unexpected range (wanted from byte {})",
+                        received
+                    )))
+                }
+                _ => received,
+            };
         }
+        progress.finish();
 
-        let bytes = response.bytes().await?.to_vec();
-        Ok(bytes)
+        Ok(received)
+    }
+
+    /// 아티팩트를 받기만 하고 버림 (simulate 모드). 받은 바이트 수 반환
+    pub async fn drain_artifact(&self, artifact_url: &str) -> Result<u64> {
+        let mut response = self.artifact_response(artifact_url, ArtifactSource::Server, None).await?;
+
+        let mut received = 0;
+        while let Some(chunk) = response.chunk().await? {
+            received += chunk.len() as u64;
+        }
+        Ok(received)
     }
 
     /// 업데이트 결과 보고
-    pub async fn report_result(&self, version: &str, success: bool, error_message: Option<&str>) -> Result<()> {
+    ///
+    /// 재시도 시 동일한 Idempotency-Key를 보낸다. 서버는 진행 중인 로그를 한 번만 종료하므로
+    /// 중복 보고는 결과를 바꾸지 않는다.
+    pub async fn report_result(&self, req: &UpdateResultRequest) -> Result<()> {
+        self.report_result_with_key(req, &uuid::Uuid::new_v4().to_string()).await
+    }
+
+    /// 업데이트 결과 보고 (저장해 둔 결과를 처음 보낸 Idempotency-Key로 재전송할 때 사용)
+    pub async fn report_result_with_key(&self, req: &UpdateResultRequest, idempotency_key: &str) -> Result<()> {
         let url = format!("{}/api/update-result", self.server_url);
-        
-        let req = UpdateResultRequest {
-            version: version.to_string(),
-            success,
-            error_message: error_message.map(|s| s.to_string()),
-        };
 
-        let response = self.client
-            .post(&url)
-            .header("X-API-Key", &self.api_key)
-            .json(&req)
-            .send()
+        let response = self
+            .send_with_retry(|| {
+                self.client
+                    .post(&url)
+                    .header("X-API-Key", &self.api_key)
+                    .header("Idempotency-Key", idempotency_key)
+                    .json(req)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!(ClientError::from_status("Report", status, &text));
+        }
+
+        Ok(())
+    }
+
+    /// 재시작/롤백 결과 보고 (서버는 같은 결과의 재전송을 성공으로 처리)
+    pub async fn report_action_result(&self, req: &ActionResultRequest) -> Result<()> {
+        let url = format!("{}/api/action-result", self.server_url);
+
+        let response = self
+            .send_with_retry(|| self.client.post(&url).header("X-API-Key", &self.api_key).json(req))
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!(ClientError::from_status("Report", status, &text));
+        }
+
+        Ok(())
+    }
+
+    /// 조사 결과 업로드 (서버는 같은 결과의 재전송을 성공으로 처리)
+    pub async fn report_survey_result(&self, req: &SurveyResultRequest) -> Result<()> {
+        let url = format!("{}/api/survey-result", self.server_url);
+
+        let response = self
+            .send_with_retry(|| self.client.post(&url).header("X-API-Key", &self.api_key).json(req))
             .await?;
 
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Report failed: {} - {}", status, text);
+            anyhow::bail!(ClientError::from_status("Report", status, &text));
         }
 
         Ok(())
@@ -0,0 +1,384 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::state::SkippedBackup;
+
+/// 백업 디렉토리 이름 접두사
+const BACKUP_PREFIX: &str = "backup_";
+
+/// 압축 백업 파일 이름 접미사 (DM_BACKUP_COMPRESS)
+/// 예: backup_1.2.3_20240501T120000Z.tar.gz
+pub const ARCHIVE_SUFFIX: &str = ".tar.gz";
+
+/// 백업 타임스탬프 형식 (UTC, `Z` 표기)
+/// 예: backup_1.2.3_20240501T120000Z
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// 이전 버전의 백업 타임스탬프 형식
+/// 예: backup_1.2.3_20240501_120000 (Utc::now() 기준으로 생성되었으므로 UTC로 해석)
+const LEGACY_BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+/// 백업 항목
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupEntry {
+    pub name: String,
+    pub version: String,
+    pub created_at: DateTime<Utc>,
+    pub path: PathBuf,
+    /// 롤백으로 복원되어 현재 실행 중인 트리 (정리 대상 제외)
+    pub active_restore_point: bool,
+    /// 백업에서 제외된 경로 (서비스 디렉토리 기준)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<String>,
+}
+
+/// 백업 메타데이터 (백업 디렉토리 옆 `<백업 이름>.json`)
+///
+/// 제외 패턴이 있을 때만 기록한다. 없으면 백업은 서비스 디렉토리의 완전한 복사본이다.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupMeta {
+    /// 적용된 제외 패턴
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// 실제로 제외된 경로 (복원 후 없을 수 있음)
+    #[serde(default)]
+    pub excluded: Vec<String>,
+}
+
+impl BackupMeta {
+    fn path(backup_path: &Path) -> PathBuf {
+        let mut path = backup_path.as_os_str().to_owned();
+        path.push(".json");
+        PathBuf::from(path)
+    }
+
+    /// 백업의 메타데이터 읽기 (없으면 제외 없는 완전한 백업)
+    pub fn load(backup_path: &Path) -> Self {
+        fs::read_to_string(Self::path(backup_path))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, backup_path: &Path) -> Result<()> {
+        fs::write(Self::path(backup_path), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// 백업 제외 패턴 (DM_BACKUP_EXCLUDE 또는 서버 ClientConfig.backup_exclude)
+///
+/// - `/`가 없는 패턴은 모든 깊이의 파일/디렉토리 이름과 비교 (예: `node_modules`)
+/// - `/`가 있는 패턴은 서비스 디렉토리 기준 상대 경로와 비교 (예: `.next/cache`)
+/// - `*`는 `/`를 제외한 임의 문자열, `**`는 `/`를 포함한 임의 문자열, `?`는 한 글자
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeSet {
+    patterns: Vec<String>,
+}
+
+impl ExcludeSet {
+    pub fn new(patterns: &[String]) -> Self {
+        Self {
+            patterns: patterns
+                .iter()
+                .map(|p| p.trim().trim_matches('/').to_string())
+                .filter(|p| !p.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// 상대 경로(`/` 구분)가 제외 대상인지 확인
+    pub fn matches(&self, relative: &str) -> bool {
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        self.patterns.iter().any(|pattern| {
+            let target = if pattern.contains('/') { relative } else { name };
+            glob_match(pattern.as_bytes(), target.as_bytes())
+        })
+    }
+}
+
+/// 단순 glob 비교 (`*`, `**`, `?`)
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `**/`는 0개 이상의 디렉토리
+            (rest.first() == Some(&b'/') && glob_match(&rest[1..], text))
+                || (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => {
+            let segment_end = text.iter().position(|&c| c == b'/').unwrap_or(text.len());
+            (0..=segment_end).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'?', rest @ ..] => matches!(text.first(), Some(&c) if c != b'/') && glob_match(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// 백업 디렉토리 이름 생성
+pub fn backup_name(version: &str, created_at: DateTime<Utc>) -> String {
+    format!(
+        "{}{}_{}",
+        BACKUP_PREFIX,
+        version,
+        created_at.format(BACKUP_TIMESTAMP_FORMAT)
+    )
+}
+
+/// 백업 디렉토리 이름 파싱 (현재 형식 + 이전 형식)
+///
+/// 버전 문자열에 `_`가 포함될 수 있으므로 타임스탬프를 뒤에서부터 분리한다.
+pub fn parse_backup_name(name: &str) -> Option<(String, DateTime<Utc>)> {
+    let name = name.strip_suffix(ARCHIVE_SUFFIX).unwrap_or(name);
+    let rest = name.strip_prefix(BACKUP_PREFIX)?;
+
+    // 현재 형식: <version>_<%Y%m%dT%H%M%SZ>
+    if let Some((version, timestamp)) = rest.rsplit_once('_') {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT) {
+            if !version.is_empty() {
+                return Some((version.to_string(), naive.and_utc()));
+            }
+        }
+    }
+
+    // 이전 형식: <version>_<%Y%m%d>_<%H%M%S>
+    let mut parts = rest.rsplitn(3, '_');
+    let time = parts.next()?;
+    let date = parts.next()?;
+    let version = parts.next().filter(|v| !v.is_empty())?;
+    let naive = NaiveDateTime::parse_from_str(
+        &format!("{}_{}", date, time),
+        LEGACY_BACKUP_TIMESTAMP_FORMAT,
+    )
+    .ok()?;
+
+    Some((version.to_string(), naive.and_utc()))
+}
+
+/// 백업 목록 조회 (최신순)
+///
+/// `restore_point`는 현재 복원 지점의 백업 디렉토리 이름 (`LocalState::restore_point_name`)
+pub fn list_backups(backup_dir: &Path, restore_point: Option<&str>) -> Vec<BackupEntry> {
+    let Ok(entries) = fs::read_dir(backup_dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<BackupEntry> = entries
+        .flatten()
+        .filter(is_backup_entry)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let (version, created_at) = parse_backup_name(&name)?;
+            Some(BackupEntry {
+                active_restore_point: restore_point == Some(name.as_str()),
+                name,
+                version,
+                created_at,
+                excluded: BackupMeta::load(&entry.path()).excluded,
+                path: entry.path(),
+            })
+        })
+        .collect();
+
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+    backups
+}
+
+/// 백업 디렉토리 또는 압축 백업 파일 (메타데이터 `.json`은 제외)
+fn is_backup_entry(entry: &fs::DirEntry) -> bool {
+    let path = entry.path();
+    path.is_dir() || (path.is_file() && entry.file_name().to_string_lossy().ends_with(ARCHIVE_SUFFIX))
+}
+
+/// 백업 삭제 (디렉토리 또는 압축 파일, 메타데이터 포함)
+pub fn remove(backup_path: &Path) -> std::io::Result<()> {
+    if backup_path.is_dir() {
+        fs::remove_dir_all(backup_path)?;
+    } else {
+        fs::remove_file(backup_path)?;
+    }
+    let _ = fs::remove_file(BackupMeta::path(backup_path));
+    Ok(())
+}
+
+/// 정리로 삭제한 백업
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrunedBackup {
+    pub name: String,
+    /// 삭제 전 크기 (회수한 공간)
+    pub bytes: u64,
+}
+
+/// 오래된 백업 정리 (DM_BACKUP_KEEP): 최신 `keep`개만 남기고 삭제
+///
+/// 이름의 타임스탬프로 정렬하고, 이름 형식이 다르면 수정 시각을 쓴다.
+/// `protect`(방금 만든 백업, 현재 복원 지점)는 개수와 상관없이 남긴다. `keep`이 0이면 정리하지 않는다.
+pub fn prune_backups(backup_dir: &Path, keep: usize, protect: &[&str]) -> Vec<PrunedBackup> {
+    if keep == 0 {
+        return Vec::new();
+    }
+    let Ok(entries) = fs::read_dir(backup_dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<(DateTime<Utc>, String, PathBuf)> = entries
+        .flatten()
+        .filter(is_backup_entry)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            if !name.starts_with(BACKUP_PREFIX) {
+                return None;
+            }
+            let created_at = match parse_backup_name(&name) {
+                Some((_, created_at)) => created_at,
+                None => entry.metadata().and_then(|m| m.modified()).ok()?.into(),
+            };
+            Some((created_at, name, entry.path()))
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.0));
+
+    let mut pruned = Vec::new();
+    for (_, name, path) in backups.into_iter().skip(keep) {
+        if protect.contains(&name.as_str()) {
+            continue;
+        }
+        let bytes = if path.is_dir() {
+            tree_size(&path)
+        } else {
+            fs::metadata(&path).map_or(0, |m| m.len())
+        };
+        match remove(&path) {
+            Ok(()) => pruned.push(PrunedBackup { name, bytes }),
+            Err(e) => tracing::warn!("Failed to remove old backup {:?}: {}", path, e),
+        }
+    }
+    pruned
+}
+
+/// 디렉토리 트리의 파일 크기 합 (읽지 못한 항목은 건너뜀, 심볼릭 링크는 따라가지 않음)
+fn tree_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => tree_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// 롤백 대상 선택 (이름 지정이 없으면 가장 최근 백업)
+///
+/// 현재 복원 지점은 이미 실행 중인 트리이므로 대상에서 거부한다.
+/// 마지막 설치가 백업 없이 진행됐으면 최신 백업은 직전 버전이 아니므로 이름을 지정해야 한다.
+pub fn rollback_target<'a>(
+    backups: &'a [BackupEntry],
+    name: Option<&str>,
+    no_backup: Option<&SkippedBackup>,
+) -> Result<&'a BackupEntry> {
+    let target = match (name, no_backup) {
+        (Some(name), _) => backups
+            .iter()
+            .find(|b| b.name == name)
+            .with_context(|| format!("Backup not found: {}", name))?,
+        // 마지막 설치가 백업 없이 진행됐으면 최신 백업은 직전 버전이 아님
+        (None, Some(skipped)) => anyhow::bail!(
+            "Cannot roll back to {}: it was replaced without a backup at {} (DM_BACKUP_REQUIRED=false / --no-backup). \
+             {}",
+            skipped.previous_version,
+            skipped.skipped_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            match backups.first() {
+                Some(b) => format!(
+                    "The latest backup is the older version {}; roll back to it explicitly with --backup {}",
+                    b.version, b.name
+                ),
+                None => "No older backups are available".to_string(),
+            }
+        ),
+        (None, None) => backups.first().context("No backups available")?,
+    };
+
+    if target.active_restore_point {
+        anyhow::bail!(
+            "{} is the active restore point: it was restored by the last rollback and is already the live tree. \
+             Choose an older backup with --backup <name> (see `dm-client backups list`)",
+            target.name
+        );
+    }
+
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // 현재 형식과 이전 형식(`%Y%m%d_%H%M%S`) 모두 파싱 (버전의 `_`, 압축 백업 `.tar.gz` 포함)
+    #[test]
+    fn backup_names_parse_in_current_and_legacy_formats() -> Result<()> {
+        let at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 45).single().context("timestamp")?;
+        for version in ["1.2.3", "1.0_rc_1", "2024_05_01"] {
+            let name = backup_name(version, at);
+            assert_eq!(parse_backup_name(&name), Some((version.to_string(), at)), "{}", name);
+            let archive = format!("{}{}", name, ARCHIVE_SUFFIX);
+            assert_eq!(parse_backup_name(&archive), Some((version.to_string(), at)), "{}", archive);
+
+            let legacy = format!("backup_{}_20240501_123045", version);
+            assert_eq!(parse_backup_name(&legacy), Some((version.to_string(), at)), "{}", legacy);
+            let legacy_archive = format!("{}{}", legacy, ARCHIVE_SUFFIX);
+            assert_eq!(
+                parse_backup_name(&legacy_archive),
+                Some((version.to_string(), at)),
+                "{}",
+                legacy_archive
+            );
+        }
+
+        for name in [
+            "backup_1.2.3",
+            "backup__20240501T123045Z",
+            "backup__20240501_123045",
+            "backup_1.2.3_20241301T123045Z",
+            "backup_1.2.3_20240501_250000",
+            "snapshot_1.2.3_20240501T123045Z",
+            "backup_1.2.3_20240501T123045Z.tar",
+        ] {
+            assert_eq!(parse_backup_name(name), None, "{}", name);
+        }
+
+        // 이전 형식과 현재 형식이 섞인 백업 디렉토리도 시각 순으로 나열
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("backup_1.0_rc_1_20240501_123045"))?;
+        fs::write(dir.path().join("backup_1.0.1_20240502T000000Z.tar.gz"), "")?;
+        fs::create_dir(dir.path().join("backup_0.9.0_20240430_235959"))?;
+        let listed: Vec<(String, String)> = list_backups(dir.path(), None)
+            .into_iter()
+            .map(|b| (b.version, b.created_at.to_rfc3339()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("1.0.1".to_string(), "2024-05-02T00:00:00+00:00".to_string()),
+                ("1.0_rc_1".to_string(), "2024-05-01T12:30:45+00:00".to_string()),
+                ("0.9.0".to_string(), "2024-04-30T23:59:59+00:00".to_string()),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// 분할 다운로드 중인 아티팩트 (backup_dir/.dm-download.part)
+const PART_FILE: &str = ".dm-download.part";
+
+/// 조각별 완료 기록 (backup_dir/.dm-download.json)
+const JOURNAL_FILE: &str = ".dm-download.json";
+
+/// 조각 크기 하한/상한
+const MIN_CHUNK_SIZE: u64 = 64 * 1024;
+const MAX_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// 연결당 조각 수 (재시작 시 다시 받는 양을 줄이고, 느린 연결의 몫을 다른 연결이 가져감)
+const CHUNKS_PER_CONNECTION: u64 = 4;
+
+/// 분할 다운로드 설정 (DM_DOWNLOAD_CONNECTIONS가 2 이상일 때만)
+#[derive(Debug, Clone)]
+pub struct ParallelDownload {
+    /// 동시 연결 수
+    pub connections: usize,
+    /// 이보다 작은 아티팩트는 단일 스트림으로 받음
+    pub min_size: u64,
+    pub part_path: PathBuf,
+    pub journal_path: PathBuf,
+}
+
+impl ParallelDownload {
+    pub fn from_config(config: &Config) -> Option<Self> {
+        if config.download_connections <= 1 {
+            return None;
+        }
+        let dir = config.download_dir();
+        Some(Self {
+            connections: config.download_connections,
+            min_size: config.download_parallel_min_bytes,
+            part_path: dir.join(PART_FILE),
+            journal_path: dir.join(JOURNAL_FILE),
+        })
+    }
+
+    /// 조각 크기 (연결당 CHUNKS_PER_CONNECTION개, 64KiB~16MiB)
+    pub fn chunk_size(&self, size: u64) -> u64 {
+        let chunks = self.connections as u64 * CHUNKS_PER_CONNECTION;
+        size.div_ceil(chunks).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE)
+    }
+
+    /// 이어받을 기록 불러오기 또는 새로 시작 (같은 아티팩트가 아니면 기존 파일 폐기)
+    ///
+    /// 새로 시작하면 전체 크기의 sparse 파일을 만들어 두고 조각마다 제자리에 쓴다.
+    pub fn prepare(&self, checksum: &str, size: u64) -> Result<DownloadJournal> {
+        let chunk_size = self.chunk_size(size);
+        let part_size = fs::metadata(&self.part_path).map(|m| m.len()).ok();
+        if let Some(journal) = DownloadJournal::load(&self.journal_path) {
+            if journal.checksum == checksum
+                && journal.size == size
+                && journal.chunk_size == chunk_size
+                && journal.done.len() == DownloadJournal::chunk_count(size, chunk_size)
+                && part_size == Some(size)
+            {
+                return Ok(journal);
+            }
+            tracing::info!("Discarding partial download of a different artifact");
+        }
+
+        self.discard();
+        if let Some(parent) = self.part_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::File::create(&self.part_path)?.set_len(size)?;
+        let journal = DownloadJournal::new(checksum, size, chunk_size);
+        journal.save(&self.journal_path)?;
+        Ok(journal)
+    }
+
+    /// 받은 파일과 기록 삭제 (완료 후 또는 다른 아티팩트로 바뀌었을 때)
+    pub fn discard(&self) {
+        let _ = fs::remove_file(&self.part_path);
+        let _ = fs::remove_file(&self.journal_path);
+    }
+}
+
+/// 조각별 완료 기록 (데몬이 재시작해도 받은 조각은 다시 받지 않음)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadJournal {
+    /// 아티팩트 체크섬 (다른 아티팩트의 기록은 버림)
+    pub checksum: String,
+    pub size: u64,
+    pub chunk_size: u64,
+    /// 조각별 완료 여부
+    pub done: Vec<bool>,
+}
+
+impl DownloadJournal {
+    pub fn new(checksum: &str, size: u64, chunk_size: u64) -> Self {
+        Self {
+            checksum: checksum.to_string(),
+            size,
+            chunk_size,
+            done: vec![false; Self::chunk_count(size, chunk_size)],
+        }
+    }
+
+    pub fn chunk_count(size: u64, chunk_size: u64) -> usize {
+        size.div_ceil(chunk_size) as usize
+    }
+
+    /// 조각의 바이트 범위 (양 끝 포함)
+    pub fn range(&self, index: usize) -> (u64, u64) {
+        let start = index as u64 * self.chunk_size;
+        let end = (start + self.chunk_size).min(self.size) - 1;
+        (start, end)
+    }
+
+    /// 아직 받지 않은 조각
+    pub fn pending(&self) -> Vec<usize> {
+        (0..self.done.len()).filter(|&i| !self.done[i]).collect()
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        let data = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    /// 임시 파일에 쓰고 교체 (중간에 꺼져도 기록이 깨지지 않도록)
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
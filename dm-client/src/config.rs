@@ -1,7 +1,45 @@
 use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::abslot::AbConfig;
+use crate::error::ClientError;
+use crate::health::{self, HealthProbe, ProbeKind};
+use crate::httpcache;
+use crate::retry::RetryPolicy;
+
+/// 데몬 업데이트 소스 (DM_MODE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaemonMode {
+    /// dm-server 체크인 (기본)
+    Server,
+    /// 정적 HTTPS 디렉토리의 manifest.json Polling (서버 없음)
+    Static,
+}
+
+impl DaemonMode {
+    fn from_env(env: &Env) -> Result<Self, ConfigError> {
+        match env.non_empty("DM_MODE")?.as_deref() {
+            None | Some("server") => Ok(Self::Server),
+            Some("static") => Ok(Self::Static),
+            Some(other) => Err(ConfigError::Invalid {
+                var: "DM_MODE",
+                reason: format!("expected \"server\" or \"static\", got {:?}", other),
+            }),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
+    /// 업데이트 소스
+    pub mode: DaemonMode,
+
+    /// Static 모드 manifest URL (e.g., "https://host/updates/manifest.json")
+    pub manifest_url: Option<String>,
+
     /// DM Server URL (e.g., "http://localhost:3000")
     pub server_url: String,
     
@@ -10,55 +48,657 @@ pub struct Config {
     
     /// Polling interval in seconds
     pub poll_interval_secs: u64,
+
+    /// 폴링 간격 무작위 편차 (DM_POLL_JITTER_PCT, 기본 20. ±퍼센트, 0이면 편차 없음)
+    pub poll_jitter_pct: u32,
     
     /// Service directory (where the Next.js app lives)
     pub service_dir: String,
     
     /// Backup directory for rollback
     pub backup_dir: String,
+
+    /// Staging directory for staged (two-phase) updates
+    pub staging_dir: String,
+
+    /// 아티팩트를 추출할 작업 디렉토리 (DM_WORK_DIR, 없으면 서비스/스테이징 디렉토리 옆)
+    pub work_dir: Option<String>,
+
+    /// 아티팩트를 받아 두는 디렉토리 (DM_DOWNLOAD_DIR, 없으면 백업 디렉토리)
+    pub download_dir: Option<String>,
+
+    /// 데몬 제어 파일 디렉토리 (pause/resume, trigger-checkin)
+    pub control_dir: String,
     
     /// Command to restart the service
     pub restart_command: String,
     
     /// Command to check service health
     pub health_check_command: Option<String>,
+
+    /// HTTP 헬스 체크 URL (DM_HEALTH_CHECK_URL, 2xx면 정상. health_check_command와 함께 있으면 둘 다 통과해야 정상)
+    pub health_check_url: Option<String>,
+
+    /// 헬스 체크 프로브 목록 JSON (DM_HEALTH_CHECKS, 있으면 health_check_url/health_check_command 대신 사용)
+    pub health_checks: Option<String>,
+
+    /// 헬스 체크 시도 횟수와 시도 간격 (DM_HEALTH_CHECK_ATTEMPTS, DM_HEALTH_CHECK_INTERVAL_SECS)
+    pub health_check_attempts: u32,
+    pub health_check_interval_secs: u64,
+
+    /// 로컬 설정 역할/그룹 (DM_CLIENT_ROLE, 서버가 보낸 역할보다 우선)
+    pub role: Option<String>,
+
+    /// 설치할 아티팩트의 제품 (DM_EXPECTED_PRODUCT, 서버가 보낸 제품보다 우선)
+    pub expected_product: Option<String>,
+
+    /// 업데이트 전체 제한 시간 (다운로드부터 헬스 체크까지)
+    pub update_timeout_secs: u64,
+
+    /// 다운로드 중 데이터가 오지 않을 때 중단하는 시간
+    pub download_idle_timeout_secs: u64,
+
+    /// 다운로드 중과 설치 직전에 서버의 타겟이 바뀌었는지 확인하는 주기 (DM_SUPERSEDE_CHECK_SECS, 0이면 확인 안 함)
+    pub supersede_check_secs: u64,
+
+    /// 아티팩트 다운로드 동시 연결 수 (DM_DOWNLOAD_CONNECTIONS, 1이면 단일 스트림)
+    pub download_connections: usize,
+
+    /// 이 크기 이상의 아티팩트만 분할 다운로드 (DM_DOWNLOAD_PARALLEL_MIN_MB)
+    pub download_parallel_min_bytes: u64,
+
+    /// 백업 후 설치 전/헬스 체크 통과 후 실행할 명령 (DM_PRE_UPDATE_SCRIPT, DM_POST_UPDATE_SCRIPT, 서버 설정이 우선)
+    pub pre_update_script: Option<String>,
+    pub post_update_script: Option<String>,
+
+    /// 서버 설정의 rollback_on_failure (false면 실패해도 롤백 안 함, true면 업데이트 후 명령 실패도 롤백)
+    pub rollback_on_failure: Option<bool>,
+
+    /// 제한 시간을 지정하지 않은 헬스 체크 프로브의 제한 시간 (DM_HEALTH_CHECK_TIMEOUT_SECS, 서버 설정의 health_check_timeout이 우선)
+    pub health_check_timeout_secs: Option<u64>,
+
+    /// 연결 오류/시간 초과/5xx 재시도 횟수와 첫 대기 시간 (DM_HTTP_RETRIES, DM_HTTP_RETRY_BASE_MS)
+    pub http_retries: u32,
+    pub http_retry_base_ms: u64,
+
+    /// 재시작/헬스 체크 명령 제한 시간
+    pub command_timeout_secs: u64,
+
+    /// 설치 상태 서명 키 (DM_STATE_SECRET, 없으면 API Key 사용)
+    pub state_secret: Option<String>,
+
+    /// 아티팩트 서명 검증 공개 키 (DM_UPDATE_PUBLIC_KEY, base64 ed25519. 설정하면 서명이 없거나 맞지 않는 아티팩트 설치 거부)
+    pub update_public_key: Option<String>,
+
+    /// 서버가 내려준 설치 스크립트 실행 허용 (DM_ALLOW_REMOTE_SCRIPTS=true)
+    pub allow_remote_scripts: bool,
+
+    /// 설치 전 백업 필수 여부 (DM_BACKUP_REQUIRED=false 또는 --no-backup이면 백업 없이 설치)
+    pub backup_required: bool,
+
+    /// 백업을 tar.gz 한 파일로 압축 (DM_BACKUP_COMPRESS, 기본 true. false면 디렉토리 복사)
+    pub backup_compress: bool,
+
+    /// 다운로드 전 여유 공간 확인 배수 (DM_DISK_SPACE_FACTOR, 기본 3. 아티팩트 크기 × 배수: 다운로드 + 추출 + 백업)
+    pub disk_space_factor: f64,
+
+    /// 다운로드 전 여유 공간 확인 생략 (DM_SKIP_DISK_CHECK=1)
+    pub skip_disk_check: bool,
+
+    /// 설치된 버전보다 낮은 버전 설치 허용 (DM_ALLOW_DOWNGRADE=1, 기본은 거부. 배포의 allow_downgrade와 별개)
+    pub allow_downgrade: bool,
+
+    /// 남길 백업 수 (DM_BACKUP_KEEP, 기본 3. 새 백업을 만든 뒤 오래된 것부터 삭제, 0이면 모두 유지)
+    pub backup_keep: usize,
+
+    /// 설치와 롤백에서 그대로 둘 서비스 디렉토리 안의 경로 (DM_PRESERVE_PATHS, 쉼표 구분. 비어 있으면 서버 설정 사용)
+    pub preserve_paths: Vec<String>,
+
+    /// 백업에서 제외할 glob 패턴 (DM_BACKUP_EXCLUDE, 쉼표 구분. 비어 있으면 서버 설정 사용)
+    pub backup_exclude: Vec<String>,
+
+    /// 제외된 경로가 있는 백업으로 롤백한 뒤 install_command 실행 (DM_ROLLBACK_REGENERATE)
+    pub rollback_regenerate: Option<bool>,
+
+    /// 의존성 설치 명령 (DM_INSTALL_COMMAND, 예: "npm ci --omit=dev")
+    pub install_command: Option<String>,
+
+    /// 빈 장비의 첫 설치(bootstrap)에서 첫 재시작 전에 한 번 실행할 명령 (DM_BOOTSTRAP_COMMAND, 예: 초기 데이터 생성)
+    pub bootstrap_command: Option<String>,
+
+    /// 평문 HTTP 서버 URL과 서버가 내려준 평문 HTTP 아티팩트 URL 거부 (DM_REQUIRE_TLS=true, loopback 제외)
+    pub require_tls: bool,
+
+    /// 조건부 GET 캐시 끄기 (DM_NO_HTTP_CACHE=1, 디버깅용)
+    pub no_http_cache: bool,
+
+    /// 보안 업데이트(urgent)를 받은 뒤의 폴링 간격 (DM_URGENT_POLL_INTERVAL, 기본 5초, poll_interval보다 길면 무시)
+    pub urgent_poll_interval_secs: u64,
+
+    /// 서버가 보낸 폴링 간격 힌트(next_poll_secs, Retry-After)의 하한/상한 (DM_POLL_HINT_MIN_SECS, DM_POLL_HINT_MAX_SECS)
+    pub poll_hint_min_secs: u64,
+    pub poll_hint_max_secs: u64,
+
+    /// 일시 중지 중에도 보안 업데이트 설치 (DM_URGENT_DURING_PAUSE=true)
+    pub urgent_during_pause: bool,
+
+    /// USB 자동 적용: 데몬이 이 마운트 루트 아래에 나타난 USB 번들을 적용 (DM_USB_WATCH_DIR, 예: /media)
+    pub usb_watch_dir: Option<String>,
+
+    /// USB 마운트 루트 검사 주기 (DM_USB_WATCH_INTERVAL, 기본 5초)
+    pub usb_watch_interval_secs: u64,
+
+    /// A/B 파티션 이미지 업데이트 설정 (DM_AB_SLOTS가 있을 때만)
+    pub ab: Option<AbConfig>,
+}
+
+/// 환경 변수 설정 오류 (시작 시 문제가 된 변수와 기대 형식을 그대로 출력)
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// 필수 변수가 없거나 비어 있음
+    #[error("Missing environment variable {var} (expected {expected})")]
+    Missing { var: &'static str, expected: &'static str },
+    /// 값을 해석할 수 없음 (UTF-8이 아니거나 형식이 맞지 않음)
+    #[error("Invalid {var}: {reason}")]
+    Invalid { var: &'static str, reason: String },
+}
+
+/// 설정을 읽을 환경 변수 조회 (실행 중에는 프로세스 환경, 테스트에서는 주입한 값)
+pub(crate) struct Env<'a>(&'a dyn Fn(&str) -> Option<OsString>);
+
+impl Env<'_> {
+    /// 환경 변수 값 (없으면 None, UTF-8이 아니면 에러)
+    pub(crate) fn var(&self, name: &'static str) -> Result<Option<String>, ConfigError> {
+        match (self.0)(name) {
+            None => Ok(None),
+            Some(value) => value.into_string().map(Some).map_err(|_| ConfigError::Invalid {
+                var: name,
+                reason: "value is not valid UTF-8".to_string(),
+            }),
+        }
+    }
+
+    /// 비어 있지 않은 환경 변수 값
+    pub(crate) fn non_empty(&self, name: &'static str) -> Result<Option<String>, ConfigError> {
+        Ok(self.var(name)?.filter(|v| !v.trim().is_empty()))
+    }
+
+    /// 필수 환경 변수 (없거나 비어 있으면 에러)
+    pub(crate) fn required(&self, name: &'static str, expected: &'static str) -> Result<String, ConfigError> {
+        self.non_empty(name)?.ok_or(ConfigError::Missing { var: name, expected })
+    }
+
+    /// 참/거짓 환경 변수 (`true`/`false` 또는 `1`/`0`, 없거나 비어 있으면 None, 그 밖의 값은 에러)
+    fn boolean(&self, name: &'static str) -> Result<Option<bool>, ConfigError> {
+        let Some(value) = self.non_empty(name)? else {
+            return Ok(None);
+        };
+        match value.trim() {
+            "true" | "1" => Ok(Some(true)),
+            "false" | "0" => Ok(Some(false)),
+            _ => Err(ConfigError::Invalid {
+                var: name,
+                reason: format!("expected true, false, 1 or 0, got {:?}", value),
+            }),
+        }
+    }
+
+    /// 참/거짓 환경 변수 (없으면 기본값)
+    fn flag(&self, name: &'static str, default: bool) -> Result<bool, ConfigError> {
+        Ok(self.boolean(name)?.unwrap_or(default))
+    }
+
+    /// 숫자 환경 변수 (없거나 비어 있으면 None, 해석할 수 없으면 기대 형식과 함께 에러)
+    fn parse<T: FromStr>(&self, name: &'static str, expected: &str) -> Result<Option<T>, ConfigError> {
+        let Some(value) = self.non_empty(name)? else {
+            return Ok(None);
+        };
+        value.trim().parse().map(Some).map_err(|_| ConfigError::Invalid {
+            var: name,
+            reason: format!("expected {}, got {:?}", expected, value),
+        })
+    }
+
+    /// 쉼표로 구분된 목록 환경 변수
+    fn list(&self, name: &'static str) -> Result<Vec<String>, ConfigError> {
+        Ok(self
+            .var(name)?
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// 초 단위 환경 변수 (없으면 기본값)
+    fn secs(&self, name: &'static str, default: u64) -> Result<u64, ConfigError> {
+        Ok(self.parse(name, SECONDS)?.unwrap_or(default))
+    }
+}
+
+const SECONDS: &str = "a whole number of seconds";
+const COUNT: &str = "a non-negative integer";
+
+/// 분할 다운로드 동시 연결 상한
+const MAX_DOWNLOAD_CONNECTIONS: usize = 16;
+
+/// 동시 연결 수 (없으면 1, 1~MAX_DOWNLOAD_CONNECTIONS 밖은 에러)
+fn env_connections(env: &Env) -> Result<usize, ConfigError> {
+    const CONNECTIONS: &str = "a number of connections from 1 to 16";
+    match env.parse::<usize>("DM_DOWNLOAD_CONNECTIONS", CONNECTIONS)? {
+        None => Ok(1),
+        Some(n) if (1..=MAX_DOWNLOAD_CONNECTIONS).contains(&n) => Ok(n),
+        Some(n) => Err(ConfigError::Invalid {
+            var: "DM_DOWNLOAD_CONNECTIONS",
+            reason: format!("expected {}, got {}", CONNECTIONS, n),
+        }),
+    }
+}
+
+/// 여유 공간 확인 배수 (없으면 3, 0 이하는 에러)
+fn env_disk_space_factor(env: &Env) -> Result<f64, ConfigError> {
+    const POSITIVE: &str = "a positive number";
+    match env.parse::<f64>("DM_DISK_SPACE_FACTOR", POSITIVE)? {
+        None => Ok(3.0),
+        Some(factor) if factor.is_finite() && factor > 0.0 => Ok(factor),
+        Some(factor) => Err(ConfigError::Invalid {
+            var: "DM_DISK_SPACE_FACTOR",
+            reason: format!("expected {}, got {}", POSITIVE, factor),
+        }),
+    }
+}
+
+/// 폴링 간격 편차 (없으면 20%, 100 초과는 에러)
+fn env_jitter_pct(env: &Env) -> Result<u32, ConfigError> {
+    const PERCENT: &str = "a percentage from 0 to 100";
+    match env.parse::<u32>("DM_POLL_JITTER_PCT", PERCENT)? {
+        None => Ok(20),
+        Some(pct) if pct <= 100 => Ok(pct),
+        Some(pct) => Err(ConfigError::Invalid {
+            var: "DM_POLL_JITTER_PCT",
+            reason: format!("expected {}, got {}", PERCENT, pct),
+        }),
+    }
+}
+
+/// 분할 다운로드 최소 크기 (MB 단위 환경 변수, 바이트로 바꿀 수 없을 만큼 크면 에러)
+fn env_parallel_min_bytes(env: &Env) -> Result<u64, ConfigError> {
+    const MEGABYTES: &str = "a whole number of megabytes";
+    let mb = env.parse("DM_DOWNLOAD_PARALLEL_MIN_MB", MEGABYTES)?.unwrap_or(64u64);
+    mb.checked_mul(1024 * 1024).ok_or_else(|| ConfigError::Invalid {
+        var: "DM_DOWNLOAD_PARALLEL_MIN_MB",
+        reason: format!("expected {}, got {} (too large)", MEGABYTES, mb),
+    })
 }
 
 impl Config {
-    pub fn from_env() -> Result<Self, env::VarError> {
+    /// 데몬용 설정 (Server 모드는 DM_SERVER_URL/DM_API_KEY, Static 모드는 DM_MANIFEST_URL 필수)
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::load(&Env(&|name| env::var_os(name)))
+    }
+
+    /// Apply/Status 등 서버 접속 없이 동작하는 명령용 (서버 접속 정보는 없어도 되지만 값이 있으면 검증)
+    pub fn from_env_optional() -> Result<Self, ConfigError> {
+        Self::load_optional(&Env(&|name| env::var_os(name)))
+    }
+
+    /// 데몬용 설정을 주어진 환경 변수 조회로 읽기
+    fn load(env: &Env) -> Result<Self, ConfigError> {
+        let mut config = Self::load_optional(env)?;
+        match config.mode {
+            DaemonMode::Server => {
+                config.server_url = env.required("DM_SERVER_URL", "the dm-server URL, e.g. http://localhost:3000")?;
+                config.api_key = env.required("DM_API_KEY", "the API key returned when the client was registered")?;
+            }
+            DaemonMode::Static => {
+                config.manifest_url = Some(env.required(
+                    "DM_MANIFEST_URL",
+                    "the manifest.json URL when DM_MODE=static, e.g. https://files.internal/updates/manifest.json",
+                )?);
+            }
+        }
+        Ok(config)
+    }
+
+    /// 서버 접속 정보 없이도 되는 설정을 주어진 환경 변수 조회로 읽기
+    fn load_optional(env: &Env) -> Result<Self, ConfigError> {
         Ok(Self {
-            server_url: env::var("DM_SERVER_URL")?,
-            api_key: env::var("DM_API_KEY")?,
-            poll_interval_secs: env::var("DM_POLL_INTERVAL")
-                .unwrap_or_else(|_| "30".to_string())
-                .parse()
-                .unwrap_or(30),
-            service_dir: env::var("DM_SERVICE_DIR")
-                .unwrap_or_else(|_| "./service".to_string()),
-            backup_dir: env::var("DM_BACKUP_DIR")
-                .unwrap_or_else(|_| "./backups".to_string()),
-            restart_command: env::var("DM_RESTART_COMMAND")
-                .unwrap_or_else(|_| "pm2 restart all".to_string()),
-            health_check_command: env::var("DM_HEALTH_CHECK_COMMAND").ok(),
+            mode: DaemonMode::from_env(env)?,
+            manifest_url: env.non_empty("DM_MANIFEST_URL")?,
+            server_url: env.var("DM_SERVER_URL")?.unwrap_or_default(),
+            api_key: env.var("DM_API_KEY")?.unwrap_or_default(),
+            poll_interval_secs: env.secs("DM_POLL_INTERVAL", 30)?,
+            poll_jitter_pct: env_jitter_pct(env)?,
+            service_dir: env.var("DM_SERVICE_DIR")?.unwrap_or_else(|| "./service".to_string()),
+            backup_dir: env.var("DM_BACKUP_DIR")?.unwrap_or_else(|| "./backups".to_string()),
+            staging_dir: env.var("DM_STAGING_DIR")?.unwrap_or_else(|| "./staging".to_string()),
+            work_dir: env.var("DM_WORK_DIR")?.filter(|s| !s.is_empty()),
+            download_dir: env.var("DM_DOWNLOAD_DIR")?.filter(|s| !s.is_empty()),
+            control_dir: env.var("DM_CONTROL_DIR")?.unwrap_or_else(|| "./control".to_string()),
+            restart_command: env.var("DM_RESTART_COMMAND")?.unwrap_or_else(|| "pm2 restart all".to_string()),
+            health_check_command: env.var("DM_HEALTH_CHECK_COMMAND")?,
+            health_check_url: env.non_empty("DM_HEALTH_CHECK_URL")?,
+            health_checks: env.non_empty("DM_HEALTH_CHECKS")?,
+            health_check_attempts: env.parse("DM_HEALTH_CHECK_ATTEMPTS", COUNT)?.unwrap_or(5u32).max(1),
+            health_check_interval_secs: env.secs("DM_HEALTH_CHECK_INTERVAL_SECS", 3)?,
+            role: env.var("DM_CLIENT_ROLE")?.filter(|r| !r.is_empty()),
+            expected_product: env.var("DM_EXPECTED_PRODUCT")?.filter(|p| !p.is_empty()),
+            update_timeout_secs: env.secs("DM_UPDATE_TIMEOUT_SECS", 7200)?,
+            download_idle_timeout_secs: env.secs("DM_DOWNLOAD_IDLE_TIMEOUT_SECS", 60)?,
+            supersede_check_secs: env.secs("DM_SUPERSEDE_CHECK_SECS", 30)?,
+            download_connections: env_connections(env)?,
+            download_parallel_min_bytes: env_parallel_min_bytes(env)?,
+            pre_update_script: env.non_empty("DM_PRE_UPDATE_SCRIPT")?,
+            post_update_script: env.non_empty("DM_POST_UPDATE_SCRIPT")?,
+            rollback_on_failure: None,
+            health_check_timeout_secs: env.parse("DM_HEALTH_CHECK_TIMEOUT_SECS", SECONDS)?.filter(|t| *t > 0),
+            http_retries: env.parse("DM_HTTP_RETRIES", COUNT)?.unwrap_or(3),
+            http_retry_base_ms: env.parse("DM_HTTP_RETRY_BASE_MS", "a whole number of milliseconds")?.unwrap_or(500),
+            command_timeout_secs: env.secs("DM_COMMAND_TIMEOUT_SECS", 300)?,
+            state_secret: env.var("DM_STATE_SECRET")?.filter(|s| !s.is_empty()),
+            update_public_key: env.non_empty("DM_UPDATE_PUBLIC_KEY")?,
+            allow_remote_scripts: env.flag("DM_ALLOW_REMOTE_SCRIPTS", false)?,
+            backup_required: env.flag("DM_BACKUP_REQUIRED", true)?,
+            backup_compress: env.flag("DM_BACKUP_COMPRESS", true)?,
+            disk_space_factor: env_disk_space_factor(env)?,
+            skip_disk_check: env.flag("DM_SKIP_DISK_CHECK", false)?,
+            allow_downgrade: env.flag("DM_ALLOW_DOWNGRADE", false)?,
+            backup_keep: env.parse("DM_BACKUP_KEEP", COUNT)?.unwrap_or(3),
+            preserve_paths: env.list("DM_PRESERVE_PATHS")?,
+            backup_exclude: env.list("DM_BACKUP_EXCLUDE")?,
+            rollback_regenerate: env.boolean("DM_ROLLBACK_REGENERATE")?,
+            install_command: env.var("DM_INSTALL_COMMAND")?.filter(|c| !c.is_empty()),
+            bootstrap_command: env.var("DM_BOOTSTRAP_COMMAND")?.filter(|c| !c.is_empty()),
+            require_tls: env.flag("DM_REQUIRE_TLS", false)?,
+            no_http_cache: env.flag("DM_NO_HTTP_CACHE", false)?,
+            urgent_poll_interval_secs: env.secs("DM_URGENT_POLL_INTERVAL", 5)?,
+            poll_hint_min_secs: env.secs("DM_POLL_HINT_MIN_SECS", 5)?,
+            poll_hint_max_secs: env.secs("DM_POLL_HINT_MAX_SECS", 3600)?,
+            urgent_during_pause: env.flag("DM_URGENT_DURING_PAUSE", false)?,
+            usb_watch_dir: env.var("DM_USB_WATCH_DIR")?.filter(|s| !s.is_empty()),
+            usb_watch_interval_secs: env.secs("DM_USB_WATCH_INTERVAL", 5)?,
+            ab: AbConfig::from_env(env)?,
+        })
+    }
+
+    /// 로컬 헬스 체크 프로브 (DM_HEALTH_CHECKS > DM_HEALTH_CHECK_URL + DM_HEALTH_CHECK_COMMAND, 모두 없으면 None)
+    pub fn health_probes(&self) -> anyhow::Result<Option<Vec<HealthProbe>>> {
+        if let Some(json) = &self.health_checks {
+            return health::parse(json).map(Some);
+        }
+        let mut probes = Vec::new();
+        if let Some(url) = &self.health_check_url {
+            let probe = HealthProbe::new(ProbeKind::Http {
+                url: url.clone(),
+                expected_status: None,
+            });
+            probe
+                .validate()
+                .map_err(|e| ClientError::Config(format!("Invalid DM_HEALTH_CHECK_URL: {}", e)))?;
+            probes.push(probe);
+        }
+        if let Some(command) = &self.health_check_command {
+            probes.push(HealthProbe::new(ProbeKind::Command {
+                command: command.clone(),
+            }));
+        }
+        Ok((!probes.is_empty()).then_some(probes))
+    }
+
+    /// 서버 요청과 다운로드 재시도 정책 (지수 백오프 + jitter)
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.http_retries,
+            base_delay: Duration::from_millis(self.http_retry_base_ms),
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// 아티팩트를 받는 디렉토리 (DM_DOWNLOAD_DIR > DM_BACKUP_DIR)
+    pub fn download_dir(&self) -> &Path {
+        Path::new(self.download_dir.as_deref().unwrap_or(&self.backup_dir))
+    }
+
+    /// 조건부 GET 캐시 파일 (DM_NO_HTTP_CACHE면 None)
+    pub fn http_cache_path(&self) -> Option<PathBuf> {
+        (!self.no_http_cache).then(|| Path::new(&self.control_dir).join(httpcache::CACHE_FILE))
+    }
+
+    /// 설치 상태 서명 키 (설정되지 않았으면 None, 서명 없이 동작)
+    pub fn state_key(&self) -> Option<&[u8]> {
+        self.state_secret
+            .as_deref()
+            .or((!self.api_key.is_empty()).then_some(self.api_key.as_str()))
+            .map(str::as_bytes)
+    }
+
+    /// 실제 적용 설정 (서버 보고용, API Key 등 비밀 값 제외)
+    pub fn effective(&self) -> serde_json::Value {
+        // URL에 포함된 인증 정보 제거
+        let server_url = match reqwest::Url::parse(&self.server_url) {
+            Ok(mut url) => {
+                let _ = url.set_username("");
+                let _ = url.set_password(None);
+                url.to_string()
+            }
+            Err(_) => self.server_url.clone(),
+        };
+
+        serde_json::json!({
+            "server_url": server_url,
+            "poll_interval_secs": self.poll_interval_secs,
+            "poll_jitter_pct": self.poll_jitter_pct,
+            "service_dir": self.service_dir,
+            "backup_dir": self.backup_dir,
+            "staging_dir": self.staging_dir,
+            "work_dir": self.work_dir,
+            "download_dir": self.download_dir(),
+            "control_dir": self.control_dir,
+            "restart_command": self.restart_command,
+            "pre_update_script": self.pre_update_script,
+            "post_update_script": self.post_update_script,
+            "rollback_on_failure": self.rollback_on_failure,
+            "health_check_timeout_secs": self.health_check_timeout_secs,
+            "health_check_command": self.health_check_command,
+            "health_check_url": self.health_check_url,
+            "health_check_attempts": self.health_check_attempts,
+            "health_check_interval_secs": self.health_check_interval_secs,
+            "health_checks": self.health_probes().ok().flatten(),
+            "role": self.role,
+            "product": self.expected_product,
+            "update_timeout_secs": self.update_timeout_secs,
+            "download_idle_timeout_secs": self.download_idle_timeout_secs,
+            "http_retries": self.http_retries,
+            "http_retry_base_ms": self.http_retry_base_ms,
+            "supersede_check_secs": self.supersede_check_secs,
+            "download_connections": self.download_connections,
+            "download_parallel_min_bytes": self.download_parallel_min_bytes,
+            "command_timeout_secs": self.command_timeout_secs,
+            "allow_remote_scripts": self.allow_remote_scripts,
+            "update_public_key": self.update_public_key,
+            "backup_required": self.backup_required,
+            "backup_compress": self.backup_compress,
+            "backup_keep": self.backup_keep,
+            "preserve_paths": self.preserve_paths,
+            "disk_space_factor": self.disk_space_factor,
+            "skip_disk_check": self.skip_disk_check,
+            "allow_downgrade": self.allow_downgrade,
+            "backup_exclude": self.backup_exclude,
+            "rollback_regenerate": self.rollback_regenerate,
+            "install_command": self.install_command,
+            "bootstrap_command": self.bootstrap_command,
+            "require_tls": self.require_tls,
+            "urgent_poll_interval_secs": self.urgent_poll_interval_secs,
+            "poll_hint_min_secs": self.poll_hint_min_secs,
+            "poll_hint_max_secs": self.poll_hint_max_secs,
+            "urgent_during_pause": self.urgent_during_pause,
+            "usb_watch_dir": self.usb_watch_dir,
+            "usb_watch_interval_secs": self.usb_watch_interval_secs,
+            "ab": self.ab.as_ref().map(AbConfig::effective),
         })
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::os::unix::ffi::OsStringExt;
+
+    const SERVER: [(&str, &str); 2] = [("DM_SERVER_URL", "http://localhost:3000"), ("DM_API_KEY", "key")];
+
+    fn lookup(vars: &[(&str, &str)]) -> HashMap<String, OsString> {
+        vars.iter().map(|(k, v)| (k.to_string(), OsString::from(v))).collect()
+    }
+
+    fn load(vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let vars = lookup(vars);
+        Config::load(&Env(&|name| vars.get(name).cloned()))
+    }
 
-    /// Apply/Status 등 서버 접속 없이 동작하는 명령용
-    pub fn from_env_optional() -> Self {
-        Self {
-            server_url: env::var("DM_SERVER_URL").unwrap_or_default(),
-            api_key: env::var("DM_API_KEY").unwrap_or_default(),
-            poll_interval_secs: env::var("DM_POLL_INTERVAL")
-                .unwrap_or_else(|_| "30".to_string())
-                .parse()
-                .unwrap_or(30),
-            service_dir: env::var("DM_SERVICE_DIR")
-                .unwrap_or_else(|_| "./service".to_string()),
-            backup_dir: env::var("DM_BACKUP_DIR")
-                .unwrap_or_else(|_| "./backups".to_string()),
-            restart_command: env::var("DM_RESTART_COMMAND")
-                .unwrap_or_else(|_| "pm2 restart all".to_string()),
-            health_check_command: env::var("DM_HEALTH_CHECK_COMMAND").ok(),
+    fn error(vars: &[(&str, &str)]) -> String {
+        load(vars).expect_err("config must be rejected").to_string()
+    }
+
+    #[test]
+    fn missing_server_settings_name_the_variable() {
+        assert_eq!(
+            error(&[]),
+            "Missing environment variable DM_SERVER_URL (expected the dm-server URL, e.g. http://localhost:3000)"
+        );
+        assert_eq!(
+            error(&[("DM_SERVER_URL", "http://localhost:3000"), ("DM_API_KEY", "  ")]),
+            "Missing environment variable DM_API_KEY (expected the API key returned when the client was registered)"
+        );
+        assert!(error(&[("DM_MODE", "static")])
+            .starts_with("Missing environment variable DM_MANIFEST_URL (expected the manifest.json URL"));
+
+        // 분류는 설정 오류 (종료 코드 3)
+        let classified = ClientError::of(&anyhow::Error::new(load(&[]).unwrap_err()));
+        assert_eq!(classified.map(|c| c.exit_code()), Some(3));
+    }
+
+    #[test]
+    fn invalid_values_are_rejected_instead_of_defaulted() {
+        let with = |name, value| {
+            let mut vars = SERVER.to_vec();
+            vars.push((name, value));
+            error(&vars)
+        };
+        assert_eq!(
+            with("DM_POLL_INTERVAL", "abc"),
+            r#"Invalid DM_POLL_INTERVAL: expected a whole number of seconds, got "abc""#
+        );
+        assert_eq!(
+            with("DM_DISK_SPACE_FACTOR", "0"),
+            "Invalid DM_DISK_SPACE_FACTOR: expected a positive number, got 0"
+        );
+        assert_eq!(
+            with("DM_MODE", "push"),
+            r#"Invalid DM_MODE: expected "server" or "static", got "push""#
+        );
+
+        // 서버 접속 정보가 없어도 되는 명령도 잘못된 값은 거부
+        let vars = lookup(&[("DM_POLL_INTERVAL", "abc")]);
+        let optional = Config::load_optional(&Env(&|name| vars.get(name).cloned()));
+        assert_eq!(
+            optional.expect_err("invalid interval must be rejected").to_string(),
+            r#"Invalid DM_POLL_INTERVAL: expected a whole number of seconds, got "abc""#
+        );
+    }
+
+    #[test]
+    fn out_of_range_download_settings_are_rejected() {
+        let with = |name, value| {
+            let mut vars = SERVER.to_vec();
+            vars.push((name, value));
+            load(&vars)
+        };
+        assert_eq!(with("DM_DOWNLOAD_CONNECTIONS", "16").unwrap().download_connections, 16);
+        for value in ["0", "17"] {
+            assert_eq!(
+                with("DM_DOWNLOAD_CONNECTIONS", value).unwrap_err().to_string(),
+                format!("Invalid DM_DOWNLOAD_CONNECTIONS: expected a number of connections from 1 to 16, got {}", value)
+            );
         }
+        assert_eq!(with("DM_DOWNLOAD_PARALLEL_MIN_MB", "2").unwrap().download_parallel_min_bytes, 2 * 1024 * 1024);
+        assert_eq!(
+            with("DM_DOWNLOAD_PARALLEL_MIN_MB", "17592186044416").unwrap_err().to_string(),
+            "Invalid DM_DOWNLOAD_PARALLEL_MIN_MB: expected a whole number of megabytes, got 17592186044416 (too large)"
+        );
+    }
+
+    #[test]
+    fn ab_slots_are_checked_at_startup() {
+        let with_slots = |slots, commands: bool| {
+            let mut vars = SERVER.to_vec();
+            vars.push(("DM_AB_SLOTS", slots));
+            if commands {
+                vars.push(("DM_AB_ACTIVE_SLOT_COMMAND", "cat /tmp/booted"));
+                vars.push(("DM_AB_SWITCH_COMMAND", "echo {slot}"));
+            }
+            load(&vars)
+        };
+
+        let ab = with_slots("A=/dev/sda2,B=/dev/sda3", true).unwrap().ab.expect("A/B config");
+        assert_eq!(ab.reboot_command, "reboot");
+        ab.validate().unwrap();
+
+        assert_eq!(
+            with_slots("A=/dev/sda2,B", true).unwrap_err().to_string(),
+            r#"Invalid DM_AB_SLOTS: expected NAME=DEVICE, got "B""#
+        );
+        assert!(with_slots("A=/dev/sda2,B=/dev/sda3", false)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Missing environment variable DM_AB_ACTIVE_SLOT_COMMAND"));
+    }
+
+    #[test]
+    fn non_utf8_values_are_rejected() {
+        let mut vars = lookup(&SERVER);
+        vars.insert("DM_POLL_INTERVAL".to_string(), OsString::from_vec(b"3\xff0".to_vec()));
+        let err = Config::load(&Env(&|name| vars.get(name).cloned())).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid DM_POLL_INTERVAL: value is not valid UTF-8");
+
+        let mut vars = lookup(&SERVER);
+        vars.insert("DM_AB_SLOTS".to_string(), OsString::from_vec(b"A=/dev/sda\xff2".to_vec()));
+        let err = Config::load(&Env(&|name| vars.get(name).cloned())).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid DM_AB_SLOTS: value is not valid UTF-8");
+    }
+
+    #[test]
+    fn booleans_accept_true_false_one_zero_only() {
+        let with = |name, value| {
+            let mut vars = SERVER.to_vec();
+            vars.push((name, value));
+            load(&vars)
+        };
+        assert!(with("DM_REQUIRE_TLS", "1").unwrap().require_tls);
+        assert!(with("DM_ALLOW_REMOTE_SCRIPTS", "true").unwrap().allow_remote_scripts);
+        assert!(!with("DM_BACKUP_REQUIRED", "0").unwrap().backup_required);
+        assert!(!with("DM_BACKUP_COMPRESS", "false").unwrap().backup_compress);
+        assert!(with("DM_SKIP_DISK_CHECK", "1").unwrap().skip_disk_check);
+        assert_eq!(with("DM_ROLLBACK_REGENERATE", "0").unwrap().rollback_regenerate, Some(false));
+
+        for (name, value) in [("DM_ALLOW_REMOTE_SCRIPTS", "yes"), ("DM_BACKUP_REQUIRED", "off"), ("DM_REQUIRE_TLS", "TRUE")] {
+            assert_eq!(
+                with(name, value).unwrap_err().to_string(),
+                format!("Invalid {}: expected true, false, 1 or 0, got {:?}", name, value)
+            );
+        }
+    }
+
+    #[test]
+    fn defaults_apply_when_variables_are_unset() {
+        let config = load(&[("DM_SERVER_URL", "http://localhost:3000"), ("DM_API_KEY", "key"), ("DM_POLL_INTERVAL", " 45 ")])
+            .expect("valid config");
+        assert_eq!(config.mode, DaemonMode::Server);
+        assert_eq!(config.poll_interval_secs, 45);
+        assert!(config.backup_required && config.backup_compress);
+        assert!(!config.require_tls && !config.allow_downgrade);
+        assert_eq!(config.rollback_regenerate, None);
+        assert!(config.ab.is_none());
     }
 }
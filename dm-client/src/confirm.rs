@@ -0,0 +1,31 @@
+use anyhow::Result;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use crate::error::ClientError;
+
+/// 파괴적 작업(apply, rollback) 전 확인
+///
+/// `--yes`면 바로 진행한다. 터미널이면 요약을 출력하고 "yes" 입력을 요구하며,
+/// 터미널이 아니면(스크립트, 파이프) `--yes` 없이는 거부한다.
+pub fn confirm(action: &str, summary: &str, assume_yes: bool) -> Result<()> {
+    if assume_yes {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        anyhow::bail!(ClientError::Config(format!(
+            "{}은(는) 서비스 디렉토리를 교체합니다. 터미널이 아닌 환경에서는 --yes(-y)로 명시적으로 확인해야 합니다",
+            action
+        )));
+    }
+
+    println!("{}", summary);
+    print!("계속하려면 yes를 입력하세요: ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if answer.trim() != "yes" {
+        anyhow::bail!("{} 취소됨", action);
+    }
+    Ok(())
+}
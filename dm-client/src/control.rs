@@ -0,0 +1,92 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// 일시 정지 상태 파일 (control_dir/pause.json)
+const PAUSE_FILE: &str = "pause.json";
+
+/// 즉시 체크인 요청 파일 (control_dir/trigger-checkin)
+const TRIGGER_FILE: &str = "trigger-checkin";
+
+/// 데몬 일시 정지 상태
+///
+/// 정지 중에도 체크인은 계속하지만("paused" 보고) 업데이트는 시작하지 않는다.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PauseState {
+    pub paused_at: DateTime<Utc>,
+    /// 자동 재개 시각 (없으면 resume 전까지 유지)
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// 일시 정지 (이미 정지 중이면 새 값으로 대체)
+pub fn pause(control_dir: &str, duration: Option<Duration>, reason: Option<String>) -> Result<PauseState> {
+    let now = Utc::now();
+    let until = duration
+        .map(chrono::Duration::from_std)
+        .transpose()?
+        .map(|d| now + d);
+    let state = PauseState {
+        paused_at: now,
+        until,
+        reason,
+    };
+
+    fs::create_dir_all(control_dir)?;
+    fs::write(
+        Path::new(control_dir).join(PAUSE_FILE),
+        serde_json::to_string_pretty(&state)?,
+    )?;
+    Ok(state)
+}
+
+/// 재개. 정지 중이었으면 true
+pub fn resume(control_dir: &str) -> Result<bool> {
+    match fs::remove_file(Path::new(control_dir).join(PAUSE_FILE)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 현재 정지 상태 (기한이 지났으면 파일을 지우고 None)
+pub fn current_pause(control_dir: &str) -> Option<PauseState> {
+    let path = Path::new(control_dir).join(PAUSE_FILE);
+    let data = fs::read_to_string(&path).ok()?;
+    let state: PauseState = match serde_json::from_str(&data) {
+        Ok(state) => state,
+        Err(e) => {
+            // 손상된 파일도 정지로 취급 (기술자가 의도한 정지를 무시하지 않음)
+            tracing::warn!("Unreadable pause file {:?} ({}), treating as paused", path, e);
+            return Some(PauseState {
+                paused_at: Utc::now(),
+                until: None,
+                reason: None,
+            });
+        }
+    };
+
+    if state.until.is_some_and(|until| until <= Utc::now()) {
+        tracing::info!("Pause expired, resuming updates");
+        let _ = fs::remove_file(&path);
+        return None;
+    }
+    Some(state)
+}
+
+/// 실행 중인 데몬에 즉시 체크인 요청
+pub fn request_checkin(control_dir: &str) -> Result<()> {
+    fs::create_dir_all(control_dir)?;
+    fs::write(Path::new(control_dir).join(TRIGGER_FILE), Utc::now().to_rfc3339())?;
+    Ok(())
+}
+
+/// 체크인 요청 확인 후 소비
+pub fn take_checkin_request(control_dir: &str) -> bool {
+    fs::remove_file(Path::new(control_dir).join(TRIGGER_FILE)).is_ok()
+}
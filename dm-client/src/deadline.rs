@@ -0,0 +1,111 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// 업데이트 단계 (타임아웃 보고용)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdatePhase {
+    Download,
+    Verify,
+    Backup,
+    PreInstall,
+    Install,
+    PostInstall,
+    Restart,
+    HealthCheck,
+    /// A/B 이미지: 부트 슬롯 전환
+    SwitchSlot,
+    /// A/B 이미지: 재부팅
+    Reboot,
+    /// A/B 이미지: 재부팅 후 새 슬롯 확정
+    ConfirmSlot,
+}
+
+impl UpdatePhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Download => "download",
+            Self::Verify => "verify",
+            Self::Backup => "backup",
+            Self::PreInstall => "pre_install",
+            Self::Install => "install",
+            Self::PostInstall => "post_install",
+            Self::Restart => "restart",
+            Self::HealthCheck => "health_check",
+            Self::SwitchSlot => "switch_slot",
+            Self::Reboot => "reboot",
+            Self::ConfirmSlot => "confirm_slot",
+        }
+    }
+}
+
+impl fmt::Display for UpdatePhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 업데이트 전체 제한 시간 초과 (DM_UPDATE_TIMEOUT_SECS)
+#[derive(Debug)]
+pub struct UpdateTimedOut {
+    pub phase: UpdatePhase,
+    pub limit: Duration,
+}
+
+impl fmt::Display for UpdateTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "update timed out after {}s during {}",
+            self.limit.as_secs(),
+            self.phase
+        )
+    }
+}
+
+impl std::error::Error for UpdateTimedOut {}
+
+/// 업데이트 전체 기한
+///
+/// 설치 전 비동기 구간(다운로드)은 `tokio::time::timeout_at`으로 취소되고, 설치와 그 이후(추출,
+/// 재시작, 헬스 체크 등 명령 실행)는 각 단계에서 `check`를 호출해 기한이 지나면 스스로 중단한다.
+/// 설치 이후의 타임아웃은 다른 실패와 같이 백업으로 롤백된다.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    at: Instant,
+    limit: Duration,
+}
+
+impl Deadline {
+    pub fn after(limit: Duration) -> Self {
+        Self {
+            at: Instant::now() + limit,
+            limit,
+        }
+    }
+
+    pub fn instant(&self) -> Instant {
+        self.at
+    }
+
+    /// 남은 시간 (지났으면 0)
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    /// 해당 단계에서의 타임아웃 에러
+    pub fn timed_out(&self, phase: UpdatePhase) -> anyhow::Error {
+        UpdateTimedOut {
+            phase,
+            limit: self.limit,
+        }
+        .into()
+    }
+
+    /// 기한이 지났으면 중단
+    pub fn check(&self, phase: UpdatePhase) -> anyhow::Result<()> {
+        if Instant::now() >= self.at {
+            return Err(self.timed_out(phase));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use sysinfo::Disks;
+
+/// 업데이트에 필요한 여유 공간이 없는 파일시스템이 있음 (다운로드 전에 중단, 서비스 디렉토리는 그대로)
+///
+/// 서버에는 `failure_reason: "insufficient_disk_space"`로 보고한다.
+#[derive(Debug)]
+pub struct InsufficientSpace {
+    /// 아티팩트 크기 (서버가 보낸 artifact_size)
+    pub artifact_size: u64,
+    /// DM_DISK_SPACE_FACTOR
+    pub factor: f64,
+    /// 파일시스템마다 필요한 바이트 수 (artifact_size × factor)
+    pub required: u64,
+    /// 부족한 파일시스템
+    pub short: Vec<Shortfall>,
+}
+
+/// 공간이 부족한 파일시스템 하나
+#[derive(Debug)]
+pub struct Shortfall {
+    pub mount_point: PathBuf,
+    pub available: u64,
+    /// 이 파일시스템에 있는 디렉토리 (설정 이름)
+    pub dirs: Vec<&'static str>,
+}
+
+impl std::error::Error for InsufficientSpace {}
+
+impl fmt::Display for InsufficientSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Insufficient disk space: need {} bytes free per filesystem (artifact {} bytes x DM_DISK_SPACE_FACTOR {})",
+            self.required, self.artifact_size, self.factor
+        )?;
+        for short in &self.short {
+            write!(
+                f,
+                "; {:?} has {} bytes free ({})",
+                short.mount_point,
+                short.available,
+                short.dirs.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 각 디렉토리가 있는 파일시스템에 `artifact_size × factor` 바이트 이상 남아 있는지 확인
+///
+/// 같은 파일시스템에 있는 디렉토리는 한 번만 검사한다. 마운트 지점을 찾지 못한 디렉토리는
+/// 검사하지 않는다 (디스크 목록을 읽을 수 없는 환경에서 업데이트를 막지 않도록).
+pub fn check(dirs: &[(&'static str, &Path)], artifact_size: u64, factor: f64) -> Result<(), InsufficientSpace> {
+    let required = (artifact_size as f64 * factor).ceil() as u64;
+    let disks = Disks::new_with_refreshed_list();
+    let mut filesystems: Vec<Shortfall> = Vec::new();
+    for (name, dir) in dirs {
+        let Some((mount_point, available)) = filesystem_of(&disks, dir) else {
+            tracing::debug!("No filesystem found for {} {:?}; skipping disk space check", name, dir);
+            continue;
+        };
+        match filesystems.iter_mut().find(|fs| fs.mount_point == mount_point) {
+            Some(fs) => fs.dirs.push(name),
+            None => filesystems.push(Shortfall {
+                mount_point,
+                available,
+                dirs: vec![name],
+            }),
+        }
+    }
+
+    for fs in &filesystems {
+        tracing::debug!(
+            "Disk space on {:?} ({}): {} bytes free, {} needed",
+            fs.mount_point,
+            fs.dirs.join(", "),
+            fs.available,
+            required
+        );
+    }
+    let short: Vec<Shortfall> = filesystems.into_iter().filter(|fs| fs.available < required).collect();
+    if short.is_empty() {
+        return Ok(());
+    }
+    Err(InsufficientSpace {
+        artifact_size,
+        factor,
+        required,
+        short,
+    })
+}
+
+/// 경로가 있는 파일시스템의 마운트 지점과 여유 공간 (가장 긴 마운트 지점 접두사)
+///
+/// 아직 없는 디렉토리는 존재하는 가장 가까운 상위 디렉토리로 찾는다.
+fn filesystem_of(disks: &Disks, dir: &Path) -> Option<(PathBuf, u64)> {
+    let path = dir
+        .ancestors()
+        .find_map(|ancestor| ancestor.canonicalize().ok())
+        .or_else(|| std::env::current_dir().ok())?;
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| (disk.mount_point().to_path_buf(), disk.available_space()))
+}
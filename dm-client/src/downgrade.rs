@@ -0,0 +1,38 @@
+use anyhow::Result;
+
+use crate::error::{Classify, ClientError};
+
+/// 설치된 버전보다 낮은 버전으로의 업데이트 (서버에는 failure_reason "downgrade_refused"로 보고)
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Refusing to downgrade from {current} to {target}; deploy with allow_downgrade, \
+     or set DM_ALLOW_DOWNGRADE=1 (dm-client apply --allow-downgrade)"
+)]
+pub struct DowngradeRefused {
+    pub current: String,
+    pub target: String,
+}
+
+/// `target`이 설치된 `current`보다 낮은 semver면 거부 (`allowed`면 경고만)
+///
+/// 둘 중 하나라도 semver가 아니면 순서를 알 수 없으므로 비교하지 않는다.
+pub fn check(current: Option<&str>, target: &str, allowed: bool) -> Result<()> {
+    let Some(current) = current else {
+        return Ok(());
+    };
+    let (Ok(installed), Ok(requested)) = (semver::Version::parse(current), semver::Version::parse(target)) else {
+        return Ok(());
+    };
+    if requested >= installed {
+        return Ok(());
+    }
+    if allowed {
+        tracing::warn!("Downgrading from {} to {} (downgrade allowed)", current, target);
+        return Ok(());
+    }
+    Err(anyhow::Error::new(DowngradeRefused {
+        current: current.to_string(),
+        target: target.to_string(),
+    }))
+    .classify(ClientError::Unsupported)
+}
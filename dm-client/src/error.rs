@@ -0,0 +1,183 @@
+use std::process::ExitCode;
+
+use crate::api::{ArtifactUrlExpired, ServerBusy};
+use crate::config::ConfigError;
+use crate::fsfault::FsFault;
+use crate::retry::CircuitOpenError;
+
+/// 분류된 클라이언트 에러
+///
+/// anyhow 체인의 근원 에러로 만들거나(`bail!(ClientError::Checksum(..))`),
+/// 하위 에러에 `Classify::classify`로 붙인다. 바이너리의 종료 코드와
+/// 데몬의 재시도/백오프/중단 판단은 이 분류를 기준으로 한다.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ClientError {
+    /// 잘못된 설정이나 인자 (환경 변수 누락, 없는 파일 등)
+    #[error("{0}")]
+    Config(String),
+    /// 서버 연결 실패, 5xx, 다운로드 중단
+    #[error("{0}")]
+    Network(String),
+    /// API Key 거부 (401/403)
+    #[error("{0}")]
+    Auth(String),
+    /// 아티팩트 체크섬 불일치
+    #[error("{0}")]
+    Checksum(String),
+    /// 추출, 설치, 설치 스크립트, 재시작 실패
+    #[error("{0}")]
+    Install(String),
+    /// 롤백 자체가 실패 (서비스 상태를 알 수 없음)
+    #[error("{0}")]
+    RollbackFailed(String),
+    /// 설치 후 헬스 체크 실패
+    #[error("{0}")]
+    HealthCheck(String),
+    /// 다른 작업이 진행 중 (재부팅 대기 중인 이미지 업데이트 등)
+    #[error("{0}")]
+    Locked(String),
+    /// 이 장비나 설정에서 지원하지 않는 작업
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+/// 실패 후 데몬의 대응
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// 다음 주기에 그대로 다시 시도
+    Retry,
+    /// 성공할 때까지 체크인 주기를 늘림 (사람이 고쳐야 하는 문제)
+    Backoff,
+    /// 재시작 전까지 업데이트를 받지 않음 (degraded)
+    GiveUp,
+}
+
+/// 분류되지 않은 에러의 종료 코드
+pub const EXIT_OTHER: u8 = 1;
+
+impl ClientError {
+    /// `error_code=` 출력과 문서에 쓰는 이름
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Config(_) => "config",
+            Self::Network(_) => "network",
+            Self::Auth(_) => "auth",
+            Self::Checksum(_) => "checksum",
+            Self::Install(_) => "install",
+            Self::RollbackFailed(_) => "rollback_failed",
+            Self::HealthCheck(_) => "health_check",
+            Self::Locked(_) => "locked",
+            Self::Unsupported(_) => "unsupported",
+        }
+    }
+
+    /// 프로세스 종료 코드 (1은 미분류, 2는 clap 인자 오류)
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Config(_) => 3,
+            Self::Network(_) => 4,
+            Self::Auth(_) => 5,
+            Self::Checksum(_) => 6,
+            Self::Install(_) => 7,
+            Self::RollbackFailed(_) => 8,
+            Self::HealthCheck(_) => 9,
+            Self::Locked(_) => 10,
+            Self::Unsupported(_) => 11,
+        }
+    }
+
+    /// 데몬의 대응
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            Self::Network(_)
+            | Self::Checksum(_)
+            | Self::Install(_)
+            | Self::HealthCheck(_)
+            | Self::Locked(_) => Recovery::Retry,
+            Self::Config(_) | Self::Auth(_) | Self::Unsupported(_) => Recovery::Backoff,
+            Self::RollbackFailed(_) => Recovery::GiveUp,
+        }
+    }
+
+    /// HTTP 응답 상태로 분류 (`what`은 실패한 요청 설명)
+    pub fn from_status(what: &str, status: reqwest::StatusCode, body: &str) -> Self {
+        let message = if body.is_empty() {
+            format!("{} failed: {}", what, status)
+        } else {
+            format!("{} failed: {} - {}", what, status, body)
+        };
+        match status.as_u16() {
+            401 | 403 => Self::Auth(message),
+            408 | 429 | 500..=599 => Self::Network(message),
+            _ => Self::Config(message),
+        }
+    }
+
+    /// 에러 체인의 분류 (ClientError가 없으면 알려진 하위 에러로 추정)
+    pub fn of(err: &anyhow::Error) -> Option<Self> {
+        if let Some(classified) = err.downcast_ref::<ClientError>() {
+            return Some(classified.clone());
+        }
+        let message = err.to_string();
+        if err.downcast_ref::<ConfigError>().is_some() {
+            return Some(Self::Config(message));
+        }
+        if err.downcast_ref::<CircuitOpenError>().is_some()
+            || err.downcast_ref::<ArtifactUrlExpired>().is_some()
+            || err.downcast_ref::<ServerBusy>().is_some()
+            || err.chain().any(|e| e.is::<reqwest::Error>())
+        {
+            return Some(Self::Network(message));
+        }
+        FsFault::classify(err).map(|_| Self::Install(message))
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(err: reqwest::Error) -> Self {
+        match err.status() {
+            Some(status) => Self::from_status("Request", status, ""),
+            None => Self::Network(err.to_string()),
+        }
+    }
+}
+
+/// 하위 에러에 분류 붙이기 (메시지와 원인 체인은 그대로, 이미 분류된 에러는 유지)
+pub trait Classify<T> {
+    fn classify(self, kind: fn(String) -> ClientError) -> anyhow::Result<T>;
+}
+
+impl<T> Classify<T> for anyhow::Result<T> {
+    fn classify(self, kind: fn(String) -> ClientError) -> anyhow::Result<T> {
+        self.map_err(|e| {
+            if e.downcast_ref::<ClientError>().is_some() {
+                return e;
+            }
+            let message = e.to_string();
+            e.context(kind(message))
+        })
+    }
+}
+
+/// 명령 실패 출력 (사람이 읽는 메시지 + 마지막 줄 `error_code=`) 후 종료 코드 반환
+pub fn report(err: &anyhow::Error) -> ExitCode {
+    // 분류를 붙이며 같은 메시지가 체인에 두 번 들어가므로 연속 중복은 생략
+    let mut messages: Vec<String> = Vec::new();
+    for cause in err.chain() {
+        let message = cause.to_string();
+        if messages.last() != Some(&message) {
+            messages.push(message);
+        }
+    }
+    eprintln!("Error: {}", messages[0]);
+    if messages.len() > 1 {
+        eprintln!("\nCaused by:");
+        for message in &messages[1..] {
+            eprintln!("    {}", message);
+        }
+    }
+
+    let classified = ClientError::of(err);
+    eprintln!("error_code={}", classified.as_ref().map_or("other", ClientError::code));
+    ExitCode::from(classified.map_or(EXIT_OTHER, |c| c.exit_code()))
+}
@@ -0,0 +1,87 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// EIO (Linux/macOS 공통)
+#[cfg(unix)]
+const EIO: i32 = 5;
+
+/// 업데이트를 계속하면 디스크를 더 망가뜨릴 수 있는 파일시스템 장애
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsFault {
+    /// EROFS: 루트 FS가 읽기 전용으로 재마운트됨 (SD 카드 불량 등)
+    ReadOnly,
+    /// ENOSPC: 디스크 공간 부족
+    DiskFull,
+    /// EIO: 저수준 I/O 오류 (디스크 불량 의심)
+    Io,
+}
+
+impl FsFault {
+    /// 에러 체인에서 파일시스템 장애 분류
+    pub fn classify(err: &anyhow::Error) -> Option<Self> {
+        err.chain()
+            .filter_map(|e| e.downcast_ref::<io::Error>())
+            .find_map(Self::from_io)
+    }
+
+    fn from_io(err: &io::Error) -> Option<Self> {
+        match err.kind() {
+            io::ErrorKind::ReadOnlyFilesystem => return Some(Self::ReadOnly),
+            io::ErrorKind::StorageFull => return Some(Self::DiskFull),
+            _ => {}
+        }
+
+        #[cfg(unix)]
+        if err.raw_os_error() == Some(EIO) {
+            return Some(Self::Io);
+        }
+
+        None
+    }
+
+    /// 서버 보고용 코드
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ReadOnly => "fs_read_only",
+            Self::DiskFull => "disk_full",
+            Self::Io => "io_error",
+        }
+    }
+
+    /// 사람이 읽는 사유
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::ReadOnly => "filesystem read-only",
+            Self::DiskFull => "disk full",
+            Self::Io => "io error — possible disk failure",
+        }
+    }
+}
+
+/// 롤백 가능 여부 판단 (장애 시 롤백도 같은 디스크에 써야 하므로 생략)
+pub fn rollback_blocked(err: &anyhow::Error) -> bool {
+    match FsFault::classify(err) {
+        Some(fault) => {
+            tracing::error!(
+                "{}: skipping rollback, it would write to the same failing filesystem",
+                fault.message()
+            );
+            true
+        }
+        None => false,
+    }
+}
+
+/// 디렉토리에 쓰기 가능한지 확인 (시작 시 점검용)
+pub fn probe_writable(dir: &str) -> Option<FsFault> {
+    let probe = Path::new(dir).join(".dm-write-probe");
+    let result = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b"ok"))
+        .and_then(|_| fs::remove_file(&probe));
+
+    match result {
+        Ok(()) => None,
+        Err(e) => FsFault::from_io(&e),
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::error::ClientError;
+
+/// 프로브 기본 제한 시간 (command는 DM_COMMAND_TIMEOUT_SECS)
+pub const DEFAULT_PROBE_TIMEOUT_SECS: u64 = 10;
+
+/// 헬스 체크 프로브 (설정된 프로브를 순서대로 실행해 모두 통과해야 정상)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthProbe {
+    #[serde(flatten)]
+    pub kind: ProbeKind,
+    /// 프로브별 제한 시간 (없으면 종류별 기본값)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// 프로브 종류 (`{"type": "tcp", "host": "127.0.0.1", "port": 5000}`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProbeKind {
+    /// HTTP GET 응답 상태 (expected_status가 없으면 2xx)
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected_status: Option<u16>,
+    },
+    /// TCP 포트 연결
+    Tcp { host: String, port: u16 },
+    /// 프로세스 실행 여부 (이름 또는 pidfile 중 하나)
+    Process {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pidfile: Option<String>,
+    },
+    /// 하트비트 파일이 max_age_secs 안에 수정되었는지
+    FileAge { path: String, max_age_secs: u64 },
+    /// 셸 명령 종료 코드 (DM_HEALTH_CHECK_COMMAND와 같음)
+    Command { command: String },
+}
+
+impl HealthProbe {
+    pub fn new(kind: ProbeKind) -> Self {
+        Self { kind, timeout_secs: None }
+    }
+
+    /// 설정 검증 (잘못된 프로브는 설치 후가 아니라 설정을 읽을 때 거부)
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.kind {
+            ProbeKind::Http { url, .. } => {
+                let parsed = reqwest::Url::parse(url).map_err(|e| format!("invalid http probe URL {}: {}", url, e))?;
+                if parsed.scheme() != "http" || parsed.host_str().is_none() {
+                    return Err(format!(
+                        "http probe supports plain http://host URLs only (use a command probe for https): {}",
+                        url
+                    ));
+                }
+            }
+            ProbeKind::Tcp { host, port } => {
+                if host.is_empty() || *port == 0 {
+                    return Err(format!("tcp probe needs a host and a non-zero port: {}:{}", host, port));
+                }
+            }
+            ProbeKind::Process { name, pidfile } => {
+                if name.is_some() == pidfile.is_some() {
+                    return Err("process probe needs exactly one of name or pidfile".to_string());
+                }
+            }
+            ProbeKind::FileAge { path, .. } => {
+                if path.is_empty() {
+                    return Err("file_age probe needs a path".to_string());
+                }
+            }
+            ProbeKind::Command { command } => {
+                if command.trim().is_empty() {
+                    return Err("command probe needs a command".to_string());
+                }
+            }
+        }
+        if self.timeout_secs == Some(0) {
+            return Err(format!("{}: timeout_secs must be positive", self));
+        }
+        Ok(())
+    }
+
+    /// 제한 시간 (command는 호출자가 DM_COMMAND_TIMEOUT_SECS를 기본값으로 사용)
+    pub fn timeout(&self, default: Duration) -> Duration {
+        self.timeout_secs.map_or(default, Duration::from_secs)
+    }
+}
+
+impl fmt::Display for HealthProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ProbeKind::Http { url, .. } => write!(f, "http probe {}", url),
+            ProbeKind::Tcp { host, port } => write!(f, "tcp probe {}:{}", host, port),
+            ProbeKind::Process { name: Some(name), .. } => write!(f, "process probe {}", name),
+            ProbeKind::Process { pidfile, .. } => {
+                write!(f, "process probe pidfile {}", pidfile.as_deref().unwrap_or(""))
+            }
+            ProbeKind::FileAge { path, .. } => write!(f, "file_age probe {}", path),
+            ProbeKind::Command { command } => write!(f, "command probe `{}`", command),
+        }
+    }
+}
+
+/// JSON 프로브 목록 파싱 및 검증 (DM_HEALTH_CHECKS)
+pub fn parse(json: &str) -> Result<Vec<HealthProbe>> {
+    let probes: Vec<HealthProbe> = serde_json::from_str(json)
+        .map_err(|e| ClientError::Config(format!("Invalid DM_HEALTH_CHECKS: {}", e)))?;
+    for probe in &probes {
+        probe
+            .validate()
+            .map_err(|e| ClientError::Config(format!("Invalid DM_HEALTH_CHECKS: {}", e)))?;
+    }
+    Ok(probes)
+}
+
+/// 기존 단일 필드(health_check_url, health_check_timeout)를 http 프로브로 변환
+pub fn legacy_http(url: &str, timeout_secs: Option<i64>) -> HealthProbe {
+    HealthProbe {
+        kind: ProbeKind::Http {
+            url: url.to_string(),
+            expected_status: None,
+        },
+        timeout_secs: timeout_secs.filter(|t| *t > 0).map(|t| t as u64),
+    }
+}
+
+/// command 외 프로브 실행 (실패 사유 반환, command는 Updater가 실행)
+pub fn check(kind: &ProbeKind, timeout: Duration) -> Result<(), String> {
+    match kind {
+        ProbeKind::Http { url, expected_status } => check_http(url, *expected_status, timeout),
+        ProbeKind::Tcp { host, port } => connect(host, *port, timeout).map(drop),
+        ProbeKind::Process { name: Some(name), .. } => check_process_name(name),
+        ProbeKind::Process { pidfile: Some(pidfile), .. } => check_pidfile(Path::new(pidfile)),
+        ProbeKind::Process { .. } => Err("process probe needs a name or pidfile".to_string()),
+        ProbeKind::FileAge { path, max_age_secs } => check_file_age(Path::new(path), *max_age_secs),
+        ProbeKind::Command { .. } => Err("command probes are run by the updater".to_string()),
+    }
+}
+
+/// 주소를 모두 시도해 처음 연결되는 스트림 반환
+fn connect(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, String> {
+    let addrs: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("cannot resolve {}: {}", host, e))?
+        .collect();
+    let mut last_error = format!("no address for {}", host);
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = format!("connect {} failed: {}", addr, e),
+        }
+    }
+    Err(last_error)
+}
+
+/// HTTP/1.0 GET 후 상태 줄만 확인 (TLS 없이 로컬 서비스 확인용)
+fn check_http(url: &str, expected_status: Option<u16>, timeout: Duration) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("invalid URL: {}", e))?;
+    let host = parsed.host_str().ok_or("URL has no host")?;
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let mut target = parsed.path().to_string();
+    if let Some(query) = parsed.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+
+    let mut stream = connect(host, port, timeout)?;
+    stream.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    // 요청을 한 번에 보내 조각난 요청을 읽고 바로 닫는 서버에서도 응답을 받도록
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: dm-client\r\nConnection: close\r\n\r\n",
+        target, host
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| format!("request failed: {}", e))?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut status_line)
+        .map_err(|e| format!("no response: {}", e))?;
+    let status: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| format!("invalid response: {:?}", status_line.trim()))?;
+
+    let healthy = match expected_status {
+        Some(expected) => status == expected,
+        None => (200..300).contains(&status),
+    };
+    if !healthy {
+        let expected = expected_status.map_or("2xx".to_string(), |s| s.to_string());
+        return Err(format!("status {} (expected {})", status, expected));
+    }
+    Ok(())
+}
+
+/// /proc의 프로세스 이름(comm, 15자로 잘림)이나 실행 파일 이름이 일치하는 프로세스가 있는지
+fn check_process_name(name: &str) -> Result<(), String> {
+    let entries = fs::read_dir("/proc").map_err(|e| format!("cannot list processes via /proc: {}", e))?;
+    let comm_name: String = name.chars().take(15).collect();
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        let dir = entry.path();
+        if fs::read_to_string(dir.join("comm")).is_ok_and(|comm| comm.trim_end() == comm_name) {
+            return Ok(());
+        }
+        let argv0 = fs::read(dir.join("cmdline"))
+            .ok()
+            .and_then(|cmdline| cmdline.split(|b| *b == 0).next().map(|a| String::from_utf8_lossy(a).into_owned()));
+        if argv0.is_some_and(|a| Path::new(&a).file_name().is_some_and(|f| f == name)) {
+            return Ok(());
+        }
+    }
+    Err("no running process with this name".to_string())
+}
+
+/// pidfile의 프로세스가 살아 있는지
+fn check_pidfile(pidfile: &Path) -> Result<(), String> {
+    let content = fs::read_to_string(pidfile).map_err(|e| format!("cannot read pidfile: {}", e))?;
+    let pid: u32 = content
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid pid in pidfile: {:?}", content.trim()))?;
+    if !Path::new("/proc").join(pid.to_string()).exists() {
+        return Err(format!("process {} is not running", pid));
+    }
+    Ok(())
+}
+
+/// 파일이 max_age_secs 안에 수정되었는지 (하트비트 파일)
+fn check_file_age(path: &Path, max_age_secs: u64) -> Result<(), String> {
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("cannot stat file: {}", e))?;
+    let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+    if age.as_secs() > max_age_secs {
+        return Err(format!("last modified {}s ago (max {}s)", age.as_secs(), max_age_secs));
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 캐시 파일 이름 (control_dir 기준)
+pub const CACHE_FILE: &str = "http-cache.json";
+
+/// 최대 항목 수 (넘으면 가장 오래 쓰지 않은 항목부터 제거)
+const MAX_ENTRIES: usize = 32;
+
+/// 항목당 최대 본문 크기 (이보다 큰 응답은 캐시하지 않음)
+const MAX_BODY_LEN: usize = 256 * 1024;
+
+/// 조건부 GET용 디스크 캐시 (URL별 ETag/Last-Modified와 본문)
+///
+/// 캐시 파일은 서버 주소와 API Key의 해시로 묶여 있어, 둘 중 하나가 바뀌면 이전 항목을 버린다.
+pub struct HttpCache {
+    path: PathBuf,
+    file: Mutex<CacheFile>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    /// sha256(server_url + API Key)
+    owner: String,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+/// 캐시된 응답
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    pub body: String,
+    pub used_at: DateTime<Utc>,
+}
+
+impl HttpCache {
+    /// 캐시 파일 열기 (없거나 깨졌거나 다른 서버/API Key의 캐시면 빈 캐시)
+    pub fn open(path: &Path, server_url: &str, api_key: &str) -> Self {
+        let owner = owner_id(server_url, api_key);
+        let file = fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<CacheFile>(&data).ok())
+            .filter(|file| file.owner == owner)
+            .unwrap_or_else(|| CacheFile {
+                owner,
+                entries: BTreeMap::new(),
+            });
+        Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        }
+    }
+
+    /// URL의 캐시 항목
+    pub fn get(&self, url: &str) -> Option<CacheEntry> {
+        self.file.lock().unwrap().entries.get(url).cloned()
+    }
+
+    /// 304 응답으로 재사용한 항목의 사용 시각 갱신
+    pub fn touch(&self, url: &str) {
+        let mut file = self.file.lock().unwrap();
+        if let Some(entry) = file.entries.get_mut(url) {
+            entry.used_at = Utc::now();
+        }
+        self.persist(&file);
+    }
+
+    /// 검증자가 있는 응답 저장 (없거나 본문이 너무 크면 기존 항목 제거)
+    pub fn store(&self, url: &str, etag: Option<String>, last_modified: Option<String>, body: &str) {
+        let mut file = self.file.lock().unwrap();
+        if (etag.is_none() && last_modified.is_none()) || body.len() > MAX_BODY_LEN {
+            if file.entries.remove(url).is_some() {
+                self.persist(&file);
+            }
+            return;
+        }
+
+        file.entries.insert(
+            url.to_string(),
+            CacheEntry {
+                etag,
+                last_modified,
+                body: body.to_string(),
+                used_at: Utc::now(),
+            },
+        );
+        while file.entries.len() > MAX_ENTRIES {
+            let Some(oldest) = file
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used_at)
+                .map(|(url, _)| url.clone())
+            else {
+                break;
+            };
+            file.entries.remove(&oldest);
+        }
+        self.persist(&file);
+    }
+
+    /// 임시 파일에 쓰고 교체 (실패해도 요청은 계속 진행)
+    fn persist(&self, file: &CacheFile) {
+        if let Err(e) = write_file(&self.path, file) {
+            tracing::debug!("Failed to write HTTP cache {:?}: {}", self.path, e);
+        }
+    }
+}
+
+fn write_file(path: &Path, file: &CacheFile) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec(file)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn owner_id(server_url: &str, api_key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(server_url.trim_end_matches('/').as_bytes());
+    hasher.update(b"\n");
+    hasher.update(api_key.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
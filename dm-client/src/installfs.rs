@@ -0,0 +1,208 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use tempfile::TempDir;
+
+use crate::error::ClientError;
+
+/// 작업 디렉토리 이름 접두사 (`.dm-work-<대상 이름>-XXXXXX`)
+const WORK_DIR_PREFIX: &str = ".dm-work-";
+
+/// 추출한 트리를 설치 대상으로 옮기는 방법
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallStrategy {
+    /// 작업 디렉토리와 대상이 같은 파일시스템: 항목별 rename (복사 없음)
+    Rename,
+    /// 다른 파일시스템(또는 rename이 EXDEV로 실패): 전체 복사 (느리고 설치 중 공간을 두 배로 씀)
+    Copy,
+}
+
+impl InstallStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rename => "rename",
+            Self::Copy => "copy",
+        }
+    }
+}
+
+/// 아티팩트를 추출할 작업 디렉토리 (DM_WORK_DIR > 설치 대상의 부모 디렉토리 > 시스템 임시 디렉토리)
+///
+/// 대상 옆에 두어야 rename으로 설치할 수 있다. 시스템 임시 디렉토리는 tmpfs인 경우가 많아
+/// 부모 디렉토리에 만들 수 없을 때만 쓴다. 이전 실행이 남긴 같은 대상의 작업 디렉토리는 먼저 지운다.
+pub fn work_dir(configured: Option<&str>, target: &Path) -> io::Result<TempDir> {
+    let target = resolve(target);
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let prefix = format!("{}{}-", WORK_DIR_PREFIX, name);
+
+    let parent = match configured {
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            PathBuf::from(dir)
+        }
+        None => match target.parent() {
+            Some(parent) if parent.as_os_str().is_empty() => PathBuf::from("."),
+            Some(parent) => parent.to_path_buf(),
+            None => return TempDir::new(),
+        },
+    };
+    clear_stale(&parent, &prefix);
+    match tempfile::Builder::new().prefix(&prefix).tempdir_in(&parent) {
+        Ok(dir) => Ok(dir),
+        Err(e) if configured.is_none() => {
+            tracing::warn!(
+                "Cannot create work directory next to {:?} ({}); extracting to the system temp directory",
+                target,
+                e
+            );
+            TempDir::new()
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 설치 전 점검: 작업 디렉토리와 대상의 장치 ID가 같으면 rename, 다르거나 알 수 없으면 복사
+pub fn preflight(work: &Path, target: &Path) -> InstallStrategy {
+    let target = resolve(target);
+    let strategy = match same_device(work, &target) {
+        Some(true) => InstallStrategy::Rename,
+        _ => InstallStrategy::Copy,
+    };
+    match strategy {
+        InstallStrategy::Rename => tracing::info!(
+            "Install strategy: rename ({:?} and {:?} are on the same filesystem)",
+            work,
+            target
+        ),
+        InstallStrategy::Copy => tracing::warn!(
+            "Install strategy: copy ({:?} and {:?} are on different filesystems; set DM_WORK_DIR on the target filesystem for rename installs)",
+            work,
+            target
+        ),
+    }
+    strategy
+}
+
+/// rename이 파일시스템 경계 때문에 실패했는지 (EXDEV)
+pub fn is_cross_device(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::CrossesDevices
+}
+
+/// 두 경로가 같은 장치에 있는지 (아직 없는 경로는 존재하는 가장 가까운 상위 디렉토리 기준)
+#[cfg(unix)]
+fn same_device(a: &Path, b: &Path) -> Option<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dev = |path: &Path| path.ancestors().find_map(|p| fs::metadata(p).ok()).map(|m| m.dev());
+    Some(dev(a)? == dev(b)?)
+}
+
+#[cfg(not(unix))]
+fn same_device(_a: &Path, _b: &Path) -> Option<bool> {
+    None
+}
+
+/// 보존 경로 검증 (DM_PRESERVE_PATHS 또는 서버 ClientConfig.preserve_paths)
+///
+/// 서비스 디렉토리 기준 상대 경로만 허용한다 (절대 경로, `..`는 거부). 다른 보존 경로 안에 있는
+/// 경로는 상위 경로와 함께 옮겨지므로 뺀다.
+pub fn preserve_paths(paths: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut preserved: Vec<PathBuf> = Vec::new();
+    for raw in paths {
+        let components = Path::new(raw).components();
+        let inside = components
+            .clone()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        let path: PathBuf = components.filter(|c| matches!(c, Component::Normal(_))).collect();
+        if !inside || path.as_os_str().is_empty() {
+            anyhow::bail!(ClientError::Config(format!(
+                "Preserved path must be a relative path inside the service directory: {:?}",
+                raw
+            )));
+        }
+        preserved.push(path);
+    }
+    preserved.sort();
+    preserved.dedup();
+    let nested: Vec<PathBuf> = preserved
+        .iter()
+        .filter(|path| preserved.iter().any(|other| other != *path && path.starts_with(other)))
+        .cloned()
+        .collect();
+    preserved.retain(|path| !nested.contains(path));
+    Ok(preserved)
+}
+
+/// 보존 경로를 `from`에서 `to`의 같은 상대 경로로 옮김 (`to`에 이미 있으면 지우고). 옮긴 경로 반환
+///
+/// 없는 경로는 건너뛴다. 중간에 실패하면 이미 옮긴 경로를 되돌린 뒤 에러를 반환한다.
+pub fn move_preserved(from: &Path, to: &Path, preserve: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut moved = Vec::new();
+    for path in preserve {
+        let source = from.join(path);
+        if fs::symlink_metadata(&source).is_err() {
+            continue;
+        }
+        if let Err(e) = move_entry(&source, &to.join(path)) {
+            return_preserved(to, from, &moved);
+            return Err(e).with_context(|| format!("Failed to preserve {:?}", source));
+        }
+        moved.push(path.clone());
+    }
+    Ok(moved)
+}
+
+/// `move_preserved`로 옮긴 경로를 되돌림 (실패는 로그만 남김)
+pub fn return_preserved(from: &Path, to: &Path, moved: &[PathBuf]) {
+    for path in moved {
+        if let Err(e) = move_entry(&from.join(path), &to.join(path)) {
+            tracing::error!("Failed to put preserved {:?} back from {:?}: {}", to.join(path), from.join(path), e);
+        }
+    }
+}
+
+/// 항목 하나를 rename (대상이 있으면 먼저 지우고, 상위 디렉토리는 만듦)
+fn move_entry(source: &Path, dest: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(dest) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(dest)?,
+        Ok(_) => fs::remove_file(dest)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(source, dest)
+}
+
+/// 심볼릭 링크를 따라간 실제 설치 대상 (없으면 그대로)
+fn resolve(target: &Path) -> PathBuf {
+    fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf())
+}
+
+/// 중단된 이전 설치가 남긴 작업 디렉토리 삭제
+///
+/// 접두사 뒤가 tempfile의 임의 문자열(영숫자)인 것만 지운다 (`app`의 접두사가 `app-2`의 작업 디렉토리와 겹치지 않도록).
+pub(crate) fn clear_stale(parent: &Path, prefix: &str) {
+    let Ok(entries) = fs::read_dir(parent) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let ours = name
+            .strip_prefix(prefix)
+            .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_alphanumeric()));
+        if ours {
+            tracing::info!("Removing leftover work directory {:?}", entry.path());
+            if let Err(e) = fs::remove_dir_all(entry.path()) {
+                tracing::warn!("Failed to remove {:?}: {}", entry.path(), e);
+            }
+        }
+    }
+}
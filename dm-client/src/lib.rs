@@ -0,0 +1,39 @@
+//! Sam DM Client 라이브러리 (바이너리와 통합 테스트가 함께 사용)
+
+// Config::effective의 json! 매크로가 기본 재귀 한도를 넘음
+#![recursion_limit = "256"]
+
+pub mod abslot;
+pub mod api;
+pub mod backup;
+pub mod chunked;
+pub mod config;
+pub mod confirm;
+pub mod control;
+pub mod deadline;
+pub mod diskspace;
+pub mod downgrade;
+pub mod error;
+pub mod fsfault;
+pub mod health;
+pub mod httpcache;
+pub mod installfs;
+pub mod lock;
+pub mod package;
+pub mod polling;
+pub mod product;
+pub mod progress;
+pub mod retry;
+pub mod scripts;
+pub mod service;
+pub mod signing;
+pub mod simulate;
+pub mod staging;
+pub mod supersede;
+pub mod state;
+pub mod static_mode;
+pub mod survey;
+pub mod tls;
+pub mod updater;
+pub mod usb;
+pub mod usbwatch;
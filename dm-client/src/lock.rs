@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::ClientError;
+
+/// 잠금 파일 이름 접두사 (서비스 디렉토리 옆 `.dm-lock-<서비스 디렉토리 이름>`)
+const LOCK_FILE_PREFIX: &str = ".dm-lock-";
+
+/// 서비스 디렉토리를 바꾸는 작업(업데이트, USB 적용, 롤백, 스테이징 활성화)의 프로세스 간 잠금
+///
+/// 잠금 파일에 flock을 걸고 pid를 적는다. 잠금은 열린 파일에 걸리므로 프로세스가 죽으면 커널이 풀고,
+/// 남은 pid는 다음 잠금이 덮어쓴다. 파일은 지우지 않는다 (지우면 이미 열어 둔 옛 파일과 새 파일에
+/// 서로 다른 프로세스가 동시에 잠금을 걸 수 있음). drop하면 pid를 지우고 잠금을 푼다.
+#[derive(Debug)]
+pub struct UpdateLock {
+    file: File,
+}
+
+impl UpdateLock {
+    /// 잠금 획득 (다른 프로세스가 잡고 있으면 기다리지 않고 `ClientError::Locked`)
+    ///
+    /// 같은 프로세스 안에서도 두 번 잡을 수 없으므로 작업의 가장 바깥에서 한 번만 잡는다.
+    pub fn acquire(service_dir: &str) -> Result<Self> {
+        let path = lock_path(Path::new(service_dir));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open update lock {:?}", path))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let message = match read_pid(&mut file) {
+                    Some(pid) => format!("update already in progress (pid {})", pid),
+                    None => "update already in progress".to_string(),
+                };
+                anyhow::bail!(ClientError::Locked(format!("{}; lock {:?}", message, path)));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {:?}", path));
+            }
+        }
+
+        // 잠금 없이 pid가 남아 있으면 정리되지 못하고 끝난 프로세스의 것
+        if let Some(pid) = read_pid(&mut file) {
+            tracing::warn!("Reclaiming update lock {:?} left by pid {} (no longer running)", path, pid);
+        }
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        writeln!(file, "{}", std::process::id())?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for UpdateLock {
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+    }
+}
+
+/// 잠금 파일 경로 (서비스 디렉토리는 설치 때 통째로 바뀌므로 그 옆에 둔다)
+pub fn lock_path(service_dir: &Path) -> PathBuf {
+    let name = service_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // 같은 서비스 디렉토리를 다른 경로 표기(./service, /opt/app/service)로 설정해도 같은 파일
+    let parent = match service_dir.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+        Some(parent) => parent,
+        None => service_dir,
+    };
+    let parent = fs::canonicalize(parent).unwrap_or_else(|_| parent.to_path_buf());
+    parent.join(format!("{}{}", LOCK_FILE_PREFIX, name))
+}
+
+/// 잠금 파일에 적힌 pid
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut contents = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
@@ -1,20 +1,37 @@
-mod api;
-mod config;
-mod polling;
-mod updater;
-mod usb;
-
+use anyhow::Context;
 use clap::{Parser, Subcommand};
+use std::process::ExitCode;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use dm_client::{
+    api, backup, config, confirm, control, error, fsfault, installfs, lock, package, polling, progress, service, simulate, staging, state, tls,
+    updater, usb, usbwatch,
+};
+
+use api::{BuildInfo, DmApiClient, UpdateResultRequest};
 use config::Config;
+use error::{ClientError, Classify};
+use fsfault::FsFault;
+use installfs::InstallStrategy;
+use lock::UpdateLock;
 use polling::PollingDaemon;
+use progress::OutputMode;
+use state::{LocalState, StateIntegrity};
+use updater::Updater;
 
 #[derive(Parser)]
 #[command(name = "dm-client", version, about = "🦊 Sam DM Client - 원격 서비스 업데이트")]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// 최종 결과와 에러만 출력 (apply, package)
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// 디버그 로그 출력 (apply, package)
+    #[arg(long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
 }
 
 #[derive(Subcommand)]
@@ -39,18 +56,285 @@ enum Commands {
         /// SHA256 체크섬
         #[arg(short, long)]
         checksum: Option<String>,
+
+        /// 확인 없이 적용 (터미널이 아닌 환경에서는 필수)
+        #[arg(short, long)]
+        yes: bool,
+
+        /// 백업 없이 적용 (DM_BACKUP_REQUIRED=false와 동일, 이 설치는 롤백할 수 없음)
+        #[arg(long)]
+        no_backup: bool,
+
+        /// 설치된 버전보다 낮은 버전도 적용 (DM_ALLOW_DOWNGRADE=1과 동일)
+        #[arg(long)]
+        allow_downgrade: bool,
+    },
+
+    /// USB 마운트 루트를 감시하다 새 번들(manifest.json)이 나타나면 적용 (버전마다 한 번, 결과는 USB의 apply-result.json)
+    UsbWatch {
+        /// 감시할 마운트 루트 (기본값: DM_USB_WATCH_DIR)
+        #[arg(long)]
+        path: Option<String>,
+
+        /// 검사 주기 (예: 5s, 1m. 기본값: DM_USB_WATCH_INTERVAL)
+        #[arg(long, value_parser = humantime::parse_duration)]
+        interval: Option<std::time::Duration>,
+
+        /// 한 번만 검사하고 종료 (적용에 실패한 번들이 있으면 실패로 종료)
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// 서비스 디렉토리를 USB 번들(update.tar.gz + manifest.json)로 패키징
+    Package {
+        /// 패키징할 디렉토리
+        #[arg(short, long)]
+        dir: String,
+
+        /// 번들 버전 (semver)
+        #[arg(short, long)]
+        version: String,
+
+        /// 출력 디렉토리
+        #[arg(short, long, default_value = ".")]
+        out: String,
+
+        /// 릴리즈 노트
+        #[arg(long)]
+        release_notes: Option<String>,
+
+        /// 대상 제품 (아티팩트에 .dm-product로 기록, 다른 제품을 기대하는 장비는 설치 거부)
+        #[arg(long)]
+        product: Option<String>,
+
+        /// 빌드 git 커밋
+        #[arg(long)]
+        git_commit: Option<String>,
+
+        /// 빌드 시각 (RFC3339, 기본값: --source-date-epoch가 있으면 그 시각, 없으면 현재 시각)
+        #[arg(long)]
+        build_time: Option<String>,
+
+        /// 추가 메타데이터 (KEY=VALUE, 반복 가능)
+        #[arg(long = "metadata", value_name = "KEY=VALUE")]
+        metadata: Vec<String>,
+
+        /// 아티팩트 압축 방식 (gzip: update.tar.gz, zstd: update.tar.zst)
+        #[arg(long, value_enum, default_value_t = package::Codec::Gzip)]
+        compression: package::Codec,
+
+        /// 압축 단계 (gzip 0-9, 기본 6 / zstd 1-22, 기본 3)
+        #[arg(long)]
+        level: Option<i32>,
+
+        /// 아카이브 항목 mtime 상한 (유닉스 초, 기본값: SOURCE_DATE_EPOCH 환경 변수 또는 1980-01-01)
+        #[arg(long)]
+        source_date_epoch: Option<u64>,
+    },
+
+    /// 게이트웨이 모드: 여러 장비를 대신하여 한 번에 체크인
+    BatchCheckin {
+        /// 장비 목록 JSON 파일 ([{"api_key", "current_version", "status"}, ...])
+        #[arg(long)]
+        batch_config: String,
+    },
+
+    /// 부하 테스트: 가상 클라이언트 N개로 서버에 체크인
+    Simulate {
+        /// 가상 클라이언트 수
+        #[arg(long, default_value_t = 100)]
+        clients: usize,
+
+        /// 체크인 주기 (초)
+        #[arg(long, default_value_t = 30)]
+        interval: u64,
+
+        /// 실행 시간 (초)
+        #[arg(long, default_value_t = 300)]
+        duration: u64,
+
+        /// 서버 URL (기본값: DM_SERVER_URL)
+        #[arg(long)]
+        server: Option<String>,
+
+        /// 미리 발급된 API Key 파일 (한 줄에 하나, 없으면 가상 클라이언트 등록)
+        #[arg(long)]
+        keys_file: Option<String>,
+
+        /// 등록한 API Key 저장 경로 (다음 실행에 --keys-file로 재사용)
+        #[arg(long)]
+        save_keys: Option<String>,
+
+        /// update 명령 수락 확률 (0.0 ~ 1.0)
+        #[arg(long, default_value_t = 0.1)]
+        accept_rate: f64,
+
+        /// 가상 클라이언트의 초기 버전
+        #[arg(long)]
+        initial_version: Option<String>,
+
+        /// 연결 사용 방식: shared(풀 공유), per-client(장비처럼 클라이언트별 연결), fresh(요청마다 새 연결)
+        #[arg(long, value_enum, default_value = "shared")]
+        connections: simulate::ConnectionMode,
+    },
+
+    /// 스테이징된 업데이트 활성화 (교체, 재시작, 헬스 체크)
+    Activate {
+        /// 활성화 대신 스테이징 취소 (스테이징 디렉토리 삭제)
+        #[arg(long)]
+        cancel: bool,
+
+        /// 백업 없이 활성화 (DM_BACKUP_REQUIRED=false와 동일, 이 설치는 롤백할 수 없음)
+        #[arg(long, conflicts_with = "cancel")]
+        no_backup: bool,
+    },
+
+    /// 로컬 백업 관리
+    Backups {
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
+
+    /// 백업으로 롤백 (복원, 서비스 재시작, 헬스 체크. 인자가 없으면 가장 최근 백업)
+    Rollback {
+        /// 가장 최근 백업으로 롤백 (기본 동작, 이전 버전 호환용)
+        #[arg(long, conflicts_with = "backup")]
+        latest: bool,
+
+        /// 롤백할 백업 이름 (`--list` 참고)
+        #[arg(long)]
+        backup: Option<String>,
+
+        /// 롤백하지 않고 백업 목록만 출력 (`backups list`와 같음)
+        #[arg(long, conflicts_with_all = ["latest", "backup"])]
+        list: bool,
+
+        /// 확인 없이 롤백 (터미널이 아닌 환경에서는 필수)
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// 실행 중인 데몬의 업데이트 일시 정지 (체크인은 계속, "paused"로 보고)
+    Pause {
+        /// 자동 재개까지의 시간 (예: 30m, 1h, 2h30m). 없으면 resume까지 유지
+        #[arg(long = "for", value_parser = humantime::parse_duration)]
+        duration: Option<std::time::Duration>,
+
+        /// 정지 사유 (로그에 기록)
+        #[arg(long)]
+        reason: Option<String>,
+    },
+
+    /// 일시 정지 해제
+    Resume,
+
+    /// 실행 중인 데몬에 즉시 체크인 요청
+    TriggerCheckin,
+
+    /// 설치 버전 수동 기록 (복구용, .dm-version과 설치 상태를 함께 갱신해 다시 서명)
+    SetVersion {
+        /// 기록할 버전
+        #[arg(long)]
+        version: String,
+
+        /// 수동 기록임을 확인 (없으면 거부)
+        #[arg(long = "i-know-what-im-doing")]
+        confirmed: bool,
     },
 
     /// 현재 버전 확인
-    Status,
+    Status {
+        /// JSON 형식으로 출력
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// 서버에 등록된 이 장비의 정보 (역할, 고정 버전, 대기 중인 배포)
+    Info {
+        /// JSON 형식으로 출력
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// 데몬 systemd 유닛 설치 (/etc/systemd/system/dm-client.service, systemd가 아니면 출력만)
+    InstallService {
+        /// 데몬 실행 사용자 (기본: root)
+        #[arg(long)]
+        user: Option<String>,
+
+        /// 데몬 환경 파일 (EnvironmentFile, 기본: /etc/sam-dm/config.env)
+        #[arg(long)]
+        config: Option<String>,
+
+        /// 설치 후 systemctl daemon-reload && systemctl enable --now
+        #[arg(long)]
+        enable: bool,
+
+        /// 아무것도 쓰지 않고 유닛 파일만 출력
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// 데몬 systemd 유닛 중지 및 삭제
+    UninstallService {
+        /// 아무것도 바꾸지 않고 할 일만 출력
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupCommands {
+    /// 백업 목록 (최신순)
+    List {
+        /// JSON 형식으로 출력
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => error::report(&e),
+    }
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    let command = cli.command.unwrap_or(Commands::Daemon);
+
+    // 출력 모드 결정 (대화형 명령만 진행률/quiet/verbose 적용)
+    let mode = match &command {
+        Commands::Apply { .. }
+        | Commands::Package { .. }
+        | Commands::Activate { .. }
+        | Commands::Rollback { .. } => {
+            if cli.quiet {
+                OutputMode::Quiet
+            } else if cli.verbose {
+                OutputMode::Verbose
+            } else {
+                OutputMode::Normal
+            }
+        }
+        _ => OutputMode::Daemon,
+    };
+    progress::set_mode(mode);
+
     // 로깅 초기화
+    let default_filter = match (&command, mode) {
+        // 가상 클라이언트 수천 개의 로그는 생략
+        (Commands::Simulate { .. }, _) => "warn,dm_client::simulate=info",
+        (_, OutputMode::Daemon) => "info,dm_client=debug",
+        (_, OutputMode::Quiet) => "error",
+        (_, OutputMode::Normal) => "info",
+        (_, OutputMode::Verbose) => "debug",
+    };
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info,dm_client=debug".into()),
+            std::env::var("RUST_LOG").unwrap_or_else(|_| default_filter.into()),
         ))
         .with(tracing_subscriber::fmt::layer())
         .init();
@@ -58,50 +342,658 @@ async fn main() -> anyhow::Result<()> {
     // .env 파일 로드
     dotenvy::dotenv().ok();
 
-    let cli = Cli::parse();
-
-    match cli.command.unwrap_or(Commands::Daemon) {
+    match command {
         Commands::Daemon => {
             // 설정 로드 (서버 모드는 전체 설정 필요)
-            let config = Config::from_env().map_err(|e| {
-                anyhow::anyhow!(
-                    "Missing environment variable: {}. Required: DM_SERVER_URL, DM_API_KEY",
-                    e
-                )
-            })?;
+            let config = Config::from_env()?;
+            // 잘못된 헬스 체크 설정은 첫 업데이트 후가 아니라 시작 시 거부
+            config.health_probes()?;
+            tls::check_startup(&config)?;
+
+            if let Some(root) = config.usb_watch_dir.clone() {
+                let interval = std::time::Duration::from_secs(config.usb_watch_interval_secs);
+                tokio::spawn(usbwatch::UsbWatcher::new(config.clone(), root).run(interval));
+            }
 
             let daemon = PollingDaemon::new(config);
             daemon.run().await
         }
 
-        Commands::Apply { file, dir, version, checksum } => {
+        Commands::Apply { file, dir, version, checksum, yes, no_backup, allow_downgrade } => {
             // Apply 모드는 서버 설정 없이도 동작
-            let config = Config::from_env_optional();
+            let mut config = Config::from_env_optional()?;
+            config.backup_required &= !no_backup;
+            config.allow_downgrade |= allow_downgrade;
+            let confirm = move |plan: &usb::ApplyPlan| confirm::confirm("apply", &plan.to_string(), yes);
 
-            if let Some(dir_path) = dir {
-                usb::apply_from_directory(&config, &dir_path)
+            let result = if let Some(dir_path) = dir {
+                usb::apply_from_directory(&config, &dir_path, confirm).await
             } else if let Some(file_path) = file {
                 usb::apply_from_file(
                     &config,
                     &file_path,
                     version.as_deref(),
                     checksum.as_deref(),
+                    confirm,
                 )
+                .await
+            } else {
+                anyhow::bail!(ClientError::Config(
+                    "--file 또는 --dir 중 하나를 지정해주세요.\n\n예시:\n  dm-client apply --dir /mnt/usb\n  dm-client apply --file /mnt/usb/update.tar.gz --version 1.0.0".to_string()
+                ))
+            };
+
+            // 파일시스템 장애는 분류된 사유로 표시
+            result.map_err(|e| match FsFault::classify(&e) {
+                Some(fault) => e.context(fault.message()),
+                None => e,
+            })?;
+
+            let installed = LocalState::load(&config.service_dir);
+            println!(
+                "🦊 업데이트 완료: {}",
+                installed.version.as_deref().unwrap_or("unknown")
+            );
+            Ok(())
+        }
+
+        Commands::UsbWatch { path, interval, once } => {
+            // apply와 같이 서버 설정 없이도 동작
+            let config = Config::from_env_optional()?;
+            let Some(root) = path.or_else(|| config.usb_watch_dir.clone()) else {
+                anyhow::bail!(ClientError::Config(
+                    "--path 또는 DM_USB_WATCH_DIR을 지정해주세요.\n\n예시:\n  dm-client usb-watch --path /media".to_string()
+                ));
+            };
+            let interval = interval.unwrap_or(std::time::Duration::from_secs(config.usb_watch_interval_secs));
+            let mut watcher = usbwatch::UsbWatcher::new(config, root);
+            if !once {
+                watcher.run(interval).await;
+                return Ok(());
+            }
+
+            let results = watcher.scan_once().await;
+            if results.is_empty() {
+                println!("적용할 새 USB 번들이 없습니다");
+            }
+            for result in &results {
+                println!("{} {} ({})", result.version, result.status, result.path.display());
+            }
+            if let Some(failed) = results.iter().find(|r| r.status == "failed") {
+                anyhow::bail!(ClientError::Install(format!(
+                    "USB 업데이트 {} 실패: {}",
+                    failed.version,
+                    failed.error.as_deref().unwrap_or("unknown")
+                )));
+            }
+            Ok(())
+        }
+
+        Commands::Activate { cancel, no_backup } => {
+            let mut config = Config::from_env_optional()?;
+            config.backup_required &= !no_backup;
+            let updater = Updater::new(config.clone());
+            let _lock = UpdateLock::acquire(&config.service_dir)?;
+
+            if cancel {
+                match staging::discard_staged(&config, &updater)? {
+                    Some(version) => println!("🦊 스테이징 취소: {}", version),
+                    None => println!("🦊 스테이징된 업데이트 없음 (스테이징 디렉토리 정리됨)"),
+                }
+                return Ok(());
+            }
+
+            let staged_version = LocalState::load(&config.service_dir)
+                .staged
+                .map(|s| s.version);
+            let result = staging::activate_staged(&config, &updater);
+
+            // 서버 설정이 있으면 결과 보고 (실패해도 무시)
+            if let (Some(version), false) = (
+                &staged_version,
+                config.server_url.is_empty() || config.api_key.is_empty(),
+            ) {
+                let api = DmApiClient::new(&config.server_url, &config.api_key)
+                    .with_retry_policy(config.retry_policy());
+                let report = match &result {
+                    Ok(_) => UpdateResultRequest::success(version),
+                    Err(e) => UpdateResultRequest::failure(version, &e.to_string()),
+                };
+                if let Err(e) = api.report_result(&report).await {
+                    tracing::warn!("Failed to report activation result: {}", e);
+                }
+            }
+
+            println!("🦊 활성화 완료: {}", result?);
+            Ok(())
+        }
+
+        Commands::Package {
+            dir,
+            version,
+            out,
+            release_notes,
+            product,
+            git_commit,
+            build_time,
+            metadata,
+            compression,
+            level,
+            source_date_epoch,
+        } => {
+            let source_date_epoch = match source_date_epoch {
+                Some(epoch) => Some(epoch),
+                None => match std::env::var("SOURCE_DATE_EPOCH") {
+                    Ok(v) => Some(v.trim().parse::<u64>().map_err(|e| {
+                        ClientError::Config(format!("SOURCE_DATE_EPOCH는 유닉스 초여야 합니다: {}", e))
+                    })?),
+                    Err(_) => None,
+                },
+            };
+            let build_time = match build_time {
+                Some(t) => chrono::DateTime::parse_from_rfc3339(&t)
+                    .map_err(|e| ClientError::Config(format!("--build-time은 RFC3339 형식이어야 합니다: {}", e)))?
+                    .with_timezone(&chrono::Utc),
+                None => source_date_epoch
+                    .and_then(|epoch| chrono::DateTime::from_timestamp(epoch as i64, 0))
+                    .unwrap_or_else(chrono::Utc::now),
+            };
+            let build_info = BuildInfo {
+                git_commit,
+                build_time: Some(build_time),
+                metadata: package::parse_metadata(&metadata)?,
+            };
+
+            let manifest = package::create_package(
+                &dir,
+                &version,
+                &out,
+                release_notes.as_deref(),
+                product.as_deref(),
+                build_info,
+                &package::PackageOptions {
+                    codec: compression,
+                    level,
+                    source_date_epoch,
+                },
+            )?;
+            println!("🦊 번들 생성 완료: {} ({})", manifest.version, out);
+            println!("   체크섬: {}", manifest.checksum);
+            Ok(())
+        }
+
+        Commands::BatchCheckin { batch_config } => {
+            let config = Config::from_env_optional()?;
+            if config.server_url.is_empty() {
+                anyhow::bail!(ClientError::Config("DM_SERVER_URL 환경변수가 필요합니다".to_string()));
+            }
+
+            let data = std::fs::read_to_string(&batch_config)
+                .map_err(|e| ClientError::Config(format!("{} 읽기 실패: {}", batch_config, e)))?;
+            let entries: Vec<api::BatchCheckinEntry> = serde_json::from_str(&data)
+                .map_err(|e| ClientError::Config(format!("{} 파싱 실패: {}", batch_config, e)))?;
+
+            let api = api::DmApiClient::new(&config.server_url, &config.api_key)
+                .with_retry_policy(config.retry_policy());
+            let results = api.checkin_batch(&entries).await?;
+
+            for (entry, result) in entries.iter().zip(&results) {
+                let version = entry.current_version.as_deref().unwrap_or("none");
+                match (&result.response, &result.error) {
+                    (Some(response), _) => {
+                        tracing::info!("[{}] {} -> {}", result.status, version, response.action)
+                    }
+                    (None, error) => tracing::warn!(
+                        "[{}] {} -> {}",
+                        result.status,
+                        version,
+                        error.as_deref().unwrap_or("unknown error")
+                    ),
+                }
+            }
+
+            println!("{}", serde_json::to_string_pretty(&results)?);
+            Ok(())
+        }
+
+        Commands::Simulate {
+            clients,
+            interval,
+            duration,
+            server,
+            keys_file,
+            save_keys,
+            accept_rate,
+            initial_version,
+            connections,
+        } => {
+            let server_url = server
+                .or_else(|| std::env::var("DM_SERVER_URL").ok())
+                .ok_or_else(|| ClientError::Config("--server 또는 DM_SERVER_URL이 필요합니다".to_string()))?;
+            if !(0.0..=1.0).contains(&accept_rate) {
+                anyhow::bail!(ClientError::Config("--accept-rate는 0.0 ~ 1.0 사이여야 합니다".to_string()));
+            }
+
+            let keys = match &keys_file {
+                Some(path) => std::fs::read_to_string(path)
+                    .map_err(|e| ClientError::Config(format!("{} 읽기 실패: {}", path, e)))?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                None => Vec::new(),
+            };
+
+            let (stats, keys) = simulate::run(simulate::SimulateOptions {
+                server_url,
+                clients,
+                interval: std::time::Duration::from_secs(interval.max(1)),
+                duration: std::time::Duration::from_secs(duration),
+                accept_rate,
+                keys,
+                initial_version,
+                connections,
+            })
+            .await?;
+
+            if let Some(path) = save_keys {
+                std::fs::write(&path, keys.join("\n") + "\n")?;
+                println!("🦊 API Key {}개 저장: {}", keys.len(), path);
+            }
+            print!("{}", stats);
+            Ok(())
+        }
+
+        Commands::Backups {
+            command: BackupCommands::List { json },
+        } => {
+            let config = Config::from_env_optional()?;
+            let state = LocalState::load(&config.service_dir);
+            let backups = backup::list_backups(
+                std::path::Path::new(&config.backup_dir),
+                state.restore_point_name().as_deref(),
+            );
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&backups)?);
+                return Ok(());
+            }
+
+            print_backups(&config, &backups);
+            Ok(())
+        }
+
+        Commands::Rollback { latest: _, backup: name, list, yes } => {
+            let config = Config::from_env_optional()?;
+            let state = LocalState::load(&config.service_dir);
+            let backups = backup::list_backups(
+                std::path::Path::new(&config.backup_dir),
+                state.restore_point_name().as_deref(),
+            );
+            if list {
+                print_backups(&config, &backups);
+                return Ok(());
+            }
+            let _lock = UpdateLock::acquire(&config.service_dir)?;
+            if backups.is_empty() {
+                anyhow::bail!(ClientError::RollbackFailed(format!(
+                    "롤백할 백업이 없습니다 ({}). 백업은 업데이트할 때마다 만들어집니다 (DM_BACKUP_REQUIRED=false면 생략)",
+                    config.backup_dir
+                )));
+            }
+            let target = backup::rollback_target(&backups, name.as_deref(), state.no_backup.as_ref())
+                .classify(ClientError::RollbackFailed)?;
+
+            let current = std::fs::read_to_string(std::path::Path::new(&config.service_dir).join(".dm-version"))
+                .map(|v| v.trim().to_string())
+                .unwrap_or_else(|_| "unknown".to_string());
+            let summary = format!(
+                "🦊 롤백\n  버전:         {} -> {}\n  백업:         {} ({})\n  서비스 경로:  {} (현재 내용은 백업 없이 교체됩니다)",
+                current,
+                target.version,
+                target.name,
+                target.created_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                config.service_dir
+            );
+            confirm::confirm("rollback", &summary, yes)?;
+
+            // 재시작 명령과 헬스 체크 대기는 블로킹 스레드에서 (복원 전에 프로브 확정)
+            let updater = Updater::new(config.clone());
+            let probes = updater.health_probes()?;
+            let restore = target.clone();
+            let result = tokio::task::spawn_blocking(move || updater.rollback_to(&restore, &probes))
+                .await
+                .unwrap_or_else(|e| Err(e.into()))
+                .classify(ClientError::RollbackFailed);
+
+            // 서버 설정이 있으면 결과 보고 (실패해도 무시)
+            if !config.server_url.is_empty() && !config.api_key.is_empty() {
+                let api = DmApiClient::new(&config.server_url, &config.api_key)
+                    .with_retry_policy(config.retry_policy());
+                let report = match &result {
+                    Ok(()) => UpdateResultRequest::success(&target.version),
+                    Err(e) => UpdateResultRequest::failure(&target.version, &format!("Manual rollback failed: {:#}", e)),
+                };
+                if let Err(e) = api.report_result(&report).await {
+                    tracing::warn!("Failed to report rollback result: {}", e);
+                }
+            }
+
+            result?;
+            println!("🦊 롤백 완료: {} ({})", target.version, target.name);
+            Ok(())
+        }
+
+        Commands::Pause { duration, reason } => {
+            let config = Config::from_env_optional()?;
+            let pause = control::pause(&config.control_dir, duration, reason)?;
+            match pause.until {
+                Some(until) => println!(
+                    "🦊 업데이트 일시 정지: {}까지",
+                    until.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                ),
+                None => println!("🦊 업데이트 일시 정지 (resume 전까지)"),
+            }
+            if let Some(reason) = &pause.reason {
+                println!("   사유: {}", reason);
+            }
+            Ok(())
+        }
+
+        Commands::Resume => {
+            let config = Config::from_env_optional()?;
+            if control::resume(&config.control_dir)? {
+                println!("🦊 일시 정지 해제");
             } else {
-                anyhow::bail!("--file 또는 --dir 중 하나를 지정해주세요.\n\n예시:\n  dm-client apply --dir /mnt/usb\n  dm-client apply --file /mnt/usb/update.tar.gz --version 1.0.0")
+                println!("🦊 일시 정지 상태가 아님");
             }
+            Ok(())
         }
 
-        Commands::Status => {
-            let config = Config::from_env_optional();
+        Commands::TriggerCheckin => {
+            let config = Config::from_env_optional()?;
+            control::request_checkin(&config.control_dir)?;
+            println!("🦊 체크인 요청됨 (실행 중인 데몬이 곧 체크인)");
+            Ok(())
+        }
+
+        Commands::SetVersion { version, confirmed } => {
+            if !confirmed {
+                anyhow::bail!(ClientError::Config(
+                    "set-version overrides the install record without installing anything; pass --i-know-what-im-doing to confirm"
+                        .to_string()
+                ));
+            }
+            let config = Config::from_env_optional()?;
+            let previous = state::set_version(&config, &version)?;
+            println!(
+                "🦊 설치 버전 기록: {} -> {}",
+                previous.as_deref().unwrap_or("none"),
+                version
+            );
+            println!("   다음 체크인부터 서버에 {}로 보고됩니다", version);
+            Ok(())
+        }
+
+        Commands::Info { json } => {
+            let config = Config::from_env_optional()?;
+            if config.server_url.is_empty() || config.api_key.is_empty() {
+                anyhow::bail!(ClientError::Config(
+                    "DM_SERVER_URL and DM_API_KEY are required".to_string()
+                ));
+            }
+            tls::check_startup(&config)?;
+            let info = DmApiClient::new(&config.server_url, &config.api_key)
+                .with_retry_policy(config.retry_policy())
+                .with_http_cache(config.http_cache_path().as_deref())
+                .fetch_self()
+                .await?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&info)?);
+                return Ok(());
+            }
+
+            let time = |t: &chrono::DateTime<chrono::Utc>| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+            println!("🦊 {} ({})", info.name, info.id);
+            println!("   서버 상태: {}", info.status);
+            println!("   역할: {}", info.role.as_deref().unwrap_or("(없음)"));
+            println!("   현재 버전: {}", info.current_version.as_deref().unwrap_or("(보고 없음)"));
+            match &info.pinned_version {
+                Some(pinned) => println!("   고정 버전: {}", pinned),
+                None => println!("   고정 버전: 없음"),
+            }
+            match (&info.target_version, info.update_pending) {
+                (Some(target), true) => {
+                    let mut line = format!("   대기 중인 배포: {}", target);
+                    if let Some(alias) = &info.target_alias {
+                        line.push_str(&format!(" (별칭 {})", alias));
+                    }
+                    if info.target_staged {
+                        line.push_str(" [스테이징 후 활성화 대기]");
+                    }
+                    if let Some(set_at) = &info.target_set_at {
+                        line.push_str(&format!(", {} 지정", time(set_at)));
+                    }
+                    println!("{}", line);
+                }
+                _ => println!("   대기 중인 배포: 없음"),
+            }
+            if let Some(last_seen) = &info.last_seen {
+                println!("   마지막 체크인: {}", time(last_seen));
+            }
+            if tls::is_plaintext_remote(&config.server_url) {
+                println!("   ⚠ 서버 URL이 평문 HTTP입니다 (API Key가 암호화되지 않고 전송됨)");
+                if let Some(upgraded) = tls::https_upgrade(&config.server_url).await {
+                    println!("     HTTPS로도 응답합니다: DM_SERVER_URL={} 로 바꾸세요", upgraded);
+                }
+            }
+            Ok(())
+        }
+
+        Commands::Status { json } => {
+            let config = Config::from_env_optional()?;
             let version_file = std::path::Path::new(&config.service_dir).join(".dm-version");
-            match std::fs::read_to_string(&version_file) {
-                Ok(version) => println!("🦊 현재 버전: {}", version.trim()),
-                Err(_) => println!("🦊 버전 정보 없음 (아직 설치되지 않음)"),
+            let version = std::fs::read_to_string(&version_file)
+                .ok()
+                .map(|v| v.trim().to_string());
+            let state = LocalState::load(&config.service_dir);
+            let backups = backup::list_backups(
+                std::path::Path::new(&config.backup_dir),
+                state.restore_point_name().as_deref(),
+            );
+            let pause = control::current_pause(&config.control_dir);
+            let integrity = state::check_integrity(&config);
+
+            if json {
+                let status = serde_json::json!({
+                    "version": version,
+                    "service_dir": config.service_dir,
+                    "backup_dir": config.backup_dir,
+                    "artifact_checksum": state.artifact_checksum,
+                    "installed_at": state.installed_at,
+                    "build_info": state.build_info,
+                    "install_strategy": state.install_strategy,
+                    "restore_point": state.restore_point,
+                    "no_backup": state.no_backup,
+                    "pending_image": state.pending_image,
+                    "paused": pause,
+                    "state_integrity": integrity.as_str(),
+                    "backups": backups,
+                });
+                println!("{}", serde_json::to_string_pretty(&status)?);
+                return Ok(());
+            }
+
+            match version {
+                Some(version) => println!("🦊 현재 버전: {}", version),
+                None => println!("🦊 버전 정보 없음 (아직 설치되지 않음)"),
             }
             println!("   서비스 디렉토리: {}", config.service_dir);
             println!("   백업 디렉토리: {}", config.backup_dir);
+            if let Some(info) = &state.build_info {
+                if let Some(commit) = &info.git_commit {
+                    println!("   Git 커밋: {}", commit);
+                }
+                if let Some(time) = &info.build_time {
+                    println!(
+                        "   빌드 시각: {}",
+                        time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                    );
+                }
+            }
+            match state.install_strategy {
+                Some(InstallStrategy::Rename) => println!("   설치 방식: rename"),
+                Some(InstallStrategy::Copy) => {
+                    println!("   설치 방식: copy (작업 디렉토리가 다른 파일시스템, DM_WORK_DIR 확인)")
+                }
+                None => {}
+            }
+            match &integrity {
+                StateIntegrity::Valid => println!("   상태 서명: 확인됨"),
+                StateIntegrity::Unverifiable => println!("   상태 서명: 키 없음 (DM_STATE_SECRET 또는 DM_API_KEY)"),
+                StateIntegrity::Unsigned => println!("   상태 서명: 없음 (데몬 시작 시 서명)"),
+                StateIntegrity::Tampered(reason) => println!("   ⚠️ 상태 변경 감지: {}", reason),
+            }
+            if let Some(pause) = &pause {
+                let until = pause.until.map_or("resume 전까지".to_string(), |until| {
+                    format!("{}까지", until.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+                });
+                match &pause.reason {
+                    Some(reason) => println!("   일시 정지: {} ({})", until, reason),
+                    None => println!("   일시 정지: {}", until),
+                }
+            }
+            if let Some(pending) = &state.pending_image {
+                println!(
+                    "   이미지 업데이트: {} (슬롯 {} → {}, 재부팅 후 확정 대기)",
+                    pending.version, pending.from_slot, pending.to_slot
+                );
+            }
+            println!("   백업: {}개", backups.len());
+            if let Some(latest) = backups.first() {
+                println!(
+                    "   최근 백업: {} ({})",
+                    latest.version,
+                    latest.created_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                );
+            }
+            if let Some(restore_point) = &state.restore_point {
+                println!(
+                    "   복원 지점: {} (롤백 {})",
+                    restore_point.backup,
+                    restore_point.restored_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                );
+            }
+            if let Some(skipped) = &state.no_backup {
+                println!(
+                    "   ⚠️ 백업 없이 설치됨: {}에서 교체 ({}, {}로 롤백 불가)",
+                    skipped.previous_version,
+                    skipped.skipped_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                    skipped.previous_version
+                );
+            }
             Ok(())
         }
+
+        Commands::InstallService { user, config, enable, dry_run } => {
+            let exec = std::env::current_exe()
+                .and_then(|exe| exe.canonicalize())
+                .context("Failed to locate the dm-client executable")?;
+            let env_file = std::path::absolute(config.as_deref().unwrap_or(service::DEFAULT_ENV_FILE))
+                .context("Failed to resolve --config")?;
+            let unit = service::render_unit(&service::UnitOptions {
+                exec,
+                env_file: env_file.clone(),
+                user,
+            })?;
+            let unit_path = std::path::Path::new(service::UNIT_DIR).join(service::UNIT_NAME);
+
+            // 유닛 내용만 stdout으로 보내 `> dm-client.service`로 받을 수 있게 함
+            if dry_run || !service::is_systemd() {
+                if dry_run {
+                    eprintln!("🦊 --dry-run: {:?}에 쓸 유닛 파일", unit_path);
+                    if enable {
+                        eprintln!("   이후 실행: systemctl daemon-reload && systemctl enable --now {}", service::UNIT_NAME);
+                    }
+                } else {
+                    eprintln!("🦊 systemd로 부팅한 시스템이 아니어서 유닛 파일을 쓰지 않고 출력만 합니다");
+                }
+                print!("{}", unit);
+                return Ok(());
+            }
+
+            if !env_file.exists() {
+                println!("⚠️ 환경 파일이 아직 없습니다: {:?} (데몬 시작 전에 만들어야 함)", env_file);
+            }
+            match service::write_unit(std::path::Path::new(service::UNIT_DIR), &unit)? {
+                service::UnitWrite::Created => println!("🦊 유닛 파일 생성: {:?}", unit_path),
+                service::UnitWrite::Updated => println!("🦊 유닛 파일 갱신: {:?}", unit_path),
+                service::UnitWrite::Unchanged => println!("🦊 유닛 파일 변경 없음: {:?}", unit_path),
+            }
+            if enable {
+                service::systemctl(&["daemon-reload"])?;
+                service::systemctl(&["enable", "--now", service::UNIT_NAME])?;
+                println!("   활성화 및 시작됨 (systemctl status dm-client, journalctl -u dm-client -f)");
+                println!("   이미 실행 중이던 데몬은 systemctl restart dm-client 후 새 유닛을 사용합니다");
+            } else {
+                println!("   시작: sudo systemctl daemon-reload && sudo systemctl enable --now dm-client");
+            }
+            Ok(())
+        }
+
+        Commands::UninstallService { dry_run } => {
+            let unit_path = std::path::Path::new(service::UNIT_DIR).join(service::UNIT_NAME);
+            if !unit_path.exists() {
+                println!("🦊 설치된 유닛 없음: {:?}", unit_path);
+                return Ok(());
+            }
+            let systemd = service::is_systemd();
+            if dry_run {
+                println!("🦊 --dry-run: 실행할 작업");
+                if systemd {
+                    println!("   systemctl disable --now {}", service::UNIT_NAME);
+                }
+                println!("   {:?} 삭제", unit_path);
+                if systemd {
+                    println!("   systemctl daemon-reload");
+                }
+                return Ok(());
+            }
+
+            if systemd {
+                // 이미 멈춰 있거나 비활성화된 유닛도 삭제는 계속
+                if let Err(e) = service::systemctl(&["disable", "--now", service::UNIT_NAME]) {
+                    tracing::warn!("{}", e);
+                }
+            }
+            service::remove_unit(std::path::Path::new(service::UNIT_DIR))?;
+            if systemd {
+                service::systemctl(&["daemon-reload"])?;
+            }
+            println!("🦊 유닛 삭제: {:?}", unit_path);
+            Ok(())
+        }
+    }
+}
+
+/// 백업 목록 출력 (최신순, 생성 시각과 원래 버전은 `backup_<version>_<ts>` 이름에서)
+fn print_backups(config: &Config, backups: &[backup::BackupEntry]) {
+    if backups.is_empty() {
+        println!("🦊 백업 없음 ({})", config.backup_dir);
+        return;
+    }
+    for b in backups {
+        println!(
+            "{}  {}  {}{}",
+            b.created_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            b.version,
+            b.name,
+            if b.active_restore_point { "  [active restore point]" } else { "" }
+        );
+        if !b.excluded.is_empty() {
+            println!("    제외됨: {}", b.excluded.join(", "));
+        }
     }
 }
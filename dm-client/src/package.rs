@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use flate2::GzBuilder;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tar::{Builder, Header, HeaderMode};
+
+use crate::api::BuildInfo;
+use crate::product::{self, PRODUCT_FILE};
+use crate::usb::UsbManifest;
+
+/// --source-date-epoch가 없을 때 항목 mtime 상한 (1980-01-01T00:00:00Z, zip 형식의 최소 시각)
+pub const DEFAULT_SOURCE_DATE_EPOCH: u64 = 315_532_800;
+
+/// 아티팩트 압축 방식
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Codec {
+    #[default]
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    /// 번들 안의 아티팩트 파일 이름
+    pub fn artifact_name(self) -> &'static str {
+        match self {
+            Codec::Gzip => "update.tar.gz",
+            Codec::Zstd => "update.tar.zst",
+        }
+    }
+
+    fn default_level(self) -> i32 {
+        match self {
+            Codec::Gzip => 6,
+            Codec::Zstd => zstd::DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+
+    fn level_range(self) -> std::ops::RangeInclusive<i32> {
+        match self {
+            Codec::Gzip => 0..=9,
+            Codec::Zstd => 1..=22,
+        }
+    }
+}
+
+/// 패키징 옵션 (기본값: gzip 6단계, mtime 상한 1980-01-01)
+#[derive(Debug, Clone, Default)]
+pub struct PackageOptions {
+    pub codec: Codec,
+    /// 압축 단계 (gzip 0-9, zstd 1-22)
+    pub level: Option<i32>,
+    /// 항목 mtime 상한 (SOURCE_DATE_EPOCH 관례, 초)
+    pub source_date_epoch: Option<u64>,
+}
+
+/// 서비스 디렉토리를 USB 배포용 번들(update.tar.gz 또는 update.tar.zst + manifest.json)로 패키징
+///
+/// 같은 트리는 어느 빌드 머신에서 만들어도 같은 바이트가 나온다: 항목은 경로 순으로 정렬하고,
+/// mtime은 source_date_epoch로 자르고, uid/gid/사용자 이름은 비우고, gzip 헤더 mtime은 0으로 둔다.
+pub fn create_package(
+    source_dir: &str,
+    version: &str,
+    out_dir: &str,
+    release_notes: Option<&str>,
+    product: Option<&str>,
+    build_info: BuildInfo,
+    options: &PackageOptions,
+) -> Result<UsbManifest> {
+    let source = Path::new(source_dir);
+    if !source.is_dir() {
+        anyhow::bail!("디렉토리를 찾을 수 없습니다: {}", source_dir);
+    }
+
+    semver::Version::parse(version).context("버전이 semver 형식이 아닙니다")?;
+
+    let codec = options.codec;
+    let level = options.level.unwrap_or(codec.default_level());
+    if !codec.level_range().contains(&level) {
+        anyhow::bail!(
+            "{:?} 압축 단계는 {}-{} 범위여야 합니다: {}",
+            codec,
+            codec.level_range().start(),
+            codec.level_range().end(),
+            level
+        );
+    }
+    let epoch = options.source_date_epoch.unwrap_or(DEFAULT_SOURCE_DATE_EPOCH);
+
+    // 제품 식별 파일 (디렉토리에 이미 있으면 같은 값이어야 함)
+    if let Some(product) = product {
+        product::validate(product)?;
+        if let Some(existing) = product::read(source)?.filter(|p| p != product) {
+            anyhow::bail!(
+                "{}에 다른 제품({})의 {}가 있습니다",
+                source_dir,
+                existing,
+                PRODUCT_FILE
+            );
+        }
+    }
+
+    let out = Path::new(out_dir);
+    fs::create_dir_all(out)?;
+
+    // 1. tar 아카이브 생성 (gzip 또는 zstd)
+    let artifact_name = codec.artifact_name();
+    let artifact_path = out.join(artifact_name);
+    tracing::info!("패키징 중: {} -> {:?} ({:?} {}단계)", source_dir, artifact_path, codec, level);
+    {
+        let file = fs::File::create(&artifact_path).context("아티팩트 파일 생성 실패")?;
+        match codec {
+            Codec::Gzip => {
+                let encoder = GzBuilder::new()
+                    .mtime(0)
+                    .write(file, flate2::Compression::new(level as u32));
+                write_tar(encoder, source, product, epoch)?.finish()?;
+            }
+            Codec::Zstd => {
+                let encoder = zstd::Encoder::new(file, level)?;
+                write_tar(encoder, source, product, epoch)?.finish()?;
+            }
+        }
+    }
+
+    // 2. 체크섬 계산
+    let data = fs::read(&artifact_path)?;
+    let checksum = crate::updater::sha256_hex(&data);
+
+    // 3. manifest.json 작성
+    let manifest = UsbManifest {
+        version: version.to_string(),
+        checksum,
+        signature: None,
+        artifact: artifact_name.to_string(),
+        artifact_url: None,
+        release_notes: release_notes.map(|s| s.to_string()),
+        changelog: None,
+        pre_install_script: None,
+        post_install_script: None,
+        build_info,
+    };
+    fs::write(out.join("manifest.json"), serde_json::to_string_pretty(&manifest)?)?;
+
+    tracing::info!("✅ 패키징 완료: {} ({} bytes)", version, data.len());
+    Ok(manifest)
+}
+
+/// 정렬된 순서로 tar 항목을 쓰고 압축 스트림 반환
+///
+/// 심볼릭 링크는 기존처럼 대상 내용으로 담는다.
+fn write_tar<W: Write>(writer: W, source: &Path, product: Option<&str>, epoch: u64) -> Result<W> {
+    let mut builder = Builder::new(writer);
+    let mut entries = Vec::new();
+    collect_entries(source, Path::new(""), &mut entries)?;
+
+    for rel in &entries {
+        let path = source.join(rel);
+        let meta = fs::metadata(&path).with_context(|| format!("{:?} 읽기 실패", path))?;
+        let mut header = Header::new_gnu();
+        header.set_metadata_in_mode(&meta, HeaderMode::Deterministic);
+        header.set_mtime(mtime_of(&meta).min(epoch));
+        if meta.is_dir() {
+            builder
+                .append_data(&mut header, rel, std::io::empty())
+                .context("아티팩트 압축 실패")?;
+        } else {
+            let file = fs::File::open(&path).with_context(|| format!("{:?} 열기 실패", path))?;
+            builder
+                .append_data(&mut header, rel, file)
+                .context("아티팩트 압축 실패")?;
+        }
+    }
+
+    if let Some(product) = product.filter(|_| !source.join(PRODUCT_FILE).exists()) {
+        let mut header = Header::new_gnu();
+        header.set_size(product.len() as u64 + 1);
+        header.set_mode(0o644);
+        header.set_mtime(epoch);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, PRODUCT_FILE, format!("{}\n", product).as_bytes())
+            .context("제품 식별 파일 추가 실패")?;
+    }
+
+    Ok(builder.into_inner()?)
+}
+
+/// 디렉토리 아래 모든 항목의 상대 경로 (이름 순, 디렉토리가 그 안의 항목보다 먼저)
+fn collect_entries(root: &Path, rel: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    let dir = root.join(rel);
+    let mut names = fs::read_dir(&dir)
+        .with_context(|| format!("{:?} 읽기 실패", dir))?
+        .map(|entry| entry.map(|e| e.file_name()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    names.sort();
+
+    for name in names {
+        let child = rel.join(name);
+        out.push(child.clone());
+        if fs::metadata(root.join(&child))?.is_dir() {
+            collect_entries(root, &child, out)?;
+        }
+    }
+    Ok(())
+}
+
+fn mtime_of(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// KEY=VALUE 형식의 메타데이터 인자 파싱
+pub fn parse_metadata(pairs: &[String]) -> Result<Option<serde_json::Value>> {
+    if pairs.is_empty() {
+        return Ok(None);
+    }
+
+    let mut map = serde_json::Map::new();
+    for pair in pairs {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("메타데이터는 KEY=VALUE 형식이어야 합니다: {}", pair))?;
+        map.insert(key.to_string(), serde_json::Value::String(value.to_string()));
+    }
+    Ok(Some(serde_json::Value::Object(map)))
+}
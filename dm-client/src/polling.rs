@@ -1,27 +1,177 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use rand::Rng;
+use sha2::{Digest, Sha256};
 use std::fs;
+use std::future::Future;
 use std::path::Path;
+use std::sync::Mutex;
 use tokio::time::{sleep, Duration};
 
-use crate::api::DmApiClient;
-use crate::config::Config;
-use crate::updater::Updater;
+use crate::abslot::{self, PendingImage, SlotDevice};
+use crate::installfs::InstallStrategy;
+use crate::lock::UpdateLock;
+use crate::api::{
+    self, ActionResultRequest, ArtifactSource, CheckinRequest, CheckinResponse, DmApiClient, DownloadedArtifact,
+    PhaseTimes, PushedConfig, ServerBusy, SurveyResultRequest, UpdateResultRequest,
+};
+use crate::backup;
+use crate::chunked::ParallelDownload;
+use crate::config::{Config, DaemonMode};
+use crate::control::{self, PauseState};
+use crate::deadline::{Deadline, UpdatePhase, UpdateTimedOut};
+use crate::diskspace::{self, InsufficientSpace};
+use crate::downgrade::{self, DowngradeRefused};
+use crate::error::{Classify, ClientError, Recovery};
+use crate::fsfault::{self, FsFault};
+use crate::product::ProductMismatch;
+use crate::retry::CircuitOpenError;
+use crate::scripts::InstallScripts;
+use crate::signing::{self, InvalidSignature};
+use crate::staging;
+use crate::static_mode;
+use crate::supersede::Superseded;
+use crate::survey;
+use crate::tls;
+use crate::state::{self, LocalState, PendingResult, StagedUpdate, StateIntegrity};
+use crate::updater::{self, Updater};
 
 const VERSION_FILE: &str = ".dm-version";
 
+/// 폴링 대기 중 제어 파일(trigger-checkin) 확인 주기
+const CONTROL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 백오프 시 폴링 간격 상한 (초)
+const MAX_BACKOFF_SECS: u64 = 3600;
+/// 백오프 단계 상한 (간격은 단계마다 두 배)
+const MAX_BACKOFF_STEPS: u32 = 10;
+
+/// 체크인이 연속으로 실패할 때 폴링 간격 상한 (초, 기본 간격이 더 길면 기본 간격)
+const MAX_CHECKIN_RETRY_SECS: u64 = 300;
+
+/// 롤백 실패 후 degraded 사유
+const ROLLBACK_FAILED: &str = "rollback failed, service state unknown";
+
+/// 로그용 일시 정지 설명 (" until ...: reason")
+fn pause_suffix(pause: &PauseState) -> String {
+    let mut suffix = String::new();
+    if let Some(until) = pause.until {
+        suffix.push_str(&format!(" until {}", until.to_rfc3339()));
+    }
+    if let Some(reason) = &pause.reason {
+        suffix.push_str(&format!(": {}", reason));
+    }
+    suffix
+}
+
+/// 블로킹 작업(재시작 명령, 헬스 체크 대기)을 런타임 밖 스레드에서 실행
+async fn off_runtime<T: Send + 'static>(
+    updater: &Updater,
+    work: impl FnOnce(&Updater) -> Result<T> + Send + 'static,
+) -> Result<T> {
+    let updater = updater.clone();
+    tokio::task::spawn_blocking(move || work(&updater)).await?
+}
+
+/// 업데이트 수행 결과
+enum UpdateOutcome {
+    /// 새 아티팩트 설치 완료 (업데이트 후 명령이 실패했으면 그 사유)
+    Installed { post_update_error: Option<String> },
+    /// 동일한 아티팩트가 이미 설치되어 있어 건너뜀
+    AlreadyInstalled,
+}
+
+/// 재부팅을 걸친 이미지 업데이트 상태
+enum ImageCheck {
+    /// 진행 중인 이미지 업데이트 없음
+    Idle,
+    /// 슬롯 전환 후 재부팅 대기 (서버 명령은 처리하지 않음)
+    AwaitingReboot,
+    /// 새 슬롯으로 부팅함, 체크인 성공 후 확정
+    Confirming(Box<PendingImage>),
+}
+
 /// Polling 기반 업데이트 루프
 pub struct PollingDaemon {
     config: Config,
+    /// 서버 설정을 덮어쓴 설정 (체크인 응답의 config를 받을 때마다 `config`에서 다시 만듦)
+    effective: Mutex<Config>,
     api: DmApiClient,
-    updater: Updater,
+    /// 큰 아티팩트 분할 다운로드 (DM_DOWNLOAD_CONNECTIONS > 1)
+    parallel: Option<ParallelDownload>,
+    /// 파일시스템 장애나 롤백 실패 시 사유 설정, 이후 업데이트 시도를 중단 (데몬 재시작 시 재점검)
+    degraded: Mutex<Option<&'static str>>,
+    /// 사람이 고쳐야 하는 실패(설정, 인증, 미지원)가 연속된 횟수 (폴링 주기를 늘림)
+    backoff: Mutex<u32>,
+    /// 연속으로 실패한 체크인(manifest 조회) 횟수 (폴링 간격을 MAX_CHECKIN_RETRY_SECS까지 늘림)
+    checkin_failures: Mutex<u32>,
+    /// 업데이트나 롤백 직후라 다음 폴링을 기다리지 않고 바로 체크인
+    repoll: Mutex<bool>,
+    /// 진행 중인 업데이트 단계 (전체 타임아웃 보고용)
+    phase: Mutex<UpdatePhase>,
+    /// 이번 업데이트에서 직접 계산한 아티팩트 체크섬 (결과 보고용)
+    verified_checksum: Mutex<Option<String>>,
+    /// 이번 업데이트에서 아티팩트를 받은 곳 (결과 보고용)
+    artifact_source: Mutex<Option<String>>,
+    /// 이번 업데이트의 단계별 시각 (결과 보고용)
+    phase_times: Mutex<PhaseTimes>,
+    /// 이번 업데이트가 빈 장비의 첫 설치인지 (결과 보고용)
+    bootstrap: Mutex<bool>,
+    /// 설치 상태가 외부에서 수정된 사유 (체크인에 state_tampered로 보고)
+    state_tampered: Mutex<Option<String>>,
+    /// 마지막으로 서명 검증을 통과한 설치 상태 (수정 전후 비교용)
+    verified_state: Mutex<Option<LocalState>>,
+    /// 서버에 마지막으로 전송한 실제 적용 설정 해시
+    reported_config_hash: Mutex<Option<String>>,
+    /// 마지막으로 받아 저장한 서버 설정의 해시 (재시작하면 다시 받음)
+    pushed_config_hash: Mutex<Option<String>>,
+    /// 마지막 체크인 응답이 보안 업데이트였는지 (다음 폴링 간격 단축)
+    urgent: Mutex<bool>,
+    /// 진행 중인 업데이트에서 추출한 트리를 설치한 방법 (결과 보고와 설치 상태에 기록)
+    install_strategy: Mutex<Option<InstallStrategy>>,
+    /// 서버가 요청한 다음 폴링 대기 시간 (next_poll_secs 또는 Retry-After, 한 번 기다리면 지움)
+    poll_hint: Mutex<Option<u64>>,
+    /// 직전 폴링 대기 시간 (다음 체크인에 보고)
+    last_poll_wait: Mutex<Option<u64>>,
+    /// 이 프로세스가 이미지 업데이트 재부팅을 요청함 (부팅 ID를 읽을 수 없을 때 재부팅 여부 판단용)
+    reboot_requested: Mutex<bool>,
 }
 
 impl PollingDaemon {
     pub fn new(config: Config) -> Self {
-        let api = DmApiClient::new(&config.server_url, &config.api_key);
-        let updater = Updater::new(config.clone());
-        
-        Self { config, api, updater }
+        let instance_id = uuid::Uuid::new_v4().to_string();
+        tracing::debug!("Daemon instance id: {}", instance_id);
+        let http = api::polling_http_client(Duration::from_secs(config.poll_interval_secs));
+        let api = DmApiClient::with_http_client(http, &config.server_url, &config.api_key)
+            .with_instance(&instance_id, chrono::Utc::now())
+            .with_retry_policy(config.retry_policy())
+            .with_download_idle_timeout(Duration::from_secs(config.download_idle_timeout_secs))
+            .with_http_cache(config.http_cache_path().as_deref());
+        let parallel = ParallelDownload::from_config(&config);
+
+        Self {
+            effective: Mutex::new(config.clone()),
+            config,
+            api,
+            parallel,
+            degraded: Mutex::new(None),
+            backoff: Mutex::new(0),
+            checkin_failures: Mutex::new(0),
+            repoll: Mutex::new(false),
+            phase: Mutex::new(UpdatePhase::Download),
+            verified_checksum: Mutex::new(None),
+            artifact_source: Mutex::new(None),
+            phase_times: Mutex::new(PhaseTimes::default()),
+            bootstrap: Mutex::new(false),
+            state_tampered: Mutex::new(None),
+            verified_state: Mutex::new(None),
+            reported_config_hash: Mutex::new(None),
+            pushed_config_hash: Mutex::new(None),
+            urgent: Mutex::new(false),
+            install_strategy: Mutex::new(None),
+            poll_hint: Mutex::new(None),
+            last_poll_wait: Mutex::new(None),
+            reboot_requested: Mutex::new(false),
+        }
     }
 
     /// 현재 버전 읽기
@@ -38,36 +188,387 @@ impl PollingDaemon {
         Ok(())
     }
 
-    /// 업데이트 실행
-    async fn perform_update(&self, target_version: &str, artifact_url: &str, checksum: &str) -> Result<()> {
+    /// 현재 업데이트 단계 기록 (SLA 보고용 시각은 단계에 처음 들어갈 때 기록)
+    fn enter_phase(&self, phase: UpdatePhase) {
+        *self.phase.lock().unwrap() = phase;
+
+        let now = Some(chrono::Utc::now());
+        let mut times = self.phase_times.lock().unwrap();
+        let slot = match phase {
+            UpdatePhase::Download => &mut times.download_started_at,
+            UpdatePhase::Verify => &mut times.download_completed_at,
+            UpdatePhase::PreInstall | UpdatePhase::Install => &mut times.install_started_at,
+            UpdatePhase::HealthCheck => &mut times.restarted_at,
+            _ => return,
+        };
+        if slot.is_none() {
+            *slot = now;
+        }
+    }
+
+    /// 다운로드하며 계산한 체크섬을 기대값과 비교
+    fn verify_artifact(&self, artifact: &DownloadedArtifact, expected: &str) -> Result<()> {
+        let actual = artifact.checksum.clone();
+        *self.verified_checksum.lock().unwrap() = Some(actual.clone());
+        if actual != expected {
+            anyhow::bail!(ClientError::Checksum(format!(
+                "Checksum verification failed! (expected {}, got {})",
+                expected, actual
+            )));
+        }
+        Ok(())
+    }
+
+    /// 아티팩트 서명 검증 (DM_UPDATE_PUBLIC_KEY가 설정된 경우, 서명이 없으면 거부)
+    fn verify_signature(&self, artifact: &DownloadedArtifact, offer: &CheckinResponse) -> Result<()> {
+        let version = offer.target_version.as_deref().unwrap_or("unknown");
+        signing::verify_artifact(&self.config, &artifact.path, offer.signature.as_deref(), version)
+    }
+
+    /// 타겟 버전의 설치 스크립트 받기 (체크인 응답의 해시로 검증)
+    ///
+    /// 서버가 준 코드를 실행하는 것이므로 DM_ALLOW_REMOTE_SCRIPTS=true일 때만 허용한다.
+    async fn fetch_install_scripts(&self, offer: &CheckinResponse) -> Result<InstallScripts> {
+        let Some(expected) = offer.scripts.as_ref().filter(|s| !s.is_empty()) else {
+            return Ok(InstallScripts::default());
+        };
+        let target_version = offer.target_version.as_deref().unwrap_or("unknown");
+        if !self.config.allow_remote_scripts {
+            anyhow::bail!(ClientError::Config(format!(
+                "Version {} requires install scripts but DM_ALLOW_REMOTE_SCRIPTS is not enabled",
+                target_version
+            )));
+        }
+
+        let scripts = self.api.fetch_scripts(target_version).await?;
+        scripts.verify(expected).classify(ClientError::Checksum)?;
+        tracing::info!("Install scripts for {} verified ✓", target_version);
+        Ok(scripts)
+    }
+
+    /// 아티팩트 다운로드
+    ///
+    /// 서버 다음으로 미러를 순서대로 시도하고 (연결 실패, 2xx가 아닌 응답), 받은 곳을 결과 보고에 남긴다.
+    /// 서명된 URL이 만료되면 다시 체크인해 새 URL로 한 번 더 시도한다.
+    ///
+    /// 받는 동안 해시를 계산하며 DM_DOWNLOAD_DIR(없으면 백업 디렉토리)의 임시 파일에 쓰므로
+    /// 아티팩트 크기만큼 메모리를 쓰지 않는다.
+    async fn download_artifact(&self, offer: &CheckinResponse) -> Result<DownloadedArtifact> {
+        let dir = self.config.download_dir();
+        self.fetch_artifact(offer, |url, source| async move {
+            if let Some((plan, checksum)) = self.parallel_download(offer) {
+                if let Some(size) = self.api.download_artifact_parallel(&url, source, checksum, plan).await? {
+                    // 같은 디렉토리이므로 이름만 바꿔 임시 파일 자리로 옮김
+                    let path = DownloadedArtifact::create_in(dir)?.into_temp_path();
+                    fs::rename(&plan.part_path, &path)?;
+                    plan.discard();
+                    let checksum = updater::sha256_file(&path)?;
+                    return Ok(DownloadedArtifact { path, size, checksum });
+                }
+            }
+            self.api.download_artifact(&url, source, dir).await
+        })
+        .await
+    }
+
+    /// 분할 다운로드 설정과 이어받기 기준 체크섬 (체크섬을 모르면 단일 스트림)
+    fn parallel_download<'a>(&'a self, offer: &'a CheckinResponse) -> Option<(&'a ParallelDownload, &'a str)> {
+        let checksum = offer.checksum.as_deref().filter(|c| !c.is_empty())?;
+        Some((self.parallel.as_ref()?, checksum))
+    }
+
+    /// 소스별로 `fetch` 시도 (서버 → 미러 순서, 만료된 서명 URL은 한 번 갱신)
+    async fn fetch_artifact<T, F, Fut>(&self, offer: &CheckinResponse, fetch: F) -> Result<T>
+    where
+        F: Fn(String, ArtifactSource) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let urls = offer.artifact_sources();
+        let mut last_error = anyhow::anyhow!("No artifact URL in checkin response");
+        for (i, url) in urls.iter().enumerate() {
+            let source = if i == 0 { ArtifactSource::Server } else { ArtifactSource::Mirror };
+            if let Err(e) = tls::check_download_url(&self.config, url) {
+                tracing::warn!("{}", e);
+                last_error = e;
+                continue;
+            }
+            let result = match fetch(url.clone(), source).await {
+                Err(e) if e.is::<api::ArtifactUrlExpired>() => {
+                    tracing::warn!("Artifact URL expired, checking in again for a fresh one");
+                    match self.refresh_artifact_url(offer).await {
+                        Ok(refreshed) => fetch(refreshed, source).await,
+                        Err(e) => Err(e),
+                    }
+                }
+                result => result,
+            };
+
+            match result {
+                Ok(data) => {
+                    // 서명 토큰 등 쿼리는 기록하지 않음
+                    let recorded = url.split('?').next().unwrap_or(url);
+                    *self.artifact_source.lock().unwrap() = Some(recorded.to_string());
+                    return Ok(data);
+                }
+                Err(e) => {
+                    if i + 1 < urls.len() {
+                        tracing::warn!("Download from {} failed: {}; trying next source", url, e);
+                    }
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// 업데이트 진행 중 체크인 요청 (status=updating, 서버는 같은 타겟이면 기존 로그를 이어서 사용)
+    fn updating_checkin(&self) -> CheckinRequest {
+        let local_state = LocalState::load(&self.config.service_dir);
+        CheckinRequest {
+            current_version: self.read_current_version(),
+            status: "updating".to_string(),
+            staged_version: local_state.staged.as_ref().map(|s| s.version.clone()),
+            role: local_state.effective_role(&self.config),
+            product: local_state.effective_product(&self.config),
+            ..Default::default()
+        }
+    }
+
+    /// 진행 중인 업데이트의 새 아티팩트 URL 받기
+    async fn refresh_artifact_url(&self, offer: &CheckinResponse) -> Result<String> {
+        let response = self.api.checkin(self.updating_checkin()).await?;
+        match response.artifact_url {
+            Some(url) if response.target_version == offer.target_version => Ok(url),
+            _ => anyhow::bail!(
+                "Target changed while refreshing artifact URL ({} -> {})",
+                offer.target_version.as_deref().unwrap_or("none"),
+                response.target_version.as_deref().unwrap_or("none")
+            ),
+        }
+    }
+
+    /// 타겟 변경 확인 주기 (Server 모드에서 DM_SUPERSEDE_CHECK_SECS > 0일 때만)
+    fn supersede_interval(&self) -> Option<Duration> {
+        (self.config.mode == DaemonMode::Server && self.config.supersede_check_secs > 0)
+            .then(|| Duration::from_secs(self.config.supersede_check_secs))
+    }
+
+    /// 서버의 타겟이 그대로인지 확인 (체크인에 실패하면 그대로 진행)
+    async fn check_superseded(&self, offer: &CheckinResponse) -> Option<Superseded> {
+        match self.api.checkin(self.updating_checkin()).await {
+            Ok(response) => Superseded::check(offer, response),
+            Err(e) => {
+                tracing::debug!("Target check during update failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 주기적으로 타겟을 확인하며 작업 실행 (다운로드 등)
+    ///
+    /// 타겟이 바뀌거나 취소되면 `within_deadline`과 같은 방식으로 작업 future를 버려 취소하고
+    /// `Superseded`를 반환한다.
+    async fn until_superseded<T>(
+        &self,
+        offer: &CheckinResponse,
+        operation: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let Some(interval) = self.supersede_interval() else {
+            return operation.await;
+        };
+        let watch = async {
+            loop {
+                sleep(interval).await;
+                if let Some(superseded) = self.check_superseded(offer).await {
+                    return superseded;
+                }
+            }
+        };
+        tokio::select! {
+            result = operation => result,
+            superseded = watch => {
+                tracing::warn!("{}; abandoning the download", superseded);
+                Err(superseded.into())
+            }
+        }
+    }
+
+    /// 설치(또는 스테이징, 슬롯 쓰기)를 시작하기 전 마지막 타겟 확인
+    async fn ensure_still_targeted(&self, offer: &CheckinResponse) -> Result<()> {
+        if self.supersede_interval().is_none() {
+            return Ok(());
+        }
+        match self.check_superseded(offer).await {
+            Some(superseded) => {
+                tracing::warn!("{}; not installing", superseded);
+                Err(superseded.into())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// 설치 전 구간에 업데이트 전체 기한 적용 (DM_UPDATE_TIMEOUT_SECS)
+    ///
+    /// 기한이 지나면 대기 중인 작업(다운로드 등)을 취소하고 진행 중이던 단계를 담아 실패로 반환한다.
+    /// 서비스 디렉토리를 바꾼 뒤에는 future를 버리면 롤백이 빠지므로 쓰지 않는다.
+    async fn within_deadline<T>(
+        &self,
+        deadline: Deadline,
+        operation: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let at = tokio::time::Instant::from_std(deadline.instant());
+        match tokio::time::timeout_at(at, operation).await {
+            Ok(result) => result,
+            Err(_) => Err(deadline.timed_out(*self.phase.lock().unwrap())),
+        }
+    }
+
+    /// 다운로드 전 여유 공간 확인 (다운로드/작업, 서비스, 백업 디렉토리의 파일시스템마다 아티팩트 크기 × DM_DISK_SPACE_FACTOR)
+    ///
+    /// 서버가 크기를 보내지 않았거나 DM_SKIP_DISK_CHECK면 생략한다.
+    fn check_disk_space(&self, offer: &CheckinResponse) -> Result<()> {
+        let Some(artifact_size) = offer.artifact_size.filter(|size| *size > 0) else {
+            return Ok(());
+        };
+        if self.config.skip_disk_check {
+            tracing::info!("Disk space check skipped (DM_SKIP_DISK_CHECK)");
+            return Ok(());
+        }
+        let mut dirs = vec![
+            ("download_dir", self.config.download_dir()),
+            ("service_dir", Path::new(&self.config.service_dir)),
+            ("backup_dir", Path::new(&self.config.backup_dir)),
+        ];
+        if let Some(work_dir) = &self.config.work_dir {
+            dirs.push(("work_dir", Path::new(work_dir)));
+        }
+        diskspace::check(&dirs, artifact_size, self.config.disk_space_factor)?;
+        Ok(())
+    }
+
+    /// 업데이트 실행 (기한이 지나면 설치 이후 단계는 롤백)
+    async fn perform_update(&self, offer: &CheckinResponse, deadline: Deadline) -> Result<UpdateOutcome> {
+        let target_version = offer.target_version.as_deref().unwrap_or("unknown");
+        let checksum = offer.checksum.as_deref().unwrap_or("");
+        self.check_downgrade(offer)?;
         let current_version = self.read_current_version().unwrap_or_else(|| "unknown".to_string());
-        
-        tracing::info!("Starting update: {} -> {}", current_version, target_version);
+        let existing = LocalState::load(&self.config.service_dir);
+        let installed_state = LocalState::installed(target_version, checksum, offer.build_info.clone())
+            .keep_server_config(&existing);
 
-        // 1. 아티팩트 다운로드
-        tracing::info!("Downloading artifact...");
-        let artifact_data = self.api.download_artifact(artifact_url).await?;
+        // 0. 동일한 아티팩트가 이미 설치되어 있는지 확인 (서버가 재설치를 요청하면 생략)
+        if !offer.force_reinstall && existing.has_artifact(checksum) {
+            tracing::info!(
+                "Artifact for {} is already installed (checksum match), skipping download",
+                target_version
+            );
+            *self.verified_checksum.lock().unwrap() = existing.artifact_checksum.clone();
+            self.write_current_version(target_version)?;
+            // 트리가 바뀌지 않았으므로 복원 지점(과 백업 생략 기록) 유지
+            LocalState {
+                restore_point: existing.restore_point,
+                no_backup: existing.no_backup,
+                install_strategy: existing.install_strategy,
+                ..installed_state
+            }
+            .save(&self.config)?;
+            return Ok(UpdateOutcome::AlreadyInstalled);
+        }
 
-        // 2. 체크섬 검증
-        tracing::info!("Verifying checksum...");
-        if !self.updater.verify_checksum(&artifact_data, checksum) {
-            anyhow::bail!("Checksum verification failed!");
+        // 빈 장비의 첫 설치: 백업/롤백할 이전 설치가 없음
+        // 업데이트 도중 받은 서버 설정은 다음 업데이트부터 적용
+        let base = self.updater();
+        let bootstrap = self.read_current_version().is_none() && base.is_first_install();
+        *self.bootstrap.lock().unwrap() = bootstrap;
+        if bootstrap {
+            tracing::info!("Bootstrap install: {} (first install, no backup or rollback)", target_version);
+        } else {
+            tracing::info!("Starting update: {} -> {}", current_version, target_version);
         }
-        tracing::info!("Checksum verified ✓");
+        // 롤백은 기한과 무관하게 끝까지 수행 (base 사용)
+        let updater = base.with_deadline(deadline);
+        let health_probes = updater.health_probes()?;
+
+        // 설치 전 단계만 기한에서 취소 (설치 이후는 각 단계가 기한을 확인하고 실패하면 롤백)
+        let (scripts, artifact, backup_path) = self
+            .within_deadline(deadline, async {
+                let scripts = self.fetch_install_scripts(offer).await?;
+
+                // 1. 아티팩트 다운로드 (받는 도중 디스크가 차지 않도록 여유 공간 먼저 확인)
+                self.enter_phase(UpdatePhase::Download);
+                self.check_disk_space(offer)?;
+                tracing::info!("Downloading artifact...");
+                let artifact = self.until_superseded(offer, self.download_artifact(offer)).await?;
+
+                // 2. 체크섬 검증
+                tracing::info!("Verifying checksum...");
+                self.enter_phase(UpdatePhase::Verify);
+                self.verify_artifact(&artifact, checksum)?;
+                tracing::info!("Checksum verified ✓");
+                self.verify_signature(&artifact, offer)?;
+                base.validate_artifact(&artifact.path)?;
+                self.ensure_still_targeted(offer).await?;
 
-        // 3. 현재 버전 백업
-        tracing::info!("Creating backup...");
-        let backup_path = self.updater.backup_current(&current_version)?;
+                // 3. 현재 버전 백업 (첫 설치는 생략)
+                let backup_path = if bootstrap {
+                    String::new()
+                } else {
+                    tracing::info!("Creating backup...");
+                    self.enter_phase(UpdatePhase::Backup);
+                    deadline.check(UpdatePhase::Backup)?;
+                    base.backup_current(&current_version)?
+                };
+                Ok((scripts, artifact, backup_path))
+            })
+            .await?;
+
+        // 업데이트 전 명령 (실패하면 서비스 디렉토리를 바꾸지 않고 중단)
+        if base.config().pre_update_script.is_some() {
+            self.enter_phase(UpdatePhase::PreInstall);
+            updater
+                .run_pre_update_script(&current_version, target_version)
+                .classify(ClientError::Install)?;
+        }
+
+        // 4. 설치 전 스크립트, 추출 및 설치, 설치 후 스크립트
+        if let Some(script) = &scripts.pre_install {
+            self.enter_phase(UpdatePhase::PreInstall);
+            if let Err(e) =
+                updater.run_install_script(UpdatePhase::PreInstall, script, target_version, &current_version)
+            {
+                tracing::error!("Pre-install script failed: {}", e);
+                self.rollback_failed_update(&base, &backup_path).await?;
+                return Err(e).classify(ClientError::Install);
+            }
+        }
 
-        // 4. 추출 및 설치
         tracing::info!("Extracting and installing...");
-        if let Err(e) = self.updater.extract_and_install(&artifact_data) {
-            tracing::error!("Installation failed: {}", e);
-            if !backup_path.is_empty() {
-                tracing::info!("Attempting rollback...");
-                self.updater.rollback(&backup_path)?;
+        self.enter_phase(UpdatePhase::Install);
+        match updater.extract_and_install(&artifact.path) {
+            Ok(strategy) => *self.install_strategy.lock().unwrap() = Some(strategy),
+            Err(e) => {
+                tracing::error!("Installation failed: {}", e);
+                if !fsfault::rollback_blocked(&e) {
+                    self.rollback_failed_update(&base, &backup_path).await?;
+                }
+                return Err(e).classify(ClientError::Install);
             }
-            return Err(e);
+        }
+
+        if let Some(script) = &scripts.post_install {
+            self.enter_phase(UpdatePhase::PostInstall);
+            if let Err(e) =
+                updater.run_install_script(UpdatePhase::PostInstall, script, target_version, &current_version)
+            {
+                tracing::error!("Post-install script failed: {}", e);
+                self.rollback_failed_update(&base, &backup_path).await?;
+                return Err(e).classify(ClientError::Install);
+            }
+        }
+
+        if bootstrap {
+            self.enter_phase(UpdatePhase::PostInstall);
+            updater.run_bootstrap_command()?;
         }
 
         // 5. 버전 파일 업데이트
@@ -75,88 +576,1164 @@ impl PollingDaemon {
 
         // 6. 서비스 재시작
         tracing::info!("Restarting service...");
-        if let Err(e) = self.updater.restart_service() {
+        self.enter_phase(UpdatePhase::Restart);
+        if let Err(e) = off_runtime(&updater, |updater| updater.restart_service()).await {
             tracing::error!("Restart failed: {}", e);
-            if !backup_path.is_empty() {
-                tracing::info!("Attempting rollback...");
-                self.updater.rollback(&backup_path)?;
+            if !fsfault::rollback_blocked(&e) && self.rollback_failed_update(&base, &backup_path).await? {
                 self.write_current_version(&current_version)?;
             }
-            return Err(e);
+            return Err(e).classify(ClientError::Install);
         }
 
         // 7. 헬스 체크
         tracing::info!("Running health check...");
-        match self.updater.health_check() {
-            Ok(true) => {
-                tracing::info!("Health check passed ✓");
-            }
-            Ok(false) | Err(_) => {
-                tracing::error!("Health check failed!");
-                if !backup_path.is_empty() {
-                    tracing::info!("Attempting rollback...");
-                    self.updater.rollback(&backup_path)?;
+        self.enter_phase(UpdatePhase::HealthCheck);
+        // 실패한 프로브와 사유(또는 기한 초과 단계)를 그대로 보고
+        if let Err(e) = off_runtime(&updater, move |updater| updater.health_check(&health_probes)).await {
+            tracing::error!("{}", e);
+            if self.rollback_failed_update(&base, &backup_path).await? {
+                self.write_current_version(&current_version)?;
+            }
+            return Err(e).classify(ClientError::HealthCheck);
+        }
+        tracing::info!("Health check passed ✓");
+
+        // 업데이트 후 명령 (rollback_on_failure=true일 때만 실패를 롤백, 아니면 결과 보고에 남김)
+        let mut post_update_error = None;
+        if let Err(e) = updater.run_post_update_script(&current_version, target_version) {
+            if base.config().rollback_on_failure == Some(true) {
+                tracing::error!("{}", e);
+                if self.rollback_failed_update(&base, &backup_path).await? {
                     self.write_current_version(&current_version)?;
                 }
-                anyhow::bail!("Health check failed after update");
+                return Err(e).classify(ClientError::Install);
             }
+            tracing::warn!("{}; keeping {}", e, target_version);
+            post_update_error = Some(e.to_string());
         }
 
+        // 8. 설치 상태 기록 (남아있는 스테이징은 폐기, 첫 설치는 건너뛴 백업이 없음)
+        let mut installed_state = if bootstrap {
+            installed_state
+        } else {
+            installed_state.record_skipped_backup(&self.config, &backup_path, &current_version)
+        };
+        installed_state.install_strategy = *self.install_strategy.lock().unwrap();
+        installed_state.save(&self.config)?;
+        base.clear_staging()?;
+
         tracing::info!("Update completed successfully: {}", target_version);
+        Ok(UpdateOutcome::Installed { post_update_error })
+    }
+
+    /// 실패한 업데이트를 백업으로 롤백. 롤백했으면 true
+    ///
+    /// 백업이 없거나(첫 설치, 백업 생략) 서버 설정의 rollback_on_failure가 꺼져 있으면 설치된 상태로 둔다.
+    async fn rollback_failed_update(&self, updater: &Updater, backup_path: &str) -> Result<bool> {
+        if backup_path.is_empty() {
+            return Ok(false);
+        }
+        if updater.config().rollback_on_failure == Some(false) {
+            tracing::warn!("Rollback disabled by server config (rollback_on_failure=false), leaving the new version in place");
+            return Ok(false);
+        }
+        tracing::info!("Attempting rollback...");
+        let backup_path = backup_path.to_string();
+        off_runtime(updater, move |updater| updater.rollback(&backup_path))
+            .await
+            .classify(ClientError::RollbackFailed)?;
+        Ok(true)
+    }
+
+    /// 지금 적용 중인 설정 (환경 변수 설정 위에 서버 설정)
+    fn current_config(&self) -> Config {
+        self.effective.lock().unwrap().clone()
+    }
+
+    /// 지금 적용 중인 설정의 업데이터
+    fn updater(&self) -> Updater {
+        Updater::new(self.current_config())
+    }
+
+    /// 설치된 버전보다 낮은 타겟 거부 (배포의 allow_downgrade나 DM_ALLOW_DOWNGRADE면 허용)
+    fn check_downgrade(&self, offer: &CheckinResponse) -> Result<()> {
+        downgrade::check(
+            self.read_current_version().as_deref(),
+            offer.target_version.as_deref().unwrap_or("unknown"),
+            offer.allow_downgrade || self.config.allow_downgrade,
+        )
+    }
+
+    /// 스테이징 실행 (다운로드, 검증, 추출까지만 수행)
+    async fn perform_stage(&self, offer: &CheckinResponse, deadline: Deadline) -> Result<()> {
+        let target_version = offer.target_version.as_deref().unwrap_or("unknown");
+        let checksum = offer.checksum.as_deref().unwrap_or("");
+        self.check_downgrade(offer)?;
+
+        let mut state = LocalState::load(&self.config.service_dir);
+        if state.has_staged(checksum) {
+            tracing::info!("Artifact for {} is already staged", target_version);
+            return Ok(());
+        }
+
+        tracing::info!("Staging update: {}", target_version);
+        let (scripts, artifact) = self
+            .within_deadline(deadline, async {
+                let scripts = self.fetch_install_scripts(offer).await?;
+
+                // 1. 아티팩트 다운로드
+                self.enter_phase(UpdatePhase::Download);
+                let artifact = self.until_superseded(offer, self.download_artifact(offer)).await?;
+
+                // 2. 체크섬 검증
+                self.enter_phase(UpdatePhase::Verify);
+                self.verify_artifact(&artifact, checksum)?;
+                tracing::info!("Checksum verified ✓");
+                self.verify_signature(&artifact, offer)?;
+                self.updater().validate_artifact(&artifact.path)?;
+                self.ensure_still_targeted(offer).await?;
+                Ok((scripts, artifact))
+            })
+            .await?;
+
+        // 3. 스테이징 디렉토리에 추출
+        self.enter_phase(UpdatePhase::Install);
+        let staged_path = self
+            .updater()
+            .with_deadline(deadline)
+            .stage(&artifact.path, target_version)?;
+
+        // 4. 스테이징 상태 기록
+        state.staged = Some(StagedUpdate {
+            version: target_version.to_string(),
+            artifact_checksum: checksum.to_string(),
+            path: staged_path.to_string_lossy().to_string(),
+            staged_at: chrono::Utc::now(),
+            build_info: offer.build_info.clone().filter(|b| !b.is_empty()),
+            scripts: Some(scripts).filter(|s| !s.is_empty()),
+        });
+        state.save(&self.config)?;
+
+        tracing::info!("Update staged: {} (awaiting activation)", target_version);
         Ok(())
     }
 
-    /// 메인 Polling 루프
-    pub async fn run(&self) -> Result<()> {
-        tracing::info!("🦊 Sam DM Client starting...");
-        tracing::info!("Server: {}", self.config.server_url);
+    /// 디스크 이미지 업데이트 (비활성 슬롯에 쓰기 → 재부팅 기록 → 부트 슬롯 전환 → 재부팅)
+    ///
+    /// 결과는 재부팅 후 새 슬롯에서 보고한다 (`check_pending_image`, `confirm_image_update`).
+    /// 부트 슬롯을 바꾸기 전에 실패하면 현재 슬롯은 건드리지 않은 상태로 남는다.
+    async fn perform_image_update(&self, offer: &CheckinResponse, deadline: Deadline) -> Result<()> {
+        let target_version = offer.target_version.as_deref().unwrap_or("unknown");
+        let checksum = offer.checksum.as_deref().unwrap_or("");
+        let ab = self.config.ab.as_ref().ok_or_else(|| {
+            ClientError::Unsupported(format!(
+                "Version {} is a disk image but DM_AB_SLOTS is not configured",
+                target_version
+            ))
+        })?;
+        ab.validate().classify(ClientError::Config)?;
+        self.check_downgrade(offer)?;
+        if offer.scripts.as_ref().is_some_and(|s| !s.is_empty()) {
+            anyhow::bail!(ClientError::Unsupported(format!(
+                "Version {} has install scripts, which image updates do not support",
+                target_version
+            )));
+        }
+        let mut state = LocalState::load(&self.config.service_dir);
+        if let Some(pending) = &state.pending_image {
+            anyhow::bail!(ClientError::Locked(format!(
+                "Image update to {} is still awaiting reboot",
+                pending.version
+            )));
+        }
+
+        let booted = ab.booted_slot()?;
+        let target = ab.other_slot(booted)?;
+        let current_version = self.read_current_version();
+        tracing::info!(
+            "Starting image update: {} -> {} (slot {} -> {})",
+            current_version.as_deref().unwrap_or("unknown"),
+            target_version,
+            booted.name,
+            target.name
+        );
+
+        let image = self
+            .within_deadline(deadline, async {
+                // 1. 아티팩트 다운로드 (메모리 대신 다운로드 디렉토리의 임시 파일로)
+                tracing::info!("Downloading image...");
+                self.enter_phase(UpdatePhase::Download);
+                let image = self.until_superseded(offer, self.download_artifact(offer)).await?;
+
+                // 2. 체크섬 검증
+                tracing::info!("Verifying checksum...");
+                self.enter_phase(UpdatePhase::Verify);
+                self.verify_artifact(&image, checksum)?;
+                tracing::info!("Checksum verified ✓");
+                self.verify_signature(&image, offer)?;
+                self.ensure_still_targeted(offer).await?;
+                Ok(image)
+            })
+            .await?;
+        let actual = image.checksum.clone();
+
+        // 3. 비활성 슬롯에 쓰기 (현재 슬롯과 부트 플래그는 그대로)
+        tracing::info!("Writing image to slot {} ({})...", target.name, target.device);
+        self.enter_phase(UpdatePhase::Install);
+        deadline.check(UpdatePhase::Install)?;
+        let written = SlotDevice::new(&target.device).write_image(&image.path)?;
+        drop(image);
+        tracing::info!("Image written to slot {} ({} bytes, read back ✓)", target.name, written.bytes);
+
+        // 4. 재부팅 전후를 잇는 기록을 전환보다 먼저 남김
+        state.pending_image = Some(PendingImage {
+            version: target_version.to_string(),
+            artifact_checksum: actual,
+            build_info: offer.build_info.clone().filter(|b| !b.is_empty()),
+            from_version: current_version,
+            from_slot: booted.name.clone(),
+            to_slot: target.name.clone(),
+            boot_id: ab.boot_id(),
+            written_at: chrono::Utc::now(),
+        });
+        state.save(&self.config)?;
+
+        // 5. 다음 부팅 슬롯 전환
+        let updater = self.updater().with_deadline(deadline);
+        let switch_command = ab.switch_command.as_deref().unwrap_or_default();
+        self.enter_phase(UpdatePhase::SwitchSlot);
+        if let Err(e) = abslot::run_slot_command(&updater, UpdatePhase::SwitchSlot, switch_command, target) {
+            self.clear_pending_image();
+            return Err(e).classify(ClientError::Install);
+        }
+
+        // 6. 재부팅 (실패하면 부트 슬롯을 되돌림)
+        tracing::info!("Rebooting into slot {}...", target.name);
+        self.enter_phase(UpdatePhase::Reboot);
+        *self.reboot_requested.lock().unwrap() = true;
+        if let Err(e) = abslot::run_slot_command(&updater, UpdatePhase::Reboot, &ab.reboot_command, target) {
+            tracing::error!("Reboot failed: {}; switching back to slot {}", e, booted.name);
+            if let Err(e) =
+                abslot::run_slot_command(&self.updater(), UpdatePhase::SwitchSlot, switch_command, booted)
+            {
+                tracing::error!("Failed to switch back to slot {}: {}", booted.name, e);
+            }
+            *self.reboot_requested.lock().unwrap() = false;
+            self.clear_pending_image();
+            return Err(e).classify(ClientError::Install);
+        }
+        Ok(())
+    }
+
+    /// 재부팅 기록 삭제
+    fn clear_pending_image(&self) {
+        let mut state = LocalState::load(&self.config.service_dir);
+        state.pending_image = None;
+        if let Err(e) = state.save(&self.config) {
+            tracing::error!("Failed to clear pending image update: {}", e);
+        }
+    }
+
+    /// 진행 중인 이미지 업데이트 확인 (체크인 전)
+    ///
+    /// 이전 슬롯으로 돌아왔으면(워치독 폴백 등) 여기서 실패를 보고하고 기록을 지운다.
+    async fn check_pending_image(&self) -> ImageCheck {
+        let Some(pending) = LocalState::load(&self.config.service_dir).pending_image else {
+            return ImageCheck::Idle;
+        };
+        let Some(ab) = &self.config.ab else {
+            tracing::error!(
+                "Image update to {} is pending but DM_AB_SLOTS is no longer set; discarding it",
+                pending.version
+            );
+            self.clear_pending_image();
+            return ImageCheck::Idle;
+        };
+
+        let rebooted = match (ab.boot_id(), &pending.boot_id) {
+            (Some(now), Some(then)) => &now != then,
+            // 부팅 ID가 없으면 재부팅을 요청한 프로세스인지로 판단
+            _ => !*self.reboot_requested.lock().unwrap(),
+        };
+        if !rebooted {
+            tracing::info!("Image update to {} is waiting for reboot", pending.version);
+            return ImageCheck::AwaitingReboot;
+        }
+
+        let booted = match ab.booted_slot() {
+            Ok(slot) => slot.name.clone(),
+            Err(e) => {
+                tracing::error!("Cannot determine booted slot for pending image update: {}", e);
+                return ImageCheck::AwaitingReboot;
+            }
+        };
+        if booted == pending.to_slot {
+            tracing::info!("Booted into slot {}; confirming {} after checkin", booted, pending.version);
+            return ImageCheck::Confirming(Box::new(pending));
+        }
+
+        let message = format!(
+            "Device came back up on slot {} instead of {}; image update to {} was not confirmed",
+            booted, pending.to_slot, pending.version
+        );
+        tracing::error!("{}", message);
+        self.clear_pending_image();
+        let mut result = UpdateResultRequest::failure(&pending.version, &message);
+        result.verified_checksum = Some(pending.artifact_checksum);
+        result.failure_reason = Some("boot_fallback".to_string());
+        self.report_update_result(result).await;
+        ImageCheck::Idle
+    }
+
+    /// 새 슬롯 확정 후 결과 보고 (재부팅 후 첫 체크인 성공 시)
+    async fn confirm_image_update(&self, pending: PendingImage) {
+        let result = match self.finish_image_update(&pending) {
+            Ok(()) => {
+                tracing::info!("Image update confirmed: {} (slot {})", pending.version, pending.to_slot);
+                self.check_state_integrity(false);
+                let mut result = UpdateResultRequest::success(&pending.version);
+                result.phases.healthy_at = Some(chrono::Utc::now());
+                result
+            }
+            Err(e) => {
+                tracing::error!("Image update failed: {}", e);
+                UpdateResultRequest::failure(&pending.version, &e.to_string())
+            }
+        };
+        let result = UpdateResultRequest {
+            verified_checksum: Some(pending.artifact_checksum),
+            ..result
+        };
+        self.report_update_result(result).await;
+    }
+
+    /// 새 슬롯 확정 명령 실행 후 설치 상태 기록
+    ///
+    /// 확정하지 못하면 다음 부팅이 이전 슬롯으로 돌아가도록 되돌린다.
+    fn finish_image_update(&self, pending: &PendingImage) -> Result<()> {
+        let ab = self.config.ab.as_ref().context("DM_AB_SLOTS is not configured")?;
+        let slot = ab.slot(&pending.to_slot)?;
+        if let Some(confirm_command) = &ab.confirm_command {
+            if let Err(e) =
+                abslot::run_slot_command(&self.updater(), UpdatePhase::ConfirmSlot, confirm_command, slot)
+            {
+                let switch_command = ab.switch_command.as_deref().unwrap_or_default();
+                if let Err(e) = ab.slot(&pending.from_slot).and_then(|from| {
+                    abslot::run_slot_command(&self.updater(), UpdatePhase::SwitchSlot, switch_command, from)
+                }) {
+                    tracing::error!("Failed to switch back to slot {}: {}", pending.from_slot, e);
+                }
+                self.clear_pending_image();
+                return Err(e);
+            }
+        }
+
+        self.write_current_version(&pending.version)?;
+        let state = LocalState::load(&self.config.service_dir);
+        LocalState::installed(&pending.version, &pending.artifact_checksum, pending.build_info.clone())
+            .keep_server_config(&state)
+            .save(&self.config)
+    }
+
+    /// 업데이트 중단 사유 (파일시스템 장애, 롤백 실패)
+    fn degraded(&self) -> Option<&'static str> {
+        *self.degraded.lock().unwrap()
+    }
+
+    /// 업데이트 명령 실행 (보고할 결과가 없으면 None)
+    ///
+    /// 서버 체크인 응답과 static 모드 manifest가 같은 파이프라인을 사용한다.
+    async fn execute_action(&self, response: &CheckinResponse) -> Option<Result<UpdateResultRequest>> {
+        let target = response.target_version.as_deref().unwrap_or("unknown");
+
+        if let Some(reason) = self.degraded() {
+            if matches!(response.action.as_str(), "update" | "stage" | "activate") {
+                tracing::warn!(
+                    "Skipping {} of {}: device degraded ({}); restart the daemon once the device is healthy",
+                    response.action,
+                    target,
+                    reason
+                );
+                return None;
+            }
+        }
+
+        if let Some(pause) = control::current_pause(&self.config.control_dir) {
+            if response.urgent && self.config.urgent_during_pause {
+                tracing::warn!(
+                    "Daemon paused{}, but {} is a security update; installing (DM_URGENT_DURING_PAUSE)",
+                    pause_suffix(&pause),
+                    target
+                );
+            } else if matches!(response.action.as_str(), "update" | "stage" | "activate") {
+                tracing::warn!(
+                    "Skipping {} of {}: daemon paused{}",
+                    response.action,
+                    target,
+                    pause_suffix(&pause)
+                );
+                return None;
+            }
+        }
+
+        // 같은 서비스 디렉토리를 바꾸는 다른 프로세스(dm-client apply, rollback)가 끝나면 다음 폴링에서 다시 시도
+        let _lock = if matches!(response.action.as_str(), "update" | "stage" | "activate" | "unstage") {
+            match UpdateLock::acquire(&self.config.service_dir) {
+                Ok(lock) => Some(lock),
+                Err(e) => {
+                    tracing::warn!("Skipping {} of {}: {}", response.action, target, e);
+                    return None;
+                }
+            }
+        } else {
+            None
+        };
+
+        *self.verified_checksum.lock().unwrap() = None;
+        *self.artifact_source.lock().unwrap() = None;
+        *self.phase_times.lock().unwrap() = PhaseTimes::default();
+        *self.bootstrap.lock().unwrap() = false;
+        *self.install_strategy.lock().unwrap() = None;
+        let deadline = Deadline::after(Duration::from_secs(self.config.update_timeout_secs));
+        let result = match response.action.as_str() {
+            "update" if response.is_image() => {
+                tracing::info!("Image update available: {}", target);
+                match self.perform_image_update(response, deadline).await {
+                    // 결과는 재부팅 후 새 슬롯에서 보고
+                    Ok(()) => return None,
+                    Err(e) => Err(e),
+                }
+            }
+            "update" => {
+                tracing::info!("Update available: {}", target);
+                let result = self.perform_update(response, deadline).await;
+                // 실패한 첫 설치는 롤백 대신 비워서 다음 시도도 첫 설치로
+                if result.is_err() && *self.bootstrap.lock().unwrap() {
+                    if let Err(e) = self.updater().clear_first_install() {
+                        tracing::warn!("Failed to clear the failed bootstrap install: {}", e);
+                    }
+                }
+                result.map(|outcome| {
+                    let mut result = UpdateResultRequest::success(target);
+                    match outcome {
+                        UpdateOutcome::AlreadyInstalled => {
+                            result.skipped_reason = Some("already_installed".to_string())
+                        }
+                        UpdateOutcome::Installed { post_update_error } => result.error_message = post_update_error,
+                    }
+                    result
+                })
+            }
+            "stage" => self
+                .perform_stage(response, deadline)
+                .await
+                .map(|_| UpdateResultRequest::staged(target)),
+            "activate" => {
+                tracing::info!("Activation requested: {}", target);
+                // 재시작 후 헬스 체크 대기가 런타임을 막지 않도록 블로킹 스레드에서 실행
+                let (config, updater) = (self.config.clone(), self.updater());
+                tokio::task::spawn_blocking(move || staging::activate_staged(&config, &updater))
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()))
+                    .map(|version| UpdateResultRequest::success(&version))
+            }
+            "unstage" => {
+                match staging::discard_staged(&self.config, &self.updater()) {
+                    Ok(_) => tracing::info!("Staged update {} discarded", target),
+                    Err(e) => tracing::error!("Failed to discard staged update: {}", e),
+                }
+                return None;
+            }
+            _ => {
+                tracing::debug!("No update required");
+                return None;
+            }
+        };
+
+        // 새로 기록한 설치 상태를 수정 비교 기준으로 갱신
+        if result.is_ok() {
+            self.check_state_integrity(false);
+        }
+
+        Some(result.map(|mut result| {
+            result.verified_checksum = self.verified_checksum.lock().unwrap().clone();
+            result.artifact_source = self.artifact_source.lock().unwrap().clone();
+            result.phases = self.phase_times.lock().unwrap().clone();
+            result.install_strategy = self.reported_install_strategy();
+            result.bootstrap = *self.bootstrap.lock().unwrap();
+            if !result.staged {
+                result.phases.healthy_at = Some(chrono::Utc::now());
+            }
+            result
+        }))
+    }
+
+    /// 결과 보고에 넣을 설치 방법
+    fn reported_install_strategy(&self) -> Option<String> {
+        self.install_strategy.lock().unwrap().map(|s| s.as_str().to_string())
+    }
+
+    /// 실패 결과 생성 (파일시스템 장애와 롤백 실패는 degraded 전환)
+    fn failure_result(&self, target: &str, e: &anyhow::Error) -> UpdateResultRequest {
+        tracing::error!("Update failed: {}", e);
+        let recovery = self.recover_from(e);
+        let mut result = UpdateResultRequest::failure(target, &e.to_string());
+        result.verified_checksum = self.verified_checksum.lock().unwrap().clone();
+        result.artifact_source = self.artifact_source.lock().unwrap().clone();
+        result.phases = self.phase_times.lock().unwrap().clone();
+        result.install_strategy = self.reported_install_strategy();
+        result.bootstrap = *self.bootstrap.lock().unwrap();
+        if let Some(timed_out) = e.downcast_ref::<UpdateTimedOut>() {
+            tracing::warn!("Update abandoned in {} phase; resuming normal polling", timed_out.phase);
+            result.failure_reason = Some("timed_out".to_string());
+        } else if e.downcast_ref::<Superseded>().is_some() {
+            result.failure_reason = Some("superseded".to_string());
+        } else if e.downcast_ref::<DowngradeRefused>().is_some() {
+            result.failure_reason = Some("downgrade_refused".to_string());
+        } else if e.downcast_ref::<ProductMismatch>().is_some() {
+            result.failure_reason = Some("product_mismatch".to_string());
+        } else if e.downcast_ref::<InvalidSignature>().is_some() {
+            result.failure_reason = Some("signature_invalid".to_string());
+        } else if e.downcast_ref::<InsufficientSpace>().is_some() {
+            result.failure_reason = Some("insufficient_disk_space".to_string());
+        } else if let Some(ClientError::HealthCheck(_)) = ClientError::of(e) {
+            // 프로브 사유(연결 거부 등)가 메시지 분류에서 다른 원인으로 보이지 않도록
+            result.failure_reason = Some("health_check".to_string());
+        } else if let Some(fault) = FsFault::classify(e) {
+            tracing::error!(
+                "Update aborted: {}; suppressing further updates until restart",
+                fault.message()
+            );
+            *self.degraded.lock().unwrap() = Some(fault.message());
+            result.error_message = Some(format!("{}: {}", fault.message(), e));
+            result.failure_reason = Some(fault.code().to_string());
+        } else if recovery == Recovery::GiveUp {
+            tracing::error!("Rollback failed, service state unknown; suppressing further updates until restart");
+            *self.degraded.lock().unwrap() = Some(ROLLBACK_FAILED);
+            result.failure_reason = Some("rollback_failed".to_string());
+        }
+        result
+    }
+
+    /// 실패 분류에 따라 다음 폴링 주기 결정 (분류되지 않은 실패는 그대로 재시도)
+    fn recover_from(&self, e: &anyhow::Error) -> Recovery {
+        let recovery = ClientError::of(e).map_or(Recovery::Retry, |c| c.recovery());
+        let mut backoff = self.backoff.lock().unwrap();
+        if recovery == Recovery::Backoff {
+            *backoff = (*backoff + 1).min(MAX_BACKOFF_STEPS);
+            drop(backoff);
+            tracing::warn!(
+                "Not retrying until fixed; next poll in {}s",
+                self.next_poll_interval().as_secs()
+            );
+        } else {
+            *backoff = 0;
+        }
+        recovery
+    }
+
+    /// 성공하면 폴링 주기 복구
+    fn reset_backoff(&self) {
+        *self.backoff.lock().unwrap() = 0;
+    }
+
+    /// 다음 폴링까지의 간격 (백오프 중이면 두 배씩, 최대 MAX_BACKOFF_SECS)
+    ///
+    /// 체크인 자체가 연속으로 실패하면 실패할 때마다 두 배씩, 최대 MAX_CHECKIN_RETRY_SECS.
+    /// 서버 힌트가 있으면 DM_POLL_HINT_MIN_SECS~DM_POLL_HINT_MAX_SECS로 제한해 그 값을 쓴다.
+    fn next_poll_interval(&self) -> Duration {
+        if let Some(hint) = *self.poll_hint.lock().unwrap() {
+            let clamped = hint.max(self.config.poll_hint_min_secs).min(self.config.poll_hint_max_secs);
+            return Duration::from_secs(clamped);
+        }
+        let base = self.config.poll_interval_secs;
+        let steps = *self.backoff.lock().unwrap();
+        let failures = *self.checkin_failures.lock().unwrap();
+        if steps == 0 && failures > 0 {
+            let retry = base.saturating_mul(1 << failures).min(MAX_CHECKIN_RETRY_SECS);
+            return Duration::from_secs(retry.max(base));
+        }
+        if steps == 0 {
+            if *self.urgent.lock().unwrap() {
+                return Duration::from_secs(base.min(self.config.urgent_poll_interval_secs));
+            }
+            return Duration::from_secs(base);
+        }
+        let backed_off = base.saturating_mul(1 << steps).min(MAX_BACKOFF_SECS);
+        Duration::from_secs(backed_off.max(base))
+    }
+
+    /// 체크인 결과 기록 (연속 실패 횟수로 다음 폴링 간격을 늘림)
+    fn record_checkin(&self, ok: bool) {
+        let mut failures = self.checkin_failures.lock().unwrap();
+        *failures = if ok { 0 } else { (*failures + 1).min(MAX_BACKOFF_STEPS) };
+    }
+
+    /// 다음 폴링까지 실제로 기다릴 시간 (next_poll_interval에 ±DM_POLL_JITTER_PCT% 무작위 편차)
+    ///
+    /// 같은 주기의 장비들이 한꺼번에 체크인하지 않도록 흩뜨린다. 서버가 요청한 대기 시간은 줄이지 않는다.
+    pub fn next_poll_wait(&self) -> Duration {
+        let interval = self.next_poll_interval();
+        let pct = f64::from(self.config.poll_jitter_pct.min(100)) / 100.0;
+        if pct == 0.0 {
+            return interval;
+        }
+        let low = if self.poll_hint.lock().unwrap().is_some() { 0.0 } else { -pct };
+        interval.mul_f64(1.0 + rand::thread_rng().gen_range(low..=pct))
+    }
+
+    /// 서버 명령 처리 및 결과 보고
+    async fn handle_action(&self, response: &CheckinResponse) {
+        if let Some(action_id) = response.action_id.as_deref() {
+            if response.action == "survey" {
+                self.run_survey(action_id, &response.survey).await;
+            } else {
+                self.run_queued_action(&response.action, action_id).await;
+            }
+            return;
+        }
+        let target = response.target_version.as_deref().unwrap_or("unknown");
+
+        // 설치는 끝났지만 서버가 아직 결과를 받지 못해 같은 업데이트를 다시 내린 경우
+        if response.action == "update" {
+            let pending = PendingResult::load(&self.config.service_dir);
+            if pending.as_ref().and_then(|p| p.installed_version(&self.config.service_dir)) == Some(target) {
+                tracing::info!("Skipping update to {}: already installed, result report pending", target);
+                return;
+            }
+        }
+
+        let outcome = self.execute_action(response).await;
+        if outcome.is_some() {
+            *self.repoll.lock().unwrap() = true;
+        }
+        match outcome {
+            Some(Ok(result)) => {
+                self.reset_backoff();
+                self.report_update_result(result).await;
+            }
+            Some(Err(e)) => {
+                let result = self.failure_result(target, &e);
+                self.report_update_result(result).await;
+                // 새 타겟으로 바뀌었으면 다음 폴링을 기다리지 않고 바로 진행
+                if let Some(next) = e.downcast::<Superseded>().ok().and_then(|s| s.next) {
+                    Box::pin(self.handle_action(&next)).await;
+                }
+            }
+            None => self.reset_backoff(),
+        }
+    }
+
+    /// 업데이트 결과 보고 (보내지 못하면 저장해 두고 다음 체크인 전에 다시 보냄)
+    async fn report_update_result(&self, result: UpdateResultRequest) {
+        let idempotency_key = uuid::Uuid::new_v4().to_string();
+        let Err(e) = self.api.report_result_with_key(&result, &idempotency_key).await else {
+            return;
+        };
+        tracing::error!(
+            "Failed to report {} of {}: {}; retrying before the next checkin",
+            if result.success { "success" } else { "failure" },
+            result.version,
+            e
+        );
+        if let Some(previous) = PendingResult::load(&self.config.service_dir) {
+            tracing::warn!(
+                "Dropping the unreported result for {} in favour of {}",
+                previous.result.version,
+                result.version
+            );
+        }
+        if let Err(e) = PendingResult::new(result, &idempotency_key).save(&self.config.service_dir) {
+            tracing::error!("Failed to save the unreported result: {}", e);
+        }
+    }
+
+    /// 저장해 둔 업데이트 결과 재전송 (서버가 받았거나 받을 수 없는 보고로 거부하면 삭제)
+    async fn retry_pending_result(&self) {
+        let Some(pending) = PendingResult::load(&self.config.service_dir) else {
+            return;
+        };
+        let version = &pending.result.version;
+        match self
+            .api
+            .report_result_with_key(&pending.result, &pending.idempotency_key)
+            .await
+        {
+            Ok(()) => tracing::info!(
+                "Reported the result for {} (first attempt failed at {})",
+                version,
+                pending.failed_at
+            ),
+            Err(e) if matches!(ClientError::of(&e), Some(ClientError::Config(_))) => {
+                tracing::warn!("Server rejected the saved result for {}; dropping it: {}", version, e)
+            }
+            Err(e) => {
+                tracing::warn!("Still cannot report the result for {}: {}", version, e);
+                return;
+            }
+        }
+        if let Err(e) = PendingResult::clear(&self.config.service_dir) {
+            tracing::error!("Failed to remove the reported result: {}", e);
+        }
+    }
+
+    /// 서버 작업 대기열의 재시작/롤백 실행 후 결과 보고 (보고 전까지 서버는 다음 작업을 보내지 않음)
+    async fn run_queued_action(&self, action: &str, action_id: &str) {
+        tracing::info!("Server requested {} (action {})", action, action_id);
+        let result = match action {
+            "restart" => off_runtime(&self.updater(), |updater| updater.restart_service()).await,
+            "rollback" => self.rollback_latest().await.map(|version| {
+                tracing::info!("Rolled back to {}", version);
+                *self.repoll.lock().unwrap() = true;
+                self.check_state_integrity(false);
+            }),
+            other => Err(ClientError::Unsupported(format!("Unsupported server action: {}", other)).into()),
+        };
+        if let Err(e) = &result {
+            tracing::error!("{} failed: {}", action, e);
+        }
+
+        let report = ActionResultRequest {
+            action_id: action_id.to_string(),
+            success: result.is_ok(),
+            error_message: result.err().map(|e| e.to_string()),
+        };
+        if let Err(e) = self.api.report_action_result(&report).await {
+            tracing::error!("Failed to report {} result: {}", action, e);
+        }
+    }
+
+    /// 서버가 요청한 조사 항목 수집 후 업로드 (업로드가 거부되면 실패로 보고해 다음 작업으로 넘어가게 함)
+    async fn run_survey(&self, action_id: &str, commands: &[String]) {
+        tracing::info!("Server requested survey {:?} (action {})", commands, action_id);
+        let report = survey::collect(commands).await;
+        let request = SurveyResultRequest {
+            action_id: action_id.to_string(),
+            report,
+        };
+        let Err(e) = self.api.report_survey_result(&request).await else {
+            return;
+        };
+        tracing::error!("Failed to upload survey result: {}", e);
+
+        let report = ActionResultRequest {
+            action_id: action_id.to_string(),
+            success: false,
+            error_message: Some(format!("Survey upload failed: {}", e)),
+        };
+        if let Err(e) = self.api.report_action_result(&report).await {
+            tracing::error!("Failed to report survey failure: {}", e);
+        }
+    }
+
+    /// 최신 백업으로 롤백 후 헬스 체크 (`dm-client rollback --latest`와 같은 대상). 복원한 버전 반환
+    ///
+    /// 복원, 재시작, 헬스 체크는 업데이트와 같이 블로킹 스레드에서 실행하고 끝날 때까지 업데이트 잠금을 잡는다.
+    async fn rollback_latest(&self) -> Result<String> {
+        let _lock = UpdateLock::acquire(&self.config.service_dir)?;
+        let state = LocalState::load(&self.config.service_dir);
+        let backups = backup::list_backups(
+            Path::new(&self.config.backup_dir),
+            state.restore_point_name().as_deref(),
+        );
+        let target = backup::rollback_target(&backups, None, state.no_backup.as_ref())
+            .classify(ClientError::RollbackFailed)?;
+        let updater = self.updater();
+        let health_probes = updater.health_probes()?;
+        let backup_path = target.path.to_string_lossy().to_string();
+        off_runtime(&updater, move |updater| updater.rollback(&backup_path))
+            .await
+            .classify(ClientError::RollbackFailed)?;
+        off_runtime(&updater, move |updater| updater.health_check(&health_probes))
+            .await
+            .classify(ClientError::HealthCheck)?;
+        Ok(target.version.clone())
+    }
+
+    /// 서버가 지정한 설정을 환경 변수 설정 위에 적용 (다음 업데이트부터, 재시작 없이)
+    ///
+    /// 매번 환경 변수 설정에서 다시 만들므로 서버에서 지운 항목은 로컬 값으로 돌아간다.
+    fn apply_pushed_config(&self, pushed: &PushedConfig) {
+        let mut effective = self.config.clone();
+        let applied = pushed.apply_to(&mut effective);
+        if applied.is_empty() {
+            tracing::info!("Server config has no overrides for local settings");
+        } else {
+            tracing::info!("Server config overrides: {}", applied.join(", "));
+        }
+        *self.effective.lock().unwrap() = effective;
+    }
+
+    /// 서버가 지정한 설정을 로컬 상태에 기록
+    ///
+    /// 역할은 오프라인 USB 번들 선택용, 백업 설정은 서버 연결 없이 실행되는 롤백에서도 쓰인다.
+    fn save_pushed_config(&self, pushed: &PushedConfig) -> bool {
+        let mut state = LocalState::load(&self.config.service_dir);
+        let health_checks = pushed.health_probes();
+        if state.role == pushed.role
+            && state.product == pushed.product
+            && state.backup_exclude == pushed.backup_exclude
+            && state.preserve_paths == pushed.preserve_paths
+            && state.rollback_regenerate == pushed.rollback_regenerate
+            && state.health_checks == health_checks
+        {
+            return true;
+        }
+
+        if state.role != pushed.role {
+            tracing::info!("Server assigned role: {}", pushed.role.as_deref().unwrap_or("(none)"));
+        }
+        if state.product != pushed.product {
+            tracing::info!("Server assigned product: {}", pushed.product.as_deref().unwrap_or("(none)"));
+        }
+        if state.backup_exclude != pushed.backup_exclude || state.rollback_regenerate != pushed.rollback_regenerate {
+            tracing::info!(
+                "Server backup settings: exclude={:?}, rollback_regenerate={:?}",
+                pushed.backup_exclude,
+                pushed.rollback_regenerate
+            );
+        }
+        if state.preserve_paths != pushed.preserve_paths {
+            tracing::info!("Server preserve paths: {:?}", pushed.preserve_paths);
+        }
+        if state.health_checks != health_checks {
+            tracing::info!(
+                "Server health checks: {} probe(s)",
+                health_checks.as_ref().map_or(0, Vec::len)
+            );
+        }
+        state.role = pushed.role.clone();
+        state.product = pushed.product.clone();
+        state.health_checks = health_checks;
+        state.backup_exclude = pushed.backup_exclude.clone();
+        state.preserve_paths = pushed.preserve_paths.clone();
+        state.rollback_regenerate = pushed.rollback_regenerate;
+        if let Err(e) = state.save(&self.config) {
+            tracing::warn!("Failed to save server config: {}", e);
+            return false;
+        }
+        true
+    }
+
+    /// 파일시스템 점검 (읽기 전용/디스크 부족이면 업데이트 중단 상태로 시작)
+    fn probe_filesystem(&self) {
+        for dir in [&self.config.service_dir, &self.config.backup_dir] {
+            if let Some(fault) = fsfault::probe_writable(dir) {
+                tracing::error!("{} is not writable: {}; updates disabled", dir, fault.message());
+                *self.degraded.lock().unwrap() = Some(fault.message());
+                break;
+            }
+        }
+    }
+
+    /// 설치 상태 무결성 확인 (dm-client 밖에서 수정되었으면 true)
+    ///
+    /// 시작 시에는 서명되지 않은 기존 상태를 받아들여 서명하고, 이후 서명이 사라지면 수정으로 본다.
+    fn check_state_integrity(&self, startup: bool) -> bool {
+        let tampered = match state::check_integrity(&self.config) {
+            StateIntegrity::Valid => None,
+            StateIntegrity::Unverifiable => return false,
+            StateIntegrity::Unsigned if startup => {
+                if let Err(e) = state::adopt_unsigned(&self.config) {
+                    tracing::warn!("Failed to sign install state: {}", e);
+                }
+                None
+            }
+            StateIntegrity::Unsigned => Some("install state signature removed".to_string()),
+            StateIntegrity::Tampered(reason) => Some(reason),
+        };
+
+        let mut reported = self.state_tampered.lock().unwrap();
+        match &tampered {
+            None => {
+                if reported.take().is_some() {
+                    tracing::info!("Install state verified again");
+                }
+                *self.verified_state.lock().unwrap() = Some(LocalState::load(&self.config.service_dir));
+            }
+            Some(reason) if reported.as_deref() != Some(reason.as_str()) => {
+                let previous = self.verified_state.lock().unwrap().clone();
+                tracing::warn!(
+                    "Install state modified outside dm-client: {} (last verified: version {}, checksum {}); use `dm-client set-version` for manual recovery",
+                    reason,
+                    previous.as_ref().and_then(|s| s.version.as_deref()).unwrap_or("unknown"),
+                    previous.as_ref().and_then(|s| s.artifact_checksum.as_deref()).unwrap_or("unknown")
+                );
+                *reported = Some(reason.clone());
+            }
+            Some(_) => {}
+        }
+        tampered.is_some()
+    }
+
+    /// Static 모드 루프: manifest.json을 주기적으로 조회해 새 버전이면 설치 (보고 없음)
+    async fn run_static(&self, manifest_url: &str) -> Result<()> {
+        tracing::info!("🦊 Sam DM Client starting (static mode)...");
+        tracing::info!("Manifest: {}", manifest_url);
         tracing::info!("Poll interval: {}s", self.config.poll_interval_secs);
         tracing::info!("Service dir: {}", self.config.service_dir);
+        if self.config.update_public_key.is_none() {
+            static_mode::warn_unsigned(manifest_url);
+        }
+
+        self.probe_filesystem();
+        self.check_state_integrity(true);
 
         loop {
+            self.check_state_integrity(false);
             let current_version = self.read_current_version();
-            
             tracing::debug!(
-                "Checking in (current version: {})",
+                "Fetching manifest (current version: {})",
                 current_version.as_deref().unwrap_or("none")
             );
 
-            // 서버에 체크인
-            match self.api.checkin(current_version.as_deref(), "online").await {
-                Ok(response) => {
-                    if response.action == "update" {
-                        let target = response.target_version.as_deref().unwrap_or("unknown");
-                        let artifact_url = response.artifact_url.as_deref().unwrap_or("");
-                        let checksum = response.checksum.as_deref().unwrap_or("");
-
-                        tracing::info!("Update available: {}", target);
-
-                        match self.perform_update(target, artifact_url, checksum).await {
-                            Ok(()) => {
-                                // 성공 보고
-                                if let Err(e) = self.api.report_result(target, true, None).await {
-                                    tracing::error!("Failed to report success: {}", e);
-                                }
-                            }
-                            Err(e) => {
-                                // 실패 보고
-                                tracing::error!("Update failed: {}", e);
-                                if let Err(e2) = self.api.report_result(target, false, Some(&e.to_string())).await {
-                                    tracing::error!("Failed to report failure: {}", e2);
-                                }
-                            }
+            let manifest = self.api.fetch_manifest(manifest_url).await;
+            if !matches!(&manifest, Err(e) if e.is::<CircuitOpenError>()) {
+                self.record_checkin(manifest.is_ok());
+            }
+            let offer = match manifest {
+                Ok(manifest) => {
+                    static_mode::offer_from_manifest(manifest, manifest_url, current_version.as_deref())
+                }
+                Err(e) => Err(e),
+            };
+
+            match offer {
+                Ok(Some(offer)) => {
+                    let target = offer.target_version.as_deref().unwrap_or("unknown");
+                    match self.execute_action(&offer).await {
+                        Some(Ok(_)) => {
+                            tracing::info!("Static update to {} completed", target);
+                            self.reset_backoff();
                         }
-                    } else {
-                        tracing::debug!("No update required");
+                        Some(Err(e)) => {
+                            self.failure_result(target, &e);
+                        }
+                        None => self.reset_backoff(),
                     }
                 }
+                Ok(None) => {
+                    tracing::debug!("Up to date");
+                    self.reset_backoff();
+                }
                 Err(e) => {
-                    tracing::error!("Checkin failed: {}", e);
+                    if let Some(open) = e.downcast_ref::<CircuitOpenError>() {
+                        tracing::debug!("Skipping manifest fetch: {}", open);
+                    } else {
+                        tracing::error!("Manifest check failed: {}", e);
+                        self.recover_from(&e);
+                    }
                 }
             }
 
+            self.wait_next_poll().await;
+        }
+    }
+
+    /// 다음 폴링까지 대기 (`dm-client trigger-checkin` 요청 시 즉시 깨어남)
+    async fn wait_next_poll(&self) {
+        let started = tokio::time::Instant::now();
+        let wake_at = started + self.next_poll_wait();
+        // 서버 힌트는 이번 대기에만 적용
+        *self.poll_hint.lock().unwrap() = None;
+        while tokio::time::Instant::now() < wake_at {
+            if control::take_checkin_request(&self.config.control_dir) {
+                tracing::info!("Checkin requested, polling now");
+                break;
+            }
+            sleep(CONTROL_POLL_INTERVAL.min(wake_at - tokio::time::Instant::now())).await;
+        }
+        *self.last_poll_wait.lock().unwrap() = Some(started.elapsed().as_secs());
+    }
+
+    /// 메인 Polling 루프
+    pub async fn run(&self) -> Result<()> {
+        if let (DaemonMode::Static, Some(manifest_url)) = (self.config.mode, &self.config.manifest_url) {
+            return self.run_static(manifest_url).await;
+        }
+
+        tracing::info!("🦊 Sam DM Client starting...");
+        tracing::info!("Server: {}", self.config.server_url);
+        tracing::info!("Poll interval: {}s", self.config.poll_interval_secs);
+        tracing::info!("Service dir: {}", self.config.service_dir);
+
+        self.startup_checks();
+        if let Some(upgraded) = tls::https_upgrade(&self.config.server_url).await {
+            tracing::warn!(
+                "⚠️  Server also answers over HTTPS: set DM_SERVER_URL={} (and DM_REQUIRE_TLS=true)",
+                upgraded
+            );
+        }
+
+        let mut repolled = false;
+        loop {
+            self.poll_once().await;
+
+            // 업데이트나 롤백 직후에는 바로 한 번 더 체크인해 새 버전을 보고 (연속으로는 한 번만)
+            let repoll = std::mem::take(&mut *self.repoll.lock().unwrap());
+            if repoll && !repolled {
+                tracing::info!("Checking in again to report the new state");
+                repolled = true;
+                *self.last_poll_wait.lock().unwrap() = None;
+                continue;
+            }
+            repolled = false;
+
             // 다음 폴링까지 대기
-            sleep(Duration::from_secs(self.config.poll_interval_secs)).await;
+            self.wait_next_poll().await;
+        }
+    }
+
+    /// 시작 시 점검 (파일시스템 쓰기 가능 여부, 설치 상태 서명)
+    pub fn startup_checks(&self) {
+        self.probe_filesystem();
+        self.check_state_integrity(true);
+    }
+
+    /// 체크인 한 번 수행 후 서버 명령 처리 (업데이트 실행과 결과 보고까지)
+    pub async fn poll_once(&self) {
+        self.retry_pending_result().await;
+        let image = self.check_pending_image().await;
+        // 새 슬롯에서는 확정 전이라도 새 버전으로 체크인 (서버가 같은 업데이트를 다시 내리지 않도록)
+        let current_version = match &image {
+            ImageCheck::Confirming(pending) => Some(pending.version.clone()),
+            _ => self.read_current_version(),
+        };
+
+        tracing::debug!(
+            "Checking in (current version: {})",
+            current_version.as_deref().unwrap_or("none")
+        );
+
+        // 서버에 체크인
+        let state_tampered = self.check_state_integrity(false);
+        let local_state = LocalState::load(&self.config.service_dir);
+        let role = local_state.effective_role(&self.config);
+        let product = local_state.effective_product(&self.config);
+        let staged_version = local_state.staged.map(|s| s.version);
+        let status = if let ImageCheck::Confirming(_) = image {
+            "confirming"
+        } else if let ImageCheck::AwaitingReboot = image {
+            "rebooting"
+        } else if self.degraded().is_some() {
+            "degraded"
+        } else if control::current_pause(&self.config.control_dir).is_some() {
+            "paused"
+        } else if staged_version.is_some() {
+            "staged"
+        } else {
+            "online"
+        };
+
+        // 실제 적용 설정: 해시는 매번, 전체 설정은 변경 시에만 전송
+        // 백업 설정은 서버가 지정할 수 있으므로 실제 적용 값으로 보고 (config_drift 비교용)
+        let mut effective_config = self.current_config().effective();
+        let local_state = LocalState::load(&self.config.service_dir);
+        effective_config["product"] = serde_json::json!(local_state.effective_product(&self.config));
+        effective_config["backup_exclude"] =
+            serde_json::json!(local_state.effective_backup_exclude(&self.config));
+        effective_config["preserve_paths"] =
+            serde_json::json!(local_state.effective_preserve_paths(&self.config));
+        effective_config["rollback_regenerate"] =
+            serde_json::json!(local_state.effective_rollback_regenerate(&self.config));
+        effective_config["health_checks"] =
+            serde_json::json!(local_state.effective_health_checks(&self.config).ok());
+        let config_hash = format!(
+            "{:x}",
+            Sha256::digest(serde_json::to_vec(&effective_config).unwrap_or_default())
+        );
+        let config_changed =
+            self.reported_config_hash.lock().unwrap().as_deref() != Some(config_hash.as_str());
+
+        // 설치 상태가 보고하는 버전과 같고 외부 수정이 없을 때만 설치 체크섬을 보고 (무결성 감사용)
+        let installed_checksum = local_state
+            .artifact_checksum
+            .clone()
+            .filter(|_| !state_tampered && local_state.version.is_some() && local_state.version == current_version);
+
+        let req = CheckinRequest {
+            current_version: current_version.clone(),
+            status: status.to_string(),
+            staged_version,
+            effective_config_hash: Some(config_hash.clone()),
+            effective_config: config_changed.then_some(effective_config),
+            role,
+            product,
+            state_tampered,
+            installed_checksum,
+            config_hash: self.pushed_config_hash.lock().unwrap().clone(),
+            urgent_during_pause: status == "paused" && self.config.urgent_during_pause,
+            poll_interval_secs: *self.last_poll_wait.lock().unwrap(),
+            ..Default::default()
+        };
+
+        let checkin = self.api.checkin(req).await;
+        if !matches!(&checkin, Err(e) if e.is::<CircuitOpenError>() || e.is::<ServerBusy>()) {
+            self.record_checkin(checkin.is_ok());
+        }
+        match checkin {
+            Ok(response) => {
+                if let Some(secs) = response.next_poll_secs {
+                    tracing::info!("Server asked to wait {}s before the next checkin", secs);
+                }
+                *self.poll_hint.lock().unwrap() = response.next_poll_secs;
+                if response.urgent && !*self.urgent.lock().unwrap() {
+                    tracing::warn!(
+                        "Security update {} requested; polling every {}s until it is applied",
+                        response.target_version.as_deref().unwrap_or("?"),
+                        self.config.poll_interval_secs.min(self.config.urgent_poll_interval_secs)
+                    );
+                }
+                *self.urgent.lock().unwrap() = response.urgent;
+                if let Some(pushed) = &response.config {
+                    self.apply_pushed_config(pushed);
+                    // 저장하지 못했으면 다음 체크인에서 다시 받음
+                    *self.pushed_config_hash.lock().unwrap() =
+                        response.config_hash.clone().filter(|_| self.save_pushed_config(pushed));
+                }
+
+                *self.reported_config_hash.lock().unwrap() = if response.effective_config_requested {
+                    None
+                } else {
+                    Some(config_hash)
+                };
+
+                if let Some(note) = &response.note {
+                    tracing::warn!("Server note: {}", note);
+                }
+                if let Some(warning) = &response.warning {
+                    tracing::error!("Server warning: {}", warning);
+                }
+
+                match image {
+                    ImageCheck::Confirming(pending) => {
+                        self.reset_backoff();
+                        self.confirm_image_update(*pending).await
+                    }
+                    ImageCheck::AwaitingReboot => {
+                        self.reset_backoff();
+                        tracing::debug!("Waiting for reboot, ignoring action {}", response.action)
+                    }
+                    ImageCheck::Idle => self.handle_action(&response).await,
+                }
+            }
+            Err(e) => {
+                // 브레이커가 열린 동안은 요청을 보내지 않고 다음 주기를 기다림
+                if let Some(open) = e.downcast_ref::<CircuitOpenError>() {
+                    tracing::debug!("Skipping checkin: {}", open);
+                } else if let Some(busy) = e.downcast_ref::<ServerBusy>() {
+                    // 서버가 알려준 시간만큼 기다림 (백오프 단계는 건드리지 않음)
+                    tracing::warn!("Checkin deferred: {}", busy);
+                    *self.poll_hint.lock().unwrap() = Some(busy.retry_after.as_secs());
+                } else {
+                    tracing::error!(
+                        "Checkin failed: {} (circuit: {})",
+                        e,
+                        self.api.breaker_summary()
+                    );
+                    self.recover_from(&e);
+                }
+            }
         }
     }
 }
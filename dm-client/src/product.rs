@@ -0,0 +1,62 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Classify, ClientError};
+
+/// 아티팩트 최상위의 제품 식별 파일 (`dm-client package --product`가 작성)
+pub const PRODUCT_FILE: &str = ".dm-product";
+
+/// 제품 식별자 최대 길이 (서버와 같은 규칙)
+const MAX_PRODUCT_LEN: usize = 64;
+
+/// 다른 제품용 아티팩트 (서버에는 failure_reason "product_mismatch"로 보고)
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Artifact is built for product {} but this device expects {}",
+    found.as_deref().unwrap_or("(none)"),
+    expected.as_deref().unwrap_or("(none)")
+)]
+pub struct ProductMismatch {
+    pub expected: Option<String>,
+    pub found: Option<String>,
+}
+
+/// 제품 식별자 검증 (a-z, 0-9, '-', '.', '_')
+pub fn validate(product: &str) -> Result<()> {
+    let valid = !product.is_empty()
+        && product.len() <= MAX_PRODUCT_LEN
+        && product
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-._".contains(c));
+    if !valid {
+        anyhow::bail!(ClientError::Config(format!(
+            "제품 식별자는 a-z, 0-9, '-', '.', '_'로 된 1-{}자여야 합니다: {}",
+            MAX_PRODUCT_LEN, product
+        )));
+    }
+    Ok(())
+}
+
+/// 추출된 트리의 제품 (`.dm-product`가 없으면 None)
+pub fn read(root: &Path) -> Result<Option<String>> {
+    let path = root.join(PRODUCT_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let product = fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+    Ok(Some(product.trim().to_string()).filter(|p| !p.is_empty()))
+}
+
+/// 추출된 트리가 기대하는 제품용인지 확인 (양쪽 모두 선언하지 않았으면 검사하지 않음)
+pub fn check(root: &Path, expected: Option<&str>) -> Result<()> {
+    let found = read(root)?;
+    if found.as_deref() == expected {
+        return Ok(());
+    }
+    Err(anyhow::Error::new(ProductMismatch {
+        expected: expected.map(str::to_string),
+        found,
+    }))
+    .classify(ClientError::Unsupported)
+}
@@ -0,0 +1,106 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// CLI 출력 모드
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// 데몬 (진행률 표시 없음)
+    Daemon,
+    /// 최종 결과와 에러만 출력
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(OutputMode::Daemon as u8);
+
+pub fn set_mode(mode: OutputMode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn mode() -> OutputMode {
+    match MODE.load(Ordering::Relaxed) {
+        1 => OutputMode::Quiet,
+        2 => OutputMode::Normal,
+        3 => OutputMode::Verbose,
+        _ => OutputMode::Daemon,
+    }
+}
+
+/// 진행률 단위
+#[derive(Debug, Clone, Copy)]
+pub enum Unit {
+    Bytes,
+    Items,
+}
+
+/// 진행률 표시
+/// TTY에서는 progress bar, 그 외에는 10% 단위 로그 라인으로 출력
+pub struct Progress {
+    bar: Option<ProgressBar>,
+    label: String,
+    total: u64,
+    current: u64,
+    last_logged_pct: u64,
+    log_lines: bool,
+}
+
+impl Progress {
+    pub fn new(label: &str, total: u64, unit: Unit) -> Self {
+        let visible = matches!(mode(), OutputMode::Normal | OutputMode::Verbose);
+        let tty = std::io::stderr().is_terminal();
+
+        let bar = (visible && tty).then(|| {
+            let template = match unit {
+                Unit::Bytes => "{msg:>12} [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
+                Unit::Items => "{msg:>12} [{bar:40.cyan/blue}] {pos}/{len}",
+            };
+            let bar = ProgressBar::new(total);
+            bar.set_style(
+                ProgressStyle::with_template(template)
+                    .unwrap_or_else(|_| ProgressStyle::default_bar())
+                    .progress_chars("=> "),
+            );
+            bar.set_message(label.to_string());
+            bar
+        });
+
+        Self {
+            bar,
+            label: label.to_string(),
+            total,
+            current: 0,
+            last_logged_pct: 0,
+            log_lines: visible && !tty,
+        }
+    }
+
+    /// 데몬 모드에서도 10% 단위 로그 라인 출력 (다운로드처럼 오래 걸리는 작업, quiet 제외)
+    pub fn logged_in_daemon(mut self) -> Self {
+        if mode() == OutputMode::Daemon {
+            self.log_lines = true;
+        }
+        self
+    }
+
+    pub fn inc(&mut self, n: u64) {
+        self.current += n;
+
+        if let Some(bar) = &self.bar {
+            bar.inc(n);
+        } else if self.log_lines && self.total > 0 {
+            let pct = (self.current * 100 / self.total).min(100);
+            if pct >= self.last_logged_pct + 10 {
+                self.last_logged_pct = pct - pct % 10;
+                tracing::info!("{}: {}%", self.label, self.last_logged_pct);
+            }
+        }
+    }
+
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 일시적 서버 오류 재시도 정책
+///
+/// 5xx 응답과 연결 오류만 재시도하며 4xx는 재시도하지 않는다.
+/// 전체 재시도 시간은 budget을 넘지 않는다 (Polling 주기를 잡아먹지 않도록).
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub budget: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            budget: Duration::from_secs(20),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// attempt번째 재시도 전 대기 시간 (지수 백오프, 절반은 jitter)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(1u32 << attempt.min(16));
+        let capped = exp.min(self.max_delay);
+        capped / 2 + capped.mul_f64(rand::random::<f64>() / 2.0)
+    }
+}
+
+/// 서킷 브레이커가 열려 요청을 보내지 않음
+#[derive(Debug, thiserror::Error)]
+#[error("circuit breaker open after repeated server failures; next attempt in {}s", retry_in.as_secs())]
+pub struct CircuitOpenError {
+    pub retry_in: Duration,
+}
+
+/// 호출 간 연속 실패 기반 서킷 브레이커
+///
+/// - closed: 정상 호출
+/// - open: threshold번 연속 실패 후 cooldown 동안 즉시 실패 (재시도 없음)
+/// - half-open: cooldown 경과 후 한 번 시도, 성공하면 closed / 실패하면 다시 open
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(60))
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// 호출 가능 여부 확인
+    pub fn check(&self) -> Result<(), CircuitOpenError> {
+        let state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(until) => {
+                let now = Instant::now();
+                if now < until {
+                    Err(CircuitOpenError { retry_in: until - now })
+                } else {
+                    tracing::info!("Circuit breaker half-open, trying server again");
+                    Ok(())
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// 서버 응답 수신 (4xx 포함)
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.open_until.is_some() {
+            tracing::info!("Circuit breaker closed, server reachable again");
+        }
+        *state = BreakerState::default();
+    }
+
+    /// 재시도 후에도 실패
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+
+        let reopening = state.open_until.is_some();
+        if reopening || state.consecutive_failures >= self.threshold {
+            state.open_until = Some(Instant::now() + self.cooldown);
+            tracing::warn!(
+                "Circuit breaker open after {} consecutive failures; pausing requests for {}s",
+                state.consecutive_failures,
+                self.cooldown.as_secs()
+            );
+        }
+    }
+
+    /// 로그용 상태 요약
+    pub fn summary(&self) -> String {
+        let state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(until) if Instant::now() < until => format!(
+                "open, {} consecutive failures",
+                state.consecutive_failures
+            ),
+            Some(_) => "half-open".to_string(),
+            None => format!("closed, {} consecutive failures", state.consecutive_failures),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+/// 버전별 설치 스크립트 (체크인 응답에는 SHA256, 스크립트 API/USB에는 내용)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstallScripts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_install: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_install: Option<String>,
+}
+
+impl InstallScripts {
+    pub fn is_empty(&self) -> bool {
+        self.pre_install.is_none() && self.post_install.is_none()
+    }
+
+    /// 체크인 응답의 해시와 내용 비교 (서버에서 받은 스크립트 검증)
+    pub fn verify(&self, expected: &InstallScripts) -> Result<()> {
+        for (phase, script, hash) in [
+            ("pre_install", &self.pre_install, &expected.pre_install),
+            ("post_install", &self.post_install, &expected.post_install),
+        ] {
+            match (script, hash) {
+                (None, None) => {}
+                (Some(script), Some(hash)) => {
+                    let actual = format!("{:x}", Sha256::digest(script.as_bytes()));
+                    if !actual.eq_ignore_ascii_case(hash) {
+                        anyhow::bail!(
+                            "Install script {} hash mismatch (expected {}, got {})",
+                            phase,
+                            hash,
+                            actual
+                        );
+                    }
+                }
+                _ => anyhow::bail!("Install script {} does not match the checkin response", phase),
+            }
+        }
+        Ok(())
+    }
+
+    /// USB manifest가 가리키는 스크립트 파일 읽기 (manifest 디렉토리 기준 경로)
+    pub fn load(base: &Path, pre_install: Option<&str>, post_install: Option<&str>) -> Result<Self> {
+        let read = |path: Option<&str>| {
+            path.map(|p| {
+                fs::read_to_string(base.join(p)).with_context(|| format!("설치 스크립트 읽기 실패: {}", p))
+            })
+            .transpose()
+        };
+        Ok(Self {
+            pre_install: read(pre_install)?,
+            post_install: read(post_install)?,
+        })
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::ClientError;
+
+/// 데몬 systemd 유닛 이름
+pub const UNIT_NAME: &str = "dm-client.service";
+
+/// 유닛 파일을 쓰는 디렉토리
+pub const UNIT_DIR: &str = "/etc/systemd/system";
+
+/// `--config`를 지정하지 않았을 때의 환경 파일 (install.sh가 만드는 경로)
+pub const DEFAULT_ENV_FILE: &str = "/etc/sam-dm/config.env";
+
+/// 유닛 파일 내용
+#[derive(Debug, Clone)]
+pub struct UnitOptions {
+    /// dm-client 실행 파일 (절대 경로)
+    pub exec: PathBuf,
+    /// EnvironmentFile (절대 경로)
+    pub env_file: PathBuf,
+    /// 데몬 실행 사용자 (없으면 root)
+    pub user: Option<String>,
+}
+
+/// 유닛 파일 쓰기 결과
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitWrite {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// systemd로 부팅한 시스템인지 (sd_booted와 같은 기준)
+pub fn is_systemd() -> bool {
+    Path::new("/run/systemd/system").is_dir()
+}
+
+/// 유닛 파일 생성 (ExecStart는 `<exec> daemon`, 비정상 종료 시 재시작)
+pub fn render_unit(options: &UnitOptions) -> Result<String> {
+    let exec = unit_value("executable path", &options.exec.to_string_lossy())?;
+    let env_file = unit_value("--config", &options.env_file.to_string_lossy())?;
+    if !options.exec.is_absolute() || !options.env_file.is_absolute() {
+        anyhow::bail!(ClientError::Config(
+            "Service unit paths must be absolute".to_string()
+        ));
+    }
+    // 공백이 있는 경로는 ExecStart에서 따옴표로 감싼다 (EnvironmentFile은 줄 끝까지 경로)
+    let exec = if exec.contains(char::is_whitespace) {
+        format!("\"{}\"", exec)
+    } else {
+        exec
+    };
+
+    let mut unit = String::new();
+    unit.push_str("[Unit]\n");
+    unit.push_str("Description=Sam DM Client\n");
+    unit.push_str("Wants=network-online.target\n");
+    unit.push_str("After=network-online.target\n");
+    unit.push('\n');
+    unit.push_str("[Service]\n");
+    unit.push_str("Type=simple\n");
+    if let Some(user) = &options.user {
+        let user = unit_value("--user", user)?;
+        if user.is_empty() || user.contains(char::is_whitespace) {
+            anyhow::bail!(ClientError::Config(format!("Invalid --user: {:?}", user)));
+        }
+        unit.push_str(&format!("User={}\n", user));
+    }
+    unit.push_str(&format!("EnvironmentFile={}\n", env_file));
+    unit.push_str(&format!("ExecStart={} daemon\n", exec));
+    unit.push_str("Restart=on-failure\n");
+    unit.push_str("RestartSec=10\n");
+    unit.push('\n');
+    unit.push_str("[Install]\n");
+    unit.push_str("WantedBy=multi-user.target\n");
+    Ok(unit)
+}
+
+/// 유닛 파일에 넣을 값 (줄바꿈이 있으면 다른 설정을 끼워 넣을 수 있으므로 거부)
+fn unit_value(what: &str, value: &str) -> Result<String> {
+    if value.contains(['\n', '\r']) {
+        anyhow::bail!(ClientError::Config(format!("Invalid {}: must be a single line", what)));
+    }
+    Ok(value.to_string())
+}
+
+/// 유닛 파일 쓰기 (임시 파일 후 rename, 내용이 같으면 그대로 둠)
+pub fn write_unit(unit_dir: &Path, unit: &str) -> Result<UnitWrite> {
+    let path = unit_dir.join(UNIT_NAME);
+    let result = match fs::read_to_string(&path) {
+        Ok(existing) if existing == unit => return Ok(UnitWrite::Unchanged),
+        Ok(_) => UnitWrite::Updated,
+        Err(_) => UnitWrite::Created,
+    };
+    let tmp = unit_dir.join(format!(".{}.tmp", UNIT_NAME));
+    fs::write(&tmp, unit)
+        .and_then(|()| fs::rename(&tmp, &path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            ClientError::Config(format!("Failed to write {:?}: {} (run as root)", path, e))
+        })?;
+    Ok(result)
+}
+
+/// 유닛 파일 삭제. 있었으면 true
+pub fn remove_unit(unit_dir: &Path) -> Result<bool> {
+    let path = unit_dir.join(UNIT_NAME);
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(ClientError::Config(format!("Failed to remove {:?}: {} (run as root)", path, e)).into()),
+    }
+}
+
+/// systemctl 실행 (실패하면 종료 상태와 함께 에러)
+pub fn systemctl(args: &[&str]) -> Result<()> {
+    let status = Command::new("systemctl")
+        .args(args)
+        .status()
+        .with_context(|| format!("Failed to run systemctl {}", args.join(" ")))?;
+    if !status.success() {
+        anyhow::bail!(ClientError::Install(format!(
+            "systemctl {} failed: {}",
+            args.join(" "),
+            status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 현재 실행 파일의 daemon, 환경 파일, 실행 사용자, 재시작 정책
+    #[test]
+    fn service_unit_runs_the_daemon_with_the_env_file() -> Result<()> {
+        let options = UnitOptions {
+            exec: PathBuf::from("/usr/local/bin/dm-client"),
+            env_file: PathBuf::from("/etc/sam-dm/config.env"),
+            user: Some("app".to_string()),
+        };
+        let unit = render_unit(&options)?;
+        for line in [
+            "ExecStart=/usr/local/bin/dm-client daemon",
+            "EnvironmentFile=/etc/sam-dm/config.env",
+            "User=app",
+            "Restart=on-failure",
+            "WantedBy=multi-user.target",
+        ] {
+            assert!(unit.lines().any(|l| l == line), "missing {:?} in\n{}", line, unit);
+        }
+        let root = render_unit(&UnitOptions { user: None, ..options.clone() })?;
+        assert!(!root.contains("User="), "{}", root);
+
+        // 줄바꿈으로 다른 설정을 끼워 넣거나 상대 경로를 쓰면 거부
+        let injected = UnitOptions { user: Some("app\nExecStartPre=/bin/sh".to_string()), ..options.clone() };
+        let error = render_unit(&injected).expect_err("newline must be rejected");
+        assert_eq!(error.to_string(), "Invalid --user: must be a single line");
+        let relative = UnitOptions { env_file: PathBuf::from("config.env"), ..options.clone() };
+        assert!(render_unit(&relative).is_err());
+
+        let dir = tempfile::tempdir()?;
+        assert_eq!(write_unit(dir.path(), &unit)?, UnitWrite::Created);
+        assert_eq!(fs::read_to_string(dir.path().join(UNIT_NAME))?, unit);
+        assert_eq!(write_unit(dir.path(), &unit)?, UnitWrite::Unchanged);
+        assert_eq!(write_unit(dir.path(), &root)?, UnitWrite::Updated);
+        assert!(remove_unit(dir.path())?);
+        assert!(!remove_unit(dir.path())?);
+        assert_eq!(fs::read_dir(dir.path())?.count(), 0);
+        Ok(())
+    }
+}
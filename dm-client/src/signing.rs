@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::error::{Classify, ClientError};
+
+/// 서명이 없거나 DM_UPDATE_PUBLIC_KEY로 검증되지 않는 아티팩트 (서버에는 failure_reason "signature_invalid"로 보고)
+#[derive(Debug, thiserror::Error)]
+#[error("Artifact signature for {version} {reason}")]
+pub struct InvalidSignature {
+    pub version: String,
+    pub reason: &'static str,
+}
+
+/// DM_UPDATE_PUBLIC_KEY 해석 (base64로 인코딩한 32바이트 ed25519 공개 키)
+pub fn public_key(encoded: &str) -> Result<VerifyingKey> {
+    let invalid = || {
+        ClientError::Config("DM_UPDATE_PUBLIC_KEY must be a base64-encoded 32-byte ed25519 public key".to_string())
+    };
+    let bytes: [u8; 32] = STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(invalid)?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| invalid().into())
+}
+
+/// 아티팩트 파일의 ed25519 서명 검증 (DM_UPDATE_PUBLIC_KEY가 없으면 검사하지 않음)
+///
+/// 키가 설정되어 있으면 서명이 없는 아티팩트도 거부한다. 파일은 메모리에 올리지 않고 나눠 읽는다.
+pub fn verify_artifact(config: &Config, artifact: &Path, signature: Option<&str>, version: &str) -> Result<()> {
+    let Some(encoded_key) = &config.update_public_key else {
+        return Ok(());
+    };
+    let key = public_key(encoded_key)?;
+    let rejected = |reason| anyhow::Error::new(InvalidSignature {
+        version: version.to_string(),
+        reason,
+    });
+
+    let Some(signature) = signature.filter(|s| !s.trim().is_empty()) else {
+        return Err(rejected("is missing but DM_UPDATE_PUBLIC_KEY is set")).classify(ClientError::Checksum);
+    };
+    let Some(signature) = STANDARD
+        .decode(signature.trim())
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+    else {
+        return Err(rejected("is not a base64-encoded ed25519 signature")).classify(ClientError::Checksum);
+    };
+
+    let Ok(mut verifier) = key.verify_stream(&signature) else {
+        return Err(rejected("does not match DM_UPDATE_PUBLIC_KEY")).classify(ClientError::Checksum);
+    };
+    let mut file = File::open(artifact).with_context(|| format!("Failed to open {:?}", artifact))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).with_context(|| format!("Failed to read {:?}", artifact))?;
+        if n == 0 {
+            break;
+        }
+        verifier.update(&buf[..n]);
+    }
+    if verifier.finalize_and_verify().is_err() {
+        return Err(rejected("does not match DM_UPDATE_PUBLIC_KEY")).classify(ClientError::Checksum);
+    }
+    tracing::info!("Signature verified ✓");
+    Ok(())
+}
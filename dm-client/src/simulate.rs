@@ -0,0 +1,286 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api::{self, CheckinRequest, ConnectionMetrics, DmApiClient, UpdateResultRequest};
+
+/// 가상 클라이언트의 연결 사용 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConnectionMode {
+    /// 모든 가상 클라이언트가 연결 풀 하나를 공유 (서버 처리량 측정용)
+    Shared,
+    /// 가상 클라이언트마다 데몬과 같은 설정의 연결 (실제 장비와 같은 연결 수)
+    PerClient,
+    /// 요청마다 새 연결 (연결 재사용 전 동작 재현, 비교 기준)
+    Fresh,
+}
+
+/// 부하 테스트 옵션
+#[derive(Debug, Clone)]
+pub struct SimulateOptions {
+    pub server_url: String,
+    pub clients: usize,
+    /// 체크인 주기
+    pub interval: Duration,
+    /// 전체 실행 시간
+    pub duration: Duration,
+    /// update 명령을 수락할 확률 (0.0 ~ 1.0)
+    pub accept_rate: f64,
+    /// 미리 발급된 API Key 목록 (없으면 등록)
+    pub keys: Vec<String>,
+    /// 초기 버전
+    pub initial_version: Option<String>,
+    pub connections: ConnectionMode,
+}
+
+/// 작업별 지연 시간 통계
+#[derive(Debug, Default)]
+struct OpStats {
+    latencies: Vec<Duration>,
+    errors: usize,
+    bytes: u64,
+    /// 처리율 계산용 구간 (첫 요청 시작 ~ 마지막 요청 종료)
+    window: Option<(Instant, Instant)>,
+}
+
+/// 모든 가상 클라이언트가 공유하는 통계
+#[derive(Default)]
+pub struct SimulationStats {
+    ops: Mutex<BTreeMap<&'static str, OpStats>>,
+    actions: Mutex<BTreeMap<String, usize>>,
+    /// 실행 중 서버가 받은 새 연결 수와 요청 수 (GET /api/metrics/connections 차이)
+    server_connections: Mutex<Option<(u64, u64)>>,
+}
+
+impl SimulationStats {
+    fn record<T>(&self, op: &'static str, started: Instant, result: &Result<T>) {
+        let mut ops = self.ops.lock().unwrap();
+        let stats = ops.entry(op).or_default();
+        let (first, _) = stats.window.unwrap_or((started, started));
+        stats.window = Some((first.min(started), Instant::now()));
+        match result {
+            Ok(_) => stats.latencies.push(started.elapsed()),
+            Err(e) => {
+                stats.errors += 1;
+                tracing::debug!("{} failed: {}", op, e);
+            }
+        }
+    }
+
+    fn record_bytes(&self, op: &'static str, bytes: u64) {
+        self.ops.lock().unwrap().entry(op).or_default().bytes += bytes;
+    }
+
+    fn record_action(&self, action: &str) {
+        *self.actions.lock().unwrap().entry(action.to_string()).or_default() += 1;
+    }
+}
+
+impl fmt::Display for SimulationStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "🦊 시뮬레이션 결과")?;
+        writeln!(
+            f,
+            "   {:<10} {:>8} {:>7} {:>8} {:>9} {:>9} {:>9} {:>9}",
+            "op", "ok", "errors", "req/s", "p50", "p95", "p99", "max"
+        )?;
+
+        let mut ops = self.ops.lock().unwrap();
+        for (op, stats) in ops.iter_mut() {
+            stats.latencies.sort();
+            let ok = stats.latencies.len();
+            let window = stats
+                .window
+                .map_or(0.0, |(first, last)| (last - first).as_secs_f64())
+                .max(0.001);
+            writeln!(
+                f,
+                "   {:<10} {:>8} {:>7} {:>8.1} {:>9} {:>9} {:>9} {:>9}",
+                op,
+                ok,
+                stats.errors,
+                (ok + stats.errors) as f64 / window,
+                fmt_ms(percentile(&stats.latencies, 0.50)),
+                fmt_ms(percentile(&stats.latencies, 0.95)),
+                fmt_ms(percentile(&stats.latencies, 0.99)),
+                fmt_ms(stats.latencies.last().copied()),
+            )?;
+            if stats.bytes > 0 {
+                writeln!(f, "   {:<10} {} bytes", "", stats.bytes)?;
+            }
+        }
+
+        let actions = self.actions.lock().unwrap();
+        if !actions.is_empty() {
+            let summary: Vec<String> = actions.iter().map(|(a, n)| format!("{}={}", a, n)).collect();
+            writeln!(f, "   actions: {}", summary.join(", "))?;
+        }
+
+        if let Some((accepted, requests)) = *self.server_connections.lock().unwrap() {
+            writeln!(
+                f,
+                "   server: 새 연결 {}개, 요청 {}개 (연결당 {:.1}개)",
+                accepted,
+                requests,
+                requests as f64 / accepted.max(1) as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 정렬된 목록의 백분위수
+fn percentile(sorted: &[Duration], p: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let index = ((sorted.len() as f64 * p).ceil() as usize).clamp(1, sorted.len()) - 1;
+    Some(sorted[index])
+}
+
+fn fmt_ms(d: Option<Duration>) -> String {
+    match d {
+        Some(d) => format!("{:.1}ms", d.as_secs_f64() * 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+/// 가상 클라이언트 (실제 데몬과 같은 DmApiClient 프로토콜 코드 사용)
+struct VirtualClient {
+    api: DmApiClient,
+    current_version: Option<String>,
+}
+
+impl VirtualClient {
+    /// 체크인 한 번 수행, 필요 시 업데이트 수락
+    async fn tick(&mut self, stats: &SimulationStats, accept_rate: f64) {
+        let req = CheckinRequest {
+            current_version: self.current_version.clone(),
+            status: "online".to_string(),
+            ..Default::default()
+        };
+
+        let started = Instant::now();
+        let result = self.api.checkin(req).await;
+        stats.record("checkin", started, &result);
+        let Ok(response) = result else { return };
+        stats.record_action(&response.action);
+
+        if response.action != "update" || !rand::thread_rng().gen_bool(accept_rate) {
+            return;
+        }
+        let (Some(version), Some(url)) = (response.target_version, response.artifact_url) else {
+            return;
+        };
+
+        let started = Instant::now();
+        let result = self.api.drain_artifact(&url).await;
+        stats.record("download", started, &result);
+        let report = match result {
+            Ok(bytes) => {
+                stats.record_bytes("download", bytes);
+                UpdateResultRequest::success(&version)
+            }
+            Err(e) => UpdateResultRequest::failure(&version, &e.to_string()),
+        };
+
+        let started = Instant::now();
+        let result = self.api.report_result(&report).await;
+        stats.record("report", started, &result);
+        if result.is_ok() && report.success {
+            self.current_version = Some(version);
+        }
+    }
+}
+
+/// 부하 테스트 실행
+///
+/// 가상 클라이언트마다 tokio task 하나를 띄운다. 연결은 `ConnectionMode`에 따라 공유하거나
+/// 가상 클라이언트별로 따로 둔다. 체크인 시점은 주기 안에서 무작위로 분산되며, 이후 ±10% jitter를 둔다.
+pub async fn run(opts: SimulateOptions) -> Result<(Arc<SimulationStats>, Vec<String>)> {
+    let http = reqwest::Client::builder()
+        .pool_max_idle_per_host(opts.clients.min(1024))
+        .build()?;
+    let client_http = || -> Result<reqwest::Client> {
+        Ok(match opts.connections {
+            ConnectionMode::Shared => http.clone(),
+            ConnectionMode::PerClient => api::polling_http_client(opts.interval),
+            ConnectionMode::Fresh => reqwest::Client::builder().pool_max_idle_per_host(0).build()?,
+        })
+    };
+    let stats = Arc::new(SimulationStats::default());
+    let admin = DmApiClient::with_http_client(http.clone(), &opts.server_url, "");
+
+    // 1. API Key 준비 (부족분은 등록)
+    let mut keys: Vec<String> = opts.keys.iter().take(opts.clients).cloned().collect();
+    if keys.len() < opts.clients {
+        let prefix = format!("sim-{}", Utc::now().format("%Y%m%dT%H%M%SZ"));
+        tracing::info!("Registering {} virtual clients", opts.clients - keys.len());
+
+        for n in keys.len()..opts.clients {
+            let started = Instant::now();
+            let result = admin.register_client(&format!("{}-{:05}", prefix, n)).await;
+            stats.record("enroll", started, &result);
+            keys.push(result.context("Failed to register virtual client")?.api_key);
+        }
+    }
+
+    // 2. 가상 클라이언트 실행
+    tracing::info!(
+        "Simulating {} clients, checkin every {}s for {}s",
+        keys.len(),
+        opts.interval.as_secs(),
+        opts.duration.as_secs()
+    );
+    // 서버 연결 통계는 있으면 비교용으로 기록 (없는 서버면 생략)
+    let before = admin.connection_metrics().await.ok();
+    let deadline = tokio::time::Instant::now() + opts.duration;
+    let mut tasks = tokio::task::JoinSet::new();
+
+    for key in &keys {
+        let mut client = VirtualClient {
+            api: DmApiClient::with_http_client(client_http()?, &opts.server_url, key)
+                .with_instance(&uuid::Uuid::new_v4().to_string(), Utc::now()),
+            current_version: opts.initial_version.clone(),
+        };
+        let stats = stats.clone();
+        let interval = opts.interval;
+        let accept_rate = opts.accept_rate;
+
+        tasks.spawn(async move {
+            let offset = interval.mul_f64(rand::thread_rng().gen::<f64>());
+            let mut next = tokio::time::Instant::now() + offset;
+            while next < deadline {
+                tokio::time::sleep_until(next).await;
+                client.tick(&stats, accept_rate).await;
+                next += interval.mul_f64(rand::thread_rng().gen_range(0.9..1.1));
+            }
+        });
+    }
+
+    tokio::select! {
+        _ = async { while tasks.join_next().await.is_some() {} } => {}
+        _ = tokio::signal::ctrl_c() => {
+            tracing::info!("Interrupted, stopping virtual clients");
+            tasks.abort_all();
+        }
+    }
+
+    if let (Some(before), Ok(after)) = (before, admin.connection_metrics().await) {
+        *stats.server_connections.lock().unwrap() = Some(connection_delta(before, after));
+    }
+
+    Ok((stats, keys))
+}
+
+/// 실행 전후 서버 연결 통계 차이 (통계 조회 요청 자체는 제외)
+fn connection_delta(before: ConnectionMetrics, after: ConnectionMetrics) -> (u64, u64) {
+    (
+        after.accepted_total.saturating_sub(before.accepted_total),
+        after.requests_total.saturating_sub(before.requests_total + 1),
+    )
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::deadline::UpdatePhase;
+use crate::error::{Classify, ClientError};
+use crate::fsfault;
+use crate::state::LocalState;
+use crate::updater::Updater;
+
+const VERSION_FILE: &str = ".dm-version";
+
+/// 스테이징된 업데이트 활성화 (교체 → 재시작 → 헬스 체크)
+///
+/// 서버의 activate 명령과 `dm-client activate` 공용. 활성화된 버전 반환
+pub fn activate_staged(config: &Config, updater: &Updater) -> Result<String> {
+    let state = LocalState::load(&config.service_dir);
+    let staged = state
+        .staged
+        .clone()
+        .ok_or_else(|| ClientError::Config("No staged update to activate".to_string()))?;
+    let health_probes = state.effective_health_checks(config)?;
+    let staged_path = Path::new(&staged.path);
+    if !staged_path.is_dir() {
+        anyhow::bail!(ClientError::Config(format!("Staged tree not found: {}", staged.path)));
+    }
+
+    let version_file = Path::new(&config.service_dir).join(VERSION_FILE);
+    let current_version = fs::read_to_string(&version_file)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    tracing::info!("Activating staged update: {} -> {}", current_version, staged.version);
+
+    // 1. 현재 버전 백업
+    let backup_path = updater.backup_current(&current_version)?;
+
+    // 2. 스테이징된 트리로 교체 (설치 전/후 스크립트 포함)
+    let scripts = staged.scripts.clone().unwrap_or_default();
+    if let Some(script) = &scripts.pre_install {
+        if let Err(e) =
+            updater.run_install_script(UpdatePhase::PreInstall, script, &staged.version, &current_version)
+        {
+            tracing::error!("Pre-install script failed: {}", e);
+            if !backup_path.is_empty() {
+                tracing::info!("Attempting rollback...");
+                updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
+            }
+            return Err(e).classify(ClientError::Install);
+        }
+    }
+
+    if let Err(e) = updater.install_from(staged_path) {
+        tracing::error!("Installation failed: {}", e);
+        if !backup_path.is_empty() && !fsfault::rollback_blocked(&e) {
+            tracing::info!("Attempting rollback...");
+            updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
+        }
+        return Err(e).classify(ClientError::Install);
+    }
+
+    if let Some(script) = &scripts.post_install {
+        if let Err(e) =
+            updater.run_install_script(UpdatePhase::PostInstall, script, &staged.version, &current_version)
+        {
+            tracing::error!("Post-install script failed: {}", e);
+            if !backup_path.is_empty() {
+                tracing::info!("Attempting rollback...");
+                updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
+            }
+            return Err(e).classify(ClientError::Install);
+        }
+    }
+
+    // 3. 버전 파일 업데이트
+    fs::create_dir_all(&config.service_dir)?;
+    fs::write(&version_file, &staged.version)?;
+
+    // 4. 서비스 재시작
+    if let Err(e) = updater.restart_service() {
+        tracing::error!("Restart failed: {}", e);
+        if !backup_path.is_empty() && !fsfault::rollback_blocked(&e) {
+            tracing::info!("Attempting rollback...");
+            updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
+            fs::write(&version_file, &current_version)?;
+        }
+        return Err(e).classify(ClientError::Install);
+    }
+
+    // 5. 헬스 체크
+    if let Err(e) = updater.health_check(&health_probes) {
+        tracing::error!("{}", e);
+        if !backup_path.is_empty() {
+            tracing::info!("Attempting rollback...");
+            updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
+            fs::write(&version_file, &current_version)?;
+        }
+        return Err(e).classify(ClientError::HealthCheck);
+    }
+    tracing::info!("Health check passed ✓");
+
+    // 6. 설치 상태 기록 및 스테이징 정리
+    LocalState::installed(&staged.version, &staged.artifact_checksum, staged.build_info)
+        .keep_server_config(&state)
+        .record_skipped_backup(config, &backup_path, &current_version)
+        .save(config)?;
+    updater.clear_staging()?;
+
+    tracing::info!("Staged update activated: {}", staged.version);
+    Ok(staged.version)
+}
+
+/// 스테이징 취소 (스테이징 디렉토리 삭제). 취소된 버전 반환
+pub fn discard_staged(config: &Config, updater: &Updater) -> Result<Option<String>> {
+    let mut state = LocalState::load(&config.service_dir);
+    let discarded = state.staged.take().map(|s| s.version);
+
+    updater.clear_staging()?;
+    if discarded.is_some() {
+        state.save(config)?;
+    }
+
+    Ok(discarded)
+}
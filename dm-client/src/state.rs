@@ -0,0 +1,480 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs;
+use std::path::Path;
+
+use crate::abslot::PendingImage;
+use crate::api::{BuildInfo, UpdateResultRequest};
+use crate::config::Config;
+use crate::health::HealthProbe;
+use crate::installfs::InstallStrategy;
+use crate::scripts::InstallScripts;
+
+const STATE_FILE: &str = ".dm-state.json";
+const VERSION_FILE: &str = ".dm-version";
+const PENDING_RESULT_FILE: &str = ".dm-pending-result.json";
+/// 기억할 이전 설치 버전 수
+const MAX_HISTORY: usize = 10;
+
+/// 설치 상태 파일 (service_dir/.dm-state.json)
+/// .dm-version과 함께 현재 설치된 아티팩트 정보를 기록
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocalState {
+    #[serde(default)]
+    pub version: Option<String>,
+    /// 설치된 아티팩트의 SHA256
+    #[serde(default)]
+    pub artifact_checksum: Option<String>,
+    #[serde(default)]
+    pub installed_at: Option<DateTime<Utc>>,
+    /// 설치된 버전의 빌드 정보 (체크인 응답 또는 USB manifest에서 기록)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_info: Option<BuildInfo>,
+    /// 현재 트리를 설치한 방법 (스테이징 활성화와 이전 버전 dm-client는 기록하지 않음)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_strategy: Option<InstallStrategy>,
+    /// 스테이징되어 활성화 대기 중인 업데이트
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staged: Option<StagedUpdate>,
+    /// 현재 실행 중인 트리의 원본 백업 (롤백으로 복원됨)
+    ///
+    /// 다음 업데이트가 성공할 때까지 유일하게 검증된 스냅샷이므로 정리 대상에서 제외한다.
+    /// 새 설치 상태(`installed`)에서는 비워져 일반 백업으로 돌아간다.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restore_point: Option<RestorePoint>,
+    /// 서버가 ClientConfig로 보낸 역할 (DM_CLIENT_ROLE이 없을 때 사용)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// 서버가 ClientConfig로 보낸 제품 (DM_EXPECTED_PRODUCT가 없을 때 사용)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product: Option<String>,
+    /// 서버가 ClientConfig로 보낸 백업 제외 패턴 (DM_BACKUP_EXCLUDE가 없을 때 사용)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_exclude: Option<Vec<String>>,
+    /// 서버가 ClientConfig로 보낸 보존 경로 (DM_PRESERVE_PATHS가 없을 때 사용)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preserve_paths: Option<Vec<String>>,
+    /// 서버가 ClientConfig로 보낸 롤백 후 재생성 여부 (DM_ROLLBACK_REGENERATE가 없을 때 사용)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollback_regenerate: Option<bool>,
+    /// 서버가 ClientConfig로 보낸 헬스 체크 프로브 (로컬 헬스 체크 설정이 없을 때 사용)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_checks: Option<Vec<HealthProbe>>,
+    /// 재부팅 후 확정을 기다리는 A/B 이미지 업데이트
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_image: Option<PendingImage>,
+    /// 백업을 건너뛰고 설치됨 (롤백 요청 시 이전 버전으로 돌아갈 수 없는 이유 안내)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_backup: Option<SkippedBackup>,
+    /// 이전에 설치했던 버전과 체크섬 (최신순, USB 파일 불일치 진단용)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<KnownVersion>,
+    /// 상태 내용의 HMAC-SHA256 (저장 시 갱신, 외부 수정 감지용)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// 설치 상태 무결성 검사 결과
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateIntegrity {
+    /// 서명과 .dm-version이 모두 일치
+    Valid,
+    /// 서명 키가 없어 검사할 수 없음
+    Unverifiable,
+    /// 서명되지 않은 상태 (이전 버전 dm-client가 기록)
+    Unsigned,
+    /// dm-client 밖에서 수정됨 (사유 포함)
+    Tampered(String),
+}
+
+impl StateIntegrity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Valid => "valid",
+            Self::Unverifiable => "unverifiable",
+            Self::Unsigned => "unsigned",
+            Self::Tampered(_) => "tampered",
+        }
+    }
+}
+
+/// 이전에 설치했던 아티팩트
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownVersion {
+    pub version: String,
+    pub artifact_checksum: String,
+    /// 그 버전을 설치한 방법 (기록이 없으면 생략)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_strategy: Option<InstallStrategy>,
+}
+
+/// 백업 없이 교체된 이전 설치
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedBackup {
+    /// 백업 없이 교체된 버전
+    pub previous_version: String,
+    pub skipped_at: DateTime<Utc>,
+}
+
+/// 롤백으로 복원된 백업
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestorePoint {
+    /// 백업 디렉토리 경로
+    pub backup: String,
+    pub restored_at: DateTime<Utc>,
+}
+
+/// 스테이징된 업데이트 (다운로드/검증/추출 완료, 교체 전)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StagedUpdate {
+    pub version: String,
+    pub artifact_checksum: String,
+    /// 추출된 트리 경로
+    pub path: String,
+    pub staged_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_info: Option<BuildInfo>,
+    /// 활성화 시 실행할 설치 스크립트 (스테이징 때 검증됨)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scripts: Option<InstallScripts>,
+}
+
+impl LocalState {
+    /// 상태 파일 읽기 (없거나 손상된 경우 기본값)
+    pub fn load(service_dir: &str) -> Self {
+        let path = Path::new(service_dir).join(STATE_FILE);
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// 상태 파일 저장 (서명 키가 있으면 서명)
+    pub fn save(&self, config: &Config) -> Result<()> {
+        let mut state = self.clone();
+        state.signature = match config.state_key() {
+            Some(key) => Some(state.sign(key)?),
+            None => None,
+        };
+
+        let path = Path::new(&config.service_dir).join(STATE_FILE);
+        fs::create_dir_all(&config.service_dir)?;
+        fs::write(path, serde_json::to_string_pretty(&state)?)?;
+        Ok(())
+    }
+
+    /// 서명 대상 (signature 필드를 제외한 상태 내용)의 HMAC
+    fn sign(&self, key: &[u8]) -> Result<String> {
+        let unsigned = LocalState {
+            signature: None,
+            ..self.clone()
+        };
+        let mut mac = Hmac::<Sha256>::new_from_slice(key)?;
+        mac.update(&serde_json::to_vec(&unsigned)?);
+        Ok(format!("{:x}", mac.finalize().into_bytes()))
+    }
+
+    /// 설치 완료 상태 생성
+    pub fn installed(version: &str, checksum: &str, build_info: Option<BuildInfo>) -> Self {
+        Self {
+            version: Some(version.to_string()),
+            artifact_checksum: Some(checksum.to_string()),
+            installed_at: Some(Utc::now()),
+            build_info: build_info.filter(|b| !b.is_empty()),
+            install_strategy: None,
+            staged: None,
+            restore_point: None,
+            role: None,
+            product: None,
+            backup_exclude: None,
+            preserve_paths: None,
+            rollback_regenerate: None,
+            health_checks: None,
+            pending_image: None,
+            no_backup: None,
+            history: Vec::new(),
+            signature: None,
+        }
+    }
+
+    /// 설치 전 상태에서 설치와 무관한 값(서버 지정 역할, 백업 설정) 유지
+    ///
+    /// 이전 설치 버전은 설치 이력에 추가한다.
+    pub fn keep_server_config(mut self, previous: &LocalState) -> Self {
+        self.role = previous.role.clone();
+        self.product = previous.product.clone();
+        self.backup_exclude = previous.backup_exclude.clone();
+        self.preserve_paths = previous.preserve_paths.clone();
+        self.rollback_regenerate = previous.rollback_regenerate;
+        self.health_checks = previous.health_checks.clone();
+
+        let mut history = previous.history.clone();
+        if let (Some(version), Some(checksum)) = (&previous.version, &previous.artifact_checksum) {
+            history.insert(
+                0,
+                KnownVersion {
+                    version: version.clone(),
+                    artifact_checksum: checksum.clone(),
+                    install_strategy: previous.install_strategy,
+                },
+            );
+        }
+        let mut seen = std::collections::HashSet::new();
+        history.retain(|known| {
+            !self.has_artifact(&known.artifact_checksum)
+                && seen.insert(known.artifact_checksum.to_ascii_lowercase())
+        });
+        history.truncate(MAX_HISTORY);
+        self.history = history;
+        self
+    }
+
+    /// 백업 없이 설치했으면 상태에 기록 (`backup_path`는 `backup_current`의 결과)
+    pub fn record_skipped_backup(mut self, config: &Config, backup_path: &str, previous_version: &str) -> Self {
+        if !config.backup_required && backup_path.is_empty() {
+            self.no_backup = Some(SkippedBackup {
+                previous_version: previous_version.to_string(),
+                skipped_at: Utc::now(),
+            });
+        }
+        self
+    }
+
+    /// 체크섬이 알려진 버전 찾기 (현재 설치, 스테이징, 확정 대기 이미지, 설치 이력 순)
+    pub fn version_for_checksum(&self, checksum: &str) -> Option<String> {
+        if checksum.is_empty() {
+            return None;
+        }
+        let current = self.version.clone().zip(self.artifact_checksum.clone());
+        let staged = self
+            .staged
+            .as_ref()
+            .map(|s| (s.version.clone(), s.artifact_checksum.clone()));
+        let pending = self
+            .pending_image
+            .as_ref()
+            .map(|p| (p.version.clone(), p.artifact_checksum.clone()));
+        let history = self
+            .history
+            .iter()
+            .map(|h| (h.version.clone(), h.artifact_checksum.clone()));
+
+        current
+            .into_iter()
+            .chain(staged)
+            .chain(pending)
+            .chain(history)
+            .find(|(_, known)| known.eq_ignore_ascii_case(checksum))
+            .map(|(version, _)| version)
+    }
+
+    /// 실제 역할 (DM_CLIENT_ROLE > 서버 지정 역할)
+    pub fn effective_role(&self, config: &Config) -> Option<String> {
+        config.role.clone().or_else(|| self.role.clone())
+    }
+
+    /// 실제 기대 제품 (DM_EXPECTED_PRODUCT > 서버 지정 제품)
+    pub fn effective_product(&self, config: &Config) -> Option<String> {
+        config.expected_product.clone().or_else(|| self.product.clone())
+    }
+
+    /// 실제 백업 제외 패턴 (DM_BACKUP_EXCLUDE > 서버 지정 패턴)
+    pub fn effective_backup_exclude(&self, config: &Config) -> Vec<String> {
+        if !config.backup_exclude.is_empty() {
+            return config.backup_exclude.clone();
+        }
+        self.backup_exclude.clone().unwrap_or_default()
+    }
+
+    /// 실제 보존 경로 (DM_PRESERVE_PATHS > 서버 지정 경로)
+    pub fn effective_preserve_paths(&self, config: &Config) -> Vec<String> {
+        if !config.preserve_paths.is_empty() {
+            return config.preserve_paths.clone();
+        }
+        self.preserve_paths.clone().unwrap_or_default()
+    }
+
+    /// 실제 롤백 후 재생성 여부 (DM_ROLLBACK_REGENERATE > 서버 지정 값, 기본 false)
+    pub fn effective_rollback_regenerate(&self, config: &Config) -> bool {
+        config.rollback_regenerate.or(self.rollback_regenerate).unwrap_or(false)
+    }
+
+    /// 실제 헬스 체크 프로브 (DM_HEALTH_CHECKS/DM_HEALTH_CHECK_COMMAND > 서버 지정 프로브, 없으면 빈 목록)
+    pub fn effective_health_checks(&self, config: &Config) -> Result<Vec<HealthProbe>> {
+        match config.health_probes()? {
+            Some(probes) => Ok(probes),
+            None => Ok(self.health_checks.clone().unwrap_or_default()),
+        }
+    }
+
+    /// 현재 복원 지점의 백업 디렉토리 이름
+    pub fn restore_point_name(&self) -> Option<String> {
+        let backup = &self.restore_point.as_ref()?.backup;
+        Path::new(backup)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    }
+
+    /// 주어진 체크섬의 아티팩트가 이미 스테이징되어 있는지 확인
+    pub fn has_staged(&self, checksum: &str) -> bool {
+        self.staged.as_ref().is_some_and(|s| {
+            !checksum.is_empty()
+                && s.artifact_checksum.eq_ignore_ascii_case(checksum)
+                && Path::new(&s.path).is_dir()
+        })
+    }
+
+    /// 현재 설치된 아티팩트가 주어진 체크섬과 동일한지 확인
+    pub fn has_artifact(&self, checksum: &str) -> bool {
+        !checksum.is_empty()
+            && self
+                .artifact_checksum
+                .as_deref()
+                .is_some_and(|c| c.eq_ignore_ascii_case(checksum))
+    }
+}
+
+/// 서버에 보고하지 못한 업데이트 결과 (service_dir/.dm-pending-result.json)
+///
+/// 데몬이 체크인 전에 처음 보낸 Idempotency-Key로 다시 보내고, 서버가 받으면 지운다.
+/// 보고하지 못한 결과는 하나만 기억한다 (새 결과가 이전 결과를 대신함).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingResult {
+    pub result: UpdateResultRequest,
+    pub idempotency_key: String,
+    /// 처음 보고에 실패한 시각
+    pub failed_at: DateTime<Utc>,
+}
+
+impl PendingResult {
+    pub fn new(result: UpdateResultRequest, idempotency_key: &str) -> Self {
+        Self {
+            result,
+            idempotency_key: idempotency_key.to_string(),
+            failed_at: Utc::now(),
+        }
+    }
+
+    /// 저장된 결과 (없거나 손상된 경우 None)
+    pub fn load(service_dir: &str) -> Option<Self> {
+        let data = fs::read_to_string(Path::new(service_dir).join(PENDING_RESULT_FILE)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    /// 저장 (임시 파일 후 rename)
+    pub fn save(&self, service_dir: &str) -> Result<()> {
+        fs::create_dir_all(service_dir)?;
+        let path = Path::new(service_dir).join(PENDING_RESULT_FILE);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// 삭제 (없으면 무시)
+    pub fn clear(service_dir: &str) -> Result<()> {
+        match fs::remove_file(Path::new(service_dir).join(PENDING_RESULT_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// 설치를 마쳤다고 보고할 버전이 지금 설치된 버전이면 그 버전 (스테이징, 실패 결과는 None)
+    pub fn installed_version(&self, service_dir: &str) -> Option<&str> {
+        let result = &self.result;
+        (result.success && !result.staged && read_version_file(service_dir).as_deref() == Some(&result.version))
+            .then_some(result.version.as_str())
+    }
+}
+
+/// .dm-version 읽기
+fn read_version_file(service_dir: &str) -> Option<String> {
+    fs::read_to_string(Path::new(service_dir).join(VERSION_FILE))
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+/// 설치 상태 무결성 검사
+///
+/// 상태 파일의 서명과, .dm-version이 서명된 상태의 버전과 같은지 확인한다.
+pub fn check_integrity(config: &Config) -> StateIntegrity {
+    let Some(key) = config.state_key() else {
+        return StateIntegrity::Unverifiable;
+    };
+    let version_file = read_version_file(&config.service_dir);
+
+    let path = Path::new(&config.service_dir).join(STATE_FILE);
+    let Ok(data) = fs::read_to_string(&path) else {
+        // 설치 전이면 검사할 것이 없음, .dm-version만 있으면 상태 기록 전
+        return if version_file.is_some() {
+            StateIntegrity::Unsigned
+        } else {
+            StateIntegrity::Valid
+        };
+    };
+    let state: LocalState = match serde_json::from_str(&data) {
+        Ok(state) => state,
+        Err(e) => return StateIntegrity::Tampered(format!("state file is unreadable: {}", e)),
+    };
+
+    let Some(signature) = &state.signature else {
+        return StateIntegrity::Unsigned;
+    };
+    match state.sign(key) {
+        Ok(expected) if &expected == signature => {}
+        _ => {
+            return StateIntegrity::Tampered(format!(
+                "state file modified (now version {}, checksum {})",
+                state.version.as_deref().unwrap_or("none"),
+                state.artifact_checksum.as_deref().unwrap_or("none")
+            ))
+        }
+    }
+
+    match (&state.version, &version_file) {
+        (Some(recorded), Some(current)) if recorded != current => StateIntegrity::Tampered(format!(
+            ".dm-version is {} but the signed install state records {}",
+            current, recorded
+        )),
+        (Some(recorded), None) => StateIntegrity::Tampered(format!(
+            ".dm-version is missing but the signed install state records {}",
+            recorded
+        )),
+        _ => StateIntegrity::Valid,
+    }
+}
+
+/// 서명되지 않은 기존 상태를 현재 내용으로 서명 (이전 버전에서 업그레이드 시)
+pub fn adopt_unsigned(config: &Config) -> Result<()> {
+    let mut state = LocalState::load(&config.service_dir);
+    if state.version.is_none() {
+        state.version = read_version_file(&config.service_dir);
+    }
+    tracing::info!(
+        "Signing existing install state (version {})",
+        state.version.as_deref().unwrap_or("none")
+    );
+    state.save(config)
+}
+
+/// 수동 복구용 버전 기록 (.dm-version과 상태를 함께 갱신하고 다시 서명). 이전 버전 반환
+///
+/// 실제 설치된 아티팩트를 알 수 없으므로 체크섬과 빌드 정보는 비운다.
+pub fn set_version(config: &Config, version: &str) -> Result<Option<String>> {
+    let previous = read_version_file(&config.service_dir);
+    let mut state = LocalState::load(&config.service_dir);
+    if state.version.as_deref() != Some(version) {
+        state.artifact_checksum = None;
+        state.build_info = None;
+        state.installed_at = None;
+    }
+    state.version = Some(version.to_string());
+
+    fs::create_dir_all(&config.service_dir)?;
+    fs::write(Path::new(&config.service_dir).join(VERSION_FILE), version)?;
+    state.save(config)?;
+    Ok(previous)
+}
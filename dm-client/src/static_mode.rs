@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use reqwest::Url;
+
+use crate::api::CheckinResponse;
+use crate::usb::UsbManifest;
+
+/// 정적 manifest를 체크인 응답 형태의 업데이트 명령으로 변환
+///
+/// 서버 경로와 같은 업데이트 파이프라인을 쓰기 위해 `action: "update"` 응답을 만든다.
+/// manifest 버전이 현재 버전보다 높지 않으면 None (다운그레이드하지 않음)
+pub fn offer_from_manifest(
+    manifest: UsbManifest,
+    manifest_url: &str,
+    current_version: Option<&str>,
+) -> Result<Option<CheckinResponse>> {
+    let target = semver::Version::parse(&manifest.version)
+        .with_context(|| format!("Invalid manifest version: {}", manifest.version))?;
+
+    match current_version.map(semver::Version::parse) {
+        Some(Ok(current)) if current == target => return Ok(None),
+        Some(Ok(current)) if current > target => {
+            tracing::warn!(
+                "Manifest version {} is older than installed {}; not downgrading",
+                target,
+                current
+            );
+            return Ok(None);
+        }
+        Some(Err(_)) => tracing::warn!(
+            "Installed version {:?} is not semver; installing manifest version {}",
+            current_version.unwrap_or_default(),
+            target
+        ),
+        _ => {}
+    }
+
+    if manifest.pre_install_script.is_some() || manifest.post_install_script.is_some() {
+        anyhow::bail!(
+            "Manifest version {} has install scripts, which static mode does not run",
+            manifest.version
+        );
+    }
+
+    let artifact_url = match manifest.artifact_url {
+        Some(url) => url,
+        None => Url::parse(manifest_url)
+            .and_then(|base| base.join(&manifest.artifact))
+            .with_context(|| format!("Cannot resolve artifact {} against {}", manifest.artifact, manifest_url))?
+            .to_string(),
+    };
+
+    Ok(Some(CheckinResponse {
+        action: "update".to_string(),
+        action_id: None,
+        target_version: Some(manifest.version),
+        artifact_url: Some(artifact_url),
+        artifact_urls: Vec::new(),
+        checksum: Some(manifest.checksum),
+        artifact_size: None,
+        signature: manifest.signature,
+        build_info: Some(manifest.build_info).filter(|b| !b.is_empty()),
+        note: None,
+        warning: None,
+        effective_config_requested: false,
+        force_reinstall: false,
+        allow_downgrade: false,
+        config: None,
+        config_hash: None,
+        scripts: None,
+        deploy_type: None,
+        urgent: false,
+        survey: Vec::new(),
+        next_poll_secs: None,
+    }))
+}
+
+/// 서명 검증이 없다는 점을 시작 시 크게 경고 (DM_UPDATE_PUBLIC_KEY가 없을 때)
+pub fn warn_unsigned(manifest_url: &str) {
+    tracing::warn!("==================================================================");
+    tracing::warn!("⚠️  STATIC MODE: artifacts are NOT signature-verified.");
+    tracing::warn!("⚠️  Updates are trusted by the manifest checksum alone; anyone who");
+    tracing::warn!("⚠️  can modify {} can install code on this device.", manifest_url);
+    tracing::warn!("⚠️  Set DM_UPDATE_PUBLIC_KEY to require signed artifacts.");
+    tracing::warn!("==================================================================");
+
+    if !manifest_url.starts_with("https://") {
+        tracing::error!(
+            "Manifest URL is not HTTPS ({}); manifest and artifact can be tampered with in transit",
+            manifest_url
+        );
+    }
+}
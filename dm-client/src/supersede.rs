@@ -0,0 +1,46 @@
+use std::fmt;
+
+use crate::api::CheckinResponse;
+
+/// 진행 중인 업데이트의 타겟이 서버에서 바뀌거나 취소됨 (설치 전이므로 롤백 없이 중단)
+///
+/// 서버에는 `failure_reason: "superseded"`로 보고하고, 새 타겟이 있으면 바로 이어서 진행한다.
+#[derive(Debug)]
+pub struct Superseded {
+    /// 중단한 업데이트의 버전
+    pub version: String,
+    /// 확인 체크인에서 받은 새 업데이트 명령 (취소되었으면 None)
+    pub next: Option<Box<CheckinResponse>>,
+}
+
+impl Superseded {
+    /// 업데이트 중 확인 체크인 응답 비교 (타겟이 그대로면 None)
+    ///
+    /// 같은 타겟의 업데이트 명령이나 대기열 작업(재시작 등)은 계속 진행하고, 다른 버전의
+    /// 업데이트/스테이징/활성화는 새 타겟으로, 명령이 없으면(취소, 검사 보류, 고정) 취소로 본다.
+    pub fn check(offer: &CheckinResponse, response: CheckinResponse) -> Option<Self> {
+        let version = offer.target_version.clone().unwrap_or_default();
+        if response.action_id.is_some() {
+            return None;
+        }
+        match response.action.as_str() {
+            "update" | "stage" | "activate" if response.target_version == offer.target_version => None,
+            "update" | "stage" | "activate" => Some(Self {
+                version,
+                next: Some(Box::new(response)),
+            }),
+            _ => Some(Self { version, next: None }),
+        }
+    }
+}
+
+impl fmt::Display for Superseded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.next.as_ref().and_then(|next| next.target_version.as_deref()) {
+            Some(next) => write!(f, "update to {} superseded by {}", self.version, next),
+            None => write!(f, "update to {} cancelled on the server", self.version),
+        }
+    }
+}
+
+impl std::error::Error for Superseded {}
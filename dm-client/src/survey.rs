@@ -0,0 +1,191 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::time::Duration;
+use sysinfo::{Disks, Networks, System};
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+
+/// 실행할 수 있는 조사 항목 (클라이언트에 컴파일된 허용 목록, 서버 설정으로 바꿀 수 없음)
+pub const PRESETS: [&str; 5] = ["system", "disks", "network", "lsblk", "ip_addr"];
+
+/// 항목별 제한 시간
+const COLLECTOR_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 명령 항목의 최대 출력 크기 (넘으면 해당 항목 실패)
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// 업로드할 결과 최대 크기 (서버 제한과 같음, 넘는 항목은 빼고 실패로 기록)
+const MAX_SURVEY_BYTES: usize = 256 * 1024;
+
+/// 명령 항목이 쓰는 PATH (장비의 환경 변수와 무관하게 고정)
+const COMMAND_PATH: &str = "/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// 조사 결과 (항목별 결과와 수집하지 못한 항목의 사유)
+#[derive(Debug, Default, Serialize)]
+pub struct SurveyReport {
+    pub results: Map<String, Value>,
+    pub errors: BTreeMap<String, String>,
+}
+
+/// 요청된 항목을 순서대로 수집 (허용 목록에 없는 이름은 실행하지 않고 실패로 기록)
+pub async fn collect(commands: &[String]) -> SurveyReport {
+    let mut report = SurveyReport::default();
+    let mut size = 0;
+
+    for name in commands {
+        if report.results.contains_key(name) || report.errors.contains_key(name) {
+            continue;
+        }
+        if !PRESETS.contains(&name.as_str()) {
+            tracing::warn!("Survey command {:?} is not in the client allowlist", name);
+            report
+                .errors
+                .insert(name.clone(), "not in the client allowlist".to_string());
+            continue;
+        }
+
+        let result = match tokio::time::timeout(COLLECTOR_TIMEOUT, run(name)).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow::anyhow!("timed out after {}s", COLLECTOR_TIMEOUT.as_secs())),
+        };
+        match result {
+            Ok(value) => {
+                let len = serde_json::to_vec(&value).map(|v| v.len()).unwrap_or_default() + name.len();
+                if size + len > MAX_SURVEY_BYTES {
+                    report
+                        .errors
+                        .insert(name.clone(), format!("result exceeds the {} byte survey limit", MAX_SURVEY_BYTES));
+                    continue;
+                }
+                size += len;
+                report.results.insert(name.clone(), value);
+            }
+            Err(e) => {
+                tracing::warn!("Survey {} failed: {}", name, e);
+                report.errors.insert(name.clone(), e.to_string());
+            }
+        }
+    }
+
+    report
+}
+
+async fn run(name: &str) -> Result<Value> {
+    match name {
+        "system" => blocking(system).await,
+        "disks" => blocking(disks).await,
+        "network" => blocking(network).await,
+        "lsblk" => {
+            run_command(
+                "lsblk",
+                &["--json", "--bytes", "-o", "NAME,SIZE,TYPE,MOUNTPOINT,MODEL,SERIAL,TRAN"],
+            )
+            .await
+        }
+        "ip_addr" => run_command("ip", &["-json", "addr", "show"]).await,
+        other => anyhow::bail!("not in the client allowlist: {}", other),
+    }
+}
+
+async fn blocking(collector: fn() -> Value) -> Result<Value> {
+    tokio::task::spawn_blocking(collector)
+        .await
+        .context("Survey collector panicked")
+}
+
+/// OS, CPU, 메모리
+fn system() -> Value {
+    let mut sys = System::new();
+    sys.refresh_memory();
+    sys.refresh_cpu();
+    json!({
+        "hostname": System::host_name(),
+        "os": System::long_os_version(),
+        "kernel": System::kernel_version(),
+        "arch": System::cpu_arch(),
+        "uptime_secs": System::uptime(),
+        "boot_time": System::boot_time(),
+        "cpu_count": sys.cpus().len(),
+        "cpu_brand": sys.cpus().first().map(|cpu| cpu.brand().to_string()),
+        "memory_total_bytes": sys.total_memory(),
+        "memory_used_bytes": sys.used_memory(),
+        "swap_total_bytes": sys.total_swap(),
+        "swap_used_bytes": sys.used_swap(),
+    })
+}
+
+/// 마운트된 디스크
+fn disks() -> Value {
+    let disks = Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .map(|disk| {
+            json!({
+                "name": disk.name().to_string_lossy(),
+                "mount_point": disk.mount_point().to_string_lossy(),
+                "file_system": disk.file_system().to_string_lossy(),
+                "kind": format!("{:?}", disk.kind()),
+                "removable": disk.is_removable(),
+                "total_bytes": disk.total_space(),
+                "available_bytes": disk.available_space(),
+            })
+        })
+        .collect()
+}
+
+/// 네트워크 인터페이스
+fn network() -> Value {
+    let networks = Networks::new_with_refreshed_list();
+    let mut interfaces: Vec<_> = networks.iter().collect();
+    interfaces.sort_by(|a, b| a.0.cmp(b.0));
+    interfaces
+        .into_iter()
+        .map(|(name, data)| {
+            json!({
+                "name": name,
+                "mac": data.mac_address().to_string(),
+                "received_bytes": data.total_received(),
+                "transmitted_bytes": data.total_transmitted(),
+            })
+        })
+        .collect()
+}
+
+/// 고정된 인자로 명령 실행 (셸과 장비 환경 변수 없이, 출력은 MAX_OUTPUT_BYTES까지)
+///
+/// 출력이 JSON이면 그대로, 아니면 문자열로 담는다. 제한 시간을 넘기면 프로세스를 종료한다.
+async fn run_command(program: &str, args: &[&str]) -> Result<Value> {
+    let mut child = Command::new(program)
+        .args(args)
+        .env_clear()
+        .env("PATH", COMMAND_PATH)
+        .env("LC_ALL", "C")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+
+    let mut output = Vec::new();
+    let stdout = child.stdout.take().context("stdout not captured")?;
+    stdout
+        .take(MAX_OUTPUT_BYTES as u64 + 1)
+        .read_to_end(&mut output)
+        .await
+        .with_context(|| format!("Failed to read {} output", program))?;
+    if output.len() > MAX_OUTPUT_BYTES {
+        anyhow::bail!("{} output exceeds {} bytes", program, MAX_OUTPUT_BYTES);
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", program, status);
+    }
+    let text = String::from_utf8_lossy(&output);
+    Ok(serde_json::from_str(&text).unwrap_or_else(|_| Value::String(text.trim().to_string())))
+}
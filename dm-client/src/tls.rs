@@ -0,0 +1,92 @@
+use anyhow::Result;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::config::{Config, DaemonMode};
+use crate::error::ClientError;
+
+/// HTTPS 전환 가능 여부 확인 제한 시간
+const UPGRADE_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 평문 HTTP로 다른 장비에 연결하는 URL인지 (loopback은 네트워크를 지나지 않으므로 제외)
+pub fn is_plaintext_remote(url: &str) -> bool {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    if parsed.scheme() != "http" {
+        return false;
+    }
+    let Some(host) = parsed.host_str() else {
+        return false;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(ip) => !ip.is_loopback(),
+        Err(_) => !host.eq_ignore_ascii_case("localhost"),
+    }
+}
+
+/// 시작 시 서버 URL 점검
+///
+/// 평문 HTTP면 API Key가 그대로 전송된다고 경고하고, DM_REQUIRE_TLS=true면 시작을 거부한다.
+pub fn check_startup(config: &Config) -> Result<()> {
+    let (name, url) = match config.mode {
+        DaemonMode::Server => ("DM_SERVER_URL", config.server_url.as_str()),
+        DaemonMode::Static => ("DM_MANIFEST_URL", config.manifest_url.as_deref().unwrap_or_default()),
+    };
+    if !is_plaintext_remote(url) {
+        return Ok(());
+    }
+    if config.require_tls {
+        anyhow::bail!(ClientError::Config(format!(
+            "DM_REQUIRE_TLS=true but {} uses plain HTTP: {}",
+            name, url
+        )));
+    }
+    tracing::warn!(
+        "⚠️  {} uses plain HTTP ({}): the API key and artifacts travel unencrypted. Switch to https:// or set DM_REQUIRE_TLS=true once migrated",
+        name,
+        url
+    );
+    Ok(())
+}
+
+/// DM_REQUIRE_TLS=true일 때 서버가 내려준 평문 HTTP 아티팩트/미러 URL 거부
+///
+/// 상대 경로는 시작 시 점검한 서버 URL 기준이므로 허용한다.
+pub fn check_download_url(config: &Config, url: &str) -> Result<()> {
+    if config.require_tls && is_plaintext_remote(url) {
+        anyhow::bail!(ClientError::Config(format!(
+            "DM_REQUIRE_TLS=true refuses plain HTTP artifact URL: {}",
+            url.split('?').next().unwrap_or(url)
+        )));
+    }
+    Ok(())
+}
+
+/// 평문 HTTP 서버 URL의 같은 호스트가 HTTPS(기본 포트)로도 응답하면 그 URL 반환
+///
+/// 시작 시 한 번만 시도하며, 실패해도 아무것도 바꾸지 않는다 (전환은 운영자가 설정으로).
+pub async fn https_upgrade(server_url: &str) -> Option<String> {
+    if !is_plaintext_remote(server_url) {
+        return None;
+    }
+    let mut url = reqwest::Url::parse(server_url).ok()?;
+    url.set_scheme("https").ok()?;
+    url.set_port(None).ok()?;
+    let upgraded = url.as_str().trim_end_matches('/').to_string();
+
+    let client = reqwest::Client::builder().timeout(UPGRADE_PROBE_TIMEOUT).build().ok()?;
+    let response = client.get(format!("{}/health", upgraded)).send().await;
+    match response {
+        Ok(response) if response.status().is_success() => Some(upgraded),
+        Ok(response) => {
+            tracing::debug!("HTTPS upgrade probe: {} answered {}", upgraded, response.status());
+            None
+        }
+        Err(e) => {
+            tracing::debug!("HTTPS upgrade probe: {} unreachable: {}", upgraded, e);
+            None
+        }
+    }
+}
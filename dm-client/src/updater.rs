@@ -1,33 +1,208 @@
 use anyhow::{Context, Result};
 use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::Path;
-use std::process::Command;
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
 use tar::Archive;
-use tempfile::TempDir;
 
+use crate::backup::{self, BackupEntry, BackupMeta, ExcludeSet};
 use crate::config::Config;
+use crate::deadline::{Deadline, UpdatePhase};
+use crate::error::ClientError;
+use crate::health::{self, HealthProbe, ProbeKind};
+use crate::installfs::{self, InstallStrategy};
+use crate::product;
+use crate::progress::{Progress, Unit};
+use crate::state::{LocalState, RestorePoint};
+
+const VERSION_FILE: &str = ".dm-version";
+
+/// 해시 계산 단위 (진행률 갱신 주기)
+const HASH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// 명령 종료 확인 주기
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 실패 메시지에 남기는 명령 출력 줄 수 (stderr, stdout 각각 마지막 줄)
+const OUTPUT_TAIL_LINES: usize = 20;
+
+/// 백업 실패 메시지에 나열하는 최대 경로 수
+const MAX_REPORTED_BACKUP_FAILURES: usize = 10;
+
+/// 설치 전 아티팩트 검사 결과
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtifactInfo {
+    /// 일반 파일 수
+    pub files: u64,
+    /// 압축을 푼 일반 파일 크기 합 (바이트)
+    pub total_bytes: u64,
+}
 
 /// 서비스 업데이터
+#[derive(Clone)]
 pub struct Updater {
     config: Config,
+    /// 업데이트 전체 기한 (없으면 단계별 제한 시간만 적용)
+    deadline: Option<Deadline>,
 }
 
 impl Updater {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            deadline: None,
+        }
+    }
+
+    /// 전체 기한 안에서만 동작하는 업데이터 (기한이 지나면 현재 단계에서 중단)
+    pub fn with_deadline(&self, deadline: Deadline) -> Self {
+        Self {
+            config: self.config.clone(),
+            deadline: Some(deadline),
+        }
     }
 
-    /// 체크섬 검증
-    pub fn verify_checksum(&self, data: &[u8], expected: &str) -> bool {
-        let mut hasher = Sha256::new();
-        hasher.update(data);
-        let actual = format!("{:x}", hasher.finalize());
-        actual == expected
+    /// 적용 중인 설정
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn check_deadline(&self, phase: UpdatePhase) -> Result<()> {
+        match &self.deadline {
+            Some(deadline) => deadline.check(phase),
+            None => Ok(()),
+        }
+    }
+
+    /// 아티팩트를 끝까지 풀어 보며 설치할 수 있는지 확인 (백업과 설치 전, 디스크에 쓰지 않음)
+    ///
+    /// 체크섬은 다운로드만 보장하므로, 잘리거나 손상된 압축/tar/zip, 절대 경로나 `..`가 든 항목,
+    /// 일반 파일이 하나도 없는 아카이브를 여기서 거부한다. 형식은 파일 이름이 아니라 시작 바이트로 판별한다.
+    pub fn validate_artifact(&self, artifact: &Path) -> Result<ArtifactInfo> {
+        let invalid = |reason: String| ClientError::Install(format!("Invalid artifact: {}", reason));
+        let info = match ArchiveFormat::detect(artifact) {
+            Ok(ArchiveFormat::Zip) => scan_zip(artifact),
+            Ok(format) => scan_tar(artifact, format),
+            Err(e) => Err(e.to_string()),
+        }
+        .map_err(invalid)?;
+
+        if info.files == 0 {
+            anyhow::bail!(invalid("archive contains no regular files".to_string()));
+        }
+        tracing::info!(
+            "Artifact contains {} files ({} bytes unpacked)",
+            info.files,
+            info.total_bytes
+        );
+        Ok(info)
+    }
+
+    /// 빈 장비의 첫 설치인지 (서비스 디렉토리가 없거나 dm-client 상태 파일만 있음)
+    ///
+    /// 백업하거나 롤백할 이전 설치가 없으므로 첫 설치는 둘 다 건너뛴다.
+    pub fn is_first_install(&self) -> bool {
+        match fs::read_dir(&self.config.service_dir) {
+            Ok(entries) => entries
+                .flatten()
+                .all(|entry| entry.file_name().to_string_lossy().starts_with(".dm-")),
+            Err(e) => e.kind() == std::io::ErrorKind::NotFound,
+        }
+    }
+
+    /// 실패한 첫 설치 정리 (dm-client 상태 파일만 남기고 비워 다음 시도도 첫 설치로 진행)
+    pub fn clear_first_install(&self) -> Result<()> {
+        let entries = match fs::read_dir(&self.config.service_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            if name.to_string_lossy().starts_with(".dm-") && name != VERSION_FILE {
+                continue;
+            }
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 첫 설치 후 첫 재시작 전 DM_BOOTSTRAP_COMMAND 실행 (서비스 디렉토리에서)
+    pub fn run_bootstrap_command(&self) -> Result<()> {
+        let Some(bootstrap_command) = &self.config.bootstrap_command else {
+            return Ok(());
+        };
+
+        tracing::info!("Running bootstrap command: {}", bootstrap_command);
+        let mut command = shell(bootstrap_command);
+        command.current_dir(&self.config.service_dir);
+        let (status, stderr) = self.run_process(command, UpdatePhase::PostInstall, bootstrap_command)?;
+        if !status.success() {
+            anyhow::bail!(ClientError::Install(format!(
+                "Bootstrap command failed ({}): {}",
+                status,
+                stderr.trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// 업데이트 전 명령 실행 (백업 후 설치 전, 실패하면 서비스 디렉토리를 바꾸기 전에 중단)
+    pub fn run_pre_update_script(&self, from_version: &str, to_version: &str) -> Result<()> {
+        match &self.config.pre_update_script {
+            Some(script) => self.run_update_script(UpdatePhase::PreInstall, "Pre-update script", script, from_version, to_version),
+            None => Ok(()),
+        }
+    }
+
+    /// 업데이트 후 명령 실행 (헬스 체크 통과 후, 실패 처리는 호출자가 rollback_on_failure로 결정)
+    pub fn run_post_update_script(&self, from_version: &str, to_version: &str) -> Result<()> {
+        match &self.config.post_update_script {
+            Some(script) => self.run_update_script(UpdatePhase::PostInstall, "Post-update script", script, from_version, to_version),
+            None => Ok(()),
+        }
+    }
+
+    /// 업데이트 전/후 명령 실행 (서비스 디렉토리에서, DM_FROM_VERSION/DM_TO_VERSION 전달)
+    fn run_update_script(
+        &self,
+        phase: UpdatePhase,
+        name: &str,
+        script: &str,
+        from_version: &str,
+        to_version: &str,
+    ) -> Result<()> {
+        tracing::info!("Running {}: {}", name, script);
+        let mut command = shell(script);
+        if Path::new(&self.config.service_dir).is_dir() {
+            command.current_dir(&self.config.service_dir);
+        }
+        command
+            .env("DM_FROM_VERSION", from_version)
+            .env("DM_TO_VERSION", to_version)
+            .env("DM_SERVICE_DIR", &self.config.service_dir);
+        let (status, stderr) = self.run_process(command, phase, script)?;
+        if !status.success() {
+            anyhow::bail!(ClientError::Install(format!("{} failed ({}): {}", name, status, stderr.trim())));
+        }
+        Ok(())
     }
 
     /// 현재 서비스 백업
+    ///
+    /// 복사하지 못한 파일이 있으면 끝까지 시도한 뒤 실패한 경로를 모두 담아 에러를 반환하고,
+    /// 불완전한 백업은 지운다. `backup_required`가 꺼져 있으면 백업 없이 빈 경로를 반환한다.
     pub fn backup_current(&self, version: &str) -> Result<String> {
         let service_dir = Path::new(&self.config.service_dir);
         
@@ -36,49 +211,132 @@ impl Updater {
             return Ok(String::new());
         }
 
+        if !self.config.backup_required {
+            tracing::warn!(
+                "⚠️  BACKUP SKIPPED (DM_BACKUP_REQUIRED=false / --no-backup): {} will be replaced without a backup and cannot be rolled back",
+                version
+            );
+            return Ok(String::new());
+        }
+
         let backup_dir = Path::new(&self.config.backup_dir);
         fs::create_dir_all(backup_dir)?;
 
-        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-        let backup_name = format!("backup_{}_{}", version, timestamp);
-        let backup_path = backup_dir.join(&backup_name);
+        let mut name = backup::backup_name(version, chrono::Utc::now());
+        if self.config.backup_compress {
+            name.push_str(backup::ARCHIVE_SUFFIX);
+        }
+        let backup_path = backup_dir.join(name);
 
         tracing::info!("Creating backup at {:?}", backup_path);
 
-        // Copy service directory to backup
-        copy_dir_recursive(service_dir, &backup_path)?;
+        // 서비스 디렉토리를 tar.gz로 묶거나(DM_BACKUP_COMPRESS) 그대로 복사 (제외 패턴이 없으면 전체)
+        let state = LocalState::load(&self.config.service_dir);
+        let excludes = ExcludeSet::new(&state.effective_backup_exclude(&self.config));
+        let report = if self.config.backup_compress {
+            archive_dir_excluding(service_dir, &backup_path, &excludes)?
+        } else {
+            copy_dir_excluding(service_dir, &backup_path, &excludes)?
+        };
+        if !report.failed.is_empty() {
+            // 불완전한 백업이 롤백에 쓰이지 않도록 삭제
+            if let Err(e) = backup::remove(&backup_path) {
+                tracing::warn!("Failed to remove incomplete backup {:?}: {}", backup_path, e);
+            }
+            return Err(backup_failed(service_dir, report.failed));
+        }
+        if !excludes.is_empty() {
+            if !report.excluded.is_empty() {
+                tracing::info!("Excluded from backup: {}", report.excluded.join(", "));
+            }
+            BackupMeta {
+                exclude: excludes.patterns().to_vec(),
+                excluded: report.excluded,
+            }
+            .save(&backup_path)?;
+        }
+
+        // 오래된 백업 정리 (방금 만든 백업과 현재 복원 지점은 유지)
+        let name = backup_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let restore_point = state.restore_point_name();
+        let mut protect = vec![name.as_str()];
+        protect.extend(restore_point.as_deref());
+        let pruned = backup::prune_backups(backup_dir, self.config.backup_keep, &protect);
+        if !pruned.is_empty() {
+            let names: Vec<&str> = pruned.iter().map(|p| p.name.as_str()).collect();
+            tracing::info!(
+                "Removed {} old backup(s) (DM_BACKUP_KEEP={}), reclaimed {} bytes: {}",
+                pruned.len(),
+                self.config.backup_keep,
+                pruned.iter().map(|p| p.bytes).sum::<u64>(),
+                names.join(", ")
+            );
+        }
 
         Ok(backup_path.to_string_lossy().to_string())
     }
 
-    /// 아티팩트 추출 및 설치
-    pub fn extract_and_install(&self, data: &[u8]) -> Result<()> {
-        let service_dir = Path::new(&self.config.service_dir);
-        
-        // Create temp directory for extraction
-        let temp_dir = TempDir::new()?;
-        let temp_path = temp_dir.path();
-
-        tracing::info!("Extracting artifact to {:?}", temp_path);
+    /// 아티팩트 추출 및 설치 (서비스 디렉토리 옆에 추출해 rename, 다른 파일시스템이면 복사). 사용한 방법 반환
+    ///
+    /// 새 트리를 옆 디렉토리에 다 옮긴 뒤에 교체하므로 중간에 실패해도 기존 서비스 디렉토리는 그대로다.
+    pub fn extract_and_install(&self, artifact: &Path) -> Result<InstallStrategy> {
+        let target = Path::new(&self.config.service_dir);
+        let work_dir = installfs::work_dir(self.config.work_dir.as_deref(), target)?;
+        let strategy = installfs::preflight(work_dir.path(), target);
+        let extracted_content = extract_archive(artifact, work_dir.path(), self.deadline.as_ref())?;
+        self.check_product(&extracted_content)?;
 
-        // Decompress and extract tar.gz
-        let tar = GzDecoder::new(data);
-        let mut archive = Archive::new(tar);
-        archive.unpack(temp_path).context("Failed to extract archive")?;
+        self.check_deadline(UpdatePhase::Install)?;
+        let preserve = self.preserve_paths()?;
+        tracing::info!("Installing to {:?} ({})", target, strategy.as_str());
+        replace_service_dir(target, &preserve, |dir| move_tree(&extracted_content, dir, strategy, "Installing"))
+    }
 
-        // Find the extracted content (might be in a subdirectory)
-        let extracted_content = find_extracted_root(temp_path)?;
+    /// 설치와 롤백에서 그대로 둘 서비스 디렉토리 안의 경로 (DM_PRESERVE_PATHS > 서버 지정 경로)
+    pub fn preserve_paths(&self) -> Result<Vec<PathBuf>> {
+        installfs::preserve_paths(&LocalState::load(&self.config.service_dir).effective_preserve_paths(&self.config))
+    }
 
-        // Clear existing service directory
-        if service_dir.exists() {
-            fs::remove_dir_all(service_dir)?;
-        }
-        fs::create_dir_all(service_dir)?;
+    /// 추출된 트리의 `.dm-product`가 기대 제품과 같은지 확인 (서비스 디렉토리 변경 전)
+    fn check_product(&self, extracted: &Path) -> Result<()> {
+        let expected = LocalState::load(&self.config.service_dir).effective_product(&self.config);
+        product::check(extracted, expected.as_deref())
+    }
 
-        // Copy extracted content to service directory
+    /// 추출된 트리를 서비스 디렉토리에 복사해 설치 (원본 유지, 스테이징 활성화용)
+    pub fn install_from(&self, source: &Path) -> Result<()> {
+        let service_dir = Path::new(&self.config.service_dir);
+        let preserve = self.preserve_paths()?;
         tracing::info!("Installing to {:?}", service_dir);
-        copy_dir_recursive(&extracted_content, service_dir)?;
+        replace_service_dir(service_dir, &preserve, |dir| copy_dir_with_progress(source, dir, "Installing"))
+    }
+
+    /// 아티팩트를 스테이징 디렉토리에 추출 (서비스 디렉토리는 변경하지 않음)
+    pub fn stage(&self, artifact: &Path, version: &str) -> Result<PathBuf> {
+        // 이전 스테이징은 하나만 유지
+        self.clear_staging()?;
+
+        let staging_dir = Path::new(&self.config.staging_dir);
+        let work_dir = installfs::work_dir(self.config.work_dir.as_deref(), staging_dir)?;
+        let strategy = installfs::preflight(work_dir.path(), staging_dir);
+        let extracted_content = extract_archive(artifact, work_dir.path(), self.deadline.as_ref())?;
+        self.check_product(&extracted_content)?;
 
+        let staged_path = staging_dir.join(version);
+        fs::create_dir_all(&staged_path)?;
+        tracing::info!("Staging to {:?} ({})", staged_path, strategy.as_str());
+        move_tree(&extracted_content, &staged_path, strategy, "Staging")?;
+
+        Ok(staged_path)
+    }
+
+    /// 스테이징 디렉토리 정리
+    pub fn clear_staging(&self) -> Result<()> {
+        let staging_dir = Path::new(&self.config.staging_dir);
+        if staging_dir.exists() {
+            tracing::info!("Removing staging directory {:?}", staging_dir);
+            fs::remove_dir_all(staging_dir)?;
+        }
         Ok(())
     }
 
@@ -86,73 +344,294 @@ impl Updater {
     pub fn restart_service(&self) -> Result<()> {
         tracing::info!("Restarting service: {}", self.config.restart_command);
 
-        let output = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", &self.config.restart_command])
-                .output()?
+        let (status, stderr) = self.run_command(&self.config.restart_command, UpdatePhase::Restart)?;
+        if !status.success() {
+            anyhow::bail!(ClientError::Install(format!("Restart command failed ({}): {}", status, stderr)));
+        }
+
+        tracing::info!("Service restarted successfully");
+        Ok(())
+    }
+
+    /// 적용할 헬스 체크 프로브 (설치로 상태 파일이 바뀌기 전에 확정)
+    pub fn health_probes(&self) -> Result<Vec<HealthProbe>> {
+        LocalState::load(&self.config.service_dir).effective_health_checks(&self.config)
+    }
+
+    /// 헬스 체크 (프로브를 순서대로 실행해 모두 통과해야 성공)
+    ///
+    /// 실패하면 DM_HEALTH_CHECK_INTERVAL_SECS 간격으로 DM_HEALTH_CHECK_ATTEMPTS번까지 다시 시도하고,
+    /// 마지막으로 실패한 프로브와 사유를 ClientError::HealthCheck 메시지로 반환해 서버 실패 보고에 남긴다.
+    /// 대기와 프로브가 스레드를 막으므로 데몬에서는 `spawn_blocking`으로 호출한다.
+    pub fn health_check(&self, probes: &[HealthProbe]) -> Result<()> {
+        if probes.is_empty() {
+            tracing::info!("No health check configured, assuming healthy");
+            return Ok(());
+        }
+
+        let attempts = self.config.health_check_attempts.max(1);
+        let interval = Duration::from_secs(self.config.health_check_interval_secs);
+        let mut attempt = 1;
+        loop {
+            let Some(failure) = self.run_probes(probes)? else {
+                return Ok(());
+            };
+            if attempt >= attempts {
+                anyhow::bail!(ClientError::HealthCheck(format!("Health check failed: {}", failure)));
+            }
+            tracing::warn!(
+                "Health check failed (attempt {}/{}): {}, retrying in {}s",
+                attempt,
+                attempts,
+                failure,
+                interval.as_secs()
+            );
+            self.check_deadline(UpdatePhase::HealthCheck)?;
+            let wait = match &self.deadline {
+                Some(deadline) => interval.min(deadline.remaining()),
+                None => interval,
+            };
+            std::thread::sleep(wait);
+            attempt += 1;
+        }
+    }
+
+    /// 프로브를 한 번씩 실행해 처음 실패한 프로브와 사유 반환 (기한 초과는 에러)
+    fn run_probes(&self, probes: &[HealthProbe]) -> Result<Option<String>> {
+        // health_check_timeout은 제한 시간을 지정하지 않은 프로브에만 적용
+        let default_timeout = |fallback: u64| Duration::from_secs(self.config.health_check_timeout_secs.unwrap_or(fallback));
+        for probe in probes {
+            self.check_deadline(UpdatePhase::HealthCheck)?;
+            tracing::info!("Running health check: {}", probe);
+            let failure = match &probe.kind {
+                ProbeKind::Command { command } => {
+                    let timeout = probe.timeout(default_timeout(self.config.command_timeout_secs));
+                    match self.run_process_within(shell(command), UpdatePhase::HealthCheck, command, timeout) {
+                        Ok((status, _)) if status.success() => None,
+                        Ok((status, stderr)) if stderr.trim().is_empty() => Some(status.to_string()),
+                        Ok((status, stderr)) => Some(format!("{}: {}", status, stderr.trim())),
+                        Err(e) => Some(e.to_string()),
+                    }
+                }
+                kind => {
+                    let timeout = probe.timeout(default_timeout(health::DEFAULT_PROBE_TIMEOUT_SECS));
+                    let timeout = match &self.deadline {
+                        Some(deadline) => timeout.min(deadline.remaining()),
+                        None => timeout,
+                    };
+                    health::check(kind, timeout).err()
+                }
+            };
+            if let Some(reason) = failure {
+                // 기한 초과로 실패했으면 단계 정보를 유지해 보고
+                self.check_deadline(UpdatePhase::HealthCheck)?;
+                return Ok(Some(format!("{}: {}", probe, reason)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 버전 설치 스크립트 실행 (실패하면 호출자가 롤백)
+    ///
+    /// 스크립트에는 DM_VERSION, DM_PREVIOUS_VERSION, DM_SERVICE_DIR 환경 변수가 주어진다.
+    pub fn run_install_script(
+        &self,
+        phase: UpdatePhase,
+        script: &str,
+        version: &str,
+        previous_version: &str,
+    ) -> Result<()> {
+        tracing::info!("Running {} install script for {}", phase, version);
+        self.check_deadline(phase)?;
+
+        let extension = if cfg!(target_os = "windows") { ".cmd" } else { ".sh" };
+        let mut file = tempfile::Builder::new()
+            .prefix("dm-script-")
+            .suffix(extension)
+            .tempfile()?;
+        std::io::Write::write_all(&mut file, script.as_bytes())?;
+        let path = file.into_temp_path();
+
+        let mut command = if cfg!(target_os = "windows") {
+            let mut command = Command::new("cmd");
+            command.arg("/C").arg(&path);
+            command
         } else {
-            Command::new("sh")
-                .args(["-c", &self.config.restart_command])
-                .output()?
+            let mut command = Command::new("sh");
+            command.arg(&path);
+            command
         };
+        command
+            .env("DM_VERSION", version)
+            .env("DM_PREVIOUS_VERSION", previous_version)
+            .env("DM_SERVICE_DIR", &self.config.service_dir);
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("Restart command failed: {}", stderr);
+        let (status, stderr) = self.run_process(command, phase, &format!("{} install script", phase))?;
+        if !status.success() {
+            let stderr = stderr.trim();
+            if stderr.is_empty() {
+                anyhow::bail!(ClientError::Install(format!("Install script {} failed ({})", phase, status)));
+            }
+            anyhow::bail!(ClientError::Install(format!(
+                "Install script {} failed ({}): {}",
+                phase, status, stderr
+            )));
         }
-
-        tracing::info!("Service restarted successfully");
+        tracing::info!("{} install script completed", phase);
         Ok(())
     }
 
-    /// 헬스 체크
-    pub fn health_check(&self) -> Result<bool> {
-        let Some(cmd) = &self.config.health_check_command else {
-            tracing::info!("No health check command configured, assuming healthy");
-            return Ok(true);
+    /// 셸 명령 실행 (DM_COMMAND_TIMEOUT_SECS와 남은 전체 기한 중 짧은 쪽을 넘기면 종료)
+    pub fn run_command(&self, command: &str, phase: UpdatePhase) -> Result<(ExitStatus, String)> {
+        self.run_process(shell(command), phase, command)
+    }
+
+    /// 제외된 경로가 있는 백업으로 복원한 뒤 처리
+    ///
+    /// rollback_regenerate면 서비스 디렉토리에서 install_command를 실행해 다시 만들고,
+    /// 아니면 없는 경로를 경고만 한다 (서비스가 없어도 동작하거나 스스로 만드는 경우).
+    fn regenerate_excluded(&self, meta: &BackupMeta, regenerate: bool) -> Result<()> {
+        if meta.excluded.is_empty() {
+            return Ok(());
+        }
+        let missing = meta.excluded.join(", ");
+
+        if !regenerate {
+            tracing::warn!("Restored backup does not contain excluded paths: {}", missing);
+            return Ok(());
+        }
+        let Some(install_command) = &self.config.install_command else {
+            tracing::warn!(
+                "Rollback regeneration is enabled but DM_INSTALL_COMMAND is not set; missing paths: {}",
+                missing
+            );
+            return Ok(());
         };
 
-        tracing::info!("Running health check: {}", cmd);
+        tracing::info!("Regenerating excluded paths ({}): {}", missing, install_command);
+        let mut command = shell(install_command);
+        command.current_dir(&self.config.service_dir);
+        let (status, stderr) = self.run_process(command, UpdatePhase::Install, install_command)?;
+        if !status.success() {
+            anyhow::bail!(ClientError::RollbackFailed(format!(
+                "Install command failed after rollback ({}): {}",
+                status,
+                stderr.trim()
+            )));
+        }
+        Ok(())
+    }
 
-        // Wait a bit for service to start
-        std::thread::sleep(std::time::Duration::from_secs(5));
+    /// 프로세스 실행 (제한 시간을 넘기면 종료)
+    ///
+    /// 출력이 많아도 파이프가 막히지 않도록 stdout/stderr는 임시 파일로 받는다.
+    /// (종료 상태, 출력 끝부분) 반환 (`output_tail` 참고)
+    fn run_process(&self, command: Command, phase: UpdatePhase, label: &str) -> Result<(ExitStatus, String)> {
+        let command_timeout = Duration::from_secs(self.config.command_timeout_secs);
+        self.run_process_within(command, phase, label, command_timeout)
+    }
 
-        let output = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", cmd])
-                .output()?
-        } else {
-            Command::new("sh")
-                .args(["-c", cmd])
-                .output()?
+    /// 주어진 제한 시간으로 프로세스 실행 (남은 전체 기한이 더 짧으면 기한까지)
+    fn run_process_within(
+        &self,
+        mut command: Command,
+        phase: UpdatePhase,
+        label: &str,
+        command_timeout: Duration,
+    ) -> Result<(ExitStatus, String)> {
+        let (timeout, limited_by_deadline) = match &self.deadline {
+            Some(deadline) if deadline.remaining() < command_timeout => (deadline.remaining(), true),
+            _ => (command_timeout, false),
+        };
+
+        let mut stdout_file = tempfile::tempfile()?;
+        let mut stderr_file = tempfile::tempfile()?;
+        let mut child = command
+            .stdout(Stdio::from(stdout_file.try_clone()?))
+            .stderr(Stdio::from(stderr_file.try_clone()?))
+            .spawn()?;
+        let mut read_output = || -> Result<String> {
+            let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+            stdout_file.rewind()?;
+            stdout_file.read_to_end(&mut stdout)?;
+            stderr_file.rewind()?;
+            stderr_file.read_to_end(&mut stderr)?;
+            Ok(output_tail(&stdout, &stderr))
+        };
+
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if started.elapsed() >= timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                if limited_by_deadline {
+                    if let Some(deadline) = &self.deadline {
+                        return Err(deadline.timed_out(phase));
+                    }
+                }
+                let output = read_output().unwrap_or_default();
+                if output.is_empty() {
+                    anyhow::bail!("{} command timed out after {}s: {}", phase, timeout.as_secs(), label);
+                }
+                anyhow::bail!(
+                    "{} command timed out after {}s: {}\n{}",
+                    phase,
+                    timeout.as_secs(),
+                    label,
+                    output
+                );
+            }
+            std::thread::sleep(COMMAND_POLL_INTERVAL);
         };
 
-        Ok(output.status.success())
+        Ok((status, read_output()?))
+    }
+
+    /// 수동 롤백 (`dm-client rollback`): 복원과 재시작 후 버전 파일을 백업 버전으로 맞추고 헬스 체크
+    ///
+    /// 헬스 체크가 실패해도 다시 롤백하지 않는다 (복원한 트리가 마지막으로 알려진 정상 버전).
+    pub fn rollback_to(&self, target: &BackupEntry, probes: &[HealthProbe]) -> Result<()> {
+        self.rollback(&target.path.to_string_lossy())?;
+        fs::write(Path::new(&self.config.service_dir).join(VERSION_FILE), &target.version)?;
+        self.health_check(probes)
     }
 
     /// 백업에서 복원 (롤백)
     pub fn rollback(&self, backup_path: &str) -> Result<()> {
         if backup_path.is_empty() {
-            anyhow::bail!("No backup available for rollback");
+            anyhow::bail!(ClientError::RollbackFailed("No backup available for rollback".to_string()));
         }
 
         let backup_dir = Path::new(backup_path);
         let service_dir = Path::new(&self.config.service_dir);
 
         if !backup_dir.exists() {
-            anyhow::bail!("Backup directory not found: {}", backup_path);
+            anyhow::bail!(ClientError::RollbackFailed(format!("Backup directory not found: {}", backup_path)));
         }
 
         tracing::info!("Rolling back from {:?}", backup_dir);
+        let meta = BackupMeta::load(backup_dir);
+        let regenerate = LocalState::load(&self.config.service_dir).effective_rollback_regenerate(&self.config);
+        let preserve = self.preserve_paths()?;
 
-        // Clear current service directory
-        if service_dir.exists() {
-            fs::remove_dir_all(service_dir)?;
+        // Restore from backup (복원이 끝난 뒤 현재 디렉토리와 교체, 압축 백업은 풀어서. 보존 경로는 현재 것 유지)
+        if backup_dir.is_file() {
+            replace_service_dir(service_dir, &preserve, |dir| unpack_backup(backup_dir, dir))?;
+        } else {
+            replace_service_dir(service_dir, &preserve, |dir| copy_dir_with_progress(backup_dir, dir, "Restoring"))?;
         }
-        fs::create_dir_all(service_dir)?;
+        self.regenerate_excluded(&meta, regenerate)?;
 
-        // Restore from backup
-        copy_dir_recursive(backup_dir, service_dir)?;
+        // 복원된 백업을 현재 트리의 복원 지점으로 기록 (다음 업데이트 성공 전까지 보호)
+        let mut state = LocalState::load(&self.config.service_dir);
+        state.restore_point = Some(RestorePoint {
+            backup: backup_path.to_string(),
+            restored_at: chrono::Utc::now(),
+        });
+        state.save(&self.config)?;
 
         // Restart service
         self.restart_service()?;
@@ -162,8 +641,682 @@ impl Updater {
     }
 }
 
-/// 디렉토리 재귀 복사
-fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
+/// 실패 메시지용 명령 출력 끝부분 (stderr 마지막 줄, stdout이 있으면 `stdout:` 아래에 이어서)
+fn output_tail(stdout: &[u8], stderr: &[u8]) -> String {
+    let tail = |bytes: &[u8]| {
+        let text = String::from_utf8_lossy(bytes);
+        let lines: Vec<&str> = text.trim_end().lines().collect();
+        lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n")
+    };
+    let (stdout, stderr) = (tail(stdout), tail(stderr));
+    match (stderr.is_empty(), stdout.is_empty()) {
+        (_, true) => stderr,
+        (true, false) => format!("stdout:\n{}", stdout),
+        (false, false) => format!("{}\nstdout:\n{}", stderr, stdout),
+    }
+}
+
+/// 셸 명령 (`sh -c` / `cmd /C`)
+fn shell(command: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    }
+}
+
+/// 마운트 포인트를 비우는 동안 보존 경로를 옮겨 두는 디렉토리 이름 접두사 (서비스 디렉토리 안)
+const PRESERVE_STASH_PREFIX: &str = ".dm-preserve-";
+/// 새로 채우는 서비스 디렉토리 이름 접미사 (`<서비스 디렉토리>.new-<시각>`)
+const SWAP_NEW_SUFFIX: &str = ".new-";
+/// 교체되어 지울 이전 서비스 디렉토리 이름 접미사 (`<서비스 디렉토리>.old-<시각>`)
+const SWAP_OLD_SUFFIX: &str = ".old-";
+
+/// 서비스 디렉토리를 새 트리로 교체. `fill`이 채울 디렉토리를 받는다
+///
+/// - 일반 디렉토리: 옆의 `<이름>.new-<시각>`을 채운 뒤 기존 디렉토리를 `<이름>.old-<시각>`으로,
+///   새 디렉토리를 제자리로 rename하고 나서 이전 디렉토리를 지운다. 교체 전에 실패하면
+///   새 디렉토리만 지우므로 기존 디렉토리는 그대로 남는다
+/// - 심볼릭 링크: 링크는 유지하고 대상 디렉토리를 교체 (대상이 없으면 생성)
+/// - 마운트 포인트: 디렉토리 자체는 바꿀 수 없으므로 내용을 비운 뒤 그 안에 설치
+///
+/// 새 디렉토리는 서비스 디렉토리 옆이라 작업 디렉토리에서 rename으로 옮길 수 있다.
+/// 마운트 포인트는 부모 디렉토리와 파일시스템이 달라 작업 디렉토리를 옆에 두면 복사로 설치된다.
+///
+/// `preserve`의 경로(.env, data/ 등)는 교체 직전에 새 트리로 rename해 그대로 유지한다 (새 트리에 같은
+/// 경로가 있으면 기존 것이 남음). 마운트 포인트는 비우기 전에 안쪽 임시 디렉토리로 옮겼다가 되돌린다.
+fn replace_service_dir<T>(
+    service_dir: &Path,
+    preserve: &[PathBuf],
+    fill: impl FnOnce(&Path) -> Result<T>,
+) -> Result<T> {
+    let meta = match fs::symlink_metadata(service_dir) {
+        Ok(meta) => Some(meta),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    if meta.as_ref().is_some_and(|m| m.file_type().is_symlink()) {
+        let link = fs::read_link(service_dir)?;
+        let target = match service_dir.parent() {
+            Some(parent) if link.is_relative() => parent.join(link),
+            _ => link,
+        };
+        fs::create_dir_all(&target)
+            .with_context(|| format!("Cannot create symlink target {:?}", target))?;
+        let target = fs::canonicalize(&target)?;
+        tracing::info!("Service directory is a symlink to {:?}, keeping the link", target);
+        return replace_service_dir(&target, preserve, fill);
+    }
+
+    let exists = meta.is_some();
+    if exists && is_mount_point(service_dir)? {
+        tracing::info!("Service directory {:?} is a mount point, clearing its contents", service_dir);
+        let stash = service_dir.join(format!("{}{}", PRESERVE_STASH_PREFIX, chrono::Utc::now().timestamp_micros()));
+        if !preserve.is_empty() {
+            fs::create_dir(&stash).with_context(|| format!("Cannot create {:?}", stash))?;
+        }
+        let kept = installfs::move_preserved(service_dir, &stash, preserve)?;
+        for entry in fs::read_dir(service_dir)? {
+            let entry = entry?;
+            if entry.path() == stash {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                fs::remove_dir_all(entry.path())?;
+            } else {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        let filled = fill(service_dir);
+        if !preserve.is_empty() {
+            let returned = installfs::move_preserved(&stash, service_dir, &kept)
+                .with_context(|| format!("Preserved paths were left in {:?}", stash))?;
+            log_preserved(&returned);
+            discard(&stash);
+        }
+        return filled;
+    }
+
+    let name = service_dir
+        .file_name()
+        .with_context(|| format!("Service directory {:?} has no name", service_dir))?
+        .to_string_lossy()
+        .into_owned();
+    let parent = match service_dir.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    fs::create_dir_all(&parent)?;
+    // 중단된 이전 설치가 남긴 디렉토리 (서비스 디렉토리가 없으면 .old가 유일한 사본일 수 있어 남김)
+    installfs::clear_stale(&parent, &format!("{}{}", name, SWAP_NEW_SUFFIX));
+    if exists {
+        installfs::clear_stale(&parent, &format!("{}{}", name, SWAP_OLD_SUFFIX));
+    }
+
+    let stamp = chrono::Utc::now().format("%Y%m%d%H%M%S%f").to_string();
+    let staged = parent.join(format!("{}{}{}", name, SWAP_NEW_SUFFIX, stamp));
+    let previous = parent.join(format!("{}{}{}", name, SWAP_OLD_SUFFIX, stamp));
+    fs::create_dir(&staged).with_context(|| format!("Cannot create {:?}", staged))?;
+    let filled = match fill(&staged) {
+        Ok(filled) => filled,
+        Err(e) => {
+            tracing::warn!("Install into {:?} failed, leaving {:?} unchanged", staged, service_dir);
+            discard(&staged);
+            return Err(e);
+        }
+    };
+    let kept = match installfs::move_preserved(service_dir, &staged, preserve) {
+        Ok(kept) => kept,
+        Err(e) => {
+            discard(&staged);
+            return Err(e);
+        }
+    };
+
+    if exists {
+        if let Err(e) = fs::rename(service_dir, &previous) {
+            installfs::return_preserved(&staged, service_dir, &kept);
+            discard(&staged);
+            return Err(e).with_context(|| format!("Failed to move {:?} aside", service_dir));
+        }
+    }
+    if let Err(e) = fs::rename(&staged, service_dir) {
+        if exists {
+            if let Err(e) = fs::rename(&previous, service_dir) {
+                tracing::error!("Failed to put {:?} back at {:?}: {}", previous, service_dir, e);
+            }
+            installfs::return_preserved(&staged, service_dir, &kept);
+        }
+        discard(&staged);
+        return Err(e).with_context(|| format!("Failed to move {:?} into place", staged));
+    }
+    log_preserved(&kept);
+    if exists {
+        discard(&previous);
+    }
+    Ok(filled)
+}
+
+/// 교체 후에도 유지한 보존 경로 로그
+fn log_preserved(kept: &[PathBuf]) {
+    if !kept.is_empty() {
+        let names: Vec<String> = kept.iter().map(|p| p.display().to_string()).collect();
+        tracing::info!("Preserved {} path(s) across the swap: {}", kept.len(), names.join(", "));
+    }
+}
+
+/// 교체에 쓰고 남은 디렉토리 삭제 (실패해도 다음 설치에서 다시 지움)
+fn discard(dir: &Path) {
+    if let Err(e) = fs::remove_dir_all(dir) {
+        tracing::warn!("Failed to remove {:?}: {}", dir, e);
+    }
+}
+
+/// 작업 디렉토리의 트리를 대상 디렉토리(비어 있음)로 이동. 실제로 사용한 방법 반환
+///
+/// rename이 EXDEV로 실패하면 (점검 후 마운트가 바뀐 경우 등) 남은 항목을 복사한다.
+fn move_tree(src: &Path, dst: &Path, strategy: InstallStrategy, label: &str) -> Result<InstallStrategy> {
+    if strategy == InstallStrategy::Rename {
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            match fs::rename(entry.path(), dst.join(entry.file_name())) {
+                Ok(()) => {}
+                Err(e) if installfs::is_cross_device(&e) => {
+                    tracing::warn!("rename into {:?} crossed filesystems, falling back to copy", dst);
+                    copy_dir_with_progress(src, dst, label)?;
+                    return Ok(InstallStrategy::Copy);
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to move {:?} into {:?}", entry.path(), dst));
+                }
+            }
+        }
+        return Ok(InstallStrategy::Rename);
+    }
+    copy_dir_with_progress(src, dst, label)?;
+    Ok(InstallStrategy::Copy)
+}
+
+/// 마운트 포인트 여부 (부모 디렉토리와 장치 ID 비교)
+#[cfg(unix)]
+fn is_mount_point(dir: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = fs::canonicalize(dir)?;
+    match dir.parent() {
+        Some(parent) => Ok(fs::metadata(&dir)?.dev() != fs::metadata(parent)?.dev()),
+        None => Ok(true),
+    }
+}
+
+#[cfg(not(unix))]
+fn is_mount_point(_dir: &Path) -> Result<bool> {
+    Ok(false)
+}
+
+/// gzip 시작 바이트
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// zstd 프레임 시작 바이트
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// zip 로컬 파일 헤더 (항목이 없는 zip은 중앙 디렉토리 끝 레코드로 시작)
+const ZIP_MAGIC: [u8; 4] = *b"PK\x03\x04";
+const ZIP_EMPTY_MAGIC: [u8; 4] = *b"PK\x05\x06";
+
+/// tar 첫 헤더의 ustar magic 위치
+const USTAR_MAGIC_OFFSET: usize = 257;
+
+/// 아티팩트 형식 (파일 이름이 아니라 시작 바이트로 판별)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    TarGzip,
+    TarZstd,
+    Tar,
+    Zip,
+}
+
+impl ArchiveFormat {
+    fn detect(artifact: &Path) -> Result<Self> {
+        let file = fs::File::open(artifact).with_context(|| format!("Failed to open artifact {:?}", artifact))?;
+        let mut head = Vec::with_capacity(512);
+        file.take(512)
+            .read_to_end(&mut head)
+            .with_context(|| format!("Failed to read artifact {:?}", artifact))?;
+        if head.starts_with(&GZIP_MAGIC) {
+            Ok(Self::TarGzip)
+        } else if head.starts_with(&ZSTD_MAGIC) {
+            Ok(Self::TarZstd)
+        } else if head.starts_with(&ZIP_MAGIC) || head.starts_with(&ZIP_EMPTY_MAGIC) {
+            Ok(Self::Zip)
+        } else if head.get(USTAR_MAGIC_OFFSET..USTAR_MAGIC_OFFSET + 5) == Some(b"ustar") {
+            Ok(Self::Tar)
+        } else {
+            anyhow::bail!("not a tar.gz, tar.zst, tar or zip archive")
+        }
+    }
+}
+
+/// tar 형식 아티팩트의 (압축 해제한) tar 스트림
+fn tar_stream(artifact: &Path, format: ArchiveFormat) -> Result<Box<dyn Read>> {
+    let file = fs::File::open(artifact).with_context(|| format!("Failed to open artifact {:?}", artifact))?;
+    let reader = io::BufReader::new(file);
+    match format {
+        ArchiveFormat::TarGzip => Ok(Box::new(GzDecoder::new(reader))),
+        ArchiveFormat::TarZstd => Ok(Box::new(
+            zstd::Decoder::with_buffer(reader).context("Failed to read zstd archive")?,
+        )),
+        ArchiveFormat::Tar => Ok(Box::new(reader)),
+        ArchiveFormat::Zip => anyhow::bail!("zip artifact is not a tar stream"),
+    }
+}
+
+/// 설치 디렉토리 밖을 가리키는 항목 경로 (절대 경로, `..`)
+fn escapes_install_dir(path: &Path) -> bool {
+    path.components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// tar 아티팩트를 끝까지 읽으며 검사 (validate_artifact)
+fn scan_tar(artifact: &Path, format: ArchiveFormat) -> std::result::Result<ArtifactInfo, String> {
+    let mut info = ArtifactInfo {
+        files: 0,
+        total_bytes: 0,
+    };
+    let mut archive = Archive::new(tar_stream(artifact, format).map_err(|e| e.to_string())?);
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("unreadable tar archive: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("unreadable tar entry: {}", e))?;
+        let path = entry
+            .path()
+            .map_err(|e| format!("bad entry path: {}", e))?
+            .into_owned();
+        if escapes_install_dir(&path) {
+            return Err(format!("entry {:?} escapes the install directory", path));
+        }
+        if entry.header().entry_type().is_file() {
+            let expected = entry.size();
+            let read = io::copy(&mut entry, &mut io::sink())
+                .map_err(|e| format!("unreadable entry {:?}: {}", path, e))?;
+            if read != expected {
+                return Err(format!("entry {:?} is truncated ({} of {} bytes)", path, read, expected));
+            }
+            info.files += 1;
+            info.total_bytes += read;
+        }
+    }
+    // tar 끝 이후의 나머지까지 읽어야 gzip/zstd 트레일러(CRC)가 확인됨
+    io::copy(&mut archive.into_inner(), &mut io::sink())
+        .map_err(|e| format!("corrupt compressed stream: {}", e))?;
+    Ok(info)
+}
+
+/// zip 아티팩트의 모든 항목을 풀어 보며 검사 (validate_artifact, 항목 CRC는 끝까지 읽을 때 확인됨)
+///
+/// 심볼릭 링크 항목은 링크를 거쳐 설치 디렉토리 밖에 쓸 수 있으므로 거부한다.
+fn scan_zip(artifact: &Path) -> std::result::Result<ArtifactInfo, String> {
+    let mut info = ArtifactInfo {
+        files: 0,
+        total_bytes: 0,
+    };
+    let file = fs::File::open(artifact).map_err(|e| format!("Failed to open artifact {:?}: {}", artifact, e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("unreadable zip archive: {}", e))?;
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("unreadable zip entry: {}", e))?;
+        let path = PathBuf::from(entry.name());
+        if escapes_install_dir(&path) {
+            return Err(format!("entry {:?} escapes the install directory", path));
+        }
+        if entry.is_symlink() {
+            return Err(format!("entry {:?} is a symlink (not supported in zip artifacts)", path));
+        }
+        if entry.is_file() {
+            let expected = entry.size();
+            let read = io::copy(&mut entry, &mut io::sink())
+                .map_err(|e| format!("unreadable entry {:?}: {}", path, e))?;
+            if read != expected {
+                return Err(format!("entry {:?} is truncated ({} of {} bytes)", path, read, expected));
+            }
+            info.files += 1;
+            info.total_bytes += read;
+        }
+    }
+    Ok(info)
+}
+
+/// 아티팩트(tar.gz/tar.zst/tar/zip)를 임시 디렉토리에 추출하고 콘텐츠 루트 반환 (항목마다 기한 확인)
+fn extract_archive(artifact: &Path, temp_path: &Path, deadline: Option<&Deadline>) -> Result<PathBuf> {
+    tracing::info!("Extracting artifact to {:?}", temp_path);
+
+    match ArchiveFormat::detect(artifact)? {
+        ArchiveFormat::Zip => extract_zip(artifact, temp_path, deadline)?,
+        format => extract_tar(artifact, format, temp_path, deadline)?,
+    }
+
+    // Find the extracted content (might be in a subdirectory)
+    find_extracted_root(temp_path)
+}
+
+fn extract_tar(artifact: &Path, format: ArchiveFormat, temp_path: &Path, deadline: Option<&Deadline>) -> Result<()> {
+    let total_entries = Archive::new(tar_stream(artifact, format)?)
+        .entries()
+        .context("Failed to read archive")?
+        .count() as u64;
+    let mut progress = Progress::new("Extracting", total_entries, Unit::Items);
+
+    let mut archive = Archive::new(tar_stream(artifact, format)?);
+    for entry in archive.entries().context("Failed to read archive")? {
+        if let Some(deadline) = deadline {
+            deadline.check(UpdatePhase::Install)?;
+        }
+        let mut entry = entry.context("Failed to read archive entry")?;
+        entry
+            .unpack_in(temp_path)
+            .context("Failed to extract archive")?;
+        progress.inc(1);
+    }
+    progress.finish();
+    Ok(())
+}
+
+/// zip 항목 추출 (경로와 심볼릭 링크는 validate_artifact와 같은 기준으로 다시 거부, 권한 비트는 유지)
+fn extract_zip(artifact: &Path, temp_path: &Path, deadline: Option<&Deadline>) -> Result<()> {
+    let file = fs::File::open(artifact).with_context(|| format!("Failed to open artifact {:?}", artifact))?;
+    let mut archive = zip::ZipArchive::new(file).context("Failed to read archive")?;
+    let mut progress = Progress::new("Extracting", archive.len() as u64, Unit::Items);
+
+    for i in 0..archive.len() {
+        if let Some(deadline) = deadline {
+            deadline.check(UpdatePhase::Install)?;
+        }
+        let mut entry = archive.by_index(i).context("Failed to read archive entry")?;
+        let path = PathBuf::from(entry.name());
+        if escapes_install_dir(&path) || entry.is_symlink() {
+            anyhow::bail!(ClientError::Install(format!("Refusing to extract zip entry {:?}", path)));
+        }
+        let dest = temp_path.join(&path);
+        if entry.is_dir() {
+            fs::create_dir_all(&dest).with_context(|| format!("Failed to create {:?}", dest))?;
+        } else {
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+            }
+            let mut out = fs::File::create(&dest).with_context(|| format!("Failed to create {:?}", dest))?;
+            io::copy(&mut entry, &mut out).context("Failed to extract archive")?;
+            #[cfg(unix)]
+            if let Some(mode) = entry.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&dest, fs::Permissions::from_mode(mode & 0o777))?;
+            }
+        }
+        progress.inc(1);
+    }
+    progress.finish();
+    Ok(())
+}
+
+/// SHA256 해시 (hex)
+pub fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    let mut progress = Progress::new("Hashing", data.len() as u64, Unit::Bytes);
+    for chunk in data.chunks(HASH_CHUNK_SIZE) {
+        hasher.update(chunk);
+        progress.inc(chunk.len() as u64);
+    }
+    progress.finish();
+    format!("{:x}", hasher.finalize())
+}
+
+/// 파일 SHA256 해시 (hex, 디스크 이미지처럼 메모리에 담기 어려운 아티팩트용)
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut progress = Progress::new("Hashing", file.metadata()?.len(), Unit::Bytes);
+    let mut buf = vec![0u8; HASH_CHUNK_SIZE];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        progress.inc(n as u64);
+    }
+    progress.finish();
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 디렉토리 복사 (진행률 표시)
+fn copy_dir_with_progress(src: &Path, dst: &Path, label: &str) -> Result<()> {
+    let mut progress = Progress::new(label, count_files(src)?, Unit::Items);
+    copy_dir_recursive(src, dst, &mut progress)?;
+    progress.finish();
+    Ok(())
+}
+
+/// 백업 복사 결과
+#[derive(Default)]
+struct CopyReport {
+    /// 제외 패턴으로 건너뛴 상대 경로
+    excluded: Vec<String>,
+    /// 읽거나 복사하지 못한 상대 경로와 원인
+    failed: Vec<(String, std::io::Error)>,
+}
+
+/// 제외 패턴을 적용한 디렉토리 복사 (백업용)
+///
+/// 원본 파일을 읽지 못해도 중단하지 않고 나머지를 복사한 뒤 실패한 경로를 함께 반환한다.
+/// 대상 디렉토리를 만들지 못하면 바로 에러.
+fn copy_dir_excluding(src: &Path, dst: &Path, excludes: &ExcludeSet) -> Result<CopyReport> {
+    let mut progress = Progress::new("Backup", count_files(src)?, Unit::Items);
+    let mut report = CopyReport::default();
+    copy_dir_filtered(src, dst, "", excludes, &mut report, &mut progress)?;
+    progress.finish();
+    Ok(report)
+}
+
+fn copy_dir_filtered(
+    src: &Path,
+    dst: &Path,
+    prefix: &str,
+    excludes: &ExcludeSet,
+    report: &mut CopyReport,
+    progress: &mut Progress,
+) -> Result<()> {
+    fs::create_dir_all(dst)?;
+
+    let entries = match fs::read_dir(src) {
+        Ok(entries) => entries,
+        Err(e) if !prefix.is_empty() => {
+            report.failed.push((prefix.to_string(), e));
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.failed.push((if prefix.is_empty() { "." } else { prefix }.to_string(), e));
+                continue;
+            }
+        };
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+        let ty = match entry.file_type() {
+            Ok(ty) => ty,
+            Err(e) => {
+                report.failed.push((relative, e));
+                continue;
+            }
+        };
+
+        if excludes.matches(&relative) {
+            progress.inc(if ty.is_dir() { count_files(&entry.path())? } else { 1 });
+            report.excluded.push(relative);
+            continue;
+        }
+
+        let dst_path = dst.join(entry.file_name());
+        if ty.is_dir() {
+            copy_dir_filtered(&entry.path(), &dst_path, &relative, excludes, report, progress)?;
+            if let Err(e) = copy_dir_metadata(&entry.path(), &dst_path) {
+                report.failed.push((relative, e));
+            }
+        } else {
+            if let Err(e) = copy_entry(&entry.path(), &dst_path, ty) {
+                report.failed.push((relative, e));
+            }
+            progress.inc(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// 제외 패턴을 적용한 tar.gz 백업 (권한, 수정 시각, 심볼릭 링크 유지)
+///
+/// 복사 백업과 같이 읽지 못한 경로와 소켓/FIFO/장치 파일은 실패로 모으고 나머지를 끝까지 기록한다.
+fn archive_dir_excluding(src: &Path, archive: &Path, excludes: &ExcludeSet) -> Result<CopyReport> {
+    let file = fs::File::create(archive).with_context(|| format!("Cannot create {:?}", archive))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(io::BufWriter::new(file), Compression::default()));
+    builder.follow_symlinks(false);
+
+    let mut progress = Progress::new("Backup", count_files(src)?, Unit::Items);
+    let mut report = CopyReport::default();
+    archive_filtered(&mut builder, src, "", excludes, &mut report, &mut progress)?;
+    progress.finish();
+
+    let writer = builder.into_inner()?.finish()?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    Ok(report)
+}
+
+fn archive_filtered<W: io::Write>(
+    builder: &mut tar::Builder<W>,
+    src: &Path,
+    prefix: &str,
+    excludes: &ExcludeSet,
+    report: &mut CopyReport,
+    progress: &mut Progress,
+) -> Result<()> {
+    let entries = match fs::read_dir(src) {
+        Ok(entries) => entries,
+        Err(e) if !prefix.is_empty() => {
+            report.failed.push((prefix.to_string(), e));
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.failed.push((if prefix.is_empty() { "." } else { prefix }.to_string(), e));
+                continue;
+            }
+        };
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+        let ty = match entry.file_type() {
+            Ok(ty) => ty,
+            Err(e) => {
+                report.failed.push((relative, e));
+                continue;
+            }
+        };
+
+        if excludes.matches(&relative) {
+            progress.inc(if ty.is_dir() { count_files(&entry.path())? } else { 1 });
+            report.excluded.push(relative);
+            continue;
+        }
+
+        if ty.is_dir() {
+            match builder.append_dir(&relative, entry.path()) {
+                Ok(()) => archive_filtered(builder, &entry.path(), &relative, excludes, report, progress)?,
+                Err(e) => report.failed.push((relative, e)),
+            }
+            continue;
+        }
+        let appended = if ty.is_file() || ty.is_symlink() {
+            builder.append_path_with_name(entry.path(), &relative)
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "not a regular file, directory or symlink",
+            ))
+        };
+        if let Err(e) = appended {
+            report.failed.push((relative, e));
+        }
+        progress.inc(1);
+    }
+
+    Ok(())
+}
+
+/// tar.gz 백업을 디렉토리에 풀기 (권한, 수정 시각, 심볼릭 링크 복원)
+fn unpack_backup(archive: &Path, dst: &Path) -> Result<()> {
+    tracing::info!("Restoring from archive {:?}", archive);
+    let file = fs::File::open(archive).with_context(|| format!("Cannot open backup {:?}", archive))?;
+    let mut archive = Archive::new(GzDecoder::new(io::BufReader::new(file)));
+    archive.set_preserve_permissions(true);
+    archive.set_preserve_mtime(true);
+    archive.set_overwrite(true);
+    archive.unpack(dst).context("Failed to unpack backup archive")?;
+    Ok(())
+}
+
+/// 백업 실패 에러 (복사하지 못한 경로 나열, 첫 원인은 파일시스템 장애 분류용으로 유지)
+fn backup_failed(service_dir: &Path, mut failed: Vec<(String, std::io::Error)>) -> anyhow::Error {
+    let total = failed.len();
+    let listed: Vec<String> = failed
+        .iter()
+        .take(MAX_REPORTED_BACKUP_FAILURES)
+        .map(|(path, e)| format!("{} ({})", path, e))
+        .collect();
+    let more = match total.saturating_sub(MAX_REPORTED_BACKUP_FAILURES) {
+        0 => String::new(),
+        n => format!(" and {} more", n),
+    };
+    let (_, first) = failed.swap_remove(0);
+    anyhow::Error::new(first).context(format!(
+        "Backup failed: could not copy {} path(s) from {}: {}{}. \
+         Fix the files or set DM_BACKUP_REQUIRED=false (--no-backup) to update without a backup",
+        total,
+        service_dir.display(),
+        listed.join(", "),
+        more
+    ))
+}
+
+/// 디렉토리 내 파일 수 (읽을 수 없는 하위 디렉토리는 세지 않음, 복사 단계에서 보고)
+fn count_files(dir: &Path) -> Result<u64> {
+    let mut count = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            count += count_files(&entry.path()).unwrap_or(0);
+        } else {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// 디렉토리 재귀 복사 (하위 항목의 권한, 수정 시각, 심볼릭 링크 유지)
+fn copy_dir_recursive(src: &Path, dst: &Path, progress: &mut Progress) -> Result<()> {
     fs::create_dir_all(dst)?;
     
     for entry in fs::read_dir(src)? {
@@ -172,16 +1325,60 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
         let src_path = entry.path();
         let dst_path = dst.join(entry.file_name());
 
-        if ty.is_dir() {
-            copy_dir_recursive(&src_path, &dst_path)?;
+        let copied = if ty.is_dir() {
+            copy_dir_recursive(&src_path, &dst_path, progress)?;
+            copy_dir_metadata(&src_path, &dst_path)
         } else {
-            fs::copy(&src_path, &dst_path)?;
+            progress.inc(1);
+            copy_entry(&src_path, &dst_path, ty)
+        };
+        if let Err(e) = copied {
+            let message = format!("Failed to copy {:?}: {}", src_path, e);
+            return Err(anyhow::Error::new(e).context(message));
         }
     }
 
     Ok(())
 }
 
+/// 디렉토리가 아닌 항목 복사: 심볼릭 링크는 링크로 다시 만들고, 일반 파일은 권한과 수정 시각 유지
+///
+/// 소켓, FIFO, 장치 파일은 복사하지 않고 에러 (FIFO는 열면 멈출 수 있음).
+fn copy_entry(src: &Path, dst: &Path, ty: fs::FileType) -> std::io::Result<()> {
+    if ty.is_symlink() {
+        return copy_symlink(src, dst);
+    }
+    let meta = fs::metadata(src)?;
+    if !meta.is_file() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "not a regular file, directory or symlink",
+        ));
+    }
+    // 권한은 fs::copy가 함께 복사
+    fs::copy(src, dst)?;
+    filetime::set_file_mtime(dst, filetime::FileTime::from_last_modification_time(&meta))
+}
+
+/// 복사한 하위 디렉토리에 원본의 권한과 수정 시각 적용 (내용을 다 쓴 뒤 호출)
+fn copy_dir_metadata(src: &Path, dst: &Path) -> std::io::Result<()> {
+    let meta = fs::metadata(src)?;
+    fs::set_permissions(dst, meta.permissions())?;
+    filetime::set_file_mtime(dst, filetime::FileTime::from_last_modification_time(&meta))
+}
+
+/// 심볼릭 링크를 같은 대상의 링크로 다시 만듦 (링크 대상은 따라가지 않음)
+#[cfg(unix)]
+fn copy_symlink(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(src)?, dst)
+}
+
+/// Windows는 링크를 만들 권한이 없는 경우가 많아 대상 내용을 복사
+#[cfg(not(unix))]
+fn copy_symlink(src: &Path, dst: &Path) -> std::io::Result<()> {
+    fs::copy(src, dst).map(|_| ())
+}
+
 /// 추출된 루트 디렉토리 찾기
 fn find_extracted_root(temp_path: &Path) -> Result<std::path::PathBuf> {
     let entries: Vec<_> = fs::read_dir(temp_path)?
@@ -1,59 +1,277 @@
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
+use crate::api::BuildInfo;
 use crate::config::Config;
-use crate::updater::Updater;
+use crate::deadline::UpdatePhase;
+use crate::downgrade;
+use crate::error::{Classify, ClientError};
+use crate::fsfault;
+use crate::lock::UpdateLock;
+use crate::scripts::InstallScripts;
+use crate::signing;
+use crate::state::LocalState;
+use crate::updater::{self, Updater};
 
 const VERSION_FILE: &str = ".dm-version";
 
 /// USB manifest.json 구조
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct UsbManifest {
     pub version: String,
     pub checksum: String,
+    /// 아티팩트 ed25519 서명 (base64, DM_UPDATE_PUBLIC_KEY가 설정된 장비는 필수)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
     #[serde(default = "default_artifact")]
     pub artifact: String,
+    /// 아티팩트 URL (static 모드, 없으면 manifest URL 기준 `artifact` 상대 경로)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub release_notes: Option<String>,
+    /// 변경 이력 경로 (manifest.json 기준, Markdown)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+    /// 설치 전 스크립트 경로 (manifest.json 기준)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_install_script: Option<String>,
+    /// 설치 후 스크립트 경로 (manifest.json 기준)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_install_script: Option<String>,
+    /// 빌드 출처 정보 (git_commit, build_time, metadata)
+    #[serde(flatten)]
+    pub build_info: BuildInfo,
+}
+
+impl UsbManifest {
+    /// manifest가 가리키는 설치 스크립트 읽기 (base: manifest.json이 있는 디렉토리)
+    pub fn install_scripts(&self, base: &Path) -> Result<InstallScripts> {
+        InstallScripts::load(
+            base,
+            self.pre_install_script.as_deref(),
+            self.post_install_script.as_deref(),
+        )
+    }
 }
 
 fn default_artifact() -> String {
     "update.tar.gz".to_string()
 }
 
+/// manifest.json: 기존 단일 항목 또는 역할/그룹별 다중 항목
+///
+/// 다중 항목 형식: `{"entries": [{"match": {"group": "pos"}, "version": ..., "artifact": ...}, ...]}`
+/// `match.group`이 없는 항목은 역할이 맞는 항목이 없을 때 쓰는 기본 항목이다.
+#[derive(Debug)]
+pub enum BundleManifest {
+    Single(Box<UsbManifest>),
+    Multi(Vec<BundleEntry>),
+}
+
+/// 다중 항목 manifest의 항목
+#[derive(Debug, Deserialize)]
+pub struct BundleEntry {
+    #[serde(rename = "match", default)]
+    pub matcher: EntryMatch,
+    #[serde(flatten)]
+    pub manifest: UsbManifest,
+}
+
+/// 항목 선택 조건
+#[derive(Debug, Default, Deserialize)]
+pub struct EntryMatch {
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MultiManifest {
+    entries: Vec<BundleEntry>,
+}
+
+impl BundleManifest {
+    /// manifest.json 파싱 (`entries`가 있으면 다중 항목)
+    pub fn parse(data: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(data).context("manifest.json 파싱 실패")?;
+        if value.get("entries").is_some() {
+            let multi: MultiManifest =
+                serde_json::from_value(value).context("manifest.json 항목 파싱 실패")?;
+            Ok(Self::Multi(multi.entries))
+        } else {
+            let manifest: UsbManifest =
+                serde_json::from_value(value).context("manifest.json 파싱 실패")?;
+            Ok(Self::Single(Box::new(manifest)))
+        }
+    }
+
+    /// manifest.json 파일 읽기
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path).context("manifest.json 읽기 실패")?;
+        Self::parse(&data)
+    }
+
+    /// 역할에 맞는 항목 선택 (정확히 일치하는 항목 > 기본 항목)
+    pub fn select(self, role: Option<&str>) -> Result<UsbManifest> {
+        let entries = match self {
+            Self::Single(manifest) => return Ok(*manifest),
+            Self::Multi(entries) => entries,
+        };
+
+        let groups: Vec<String> = entries
+            .iter()
+            .map(|e| e.matcher.group.clone().unwrap_or_else(|| "(기본)".to_string()))
+            .collect();
+
+        let (mut matched, defaults): (Vec<BundleEntry>, Vec<BundleEntry>) = entries
+            .into_iter()
+            .filter(|e| e.matcher.group.is_none() || e.matcher.group.as_deref() == role)
+            .partition(|e| e.matcher.group.is_some());
+        if matched.is_empty() {
+            matched = defaults;
+        }
+
+        match matched.len() {
+            1 => Ok(matched.remove(0).manifest),
+            0 => match role {
+                Some(role) => anyhow::bail!(ClientError::Config(format!(
+                    "역할 '{}'에 맞는 번들 항목이 없습니다 (번들 항목: {})",
+                    role,
+                    groups.join(", ")
+                ))),
+                None => anyhow::bail!(ClientError::Config(format!(
+                    "역할별 번들입니다 (항목: {}). DM_CLIENT_ROLE을 설정하세요",
+                    groups.join(", ")
+                ))),
+            },
+            n => anyhow::bail!(ClientError::Config(format!(
+                "역할 '{}'에 맞는 번들 항목이 {}개입니다. manifest.json을 확인하세요",
+                role.unwrap_or("(기본)"),
+                n
+            ))),
+        }
+    }
+}
+
+/// 이 장비의 역할 (DM_CLIENT_ROLE > 서버 지정 역할)
+pub(crate) fn client_role(config: &Config) -> Option<String> {
+    LocalState::load(&config.service_dir).effective_role(config)
+}
+
+/// 설치 직전 요약 (체크섬 검증 후, 서비스 디렉토리를 건드리기 전에 확인용)
+#[derive(Debug)]
+pub struct ApplyPlan {
+    pub current_version: String,
+    pub target_version: String,
+    pub artifact: String,
+    pub artifact_size: u64,
+    /// 검증한 체크섬 (체크섬 없이 진행하면 None)
+    pub verified_checksum: Option<String>,
+    pub service_dir: String,
+    /// 현재 서비스 디렉토리 백업 여부 (디렉토리가 없으면 백업 생략)
+    pub backup: bool,
+    /// 백업을 명시적으로 건너뜀 (DM_BACKUP_REQUIRED=false / --no-backup)
+    pub backup_skipped: bool,
+}
+
+impl std::fmt::Display for ApplyPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "🦊 업데이트 적용")?;
+        writeln!(f, "  버전:         {} -> {}", self.current_version, self.target_version)?;
+        writeln!(f, "  아티팩트:     {} ({} bytes)", self.artifact, self.artifact_size)?;
+        match &self.verified_checksum {
+            Some(checksum) => writeln!(f, "  체크섬:       검증됨 ({})", checksum)?,
+            None => writeln!(f, "  체크섬:       검증 안 함 (--checksum 또는 manifest.json 없음)")?,
+        }
+        writeln!(f, "  서비스 경로:  {} (내용이 교체됩니다)", self.service_dir)?;
+        write!(
+            f,
+            "  백업:         {}",
+            if self.backup_skipped {
+                "⚠️  건너뜀 (--no-backup: 현재 버전으로 롤백할 수 없습니다)"
+            } else if self.backup {
+                "현재 버전 백업 후 설치"
+            } else {
+                "없음 (서비스 디렉토리가 없음)"
+            }
+        )
+    }
+}
+
 /// USB/로컬 파일로 업데이트 수행
-pub fn apply_from_file(
+///
+/// `confirm`은 체크섬 검증 후, 백업/설치 전에 호출되며 에러를 반환하면 아무것도 바꾸지 않는다.
+/// 설치, 재시작 명령, 헬스 체크 대기가 런타임을 막지 않도록 블로킹 스레드에서 실행한다.
+pub async fn apply_from_file(
     config: &Config,
     file_path: &str,
     version: Option<&str>,
     checksum: Option<&str>,
+    confirm: impl FnOnce(&ApplyPlan) -> Result<()> + Send + 'static,
+) -> Result<()> {
+    let config = config.clone();
+    let file_path = file_path.to_string();
+    let version = version.map(str::to_string);
+    let checksum = checksum.map(str::to_string);
+    tokio::task::spawn_blocking(move || {
+        apply_file_blocking(&config, &file_path, version.as_deref(), checksum.as_deref(), confirm)
+    })
+    .await?
+}
+
+fn apply_file_blocking(
+    config: &Config,
+    file_path: &str,
+    version: Option<&str>,
+    checksum: Option<&str>,
+    confirm: impl FnOnce(&ApplyPlan) -> Result<()>,
 ) -> Result<()> {
-    let updater = Updater::new(config.clone());
     let file = Path::new(file_path);
 
     if !file.exists() {
-        anyhow::bail!("파일을 찾을 수 없습니다: {}", file_path);
+        anyhow::bail!(ClientError::Config(format!("파일을 찾을 수 없습니다: {}", file_path)));
     }
 
     // manifest.json 자동 탐지 (같은 디렉토리)
     let parent = file.parent().unwrap_or(Path::new("."));
     let manifest_path = parent.join("manifest.json");
     let manifest = if manifest_path.exists() {
-        let data = fs::read_to_string(&manifest_path)
-            .context("manifest.json 읽기 실패")?;
-        Some(serde_json::from_str::<UsbManifest>(&data)
-            .context("manifest.json 파싱 실패")?)
+        Some(BundleManifest::load(&manifest_path)?.select(client_role(config).as_deref())?)
     } else {
         None
     };
+    let scripts = match &manifest {
+        Some(m) => m.install_scripts(parent)?,
+        None => InstallScripts::default(),
+    };
+
+    apply_artifact(config, file, version, checksum, manifest, scripts, confirm)
+}
+
+/// 아티팩트 설치 (버전/체크섬: CLI 인자 > manifest)
+fn apply_artifact(
+    config: &Config,
+    file: &Path,
+    version: Option<&str>,
+    checksum: Option<&str>,
+    manifest: Option<UsbManifest>,
+    scripts: InstallScripts,
+    confirm: impl FnOnce(&ApplyPlan) -> Result<()>,
+) -> Result<()> {
+    // 데몬이나 다른 apply/rollback이 서비스 디렉토리를 바꾸는 중이면 기다리지 않고 중단
+    let _lock = UpdateLock::acquire(&config.service_dir)?;
+    let updater = Updater::new(config.clone());
+    let previous = LocalState::load(&config.service_dir);
+    let health_probes = previous.effective_health_checks(config)?;
 
     // 버전 결정 (CLI 인자 > manifest > 필수)
     let target_version = version
         .map(|v| v.to_string())
         .or_else(|| manifest.as_ref().map(|m| m.version.clone()))
-        .ok_or_else(|| anyhow::anyhow!(
-            "버전을 지정해주세요: --version 또는 manifest.json"
+        .ok_or_else(|| ClientError::Config(
+            "버전을 지정해주세요: --version 또는 manifest.json".to_string()
         ))?;
 
     // 체크섬 결정 (CLI 인자 > manifest > 스킵)
@@ -69,40 +287,108 @@ pub fn apply_from_file(
         .unwrap_or_else(|| "unknown".to_string());
 
     tracing::info!("🦊 USB 업데이트 시작: {} -> {}", current_version, target_version);
+    downgrade::check(Some(&current_version), &target_version, config.allow_downgrade)?;
 
     if let Some(notes) = manifest.as_ref().and_then(|m| m.release_notes.as_ref()) {
         tracing::info!("릴리즈 노트: {}", notes);
     }
+    if let Some(changelog) = manifest.as_ref().and_then(|m| m.changelog.as_ref()) {
+        tracing::info!("변경 이력: {} (manifest.json 기준)", changelog);
+    }
 
-    // 1. 파일 읽기
-    tracing::info!("아티팩트 읽는 중: {}", file_path);
-    let artifact_data = fs::read(file)
-        .context("아티팩트 파일 읽기 실패")?;
+    // 1. 파일 확인 (메모리에 올리지 않고 파일에서 바로 해시/추출)
+    tracing::info!("아티팩트 읽는 중: {}", file.display());
+    let artifact_size = fs::metadata(file)
+        .context("아티팩트 파일 읽기 실패")?
+        .len();
 
     // 2. 체크섬 검증
+    let installed_checksum = updater::sha256_file(file).context("아티팩트 파일 읽기 실패")?;
     if let Some(ref expected) = expected_checksum {
         tracing::info!("체크섬 검증 중...");
-        if !updater.verify_checksum(&artifact_data, expected) {
-            anyhow::bail!("체크섬 불일치! 파일이 손상되었을 수 있습니다.");
+        if !installed_checksum.eq_ignore_ascii_case(expected) {
+            anyhow::bail!(ClientError::Checksum(checksum_mismatch(
+                &previous,
+                &target_version,
+                expected,
+                &installed_checksum,
+                artifact_size
+            )));
         }
         tracing::info!("체크섬 검증 ✓");
     } else {
         tracing::warn!("체크섬 없이 진행합니다 (--checksum 또는 manifest.json 권장)");
     }
+    // 서명 검증 (DM_UPDATE_PUBLIC_KEY가 있으면 manifest.json의 signature 필수)
+    signing::verify_artifact(
+        config,
+        file,
+        manifest.as_ref().and_then(|m| m.signature.as_deref()),
+        &target_version,
+    )?;
+    // 백업과 설치 전에 아카이브 구조 확인 (실패하면 서비스 디렉토리는 그대로)
+    updater.validate_artifact(file)?;
+
+    confirm(&ApplyPlan {
+        current_version: current_version.clone(),
+        target_version: target_version.clone(),
+        artifact: file.display().to_string(),
+        artifact_size,
+        verified_checksum: expected_checksum.as_ref().map(|_| installed_checksum.clone()),
+        service_dir: config.service_dir.clone(),
+        backup: Path::new(&config.service_dir).exists() && config.backup_required,
+        backup_skipped: Path::new(&config.service_dir).exists() && !config.backup_required,
+    })?;
 
     // 3. 백업
     tracing::info!("현재 버전 백업 중...");
     let backup_path = updater.backup_current(&current_version)?;
 
-    // 4. 설치
+    // 업데이트 전 명령 (실패하면 서비스 디렉토리를 바꾸지 않고 중단)
+    updater
+        .run_pre_update_script(&current_version, &target_version)
+        .classify(ClientError::Install)?;
+
+    // 4. 설치 (설치 전/후 스크립트 포함)
+    if let Some(script) = &scripts.pre_install {
+        tracing::info!("설치 전 스크립트 실행 중...");
+        if let Err(e) =
+            updater.run_install_script(UpdatePhase::PreInstall, script, &target_version, &current_version)
+        {
+            tracing::error!("설치 전 스크립트 실패: {}", e);
+            if !backup_path.is_empty() {
+                tracing::info!("롤백 중...");
+                updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
+            }
+            return Err(e).classify(ClientError::Install);
+        }
+    }
+
     tracing::info!("설치 중...");
-    if let Err(e) = updater.extract_and_install(&artifact_data) {
-        tracing::error!("설치 실패: {}", e);
-        if !backup_path.is_empty() {
-            tracing::info!("롤백 중...");
-            updater.rollback(&backup_path)?;
+    let install_strategy = match updater.extract_and_install(file) {
+        Ok(strategy) => strategy,
+        Err(e) => {
+            tracing::error!("설치 실패: {}", e);
+            if !backup_path.is_empty() && !fsfault::rollback_blocked(&e) {
+                tracing::info!("롤백 중...");
+                updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
+            }
+            return Err(e).classify(ClientError::Install);
+        }
+    };
+
+    if let Some(script) = &scripts.post_install {
+        tracing::info!("설치 후 스크립트 실행 중...");
+        if let Err(e) =
+            updater.run_install_script(UpdatePhase::PostInstall, script, &target_version, &current_version)
+        {
+            tracing::error!("설치 후 스크립트 실패: {}", e);
+            if !backup_path.is_empty() {
+                tracing::info!("롤백 중...");
+                updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
+            }
+            return Err(e).classify(ClientError::Install);
         }
-        return Err(e);
     }
 
     // 5. 버전 파일 업데이트
@@ -113,67 +399,127 @@ pub fn apply_from_file(
     tracing::info!("서비스 재시작 중...");
     if let Err(e) = updater.restart_service() {
         tracing::error!("재시작 실패: {}", e);
-        if !backup_path.is_empty() {
+        if !backup_path.is_empty() && !fsfault::rollback_blocked(&e) {
             tracing::info!("롤백 중...");
-            updater.rollback(&backup_path)?;
+            updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
             fs::write(&version_file, &current_version)?;
         }
-        return Err(e);
+        return Err(e).classify(ClientError::Install);
     }
 
     // 7. 헬스 체크
     tracing::info!("헬스 체크 중...");
-    match updater.health_check() {
-        Ok(true) => {
-            tracing::info!("헬스 체크 통과 ✓");
+    if let Err(e) = updater.health_check(&health_probes) {
+        tracing::error!("헬스 체크 실패: {}", e);
+        if !backup_path.is_empty() {
+            tracing::info!("롤백 중...");
+            updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
+            fs::write(&version_file, &current_version)?;
         }
-        Ok(false) | Err(_) => {
-            tracing::error!("헬스 체크 실패!");
-            if !backup_path.is_empty() {
-                tracing::info!("롤백 중...");
-                updater.rollback(&backup_path)?;
-                fs::write(&version_file, &current_version)?;
-            }
-            anyhow::bail!("헬스 체크 실패 - 롤백 완료");
+        return Err(e.context("헬스 체크 실패 - 롤백 완료")).classify(ClientError::HealthCheck);
+    }
+    tracing::info!("헬스 체크 통과 ✓");
+
+    // 업데이트 후 명령 (rollback_on_failure=true일 때만 롤백, 아니면 경고만)
+    if let Err(e) = updater.run_post_update_script(&current_version, &target_version) {
+        if config.rollback_on_failure == Some(true) && !backup_path.is_empty() {
+            tracing::error!("업데이트 후 명령 실패: {}", e);
+            tracing::info!("롤백 중...");
+            updater.rollback(&backup_path).classify(ClientError::RollbackFailed)?;
+            fs::write(&version_file, &current_version)?;
+            return Err(e).classify(ClientError::Install);
         }
+        tracing::warn!("업데이트 후 명령 실패 (새 버전 유지): {}", e);
     }
 
+    // 8. 설치 상태 기록
+    let build_info = manifest
+        .filter(|m| m.version == target_version)
+        .map(|m| m.build_info);
+    let mut installed_state = LocalState::installed(&target_version, &installed_checksum, build_info)
+        .keep_server_config(&previous)
+        .record_skipped_backup(config, &backup_path, &current_version);
+    installed_state.install_strategy = Some(install_strategy);
+    installed_state.save(config)?;
+    updater.clear_staging()?;
+
     tracing::info!("✅ USB 업데이트 완료: {}", target_version);
     Ok(())
 }
 
-/// USB 경로에서 자동 탐지하여 업데이트
-pub fn apply_from_directory(config: &Config, dir_path: &str) -> Result<()> {
+/// 체크섬 불일치 진단 메시지 (전화 지원용으로 기대/실제 다이제스트와 크기 포함)
+///
+/// 아티팩트가 이전에 본 다른 버전과 일치하면 파일 손상이 아니라
+/// manifest와 아티팩트가 서로 다른 릴리즈에서 복사된 것으로 안내한다.
+fn checksum_mismatch(
+    state: &LocalState,
+    target_version: &str,
+    expected: &str,
+    actual: &str,
+    size: u64,
+) -> String {
+    let details = format!(
+        "  기대 체크섬: {}\n  실제 체크섬: {}\n  아티팩트 크기: {} bytes",
+        expected, actual, size
+    );
+    match state.version_for_checksum(actual) {
+        Some(known) if known != target_version => format!(
+            "체크섬 불일치! 아티팩트는 버전 {}와 일치하지만 manifest는 {}입니다 \
+             — USB의 파일이 서로 다른 릴리즈에서 복사되었습니다.\n{}",
+            known, target_version, details
+        ),
+        _ => format!("체크섬 불일치! 파일이 손상되었을 수 있습니다.\n{}", details),
+    }
+}
+
+/// USB 경로에서 자동 탐지하여 업데이트 (블로킹 스레드에서 실행)
+pub async fn apply_from_directory(
+    config: &Config,
+    dir_path: &str,
+    confirm: impl FnOnce(&ApplyPlan) -> Result<()> + Send + 'static,
+) -> Result<()> {
+    let config = config.clone();
+    let dir_path = dir_path.to_string();
+    tokio::task::spawn_blocking(move || apply_directory_blocking(&config, &dir_path, confirm)).await?
+}
+
+fn apply_directory_blocking(
+    config: &Config,
+    dir_path: &str,
+    confirm: impl FnOnce(&ApplyPlan) -> Result<()>,
+) -> Result<()> {
     let dir = Path::new(dir_path);
 
     if !dir.exists() || !dir.is_dir() {
-        anyhow::bail!("디렉토리를 찾을 수 없습니다: {}", dir_path);
+        anyhow::bail!(ClientError::Config(format!("디렉토리를 찾을 수 없습니다: {}", dir_path)));
     }
 
     // manifest.json 찾기
     let manifest_path = dir.join("manifest.json");
     if !manifest_path.exists() {
-        anyhow::bail!(
+        anyhow::bail!(ClientError::Config(
             "manifest.json을 찾을 수 없습니다.\n\
              USB에 다음 파일이 필요합니다:\n\
              - manifest.json (버전, 체크섬 정보)\n\
-             - update.tar.gz (아티팩트)"
-        );
+             - update.tar.gz (아티팩트, manifest의 artifact로 update.zip 등 다른 이름 지정 가능)"
+                .to_string()
+        ));
     }
 
-    let manifest_data = fs::read_to_string(&manifest_path)?;
-    let manifest: UsbManifest = serde_json::from_str(&manifest_data)
-        .context("manifest.json 파싱 실패")?;
+    let role = client_role(config);
+    let manifest = BundleManifest::load(&manifest_path)?.select(role.as_deref())?;
+    if let Some(role) = &role {
+        tracing::info!("역할 '{}' 항목 선택: {}", role, manifest.version);
+    }
 
     let artifact_path = dir.join(&manifest.artifact);
     if !artifact_path.exists() {
-        anyhow::bail!("아티팩트 파일을 찾을 수 없습니다: {}", manifest.artifact);
+        anyhow::bail!(ClientError::Config(format!(
+            "아티팩트 파일을 찾을 수 없습니다: {}",
+            manifest.artifact
+        )));
     }
 
-    apply_from_file(
-        config,
-        artifact_path.to_str().unwrap(),
-        Some(&manifest.version),
-        Some(&manifest.checksum),
-    )
+    let scripts = manifest.install_scripts(dir)?;
+    apply_artifact(config, &artifact_path, None, None, Some(manifest), scripts, confirm)
 }
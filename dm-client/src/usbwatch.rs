@@ -0,0 +1,208 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::error::ClientError;
+use crate::usb::{self, BundleManifest};
+
+/// 처리한 USB 번들 버전 기록 (control_dir/usb-applied.json)
+///
+/// 서비스 디렉토리의 `.dm-*` 파일은 설치 때 새 트리로 바뀌므로 제어 디렉토리에 둔다.
+const APPLIED_FILE: &str = "usb-applied.json";
+
+/// 적용 결과를 USB에 남기는 파일 (manifest.json 옆)
+pub const RESULT_FILE: &str = "apply-result.json";
+
+const MANIFEST_FILE: &str = "manifest.json";
+const VERSION_FILE: &str = ".dm-version";
+
+/// 마운트 루트 아래에서 manifest.json을 찾는 깊이 (`/media/<라벨>`, `/media/<사용자>/<라벨>`)
+const MAX_DEPTH: usize = 2;
+
+/// USB 번들 하나의 처리 결과 (USB의 apply-result.json, usb-applied.json 항목)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsbApplyResult {
+    pub version: String,
+    /// "completed", "failed", "skipped"(이미 설치된 버전)
+    pub status: String,
+    #[serde(default)]
+    pub previous_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 번들 디렉토리
+    pub path: PathBuf,
+    #[serde(default)]
+    pub hostname: Option<String>,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// 처리한 USB 번들 버전 (버전 → 마지막 결과). 같은 버전은 다시 적용하지 않는다
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AppliedVersions {
+    #[serde(default)]
+    pub versions: BTreeMap<String, UsbApplyResult>,
+}
+
+impl AppliedVersions {
+    /// 저장된 기록 (없거나 손상된 경우 빈 기록)
+    pub fn load(control_dir: &str) -> Self {
+        fs::read_to_string(Path::new(control_dir).join(APPLIED_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// 저장 (임시 파일 후 rename)
+    pub fn save(&self, control_dir: &str) -> Result<()> {
+        fs::create_dir_all(control_dir)?;
+        let path = Path::new(control_dir).join(APPLIED_FILE);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+/// 마운트 루트를 주기적으로 검사해 새 USB 번들을 적용 (`dm-client usb-watch`, DM_USB_WATCH_DIR)
+pub struct UsbWatcher {
+    config: Config,
+    root: PathBuf,
+    /// 이번 실행에서 이미 경고한 읽을 수 없는 manifest (검사마다 반복하지 않음)
+    warned: HashSet<PathBuf>,
+}
+
+impl UsbWatcher {
+    pub fn new(config: Config, root: impl Into<PathBuf>) -> Self {
+        Self {
+            config,
+            root: root.into(),
+            warned: HashSet::new(),
+        }
+    }
+
+    /// `interval`마다 검사 (끝나지 않음)
+    pub async fn run(mut self, interval: Duration) {
+        tracing::info!("Watching {:?} for USB bundles (every {}s)", self.root, interval.as_secs());
+        loop {
+            self.scan_once().await;
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// 마운트 루트를 한 번 검사해 처음 보는 버전의 번들을 적용하고, 이번에 처리한 결과 반환
+    ///
+    /// 처리한 버전은 성공/실패와 상관없이 기록해 다시 적용하지 않는다 (실패한 번들이 꽂혀 있는 동안
+    /// 반복 설치하지 않도록). 다른 업데이트가 진행 중이라 잠금을 얻지 못한 경우만 다음 검사에서 다시 시도한다.
+    pub async fn scan_once(&mut self) -> Vec<UsbApplyResult> {
+        let mut results = Vec::new();
+        for dir in find_bundles(&self.root) {
+            let version = match self.bundle_version(&dir) {
+                Ok(version) => version,
+                Err(e) => {
+                    if self.warned.insert(dir.clone()) {
+                        tracing::warn!("Ignoring USB bundle {:?}: {:#}", dir, e);
+                    }
+                    continue;
+                }
+            };
+            let mut applied = AppliedVersions::load(&self.config.control_dir);
+            if applied.versions.contains_key(&version) {
+                tracing::trace!("USB bundle {:?} ({}) was already handled", dir, version);
+                continue;
+            }
+
+            let Some(result) = self.apply(&dir, &version).await else {
+                continue;
+            };
+            match &result.error {
+                Some(error) => tracing::error!("USB update {} from {:?} {}: {}", version, dir, result.status, error),
+                None => tracing::info!("USB update {} from {:?} {}", version, dir, result.status),
+            }
+            if let Err(e) = write_result(&dir, &result) {
+                tracing::warn!("Could not write {} to {:?}: {:#}", RESULT_FILE, dir, e);
+            }
+            applied.versions.insert(version, result.clone());
+            if let Err(e) = applied.save(&self.config.control_dir) {
+                tracing::warn!("Could not record USB bundle {}: {:#}", result.version, e);
+            }
+            results.push(result);
+        }
+        results
+    }
+
+    /// 이 장비에 맞는 manifest 항목의 버전
+    fn bundle_version(&self, dir: &Path) -> Result<String> {
+        let manifest = BundleManifest::load(&dir.join(MANIFEST_FILE))?.select(usb::client_role(&self.config).as_deref())?;
+        Ok(manifest.version)
+    }
+
+    /// 번들 적용 (이미 설치된 버전이면 건너뜀). 잠금 때문에 시작하지 못하면 None
+    async fn apply(&self, dir: &Path, version: &str) -> Option<UsbApplyResult> {
+        let previous_version = fs::read_to_string(Path::new(&self.config.service_dir).join(VERSION_FILE))
+            .ok()
+            .map(|s| s.trim().to_string());
+        let (status, error) = if previous_version.as_deref() == Some(version) {
+            ("skipped", None)
+        } else {
+            tracing::info!("Applying USB bundle {:?} ({})", dir, version);
+            match usb::apply_from_directory(&self.config, &dir.to_string_lossy(), |_| Ok(())).await {
+                Ok(()) => ("completed", None),
+                Err(e) if matches!(ClientError::of(&e), Some(ClientError::Locked(_))) => {
+                    tracing::info!("USB bundle {:?} waits for the running update: {:#}", dir, e);
+                    return None;
+                }
+                Err(e) => ("failed", Some(format!("{:#}", e))),
+            }
+        };
+        Some(UsbApplyResult {
+            version: version.to_string(),
+            status: status.to_string(),
+            previous_version,
+            error,
+            path: dir.to_path_buf(),
+            hostname: sysinfo::System::host_name(),
+            finished_at: Utc::now(),
+        })
+    }
+}
+
+/// 마운트 루트와 그 아래 `MAX_DEPTH`단계까지 manifest.json이 있는 디렉토리 (숨김 디렉토리 제외, 이름순)
+pub fn find_bundles(root: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut level = vec![root.to_path_buf()];
+    for depth in 0..=MAX_DEPTH {
+        let mut next = Vec::new();
+        for dir in level {
+            if dir.join(MANIFEST_FILE).is_file() {
+                found.push(dir);
+                continue;
+            }
+            if depth == MAX_DEPTH {
+                continue;
+            }
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                if !hidden && entry.path().is_dir() {
+                    next.push(entry.path());
+                }
+            }
+        }
+        next.sort();
+        level = next;
+    }
+    found
+}
+
+/// 결과를 USB의 apply-result.json으로 기록 (작업자가 장비에 접속하지 않고 확인)
+fn write_result(dir: &Path, result: &UsbApplyResult) -> Result<()> {
+    let path = dir.join(RESULT_FILE);
+    fs::write(&path, serde_json::to_vec_pretty(result)?).with_context(|| format!("Failed to write {:?}", path))
+}
@@ -0,0 +1 @@
+//! dm-server와 dm-client를 함께 띄우는 통합 테스트 크레이트 (`tests/` 참고)
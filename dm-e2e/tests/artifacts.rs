@@ -0,0 +1,1046 @@
+//! 버전 업로드와 아티팩트 저장·다운로드 (이름, Range, 공유 링크, 변경 내역, 패키징)
+
+mod common;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use sqlx::{Executor, Row};
+use tempfile::TempDir;
+
+use dm_server::AppState;
+
+use common::*;
+
+#[tokio::test]
+async fn base_path_artifact_url_resolves_through_prefixed_router() -> Result<()> {
+    let Some(server) = TestServer::start_with(|config| {
+        config.base_path = "/dm".to_string();
+        config.trusted_proxies = vec!["127.0.0.1/32".parse().expect("valid network")];
+    })
+    .await?
+    else {
+        return Ok(());
+    };
+    let probe = server.register("e2e-base-path-probe").await?;
+    let client = server.register("e2e-base-path").await?;
+
+    let v1 = artifact("v1");
+    server.publish(&probe, "1.0.0", v1.clone()).await?;
+    server.deploy(&client, "1.0.0").await?;
+
+    // 루트에는 API가 없음
+    let root = server.http.get(format!("{}/api/clients", server.origin)).send().await?;
+    assert_eq!(root.status(), reqwest::StatusCode::NOT_FOUND);
+
+    // 체크인 응답의 artifact_url은 접두사를 포함하고, 같은 서버에서 그대로 받을 수 있음
+    let checkin = |forwarded_prefix: Option<&str>| {
+        let mut request = server
+            .http
+            .post(format!("{}/api/checkin", server.url))
+            .header("X-API-Key", &probe.api_key)
+            .json(&serde_json::json!({ "current_version": null, "status": "online" }));
+        if let Some(prefix) = forwarded_prefix {
+            request = request.header("X-Forwarded-Prefix", prefix);
+        }
+        request.send()
+    };
+    let response: serde_json::Value = checkin(None).await?.error_for_status()?.json().await?;
+    let artifact_url = response["artifact_url"].as_str().context("artifact_url missing")?;
+    assert_eq!(artifact_url, "/dm/api/artifacts/1.0.0");
+
+    let downloaded = server
+        .http
+        .get(format!("{}{}", server.origin, artifact_url))
+        .header("X-API-Key", &probe.api_key)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    assert_eq!(downloaded.as_ref(), v1.as_slice());
+
+    // 신뢰하는 프록시가 보낸 X-Forwarded-Prefix가 우선
+    let response: serde_json::Value = checkin(Some("/edge/dm/")).await?.error_for_status()?.json().await?;
+    assert_eq!(response["artifact_url"], "/edge/dm/api/artifacts/1.0.0");
+
+    // 데몬은 접두사가 붙은 server_url과 artifact_url을 중복 없이 합쳐 설치
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn long_release_notes_are_kept_as_changelog() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+
+    // 요약 한도를 넘는 릴리즈 노트는 거부
+    let oversized = "- fix\n".repeat(1000);
+    let form = reqwest::multipart::Form::new()
+        .text("version", "1.0.0")
+        .text("release_notes", oversized.clone())
+        .part("artifact", reqwest::multipart::Part::bytes(artifact("v1")).file_name("app.tar.gz"));
+    let response = server.http.post(format!("{}/api/versions", server.url)).multipart(form).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.text().await?.contains("changelog"));
+
+    // 요약과 긴 변경 이력을 따로 업로드
+    let changelog = format!("# 1.0.0\n\n{}", oversized);
+    let form = reqwest::multipart::Form::new()
+        .text("version", "1.0.0")
+        .text("release_notes", "Bug fixes")
+        .part("changelog", reqwest::multipart::Part::text(changelog.clone()).file_name("CHANGELOG.md"))
+        .part("artifact", reqwest::multipart::Part::bytes(artifact("v1")).file_name("app.tar.gz"));
+    server
+        .http
+        .post(format!("{}/api/versions", server.url))
+        .multipart(form)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let versions: serde_json::Value = server.http.get(format!("{}/api/versions", server.url)).send().await?.json().await?;
+    assert_eq!(versions[0]["release_notes"], "Bug fixes");
+    assert_eq!(versions[0]["has_changelog"], true);
+
+    let response = server
+        .http
+        .get(format!("{}/api/versions/1.0.0/changelog", server.url))
+        .send()
+        .await?
+        .error_for_status()?;
+    assert_eq!(response.headers()["content-type"], "text/markdown; charset=utf-8");
+    assert_eq!(response.text().await?, changelog);
+
+    // USB 번들에 변경 이력 포함
+    let bundle = server
+        .http
+        .post(format!("{}/api/bundles", server.url))
+        .json(&serde_json::json!({ "entries": [{ "version": "1.0.0" }] }))
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let mut files = HashMap::new();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(&bundle[..]));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut content = String::new();
+        if std::io::Read::read_to_string(&mut entry, &mut content).is_ok() {
+            files.insert(path, content);
+        }
+    }
+    let manifest: serde_json::Value = serde_json::from_str(&files["manifest.json"])?;
+    assert_eq!(manifest["entries"][0]["changelog"], "changelogs/1.0.0.md");
+    assert_eq!(files["changelogs/1.0.0.md"], changelog);
+
+    // 변경 이력이 없는 버전은 404
+    server.upload("1.1.0", artifact("v1.1")).await?;
+    let response = server.http.get(format!("{}/api/versions/1.1.0/changelog", server.url)).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    // 한도 도입 전에 저장된 긴 노트는 서버 시작 시 변경 이력 파일로 이동
+    sqlx::query("UPDATE versions SET release_notes = $1 WHERE version = '1.1.0'")
+        .bind(&oversized)
+        .execute(&server.pool)
+        .await?;
+    let moved = dm_server::changelog::migrate_oversized_notes(&server.pool, &server.artifact_dir).await?;
+    assert_eq!(moved, 1);
+    let version: serde_json::Value =
+        server.http.get(format!("{}/api/versions/1.1.0", server.url)).send().await?.json().await?;
+    let summary = version["release_notes"].as_str().unwrap_or_default();
+    assert!(summary.len() <= dm_server::changelog::MAX_RELEASE_NOTES_LEN, "{}", summary.len());
+    assert!(summary.starts_with("- fix\n") && summary.ends_with('…'), "{}", summary);
+    assert_eq!(version["has_changelog"], true);
+    let full = server
+        .http
+        .get(format!("{}/api/versions/1.1.0/changelog", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    assert_eq!(full, oversized);
+
+    // 버전 삭제 시 변경 이력 파일도 삭제
+    server.http.delete(format!("{}/api/versions/1.0.0", server.url)).send().await?.error_for_status()?;
+    assert!(!server.artifact_dir.join("1.0.0.changelog.md").exists());
+
+    server.stop().await
+}
+
+/// 버전 행을 만들지 못하거나 변경 이력을 쓰지 못한 업로드는 변경 이력, 아티팩트, 행을 남기지 않음
+#[tokio::test]
+async fn failed_uploads_leave_no_changelog_behind() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let upload = |version: &'static str| {
+        let form = reqwest::multipart::Form::new()
+            .text("version", version)
+            .part("changelog", reqwest::multipart::Part::text(format!("# {}", version)).file_name("CHANGELOG.md"))
+            .part("artifact", reqwest::multipart::Part::bytes(artifact(version)).file_name("app.tar.gz"));
+        server.http.post(format!("{}/api/versions", server.url)).multipart(form).send()
+    };
+    let pool = &server.pool;
+    let version_exists = |version: &'static str| async move {
+        let row = sqlx::query("SELECT EXISTS (SELECT 1 FROM versions WHERE version = $1)")
+            .bind(version)
+            .fetch_one(pool)
+            .await?;
+        Ok::<bool, anyhow::Error>(row.get(0))
+    };
+
+    // 검증을 통과한 뒤 행 삽입이 실패 (같은 버전을 동시에 올린 경우처럼)
+    server
+        .pool
+        .execute(
+            r#"
+            CREATE FUNCTION reject_e2e_version() RETURNS trigger AS $$
+            BEGIN RAISE EXCEPTION 'duplicate key value violates unique constraint'; END
+            $$ LANGUAGE plpgsql;
+            CREATE TRIGGER reject_e2e_version BEFORE INSERT ON versions
+                FOR EACH ROW WHEN (NEW.version = '1.0.0') EXECUTE FUNCTION reject_e2e_version();
+            "#,
+        )
+        .await?;
+    let response = upload("1.0.0").await?;
+    assert_eq!(response.status(), reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(!server.artifact_dir.join("1.0.0.changelog.md").exists());
+    assert!(!server.artifact_dir.join("1.0.0.tar.gz").exists());
+
+    // 변경 이력을 쓰지 못하면 행과 옮겨 둔 아티팩트도 지움
+    fs::create_dir(server.artifact_dir.join("1.1.0.changelog.md"))?;
+    let response = upload("1.1.0").await?;
+    assert_eq!(response.status(), reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(!version_exists("1.1.0").await?);
+    assert!(!server.artifact_dir.join("1.1.0.tar.gz").exists());
+
+    // 정상 업로드는 그대로
+    upload("1.2.0").await?.error_for_status()?;
+    assert!(version_exists("1.2.0").await?);
+    assert_eq!(fs::read_to_string(server.artifact_dir.join("1.2.0.changelog.md"))?, "# 1.2.0");
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn artifacts_for_another_product_are_refused() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server
+        .register_with("e2e-product", |config| config.expected_product = Some("pos".to_string()))
+        .await?;
+    let upload = |version: &str, product: &str, artifact: Vec<u8>| {
+        let form = reqwest::multipart::Form::new()
+            .text("version", version.to_string())
+            .text("product", product.to_string())
+            .part("artifact", reqwest::multipart::Part::bytes(artifact).file_name("app.tar.gz"));
+        server.http.post(format!("{}/api/versions", server.url)).multipart(form).send()
+    };
+
+    // 체크인으로 장비의 제품(pos)을 서버에 알림
+    client.daemon.poll_once().await;
+
+    // 같은 버전 번호를 쓰는 다른 제품의 버전은 서버가 배포 거부
+    upload("2.0.0", "kiosk", artifact_files(&[("app.txt", b"kiosk"), (".dm-product", b"kiosk\n")]))
+        .await?
+        .error_for_status()?;
+    let response = server
+        .http
+        .post(format!("{}/api/clients/{}/deploy", server.url, client.id))
+        .json(&serde_json::json!({ "version": "2.0.0" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::CONFLICT);
+    let message = response.text().await?;
+    assert!(message.contains("kiosk") && message.contains("pos"), "{}", message);
+
+    // 선언과 다른 .dm-product는 업로드에서 거부
+    let misdeclared = artifact_files(&[("app.txt", b"kiosk"), (".dm-product", b"kiosk\n")]);
+    let response = upload("2.0.1", "pos", misdeclared.clone()).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // 검사 이전에 올라간 버전은 장비가 추출한 .dm-product로 설치 거부 (서비스 디렉토리 변경 없음)
+    let stored = upload("2.0.1", "pos", artifact_files(&[("app.txt", b"pos"), (".dm-product", b"pos\n")]))
+        .await?
+        .error_for_status()?
+        .json::<serde_json::Value>()
+        .await?;
+    let stored_path = stored["artifact_path"].as_str().context("artifact_path missing")?;
+    fs::write(server.artifact_dir.join(stored_path), &misdeclared)?;
+    sqlx::query("UPDATE versions SET checksum = $2, artifact_size = $3 WHERE version = $1")
+        .bind("2.0.1")
+        .bind(sha256(&misdeclared))
+        .bind(misdeclared.len() as i64)
+        .execute(&server.pool)
+        .await?;
+    server.deploy(&client, "2.0.1").await?;
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "failed");
+    let error = logs[0].error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("product kiosk"), "{}", error);
+    let reason: Option<String> =
+        sqlx::query_scalar("SELECT failure_reason FROM update_logs WHERE client_id = $1")
+            .bind(client.id)
+            .fetch_one(&server.pool)
+            .await?;
+    assert_eq!(reason.as_deref(), Some("product_mismatch"));
+    assert_eq!(client.read("app.txt"), None);
+
+    // 제품이 맞으면 설치
+    upload("2.0.2", "pos", artifact_files(&[("app.txt", b"pos"), (".dm-product", b"pos\n")]))
+        .await?
+        .error_for_status()?;
+    server.deploy(&client, "2.0.2").await?;
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.last().map(|l| l.status.as_str()), Some("completed"), "{:?}", logs);
+    assert_eq!(client.read("app.txt").as_deref(), Some("pos"));
+    assert_eq!(client.read(".dm-product").as_deref(), Some("pos\n"));
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn packaging_is_reproducible_and_zstd_artifacts_install() -> Result<()> {
+    use dm_client::api::BuildInfo;
+    use dm_client::package::{self, Codec, PackageOptions};
+
+    let dir = TempDir::new()?;
+    let tree = dir.path().join("tree");
+    fs::create_dir_all(tree.join("static/js"))?;
+    fs::write(tree.join("app.txt"), "v1")?;
+    fs::write(tree.join("static/js/main.js"), "console.log(1)")?;
+    fs::write(tree.join("static/index.html"), "<html></html>")?;
+
+    let pack = |out: &str, options: &PackageOptions| -> Result<(String, Vec<u8>)> {
+        let out = dir.path().join(out);
+        let manifest = package::create_package(
+            tree.to_str().unwrap(),
+            "1.0.0",
+            out.to_str().unwrap(),
+            None,
+            None,
+            BuildInfo::default(),
+            options,
+        )?;
+        Ok((manifest.checksum, fs::read(out.join(&manifest.artifact))?))
+    };
+
+    // 같은 트리를 두 번 패키징하면 바이트 단위로 같음
+    let gzip = PackageOptions::default();
+    let (first_sum, first) = pack("first", &gzip)?;
+    let (second_sum, second) = pack("second", &gzip)?;
+    assert_eq!(first_sum, second_sum);
+    assert_eq!(first, second);
+
+    // mtime만 바뀐 파일은 상한으로 잘려 결과가 같음
+    fs::File::options()
+        .write(true)
+        .open(tree.join("app.txt"))?
+        .set_modified(std::time::SystemTime::now())?;
+    assert_eq!(pack("touched", &gzip)?.0, first_sum);
+
+    // 내용이 바뀌면 체크섬이 바뀜
+    fs::write(tree.join("app.txt"), "v2")?;
+    let (edited_sum, _) = pack("edited", &gzip)?;
+    assert_ne!(edited_sum, first_sum);
+
+    // zstd도 결정적이고, 데몬이 내려받아 설치할 수 있음
+    let zstd = PackageOptions {
+        codec: Codec::Zstd,
+        level: Some(19),
+        ..Default::default()
+    };
+    let (zstd_sum, zstd_bytes) = pack("zstd-a", &zstd)?;
+    assert_eq!(pack("zstd-b", &zstd)?.0, zstd_sum);
+    assert!(fs::metadata(dir.path().join("zstd-a/update.tar.zst")).is_ok());
+    assert!(package::create_package(
+        tree.to_str().unwrap(),
+        "1.0.0",
+        dir.path().join("bad-level").to_str().unwrap(),
+        None,
+        None,
+        BuildInfo::default(),
+        &PackageOptions {
+            level: Some(10),
+            ..Default::default()
+        },
+    )
+    .is_err());
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-zstd-package").await?;
+
+    // `.tar.zst` 이름은 그대로 저장
+    let form = reqwest::multipart::Form::new()
+        .text("version", "1.0.0")
+        .part(
+            "artifact",
+            reqwest::multipart::Part::bytes(zstd_bytes).file_name("update.tar.zst"),
+        );
+    let uploaded: serde_json::Value = server
+        .http
+        .post(format!("{}/api/versions", server.url))
+        .multipart(form)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(uploaded["artifact_path"], "1.0.0.tar.zst");
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert_eq!(logs[0].verified_checksum.as_deref(), Some(zstd_sum.as_str()));
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    assert_eq!(client.read("static/js/main.js").as_deref(), Some("console.log(1)"));
+
+    // USB 번들의 manifest가 update.tar.zst를 가리켜도 설치
+    fs::write(tree.join("app.txt"), "v3")?;
+    let usb = dir.path().join("usb");
+    let manifest = package::create_package(
+        tree.to_str().unwrap(),
+        "1.1.0",
+        usb.to_str().unwrap(),
+        None,
+        None,
+        BuildInfo::default(),
+        &zstd,
+    )?;
+    assert_eq!(manifest.artifact, "update.tar.zst");
+    dm_client::usb::apply_from_directory(&client.config, usb.to_str().unwrap(), |_| Ok(())).await?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v3"));
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.1.0"));
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn share_links_count_downloads_and_expire() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let v1 = artifact("v1");
+    server.upload("1.0.0", v1.clone()).await?;
+
+    let share = |body: serde_json::Value| {
+        server
+            .http
+            .post(format!("{}/api/versions/1.0.0/share", server.url))
+            .json(&body)
+            .send()
+    };
+    let link: serde_json::Value = share(serde_json::json!({
+        "expires_in": 3600, "max_downloads": 2, "created_by": "alice"
+    }))
+    .await?
+    .error_for_status()?
+    .json()
+    .await?;
+    assert_eq!(link["created_by"], "alice");
+    let url = format!("{}{}", server.url, link["url"].as_str().unwrap_or_default());
+
+    // 자격 증명 없이 아티팩트 그대로
+    let plain = reqwest::Client::new();
+    let body = plain.get(&url).send().await?.error_for_status()?.bytes().await?;
+    assert_eq!(sha256(&body), sha256(&v1));
+
+    // manifest.json을 붙인 tar.gz
+    let bundle = plain
+        .get(format!("{}?bundle=true", url))
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let mut names = Vec::new();
+    let mut manifest = String::new();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(&bundle[..]));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        if path == "manifest.json" {
+            std::io::Read::read_to_string(&mut entry, &mut manifest)?;
+        }
+        names.push(path);
+    }
+    let manifest: serde_json::Value = serde_json::from_str(&manifest)?;
+    assert_eq!(manifest["entries"][0]["version"], "1.0.0");
+    assert_eq!(manifest["entries"][0]["checksum"], sha256(&v1));
+    assert!(
+        names.iter().any(|n| Some(n.as_str()) == manifest["entries"][0]["artifact"].as_str()),
+        "{:?}",
+        names
+    );
+
+    // 횟수를 다 쓰면 410
+    let response = plain.get(&url).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::GONE);
+    assert!(response.text().await?.contains("download limit"));
+
+    // 조기 취소
+    let revoked: serde_json::Value = share(serde_json::json!({ "expires_in": 3600 }))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let token = revoked["token"].as_str().unwrap_or_default().to_string();
+    assert_eq!(revoked["created_by"], "api");
+    server
+        .http
+        .delete(format!("{}/api/share/{}", server.url, token))
+        .send()
+        .await?
+        .error_for_status()?;
+    let response = plain.get(format!("{}/api/share/{}", server.url, token)).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::GONE);
+
+    // 만료
+    let expired: serde_json::Value = share(serde_json::json!({ "expires_in": 3600 }))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let token = expired["token"].as_str().unwrap_or_default().to_string();
+    sqlx::query("UPDATE share_links SET expires_at = NOW() - INTERVAL '1 minute' WHERE token = $1")
+        .bind(&token)
+        .execute(&server.pool)
+        .await?;
+    let response = plain.get(format!("{}/api/share/{}", server.url, token)).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::GONE);
+    assert!(response.text().await?.contains("expired"));
+
+    // 없는 토큰은 404, 잘못된 요청은 400
+    let response = plain.get(format!("{}/api/share/nope", server.url)).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    let response = share(serde_json::json!({ "expires_in": 3600, "max_downloads": 0 })).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // 감사 기록: 링크별 다운로드 이력
+    let audits: Vec<serde_json::Value> = server
+        .http
+        .get(format!("{}/api/versions/1.0.0/shares", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(audits.len(), 3, "{:?}", audits);
+    let first = audits
+        .iter()
+        .find(|a| a["created_by"] == "alice")
+        .context("alice's link missing")?;
+    assert_eq!(first["download_count"], 2);
+    let bundles: Vec<bool> = first["downloads"]
+        .as_array()
+        .context("downloads missing")?
+        .iter()
+        .map(|d| d["bundle"].as_bool().unwrap_or_default())
+        .collect();
+    assert_eq!(bundles, vec![false, true]);
+    assert!(audits.iter().any(|a| a["revoked_at"].is_string()));
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn upload_validation_matches_the_real_upload_without_publishing() -> Result<()> {
+    let Some(server) = TestServer::start_with(|config| config.max_artifact_bytes = 64 * 1024).await? else {
+        return Ok(());
+    };
+    let marked = artifact_files(&[(".dm-product", b"sam-app\n"), ("app.txt", b"v1")]);
+    let form = |version: &str, artifact: Vec<u8>, file_name: &str| {
+        reqwest::multipart::Form::new()
+            .text("version", version.to_string())
+            .text("product", "sam-app")
+            .part(
+                "artifact",
+                reqwest::multipart::Part::bytes(artifact).file_name(file_name.to_string()),
+            )
+    };
+    let validate = |form: reqwest::multipart::Form| {
+        server
+            .http
+            .post(format!("{}/api/versions/validate", server.url))
+            .multipart(form)
+            .send()
+    };
+    let upload = |form: reqwest::multipart::Form| {
+        server
+            .http
+            .post(format!("{}/api/versions", server.url))
+            .multipart(form)
+            .send()
+    };
+    let failed = |report: &serde_json::Value| -> Vec<String> {
+        report["rules"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|r| r["status"] == "fail")
+            .filter_map(|r| r["rule"].as_str().map(str::to_string))
+            .collect()
+    };
+
+    // 통과해도 DB와 아티팩트 디렉토리에는 아무것도 남기지 않음
+    let response = validate(form("1.0.0", marked.clone(), "app-1.0.0.tar.gz")).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let report: serde_json::Value = response.json().await?;
+    assert_eq!(report["valid"], true, "{}", report);
+    assert_eq!(report["mode"], "artifact");
+    assert_eq!(report["checksum"], sha256(&marked));
+    let version = server.http.get(format!("{}/api/versions/1.0.0", server.url)).send().await?;
+    assert_eq!(version.status(), reqwest::StatusCode::NOT_FOUND);
+    assert_eq!(fs::read_dir(&server.artifact_dir).map_or(0, |d| d.count()), 0);
+
+    // 잘못된 semver, 풀 수 없는 복합 확장자, 제품 표식 없음을 한 번에 보고
+    let unmarked = artifact("v1");
+    let response = validate(form("1.0", unmarked.clone(), "app-1.0.0.tar.xz")).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+    let report: serde_json::Value = response.json().await?;
+    assert_eq!(failed(&report), ["version", "archive_format", "product_marker"], "{}", report);
+    let report: serde_json::Value = validate(form("1.0.0", unmarked.clone(), "app.tgz")).await?.json().await?;
+    assert_eq!(failed(&report), ["product_marker"], "{}", report);
+
+    // 실제 업로드도 같은 규칙으로 거부
+    let rejected = upload(form("1.0.0", unmarked.clone(), "app.tar.bz2")).await?;
+    assert_eq!(rejected.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(rejected.text().await?.contains(".tar.bz2"));
+    let rejected = upload(form("1.0.0", unmarked, "app.tar.gz")).await?;
+    assert_eq!(rejected.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // 크기 제한: 파일을 보내든 크기만 선언하든 같은 결과
+    let oversized = artifact_files(&[(".dm-product", b"sam-app"), ("app.bin", &noise(200 * 1024, "big"))]);
+    assert!(oversized.len() > 64 * 1024);
+    let report: serde_json::Value = validate(form("1.0.0", oversized.clone(), "app.tar.gz")).await?.json().await?;
+    assert_eq!(failed(&report), ["artifact_size"], "{}", report);
+    let rejected = upload(form("1.0.0", oversized.clone(), "app.tar.gz")).await?;
+    assert_eq!(rejected.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+
+    let metadata_only = |size: usize, file_name: &str| {
+        reqwest::multipart::Form::new()
+            .text("version", "1.0.0")
+            .text("product", "sam-app")
+            .text("checksum", sha256(&oversized))
+            .text("artifact_size", size.to_string())
+            .text("file_name", file_name.to_string())
+    };
+    let report: serde_json::Value = validate(metadata_only(oversized.len(), "app.tar.gz")).await?.json().await?;
+    assert_eq!(report["mode"], "metadata");
+    assert_eq!(failed(&report), ["artifact_size"], "{}", report);
+    let response = validate(metadata_only(1024, "app.tar.zst")).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let report: serde_json::Value = response.json().await?;
+    let skipped: Vec<&str> = report["rules"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|r| r["status"] == "skipped")
+        .filter_map(|r| r["rule"].as_str())
+        .collect();
+    assert_eq!(skipped, ["product_marker"], "{}", report);
+    let report: serde_json::Value = validate(metadata_only(1024, "app.tar.xz")).await?.json().await?;
+    assert_eq!(failed(&report), ["archive_format"], "{}", report);
+
+    // 올린 뒤에는 같은 버전이 중복으로 보고됨
+    upload(form("1.0.0", marked.clone(), "app.tar.gz")).await?.error_for_status()?;
+    let report: serde_json::Value = validate(form("1.0.0", marked.clone(), "app.tar.gz")).await?.json().await?;
+    assert_eq!(failed(&report), ["version_available"], "{}", report);
+    let duplicate = upload(form("1.0.0", marked, "app.tar.gz")).await?;
+    assert_eq!(duplicate.status(), reqwest::StatusCode::CONFLICT);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn share_links_warn_before_expiry_and_can_be_renewed() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    server.upload("1.0.0", artifact("v1")).await?;
+    let link: serde_json::Value = server
+        .http
+        .post(format!("{}/api/versions/1.0.0/share", server.url))
+        .json(&serde_json::json!({ "expires_in": 3 * 86400, "max_downloads": 2, "created_by": "alice" }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let token = link["token"].as_str().unwrap_or_default().to_string();
+    let list_shares = || async {
+        let shares: Vec<serde_json::Value> = server
+            .http
+            .get(format!("{}/api/versions/1.0.0/shares", server.url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        anyhow::Ok(shares)
+    };
+    let renew = |body: serde_json::Value| {
+        server
+            .http
+            .post(format!("{}/api/share/{}/renew", server.url, token))
+            .json(&body)
+            .send()
+    };
+
+    // 목록에 남은 횟수와 만료까지 남은 시간
+    let shares = list_shares().await?;
+    assert_eq!(shares[0]["remaining_downloads"], 2);
+    let expires_in = shares[0]["expires_in_secs"].as_i64().unwrap_or_default();
+    assert!((3 * 86400 - 60..=3 * 86400).contains(&expires_in), "{}", expires_in);
+
+    // 알림 기간(1일) 안에 들어오면 한 번만 알림 (이틀 23시간이 지난 것으로)
+    let state = AppState::new(server_config("", &server.artifact_dir), server.pool.clone());
+    assert_eq!(dm_server::api::warn_expiring_share_links(&state).await?, 0);
+    sqlx::query(
+        "UPDATE share_links SET created_at = created_at - INTERVAL '71 hours',
+                                expires_at = expires_at - INTERVAL '71 hours'
+         WHERE token = $1",
+    )
+        .bind(&token)
+        .execute(&server.pool)
+        .await?;
+    assert_eq!(dm_server::api::warn_expiring_share_links(&state).await?, 1);
+    assert_eq!(dm_server::api::warn_expiring_share_links(&state).await?, 0);
+    assert!(!list_shares().await?[0]["expiry_warned_at"].is_null());
+
+    // 연장하면 기록이 남고 알림 상태 초기화
+    let response = renew(serde_json::json!({ "extend_by": 31 * 86400 })).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let response = renew(serde_json::json!({ "extend_by": 30 * 86400 })).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let renewed: serde_json::Value = renew(serde_json::json!({ "extend_by": 86400, "renewed_by": "bob" }))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert!(renewed["expiry_warned_at"].is_null(), "{}", renewed);
+    let shares = list_shares().await?;
+    let expires_in = shares[0]["expires_in_secs"].as_i64().unwrap_or_default();
+    assert!((25 * 3600 - 60..=25 * 3600).contains(&expires_in), "{}", expires_in);
+    assert_eq!(shares[0]["renewals"].as_array().map(Vec::len), Some(1));
+    assert_eq!(shares[0]["renewals"][0]["renewed_by"], "bob");
+    assert_eq!(shares[0]["renewals"][0]["expires_at"], renewed["expires_at"]);
+
+    // 횟수를 다 쓴 링크는 연장하지 않음
+    let url = format!("{}/api/share/{}", server.url, token);
+    for _ in 0..2 {
+        reqwest::get(&url).await?.error_for_status()?;
+    }
+    assert_eq!(list_shares().await?[0]["remaining_downloads"], 0);
+    let response = renew(serde_json::json!({ "extend_by": 3600 })).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::CONFLICT);
+    let response = server
+        .http
+        .post(format!("{}/api/share/nope/renew", server.url))
+        .json(&serde_json::json!({ "extend_by": 3600 }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn artifact_downloads_require_a_client_key_or_admin_token() -> Result<()> {
+    let Some(server) = TestServer::start_with(|config| config.admin_token = Some("ops-secret".to_string())).await?
+    else {
+        return Ok(());
+    };
+    let client = server.register("e2e-artifact-auth").await?;
+    let v1 = artifact("v1");
+    server.upload("1.0.0", v1.clone()).await?;
+
+    let url = format!("{}/api/artifacts/1.0.0", server.url);
+    let status = |request: reqwest::RequestBuilder| async move { anyhow::Ok(request.send().await?.status()) };
+
+    // 인증 없음 / 모르는 키 / 틀린 운영자 토큰
+    assert_eq!(status(server.http.get(&url)).await?, reqwest::StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        status(server.http.get(&url).header("X-API-Key", "not-a-key")).await?,
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(server.http.get(&url).header("X-Admin-Token", "guess")).await?,
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+    // 없는 버전도 인증 전에는 드러나지 않음
+    assert_eq!(
+        status(server.http.get(format!("{}/api/artifacts/9.9.9", server.url))).await?,
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+
+    // 클라이언트 API 키: 다운로드 기록에 클라이언트가 남음
+    let body = server
+        .http
+        .get(&url)
+        .header("X-API-Key", &client.api_key)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    assert_eq!(body.as_ref(), v1.as_slice());
+    let downloads: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM artifact_downloads WHERE version = '1.0.0' AND client_id = $1")
+            .bind(client.id)
+            .fetch_one(&server.pool)
+            .await?;
+    assert_eq!(downloads, 1);
+
+    // 운영자 토큰 (헤더 두 가지)
+    for request in [
+        server.http.get(&url).header("X-Admin-Token", "ops-secret"),
+        server.http.get(&url).bearer_auth("ops-secret"),
+    ] {
+        assert_eq!(status(request).await?, reqwest::StatusCode::OK);
+    }
+
+    // 데몬은 원래 키를 보내므로 그대로 설치
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn ranged_artifact_requests_resume_and_reject_bad_ranges() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-ranges").await?;
+    let data = artifact_file("big.bin", &noise(300 * 1024 + 17, "ranges"));
+    server.upload("1.0.0", data.clone()).await?;
+
+    let url = format!("{}/api/artifacts/1.0.0", server.url);
+    let get = |range: &str| {
+        server
+            .http
+            .get(&url)
+            .header("X-API-Key", &client.api_key)
+            .header(reqwest::header::RANGE, range)
+            .send()
+    };
+
+    // 90%에서 끊긴 다운로드를 나머지 범위로 이어받음
+    let cut = data.len() * 9 / 10;
+    let first = get(&format!("bytes=0-{}", cut - 1)).await?;
+    assert_eq!(first.status(), reqwest::StatusCode::PARTIAL_CONTENT);
+    let checksum = first.headers()["x-checksum-sha256"].to_str()?.to_string();
+    let mut joined = first.bytes().await?.to_vec();
+    let rest = get(&format!("bytes={}-", cut)).await?;
+    assert_eq!(rest.status(), reqwest::StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        rest.headers()[reqwest::header::CONTENT_RANGE].to_str()?,
+        format!("bytes {}-{}/{}", cut, data.len() - 1, data.len())
+    );
+    assert_eq!(rest.headers()["x-checksum-sha256"].to_str()?, checksum);
+    joined.extend_from_slice(&rest.bytes().await?);
+    assert_eq!(sha256(&joined), checksum);
+    assert_eq!(checksum, sha256(&data));
+
+    // 잘못된 범위는 416과 전체 크기, 여러 범위는 전체 전송 (범위 해석은 artifacts.rs 단위 테스트)
+    let response = get(&format!("bytes={}-", data.len())).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        response.headers()[reqwest::header::CONTENT_RANGE].to_str()?,
+        format!("bytes */{}", data.len())
+    );
+    assert_eq!(get("bytes=0-1,5-6").await?.status(), reqwest::StatusCode::OK);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn large_uploads_are_streamed_to_disk_and_cleaned_up_on_failure() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-streamed-upload").await?;
+    let leftovers = |dir: &Path| -> Result<Vec<String>> {
+        Ok(fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(".upload-"))
+            .collect())
+    };
+
+    // 여러 조각으로 나뉘어 오는 큰 아티팩트도 크기와 체크섬이 그대로 기록되고 내려받아짐
+    let data = artifact_file("big.bin", &noise(6 * 1024 * 1024 + 5, "streamed"));
+    let path = server.upload("1.0.0", data.clone()).await?;
+    let stored = fs::read(server.artifact_dir.join(&path))?;
+    assert_eq!(stored, data);
+    let version: serde_json::Value = server
+        .http
+        .get(format!("{}/api/versions/1.0.0", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(version["artifact_size"], data.len() as u64);
+    assert_eq!(version["checksum"], sha256(&data));
+    let downloaded = server
+        .http
+        .get(format!("{}/api/artifacts/1.0.0", server.url))
+        .header("X-API-Key", &client.api_key)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    assert_eq!(sha256(&downloaded), sha256(&data));
+    assert!(leftovers(&server.artifact_dir)?.is_empty());
+
+    // 거부된 업로드는 임시 파일도 아티팩트도 남기지 않음
+    let form = reqwest::multipart::Form::new()
+        .text("version", "2.0.0")
+        .text("checksum", "0".repeat(64))
+        .part("artifact", reqwest::multipart::Part::bytes(data).file_name("app.tar.gz"));
+    let rejected = server
+        .http
+        .post(format!("{}/api/versions", server.url))
+        .multipart(form)
+        .send()
+        .await?;
+    assert_eq!(rejected.status(), reqwest::StatusCode::BAD_REQUEST);
+    assert!(leftovers(&server.artifact_dir)?.is_empty());
+    assert!(!server.artifact_dir.join("2.0.0.tar.gz").exists());
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn uploaded_artifacts_keep_their_compound_extension_and_name() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-artifact-names").await?;
+    let upload = |version: &str, file_name: &str, deploy_type: &str| {
+        let form = reqwest::multipart::Form::new()
+            .text("version", version.to_string())
+            .text("deploy_type", deploy_type.to_string())
+            .part(
+                "artifact",
+                reqwest::multipart::Part::bytes(artifact("named")).file_name(file_name.to_string()),
+            );
+        server.http.post(format!("{}/api/versions", server.url)).multipart(form).send()
+    };
+
+    // 저장 이름과 Content-Disposition 규칙 자체는 artifacts.rs 단위 테스트에서 확인하고,
+    // 여기서는 업로드 응답, 저장 파일, 다운로드 헤더가 같은 이름을 쓰는지만 확인
+    let cases = [
+        ("1.0.0", "update.tar.gz", "1.0.0.tar.gz", None),
+        ("1.0.1", "myapp-1.2.3", "1.0.1", None),
+        (
+            "1.0.2",
+            "업데이트.tar.gz",
+            "1.0.2.tar.gz",
+            Some("attachment; filename=\"____.tar.gz\"; filename*=UTF-8''%EC%97%85%EB%8D%B0%EC%9D%B4%ED%8A%B8.tar.gz"),
+        ),
+    ];
+    for (version, file_name, stored, disposition) in cases {
+        let uploaded: serde_json::Value = upload(version, file_name, "app")
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        assert_eq!(uploaded["artifact_path"], stored, "{}", file_name);
+        assert_eq!(uploaded["original_filename"], file_name);
+        assert!(server.artifact_dir.join(stored).exists(), "{}", stored);
+
+        let response = server
+            .http
+            .get(format!("{}/api/artifacts/{}", server.url, version))
+            .header("X-API-Key", &client.api_key)
+            .send()
+            .await?
+            .error_for_status()?;
+        let expected = match disposition {
+            Some(disposition) => disposition.to_string(),
+            None => format!("attachment; filename=\"{}\"", file_name),
+        };
+        assert_eq!(response.headers()[reqwest::header::CONTENT_DISPOSITION].to_str()?, expected);
+    }
+
+    // 파일 이름 없이 올리면 내용으로 판별한 형식의 확장자로 저장하고 그 이름으로 내려줌
+    // (형식 판별은 upload.rs 단위 테스트에서 확인, 여기서는 아카이브와 이미지 경로를 하나씩)
+    let mut gz_image = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gz_image.write_all(&noise(4096, "gz-image"))?;
+    let unnamed = [
+        ("1.1.0", zip_artifact(&[("app.txt", b"zip", 0o644)])?, "app", "1.1.0.zip"),
+        ("1.1.1", gz_image.finish()?, "image", "1.1.1.img.gz"),
+    ];
+    for (version, data, deploy_type, stored) in unnamed {
+        let form = reqwest::multipart::Form::new()
+            .text("version", version)
+            .text("deploy_type", deploy_type)
+            .part("artifact", reqwest::multipart::Part::bytes(data));
+        let uploaded: serde_json::Value = server
+            .http
+            .post(format!("{}/api/versions", server.url))
+            .multipart(form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        assert_eq!(uploaded["artifact_path"], stored);
+        assert!(uploaded["original_filename"].is_null(), "{}", uploaded);
+
+        let response = server
+            .http
+            .get(format!("{}/api/artifacts/{}", server.url, version))
+            .header("X-API-Key", &client.api_key)
+            .send()
+            .await?
+            .error_for_status()?;
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_DISPOSITION].to_str()?,
+            format!("attachment; filename=\"{}\"", stored)
+        );
+    }
+
+    // 애플리케이션 아티팩트는 장비가 풀 수 없는 이름이면 거부
+    let response = upload("1.0.3", "foo.tar.xz", "app").await?;
+    assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // 디스크 이미지는 이름과 상관없이 내용이 gzip 외의 압축이면 거부 (장비가 압축된 채로 씀)
+    let form = reqwest::multipart::Form::new()
+        .text("version", "1.0.4")
+        .text("deploy_type", "image")
+        .part("artifact", reqwest::multipart::Part::bytes(zip_artifact(&[("rootfs.img", b"raw", 0o644)])?).file_name("foo.zip"));
+    let response = server.http.post(format!("{}/api/versions", server.url)).multipart(form).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.text().await?.contains("zip compressed"));
+
+    server.stop().await
+}
@@ -0,0 +1,1636 @@
+//! 체크인과 서버 명령 (배포 대기열, 결과 보고, 서버 설정, 폴링 간격, 인스턴스 감지)
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use sqlx::{Executor, Row};
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+
+use dm_client::polling::PollingDaemon;
+use dm_client::state::LocalState;
+
+use common::*;
+
+#[tokio::test]
+async fn deactivated_version_cancels_pending_deploy() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-deactivated").await?;
+
+    server.publish(&client, "1.0.0", artifact("v1")).await?;
+    // 장비가 체크인하기 전에 버전 비활성화
+    server.deactivate("1.0.0").await?;
+
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "cancelled");
+    assert_eq!(server.client_versions(&client).await?.1, None);
+    assert_eq!(client.read("app.txt"), None);
+
+    server.stop().await
+}
+
+/// REQUIRE_TLS_CLIENTS: 신뢰하는 프록시가 HTTPS로 받았다고 알린 체크인만 허용
+#[tokio::test]
+async fn plaintext_checkins_are_rejected_when_tls_is_required() -> Result<()> {
+    let Some(server) = TestServer::start_with(|config| {
+        config.require_tls_clients = true;
+        config.trusted_proxies = vec!["127.0.0.1/32".parse().expect("valid network")];
+    })
+    .await?
+    else {
+        return Ok(());
+    };
+    let client = server.register("e2e-require-tls").await?;
+    let checkin = |proto: Option<&str>| {
+        let mut request = server
+            .http
+            .post(format!("{}/api/checkin", server.url))
+            .header("X-API-Key", &client.api_key)
+            .json(&serde_json::json!({ "current_version": null, "status": "online" }));
+        if let Some(proto) = proto {
+            request = request.header("X-Forwarded-Proto", proto);
+        }
+        request.send()
+    };
+
+    let response = checkin(None).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+    assert!(response.text().await?.contains("HTTPS required"));
+    assert_eq!(checkin(Some("http")).await?.status(), reqwest::StatusCode::FORBIDDEN);
+
+    // 프록시를 여러 번 거친 경우 첫 값이 클라이언트의 프로토콜
+    checkin(Some("https")).await?.error_for_status()?;
+    checkin(Some("https, http")).await?.error_for_status()?;
+
+    server.stop().await
+}
+
+/// 체크인 응답의 설정은 데몬 재시작 없이 다음 업데이트에 적용
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn pushed_config_overrides_local_settings_on_the_next_poll() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let marks = tempfile::tempdir()?;
+    let log = marks.path().join("log");
+    let trusted = server
+        .register_with("e2e-pushed-config", |config| config.allow_remote_scripts = true)
+        .await?;
+    let untrusted = server.register("e2e-pushed-config-untrusted").await?;
+    let push = |client: &TestClient| {
+        let broken = client.service_dir.join("broken");
+        server
+            .http
+            .put(format!("{}/api/clients/{}/config", server.url, client.id))
+            .json(&serde_json::json!({ "config": {
+                "service_dir": "/somewhere/else",
+                "restart_command": format!("echo restart >> {}", log.display()),
+                "pre_update_script": format!("echo pre >> {}", log.display()),
+                "post_update_script": format!("echo post >> {}", log.display()),
+                "rollback_on_failure": false,
+                "health_checks": [{ "type": "command", "command": format!("test ! -f {}", broken.display()) }],
+            } }))
+            .send()
+    };
+    let read_log = || fs::read_to_string(&log).unwrap_or_default();
+
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.upload("1.0.1", artifact_files(&[("app.txt", b"v2"), ("broken", b"")])).await?;
+
+    // 설정 변경과 같은 체크인의 업데이트부터 적용 (설치 경로는 그대로)
+    push(&trusted).await?.error_for_status()?;
+    server.deploy(&trusted, "1.0.0").await?;
+    trusted.daemon.poll_once().await;
+    let logs = server.update_logs(&trusted).await?;
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert_eq!(trusted.read("app.txt").as_deref(), Some("v1"));
+    assert_eq!(read_log(), "pre\nrestart\npost\n");
+
+    // rollback_on_failure=false: 헬스 체크가 실패해도 새 버전을 그대로 둠
+    server.deploy(&trusted, "1.0.1").await?;
+    trusted.daemon.poll_once().await;
+    let logs = server.update_logs(&trusted).await?;
+    assert_eq!(logs[1].status, "failed");
+    assert!(
+        logs[1].error_message.as_deref().is_some_and(|m| m.contains("Health check failed")),
+        "{:?}",
+        logs[1].error_message
+    );
+    assert_eq!(trusted.read("app.txt").as_deref(), Some("v2"));
+    assert!(trusted.service_dir.join("broken").exists());
+
+    // DM_ALLOW_REMOTE_SCRIPTS가 없으면 명령은 적용하지 않음
+    fs::remove_file(&log)?;
+    push(&untrusted).await?.error_for_status()?;
+    server.deploy(&untrusted, "1.0.0").await?;
+    untrusted.daemon.poll_once().await;
+    let logs = server.update_logs(&untrusted).await?;
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert_eq!(read_log(), "");
+
+    server.stop().await
+}
+
+/// `?tz=`는 UTC 값을 유지한 채 `<필드>_local`을 덧붙이고, 알 수 없는 타임존은 400
+#[tokio::test]
+async fn tz_query_adds_local_times_and_rejects_unknown_zones() -> Result<()> {
+    use sqlx::types::chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-tz-query").await?;
+    server
+        .http
+        .put(format!("{}/api/clients/{}/config", server.url, client.id))
+        .json(&serde_json::json!({ "config": { "health_check_timeout": 20 } }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let history_url = format!("{}/api/clients/{}/config/history", server.url, client.id);
+
+    let utc: serde_json::Value = server.http.get(&history_url).send().await?.error_for_status()?.json().await?;
+    let entry = &utc.as_array().context("history")?[0];
+    let changed_at = entry["changed_at"].as_str().context("changed_at")?;
+    assert!(changed_at.ends_with('Z'), "{}", changed_at);
+    assert!(entry.get("changed_at_local").is_none(), "{}", entry);
+
+    for (tz, offset, abbreviation) in [("Asia/Seoul", 9, "KST"), ("UTC", 0, "UTC"), ("America/Sao_Paulo", -3, "-03")] {
+        let local: serde_json::Value = server
+            .http
+            .get(&history_url)
+            .query(&[("tz", tz)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let entry = &local.as_array().context("history")?[0];
+        assert_eq!(entry["changed_at"].as_str(), Some(changed_at), "{}", tz);
+        let shown = entry["changed_at_local"].as_str().context("changed_at_local")?;
+        let (wall, zone) = shown.rsplit_once(' ').context("zone")?;
+        assert_eq!(zone, abbreviation, "{}", shown);
+        // 로컬 표시를 해당 오프셋으로 되돌리면 같은 UTC 시각
+        let wall = NaiveDateTime::parse_from_str(wall, "%Y-%m-%d %H:%M:%S")?;
+        let round_trip = FixedOffset::east_opt(offset * 3600)
+            .and_then(|zone| zone.from_local_datetime(&wall).single())
+            .context("local time")?;
+        assert_eq!(
+            round_trip.timestamp(),
+            DateTime::parse_from_rfc3339(changed_at)?.timestamp(),
+            "{} -> {}",
+            changed_at,
+            shown
+        );
+    }
+
+    for tz in ["Not/AZone", "Asia/Seoull", ""] {
+        let response = server.http.get(&history_url).query(&[("tz", tz)]).send().await?;
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST, "{}", tz);
+        assert!(response.text().await?.contains("Unknown time zone"), "{}", tz);
+    }
+    let response = server
+        .http
+        .get(format!("{}/api/attention", server.url))
+        .query(&[("tz", "Mars/Olympus_Mons")])
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn queued_actions_are_delivered_one_at_a_time() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let marker = tempfile::tempdir()?;
+    let restarts = marker.path().join("restarts");
+    let client = server
+        .register_with("e2e-action-queue", |config| {
+            config.restart_command = format!("echo restart >> {}", restarts.display());
+        })
+        .await?;
+    let restart_count = || fs::read_to_string(&restarts).map_or(0, |s| s.lines().count());
+    let actions_url = format!("{}/api/clients/{}/actions", server.url, client.id);
+    let deploy = |version: &str| {
+        server
+            .http
+            .post(format!("{}/api/clients/{}/deploy", server.url, client.id))
+            .json(&serde_json::json!({ "version": version }))
+            .send()
+    };
+    let queue = |action: &str| {
+        server
+            .http
+            .post(&actions_url)
+            .json(&serde_json::json!({ "type": action, "reason": "e2e" }))
+            .send()
+    };
+
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.upload("1.1.0", artifact("v2")).await?;
+
+    // 두 번째 배포와 재시작은 첫 배포를 덮어쓰지 않고 뒤에 대기
+    let first: serde_json::Value = deploy("1.0.0").await?.error_for_status()?.json().await?;
+    assert_eq!(first["queue_position"], 0);
+    let second: serde_json::Value = deploy("1.1.0").await?.error_for_status()?.json().await?;
+    assert_eq!(second["queue_position"], 1);
+    assert_eq!(deploy("1.1.0").await?.status(), reqwest::StatusCode::CONFLICT);
+    let restart: serde_json::Value = queue("restart").await?.error_for_status()?.json().await?;
+    assert_eq!(restart["queue_position"], 2);
+    assert_eq!(queue("deploy").await?.status(), reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(server.client_versions(&client).await?.1.as_deref(), Some("1.0.0"));
+
+    // 대기 중인 작업은 개별 취소
+    let rollback: serde_json::Value = queue("rollback").await?.error_for_status()?.json().await?;
+    let cancel_url = format!("{}/{}", actions_url, rollback["action_id"].as_str().context("action_id missing")?);
+    server.http.delete(&cancel_url).send().await?.error_for_status()?;
+    assert_eq!(server.http.delete(&cancel_url).send().await?.status(), reqwest::StatusCode::CONFLICT);
+
+    let open: Vec<serde_json::Value> = server.http.get(&actions_url).send().await?.json().await?;
+    let types: Vec<&str> = open.iter().filter_map(|a| a["action_type"].as_str()).collect();
+    assert_eq!(types, ["deploy", "deploy", "restart"]);
+
+    // 설치 결과 보고와 함께 다음 배포가 타겟이 됨
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    assert_eq!(
+        server.client_versions(&client).await?,
+        (Some("1.0.0".to_string()), Some("1.1.0".to_string()))
+    );
+
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    assert_eq!(server.client_versions(&client).await?.1, None);
+    let after_installs = restart_count();
+
+    // 재시작은 배포가 모두 끝난 뒤 전달되고 결과 보고로 완료
+    client.daemon.poll_once().await;
+    assert_eq!(restart_count(), after_installs + 1);
+    let open: Vec<serde_json::Value> = server.http.get(&actions_url).send().await?.json().await?;
+    assert!(open.is_empty(), "{:?}", open);
+
+    let all: Vec<serde_json::Value> = server
+        .http
+        .get(format!("{}?all=true", actions_url))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let states: Vec<(&str, &str)> = all
+        .iter()
+        .filter_map(|a| Some((a["action_type"].as_str()?, a["state"].as_str()?)))
+        .collect();
+    assert_eq!(
+        states,
+        [
+            ("deploy", "completed"),
+            ("deploy", "completed"),
+            ("restart", "completed"),
+            ("rollback", "cancelled")
+        ]
+    );
+
+    // 다음 체크인은 할 일 없음
+    client.daemon.poll_once().await;
+    assert_eq!(restart_count(), after_installs + 1);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn every_deploy_path_records_who_initiated_it() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-initiated-by").await?;
+
+    // 운영자를 밝힌 배포
+    server.upload("1.0.0", artifact("v1")).await?;
+    let queued: serde_json::Value = server
+        .http
+        .post(format!("{}/api/clients/{}/deploy", server.url, client.id))
+        .json(&serde_json::json!({ "version": "1.0.0", "initiated_by": "alice" }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(queued["initiated_by"], "alice");
+    client.daemon.poll_once().await;
+
+    // 요청자를 밝히지 않은 배포는 "api"
+    server.install(&client, "1.1.0", artifact("v2")).await?;
+
+    // 별칭을 따라가는 배포: 별칭 이동으로 인한 재지정은 별칭이 시작 주체
+    server.upload("1.2.0", artifact("v3")).await?;
+    server.upload("1.3.0", artifact("v4")).await?;
+    let set_alias = |version: &str| {
+        server
+            .http
+            .put(format!("{}/api/aliases/stable", server.url))
+            .json(&serde_json::json!({ "version": version, "moved_by": "bob" }))
+            .send()
+    };
+    set_alias("1.2.0").await?.error_for_status()?;
+    server
+        .http
+        .post(format!("{}/api/clients/{}/deploy", server.url, client.id))
+        .json(&serde_json::json!({ "version": "alias:stable", "track": true, "initiated_by": "carol" }))
+        .send()
+        .await?
+        .error_for_status()?;
+    client.daemon.poll_once().await;
+    set_alias("1.3.0").await?.error_for_status()?;
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    let summary: Vec<(&str, &str, Option<&str>)> = logs
+        .iter()
+        .map(|l| (l.to_version.as_str(), l.status.as_str(), l.initiated_by.as_deref()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("1.0.0", "completed", Some("alice")),
+            ("1.1.0", "completed", Some("api")),
+            ("1.2.0", "completed", Some("carol")),
+            ("1.3.0", "completed", Some("alias:stable")),
+        ]
+    );
+    assert_eq!(client.read("app.txt").as_deref(), Some("v4"));
+
+    // 로그 API에서 시작 주체로 조회
+    let by_alice: Vec<serde_json::Value> = server
+        .http
+        .get(format!("{}/api/logs?initiated_by=alice", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(by_alice.len(), 1, "{:?}", by_alice);
+    assert_eq!(by_alice[0]["to_version"], "1.0.0");
+    assert_eq!(by_alice[0]["initiated_by"], "alice");
+
+    // 재시작/롤백 작업도 요청자를 기록
+    let action: serde_json::Value = server
+        .http
+        .post(format!("{}/api/clients/{}/actions", server.url, client.id))
+        .json(&serde_json::json!({ "type": "restart", "initiated_by": "dave" }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(action["initiated_by"], "dave");
+    let queued: Vec<serde_json::Value> = server
+        .http
+        .get(format!("{}/api/clients/{}/actions", server.url, client.id))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(queued[0]["payload"]["initiated_by"], "dave");
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn conditional_gets_reuse_cached_bodies() -> Result<()> {
+    use dm_client::api::DmApiClient;
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-http-cache").await?;
+    let other = server.register("e2e-http-cache-other").await?;
+    let dir = TempDir::new()?;
+    let cache_path = dir.path().join("http-cache.json");
+    let api = |key: &str, cache: Option<&Path>| DmApiClient::new(&server.url, key).with_http_cache(cache);
+
+    // 스크립트 조회: ETag가 같으면 304
+    server.upload("1.0.0", artifact("v1")).await?;
+    let scripts_url = format!("{}/api/versions/1.0.0/scripts", server.url);
+    let first = server.http.get(&scripts_url).send().await?.error_for_status()?;
+    let etag = first.headers()["etag"].to_str()?.to_string();
+    let revalidated = server
+        .http
+        .get(&scripts_url)
+        .header("If-None-Match", &etag)
+        .send()
+        .await?;
+    assert_eq!(revalidated.status(), reqwest::StatusCode::NOT_MODIFIED);
+    assert_eq!(revalidated.headers()["etag"].to_str()?, etag);
+
+    // 첫 조회로 캐시 저장 → 본문을 바꿔 두면 304 응답에서 캐시된 본문이 쓰였는지 확인할 수 있음
+    let info = api(&client.api_key, Some(&cache_path)).fetch_self().await?;
+    assert_eq!(info.name, "e2e-http-cache");
+    let cached = fs::read_to_string(&cache_path)?;
+    assert!(cached.contains("e2e-http-cache"), "{}", cached);
+    fs::write(&cache_path, cached.replace("\"name\\\":\\\"e2e-http-cache\\\"", "\"name\\\":\\\"from-cache\\\""))?;
+    let info = api(&client.api_key, Some(&cache_path)).fetch_self().await?;
+    assert_eq!(info.name, "from-cache");
+
+    // 캐시를 끄면 (DM_NO_HTTP_CACHE) 항상 서버 본문
+    let info = api(&client.api_key, None).fetch_self().await?;
+    assert_eq!(info.name, "e2e-http-cache");
+
+    // 내용이 바뀌면 ETag가 달라져 새 본문을 받고 캐시도 갱신
+    server.deploy(&client, "1.0.0").await?;
+    let info = api(&client.api_key, Some(&cache_path)).fetch_self().await?;
+    assert_eq!(info.name, "e2e-http-cache");
+    assert_eq!(info.target_version.as_deref(), Some("1.0.0"));
+
+    // API Key가 바뀌면 이전 항목은 버려짐
+    let info = api(&other.api_key, Some(&cache_path)).fetch_self().await?;
+    assert_eq!(info.name, "e2e-http-cache-other");
+    let cached = fs::read_to_string(&cache_path)?;
+    assert!(!cached.contains("target_version\\\":\\\"1.0.0"), "{}", cached);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn installed_checksums_feed_the_integrity_report() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-integrity").await?;
+    let idle = server.register("e2e-integrity-idle").await?;
+
+    let v1 = artifact("v1");
+    server.install(&client, "1.0.0", v1.clone()).await?;
+    client.daemon.poll_once().await;
+    idle.daemon.poll_once().await;
+
+    let integrity = |id: Uuid| {
+        sqlx::query_as::<_, (Option<String>, Option<bool>)>(
+            "SELECT reported_checksum, integrity_ok FROM clients WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_one(&server.pool)
+    };
+    assert_eq!(integrity(client.id).await?, (Some(sha256(&v1)), Some(true)));
+    // 설치한 적 없는 장비는 보고할 체크섬이 없음
+    assert_eq!(integrity(idle.id).await?, (None, None));
+
+    // 서버 기록과 다른 아티팩트가 설치된 것처럼 만듦
+    sqlx::query("UPDATE clients SET current_checksum = $2 WHERE id = $1")
+        .bind(client.id)
+        .bind("0".repeat(64))
+        .execute(&server.pool)
+        .await?;
+    client.daemon.poll_once().await;
+    assert_eq!(integrity(client.id).await?, (Some(sha256(&v1)), Some(false)));
+
+    let report: serde_json::Value = server
+        .http
+        .get(format!("{}/api/reports/integrity", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(report["mismatched"], 1, "{}", report);
+    assert_eq!(report["unknown"], 1, "{}", report);
+    assert_eq!(report["mismatches"][0]["name"], "e2e-integrity");
+    assert_eq!(report["mismatches"][0]["reported_checksum"], sha256(&v1));
+    assert_eq!(report["mismatches"][0]["expected_checksum"], "0".repeat(64));
+    assert!(report["mismatches"][0]["mismatch_since"].is_string(), "{}", report);
+    assert!(report["mismatches"][0]["last_ok_at"].is_string(), "{}", report);
+    assert_eq!(report["unknown_clients"][0]["name"], "e2e-integrity-idle");
+
+    let attention: serde_json::Value = server
+        .http
+        .get(format!("{}/api/attention", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let flagged = attention["integrity_mismatch"].as_array().cloned().unwrap_or_default();
+    assert_eq!(flagged.len(), 1, "{}", attention);
+    assert_eq!(flagged[0]["reason"], "integrity_mismatch");
+
+    // 다시 배포하면 일치로 돌아오고 불일치 시각이 지워짐
+    server.install(&client, "1.1.0", artifact("v2")).await?;
+    client.daemon.poll_once().await;
+    assert_eq!(integrity(client.id).await?, (Some(sha256(&artifact("v2"))), Some(true)));
+    let cleared: bool =
+        sqlx::query_scalar("SELECT integrity_mismatch_at IS NULL FROM clients WHERE id = $1")
+            .bind(client.id)
+            .fetch_one(&server.pool)
+            .await?;
+    assert!(cleared);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn unchanged_server_config_is_not_resent_on_checkin() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-config-hash").await?;
+    let set_config = |role: &str| {
+        let exclude: Vec<String> = (0..200).map(|i| format!("data/cache-{}/**", i)).collect();
+        server
+            .http
+            .put(format!("{}/api/clients/{}/config", server.url, client.id))
+            .json(&serde_json::json!({ "config": { "role": role, "backup_exclude": exclude } }))
+            .send()
+    };
+    set_config("pos").await?.error_for_status()?;
+
+    let checkin = |config_hash: Option<&str>| {
+        server
+            .http
+            .post(format!("{}/api/checkin", server.url))
+            .header("X-API-Key", &client.api_key)
+            .json(&serde_json::json!({
+                "current_version": null, "status": "online", "config_hash": config_hash
+            }))
+            .send()
+    };
+
+    // 해시가 없는 (이전 버전) 클라이언트는 매번 전체 설정
+    let full = checkin(None).await?.error_for_status()?.bytes().await?;
+    let response: serde_json::Value = serde_json::from_slice(&full)?;
+    assert_eq!(response["config"]["role"], "pos");
+    let hash = response["config_hash"].as_str().context("config_hash missing")?.to_string();
+
+    // 받은 해시를 보내면 설정 생략
+    let lean = checkin(Some(&hash)).await?.error_for_status()?.bytes().await?;
+    let response: serde_json::Value = serde_json::from_slice(&lean)?;
+    assert!(response.get("config").is_none(), "{}", response);
+    assert!(response.get("config_hash").is_none(), "{}", response);
+    assert!(lean.len() * 20 < full.len(), "{} vs {} bytes", lean.len(), full.len());
+
+    // 설정이 바뀌면 다시 전송
+    set_config("kiosk").await?.error_for_status()?;
+    let response: serde_json::Value = checkin(Some(&hash)).await?.error_for_status()?.json().await?;
+    assert_eq!(response["config"]["role"], "kiosk");
+    assert_ne!(response["config_hash"], hash.as_str());
+
+    // 데몬: 한 번 받은 설정은 저장하고, 바뀐 설정은 다음 체크인에서 반영
+    client.daemon.poll_once().await;
+    assert_eq!(LocalState::load(&client.service_dir.to_string_lossy()).role.as_deref(), Some("kiosk"));
+    client.daemon.poll_once().await;
+    set_config("pos").await?.error_for_status()?;
+    client.daemon.poll_once().await;
+    assert_eq!(LocalState::load(&client.service_dir.to_string_lossy()).role.as_deref(), Some("pos"));
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn security_versions_are_urgent_and_may_bypass_pause() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let cautious = server.register("e2e-security-cautious").await?;
+    let eager = server
+        .register_with("e2e-security-eager", |config| config.urgent_during_pause = true)
+        .await?;
+
+    server.upload("1.0.0", artifact("fix")).await?;
+    let set_severity = |severity: &str| {
+        server
+            .http
+            .patch(format!("{}/api/versions/1.0.0", server.url))
+            .json(&serde_json::json!({ "severity": severity }))
+            .send()
+    };
+    assert_eq!(set_severity("critical").await?.status(), reqwest::StatusCode::BAD_REQUEST);
+    let version: serde_json::Value = set_severity("security").await?.error_for_status()?.json().await?;
+    assert_eq!(version["severity"], "security");
+
+    // 두 장비 모두 현장에서 일시 중지
+    for client in [&cautious, &eager] {
+        dm_client::control::pause(&client.config.control_dir, None, Some("inventory".to_string()))?;
+        server.deploy(client, "1.0.0").await?;
+        client.daemon.poll_once().await;
+    }
+
+    // 정책이 없으면 보안 업데이트도 보류, 허용한 장비는 설치
+    assert_eq!(cautious.read(".dm-version"), None);
+    assert_eq!(eager.read(".dm-version").as_deref(), Some("1.0.0"));
+    assert_eq!(eager.read("app.txt").as_deref(), Some("fix"));
+
+    // 일시 중지가 풀리면 나머지도 설치하고, 응답에 urgent 표시
+    dm_client::control::resume(&cautious.config.control_dir)?;
+    let response: serde_json::Value = server
+        .http
+        .post(format!("{}/api/checkin", server.url))
+        .header("X-API-Key", &cautious.api_key)
+        .json(&serde_json::json!({ "current_version": null, "status": "online" }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(response["action"], "update");
+    assert_eq!(response["urgent"], true);
+    cautious.daemon.poll_once().await;
+    assert_eq!(cautious.read(".dm-version").as_deref(), Some("1.0.0"));
+
+    // 보안 버전만 따로 집계한 패치 적용 시간
+    let sla: serde_json::Value = server
+        .http
+        .get(format!("{}/api/reports/sla?severity=security&window=1d", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(sla["severity"], "security");
+    assert_eq!(sla["completed"], 2, "{}", sla);
+    let normal: serde_json::Value = server
+        .http
+        .get(format!("{}/api/reports/sla?severity=normal&window=1d", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(normal["offered"], 0, "{}", normal);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn surveys_run_allowlisted_collectors_and_store_results() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-survey").await?;
+    let survey_url = format!("{}/api/clients/{}/survey", server.url, client.id);
+    let queue = |commands: serde_json::Value| {
+        server
+            .http
+            .post(&survey_url)
+            .json(&serde_json::json!({ "commands": commands, "initiated_by": "erin" }))
+            .send()
+    };
+
+    // 정해진 이름만 요청 가능 (명령 문자열은 거부)
+    assert_eq!(queue(serde_json::json!(["uname -a"])).await?.status(), reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(queue(serde_json::json!([])).await?.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // 읽기 전용이라 일시 중지 중에도 수집
+    dm_client::control::pause(&client.config.control_dir, None, Some("inventory".to_string()))?;
+    let queued: serde_json::Value = queue(serde_json::json!(["disks", "system", "system"]))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(queued["commands"], serde_json::json!(["system", "disks"]));
+    client.daemon.poll_once().await;
+
+    let surveys: Vec<serde_json::Value> = server
+        .http
+        .get(format!("{}/api/clients/{}/surveys", server.url, client.id))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(surveys.len(), 1);
+    let survey = &surveys[0];
+    assert_eq!(survey["action_id"], queued["action_id"]);
+    assert!(survey["results"]["system"]["cpu_count"].as_u64().unwrap_or_default() > 0, "{}", survey);
+    assert!(survey["results"]["disks"].is_array(), "{}", survey);
+    assert!(survey["size_bytes"].as_i64().unwrap_or_default() > 0);
+
+    let actions: Vec<serde_json::Value> = server
+        .http
+        .get(format!("{}/api/clients/{}/actions?all=true", server.url, client.id))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(actions[0]["action_type"], "survey");
+    assert_eq!(actions[0]["state"], "completed");
+
+    // 요청하지 않은 항목이나 제한을 넘는 결과는 거부
+    let next: serde_json::Value = queue(serde_json::json!(["network"])).await?.error_for_status()?.json().await?;
+    let upload = |results: serde_json::Value| {
+        server
+            .http
+            .post(format!("{}/api/survey-result", server.url))
+            .header("X-API-Key", &client.api_key)
+            .json(&serde_json::json!({ "action_id": next["action_id"], "results": results }))
+            .send()
+    };
+    let unrequested = upload(serde_json::json!({ "system": {} })).await?;
+    assert_eq!(unrequested.status(), reqwest::StatusCode::BAD_REQUEST);
+    let oversized = upload(serde_json::json!({ "network": "x".repeat(300 * 1024) })).await?;
+    assert_eq!(oversized.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+    let recorded: serde_json::Value = upload(serde_json::json!({ "network": [] }))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(recorded["state"], "completed");
+    // 재전송은 성공으로 처리
+    upload(serde_json::json!({ "network": [] })).await?.error_for_status()?;
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn deleted_clients_keep_their_update_logs_until_purged() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let gone = server.register("e2e-history-gone").await?;
+    let kept = server.register("e2e-history-kept").await?;
+    server.upload("1.0.0", artifact("v1")).await?;
+    for client in [&gone, &kept] {
+        server.deploy(client, "1.0.0").await?;
+        client.daemon.poll_once().await;
+    }
+
+    let list_logs = || async {
+        let logs: Vec<serde_json::Value> = server
+            .http
+            .get(format!("{}/api/logs", server.url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        anyhow::Ok(logs)
+    };
+    let purge = |body: serde_json::Value| {
+        server
+            .http
+            .post(format!("{}/api/admin/purge-client-history", server.url))
+            .json(&body)
+            .send()
+    };
+
+    // 삭제해도 로그는 기록 당시 이름으로 남음
+    let deleted: serde_json::Value = server
+        .http
+        .delete(format!("{}/api/clients/{}", server.url, gone.id))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(deleted["retained_update_logs"], 1, "{}", deleted);
+    assert_eq!(deleted["forced"], false);
+    let status = server
+        .http
+        .delete(format!("{}/api/clients/{}", server.url, gone.id))
+        .send()
+        .await?
+        .status();
+    assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+
+    let logs = list_logs().await?;
+    let mut names: Vec<(&str, bool)> = logs
+        .iter()
+        .map(|l| (l["client_name"].as_str().unwrap(), l["client_deleted"].as_bool().unwrap()))
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![("e2e-history-gone (deleted)", true), ("e2e-history-kept", false)]
+    );
+    let orphan = logs.iter().find(|l| l["client_deleted"] == true).unwrap();
+    assert!(orphan["client_id"].is_null(), "{}", orphan);
+    assert_eq!(orphan["status"], "completed");
+
+    // 삭제된 클라이언트의 API Key는 더 이상 쓸 수 없음
+    let status = server
+        .http
+        .post(format!("{}/api/checkin", server.url))
+        .header("X-API-Key", &gone.api_key)
+        .json(&serde_json::json!({ "current_version": "1.0.0", "status": "online" }))
+        .send()
+        .await?
+        .status();
+    assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+
+    // 삭제된 클라이언트는 이름으로, 현재 클라이언트는 ID로 영구 삭제
+    assert_eq!(purge(serde_json::json!({})).await?.status(), reqwest::StatusCode::BAD_REQUEST);
+    let purged: serde_json::Value = purge(serde_json::json!({ "client_name": "e2e-history-gone" }))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(purged["update_logs"], 1, "{}", purged);
+    let logs = list_logs().await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0]["client_name"], "e2e-history-kept");
+
+    let purged: serde_json::Value = purge(serde_json::json!({ "client_id": kept.id }))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(purged["update_logs"], 1, "{}", purged);
+    assert_eq!(purged["artifact_downloads"], 1, "{}", purged);
+    assert!(list_logs().await?.is_empty());
+    assert!(server.update_logs(&kept).await?.is_empty());
+
+    // 업데이트 중인 클라이언트는 force 없이 삭제 거부
+    let checkin = server
+        .http
+        .post(format!("{}/api/checkin", server.url))
+        .header("X-API-Key", &kept.api_key)
+        .json(&serde_json::json!({ "current_version": "1.0.0", "status": "updating" }))
+        .send()
+        .await?;
+    checkin.error_for_status()?;
+    let delete = |query: &'static str| {
+        server
+            .http
+            .delete(format!("{}/api/clients/{}{}", server.url, kept.id, query))
+            .send()
+    };
+    assert_eq!(delete("").await?.status(), reqwest::StatusCode::CONFLICT);
+    let deleted: serde_json::Value = delete("?force=true").await?.error_for_status()?.json().await?;
+    assert_eq!(deleted["forced"], true, "{}", deleted);
+    assert_eq!(delete("?force=true").await?.status(), reqwest::StatusCode::NOT_FOUND);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn empty_device_bootstraps_from_the_role_default() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    server.upload("1.0.0", artifact("base")).await?;
+    server.upload("1.1.0", artifact("kiosk")).await?;
+    let set_bootstrap = |role: &str, version: &str| {
+        server
+            .http
+            .put(format!("{}/api/bootstrap/{}", server.url, role))
+            .json(&serde_json::json!({ "version": version, "set_by": "ops" }))
+            .send()
+    };
+    set_bootstrap("*", "1.0.0").await?.error_for_status()?;
+    set_bootstrap("kiosk", "1.1.0").await?.error_for_status()?;
+    let response = set_bootstrap("kiosk", "9.9.9").await?;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    // 역할 지정이 있으면 그 버전, 첫 재시작 전에 bootstrap 명령 실행
+    let kiosk = server
+        .register_with("e2e-bootstrap-kiosk", |config| {
+            config.role = Some("kiosk".to_string());
+            config.bootstrap_command = Some("echo seeded > seed.txt".to_string());
+        })
+        .await?;
+    kiosk.daemon.poll_once().await;
+
+    assert_eq!(kiosk.read("app.txt").as_deref(), Some("kiosk"));
+    assert_eq!(kiosk.read("seed.txt").as_deref().map(str::trim), Some("seeded"));
+    assert!(kiosk.backups().is_empty(), "nothing to back up on a bootstrap install");
+    let logs = server.update_logs(&kiosk).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].to_version, "1.1.0");
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert_eq!(logs[0].initiated_by.as_deref(), Some("bootstrap"));
+    assert!(logs[0].bootstrap);
+
+    // 설치된 뒤에는 다시 배포하지 않음, 다음 업데이트는 백업하고 bootstrap 명령은 실행하지 않음
+    kiosk.daemon.poll_once().await;
+    assert_eq!(server.update_logs(&kiosk).await?.len(), 1);
+    fs::remove_file(kiosk.service_dir.join("seed.txt"))?;
+    server.install(&kiosk, "1.2.0", artifact("kiosk2")).await?;
+    let logs = server.update_logs(&kiosk).await?;
+    assert_eq!(logs[1].status, "completed", "{:?}", logs[1].error_message);
+    assert!(!logs[1].bootstrap);
+    assert_eq!(kiosk.backups().len(), 1);
+    assert!(kiosk.read("seed.txt").is_none());
+
+    // 역할이 없으면 "*"
+    let plain = server.register("e2e-bootstrap-plain").await?;
+    plain.daemon.poll_once().await;
+    assert_eq!(plain.read("app.txt").as_deref(), Some("base"));
+
+    // 해제하면 새 장비는 배포를 기다림
+    server
+        .http
+        .delete(format!("{}/api/bootstrap/*", server.url))
+        .send()
+        .await?
+        .error_for_status()?;
+    let listed: Vec<serde_json::Value> = server
+        .http
+        .get(format!("{}/api/bootstrap", server.url))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["role"], "kiosk");
+    let idle = server.register("e2e-bootstrap-idle").await?;
+    idle.daemon.poll_once().await;
+    assert!(server.update_logs(&idle).await?.is_empty());
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn poll_hints_slow_the_fleet_and_show_in_interval_metrics() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let first = server.register("e2e-poll-hint-a").await?;
+    let second = server.register("e2e-poll-hint-b").await?;
+    let checkin = |client: &TestClient, waited: u64| {
+        server
+            .http
+            .post(format!("{}/api/checkin", server.url))
+            .header("X-API-Key", &client.api_key)
+            .json(&serde_json::json!({
+                "current_version": null,
+                "status": "online",
+                "poll_interval_secs": waited,
+            }))
+            .send()
+    };
+
+    // 힌트가 없으면 응답에 next_poll_secs 없음
+    let response: serde_json::Value = checkin(&first, 30).await?.error_for_status()?.json().await?;
+    assert!(response.get("next_poll_secs").is_none(), "{}", response);
+
+    let set_hint = |secs: i64| {
+        server
+            .http
+            .put(format!("{}/api/settings/poll-hint", server.url))
+            .json(&serde_json::json!({
+                "next_poll_secs": secs,
+                "duration_secs": 3600,
+                "reason": "db failover",
+                "set_by": "ops",
+            }))
+            .send()
+    };
+    assert_eq!(set_hint(0).await?.status(), reqwest::StatusCode::BAD_REQUEST);
+    let hint: serde_json::Value = set_hint(600).await?.error_for_status()?.json().await?;
+    assert_eq!(hint["next_poll_secs"], 600);
+    assert_eq!(hint["reason"], "db failover");
+    let settings: serde_json::Value = server
+        .http
+        .get(format!("{}/api/settings", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(settings["poll_hint"]["next_poll_secs"], 600);
+    assert_eq!(settings["checkin_shed"]["max_in_flight"], 0);
+
+    // 모든 체크인 응답에 힌트, 데몬도 그대로 체크인
+    let response: serde_json::Value = checkin(&second, 600).await?.error_for_status()?.json().await?;
+    assert_eq!(response["next_poll_secs"], 600);
+    second.daemon.poll_once().await;
+
+    // 보고된 대기 시간 분포: 30초 1대, 600초 1대
+    let metrics: serde_json::Value = server
+        .http
+        .get(format!("{}/api/metrics/poll-intervals?window=1h", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(metrics["clients"], 2, "{}", metrics);
+    assert_eq!(metrics["reporting"], 2, "{}", metrics);
+    assert_eq!(metrics["max_secs"], 600);
+    assert_eq!(metrics["next_poll_secs"], 600);
+    let count = |le: serde_json::Value| {
+        metrics["buckets"]
+            .as_array()
+            .and_then(|buckets| buckets.iter().find(|b| b["le_secs"] == le))
+            .map(|b| b["count"].clone())
+    };
+    assert_eq!(count(serde_json::json!(30)), Some(serde_json::json!(1)));
+    assert_eq!(count(serde_json::json!(600)), Some(serde_json::json!(1)));
+    assert_eq!(count(serde_json::Value::Null), Some(serde_json::json!(0)));
+
+    // 해제하면 다음 체크인부터 힌트 없음
+    let clear = || {
+        server
+            .http
+            .delete(format!("{}/api/settings/poll-hint", server.url))
+            .send()
+    };
+    clear().await?.error_for_status()?;
+    assert_eq!(clear().await?.status(), reqwest::StatusCode::NOT_FOUND);
+    let response: serde_json::Value = checkin(&first, 600).await?.error_for_status()?.json().await?;
+    assert!(response.get("next_poll_secs").is_none(), "{}", response);
+
+    // 과부하 응답의 Retry-After는 재시도 없이 ServerBusy로 전달
+    let busy = TcpListener::bind("127.0.0.1:0").await?;
+    let busy_url = format!("http://{}", busy.local_addr()?);
+    let requests = Arc::new(Mutex::new(0));
+    let seen = requests.clone();
+    let busy_server = tokio::spawn(async move {
+        while let Ok((mut stream, _)) = busy.accept().await {
+            *seen.lock().unwrap() += 1;
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await;
+            let _ = stream
+                .write_all(b"HTTP/1.1 503 Service Unavailable\r\nRetry-After: 120\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await;
+        }
+    });
+    let api = dm_client::api::DmApiClient::new(&busy_url, "key");
+    let err = api
+        .checkin(dm_client::api::CheckinRequest {
+            status: "online".to_string(),
+            ..Default::default()
+        })
+        .await
+        .expect_err("busy server must reject the checkin");
+    let busy_err = err
+        .downcast_ref::<dm_client::api::ServerBusy>()
+        .unwrap_or_else(|| panic!("expected ServerBusy, got {:#}", err));
+    assert_eq!(busy_err.retry_after, Duration::from_secs(120));
+    assert_eq!(*requests.lock().unwrap(), 1, "Retry-After beyond the retry budget is not retried");
+    busy_server.abort();
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn bulk_deploy_queues_matching_clients_and_lists_skips() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let first = server.register("e2e-bulk-a").await?;
+    let second = server.register("e2e-bulk-b").await?;
+    let current = server.register("e2e-bulk-current").await?;
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.upload("1.1.0", artifact("v2")).await?;
+    server.deploy(&current, "1.0.0").await?;
+    current.daemon.poll_once().await;
+
+    let bulk = |body: serde_json::Value| {
+        server
+            .http
+            .post(format!("{}/api/deploy", server.url))
+            .json(&body)
+            .send()
+    };
+    let names = |list: &serde_json::Value| {
+        let mut names: Vec<String> = list
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["client_name"].as_str().unwrap_or("?").to_string())
+            .collect();
+        names.sort();
+        names
+    };
+
+    // 대상 조건 없음 / 없는 버전
+    assert_eq!(
+        bulk(serde_json::json!({ "version": "1.0.0" })).await?.status(),
+        reqwest::StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        bulk(serde_json::json!({ "version": "9.9.9", "all": true })).await?.status(),
+        reqwest::StatusCode::NOT_FOUND
+    );
+
+    // 전체 배포: 이미 1.0.0인 클라이언트는 건너뜀
+    let result: serde_json::Value = bulk(serde_json::json!({ "version": "1.0.0", "all": true, "initiated_by": "alice" }))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(result["target_version"], "1.0.0");
+    assert_eq!(names(&result["queued"]), vec!["e2e-bulk-a", "e2e-bulk-b"]);
+    assert_eq!(names(&result["skipped"]), vec!["e2e-bulk-current"]);
+    assert_eq!(result["skipped"][0]["reason"], "Already on 1.0.0");
+
+    // 같은 요청을 다시 보내면 대기 중인 배포는 중복으로 건너뜀
+    let again: serde_json::Value = bulk(serde_json::json!({ "version": "1.0.0", "all": true }))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert!(again["queued"].as_array().unwrap().is_empty(), "{}", again);
+    assert_eq!(again["skipped"].as_array().unwrap().len(), 3, "{}", again);
+
+    for client in [&first, &second] {
+        client.daemon.poll_once().await;
+        assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+        let logs = server.update_logs(client).await?;
+        assert_eq!(logs[0].initiated_by.as_deref(), Some("alice"));
+    }
+
+    // ID 목록: 없는 ID는 건너뛴 목록에
+    let missing = Uuid::new_v4();
+    let result: serde_json::Value = bulk(serde_json::json!({ "version": "1.1.0", "client_ids": [first.id, missing] }))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(names(&result["queued"]), vec!["e2e-bulk-a"]);
+    assert_eq!(result["skipped"][0]["client_id"], missing.to_string());
+    assert_eq!(result["skipped"][0]["reason"], "Client not found");
+
+    // 비활성 버전은 거부
+    server.deactivate("1.1.0").await?;
+    assert_eq!(
+        bulk(serde_json::json!({ "version": "1.1.0", "client_ids": [second.id] })).await?.status(),
+        reqwest::StatusCode::CONFLICT
+    );
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn repeated_checkins_reuse_the_pending_update_log() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-pending-log").await?;
+    server.publish(&client, "1.0.0", artifact("v1")).await?;
+
+    let checkin = |status: &'static str| {
+        server
+            .http
+            .post(format!("{}/api/checkin", server.url))
+            .header("X-API-Key", &client.api_key)
+            .json(&serde_json::json!({ "current_version": null, "status": status }))
+            .send()
+    };
+    // 다운로드가 길어져 재시작한 클라이언트처럼 같은 타겟을 여러 번 받아도 로그는 하나
+    for status in ["online", "online", "updating", "online"] {
+        let response: serde_json::Value = checkin(status).await?.error_for_status()?.json().await?;
+        assert_eq!(response["action"], "update");
+    }
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "pending");
+
+    // 실패 보고는 그 로그를 닫고, 다음 시도부터 새 로그
+    server
+        .http
+        .post(format!("{}/api/update-result", server.url))
+        .header("X-API-Key", &client.api_key)
+        .json(&serde_json::json!({ "version": "1.0.0", "success": false, "error_message": "disk full" }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "failed");
+
+    checkin("online").await?.error_for_status()?;
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 2, "{:?}", logs);
+    assert_eq!(logs[1].status, "completed", "{:?}", logs[1].error_message);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn update_results_close_or_create_their_log_once() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-result-log").await?;
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.upload("1.1.0", artifact("v2")).await?;
+
+    let report = |key: &'static str, body: serde_json::Value| {
+        server
+            .http
+            .post(format!("{}/api/update-result", server.url))
+            .header("X-API-Key", &client.api_key)
+            .header("Idempotency-Key", key)
+            .json(&body)
+            .send()
+    };
+    let completed = |version: &'static str| {
+        sqlx::query_scalar::<_, bool>(
+            "SELECT completed_at IS NOT NULL FROM update_logs WHERE client_id = $1 AND to_version = $2",
+        )
+        .bind(client.id)
+        .bind(version)
+        .fetch_one(&server.pool)
+    };
+
+    // 서버가 내려준 적 없는 설치 (USB/apply): 완료된 로그를 새로 만들고, 재전송은 무시
+    let success = serde_json::json!({ "version": "1.0.0", "success": true });
+    report("usb-1", success.clone()).await?.error_for_status()?;
+    let again: serde_json::Value = report("usb-1", success).await?.error_for_status()?.json().await?;
+    assert_eq!(again["message"], "Update result already recorded");
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].to_version, "1.0.0");
+    assert_eq!(logs[0].status, "completed");
+    assert!(completed("1.0.0").await?);
+    assert_eq!(server.client_versions(&client).await?.0.as_deref(), Some("1.0.0"));
+
+    // 체크인에서 만든 로그는 실패 보고가 닫음 (메시지와 완료 시각 포함)
+    server.deploy(&client, "1.1.0").await?;
+    server
+        .http
+        .post(format!("{}/api/checkin", server.url))
+        .header("X-API-Key", &client.api_key)
+        .json(&serde_json::json!({ "current_version": "1.0.0", "status": "online" }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let failure = serde_json::json!({ "version": "1.1.0", "success": false, "error_message": "disk full" });
+    report("fail-1", failure.clone()).await?.error_for_status()?;
+    report("fail-1", failure).await?.error_for_status()?;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 2, "{:?}", logs);
+    assert_eq!(logs[1].status, "failed");
+    assert_eq!(logs[1].error_message.as_deref(), Some("disk full"));
+    assert!(completed("1.1.0").await?);
+
+    // 재시도 성공도 새 로그로 남음
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 3, "{:?}", logs);
+    assert_eq!(logs[2].status, "completed", "{:?}", logs[2].error_message);
+
+    server.stop().await
+}
+
+/// 같은 Idempotency-Key로 동시에 재전송된 결과 보고는 하나만 기록
+#[tokio::test]
+async fn concurrent_result_resends_are_recorded_once() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-result-race").await?;
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.upload("1.1.0", artifact("v2")).await?;
+
+    let report = |key: String, body: serde_json::Value| {
+        let request = server
+            .http
+            .post(format!("{}/api/update-result", server.url))
+            .header("X-API-Key", &client.api_key)
+            .header("Idempotency-Key", key)
+            .json(&body);
+        async move { anyhow::Ok(request.send().await?.error_for_status()?) }
+    };
+    let rows = |status: &'static str| {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM update_logs WHERE client_id = $1 AND status = $2")
+            .bind(client.id)
+            .bind(status)
+            .fetch_one(&server.pool)
+    };
+
+    // 진행 중인 로그가 없는 보고 (USB/apply): 로그를 하나만 새로 만듦
+    let success = serde_json::json!({ "version": "1.0.0", "success": true });
+    for round in 0..5 {
+        let key = format!("usb-{}", round);
+        let (first, second) = tokio::join!(report(key.clone(), success.clone()), report(key, success.clone()));
+        first?;
+        second?;
+        assert_eq!(rows("completed").await?, round + 1);
+    }
+
+    // 체크인에서 만든 로그: 한 보고만 닫고 실패를 기록
+    server.deploy(&client, "1.1.0").await?;
+    server
+        .http
+        .post(format!("{}/api/checkin", server.url))
+        .header("X-API-Key", &client.api_key)
+        .json(&serde_json::json!({ "current_version": "1.0.0", "status": "online" }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let failure = serde_json::json!({ "version": "1.1.0", "success": false, "error_message": "disk full" });
+    let (first, second) = tokio::join!(
+        report("fail-1".to_string(), failure.clone()),
+        report("fail-1".to_string(), failure)
+    );
+    let messages: Vec<serde_json::Value> = vec![first?.json().await?, second?.json().await?];
+    assert_eq!(
+        messages.iter().filter(|m| m["message"] == "Update result already recorded").count(),
+        1,
+        "{:?}",
+        messages
+    );
+    assert_eq!(rows("failed").await?, 1);
+    assert_eq!(rows("completed").await?, 5);
+
+    server.stop().await
+}
+
+/// 체크인이 연속으로 실패하면 간격을 두 배씩 늘리되 5분에서 멈추고, 성공하면 원래 간격으로 돌아감
+///
+/// 대기 시간에는 ±DM_POLL_JITTER_PCT% 편차가 붙지만 서버가 요청한 대기 시간보다 짧아지지는 않는다.
+#[tokio::test]
+async fn checkin_failures_back_off_to_five_minutes_and_waits_are_jittered() -> Result<()> {
+    /// 앞의 `failures`개 요청은 500, 그 뒤로는 next_poll_secs 힌트가 있는 정상 응답
+    async fn respond(mut stream: TcpStream, failed: bool) {
+        // 본문까지 읽은 뒤 응답해야 클라이언트가 연결 리셋을 보지 않음
+        let mut request = Vec::new();
+        let mut buf = [0u8; 8192];
+        loop {
+            let Ok(n) = stream.read(&mut buf).await else { return };
+            if n == 0 {
+                return;
+            }
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text[..end]
+                    .lines()
+                    .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if request.len() >= end + 4 + length {
+                    break;
+                }
+            }
+        }
+        let response = if failed {
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        } else {
+            let body = r#"{"action":"none","next_poll_secs":100}"#;
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        };
+        let _ = stream.write_all(response.as_bytes()).await;
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let failures = 4;
+    let server = tokio::spawn(async move {
+        let mut served = 0;
+        while let Ok((stream, _)) = listener.accept().await {
+            served += 1;
+            respond(stream, served <= failures).await;
+        }
+    });
+
+    let dir = TempDir::new()?;
+    let mut config = client_config(&url, "key", dir.path());
+    config.poll_interval_secs = 30;
+    config.http_retries = 0;
+    let daemon = PollingDaemon::new(config.clone());
+    assert_eq!(daemon.next_poll_wait(), Duration::from_secs(30));
+    for expected in [60, 120, 240, 300] {
+        daemon.poll_once().await;
+        assert_eq!(daemon.next_poll_wait(), Duration::from_secs(expected));
+    }
+    // 성공하면 서버 힌트, 힌트를 한 번 기다린 뒤에는 원래 간격
+    daemon.poll_once().await;
+    assert_eq!(daemon.next_poll_wait(), Duration::from_secs(100));
+    server.abort();
+
+    let mut jittered = config.clone();
+    jittered.poll_jitter_pct = 20;
+    let daemon = PollingDaemon::new(jittered);
+    let waits: Vec<Duration> = (0..200).map(|_| daemon.next_poll_wait()).collect();
+    assert!(waits.iter().all(|w| (24.0..=36.0).contains(&w.as_secs_f64())), "{:?}", waits);
+    assert!(waits.iter().any(|w| w.as_secs_f64() < 27.0), "{:?}", waits);
+    assert!(waits.iter().any(|w| w.as_secs_f64() > 33.0), "{:?}", waits);
+    Ok(())
+}
+
+/// 업데이트가 끝나면 폴링 간격을 기다리지 않고 바로 한 번 더 체크인
+#[tokio::test]
+async fn daemon_checks_in_again_right_after_an_update() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server
+        .register_with("e2e-repoll", |config| config.poll_interval_secs = 3600)
+        .await?;
+    server.publish(&client, "1.0.0", artifact("v1")).await?;
+
+    // 완료 보고 뒤에 체크인이 있어야 last_seen이 완료 시각보다 늦음
+    let checked_in_after_update = async {
+        loop {
+            let row = sqlx::query(
+                "SELECT u.status, c.last_seen > u.completed_at AS after FROM clients c
+                 JOIN update_logs u ON u.client_id = c.id WHERE c.id = $1",
+            )
+            .bind(client.id)
+            .fetch_optional(&server.pool)
+            .await?;
+            if let Some(row) = row {
+                if row.get::<String, _>("status") == "completed" && row.get::<Option<bool>, _>("after") == Some(true) {
+                    return Ok::<_, anyhow::Error>(());
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    };
+    tokio::select! {
+        result = client.daemon.run() => anyhow::bail!("daemon stopped: {:?}", result),
+        waited = tokio::time::timeout(Duration::from_secs(30), checked_in_after_update) => {
+            waited.context("no checkin after the update within 30s")??
+        }
+    }
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+
+    server.stop().await
+}
+
+/// 재시작한 데몬(새 인스턴스가 처음 체크인)은 다중 에이전트가 아니고,
+/// 이전 인스턴스가 그 뒤에 다시 체크인하면 다중 에이전트로 감지
+#[tokio::test]
+async fn daemon_restarts_are_not_reported_as_multiple_agents() -> Result<()> {
+    use sqlx::types::chrono::Utc;
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-instance-restart").await?;
+    let checkin = |instance_id: &'static str, started_at: String| {
+        server
+            .http
+            .post(format!("{}/api/checkin", server.url))
+            .header("X-API-Key", &client.api_key)
+            .json(&serde_json::json!({
+                "status": "online",
+                "instance_id": instance_id,
+                "instance_started_at": started_at,
+            }))
+            .send()
+    };
+    let flagged = || async {
+        let row = sqlx::query("SELECT multiple_agents_at IS NOT NULL FROM clients WHERE id = $1")
+            .bind(client.id)
+            .fetch_one(&server.pool)
+            .await?;
+        Ok::<bool, anyhow::Error>(row.get(0))
+    };
+
+    let old_started = (Utc::now() - Duration::from_secs(3600)).to_rfc3339();
+    let response: serde_json::Value = checkin("old", old_started.clone()).await?.error_for_status()?.json().await?;
+    assert!(response["warning"].is_null(), "{}", response);
+
+    // 재시작: 새 인스턴스가 처음 체크인
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let new_started = Utc::now().to_rfc3339();
+    let response: serde_json::Value = checkin("new", new_started).await?.error_for_status()?.json().await?;
+    assert!(response["warning"].is_null(), "{}", response);
+    assert!(!flagged().await?);
+
+    // 이전 인스턴스가 계속 살아 있으면 경합
+    let response: serde_json::Value = checkin("old", old_started).await?.error_for_status()?.json().await?;
+    let warning = response["warning"].as_str().unwrap_or_default();
+    assert!(warning.starts_with("multiple_agents") && warning.contains("older"), "{}", response);
+    assert!(flagged().await?);
+
+    server.stop().await
+}
+
+/// 장비 시계가 서버보다 빨라도 두 데몬이 번갈아 체크인하면 다중 에이전트로 감지하고,
+/// 느린 시계로 시작한 재시작은 경고하지 않음
+#[tokio::test]
+async fn multiple_agents_are_detected_with_a_skewed_device_clock() -> Result<()> {
+    use sqlx::types::chrono::Utc;
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-instance-skew").await?;
+    let checkin = |instance_id: &'static str, started_at: String| {
+        server
+            .http
+            .post(format!("{}/api/checkin", server.url))
+            .header("X-API-Key", &client.api_key)
+            .json(&serde_json::json!({
+                "status": "online",
+                "instance_id": instance_id,
+                "instance_started_at": started_at,
+            }))
+            .send()
+    };
+    let warning = |response: serde_json::Value| response["warning"].as_str().map(str::to_string);
+
+    // 장비 시계가 1시간 빠름: 두 인스턴스 모두 서버의 마지막 체크인보다 "나중에" 시작한 것처럼 보임
+    let ahead = Utc::now() + Duration::from_secs(3600);
+    let first = ahead.to_rfc3339();
+    let second = (ahead + Duration::from_secs(1)).to_rfc3339();
+    assert_eq!(warning(checkin("tmux", first.clone()).await?.error_for_status()?.json().await?), None);
+    assert_eq!(warning(checkin("systemd", second.clone()).await?.error_for_status()?.json().await?), None);
+    let response: serde_json::Value = checkin("tmux", first).await?.error_for_status()?.json().await?;
+    let flagged = warning(response).context("older instance not warned")?;
+    assert!(flagged.starts_with("multiple_agents") && flagged.contains("older"), "{}", flagged);
+    let response: serde_json::Value = checkin("systemd", second).await?.error_for_status()?.json().await?;
+    let flagged = warning(response).context("newer instance not warned")?;
+    assert!(flagged.contains("newest"), "{}", flagged);
+
+    // 장비 시계가 1시간 느림: 재시작한 인스턴스가 이전 체크인보다 "먼저" 시작한 것처럼 보여도 재시작
+    let other = server.register("e2e-instance-skew-behind").await?;
+    let behind = Utc::now() - Duration::from_secs(3600);
+    for (instance_id, started_at) in [("boot-1", behind), ("boot-2", behind + Duration::from_secs(30))] {
+        let response: serde_json::Value = server
+            .http
+            .post(format!("{}/api/checkin", server.url))
+            .header("X-API-Key", &other.api_key)
+            .json(&serde_json::json!({
+                "status": "online",
+                "instance_id": instance_id,
+                "instance_started_at": started_at.to_rfc3339(),
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        assert!(response["warning"].is_null(), "{}", response);
+    }
+    let row = sqlx::query("SELECT multiple_agents_at IS NULL FROM clients WHERE id = $1")
+        .bind(other.id)
+        .fetch_one(&server.pool)
+        .await?;
+    assert!(row.get::<bool, _>(0));
+
+    server.stop().await
+}
+
+/// 설치 직후 결과 보고가 실패하면 저장해 두고, 같은 업데이트를 다시 설치하지 않고 다음 폴링에서 재전송
+#[tokio::test]
+async fn unreported_results_are_saved_and_retried() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server
+        .register_with("e2e-pending-result", |config| config.http_retries = 0)
+        .await?;
+    server.install(&client, "1.0.0", artifact("v1")).await?;
+
+    // 재설치 요청은 완료 보고를 받을 때까지 체크인마다 같은 업데이트를 다시 내림
+    server
+        .http
+        .post(format!("{}/api/clients/{}/deploy", server.url, client.id))
+        .json(&serde_json::json!({ "version": "1.0.0", "force_reinstall": true }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    // 업데이트 로그를 닫지 못하게 해 결과 보고만 500으로 실패
+    server
+        .pool
+        .execute(
+            "CREATE FUNCTION e2e_block_report() RETURNS trigger AS $$
+             BEGIN RAISE EXCEPTION 'report blocked'; END $$ LANGUAGE plpgsql;
+             CREATE TRIGGER e2e_block_report BEFORE UPDATE ON update_logs
+             FOR EACH ROW EXECUTE FUNCTION e2e_block_report();",
+        )
+        .await?;
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    let pending = dm_client::state::PendingResult::load(&client.config.service_dir).context("result saved")?;
+    assert!(pending.result.success);
+    assert_eq!(pending.result.version, "1.0.0");
+
+    // 서버가 같은 업데이트를 다시 내려도 설치하지 않음 (설치하면 서비스 디렉토리가 바뀌어 표시 파일이 사라짐)
+    fs::write(client.service_dir.join("marker.txt"), "kept")?;
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("marker.txt").as_deref(), Some("kept"));
+    let still = dm_client::state::PendingResult::load(&client.config.service_dir).context("result kept")?;
+    assert_eq!(still.idempotency_key, pending.idempotency_key);
+
+    // 서버가 회복되면 체크인 전에 재전송하고 파일 삭제
+    server
+        .pool
+        .execute("DROP TRIGGER e2e_block_report ON update_logs")
+        .await?;
+    client.daemon.poll_once().await;
+    assert!(dm_client::state::PendingResult::load(&client.config.service_dir).is_none());
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 2, "{:?}", logs);
+    assert!(logs.iter().all(|log| log.status == "completed"), "{:?}", logs);
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("marker.txt").as_deref(), Some("kept"));
+
+    server.stop().await
+}
@@ -0,0 +1,2135 @@
+//! 클라이언트 업데이트 흐름 (다운로드, 검증, 백업, 설치, 재시작, 헬스 체크, 롤백)
+
+mod common;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use dm_client::backup;
+use dm_client::config::Config as ClientConfig;
+use dm_client::state::LocalState;
+use dm_client::updater::Updater;
+
+use common::*;
+
+/// 최소한의 HTTP/1.1 아티팩트 미러 (Range 처리 방식을 테스트마다 바꿈)
+struct FakeMirror {
+    url: String,
+    /// 받은 요청의 Range 헤더 (순서대로, 없으면 None)
+    ranges: Arc<Mutex<Vec<Option<String>>>>,
+    server: JoinHandle<()>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MirrorMode {
+    /// Range 헤더를 무시하고 항상 200으로 전체 전송
+    IgnoreRanges,
+    /// Range를 지원하지만 첫 조각 요청은 절반만 보내고 연결을 끊음
+    CutFirstChunk,
+    /// 200으로 절반만 보내고 연결을 끊지 않고 멈춤
+    Stall,
+    /// Range를 지원하지만 Range 없는 첫 요청은 절반만 보내고 연결을 끊음
+    CutFirstStream,
+}
+
+impl FakeMirror {
+    async fn start(body: Vec<u8>, mode: MirrorMode) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let body = Arc::new(body);
+        let cut = Arc::new(AtomicBool::new(matches!(
+            mode,
+            MirrorMode::CutFirstChunk | MirrorMode::CutFirstStream
+        )));
+
+        let seen = ranges.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (body, seen, cut) = (body.clone(), seen.clone(), cut.clone());
+                tokio::spawn(async move {
+                    let _ = serve_mirror(stream, &body, mode, &seen, &cut).await;
+                });
+            }
+        });
+        Ok(Self { url, ranges, server })
+    }
+
+    /// 지금까지 받은 Range 헤더
+    fn ranges(&self) -> Vec<Option<String>> {
+        self.ranges.lock().unwrap().clone()
+    }
+}
+
+impl Drop for FakeMirror {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// 미러 요청 하나 처리 (요청마다 연결을 닫음)
+async fn serve_mirror(
+    mut stream: TcpStream,
+    body: &[u8],
+    mode: MirrorMode,
+    seen: &Mutex<Vec<Option<String>>>,
+    cut: &AtomicBool,
+) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    let range = String::from_utf8_lossy(&request).lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.eq_ignore_ascii_case("range").then(|| value.trim().to_string())
+    });
+    seen.lock().unwrap().push(range.clone());
+
+    let size = body.len();
+    let spec = range.as_deref().and_then(|r| r.strip_prefix("bytes="));
+    let (start, end) = match (mode, spec.and_then(|s| s.split_once('-'))) {
+        (MirrorMode::Stall, _) => {
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", size);
+            stream.write_all(head.as_bytes()).await?;
+            stream.write_all(&body[..size / 2]).await?;
+            stream.flush().await?;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            return Ok(());
+        }
+        (MirrorMode::IgnoreRanges, _) | (_, None) => {
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                size
+            );
+            stream.write_all(head.as_bytes()).await?;
+            if mode == MirrorMode::CutFirstStream && cut.swap(false, Ordering::SeqCst) {
+                stream.write_all(&body[..size / 2]).await?;
+                stream.flush().await?;
+                return Ok(());
+            }
+            stream.write_all(body).await?;
+            return stream.shutdown().await;
+        }
+        (_, Some(("", suffix))) => (size - suffix.parse::<usize>().unwrap(), size - 1),
+        (_, Some((start, end))) => (
+            start.parse().unwrap(),
+            end.parse::<usize>().map_or(size - 1, |e| e.min(size - 1)),
+        ),
+    };
+
+    let part = &body[start..=end];
+    let head = format!(
+        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        start,
+        end,
+        size,
+        part.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    // 첫 조각 요청은 절반만 보내고 끊음 (크기 확인용 1바이트 요청은 제외)
+    if mode == MirrorMode::CutFirstChunk && part.len() > 1 && cut.swap(false, Ordering::SeqCst) {
+        stream.write_all(&part[..part.len() / 2]).await?;
+        stream.flush().await?;
+        return Ok(());
+    }
+    stream.write_all(part).await?;
+    stream.shutdown().await
+}
+
+/// 4개 연결로 분할 다운로드 (크기 기준 없음)
+fn parallel_downloads(config: &mut ClientConfig) {
+    config.download_connections = 4;
+    config.download_parallel_min_bytes = 0;
+}
+
+#[tokio::test]
+async fn update_round_trip() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-round-trip").await?;
+
+    // 1.0.0 업로드 → 배포 명령
+    let v1 = artifact("v1");
+    server.publish(&client, "1.0.0", v1.clone()).await?;
+    assert_eq!(server.client_versions(&client).await?.1.as_deref(), Some("1.0.0"));
+
+    // 체크인 → 다운로드 → 설치 → 결과 보고
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].to_version, "1.0.0");
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert_eq!(logs[0].verified_checksum.as_deref(), Some(sha256(&v1).as_str()));
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.0.0"));
+
+    let downloads: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM artifact_downloads WHERE version = '1.0.0' AND client_id = $1",
+    )
+    .bind(client.id)
+    .fetch_one(&server.pool)
+    .await?;
+    assert_eq!(downloads, 1);
+
+    // 다음 체크인: 할 일 없음, 서버에 새 버전 반영
+    client.daemon.poll_once().await;
+    assert_eq!(server.update_logs(&client).await?.len(), 1);
+    assert_eq!(server.client_versions(&client).await?, (Some("1.0.0".to_string()), None));
+
+    // 1.1.0으로 업데이트: 이전 버전 백업 후 교체
+    server.install(&client, "1.1.0", artifact("v2")).await?;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 2, "{:?}", logs);
+    assert_eq!(logs[1].status, "completed", "{:?}", logs[1].error_message);
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.1.0"));
+    assert!(
+        fs::read_dir(&client.backup_dir)?.count() > 0,
+        "backup of 1.0.0 was not created"
+    );
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn bad_checksum_is_not_installed() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-bad-checksum").await?;
+
+    // 업로드 후 디스크의 아티팩트를 바꿔 다른 내용이 전송되도록 함
+    let stored = server.upload("1.0.0", artifact("v1")).await?;
+    fs::write(server.artifact_dir.join(&stored), artifact("tampered"))?;
+    server.deploy(&client, "1.0.0").await?;
+
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "failed");
+    let error = logs[0].error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("Checksum verification failed"), "{}", error);
+    assert_eq!(client.read("app.txt"), None);
+    assert_eq!(client.read(".dm-version"), None);
+
+    server.stop().await
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn backup_failure_names_every_unreadable_path() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-backup-failure").await?;
+
+    server.install(&client, "1.0.0", artifact("v1")).await?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    let backups_before = client.backups().len();
+
+    // 백업 중 복사할 수 없는 파일 두 개 (최상위, 하위 디렉토리)
+    client.plant_unreadable("broken.txt")?;
+    client.plant_unreadable("data/missing.db")?;
+
+    server.install(&client, "1.1.0", artifact("v2")).await?;
+
+    // 업데이트는 중단되고 실패한 경로가 모두 보고됨
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 2, "{:?}", logs);
+    assert_eq!(logs[1].status, "failed");
+    let error = logs[1].error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("Backup failed: could not copy 2 path(s)"), "{}", error);
+    assert!(error.contains("broken.txt"), "{}", error);
+    assert!(error.contains("data/missing.db"), "{}", error);
+
+    // 서비스는 그대로이고 불완전한 백업은 남지 않음
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.0.0"));
+    assert_eq!(client.backups().len(), backups_before);
+
+    server.stop().await
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn skipped_backup_installs_and_blocks_latest_rollback() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server
+        .register_with("e2e-no-backup", |config| config.backup_required = false)
+        .await?;
+
+    server.install(&client, "1.0.0", artifact("v1")).await?;
+    client.plant_unreadable("broken.txt")?;
+
+    server.install(&client, "1.1.0", artifact("v2")).await?;
+
+    // 읽을 수 없는 파일이 있어도 백업 없이 설치
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 2, "{:?}", logs);
+    assert_eq!(logs[1].status, "completed", "{:?}", logs[1].error_message);
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    assert!(client.backups().is_empty(), "{:?}", client.backups());
+
+    // 설치 상태에 백업 생략이 기록되고, 최신 백업 롤백은 이유와 함께 거부됨
+    let state = LocalState::load(&client.service_dir.to_string_lossy());
+    let skipped = state.no_backup.as_ref().context("no_backup not recorded")?;
+    assert_eq!(skipped.previous_version, "1.0.0");
+
+    let backups = client.backups();
+    let error = backup::rollback_target(&backups, None, state.no_backup.as_ref())
+        .expect_err("latest rollback must be refused")
+        .to_string();
+    assert!(error.contains("Cannot roll back to 1.0.0"), "{}", error);
+    assert!(error.contains("--no-backup"), "{}", error);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn parallel_download_reassembles_artifact() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register_with("e2e-parallel", parallel_downloads).await?;
+
+    let content = noise(600_001, "parallel");
+    let data = artifact_file("app.bin", &content);
+    let plan = client.parallel();
+    assert!(
+        data.len() as u64 > 4 * plan.chunk_size(data.len() as u64),
+        "artifact must span more chunks than connections"
+    );
+    server.publish(&client, "1.0.0", data.clone()).await?;
+
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert_eq!(logs[0].verified_checksum.as_deref(), Some(sha256(&data).as_str()));
+    assert!(client.read_bytes("app.bin") == Some(content), "reassembled content differs");
+
+    // 조각 요청은 한 번의 다운로드로 기록되고, 받은 파일과 기록은 정리됨
+    assert_eq!(server.download_count("1.0.0").await?, 1);
+    assert!(!plan.part_path.exists());
+    assert!(!plan.journal_path.exists());
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn parallel_download_resumes_from_journal() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register_with("e2e-parallel-resume", parallel_downloads).await?;
+
+    let content = noise(600_001, "resume");
+    let data = artifact_file("app.bin", &content);
+    server.upload("1.0.0", data.clone()).await?;
+
+    // 앞쪽 절반 조각을 받은 뒤 중단된 상태 재현 (나머지는 sparse 파일의 0)
+    let plan = client.parallel();
+    let mut journal = plan.prepare(&sha256(&data), data.len() as u64)?;
+    let half = journal.done.len() / 2;
+    assert!(half >= 2, "{} chunks", journal.done.len());
+    let mut part = fs::OpenOptions::new().write(true).open(&plan.part_path)?;
+    for index in 0..half {
+        let (start, end) = journal.range(index);
+        part.seek(SeekFrom::Start(start))?;
+        part.write_all(&data[start as usize..=end as usize])?;
+        journal.done[index] = true;
+    }
+    journal.save(&plan.journal_path)?;
+
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert!(client.read_bytes("app.bin") == Some(content), "resumed content differs");
+    // 첫 조각은 이미 받았으므로 서버에 다시 요청하지 않음
+    assert_eq!(server.download_count("1.0.0").await?, 0);
+    assert!(!plan.part_path.exists());
+    assert!(!plan.journal_path.exists());
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn corrupt_resumed_chunk_fails_checksum_then_downloads_fresh() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register_with("e2e-parallel-corrupt", parallel_downloads).await?;
+
+    let content = noise(600_001, "corrupt");
+    let data = artifact_file("app.bin", &content);
+    server.upload("1.0.0", data.clone()).await?;
+
+    // 받지 않은 조각이 완료로 기록된 상태 (기록을 믿고 다시 받지 않음 → 체크섬 불일치)
+    let plan = client.parallel();
+    let mut journal = plan.prepare(&sha256(&data), data.len() as u64)?;
+    journal.done[1] = true;
+    journal.save(&plan.journal_path)?;
+
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "failed");
+    let error = logs[0].error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("Checksum verification failed"), "{}", error);
+    assert_eq!(client.read(".dm-version"), None);
+    assert!(!plan.part_path.exists());
+    assert!(!plan.journal_path.exists());
+
+    // 잘못된 기록은 버려졌으므로 다음 시도는 처음부터 받음
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 2, "{:?}", logs);
+    assert_eq!(logs[1].status, "completed", "{:?}", logs[1].error_message);
+    assert!(client.read_bytes("app.bin") == Some(content), "downloaded content differs");
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn parallel_download_falls_back_when_range_is_ignored() -> Result<()> {
+    let content = noise(300_001, "fallback");
+    let data = artifact_file("app.bin", &content);
+    let mirror = FakeMirror::start(data.clone(), MirrorMode::IgnoreRanges).await?;
+    let mirrors = HashMap::from([("*".to_string(), vec![mirror.url.clone()])]);
+    let Some(server) = TestServer::start_with(|config| config.artifact_mirrors = mirrors).await? else {
+        return Ok(());
+    };
+    let client = server.register_with("e2e-parallel-fallback", parallel_downloads).await?;
+
+    // 서버의 파일을 지워 미러에서 받도록 함
+    let stored = server.upload("1.0.0", data.clone()).await?;
+    fs::remove_file(server.artifact_dir.join(&stored))?;
+    server.deploy(&client, "1.0.0").await?;
+
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert!(client.read_bytes("app.bin") == Some(content), "fallback content differs");
+
+    // 크기 확인 요청이 200으로 오면 Range 없이 한 번 더 받음
+    assert_eq!(mirror.ranges(), vec![Some("bytes=-1".to_string()), None]);
+    assert!(!client.parallel().journal_path.exists());
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn interrupted_chunk_is_retried_from_where_it_stopped() -> Result<()> {
+    let content = noise(600_001, "retry");
+    let data = artifact_file("app.bin", &content);
+    let mirror = FakeMirror::start(data.clone(), MirrorMode::CutFirstChunk).await?;
+    let mirrors = HashMap::from([("*".to_string(), vec![mirror.url.clone()])]);
+    let Some(server) = TestServer::start_with(|config| config.artifact_mirrors = mirrors).await? else {
+        return Ok(());
+    };
+    let client = server.register_with("e2e-parallel-retry", parallel_downloads).await?;
+
+    let stored = server.upload("1.0.0", data.clone()).await?;
+    fs::remove_file(server.artifact_dir.join(&stored))?;
+    server.deploy(&client, "1.0.0").await?;
+
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert!(client.read_bytes("app.bin") == Some(content), "retried content differs");
+
+    // 끊긴 조각만 받은 위치부터 다시 요청 (조각 경계가 아닌 시작 위치)
+    let chunk_size = client.parallel().chunk_size(data.len() as u64);
+    let resumed: Vec<u64> = mirror
+        .ranges()
+        .iter()
+        .flatten()
+        .filter_map(|r| r.strip_prefix("bytes=")?.split_once('-')?.0.parse().ok())
+        .filter(|start| start % chunk_size != 0)
+        .collect();
+    assert_eq!(resumed.len(), 1, "{:?}", mirror.ranges());
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried_with_backoff() -> Result<()> {
+    use dm_client::api::{CheckinRequest, DmApiClient};
+
+    /// 앞의 응답들을 차례로 보낸 뒤에는 정상 체크인 응답 (None이면 응답 없이 연결을 끊음)
+    async fn flaky(failures: Vec<Option<&'static str>>) -> Result<(String, Arc<Mutex<usize>>, JoinHandle<()>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let requests = Arc::new(Mutex::new(0));
+        let seen = requests.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let index = {
+                    let mut seen = seen.lock().unwrap();
+                    *seen += 1;
+                    *seen - 1
+                };
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let response = match failures.get(index) {
+                    Some(None) => continue,
+                    Some(Some(status)) => format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status),
+                    None => {
+                        let body = r#"{"action":"none"}"#;
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            body.len(),
+                            body
+                        )
+                    }
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        Ok((url, requests, server))
+    }
+    let checkin = |api: DmApiClient| async move {
+        api.checkin(CheckinRequest {
+            status: "online".to_string(),
+            ..Default::default()
+        })
+        .await
+    };
+    let dir = tempfile::tempdir()?;
+    let policy = |retries: u32| {
+        let mut config = client_config("http://unused", "key", dir.path());
+        config.http_retries = retries;
+        config.http_retry_base_ms = 10;
+        config.retry_policy()
+    };
+
+    // 끊긴 연결과 5xx 뒤에 성공하면 체크인은 성공
+    let (url, requests, server) = flaky(vec![None, Some("502 Bad Gateway"), Some("503 Service Unavailable")]).await?;
+    let response = checkin(DmApiClient::new(&url, "key").with_retry_policy(policy(3))).await?;
+    assert_eq!(response.action, "none");
+    assert_eq!(*requests.lock().unwrap(), 4);
+    server.abort();
+
+    // 재시도 횟수를 넘으면 실패
+    let (url, requests, server) = flaky(vec![Some("500 Internal Server Error"); 2]).await?;
+    let err = checkin(DmApiClient::new(&url, "key").with_retry_policy(policy(1)))
+        .await
+        .expect_err("retries are exhausted");
+    assert!(err.to_string().contains("500"), "{:#}", err);
+    assert_eq!(*requests.lock().unwrap(), 2);
+    server.abort();
+
+    // 4xx는 재시도하지 않음
+    for status in ["401 Unauthorized", "404 Not Found"] {
+        let (url, requests, server) = flaky(vec![Some(status); 4]).await?;
+        checkin(DmApiClient::new(&url, "key").with_retry_policy(policy(3)))
+            .await
+            .expect_err("4xx is not retried");
+        assert_eq!(*requests.lock().unwrap(), 1, "{}", status);
+        server.abort();
+    }
+
+    // 다운로드 도중 끊기면 받은 위치부터 이어 받음
+    let content = noise(300_001, "resume-stream");
+    let data = artifact_file("app.bin", &content);
+    let mirror = FakeMirror::start(data.clone(), MirrorMode::CutFirstStream).await?;
+    let mirrors = HashMap::from([("*".to_string(), vec![mirror.url.clone()])]);
+    let Some(server) = TestServer::start_with(|config| config.artifact_mirrors = mirrors).await? else {
+        return Ok(());
+    };
+    let client = server
+        .register_with("e2e-download-retry", |config| config.http_retry_base_ms = 10)
+        .await?;
+    let stored = server.upload("1.0.0", data.clone()).await?;
+    fs::remove_file(server.artifact_dir.join(&stored))?;
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert!(client.read_bytes("app.bin") == Some(content), "resumed content differs");
+    let ranges = mirror.ranges();
+    assert_eq!(ranges.len(), 2, "{:?}", ranges);
+    assert_eq!(ranges[0], None);
+    let resumed_at: u64 = ranges[1]
+        .as_deref()
+        .and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok())
+        .context("resume request without an open-ended range")?;
+    assert!(resumed_at > 0 && resumed_at < data.len() as u64, "{:?}", ranges);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn superseding_deploy_abandons_the_inflight_download() -> Result<()> {
+    let broken = artifact("broken");
+    let mirror = FakeMirror::start(broken.clone(), MirrorMode::Stall).await?;
+    let mirrors = HashMap::from([("*".to_string(), vec![mirror.url.clone()])]);
+    let Some(server) = TestServer::start_with(|config| config.artifact_mirrors = mirrors).await? else {
+        return Ok(());
+    };
+    let client = server
+        .register_with("e2e-supersede", |config| config.supersede_check_secs = 1)
+        .await?;
+
+    // 1.0.0은 멈추는 미러에서만 받을 수 있음
+    let stored = server.upload("1.0.0", broken).await?;
+    fs::remove_file(server.artifact_dir.join(&stored))?;
+    server.upload("1.0.1", artifact("fixed")).await?;
+    server.deploy(&client, "1.0.0").await?;
+
+    // 다운로드가 미러에서 멈춘 동안 수정 버전을 대체 배포
+    let redeploy = async {
+        while mirror.ranges().is_empty() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        server
+            .http
+            .post(format!("{}/api/clients/{}/deploy", server.url, client.id))
+            .json(&serde_json::json!({ "version": "1.0.1", "supersede": true }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<serde_json::Value>()
+            .await
+    };
+    let (_, redeployed) = tokio::time::timeout(
+        Duration::from_secs(30),
+        async { tokio::join!(client.daemon.poll_once(), redeploy) },
+    )
+    .await?;
+    assert_eq!(redeployed?["superseded_version"], "1.0.0");
+
+    // 같은 폴링에서 새 타겟 설치, 이전 시도는 실패가 아닌 취소로 남음
+    assert_eq!(client.read("app.txt").as_deref(), Some("fixed"));
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 2, "{:?}", logs);
+    assert_eq!(logs[0].to_version, "1.0.0");
+    assert_eq!(logs[0].status, "cancelled");
+    assert!(logs[0].error_message.as_deref().is_some_and(|m| m.contains("Superseded by deploy of 1.0.1")));
+    assert_eq!(logs[1].to_version, "1.0.1");
+    assert_eq!(logs[1].status, "completed", "{:?}", logs[1].error_message);
+
+    server.stop().await
+}
+
+/// 서버가 지정한 프로브를 모두 통과해야 설치 완료, 실패하면 어느 프로브인지 보고
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn failing_health_probe_is_named_in_failure_report() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-health-probes").await?;
+    let set_probes = |probes: serde_json::Value| {
+        server
+            .http
+            .put(format!("{}/api/clients/{}/config", server.url, client.id))
+            .json(&serde_json::json!({ "config": { "health_checks": probes } }))
+            .send()
+    };
+
+    // 실행할 수 없는 프로브는 저장 거부
+    let response = set_probes(serde_json::json!([
+        { "type": "process", "name": "node", "pidfile": "/run/node.pid" }
+    ]))
+    .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // 서버(http), 열린 포트(tcp), 아직 없는 하트비트 파일(file_age)
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let heartbeat_dir = tempfile::tempdir()?;
+    let heartbeat = heartbeat_dir.path().join("heartbeat");
+    set_probes(serde_json::json!([
+        { "type": "http", "url": format!("{}/health", server.url), "expected_status": 200 },
+        { "type": "tcp", "host": "127.0.0.1", "port": port.local_addr()?.port() },
+        { "type": "file_age", "path": heartbeat, "max_age_secs": 60, "timeout_secs": 2 }
+    ]))
+    .await?
+    .error_for_status()?;
+    client.daemon.poll_once().await;
+
+    server.install(&client, "1.0.0", artifact("first")).await?;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "failed");
+    let error = logs[0].error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("file_age probe") && error.contains("heartbeat"), "{}", error);
+    let reason: Option<String> =
+        sqlx::query_scalar("SELECT failure_reason FROM update_logs WHERE client_id = $1")
+            .bind(client.id)
+            .fetch_one(&server.pool)
+            .await?;
+    assert_eq!(reason.as_deref(), Some("health_check"));
+    assert_eq!(client.read("app.txt"), None);
+
+    // 하트비트가 갱신되면 모든 프로브 통과
+    fs::write(&heartbeat, b"alive")?;
+    server.install(&client, "1.0.1", artifact("second")).await?;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.last().map(|l| l.status.as_str()), Some("completed"), "{:?}", logs);
+    assert_eq!(client.read("app.txt").as_deref(), Some("second"));
+
+    server.stop().await
+}
+
+/// DM_HEALTH_CHECK_URL은 2xx가 될 때까지 간격을 두고 다시 시도하고, 명령과 함께 있으면 둘 다 통과해야 정상
+///
+/// 헬스 서버가 같은 단일 스레드 런타임에 있으므로 대기가 런타임을 막으면 응답하지 못한다.
+#[tokio::test]
+async fn health_check_url_is_retried_until_healthy() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let health_url = format!("http://{}/healthz", listener.local_addr()?);
+    let requests = Arc::new(Mutex::new(0));
+    let seen = requests.clone();
+    let health = tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let index = {
+                let mut seen = seen.lock().unwrap();
+                *seen += 1;
+                *seen
+            };
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                match stream.read(&mut buf).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            let status = if index <= 2 { "503 Service Unavailable" } else { "200 OK" };
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+    let client = server
+        .register_with("e2e-health-url", |config| {
+            config.health_check_url = Some(health_url.clone());
+            config.health_check_attempts = 3;
+            config.health_check_interval_secs = 1;
+            config.health_check_command = Some(format!("test ! -f {}/broken", config.service_dir));
+        })
+        .await?;
+
+    // 503 두 번 뒤 200: 세 번째 시도에서 통과
+    server.install(&client, "1.0.0", artifact("v1")).await?;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert_eq!(*requests.lock().unwrap(), 3);
+
+    // URL은 통과해도 명령이 실패하면 시도 횟수를 다 쓰고 롤백
+    server.install(&client, "1.0.1", artifact_files(&[("app.txt", b"v2"), ("broken", b"")])).await?;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs[1].status, "failed");
+    assert!(
+        logs[1].error_message.as_deref().is_some_and(|m| m.contains("Health check failed: command probe")),
+        "{:?}",
+        logs[1].error_message
+    );
+    assert_eq!(*requests.lock().unwrap(), 6);
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+
+    health.abort();
+    server.stop().await
+}
+
+/// 수동 롤백은 최신 백업을 복원하고 버전 파일을 맞춘 뒤 재시작과 헬스 체크까지 수행
+#[tokio::test]
+async fn manual_rollback_restores_the_latest_backup_and_checks_health() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let marks = tempfile::tempdir()?;
+    let restarts = marks.path().join("restarts");
+    let unhealthy = marks.path().join("unhealthy");
+    let client = server
+        .register_with("e2e-manual-rollback", |config| {
+            config.restart_command = format!("echo restart >> {}", restarts.display());
+            config.health_check_command = Some(format!("test ! -f {}", unhealthy.display()));
+        })
+        .await?;
+    let updater = Updater::new(client.config.clone());
+
+    // 백업이 없으면 대상이 없음
+    assert!(backup::rollback_target(&client.backups(), None, None).is_err());
+
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.upload("1.0.1", artifact("v2")).await?;
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+    server.deploy(&client, "1.0.1").await?;
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    fs::remove_file(&restarts)?;
+
+    let backups = client.backups();
+    let target = backup::rollback_target(&backups, None, None)?;
+    assert_eq!(target.version, "1.0.0");
+    updater.rollback_to(target, &updater.health_probes()?)?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.0.0"));
+    assert_eq!(fs::read_to_string(&restarts)?, "restart\n");
+
+    // 헬스 체크 실패는 롤백 실패로 보고 (복원한 트리는 그대로)
+    server.deploy(&client, "1.0.1").await?;
+    client.daemon.poll_once().await;
+    fs::write(&unhealthy, "")?;
+    let backups = client.backups();
+    let target = backup::rollback_target(&backups, None, None)?;
+    let error = updater
+        .rollback_to(target, &updater.health_probes()?)
+        .expect_err("unhealthy rollback must fail");
+    assert!(error.to_string().contains("Health check failed"), "{}", error);
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.0.0"));
+
+    server.stop().await
+}
+
+/// 새 백업을 만들면 DM_BACKUP_KEEP개만 남기고 오래된 백업부터 삭제 (방금 만든 백업과 복원 지점은 유지)
+#[tokio::test]
+async fn old_backups_are_pruned_after_a_new_backup() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-backup-keep").await?;
+    server.install(&client, "1.0.0", artifact("v1")).await?;
+
+    // 다섯 개의 가짜 백업 (이름 순서와 시각 순서가 다르도록 버전을 섞음)
+    let fake: Vec<String> = ["0.5.0", "0.1.0", "0.4.0", "0.2.0", "0.3.0"]
+        .iter()
+        .enumerate()
+        .map(|(i, version)| format!("backup_{}_2024050{}T120000Z", version, i + 1))
+        .collect();
+    for name in &fake {
+        fs::create_dir_all(client.backup_dir.join(name))?;
+        fs::write(client.backup_dir.join(name).join("app.txt"), "old")?;
+    }
+    fs::write(client.backup_dir.join(format!("{}.json", fake[0])), "{}")?;
+    // 백업이 아닌 디렉토리는 건드리지 않음
+    fs::create_dir_all(client.backup_dir.join("keep-me"))?;
+
+    let created = Updater::new(client.config.clone()).backup_current("1.0.0")?;
+    let mut remaining: Vec<String> = client.backups().into_iter().map(|b| b.name).collect();
+    remaining.sort();
+    let mut expected = vec![
+        Path::new(&created).file_name().context("backup name")?.to_string_lossy().into_owned(),
+        fake[3].clone(),
+        fake[4].clone(),
+    ];
+    expected.sort();
+    assert_eq!(remaining, expected);
+    assert!(!client.backup_dir.join(format!("{}.json", fake[0])).exists());
+    assert!(client.backup_dir.join("keep-me").exists());
+
+    server.stop().await
+}
+
+/// 멈춘 재시작 명령은 DM_COMMAND_TIMEOUT_SECS 뒤 종료하고 출력 끝부분과 함께 실패 보고 후 롤백
+#[tokio::test]
+async fn hung_restart_command_is_killed_and_reported_with_its_output() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let marks = tempfile::tempdir()?;
+    let hang = marks.path().join("hang");
+    let client = server
+        .register_with("e2e-hung-restart", |config| {
+            config.command_timeout_secs = 1;
+            config.restart_command = format!(
+                "if [ -f {} ]; then echo restarting; echo 'pm2: waiting for app' >&2; sleep 30; fi",
+                hang.display()
+            );
+        })
+        .await?;
+
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.upload("1.0.1", artifact("v2")).await?;
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+
+    fs::write(&hang, "")?;
+    server.deploy(&client, "1.0.1").await?;
+    let started = std::time::Instant::now();
+    client.daemon.poll_once().await;
+    assert!(started.elapsed() < Duration::from_secs(20), "{:?}", started.elapsed());
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs[1].status, "failed");
+    let error = logs[1].error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("timed out after 1s"), "{}", error);
+    assert!(error.contains("pm2: waiting for app") && error.contains("stdout:\nrestarting"), "{}", error);
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+
+    server.stop().await
+}
+
+/// 설치 후 재시작이나 헬스 체크에서 DM_UPDATE_TIMEOUT_SECS가 지나면 백업으로 롤백하고 단계와 함께 timed_out 보고
+#[tokio::test]
+async fn update_timeout_after_install_rolls_back() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    // 새 버전(v2)이 설치된 동안에만 느린 명령 (롤백한 v1에서는 바로 끝남)
+    let slow = |config: &ClientConfig| format!("if grep -q v2 {}/app.txt; then sleep 30; fi", config.service_dir);
+    let timeouts = |config: &mut ClientConfig| {
+        config.update_timeout_secs = 3;
+        config.command_timeout_secs = 30;
+    };
+    let restart = server
+        .register_with("e2e-timeout-restart", |config| {
+            timeouts(config);
+            config.restart_command = slow(config);
+        })
+        .await?;
+    let health = server
+        .register_with("e2e-timeout-health", |config| {
+            timeouts(config);
+            config.health_check_command = Some(format!("true; {}", slow(config)));
+        })
+        .await?;
+
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.upload("1.0.1", artifact("v2")).await?;
+    for (client, phase) in [(&restart, "restart"), (&health, "health_check")] {
+        server.deploy(client, "1.0.0").await?;
+        client.daemon.poll_once().await;
+        assert_eq!(client.read(".dm-version").as_deref(), Some("1.0.0"));
+
+        server.deploy(client, "1.0.1").await?;
+        let started = std::time::Instant::now();
+        client.daemon.poll_once().await;
+        assert!(started.elapsed() < Duration::from_secs(20), "{:?}", started.elapsed());
+
+        assert_eq!(client.read("app.txt").as_deref(), Some("v1"), "{}", phase);
+        assert_eq!(client.read(".dm-version").as_deref(), Some("1.0.0"), "{}", phase);
+        let logs = server.update_logs(client).await?;
+        assert_eq!(logs[1].status, "failed", "{:?}", logs);
+        let error = logs[1].error_message.as_deref().unwrap_or_default();
+        assert!(error.contains(&format!("update timed out after 3s during {}", phase)), "{}", error);
+        let reason: Option<String> = sqlx::query_scalar(
+            "SELECT failure_reason FROM update_logs WHERE client_id = $1 AND to_version = '1.0.1'",
+        )
+        .bind(client.id)
+        .fetch_one(&server.pool)
+        .await?;
+        assert_eq!(reason.as_deref(), Some("timed_out"), "{}", phase);
+    }
+
+    server.stop().await
+}
+
+/// 업데이트 전 명령은 백업 후 설치 전, 업데이트 후 명령은 헬스 체크 후 실행
+#[tokio::test]
+async fn update_scripts_run_around_the_install_with_versions() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let marks = tempfile::tempdir()?;
+    let log = marks.path().join("log");
+    let fail_pre = marks.path().join("fail-pre");
+    let fail_post = marks.path().join("fail-post");
+    let client = server
+        .register_with("e2e-update-scripts", |config| {
+            config.pre_update_script = Some(format!(
+                "test ! -f {} && echo \"pre $DM_FROM_VERSION $DM_TO_VERSION $(cat app.txt)\" >> {}",
+                fail_pre.display(),
+                log.display()
+            ));
+            config.post_update_script = Some(format!(
+                "echo \"post $DM_FROM_VERSION $DM_TO_VERSION $(cat app.txt)\" >> {} && test ! -f {}",
+                log.display(),
+                fail_post.display()
+            ));
+        })
+        .await?;
+    let read_log = || fs::read_to_string(&log).unwrap_or_default();
+
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.upload("1.0.1", artifact("v2")).await?;
+    server.upload("1.0.2", artifact("v3")).await?;
+    server.upload("1.0.3", artifact("v4")).await?;
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+    fs::remove_file(&log)?;
+
+    // 전 명령은 이전 파일을, 후 명령은 새 파일을 봄
+    server.deploy(&client, "1.0.1").await?;
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs[1].status, "completed", "{:?}", logs[1].error_message);
+    assert_eq!(logs[1].error_message, None);
+    assert_eq!(read_log(), "pre 1.0.0 1.0.1 v1\npost 1.0.0 1.0.1 v2\n");
+
+    // 전 명령이 실패하면 서비스 디렉토리를 바꾸지 않음
+    fs::remove_file(&log)?;
+    fs::write(&fail_pre, "")?;
+    server.deploy(&client, "1.0.2").await?;
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs[2].status, "failed");
+    assert!(
+        logs[2].error_message.as_deref().is_some_and(|m| m.contains("Pre-update script failed")),
+        "{:?}",
+        logs[2].error_message
+    );
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    assert_eq!(read_log(), "");
+
+    // 후 명령 실패는 롤백하지 않고 결과에 보고
+    fs::remove_file(&fail_pre)?;
+    fs::write(&fail_post, "")?;
+    server.deploy(&client, "1.0.3").await?;
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs[3].status, "completed", "{:?}", logs[3].error_message);
+    assert!(
+        logs[3].error_message.as_deref().is_some_and(|m| m.contains("Post-update script failed")),
+        "{:?}",
+        logs[3].error_message
+    );
+    assert_eq!(client.read("app.txt").as_deref(), Some("v4"));
+    assert_eq!(read_log(), "pre 1.0.1 1.0.3 v2\npost 1.0.1 1.0.3 v4\n");
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn install_strategy_follows_the_work_dir_filesystem() -> Result<()> {
+    use dm_client::installfs::InstallStrategy;
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    server.upload("1.0.0", artifact("v1")).await?;
+
+    // 기본 작업 디렉토리는 서비스 디렉토리 옆: 같은 파일시스템이라 rename, 끝나면 작업 디렉토리 없음
+    let client = server.register("e2e-install-rename").await?;
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert_eq!(logs[0].install_strategy.as_deref(), Some("rename"));
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    let state = LocalState::load(&client.service_dir.to_string_lossy());
+    assert_eq!(state.install_strategy, Some(InstallStrategy::Rename));
+    let root = client.service_dir.parent().context("service dir has a parent")?;
+    let leftovers: Vec<_> = fs::read_dir(root)?
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with(".dm-work-"))
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+
+    let renamed: Vec<serde_json::Value> = server
+        .http
+        .get(format!("{}/api/logs?install_strategy=rename", server.url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(renamed.len(), 1);
+
+    // 다른 파일시스템의 DM_WORK_DIR (/dev/shm): 복사로 설치하고 그대로 보고
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let shm = Path::new("/dev/shm");
+        let cross_device = fs::metadata(shm)
+            .and_then(|m| Ok(m.dev() != fs::metadata(root)?.dev()))
+            .unwrap_or(false);
+        if !cross_device {
+            eprintln!("skipping copy install check: /dev/shm is missing or on the same filesystem");
+        } else {
+            let work = shm.join(format!("dm-e2e-{}", Uuid::new_v4()));
+            let work_dir = work.to_string_lossy().into_owned();
+            let client = server
+                .register_with("e2e-install-copy", |config| config.work_dir = Some(work_dir))
+                .await?;
+            server.deploy(&client, "1.0.0").await?;
+            client.daemon.poll_once().await;
+
+            let logs = server.update_logs(&client).await?;
+            assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+            assert_eq!(logs[0].install_strategy.as_deref(), Some("copy"));
+            assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+            let state = LocalState::load(&client.service_dir.to_string_lossy());
+            assert_eq!(state.install_strategy, Some(InstallStrategy::Copy));
+            fs::remove_dir_all(&work)?;
+        }
+    }
+
+    server.stop().await
+}
+
+/// 두 임시 파일을 A/B 슬롯으로 삼아 쓰기·검증·전환·확정과 워치독 폴백까지 (DM_AB_SLOTS)
+///
+/// 재부팅 명령은 아무것도 하지 않고, 테스트가 부팅 ID와 현재 슬롯 파일을 바꿔 재부팅을 흉내 낸다.
+#[tokio::test]
+async fn image_updates_write_switch_confirm_and_report_boot_fallback() -> Result<()> {
+    use dm_client::abslot::{AbConfig, Slot, SlotDevice};
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let board = tempfile::tempdir()?;
+    let path = |name: &str| board.path().join(name);
+    let (slot_a, slot_b) = (path("slot-a.img"), path("slot-b.img"));
+    fs::write(&slot_a, noise(4096, "factory"))?;
+    fs::write(&slot_b, b"")?;
+    fs::write(path("booted"), "A\n")?;
+    fs::write(path("boot_id"), "boot-1\n")?;
+    let ab = AbConfig {
+        slots: vec![
+            Slot { name: "A".into(), device: slot_a.to_string_lossy().into_owned() },
+            Slot { name: "B".into(), device: slot_b.to_string_lossy().into_owned() },
+        ],
+        active_slot_command: Some(format!("cat {}", path("booted").display())),
+        switch_command: Some(format!("echo {{slot}} > {}", path("next").display())),
+        confirm_command: Some(format!("echo {{slot}} >> {}", path("confirmed").display())),
+        reboot_command: format!("echo {{slot}} >> {}", path("reboots").display()),
+        boot_id_file: path("boot_id").to_string_lossy().into_owned(),
+    };
+    let client = server.register_with("e2e-ab-slots", |config| config.ab = Some(ab)).await?;
+    let reboot = |booted: &str, boot_id: &str| -> Result<()> {
+        fs::write(path("booted"), format!("{}\n", booted))?;
+        fs::write(path("boot_id"), format!("{}\n", boot_id))?;
+        Ok(())
+    };
+    let upload_image = |version: &str, data: Vec<u8>| {
+        let form = reqwest::multipart::Form::new()
+            .text("version", version.to_string())
+            .text("deploy_type", "image")
+            .part("artifact", reqwest::multipart::Part::bytes(data).file_name("rootfs.img"));
+        server.http.post(format!("{}/api/versions", server.url)).multipart(form).send()
+    };
+
+    // gzip 이미지는 풀어서 비활성 슬롯(B)에 쓰고, 기록 후 B로 전환해 재부팅
+    let rootfs = noise(3 * 1024 * 1024 + 17, "rootfs-2");
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gz.write_all(&rootfs)?;
+    upload_image("2.0.0", gz.finish()?).await?.error_for_status()?;
+    server.deploy(&client, "2.0.0").await?;
+    client.daemon.poll_once().await;
+    assert!(fs::read(&slot_b)? == rootfs, "slot B does not hold the decompressed image");
+    assert_eq!(fs::read(&slot_a)?, noise(4096, "factory"));
+    assert_eq!(fs::read_to_string(path("next"))?.trim(), "B");
+    assert_eq!(fs::read_to_string(path("reboots"))?, "B\n");
+    let pending = LocalState::load(&client.service_dir.to_string_lossy()).pending_image.context("pending image")?;
+    assert_eq!((pending.from_slot.as_str(), pending.to_slot.as_str()), ("A", "B"));
+    assert!(server.update_logs(&client).await?.iter().all(|log| log.status != "completed"));
+
+    // 재부팅 전 검사는 기다리기만 함
+    client.daemon.poll_once().await;
+    assert!(!path("confirmed").exists());
+
+    // B로 부팅하면 체크인 후 확정하고 완료 보고
+    reboot("B", "boot-2")?;
+    client.daemon.poll_once().await;
+    assert_eq!(fs::read_to_string(path("confirmed"))?, "B\n");
+    assert_eq!(client.read(".dm-version").as_deref(), Some("2.0.0"));
+    let logs = server.update_logs(&client).await?;
+    let last = logs.last().context("update log")?;
+    assert_eq!((last.to_version.as_str(), last.status.as_str()), ("2.0.0", "completed"), "{:?}", last.error_message);
+    assert_eq!(last.verified_checksum.as_deref(), Some(pending.artifact_checksum.as_str()));
+    assert!(LocalState::load(&client.service_dir.to_string_lossy()).pending_image.is_none());
+
+    // raw 이미지는 그대로 A에 쓰지만, 워치독이 B로 되돌리면 확정하지 않고 실패 보고
+    let rootfs3 = noise(1024 * 1024, "rootfs-3");
+    upload_image("3.0.0", rootfs3.clone()).await?.error_for_status()?;
+    server.deploy(&client, "3.0.0").await?;
+    client.daemon.poll_once().await;
+    assert!(fs::read(&slot_a)? == rootfs3, "slot A does not hold the raw image");
+    assert_eq!(fs::read_to_string(path("next"))?.trim(), "A");
+    reboot("B", "boot-3")?;
+    client.daemon.poll_once().await;
+    assert_eq!(fs::read_to_string(path("confirmed"))?, "B\n");
+    assert_eq!(client.read(".dm-version").as_deref(), Some("2.0.0"));
+    let logs = server.update_logs(&client).await?;
+    let last = logs.last().context("update log")?;
+    assert_eq!((last.to_version.as_str(), last.status.as_str()), ("3.0.0", "failed"));
+    assert!(last.error_message.as_deref().unwrap_or_default().contains("instead of A"), "{:?}", last.error_message);
+    let reason: Option<String> = sqlx::query_scalar(
+        "SELECT failure_reason FROM update_logs WHERE client_id = $1 AND to_version = '3.0.0'",
+    )
+    .bind(client.id)
+    .fetch_one(&server.pool)
+    .await?;
+    assert_eq!(reason.as_deref(), Some("boot_fallback"));
+    assert!(LocalState::load(&client.service_dir.to_string_lossy()).pending_image.is_none());
+
+    // gzip 외의 압축 이미지는 업로드에서 거부하고, 장비도 슬롯에 쓰기 전에 거부
+    let mut zstd = vec![0x28, 0xb5, 0x2f, 0xfd];
+    zstd.extend(noise(64, "zstd"));
+    let response = upload_image("4.0.0", zstd.clone()).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.text().await?.contains("zstd compressed"));
+    fs::write(path("rootfs.img.zst"), &zstd)?;
+    let error = SlotDevice::new(&slot_b)
+        .write_image(&path("rootfs.img.zst"))
+        .expect_err("zstd images must not be written");
+    assert!(error.to_string().contains("zstd compressed"), "{}", error);
+    assert!(fs::read(&slot_b)? == rootfs, "rejected image touched slot B");
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn artifacts_download_to_the_download_dir_and_are_removed_afterwards() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let downloads = tempfile::tempdir()?;
+    let download_dir = downloads.path().join("downloads");
+    let client = server
+        .register_with("e2e-download-dir", |config| {
+            config.download_dir = Some(download_dir.to_string_lossy().into_owned())
+        })
+        .await?;
+    let leftovers = || -> Vec<String> {
+        [&download_dir, &client.backup_dir]
+            .into_iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.flatten())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(".dm-download"))
+            .collect()
+    };
+
+    let content = noise(3_000_001, "download-dir");
+    let data = artifact_file("app.bin", &content);
+    server.install(&client, "1.0.0", data.clone()).await?;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "completed", "{:?}", logs[0].error_message);
+    assert_eq!(logs[0].verified_checksum.as_deref(), Some(sha256(&data).as_str()));
+    assert!(client.read_bytes("app.bin") == Some(content), "installed content differs");
+    assert!(download_dir.is_dir(), "download dir was not used");
+    assert!(leftovers().is_empty(), "{:?}", leftovers());
+
+    // 체크섬이 맞지 않아 실패해도 받은 파일은 남지 않음
+    server.upload("1.0.1", artifact("second")).await?;
+    fs::write(server.artifact_dir.join("1.0.1.tar.gz"), artifact("tampered"))?;
+    server.deploy(&client, "1.0.1").await?;
+    client.daemon.poll_once().await;
+
+    let logs = server.update_logs(&client).await?;
+    let failed = logs.last().context("no update log")?;
+    assert_eq!(failed.status, "failed", "{:?}", failed.error_message);
+    assert!(
+        failed.error_message.as_deref().unwrap_or_default().contains("Checksum"),
+        "{:?}",
+        failed.error_message
+    );
+    assert_eq!(client.read("app.txt"), None);
+    assert!(leftovers().is_empty(), "{:?}", leftovers());
+
+    server.stop().await
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn failed_copy_leaves_the_service_directory_untouched() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    // 복원 중 복사 실패를 만들기 위해 디렉토리 백업 사용
+    let client = server
+        .register_with("e2e-install-swap", |config| config.backup_compress = false)
+        .await?;
+    let siblings = || -> Result<Vec<String>> {
+        let name = client.service_dir.file_name().context("service dir name")?.to_string_lossy().into_owned();
+        let parent = client.service_dir.parent().context("service dir parent")?;
+        Ok(fs::read_dir(parent)?
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .filter(|n| n.starts_with(&format!("{}.new-", name)) || n.starts_with(&format!("{}.old-", name)))
+            .collect())
+    };
+
+    // 설치는 옆 디렉토리를 채운 뒤 교체하고, 이전 디렉토리는 지움
+    server.install(&client, "1.0.0", artifact("v1")).await?;
+    server.install(&client, "1.1.0", artifact("v2")).await?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    assert!(siblings()?.is_empty(), "{:?}", siblings()?);
+
+    // 복사 도중 실패하는 복원 (백업에 복사할 수 없는 파일): 서비스 디렉토리는 그대로
+    let backup = client.backups().into_iter().next().context("backup of 1.0.0")?;
+    let socket = backup.path.join("zz.sock");
+    plant_socket(&socket)?;
+    let updater = Updater::new(client.config.clone());
+    let error = updater
+        .rollback(&backup.path.to_string_lossy())
+        .expect_err("copying a socket must fail");
+    assert!(error.to_string().contains("zz.sock"), "{}", error);
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.1.0"));
+    assert!(siblings()?.is_empty(), "{:?}", siblings()?);
+
+    // 원인을 없애면 같은 복원이 교체로 끝남
+    fs::remove_file(&socket)?;
+    updater.rollback(&backup.path.to_string_lossy())?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    assert!(siblings()?.is_empty(), "{:?}", siblings()?);
+
+    server.stop().await
+}
+
+/// 서비스 디렉토리가 심볼릭 링크면 설치와 롤백은 대상 디렉토리를 교체하고 링크는 그대로 둠
+#[cfg(unix)]
+#[tokio::test]
+async fn symlinked_service_dir_keeps_its_link_across_install_and_rollback() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let marks = tempfile::tempdir()?;
+    let unhealthy = marks.path().join("unhealthy");
+    let client = server
+        .register_with("e2e-symlinked-service", |config| {
+            config.health_check_command = Some(format!("test ! -f {}", unhealthy.display()));
+        })
+        .await?;
+    let parent = client.service_dir.parent().context("service dir parent")?.to_path_buf();
+    let target = parent.join("releases").join("current");
+    fs::create_dir_all(&target)?;
+    fs::remove_dir_all(&client.service_dir)?;
+    std::os::unix::fs::symlink("releases/current", &client.service_dir)?;
+    let check_link = || -> Result<()> {
+        let meta = fs::symlink_metadata(&client.service_dir)?;
+        assert!(meta.file_type().is_symlink(), "{:?} is no longer a symlink", client.service_dir);
+        assert_eq!(fs::read_link(&client.service_dir)?, Path::new("releases/current"));
+        assert_eq!(fs::canonicalize(&client.service_dir)?, fs::canonicalize(&target)?);
+        // 교체용 임시 디렉토리는 링크 옆에도 대상 옆에도 남지 않음
+        for dir in [&parent, &parent.join("releases")] {
+            let leftovers: Vec<String> = fs::read_dir(dir)?
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .filter(|n| n.contains(".new-") || n.contains(".old-"))
+                .collect();
+            assert!(leftovers.is_empty(), "{:?}", leftovers);
+        }
+        Ok(())
+    };
+
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.upload("1.0.1", artifact("v2")).await?;
+    server.upload("1.0.2", artifact("v3")).await?;
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+    server.deploy(&client, "1.0.1").await?;
+    client.daemon.poll_once().await;
+    assert_eq!(fs::read_to_string(target.join("app.txt"))?, "v2");
+    check_link()?;
+
+    // 헬스 체크 실패로 인한 자동 롤백
+    fs::write(&unhealthy, "")?;
+    server.deploy(&client, "1.0.2").await?;
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    let last = logs.last().context("update log")?;
+    assert_eq!(last.status, "failed", "{:?}", last.error_message);
+    assert_eq!(fs::read_to_string(target.join("app.txt"))?, "v2");
+    check_link()?;
+    fs::remove_file(&unhealthy)?;
+
+    // 수동 롤백
+    let updater = Updater::new(client.config.clone());
+    let backups = client.backups();
+    let backup = backups.iter().find(|b| b.version == "1.0.0").context("backup of 1.0.0")?;
+    updater.rollback_to(backup, &updater.health_probes()?)?;
+    assert_eq!(fs::read_to_string(target.join("app.txt"))?, "v1");
+    assert_eq!(fs::read_to_string(target.join(".dm-version"))?.trim(), "1.0.0");
+    check_link()?;
+
+    server.stop().await
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn backup_and_rollback_keep_modes_mtimes_and_symlinks() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server
+        .register_with("e2e-copy-metadata", |config| config.backup_compress = false)
+        .await?;
+    const MTIME: u64 = 1_600_000_000;
+
+    // Next.js 번들처럼 실행 스크립트와 node_modules/.bin 링크가 든 아티팩트
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    for (name, mode, content) in [
+        ("start.sh", 0o755, &b"#!/bin/sh\nexec node server.js\n"[..]),
+        ("node_modules/next/dist/bin/next", 0o755, &b"#!/usr/bin/env node\n"[..]),
+        ("config.json", 0o640, &b"{}"[..]),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(mode);
+        header.set_mtime(MTIME);
+        header.set_cksum();
+        builder.append_data(&mut header, name, content)?;
+    }
+    let mut link = tar::Header::new_gnu();
+    link.set_entry_type(tar::EntryType::Symlink);
+    link.set_size(0);
+    link.set_mode(0o777);
+    builder.append_link(&mut link, "node_modules/.bin/next", "../next/dist/bin/next")?;
+    server.install(&client, "1.0.0", builder.into_inner()?.finish()?).await?;
+    assert_eq!(server.update_logs(&client).await?[0].status, "completed");
+
+    let check = |root: &Path| -> Result<()> {
+        let mode = |name: &str| -> Result<u32> { Ok(fs::metadata(root.join(name))?.permissions().mode() & 0o777) };
+        assert_eq!(mode("start.sh")?, 0o755, "{:?}", root);
+        assert_eq!(mode("node_modules/next/dist/bin/next")?, 0o755, "{:?}", root);
+        assert_eq!(mode("config.json")?, 0o640, "{:?}", root);
+        let link = root.join("node_modules/.bin/next");
+        assert!(fs::symlink_metadata(&link)?.file_type().is_symlink(), "{:?}", link);
+        assert_eq!(fs::read_link(&link)?, Path::new("../next/dist/bin/next"));
+        let mtime = fs::metadata(root.join("start.sh"))?
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        assert_eq!(mtime, MTIME, "{:?}", root);
+        Ok(())
+    };
+    check(&client.service_dir)?;
+
+    // 다음 업데이트의 백업과 그 백업으로의 롤백에서도 그대로 유지
+    server.install(&client, "1.1.0", artifact("v2")).await?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    let backup = client.backups().into_iter().next().context("backup of 1.0.0")?;
+    check(&backup.path)?;
+
+    Updater::new(client.config.clone()).rollback(&backup.path.to_string_lossy())?;
+    check(&client.service_dir)?;
+
+    server.stop().await
+}
+
+/// 압축 백업(DM_BACKUP_COMPRESS)도 권한, 수정 시각, 심볼릭 링크, 내용을 그대로 복원하고
+/// 켜기 전에 만든 디렉토리 백업으로도 롤백할 수 있음
+#[cfg(unix)]
+#[tokio::test]
+async fn compressed_backups_round_trip_and_directory_backups_still_restore() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-compressed-backup").await?;
+    server.upload("1.0.0", artifact_files(&[("app.txt", b"v1"), ("data/big.bin", &[b'a'; 256 * 1024])])).await?;
+    server.upload("1.1.0", artifact("v2")).await?;
+    server.upload("1.2.0", artifact("v3")).await?;
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+
+    // 설치 후 생긴 파일 (권한, 수정 시각, 링크)
+    let script = client.service_dir.join("run.sh");
+    fs::write(&script, "#!/bin/sh\n")?;
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o750))?;
+    fs::File::options()
+        .write(true)
+        .open(&script)?
+        .set_modified(std::time::UNIX_EPOCH + Duration::from_secs(1_600_000_000))?;
+    std::os::unix::fs::symlink("data/big.bin", client.service_dir.join("current"))?;
+    let snapshot = |root: &Path| -> Result<Vec<(String, Vec<u8>)>> {
+        let mut files = Vec::new();
+        for name in ["app.txt", "data/big.bin", "run.sh", ".dm-version"] {
+            files.push((name.to_string(), fs::read(root.join(name))?));
+        }
+        Ok(files)
+    };
+    let before = snapshot(&client.service_dir)?;
+
+    server.deploy(&client, "1.1.0").await?;
+    client.daemon.poll_once().await;
+    let backup = client.backups().into_iter().next().context("backup of 1.0.0")?;
+    assert!(backup.name.ends_with(".tar.gz") && backup.path.is_file(), "{:?}", backup.path);
+    assert_eq!(backup.version, "1.0.0");
+    assert!(fs::metadata(&backup.path)?.len() < 64 * 1024);
+
+    let updater = Updater::new(client.config.clone());
+    updater.rollback(&backup.path.to_string_lossy())?;
+    assert_eq!(snapshot(&client.service_dir)?, before);
+    assert_eq!(fs::metadata(&script)?.permissions().mode() & 0o777, 0o750);
+    assert_eq!(
+        fs::metadata(&script)?.modified()?.duration_since(std::time::UNIX_EPOCH)?.as_secs(),
+        1_600_000_000
+    );
+    assert_eq!(fs::read_link(client.service_dir.join("current"))?, Path::new("data/big.bin"));
+
+    // 압축을 켜기 전의 디렉토리 백업
+    let mut plain = client.config.clone();
+    plain.backup_compress = false;
+    server.deploy(&client, "1.2.0").await?;
+    client.daemon.poll_once().await;
+    let directory = Updater::new(plain).backup_current("1.2.0")?;
+    assert!(Path::new(&directory).is_dir());
+    updater.rollback(&backup.path.to_string_lossy())?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    updater.rollback(&directory)?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v3"));
+
+    server.stop().await
+}
+
+/// 제외 패턴으로 줄인 백업으로 롤백해도 rollback_regenerate면 install_command로 다시 만든 뒤 헬스 체크 통과
+#[tokio::test]
+async fn rollback_regenerates_excluded_paths_before_the_health_check() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server
+        .register_with("e2e-rollback-regenerate", |config| {
+            config.backup_exclude = vec!["node_modules".into()];
+            config.rollback_regenerate = Some(true);
+            config.install_command = Some("mkdir -p node_modules && printf regenerated > node_modules/dep.js".into());
+            config.health_check_command = Some(format!("test -f {}/node_modules/dep.js", config.service_dir));
+        })
+        .await?;
+    server.upload("1.0.0", artifact_files(&[("app.txt", b"v1"), ("node_modules/dep.js", b"installed")])).await?;
+    server.upload("1.1.0", artifact_files(&[("app.txt", b"v2"), ("node_modules/dep.js", b"installed")])).await?;
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+    server.deploy(&client, "1.1.0").await?;
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+
+    let backups = client.backups();
+    let backup = backups.first().context("backup of 1.0.0")?;
+    assert_eq!(backup.version, "1.0.0");
+    assert_eq!(backup.excluded, vec!["node_modules".to_string()]);
+
+    let updater = Updater::new(client.config.clone());
+    updater.rollback_to(backup, &updater.health_probes()?)?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    assert_eq!(client.read("node_modules/dep.js").as_deref(), Some("regenerated"));
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.0.0"));
+
+    // 끄면 제외된 경로 없이 복원되어 같은 헬스 체크가 실패
+    server.deploy(&client, "1.1.0").await?;
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    let mut config = client.config.clone();
+    config.rollback_regenerate = Some(false);
+    let updater = Updater::new(config);
+    let backups = client.backups();
+    let backup = backups.first().context("backup of 1.0.0")?;
+    let error = updater
+        .rollback_to(backup, &updater.health_probes()?)
+        .expect_err("rollback without node_modules must fail the health check");
+    assert!(error.to_string().contains("Health check failed"), "{}", error);
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    assert_eq!(client.read("node_modules/dep.js"), None);
+
+    server.stop().await
+}
+
+/// 여유 공간이 artifact_size × DM_DISK_SPACE_FACTOR보다 적으면 다운로드 전에 중단하고 보고,
+/// DM_SKIP_DISK_CHECK면 그대로 설치
+#[tokio::test]
+async fn updates_abort_before_download_without_enough_disk_space() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    // 어떤 디스크보다 큰 요구량
+    let client = server
+        .register_with("e2e-disk-short", |config| config.disk_space_factor = 1e15)
+        .await?;
+    let skipping = server
+        .register_with("e2e-disk-skip", |config| {
+            config.disk_space_factor = 1e15;
+            config.skip_disk_check = true;
+        })
+        .await?;
+    server.upload("1.0.0", artifact("v1")).await?;
+    fs::write(client.service_dir.join("app.txt"), "v0")?;
+    server.deploy(&client, "1.0.0").await?;
+    server.deploy(&skipping, "1.0.0").await?;
+
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 1, "{:?}", logs);
+    assert_eq!(logs[0].status, "failed");
+    let error = logs[0].error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("Insufficient disk space") && error.contains("DM_DISK_SPACE_FACTOR"), "{}", error);
+    assert!(error.contains("service_dir") && error.contains("backup_dir"), "{}", error);
+    let reason: Option<String> =
+        sqlx::query_scalar("SELECT failure_reason FROM update_logs WHERE client_id = $1")
+            .bind(client.id)
+            .fetch_one(&server.pool)
+            .await?;
+    assert_eq!(reason.as_deref(), Some("insufficient_disk_space"));
+    assert_eq!(client.read("app.txt").as_deref(), Some("v0"));
+    assert!(client.backups().is_empty());
+
+    skipping.daemon.poll_once().await;
+    assert_eq!(server.update_logs(&skipping).await?[0].status, "completed");
+    assert_eq!(skipping.read("app.txt").as_deref(), Some("v1"));
+
+    server.stop().await
+}
+
+/// 보존 경로(서버 ClientConfig.preserve_paths 또는 DM_PRESERVE_PATHS)는 업데이트와 롤백 후에도 그대로,
+/// 서비스 디렉토리 밖을 가리키는 경로는 서버와 장비 모두 거부
+#[tokio::test]
+async fn preserved_paths_survive_updates_and_rollbacks() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-preserve").await?;
+    let set_config = |paths: &[&str]| {
+        server
+            .http
+            .put(format!("{}/api/clients/{}/config", server.url, client.id))
+            .json(&serde_json::json!({ "config": { "preserve_paths": paths } }))
+            .send()
+    };
+    let response = set_config(&[".env", "../etc"]).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    set_config(&[".env", "data", "uploads/", "missing.txt"]).await?.error_for_status()?;
+
+    server.upload("1.0.0", artifact_files(&[("app.txt", b"v1"), (".env", b"PORT=1")])).await?;
+    server.upload("1.1.0", artifact_files(&[("app.txt", b"v2"), (".env", b"PORT=2"), ("data/seed.db", b"seed")])).await?;
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+    assert_eq!(client.read(".env").as_deref(), Some("PORT=1"));
+
+    // 장비에서 고친 설정과 쌓인 데이터
+    fs::write(client.service_dir.join(".env"), "PORT=8080")?;
+    fs::create_dir_all(client.service_dir.join("data/db"))?;
+    fs::write(client.service_dir.join("data/db/app.db"), "rows")?;
+    fs::create_dir_all(client.service_dir.join("uploads"))?;
+    fs::write(client.service_dir.join("uploads/logo.png"), "png")?;
+
+    server.deploy(&client, "1.1.0").await?;
+    client.daemon.poll_once().await;
+    assert_eq!(server.update_logs(&client).await?[0].status, "completed");
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+    assert_eq!(client.read(".env").as_deref(), Some("PORT=8080"));
+    assert_eq!(client.read("data/db/app.db").as_deref(), Some("rows"));
+    assert!(!client.service_dir.join("data/seed.db").exists());
+    assert_eq!(client.read("uploads/logo.png").as_deref(), Some("png"));
+
+    // 롤백도 백업의 사본이 아니라 현재 값을 유지
+    fs::write(client.service_dir.join("data/db/app.db"), "more rows")?;
+    let backup = client.backups().into_iter().next().context("backup of 1.0.0")?;
+    Updater::new(client.config.clone()).rollback(&backup.path.to_string_lossy())?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    assert_eq!(client.read(".env").as_deref(), Some("PORT=8080"));
+    assert_eq!(client.read("data/db/app.db").as_deref(), Some("more rows"));
+    assert_eq!(client.read("uploads/logo.png").as_deref(), Some("png"));
+
+    // 서비스 디렉토리 밖을 가리키는 로컬 설정은 설치 전에 거부
+    let outside = server
+        .register_with("e2e-preserve-outside", |config| config.preserve_paths = vec!["../shared".into()])
+        .await?;
+    server.deploy(&outside, "1.0.0").await?;
+    outside.daemon.poll_once().await;
+    let logs = server.update_logs(&outside).await?;
+    assert_eq!(logs[0].status, "failed");
+    let error = logs[0].error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("inside the service directory"), "{}", error);
+    assert!(outside.read("app.txt").is_none());
+
+    server.stop().await
+}
+
+/// DM_UPDATE_PUBLIC_KEY가 있으면 서명이 맞는 아티팩트만 설치 (체크섬이 맞게 바꿔치기한 아티팩트, 서명 없는
+/// 아티팩트는 거부), USB manifest의 서명도 같은 규칙
+#[tokio::test]
+async fn signed_artifacts_install_and_tampered_ones_are_rejected() -> Result<()> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let key = SigningKey::from_bytes(&[7u8; 32]);
+    let public_key = STANDARD.encode(key.verifying_key().as_bytes());
+    let sign = |data: &[u8]| STANDARD.encode(key.sign(data).to_bytes());
+    let client = server
+        .register_with("e2e-signed", |config| config.update_public_key = Some(public_key.clone()))
+        .await?;
+    let upload = |version: &str, data: Vec<u8>, signature: Option<String>| {
+        let mut form = reqwest::multipart::Form::new()
+            .text("version", version.to_string())
+            .part("artifact", reqwest::multipart::Part::bytes(data).file_name("app.tar.gz"));
+        if let Some(signature) = signature {
+            form = form.text("signature", signature);
+        }
+        server.http.post(format!("{}/api/versions", server.url)).multipart(form).send()
+    };
+
+    let response = upload("0.9.0", artifact("v0"), Some("not-a-signature".to_string())).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let v1 = artifact("v1");
+    upload("1.0.0", v1.clone(), Some(sign(&v1))).await?.error_for_status()?;
+    let download = server
+        .http
+        .get(format!("{}/api/artifacts/1.0.0", server.url))
+        .header("X-API-Key", &client.api_key)
+        .send()
+        .await?
+        .error_for_status()?;
+    assert_eq!(download.headers()["X-Signature"], sign(&v1).as_str());
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+    assert_eq!(server.update_logs(&client).await?[0].status, "completed");
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+
+    // 서버가 뚫려 아티팩트와 체크섬을 함께 바꿔도 서명은 맞출 수 없음
+    let v2 = artifact("v2");
+    let stored = upload("1.1.0", v2.clone(), Some(sign(&v2)))
+        .await?
+        .error_for_status()?
+        .json::<serde_json::Value>()
+        .await?;
+    let malicious = artifact("evil");
+    fs::write(server.artifact_dir.join(stored["artifact_path"].as_str().context("artifact_path")?), &malicious)?;
+    sqlx::query("UPDATE versions SET checksum = $2, artifact_size = $3 WHERE version = $1")
+        .bind("1.1.0")
+        .bind(sha256(&malicious))
+        .bind(malicious.len() as i64)
+        .execute(&server.pool)
+        .await?;
+    server.deploy(&client, "1.1.0").await?;
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    let log = logs.last().context("update log")?;
+    assert_eq!(log.status, "failed", "{:?}", logs);
+    let error = log.error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("does not match DM_UPDATE_PUBLIC_KEY"), "{}", error);
+    let reason: Option<String> = sqlx::query_scalar(
+        "SELECT failure_reason FROM update_logs WHERE client_id = $1 AND to_version = '1.1.0'",
+    )
+    .bind(client.id)
+    .fetch_one(&server.pool)
+    .await?;
+    assert_eq!(reason.as_deref(), Some("signature_invalid"));
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+
+    // 키가 설정된 장비는 서명 없는 아티팩트도 거부
+    upload("1.2.0", artifact("v3"), None).await?.error_for_status()?;
+    server.deploy(&client, "1.2.0").await?;
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    let error = logs.last().and_then(|l| l.error_message.as_deref()).unwrap_or_default();
+    assert!(error.contains("is missing"), "{}", error);
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+
+    // USB: manifest.json의 signature로 검증
+    let usb = tempfile::tempdir()?;
+    let file = usb.path().join("update.tar.gz");
+    let v4 = artifact("v4");
+    fs::write(&file, &v4)?;
+    let manifest = |signature: String| {
+        serde_json::json!({ "version": "1.3.0", "checksum": sha256(&v4), "signature": signature }).to_string()
+    };
+    fs::write(usb.path().join("manifest.json"), manifest(sign(b"something else")))?;
+    let error = dm_client::usb::apply_from_file(&client.config, &file.to_string_lossy(), None, None, |_| Ok(()))
+        .await
+        .expect_err("tampered USB artifact must be rejected");
+    assert!(format!("{:#}", error).contains("does not match"), "{:#}", error);
+    fs::write(usb.path().join("manifest.json"), manifest(sign(&v4)))?;
+    dm_client::usb::apply_from_file(&client.config, &file.to_string_lossy(), None, None, |_| Ok(())).await?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v4"));
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn corrupt_archives_are_rejected_before_backup() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-archive-validation").await?;
+    server.install(&client, "1.0.0", artifact("v1")).await?;
+    let backups_before = client.backups().len();
+
+    // 체크섬은 맞지만 잘린 아카이브 (업로드 뒤 저장된 파일을 바꿔 서버 검사를 우회)
+    let whole = artifact_file("big.bin", &noise(256 * 1024, "truncated"));
+    let truncated = whole[..whole.len() * 6 / 10].to_vec();
+    let path = server.upload("1.1.0", whole).await?;
+    fs::write(server.artifact_dir.join(path), &truncated)?;
+    sqlx::query("UPDATE versions SET checksum = $2, artifact_size = $3 WHERE version = $1")
+        .bind("1.1.0")
+        .bind(sha256(&truncated))
+        .bind(truncated.len() as i64)
+        .execute(&server.pool)
+        .await?;
+    server.deploy(&client, "1.1.0").await?;
+    client.daemon.poll_once().await;
+
+    // `..`로 서비스 디렉토리 밖을 가리키는 항목
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    let mut header = tar::Header::new_gnu();
+    header.as_old_mut().name[..13].copy_from_slice(b"../escape.txt");
+    header.set_size(3);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append(&header, &b"out"[..])?;
+    server.install(&client, "1.2.0", builder.into_inner()?.finish()?).await?;
+
+    let logs = server.update_logs(&client).await?;
+    assert_eq!(logs.len(), 3, "{:?}", logs);
+    for (log, reason) in logs[1..].iter().zip(["big.bin", "escapes the install directory"]) {
+        assert_eq!(log.status, "failed");
+        let error = log.error_message.as_deref().unwrap_or_default();
+        assert!(error.contains("Invalid artifact") && error.contains(reason), "{}", error);
+    }
+    let parent = client.service_dir.parent().context("service dir parent")?;
+    assert!(!parent.join("escape.txt").exists());
+
+    // 오프라인 적용도 같은 검사로 백업 전에 중단
+    let file = client.service_dir.with_file_name("truncated.tar.gz");
+    fs::write(&file, &truncated)?;
+    let error = dm_client::usb::apply_from_file(&client.config, &file.to_string_lossy(), Some("1.1.0"), None, |_| Ok(()))
+        .await
+        .expect_err("truncated archive must be rejected");
+    assert!(error.to_string().contains("Invalid artifact"), "{}", error);
+
+    // 서비스 디렉토리와 백업은 그대로
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.0.0"));
+    assert_eq!(client.backups().len(), backups_before);
+
+    server.stop().await
+}
+
+#[tokio::test]
+async fn plain_tar_and_zip_artifacts_install_and_unsafe_zip_entries_are_rejected() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-archive-formats").await?;
+    let upload = |version: &str, file_name: &str, artifact: Vec<u8>| {
+        let form = reqwest::multipart::Form::new()
+            .text("version", version.to_string())
+            .part(
+                "artifact",
+                reqwest::multipart::Part::bytes(artifact).file_name(file_name.to_string()),
+            );
+        server.http.post(format!("{}/api/versions", server.url)).multipart(form).send()
+    };
+
+    // tar.gz
+    server.install(&client, "1.0.0", artifact("v1")).await?;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+
+    // 압축하지 않은 tar (이름과 상관없이 내용으로 판별)
+    let mut builder = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(2);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, "app/app.txt", &b"v2"[..])?;
+    let uploaded: serde_json::Value = upload("1.1.0", "app.tar", builder.into_inner()?)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(uploaded["artifact_path"], "1.1.0.tar");
+    server.deploy(&client, "1.1.0").await?;
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v2"));
+
+    // zip (실행 권한 유지)
+    let zipped = zip_artifact(&[("app/app.txt", b"v3", 0o644), ("app/bin/run.sh", b"#!/bin/sh\n", 0o755)])?;
+    let uploaded: serde_json::Value = upload("1.2.0", "app.zip", zipped)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(uploaded["artifact_path"], "1.2.0.zip");
+    server.deploy(&client, "1.2.0").await?;
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v3"));
+    let mode = fs::metadata(client.service_dir.join("bin/run.sh"))?.permissions().mode() & 0o777;
+    assert_eq!(mode, 0o755);
+
+    // 다른 형식은 이름이 맞아도 업로드에서 거부
+    let response = upload("1.3.0", "app.zip", b"not an archive".to_vec()).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // `..` 항목은 tar와 같이 설치 전에 거부
+    let escaping = zip_artifact(&[("app.txt", b"v4", 0o644), ("../escape.txt", b"out", 0o644)])?;
+    upload("1.4.0", "app.zip", escaping).await?.error_for_status()?;
+    server.deploy(&client, "1.4.0").await?;
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    let failed = logs.last().context("update log")?;
+    assert_eq!(failed.to_version, "1.4.0");
+    assert_eq!(failed.status, "failed");
+    let error = failed.error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("Invalid artifact") && error.contains("escapes the install directory"), "{}", error);
+
+    // 절대 경로 항목은 오프라인 적용에서도 거부
+    let file = client.service_dir.with_file_name("absolute.zip");
+    fs::write(&file, zip_artifact(&[("app.txt", b"v5", 0o644), ("/tmp/dm-e2e-absolute.txt", b"out", 0o644)])?)?;
+    let error = dm_client::usb::apply_from_file(&client.config, &file.to_string_lossy(), Some("1.5.0"), None, |_| Ok(()))
+        .await
+        .expect_err("absolute zip entry must be rejected");
+    assert!(error.to_string().contains("escapes the install directory"), "{}", error);
+
+    let parent = client.service_dir.parent().context("service dir parent")?;
+    assert!(!parent.join("escape.txt").exists());
+    assert!(!Path::new("/tmp/dm-e2e-absolute.txt").exists());
+    assert_eq!(client.read("app.txt").as_deref(), Some("v3"));
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.2.0"));
+
+    server.stop().await
+}
+
+/// 감시하는 마운트 루트에 나타난 USB 번들은 버전마다 한 번만 처리하고 결과를 USB에 남김
+#[tokio::test]
+async fn usb_watch_applies_each_bundle_version_once() -> Result<()> {
+    use dm_client::api::BuildInfo;
+    use dm_client::package::{self, PackageOptions};
+    use dm_client::usbwatch::{UsbWatcher, RESULT_FILE};
+
+    let root = tempfile::tempdir()?;
+    let config = client_config("http://127.0.0.1:9", "unused", root.path());
+    let media = root.path().join("media");
+    let bundle = |stick: &str, version: &str, content: &str| -> Result<PathBuf> {
+        let tree = root.path().join(format!("tree-{}", version));
+        fs::create_dir_all(&tree)?;
+        fs::write(tree.join("app.txt"), content)?;
+        let out = media.join(stick);
+        package::create_package(
+            tree.to_str().unwrap(),
+            version,
+            out.to_str().unwrap(),
+            None,
+            None,
+            BuildInfo::default(),
+            &PackageOptions::default(),
+        )?;
+        Ok(out)
+    };
+    let result_of = |stick: &Path| -> Result<serde_json::Value> {
+        Ok(serde_json::from_slice(&fs::read(stick.join(RESULT_FILE))?)?)
+    };
+
+    // 사용자별 마운트 아래(/media/<사용자>/<라벨>)의 번들도 찾아 적용
+    let first = bundle("tech/STICK-A", "1.0.0", "v1")?;
+    let mut watcher = UsbWatcher::new(config.clone(), &media);
+    let results = watcher.scan_once().await;
+    assert_eq!(results.len(), 1, "{:?}", results);
+    assert_eq!(results[0].status, "completed", "{:?}", results[0].error);
+    assert_eq!(fs::read_to_string(Path::new(&config.service_dir).join("app.txt"))?, "v1");
+    let written = result_of(&first)?;
+    assert_eq!(written["version"], "1.0.0");
+    assert_eq!(written["status"], "completed");
+
+    // 같은 번들이 꽂혀 있어도 다시 적용하지 않음
+    assert!(watcher.scan_once().await.is_empty());
+
+    // 처리 기록이 없어도 설치된 버전과 같으면 건너뜀
+    fs::remove_file(Path::new(&config.control_dir).join("usb-applied.json"))?;
+    let results = watcher.scan_once().await;
+    assert_eq!(results.len(), 1, "{:?}", results);
+    assert_eq!(results[0].status, "skipped");
+    assert_eq!(result_of(&first)?["status"], "skipped");
+
+    // 실패한 번들은 한 번만 시도하고 결과를 남김
+    let broken = bundle("STICK-B", "1.1.0", "v2")?;
+    fs::write(broken.join("update.tar.gz"), b"not the packaged artifact")?;
+    let results = watcher.scan_once().await;
+    assert_eq!(results.len(), 1, "{:?}", results);
+    assert_eq!(results[0].status, "failed");
+    let written = result_of(&broken)?;
+    assert_eq!(written["status"], "failed");
+    assert!(written["error"].as_str().is_some_and(|e| !e.is_empty()), "{}", written);
+    assert!(watcher.scan_once().await.is_empty());
+
+    // 새 버전 번들은 다른 번들이 꽂혀 있어도 적용
+    bundle("STICK-C", "1.2.0", "v3")?;
+    let results = UsbWatcher::new(config.clone(), &media).scan_once().await;
+    assert_eq!(results.len(), 1, "{:?}", results);
+    assert_eq!((results[0].version.as_str(), results[0].status.as_str()), ("1.2.0", "completed"));
+    assert_eq!(fs::read_to_string(Path::new(&config.service_dir).join("app.txt"))?, "v3");
+    assert_eq!(fs::read_to_string(Path::new(&config.service_dir).join(".dm-version"))?.trim(), "1.2.0");
+
+    Ok(())
+}
+
+/// 같은 서비스 디렉토리에 동시에 적용하면 하나만 진행하고 나머지는 잠금을 잡은 pid와 함께 바로 실패
+#[test]
+fn concurrent_applies_are_serialized_by_the_update_lock() -> Result<()> {
+    use std::sync::mpsc;
+
+    let root = tempfile::tempdir()?;
+    let config = client_config("http://127.0.0.1:9", "unused", root.path());
+    fs::create_dir_all(&config.service_dir)?;
+    fs::write(Path::new(&config.service_dir).join("app.txt"), "v0")?;
+    let usb = root.path().join("usb");
+    fs::create_dir_all(&usb)?;
+    let file = usb.join("update.tar.gz");
+    fs::write(&file, artifact("v1"))?;
+
+    // 먼저 잠금을 잡은 쪽은 다른 쪽이 끝날 때까지 확인 단계에서 기다림
+    let (done, finished) = mpsc::channel::<()>();
+    let finished = Arc::new(Mutex::new(finished));
+    let apply = |version: &'static str| {
+        let (config, file, done, finished) = (config.clone(), file.clone(), done.clone(), finished.clone());
+        move || {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+            let result = runtime.block_on(dm_client::usb::apply_from_file(
+                &config,
+                &file.to_string_lossy(),
+                Some(version),
+                None,
+                move |_| {
+                    let _ = finished.lock().unwrap().recv_timeout(Duration::from_secs(5));
+                    Ok(())
+                },
+            ));
+            let _ = done.send(());
+            Ok::<_, anyhow::Error>(result)
+        }
+    };
+    let (first, second) = std::thread::scope(|scope| {
+        let first = scope.spawn(apply("1.0.0"));
+        let second = scope.spawn(apply("1.0.1"));
+        (first.join().unwrap(), second.join().unwrap())
+    });
+    let results = [first?, second?];
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1, "{:?}", results);
+    let error = results.iter().find_map(|r| r.as_ref().err()).context("one apply must fail")?;
+    let expected = format!("update already in progress (pid {})", std::process::id());
+    assert!(error.to_string().contains(&expected), "{}", error);
+    assert_eq!(
+        error.downcast_ref::<dm_client::error::ClientError>().map(|e| e.exit_code()),
+        Some(10)
+    );
+    assert_eq!(fs::read_to_string(Path::new(&config.service_dir).join("app.txt"))?, "v1");
+
+    // 죽은 프로세스가 남긴 잠금 파일(pid만 있고 flock 없음)은 다시 잡음
+    let lock = dm_client::lock::lock_path(Path::new(&config.service_dir));
+    fs::write(&lock, "999999\n")?;
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    fs::write(&file, artifact("v2"))?;
+    runtime.block_on(dm_client::usb::apply_from_file(
+        &config,
+        &file.to_string_lossy(),
+        Some("2.0.0"),
+        None,
+        |_| Ok(()),
+    ))?;
+    assert_eq!(fs::read_to_string(Path::new(&config.service_dir).join("app.txt"))?, "v2");
+    assert_eq!(fs::read_to_string(&lock)?, "", "lock file is emptied on release");
+    Ok(())
+}
+
+/// 설치된 버전보다 낮은 타겟은 배포의 allow_downgrade 없이는 설치하지 않음 (USB는 --allow-downgrade)
+#[tokio::test]
+async fn downgrades_are_refused_unless_allowed() -> Result<()> {
+    let Some(server) = TestServer::start().await? else {
+        return Ok(());
+    };
+    let client = server.register("e2e-downgrade").await?;
+    server.upload("1.0.0", artifact("v1")).await?;
+    server.install(&client, "1.1.0", artifact("v1.1")).await?;
+    assert_eq!(client.read(".dm-version").as_deref(), Some("1.1.0"));
+
+    // 실수로 낮은 버전을 배포하면 다운로드 전에 거부하고 보고
+    server.deploy(&client, "1.0.0").await?;
+    client.daemon.poll_once().await;
+    let logs = server.update_logs(&client).await?;
+    let log = logs.last().context("downgrade log")?;
+    assert_eq!((log.to_version.as_str(), log.status.as_str()), ("1.0.0", "failed"));
+    let error = log.error_message.as_deref().unwrap_or_default();
+    assert!(error.contains("Refusing to downgrade from 1.1.0 to 1.0.0"), "{}", error);
+    let reason: Option<String> = sqlx::query_scalar(
+        "SELECT failure_reason FROM update_logs WHERE client_id = $1 AND to_version = '1.0.0'",
+    )
+    .bind(client.id)
+    .fetch_one(&server.pool)
+    .await?;
+    assert_eq!(reason.as_deref(), Some("downgrade_refused"));
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1.1"));
+
+    // 거부된 배포는 취소되므로 의도한 되돌리기는 allow_downgrade로 다시 배포
+    let deployed: serde_json::Value = server
+        .http
+        .post(format!("{}/api/clients/{}/deploy", server.url, client.id))
+        .json(&serde_json::json!({ "version": "1.0.0", "allow_downgrade": true }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(deployed["allow_downgrade"], true);
+    client.daemon.poll_once().await;
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    assert_eq!(server.update_logs(&client).await?.last().map(|l| l.status.as_str()), Some("completed"));
+
+    // USB 적용도 같은 규칙 (DM_ALLOW_DOWNGRADE / --allow-downgrade로 허용)
+    let file = client.service_dir.with_file_name("old.tar.gz");
+    fs::write(&file, artifact("v0.9"))?;
+    let apply = |config: ClientConfig| {
+        let file = file.to_string_lossy().into_owned();
+        async move { dm_client::usb::apply_from_file(&config, &file, Some("0.9.0"), None, |_| Ok(())).await }
+    };
+    let error = apply(client.config.clone()).await.expect_err("USB downgrade must be refused");
+    assert!(error.downcast_ref::<dm_client::downgrade::DowngradeRefused>().is_some(), "{:#}", error);
+    assert_eq!(dm_client::error::ClientError::of(&error).map(|e| e.exit_code()), Some(11));
+    assert_eq!(client.read("app.txt").as_deref(), Some("v1"));
+    apply(ClientConfig {
+        allow_downgrade: true,
+        ..client.config.clone()
+    })
+    .await?;
+    assert_eq!(client.read(".dm-version").as_deref(), Some("0.9.0"));
+
+    server.stop().await
+}
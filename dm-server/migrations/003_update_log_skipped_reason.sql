@@ -0,0 +1,2 @@
+-- 업데이트 건너뜀 사유 (예: 동일한 아티팩트가 이미 설치됨)
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS skipped_reason VARCHAR(100);
@@ -0,0 +1,9 @@
+-- 버전 메타데이터 (빌드 출처 추적)
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS git_commit VARCHAR(64);
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS build_time TIMESTAMPTZ;
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS metadata JSONB NOT NULL DEFAULT '{}';
+
+-- metadata.<key>=<value> 필터링용
+CREATE INDEX IF NOT EXISTS idx_versions_metadata ON versions USING GIN (metadata);
+
+COMMENT ON COLUMN versions.metadata IS 'Free-form build metadata (branch, ci_run, labels, ...)';
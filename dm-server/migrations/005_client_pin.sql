@@ -0,0 +1,4 @@
+-- 클라이언트 버전 고정 (인증된 버전 유지가 필요한 사이트)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS pinned_version VARCHAR(50);
+
+COMMENT ON COLUMN clients.pinned_version IS 'Version the client is pinned to; other targets are blocked while set';
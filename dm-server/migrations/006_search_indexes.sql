@@ -0,0 +1,7 @@
+-- 검색용 trigram 인덱스 (GET /api/search, ILIKE '%q%')
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS idx_clients_name_trgm ON clients USING GIN (name gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_versions_version_trgm ON versions USING GIN (version gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_versions_release_notes_trgm ON versions USING GIN (release_notes gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_update_logs_error_message_trgm ON update_logs USING GIN (error_message gin_trgm_ops);
@@ -0,0 +1,8 @@
+-- 동일 API Key로 동작하는 다중 에이전트 감지
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS last_instance_id VARCHAR(64);
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS active_instance_id VARCHAR(64);
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS active_instance_started_at TIMESTAMPTZ;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS multiple_agents_at TIMESTAMPTZ;
+
+COMMENT ON COLUMN clients.active_instance_id IS 'Most recently started daemon instance; only it receives updates';
+COMMENT ON COLUMN clients.multiple_agents_at IS 'Last time two daemon instances were seen using the same API key';
@@ -0,0 +1,4 @@
+-- 2단계 배포: 스테이징 후 수동 활성화
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS target_staged BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN clients.target_staged IS 'Target version is staged only; the client waits for activation before swapping';
@@ -0,0 +1,6 @@
+-- 클라이언트가 실제로 적용한 설정 (env + 서버 설정 병합 결과)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS effective_config JSONB;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS effective_config_hash VARCHAR(64);
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS effective_config_at TIMESTAMPTZ;
+
+COMMENT ON COLUMN clients.effective_config IS 'Resolved config last reported by the client (secrets stripped client-side)';
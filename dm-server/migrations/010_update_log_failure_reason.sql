@@ -0,0 +1,2 @@
+-- 분류된 업데이트 실패 원인 (예: fs_read_only, disk_full, io_error)
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS failure_reason VARCHAR(32);
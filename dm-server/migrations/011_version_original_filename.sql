@@ -0,0 +1,11 @@
+-- 업로드된 원본 아티팩트 파일 이름 (Content-Disposition용)
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS original_filename TEXT;
+
+-- 기존 데이터: 확장자 처리 버그로 "<version>.gz"로 저장된 tar.gz 아티팩트
+UPDATE versions
+SET original_filename = version || '.tar.gz'
+WHERE original_filename IS NULL AND artifact_path = version || '.gz';
+
+UPDATE versions
+SET original_filename = artifact_path
+WHERE original_filename IS NULL;
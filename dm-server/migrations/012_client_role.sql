@@ -0,0 +1,4 @@
+-- 클라이언트 역할/그룹 (예: pos, kiosk), 체크인 시 클라이언트가 보고
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS role VARCHAR(64);
+
+CREATE INDEX IF NOT EXISTS idx_clients_role ON clients(role);
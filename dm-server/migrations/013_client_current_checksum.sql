@@ -0,0 +1,11 @@
+-- 설치된 아티팩트 체크섬 추적 (재태깅된 동일 빌드의 불필요한 재설치 방지)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS current_checksum VARCHAR(64);
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS target_force_reinstall BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE clients c
+SET current_checksum = v.checksum
+FROM versions v
+WHERE v.version = c.current_version AND c.current_checksum IS NULL;
+
+COMMENT ON COLUMN clients.current_checksum IS 'Checksum of the artifact currently installed on the client';
+COMMENT ON COLUMN clients.target_force_reinstall IS 'Reinstall the target even if its checksum matches the installed artifact';
@@ -0,0 +1,8 @@
+-- 배포 사유와 변경 요청(티켓) 번호: 대기 중인 배포와 업데이트 로그에 기록
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS target_reason TEXT;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS target_ticket VARCHAR(64);
+
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS reason TEXT;
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS ticket VARCHAR(64);
+
+CREATE INDEX IF NOT EXISTS idx_update_logs_ticket ON update_logs(ticket) WHERE ticket IS NOT NULL;
@@ -0,0 +1,16 @@
+-- 아티팩트 출처 추적: 업로드 → 서버 전송 → 장비 검증
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS uploaded_by VARCHAR(128);
+
+-- 장비가 설치 전에 직접 계산한 체크섬
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS verified_checksum VARCHAR(64);
+
+-- 아티팩트 다운로드 기록 (전송한 체크섬 헤더 포함)
+CREATE TABLE IF NOT EXISTS artifact_downloads (
+    id UUID PRIMARY KEY,
+    version VARCHAR(50) NOT NULL,
+    client_id UUID REFERENCES clients(id) ON DELETE SET NULL,
+    served_checksum VARCHAR(64) NOT NULL,
+    downloaded_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_artifact_downloads_version ON artifact_downloads(version, client_id, downloaded_at DESC);
@@ -0,0 +1,20 @@
+-- 실패 메시지 fingerprint (정규화된 메시지 해시)
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS failure_fingerprint VARCHAR(16);
+
+CREATE INDEX IF NOT EXISTS idx_update_logs_failure_fingerprint
+    ON update_logs(to_version, failure_fingerprint)
+    WHERE failure_fingerprint IS NOT NULL;
+
+-- 버전별 실패 유형 집계
+CREATE TABLE IF NOT EXISTS failure_signatures (
+    version VARCHAR(50) NOT NULL,
+    fingerprint VARCHAR(16) NOT NULL,
+    category VARCHAR(32) NOT NULL,
+    example_message TEXT NOT NULL,
+    count BIGINT NOT NULL DEFAULT 1,
+    first_seen TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (version, fingerprint)
+);
+
+CREATE INDEX IF NOT EXISTS idx_failure_signatures_last_seen ON failure_signatures(last_seen DESC);
@@ -0,0 +1,5 @@
+-- 클라이언트 설치 상태(.dm-version 등)가 dm-client 밖에서 수정된 시각 (정상 보고 시 NULL)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS state_tampered_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_clients_state_tampered ON clients(state_tampered_at)
+    WHERE state_tampered_at IS NOT NULL;
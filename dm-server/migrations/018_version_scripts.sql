@@ -0,0 +1,3 @@
+-- 버전별 설치 스크립트 (클라이언트가 DM_ALLOW_REMOTE_SCRIPTS=true일 때만 실행)
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS pre_install_script TEXT;
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS post_install_script TEXT;
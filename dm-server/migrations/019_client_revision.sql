@@ -0,0 +1,3 @@
+-- 관리자가 변경하는 클라이언트 설정(config, 버전 고정)의 리비전
+-- 체크인마다 갱신되는 updated_at 대신 낙관적 동시성 검사(If-Match / expected_revision)에 사용
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS revision BIGINT NOT NULL DEFAULT 0;
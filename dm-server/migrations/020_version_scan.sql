@@ -0,0 +1,5 @@
+-- 업로드된 아티팩트 검사 결과 (SCAN_COMMAND). clean이 아니면 배포 불가
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS scan_status TEXT NOT NULL DEFAULT 'clean';
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS scan_output TEXT;
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS scan_duration_ms BIGINT;
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS scanned_at TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+-- 아티팩트를 받은 곳 (서버 또는 미러 URL)
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS artifact_source TEXT;
@@ -0,0 +1,2 @@
+-- 배포 유형: app(애플리케이션 아카이브) / image(A/B 슬롯에 쓰는 디스크 이미지)
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS deploy_type TEXT NOT NULL DEFAULT 'app';
@@ -0,0 +1,11 @@
+-- 클라이언트 설정 변경 이력 (변경 직전의 설정을 기록, 롤백에 사용)
+CREATE TABLE IF NOT EXISTS client_config_history (
+    id UUID PRIMARY KEY,
+    client_id UUID NOT NULL REFERENCES clients(id) ON DELETE CASCADE,
+    config JSONB NOT NULL,
+    changed_by TEXT,
+    changed_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_client_config_history_client
+    ON client_config_history (client_id, changed_at DESC);
@@ -0,0 +1,21 @@
+-- 업데이트 단계별 시각 (SLA 보고용, 클라이언트가 결과 보고에 포함)
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS offered_at TIMESTAMPTZ;
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS download_started_at TIMESTAMPTZ;
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS download_completed_at TIMESTAMPTZ;
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS install_started_at TIMESTAMPTZ;
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS restarted_at TIMESTAMPTZ;
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS healthy_at TIMESTAMPTZ;
+
+-- 기존 로그는 생성 시각이 제공 시각
+UPDATE update_logs SET offered_at = started_at WHERE offered_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_update_logs_offered_at ON update_logs (offered_at);
+
+-- 클라이언트가 체크인한 시간대 (1시간 단위, SLA 보고에서 대부분 오프라인이던 클라이언트 제외)
+CREATE TABLE IF NOT EXISTS client_presence (
+    client_id UUID NOT NULL REFERENCES clients(id) ON DELETE CASCADE,
+    hour TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (client_id, hour)
+);
+
+CREATE INDEX IF NOT EXISTS idx_client_presence_hour ON client_presence (hour);
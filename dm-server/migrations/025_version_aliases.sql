@@ -0,0 +1,24 @@
+-- 버전 별칭 (예: "lts" -> 1.18.7), 배포/고정/번들에서 "alias:<이름>"으로 사용
+CREATE TABLE IF NOT EXISTS version_aliases (
+    name TEXT PRIMARY KEY,
+    version TEXT NOT NULL,
+    moved_at TIMESTAMPTZ NOT NULL,
+    moved_by TEXT,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+-- 별칭 이동 기록 (생성 시 from_version은 NULL)
+CREATE TABLE IF NOT EXISTS version_alias_moves (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    from_version TEXT,
+    to_version TEXT NOT NULL,
+    moved_by TEXT,
+    moved_at TIMESTAMPTZ NOT NULL,
+    tracking_clients BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_version_alias_moves_name ON version_alias_moves (name, moved_at DESC);
+
+-- 별칭을 따라가는 클라이언트 (별칭이 옮겨지면 다음 체크인에서 새 버전으로 재지정)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS target_alias TEXT;
@@ -0,0 +1,5 @@
+-- 타겟 버전이 지정된 시각 (장비에서 GET /api/clients/self로 조회)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS target_set_at TIMESTAMPTZ;
+
+UPDATE clients SET target_set_at = updated_at
+WHERE target_version IS NOT NULL AND target_set_at IS NULL;
@@ -0,0 +1,3 @@
+-- 긴 변경 이력 파일 여부 (artifact_dir/<version>.changelog.md, GET /api/versions/:version/changelog)
+-- release_notes는 목록 응답에 실리는 요약 (4KB 이하, 한도를 넘는 기존 노트는 서버 시작 시 파일로 이동)
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS has_changelog BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+-- 아티팩트가 대상으로 하는 제품 (업로드 시 선언, 배포 시 클라이언트 제품과 비교)
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS product TEXT;
+
+-- 클라이언트가 보고한 제품 (DM_EXPECTED_PRODUCT 또는 서버 설정의 product)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS product TEXT;
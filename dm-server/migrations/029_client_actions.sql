@@ -0,0 +1,35 @@
+-- 클라이언트별 작업 대기열 (배포/재시작/롤백을 순서대로 하나씩 전달)
+--
+-- state: pending(대기) → delivered(전달됨, 결과 대기) → completed/failed/cancelled/expired
+-- 배포 작업은 전달되는 동안 clients.target_version에 그대로 반영된다.
+CREATE TABLE IF NOT EXISTS client_actions (
+    id UUID PRIMARY KEY,
+    client_id UUID NOT NULL REFERENCES clients(id) ON DELETE CASCADE,
+    action_type TEXT NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}',
+    state TEXT NOT NULL DEFAULT 'pending',
+    error_message TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    delivered_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_client_actions_open
+    ON client_actions (client_id, created_at)
+    WHERE state IN ('pending', 'delivered');
+
+-- 기존 타겟 버전은 전달된 배포 작업으로 옮김
+INSERT INTO client_actions (id, client_id, action_type, payload, state, created_at, delivered_at)
+SELECT gen_random_uuid(), c.id, 'deploy',
+       jsonb_build_object(
+           'version', c.target_version,
+           'staged', c.target_staged,
+           'force_reinstall', c.target_force_reinstall,
+           'reason', c.target_reason,
+           'ticket', c.target_ticket,
+           'alias', c.target_alias
+       ),
+       'delivered', COALESCE(c.target_set_at, c.updated_at), COALESCE(c.target_set_at, c.updated_at)
+FROM clients c
+WHERE c.target_version IS NOT NULL
+  AND NOT EXISTS (SELECT 1 FROM client_actions a WHERE a.client_id = c.id);
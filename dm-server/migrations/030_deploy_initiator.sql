@@ -0,0 +1,7 @@
+-- 배포를 시작한 주체: 대기 중인 배포와 업데이트 로그에 기록
+-- (운영자 이름 또는 "alias:<이름>"처럼 자동 재지정의 출처)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS target_initiated_by TEXT;
+
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS initiated_by TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_update_logs_initiated_by ON update_logs(initiated_by) WHERE initiated_by IS NOT NULL;
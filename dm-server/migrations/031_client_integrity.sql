@@ -0,0 +1,10 @@
+-- 설치 아티팩트 무결성 감사: 장비가 체크인마다 보고한 설치 체크섬과 버전 체크섬 비교
+-- integrity_ok: TRUE(일치), FALSE(불일치), NULL(장비가 체크섬을 모르거나 서버에 없는 버전)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS reported_checksum VARCHAR(64);
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS integrity_ok BOOLEAN;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS integrity_checked_at TIMESTAMPTZ;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS integrity_ok_at TIMESTAMPTZ;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS integrity_mismatch_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_clients_integrity_mismatch ON clients(integrity_mismatch_at)
+    WHERE integrity_ok = FALSE;
@@ -0,0 +1,25 @@
+-- 일회성 공유 링크 (서버 자격 증명 없는 외부 설치 기사가 특정 버전 아티팩트를 받는 URL)
+CREATE TABLE IF NOT EXISTS share_links (
+    token VARCHAR(64) PRIMARY KEY,
+    version VARCHAR(50) NOT NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    max_downloads INTEGER NOT NULL,
+    download_count INTEGER NOT NULL DEFAULT 0,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_share_links_version ON share_links(version, created_at DESC);
+
+-- 공유 링크 다운로드 기록 (감사용)
+CREATE TABLE IF NOT EXISTS share_downloads (
+    id UUID PRIMARY KEY,
+    token VARCHAR(64) NOT NULL REFERENCES share_links(token) ON DELETE CASCADE,
+    bundle BOOLEAN NOT NULL DEFAULT FALSE,
+    remote_addr VARCHAR(64),
+    user_agent TEXT,
+    downloaded_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_share_downloads_token ON share_downloads(token, downloaded_at);
@@ -0,0 +1,2 @@
+-- 버전 심각도: normal(일반) / security(보안 수정, 클라이언트에 긴급 전달)
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS severity TEXT NOT NULL DEFAULT 'normal';
@@ -0,0 +1,15 @@
+-- 장비 현황 조사 결과 (POST /api/clients/:id/survey로 요청한 survey 작업의 결과)
+--
+-- results: 수집 항목별 결과, errors: 수집하지 못한 항목과 사유
+CREATE TABLE IF NOT EXISTS client_surveys (
+    id UUID PRIMARY KEY,
+    client_id UUID NOT NULL REFERENCES clients(id) ON DELETE CASCADE,
+    action_id UUID NOT NULL UNIQUE REFERENCES client_actions(id) ON DELETE CASCADE,
+    commands TEXT[] NOT NULL,
+    results JSONB NOT NULL DEFAULT '{}',
+    errors JSONB NOT NULL DEFAULT '{}',
+    size_bytes INTEGER NOT NULL,
+    collected_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_client_surveys_client ON client_surveys (client_id, collected_at DESC);
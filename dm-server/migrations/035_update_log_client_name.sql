@@ -0,0 +1,22 @@
+-- 업데이트 로그에 기록 당시 클라이언트 이름 보존
+--
+-- 클라이언트를 삭제해도 로그는 남고 (client_id는 NULL), 이름으로 계속 조회/정리할 수 있다.
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS client_name TEXT;
+
+UPDATE update_logs l
+SET client_name = c.name
+FROM clients c
+WHERE c.id = l.client_id AND l.client_name IS NULL;
+
+ALTER TABLE update_logs ALTER COLUMN client_name SET NOT NULL;
+
+-- 클라이언트 삭제 시 로그 삭제(CASCADE) 대신 연결만 해제
+ALTER TABLE update_logs ALTER COLUMN client_id DROP NOT NULL;
+ALTER TABLE update_logs DROP CONSTRAINT IF EXISTS update_logs_client_id_fkey;
+ALTER TABLE update_logs
+    ADD CONSTRAINT update_logs_client_id_fkey
+    FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE SET NULL;
+
+-- 삭제된 클라이언트의 로그를 이름으로 정리할 때 사용
+CREATE INDEX IF NOT EXISTS idx_update_logs_deleted_client_name
+    ON update_logs (client_name) WHERE client_id IS NULL;
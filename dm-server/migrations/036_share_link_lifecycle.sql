@@ -0,0 +1,19 @@
+-- 공유 링크 만료 임박 알림과 연장 기록
+--
+-- expiry_warned_at: share.expiring 웹훅을 보낸 시각 (연장하면 초기화되어 다시 알림)
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS expiry_warned_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_share_links_expiry_pending ON share_links (expires_at)
+    WHERE revoked_at IS NULL AND expiry_warned_at IS NULL;
+
+-- 공유 링크 연장 기록 (감사용)
+CREATE TABLE IF NOT EXISTS share_link_renewals (
+    id UUID PRIMARY KEY,
+    token VARCHAR(64) NOT NULL REFERENCES share_links(token) ON DELETE CASCADE,
+    previous_expires_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    renewed_by VARCHAR(255) NOT NULL,
+    renewed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_share_link_renewals_token ON share_link_renewals (token, renewed_at);
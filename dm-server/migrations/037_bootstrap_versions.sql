@@ -0,0 +1,13 @@
+-- 역할별 첫 설치(bootstrap) 버전
+--
+-- 버전이 없는 새 장비가 타겟 없이 체크인하면 역할(없으면 "*")의 버전으로 배포를 대기열에 추가한다.
+-- 버전을 삭제하면 함께 해제된다.
+CREATE TABLE IF NOT EXISTS bootstrap_versions (
+    role TEXT PRIMARY KEY,
+    version VARCHAR(50) NOT NULL REFERENCES versions(version) ON DELETE CASCADE,
+    set_by TEXT,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+-- 빈 장비에 백업/롤백 없이 설치한 업데이트 (클라이언트가 결과 보고에 bootstrap=true로 알림)
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS bootstrap BOOLEAN NOT NULL DEFAULT FALSE;
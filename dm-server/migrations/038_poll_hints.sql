@@ -0,0 +1,14 @@
+-- 서버 전체 설정 (한 행, 모든 서버 인스턴스가 공유)
+--
+-- poll_hint_*: 장애 중 전체 장비의 다음 폴링 간격을 늘리는 임시 힌트 (만료되면 무시)
+CREATE TABLE IF NOT EXISTS server_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    poll_hint_secs INTEGER,
+    poll_hint_expires_at TIMESTAMPTZ,
+    poll_hint_reason TEXT,
+    poll_hint_set_by TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- 클라이언트가 보고한 직전 폴링 대기 시간 (초, 실제로 기다린 시간이라 힌트/백오프가 반영됨)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS poll_interval_secs INTEGER;
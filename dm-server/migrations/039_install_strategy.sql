@@ -0,0 +1,2 @@
+-- 장비가 추출한 트리를 설치한 방법 (rename: 같은 파일시스템, copy: 다른 파일시스템)
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS install_strategy TEXT;
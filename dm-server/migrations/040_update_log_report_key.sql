@@ -0,0 +1,4 @@
+-- 결과 보고의 Idempotency-Key (재전송된 보고가 로그를 다시 만들거나 바꾸지 않도록)
+ALTER TABLE update_logs ADD COLUMN IF NOT EXISTS report_key TEXT;
+CREATE INDEX IF NOT EXISTS idx_update_logs_report_key ON update_logs(client_id, report_key)
+    WHERE report_key IS NOT NULL;
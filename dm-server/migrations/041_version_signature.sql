@@ -0,0 +1,2 @@
+-- 아티팩트 ed25519 서명 (base64, 업로드 시 선택). 체크인 응답과 다운로드 X-Signature 헤더로 전달
+ALTER TABLE versions ADD COLUMN IF NOT EXISTS signature TEXT;
@@ -0,0 +1,4 @@
+-- 설치된 버전보다 낮은 타겟 허용 (배포의 allow_downgrade, 체크인 응답으로 전달)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS target_allow_downgrade BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN clients.target_allow_downgrade IS 'Let the client install the target even if it is older than the installed version';
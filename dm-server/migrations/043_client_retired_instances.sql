@@ -0,0 +1,4 @@
+-- 다른 인스턴스로 바뀐 이전 인스턴스 (다시 체크인하면 다중 에이전트)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS retired_instance_ids TEXT[] NOT NULL DEFAULT '{}';
+
+COMMENT ON COLUMN clients.retired_instance_ids IS 'Instances the server saw replaced by a newer one; a check-in from one of them means two daemons are running';
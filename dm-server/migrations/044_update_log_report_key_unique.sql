@@ -0,0 +1,13 @@
+-- 결과 보고의 Idempotency-Key는 클라이언트마다 한 로그에만 (동시에 재전송된 보고가 둘 다 기록되지 않도록)
+UPDATE update_logs SET report_key = NULL
+WHERE id IN (
+    SELECT id FROM (
+        SELECT id, ROW_NUMBER() OVER (PARTITION BY client_id, report_key ORDER BY started_at, id) AS n
+        FROM update_logs
+        WHERE report_key IS NOT NULL
+    ) ranked
+    WHERE n > 1
+);
+DROP INDEX IF EXISTS idx_update_logs_report_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_update_logs_report_key ON update_logs(client_id, report_key)
+    WHERE report_key IS NOT NULL;
@@ -0,0 +1,300 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::db::{self, Client, ClientAction, ClientActionQuery, QueueActionRequest};
+use crate::AppState;
+
+/// 운영자가 직접 추가하는 작업 (배포는 POST /api/clients/:id/deploy, 조사는 POST /api/clients/:id/survey로 추가)
+const COMMAND_ACTIONS: [&str; 2] = ["restart", "rollback"];
+
+/// 요청자를 밝히지 않은 API 호출의 시작 주체
+pub(crate) const DEFAULT_INITIATOR: &str = "api";
+
+/// 클라이언트 작업 대기열 (전달 순서)
+/// GET /api/clients/:id/actions
+/// Query: all=true (끝난 작업도 포함)
+pub async fn list_client_actions(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ClientActionQuery>,
+) -> Result<Json<Vec<ClientAction>>, (StatusCode, String)> {
+    db::get_client_by_id(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+
+    let actions = db::list_client_actions(&state.pool, id, query.all)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(actions))
+}
+
+/// 재시작/롤백 작업 추가 (앞선 작업이 끝난 뒤 체크인에서 전달)
+/// POST /api/clients/:id/actions
+/// Body: {"type": "restart" | "rollback", "reason": "...", "initiated_by": "..."}
+pub async fn queue_client_action(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<QueueActionRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let mut client = db::get_client_by_id(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+
+    if !COMMAND_ACTIONS.contains(&req.action_type.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Unsupported action type {:?} (expected restart or rollback; deploys use POST /api/clients/:id/deploy, surveys POST /api/clients/:id/survey)",
+                req.action_type
+            ),
+        ));
+    }
+
+    let initiated_by = req.initiated_by.as_deref().unwrap_or(DEFAULT_INITIATOR);
+    let payload = serde_json::json!({ "reason": req.reason, "initiated_by": initiated_by });
+    let action = db::create_client_action(&state.pool, id, &req.action_type, &payload)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    advance(&state, &mut client).await?;
+    let position = queue_position(&state, id, action.id).await?;
+
+    tracing::info!(
+        "Client {} ({}): {} queued at position {}",
+        client.name,
+        id,
+        action.action_type,
+        position
+    );
+
+    Ok(Json(serde_json::json!({
+        "message": "Action queued",
+        "client_id": id,
+        "action_id": action.id,
+        "type": action.action_type,
+        "queue_position": position,
+        "reason": req.reason,
+        "initiated_by": initiated_by
+    })))
+}
+
+/// 대기열의 작업 취소
+/// DELETE /api/clients/:id/actions/:action_id
+///
+/// 전달된 배포는 DELETE /api/clients/:id/deploy와 같이 타겟을 해제하고 다음 작업으로 넘어간다.
+/// 이미 전달된 재시작/롤백은 장비가 실행 중일 수 있으므로 결과 보고나 만료를 기다린다 (409).
+pub async fn cancel_client_action(
+    State(state): State<AppState>,
+    Path((id, action_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let mut client = db::get_client_by_id(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+    let action = db::get_client_action(&state.pool, id, action_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Action not found".to_string()))?;
+
+    if !action.is_open() {
+        return Err((StatusCode::CONFLICT, format!("Action is already {}", action.state)));
+    }
+    let in_flight_deploy = action.action_type == "deploy" && action.state == "delivered";
+    if action.state == "delivered" && !in_flight_deploy {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "{} was already delivered; wait for the result or the {}s timeout",
+                action.action_type, state.config.action_timeout_secs
+            ),
+        ));
+    }
+
+    let message = "Cancelled by operator";
+    if in_flight_deploy {
+        clear_deploy(&state, &mut client, message).await?;
+    }
+    db::finish_client_action(&state.pool, action.id, "cancelled", Some(message))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    advance(&state, &mut client).await?;
+
+    tracing::info!("Client {} ({}): {} action {} cancelled", client.name, id, action.action_type, action.id);
+
+    Ok(Json(serde_json::json!({
+        "message": "Action cancelled",
+        "client_id": id,
+        "action_id": action.id,
+        "type": action.action_type,
+        "target_version": client.target_version
+    })))
+}
+
+/// 대기열에서의 위치 (0이면 전달되었거나 다음 체크인에서 전달)
+pub(crate) async fn queue_position(
+    state: &AppState,
+    client_id: Uuid,
+    action_id: Uuid,
+) -> Result<usize, (StatusCode, String)> {
+    let open = db::list_client_actions(&state.pool, client_id, false)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(open.iter().position(|a| a.id == action_id).unwrap_or_default())
+}
+
+/// 대기열 진행 후 맨 앞 작업 반환
+///
+/// 맨 앞의 배포는 타겟 버전으로 옮겨 기존 체크인 흐름으로 전달하고, 타겟이 다른 경로로 해제된
+/// 배포는 닫는다. 실패가 보고된 배포나 결과 없이 ACTION_TIMEOUT_SECS가 지난 작업은 뒤에 기다리는
+/// 작업이 있으면 닫고 다음 작업으로 넘어간다. 뒤에 작업이 없는 배포는 지금처럼 계속 재시도하고,
+/// 스테이징된 배포는 활성화를 기다린다.
+pub(crate) async fn advance(
+    state: &AppState,
+    client: &mut Client,
+) -> Result<Option<ClientAction>, (StatusCode, String)> {
+    let timeout_secs = state.config.action_timeout_secs;
+    loop {
+        let Some((head, waiting)) = db::head_client_action(&state.pool, client.id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        else {
+            return Ok(None);
+        };
+        let timed_out = head
+            .delivered_at
+            .is_some_and(|t| Utc::now() - t > Duration::seconds(timeout_secs as i64));
+        let expired = format!("No result within {}s; moved on to the next queued action", timeout_secs);
+
+        if head.action_type != "deploy" {
+            if !timed_out {
+                return Ok(Some(head));
+            }
+            close(state, client, &head, "expired", &expired).await?;
+            continue;
+        }
+
+        let Some(deploy) = head.deploy() else {
+            close(state, client, &head, "failed", "Invalid deploy payload").await?;
+            continue;
+        };
+
+        if head.state == "pending" {
+            db::set_client_target_version(&state.pool, client.id, &deploy)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            db::mark_client_action_delivered(&state.pool, head.id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            tracing::info!(
+                "Client {} ({}): deploy of {} is next in queue ({} waiting)",
+                client.name,
+                client.id,
+                deploy.version,
+                waiting
+            );
+
+            client.target_version = Some(deploy.version);
+            client.target_staged = deploy.staged;
+            client.target_force_reinstall = deploy.force_reinstall;
+            client.target_allow_downgrade = deploy.allow_downgrade;
+            client.target_reason = deploy.reason;
+            client.target_ticket = deploy.ticket;
+            client.target_alias = deploy.alias;
+            client.target_initiated_by = deploy.initiated_by;
+            client.target_set_at = Some(Utc::now());
+            return Ok(Some(head));
+        }
+
+        // 버전 삭제 등으로 타겟이 해제된 배포
+        if client.target_version.is_none() {
+            close(state, client, &head, "cancelled", "Target cleared").await?;
+            continue;
+        }
+        if waiting == 0 || client.target_staged {
+            return Ok(Some(head));
+        }
+        // 실패 후 재시도 중이 아니면 (진행 중인 업데이트 로그 없음) 다음 작업으로
+        if let Some(error) = head.error_message.as_deref() {
+            let target = client.target_version.clone().unwrap_or_default();
+            let retrying = db::get_pending_update_log(&state.pool, client.id, &target)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .is_some();
+            if !retrying {
+                let message = format!("Not retried; next queued action runs instead ({})", error);
+                clear_deploy(state, client, &message).await?;
+                close(state, client, &head, "failed", error).await?;
+                continue;
+            }
+        }
+        if timed_out {
+            clear_deploy(state, client, &expired).await?;
+            close(state, client, &head, "expired", &expired).await?;
+            continue;
+        }
+        return Ok(Some(head));
+    }
+}
+
+/// 작업 종료 기록
+async fn close(
+    state: &AppState,
+    client: &Client,
+    action: &ClientAction,
+    action_state: &str,
+    message: &str,
+) -> Result<(), (StatusCode, String)> {
+    db::finish_client_action(&state.pool, action.id, action_state, Some(message))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    tracing::warn!(
+        "Client {} ({}): {} action {} {}: {}",
+        client.name,
+        client.id,
+        action.action_type,
+        action.id,
+        action_state,
+        message
+    );
+    Ok(())
+}
+
+/// 진행 중인 배포 해제 (타겟 클리어, 진행 중인 업데이트 로그는 취소로 종료)
+pub(crate) async fn clear_deploy(
+    state: &AppState,
+    client: &mut Client,
+    message: &str,
+) -> Result<(), (StatusCode, String)> {
+    let Some(target_version) = client.target_version.take() else {
+        return Ok(());
+    };
+
+    db::clear_client_target_version(&state.pool, client.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if let Some(log) = db::get_pending_update_log(&state.pool, client.id, &target_version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        db::update_log_status(&state.pool, log.id, "cancelled", Some(message))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    client.target_staged = false;
+    client.target_force_reinstall = false;
+    client.target_allow_downgrade = false;
+    client.target_reason = None;
+    client.target_ticket = None;
+    client.target_alias = None;
+    client.target_initiated_by = None;
+    client.target_set_at = None;
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::StatusCode,
+    response::Response,
+    Json,
+};
+use std::path::PathBuf;
+
+use super::artifacts::gzip_attachment;
+use crate::archive::{self, ExportOptions};
+use crate::db::{self, PurgeClientHistoryRequest, PurgeClientHistoryResponse};
+use crate::AppState;
+
+/// 서버 상태 내보내기 (tar.gz 스트리밍)
+/// GET /api/admin/export?include_secrets=&include_update_logs=
+pub async fn export_state(
+    State(state): State<AppState>,
+    Query(opts): Query<ExportOptions>,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let data = archive::collect(&state.pool, &state.config, &opts)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!(
+        "Exporting state: {} clients, {} versions, {} update logs (secrets: {})",
+        data.manifest.counts.clients,
+        data.manifest.counts.versions,
+        data.manifest.counts.update_logs,
+        opts.include_secrets
+    );
+
+    let filename = format!(
+        "sam-dm-export_{}.tar.gz",
+        data.manifest.exported_at.format("%Y%m%dT%H%M%SZ")
+    );
+
+    // 아카이브는 blocking으로 작성하고 파이프로 스트리밍
+    let artifact_dir = PathBuf::from(&state.config.artifact_dir);
+    gzip_attachment(&filename, move |writer| {
+        archive::write_archive(&data, &artifact_dir, writer).map(|_| ())
+    })
+}
+
+/// 클라이언트의 업데이트 로그 영구 삭제 (개인정보 삭제 요청 등 이력을 남길 수 없을 때)
+/// POST /api/admin/purge-client-history
+/// Body: {"client_id": "<uuid>"} (현재 클라이언트, 다운로드 기록 포함)
+///    또는 {"client_name": "store-01"} (이미 삭제된 클라이언트의 로그)
+pub async fn purge_client_history(
+    State(state): State<AppState>,
+    Json(req): Json<PurgeClientHistoryRequest>,
+) -> Result<Json<PurgeClientHistoryResponse>, (StatusCode, String)> {
+    let purged = match (req.client_id, req.client_name.as_deref()) {
+        (Some(id), None) => {
+            db::get_client_by_id(&state.pool, id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+            db::purge_client_history(&state.pool, id).await
+        }
+        (None, Some(name)) if !name.trim().is_empty() => {
+            db::purge_deleted_client_history(&state.pool, name).await
+        }
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Exactly one of client_id or client_name is required".to_string(),
+            ))
+        }
+    }
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::warn!(
+        "Purged history of client {}: {} update logs, {} artifact downloads",
+        req.client_id.map(|id| id.to_string()).or(req.client_name).unwrap_or_default(),
+        purged.update_logs,
+        purged.artifact_downloads
+    );
+    Ok(Json(purged))
+}
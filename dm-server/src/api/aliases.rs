@@ -0,0 +1,161 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+
+use crate::db::{self, SetAliasRequest, VersionAlias, VersionAliasDetail};
+use crate::scan;
+use crate::AppState;
+
+/// 버전 문자열에서 별칭을 가리키는 접두사 (예: "alias:lts")
+const ALIAS_PREFIX: &str = "alias:";
+
+const MAX_ALIAS_LEN: usize = 64;
+const MAX_MOVED_BY_LEN: usize = 128;
+
+/// 버전 문자열을 실제 버전으로 해석
+///
+/// "alias:<name>"이면 호출 시점에 별칭이 가리키는 버전과 별칭 이름을, 아니면 그대로 반환
+pub(crate) async fn resolve_version(
+    state: &AppState,
+    version: &str,
+) -> Result<(String, Option<String>), (StatusCode, String)> {
+    let Some(name) = version.strip_prefix(ALIAS_PREFIX) else {
+        return Ok((version.to_string(), None));
+    };
+    let alias = db::get_version_alias(&state.pool, name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, format!("Alias {} not found", name)))?;
+    Ok((alias.version, Some(alias.name)))
+}
+
+fn validate_name(name: &str) -> Result<(), (StatusCode, String)> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_ALIAS_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-._".contains(c));
+    if !valid {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "alias name must be 1-{} characters of a-z, 0-9, '-', '.', '_'",
+                MAX_ALIAS_LEN
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// 버전 별칭 목록
+/// GET /api/aliases
+pub async fn list_aliases(State(state): State<AppState>) -> Result<Json<Vec<VersionAlias>>, (StatusCode, String)> {
+    let aliases = db::list_version_aliases(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(aliases))
+}
+
+/// 버전 별칭 상세 (이동 기록 포함)
+/// GET /api/aliases/:name
+pub async fn get_alias(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<VersionAliasDetail>, (StatusCode, String)> {
+    let alias = db::get_version_alias(&state.pool, &name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Alias not found".to_string()))?;
+    let moves = db::list_version_alias_moves(&state.pool, &name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(VersionAliasDetail { alias, moves }))
+}
+
+/// 버전 별칭 생성 또는 이동
+/// PUT /api/aliases/:name
+/// Body: {"version": "1.18.7", "moved_by": "ops"}
+///
+/// 별칭을 따라가는 클라이언트는 다음 체크인에서 새 버전으로 재지정된다.
+pub async fn set_alias(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<SetAliasRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    validate_name(&name)?;
+    if req.moved_by.as_ref().is_some_and(|m| m.len() > MAX_MOVED_BY_LEN) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("moved_by must be at most {} characters", MAX_MOVED_BY_LEN),
+        ));
+    }
+
+    let version = db::get_version(&state.pool, &req.version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+    scan::ensure_deployable(&version)?;
+
+    let (from_version, tracking_clients) =
+        db::set_version_alias(&state.pool, &name, &req.version, req.moved_by.as_deref())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!(
+        "Alias {} moved from {} to {} ({} tracking clients)",
+        name,
+        from_version.as_deref().unwrap_or("none"),
+        req.version,
+        tracking_clients
+    );
+    state.webhook.emit(
+        "alias.moved",
+        serde_json::json!({
+            "name": name,
+            "from_version": from_version,
+            "to_version": req.version,
+            "moved_by": req.moved_by,
+            "tracking_clients": tracking_clients,
+        }),
+    );
+
+    Ok(Json(serde_json::json!({
+        "message": if from_version.is_some() { "Alias moved" } else { "Alias created" },
+        "name": name,
+        "from_version": from_version,
+        "version": req.version,
+        "tracking_clients": tracking_clients
+    })))
+}
+
+/// 버전 별칭 삭제 (따라가는 클라이언트가 있으면 409)
+/// DELETE /api/aliases/:name
+pub async fn delete_alias(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let alias = db::get_version_alias(&state.pool, &name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Alias not found".to_string()))?;
+    if alias.tracking_clients > 0 {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "{} clients track alias {}; redeploy them to a version first",
+                alias.tracking_clients, name
+            ),
+        ));
+    }
+
+    db::delete_version_alias(&state.pool, &name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Alias deleted",
+        "name": name
+    })))
+}
@@ -1,50 +1,418 @@
 use axum::{
     body::Body,
-    extract::{Path, State},
-    http::{header, StatusCode},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
     response::Response,
 };
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use tokio::fs::File;
-use tokio_util::io::ReaderStream;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, DuplexStream};
+use tokio_util::io::{ReaderStream, SyncIoBridge};
+use uuid::Uuid;
 
-use crate::db;
+use crate::api::polling;
+use crate::config::Config;
+use crate::db::{self, Version};
 use crate::AppState;
 
+/// 만료된 서명 URL 거부 메시지 (클라이언트는 이 문구로 재체크인 여부를 판단)
+const EXPIRED_MESSAGE: &str = "Artifact URL expired";
+
+/// 서명된 아티팩트 URL 쿼리
+#[derive(Debug, Deserialize)]
+pub struct ArtifactTokenQuery {
+    pub client: Option<Uuid>,
+    pub exp: Option<i64>,
+    pub token: Option<String>,
+}
+
+/// 체크인 응답에 넣을 아티팩트 URL (`prefix`는 외부 경로 접두사, `PublicPrefix`)
+///
+/// SIGNED_ARTIFACT_URLS=true이면 버전+클라이언트+만료 시각에 대한 HMAC 토큰을 붙여
+/// X-API-Key 없이 (CDN 등을 거쳐) 받을 수 있게 한다.
+pub(crate) fn artifact_url(config: &Config, prefix: &str, version: &str, client_id: Uuid) -> String {
+    let path = format!("{}/api/artifacts/{}", prefix, version);
+    if !config.signed_artifact_urls {
+        return path;
+    }
+
+    let exp = chrono::Utc::now().timestamp() + config.artifact_url_ttl_secs as i64;
+    let token = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .encode(token_mac(config, version, client_id, exp).finalize().into_bytes());
+    format!("{}?client={}&exp={}&token={}", path, client_id, exp, token)
+}
+
+fn token_mac(config: &Config, version: &str, client_id: Uuid, exp: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&config.artifact_url_key)
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}\n{}\n{}", version, client_id, exp).as_bytes());
+    mac
+}
+
+/// 서명 토큰 검증. 토큰에 담긴 클라이언트 ID 반환 (만료/불일치 시 403)
+fn verify_token(
+    config: &Config,
+    version: &str,
+    query: &ArtifactTokenQuery,
+) -> Result<Uuid, (StatusCode, String)> {
+    let invalid = || (StatusCode::FORBIDDEN, "Invalid artifact token".to_string());
+    let (Some(client_id), Some(exp), Some(token)) = (query.client, query.exp, &query.token) else {
+        return Err(invalid());
+    };
+    let signature = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(token)
+        .map_err(|_| invalid())?;
+    token_mac(config, version, client_id, exp)
+        .verify_slice(&signature)
+        .map_err(|_| invalid())?;
+
+    if chrono::Utc::now().timestamp() > exp {
+        return Err((StatusCode::FORBIDDEN, EXPIRED_MESSAGE.to_string()));
+    }
+    Ok(client_id)
+}
+
+/// 아티팩트 다운로드 인증 (다운로드를 기록할 클라이언트 ID 반환, 운영자 토큰이면 None)
+///
+/// 서명된 URL은 토큰을 검증하고(만료되었거나 다른 버전/클라이언트의 토큰이면 403), 그 밖에는
+/// `X-API-Key`의 클라이언트나 ADMIN_TOKEN(`X-Admin-Token` 또는 `Authorization: Bearer`)이 필요하다.
+async fn authorize_download(
+    state: &AppState,
+    version: &str,
+    query: &ArtifactTokenQuery,
+    headers: &HeaderMap,
+) -> Result<Option<Uuid>, (StatusCode, String)> {
+    if query.token.is_some() {
+        return verify_token(&state.config, version, query).map(Some);
+    }
+    if let Some(api_key) = polling::extract_api_key(headers) {
+        let client = db::get_client_by_api_key(&state.pool, &api_key)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .ok_or((StatusCode::UNAUTHORIZED, "Invalid API key".to_string()))?;
+        return Ok(Some(client.id));
+    }
+    if is_admin(&state.config, headers) {
+        return Ok(None);
+    }
+    Err((
+        StatusCode::UNAUTHORIZED,
+        "X-API-Key header, signed artifact URL or admin token required".to_string(),
+    ))
+}
+
+/// 요청에 ADMIN_TOKEN이 있는지 (해시끼리 비교해 토큰 내용이 비교 시간으로 드러나지 않게)
+fn is_admin(config: &Config, headers: &HeaderMap) -> bool {
+    let Some(expected) = &config.admin_token else {
+        return false;
+    };
+    let presented = headers
+        .get("X-Admin-Token")
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        });
+    presented.is_some_and(|token| Sha256::digest(token.as_bytes()) == Sha256::digest(expected.as_bytes()))
+}
+
+/// 요청한 바이트 범위 (양 끝 포함)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ByteRange {
+    start: u64,
+    end: u64,
+}
+
+/// Range 헤더 해석 (`bytes=a-b`, `bytes=a-`, `bytes=-n` 한 개만 지원)
+///
+/// 단위가 bytes가 아니거나 여러 범위면 None(전체 전송), 범위가 잘못되었거나(`bytes=5-2`,
+/// `bytes=abc-`) 파일 밖이면 Err(416)
+fn parse_range(header: &str, size: u64) -> Option<Result<ByteRange, ()>> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    Some(parse_byte_range(spec, size).ok_or(()))
+}
+
+/// 범위 하나 해석 (파일 안의 범위가 아니면 None)
+fn parse_byte_range(spec: &str, size: u64) -> Option<ByteRange> {
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 || size == 0 {
+                return None;
+            }
+            (size.saturating_sub(suffix), size - 1)
+        }
+        (start, "") => (start.parse().ok()?, size.saturating_sub(1)),
+        (start, end) => {
+            let (start, end): (u64, u64) = (start.parse().ok()?, end.parse().ok()?);
+            if end < start {
+                return None;
+            }
+            (start, end.min(size.saturating_sub(1)))
+        }
+    };
+    (start < size).then_some(ByteRange { start, end })
+}
+
 /// 아티팩트 다운로드
 /// GET /api/artifacts/:version
+/// Header: X-API-Key (다운로드 기록에 클라이언트 연결) 또는 X-Admin-Token / Authorization: Bearer (ADMIN_TOKEN)
+/// Header: Range (optional, `bytes=a-b` 하나. 이어받기와 분할 다운로드용으로 206, 잘못된 범위는 416)
+/// Query: client, exp, token (체크인 응답의 서명된 URL. 있으면 X-API-Key 대신 검증)
 pub async fn download_artifact(
     State(state): State<AppState>,
     Path(version): Path<String>,
+    Query(query): Query<ArtifactTokenQuery>,
+    headers: HeaderMap,
 ) -> Result<Response<Body>, (StatusCode, String)> {
+    // 서명된 URL 토큰, 클라이언트 API 키, 운영자 토큰 중 하나가 있어야 받을 수 있음
+    let client_id = authorize_download(&state, &version, &query, &headers).await?;
+
     // 버전 조회
     let ver = db::get_version(&state.pool, &version)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
         .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
 
-    // 파일 경로
-    let file_path = std::path::Path::new(&state.config.artifact_dir).join(&ver.artifact_path);
+    let (file, size) = open_artifact(&state.config, &ver).await?;
+
+    let range = match headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_range(v, size))
+    {
+        Some(Ok(range)) => Some(range),
+        Some(Err(())) => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+                .body(Body::empty())
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+        None => None,
+    };
 
-    // 파일 열기
+    // 다운로드 기록 (기록 실패로 업데이트를 막지 않음)
+    // 분할 다운로드는 첫 조각(0번째 바이트부터)만 기록
+    let recorded = range.is_none_or(|r| r.start == 0);
+    if recorded {
+        if let Err(e) = db::record_artifact_download(&state.pool, &ver.version, client_id, &ver.checksum).await {
+            tracing::warn!("Failed to record download of {}: {}", ver.version, e);
+        }
+    }
+
+    artifact_response(file, size, &ver, range).await
+}
+
+/// 아티팩트 파일 열기 (파일과 크기)
+pub(crate) async fn open_artifact(config: &Config, ver: &Version) -> Result<(File, u64), (StatusCode, String)> {
+    let file_path = std::path::Path::new(&config.artifact_dir).join(&ver.artifact_path);
     let file = File::open(&file_path)
         .await
         .map_err(|_| (StatusCode::NOT_FOUND, "Artifact file not found".to_string()))?;
+    let size = file
+        .metadata()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .len();
+    Ok((file, size))
+}
 
-    // 스트리밍 응답
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
-
-    let response = Response::builder()
-        .status(StatusCode::OK)
+/// 아티팩트 스트리밍 응답 (범위 요청이면 해당 부분만 206)
+pub(crate) async fn artifact_response(
+    mut file: File,
+    size: u64,
+    ver: &Version,
+    range: Option<ByteRange>,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let mut builder = Response::builder()
         .header(header::CONTENT_TYPE, "application/octet-stream")
         .header(
             header::CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{}\"", ver.artifact_path),
+            content_disposition(ver.download_filename()),
         )
-        .header(header::CONTENT_LENGTH, ver.artifact_size)
-        .header("X-Checksum-SHA256", ver.checksum)
-        .body(body)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header("X-Checksum-SHA256", &ver.checksum);
+    if let Some(signature) = &ver.signature {
+        builder = builder.header("X-Signature", signature);
+    }
+    let response = match range {
+        Some(range) => {
+            file.seek(std::io::SeekFrom::Start(range.start))
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            let length = range.end - range.start + 1;
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", range.start, range.end, size),
+                )
+                .header(header::CONTENT_LENGTH, length)
+                .body(Body::from_stream(ReaderStream::new(file.take(length))))
+        }
+        None => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, size)
+            .body(Body::from_stream(ReaderStream::new(file))),
+    }
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(response)
 }
+
+/// blocking writer로 만든 tar.gz를 스트리밍 응답으로 전송 (내보내기, USB 번들)
+pub(crate) fn gzip_attachment<F>(filename: &str, write: F) -> Result<Response<Body>, (StatusCode, String)>
+where
+    F: FnOnce(SyncIoBridge<DuplexStream>) -> anyhow::Result<()> + Send + 'static,
+{
+    let (reader, writer) = tokio::io::duplex(64 * 1024);
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = write(SyncIoBridge::new(writer)) {
+            tracing::error!("Archive streaming failed: {}", e);
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/gzip")
+        .header(header::CONTENT_DISPOSITION, content_disposition(filename))
+        .body(Body::from_stream(ReaderStream::new(reader)))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// 파일 이름만 보고 유지할 복합 확장자
+const COMPOUND_EXTENSIONS: &[&str] = &["tar.gz", "tar.zst", "tar.xz", "tar.bz2"];
+
+/// 업로드 파일 이름에서 저장용 확장자 추출
+///
+/// `.tar.gz` 같은 복합 확장자는 그대로 유지한다. 확장자가 없거나
+/// (`myapp-1.2.3`처럼 숫자뿐인 경우 포함) 영숫자가 아니면 None
+pub(crate) fn artifact_extension(file_name: &str) -> Option<String> {
+    let lower = file_name.to_ascii_lowercase();
+    if let Some(ext) = COMPOUND_EXTENSIONS
+        .iter()
+        .find(|ext| lower.len() > ext.len() + 1 && lower.ends_with(&format!(".{}", ext)))
+    {
+        return Some(ext.to_string());
+    }
+
+    let (stem, ext) = lower.rsplit_once('.')?;
+    let valid = !stem.is_empty()
+        && ext.len() <= 10
+        && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        && ext.chars().any(|c| c.is_ascii_alphabetic());
+    valid.then(|| ext.to_string())
+}
+
+/// 업로드된 원본 파일 이름 정리 (경로와 제어 문자 제거)
+pub(crate) fn sanitize_file_name(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let cleaned: String = base.chars().filter(|c| !c.is_control()).collect();
+    let cleaned = cleaned.trim();
+    (!cleaned.is_empty()).then(|| cleaned.to_string())
+}
+
+/// Content-Disposition 헤더 값 (RFC 6266)
+///
+/// ASCII가 아닌 이름은 `filename*`(RFC 5987)로 함께 보내고, `filename`에는 대체 문자를 쓴다.
+pub(crate) fn content_disposition(file_name: &str) -> String {
+    let fallback: String = file_name
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c == ' ' || c.is_ascii_graphic() => c,
+            _ => '_',
+        })
+        .collect();
+
+    if fallback == file_name {
+        return format!("attachment; filename=\"{}\"", file_name);
+    }
+
+    let encoded: String = file_name
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'!' | b'#' | b'$' | b'&' | b'+' | b'-' | b'.'
+            | b'^' | b'_' | b'`' | b'|' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u64, end: u64) -> Option<Result<ByteRange, ()>> {
+        Some(Ok(ByteRange { start, end }))
+    }
+
+    #[test]
+    fn ranges_are_clamped_to_the_file() {
+        assert_eq!(parse_range("bytes=0-99", 1000), range(0, 99));
+        assert_eq!(parse_range("bytes=500-", 1000), range(500, 999));
+        assert_eq!(parse_range("bytes=-100", 1000), range(900, 999));
+        assert_eq!(parse_range("bytes=-5000", 1000), range(0, 999));
+        assert_eq!(parse_range("bytes=900-5000", 1000), range(900, 999));
+        assert_eq!(parse_range(" bytes= 10 - 20 ", 1000), range(10, 20));
+    }
+
+    #[test]
+    fn unsupported_ranges_send_the_whole_file() {
+        assert_eq!(parse_range("items=0-10", 1000), None);
+        assert_eq!(parse_range("bytes=0-10,20-30", 1000), None);
+    }
+
+    #[test]
+    fn unsatisfiable_ranges_are_rejected() {
+        for header in ["bytes=5-2", "bytes=abc-", "bytes=1000-", "bytes=-0", "bytes=", "bytes=10"] {
+            assert_eq!(parse_range(header, 1000), Some(Err(())), "{}", header);
+        }
+        assert_eq!(parse_byte_range("-10", 0), None);
+        assert_eq!(parse_byte_range("0-", 0), None);
+    }
+
+    #[test]
+    fn compound_extensions_are_kept() {
+        assert_eq!(artifact_extension("update.tar.gz").as_deref(), Some("tar.gz"));
+        assert_eq!(artifact_extension("App.TAR.ZST").as_deref(), Some("tar.zst"));
+        assert_eq!(artifact_extension("foo.tgz").as_deref(), Some("tgz"));
+        assert_eq!(artifact_extension("firmware.img").as_deref(), Some("img"));
+    }
+
+    #[test]
+    fn names_without_a_usable_extension_are_stored_bare() {
+        for name in ["myapp", "myapp-1.2.3", ".bashrc", "a.b-c", "a.verylongextension", "trailing."] {
+            assert_eq!(artifact_extension(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn ascii_names_are_sent_as_is() {
+        assert_eq!(content_disposition("1.0.0.tar.gz"), "attachment; filename=\"1.0.0.tar.gz\"");
+        assert_eq!(content_disposition("my app.zip"), "attachment; filename=\"my app.zip\"");
+    }
+
+    #[test]
+    fn other_names_get_an_encoded_filename() {
+        assert_eq!(
+            content_disposition("배포.zip"),
+            "attachment; filename=\"__.zip\"; filename*=UTF-8''%EB%B0%B0%ED%8F%AC.zip"
+        );
+        assert_eq!(
+            content_disposition("a\"b.zip"),
+            "attachment; filename=\"a_b.zip\"; filename*=UTF-8''a%22b.zip"
+        );
+    }
+}
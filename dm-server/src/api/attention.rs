@@ -0,0 +1,54 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+};
+use chrono::{Duration, Utc};
+
+use crate::db::{self, AttentionReport};
+use crate::timefmt::{Localized, TzQuery};
+use crate::AppState;
+
+/// 다중 에이전트 감지 후 attention 목록에 유지되는 기간
+const MULTIPLE_AGENTS_RETENTION_HOURS: i64 = 24;
+
+/// 주의가 필요한 클라이언트 조회
+/// GET /api/attention
+/// Query: tz=<IANA 타임존> (로컬 표시 필드 추가)
+pub async fn get_attention(
+    State(state): State<AppState>,
+    Query(tz): Query<TzQuery>,
+) -> Result<Localized<AttentionReport>, (StatusCode, String)> {
+    let tz = tz.parse()?;
+    let since = Utc::now() - Duration::hours(MULTIPLE_AGENTS_RETENTION_HOURS);
+
+    let multiple_agents = db::get_multiple_agent_clients(&state.pool, since)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let update_failed = db::get_failed_clients(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let hardware_suspect = db::get_hardware_suspect_clients(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let provenance_mismatch = db::get_provenance_mismatch_clients(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let state_tampered = db::get_state_tampered_clients(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let integrity_mismatch = db::get_integrity_mismatch_clients(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Localized(
+        AttentionReport {
+            multiple_agents,
+            update_failed,
+            hardware_suspect,
+            provenance_mismatch,
+            state_tampered,
+            integrity_mismatch,
+        },
+        tz,
+    ))
+}
@@ -0,0 +1,179 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+
+use super::{actions, aliases};
+use crate::db::{self, BootstrapVersion, Client, SetBootstrapRequest};
+use crate::scan;
+use crate::AppState;
+
+/// 첫 설치 배포의 시작 주체 (업데이트 로그와 작업 대기열에 기록)
+pub(crate) const BOOTSTRAP_INITIATOR: &str = "bootstrap";
+
+const MAX_ROLE_LEN: usize = 64;
+const MAX_SET_BY_LEN: usize = 128;
+
+/// 역할별 첫 설치 버전 목록
+/// GET /api/bootstrap
+pub async fn list_bootstrap_versions(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<BootstrapVersion>>, (StatusCode, String)> {
+    let versions = db::list_bootstrap_versions(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(versions))
+}
+
+/// 역할의 첫 설치 버전 지정 ("*"는 역할이 없거나 따로 지정되지 않은 역할)
+/// PUT /api/bootstrap/:role
+/// Body: {"version": "1.4.0", "set_by": "ops"}
+///
+/// 이미 버전이 있는 장비에는 영향이 없고, 버전 없이 체크인하는 새 장비만 이 버전으로 배포된다.
+pub async fn set_bootstrap_version(
+    State(state): State<AppState>,
+    Path(role): Path<String>,
+    Json(req): Json<SetBootstrapRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if role.is_empty() || role.len() > MAX_ROLE_LEN {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("role must be 1-{} characters", MAX_ROLE_LEN),
+        ));
+    }
+    if req.set_by.as_ref().is_some_and(|s| s.len() > MAX_SET_BY_LEN) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("set_by must be at most {} characters", MAX_SET_BY_LEN),
+        ));
+    }
+
+    let (resolved, _) = aliases::resolve_version(&state, &req.version).await?;
+    let version = db::get_version(&state.pool, &resolved)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+    scan::ensure_deployable(&version)?;
+    if version.is_image() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Version {} is a disk image; bootstrap installs need an archive artifact",
+                version.version
+            ),
+        ));
+    }
+
+    let previous = db::set_bootstrap_version(&state.pool, &role, &version.version, req.set_by.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!(
+        "Bootstrap version for role {} set to {} (was {})",
+        role,
+        version.version,
+        previous.as_deref().unwrap_or("none")
+    );
+
+    Ok(Json(serde_json::json!({
+        "message": "Bootstrap version set",
+        "role": role,
+        "previous_version": previous,
+        "version": version.version,
+        "set_by": req.set_by
+    })))
+}
+
+/// 역할의 첫 설치 버전 해제
+/// DELETE /api/bootstrap/:role
+pub async fn delete_bootstrap_version(
+    State(state): State<AppState>,
+    Path(role): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let deleted = db::delete_bootstrap_version(&state.pool, &role)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, "No bootstrap version for this role".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "Bootstrap version removed",
+        "role": role
+    })))
+}
+
+/// 버전 없이 체크인한 새 장비에 역할의 첫 설치 버전 배포 (대기열이 비어 있을 때 체크인에서 호출)
+///
+/// 지정된 버전이 없거나 비활성/검사 미통과/다른 제품용이면 아무 것도 하지 않는다.
+pub(crate) async fn assign_bootstrap(
+    state: &AppState,
+    client: &mut Client,
+    role: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    let Some(bootstrap) = db::get_bootstrap_version(&state.pool, role)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Ok(());
+    };
+    let Some(version) = db::get_version(&state.pool, &bootstrap.version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Ok(());
+    };
+    if !version.is_active || scan::ensure_deployable(&version).is_err() {
+        tracing::warn!(
+            "Client {} ({}): bootstrap version {} is not deployable",
+            client.name,
+            client.id,
+            version.version
+        );
+        return Ok(());
+    }
+    if let Some(mismatch) = version.product_mismatch(client) {
+        tracing::warn!("Not bootstrapping client {} with {}: {}", client.id, version.version, mismatch);
+        return Ok(());
+    }
+    if client.pinned_version.as_deref().is_some_and(|p| p != version.version) {
+        return Ok(());
+    }
+
+    let deploy = db::DeployPayload {
+        version: version.version.clone(),
+        staged: false,
+        force_reinstall: false,
+        allow_downgrade: false,
+        reason: Some(format!("bootstrap version for role {}", bootstrap.role)),
+        ticket: None,
+        alias: None,
+        initiated_by: Some(BOOTSTRAP_INITIATOR.to_string()),
+    };
+    let payload = serde_json::to_value(&deploy).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let action = db::create_client_action(&state.pool, client.id, "deploy", &payload)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    actions::advance(state, client).await?;
+
+    tracing::info!(
+        "Client {} ({}): no installed version, bootstrapping with {} (role {})",
+        client.name,
+        client.id,
+        version.version,
+        bootstrap.role
+    );
+    state.webhook.emit(
+        "client.bootstrap_assigned",
+        serde_json::json!({
+            "client_id": client.id,
+            "client_name": client.name,
+            "role": role,
+            "bootstrap_role": bootstrap.role,
+            "target_version": version.version,
+            "action_id": action.id,
+        }),
+    );
+    Ok(())
+}
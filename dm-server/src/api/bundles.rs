@@ -0,0 +1,111 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::StatusCode,
+    response::Response,
+    Json,
+};
+use std::path::PathBuf;
+
+use super::artifacts::gzip_attachment;
+use crate::api::aliases;
+use crate::bundle::{self, BundleManifest, BundleManifestEntry, BundleRequest};
+use crate::changelog;
+use crate::db;
+use crate::scan;
+use crate::AppState;
+
+/// 역할/그룹별 다중 항목 USB 번들 생성 (tar.gz 스트리밍)
+/// POST /api/bundles
+/// Body: {"entries": [{"group": "pos", "version": "1.2.0"}, {"group": "kiosk"}, ...]}
+pub async fn create_bundle(
+    State(state): State<AppState>,
+    Json(req): Json<BundleRequest>,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    if req.entries.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "entries must not be empty".to_string()));
+    }
+
+    let mut entries = Vec::with_capacity(req.entries.len());
+    let mut versions = Vec::with_capacity(req.entries.len());
+    for entry in req.entries {
+        if entries
+            .iter()
+            .any(|e: &BundleManifestEntry| e.matcher.group == entry.group)
+        {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                match &entry.group {
+                    Some(group) => format!("Duplicate entry for group {}", group),
+                    None => "Only one default entry (without group) is allowed".to_string(),
+                },
+            ));
+        }
+
+        // 버전 미지정 시 해당 역할 클라이언트 기준으로 결정
+        let version = match (&entry.version, &entry.group) {
+            // 별칭은 내보내는 시점의 버전으로 고정
+            (Some(version), _) => aliases::resolve_version(&state, version).await?.0,
+            (None, Some(group)) => db::get_role_version(&state.pool, group)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .ok_or((
+                    StatusCode::BAD_REQUEST,
+                    format!("No clients with role {} have a version; specify one", group),
+                ))?,
+            (None, None) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "version is required for the default entry".to_string(),
+                ))
+            }
+        };
+
+        let ver = db::get_version(&state.pool, &version)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, format!("Version {} not found", version)))?;
+        scan::ensure_deployable(&ver)?;
+        if ver.is_image() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Version {} is a disk image and cannot be bundled for USB install", version),
+            ));
+        }
+        if !std::path::Path::new(&state.config.artifact_dir)
+            .join(&ver.artifact_path)
+            .is_file()
+        {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("Artifact file for {} not found", version),
+            ));
+        }
+        if ver.has_changelog
+            && !std::path::Path::new(&state.config.artifact_dir)
+                .join(changelog::file_name(&ver.version))
+                .is_file()
+        {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("Changelog file for {} not found", version),
+            ));
+        }
+
+        entries.push(BundleManifestEntry::new(entry.group, &ver));
+        versions.push(ver);
+    }
+
+    let groups: Vec<String> = entries
+        .iter()
+        .map(|e| format!("{}={}", e.matcher.group.as_deref().unwrap_or("*"), e.version))
+        .collect();
+    tracing::info!("Creating USB bundle: {}", groups.join(", "));
+
+    let filename = format!("sam-dm-bundle_{}.tar.gz", chrono::Utc::now().format("%Y%m%dT%H%M%SZ"));
+    let manifest = BundleManifest { entries };
+    let artifact_dir = PathBuf::from(&state.config.artifact_dir);
+    gzip_attachment(&filename, move |writer| {
+        bundle::write_bundle(&manifest, &versions, &artifact_dir, writer)
+    })
+}
@@ -1,15 +1,89 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use uuid::Uuid;
 
-use crate::db::{self, RegisterClientRequest, RegisterClientResponse, UpdateClientConfigRequest};
+use crate::db::{
+    self, PinRequest, RegisterClientRequest, RegisterClientResponse, RollbackClientConfigRequest,
+    UpdateClientConfigRequest,
+};
+use crate::api::{actions, aliases, versions};
+use crate::scan;
+use crate::timefmt::{Localized, TzQuery};
 use crate::AppState;
 
+/// 티켓 번호 최대 길이 (update_logs.ticket 컬럼 크기)
+const MAX_TICKET_LEN: usize = 64;
+
+/// 관리자 변경 요청 에러
+///
+/// 리비전이 맞지 않으면 412와 함께 현재 클라이언트 상태를 돌려줘 UI가 병합할 수 있게 한다.
+pub enum MutationError {
+    Status(StatusCode, String),
+    PreconditionFailed(Box<db::ClientDetail>),
+}
+
+impl From<(StatusCode, String)> for MutationError {
+    fn from((status, message): (StatusCode, String)) -> Self {
+        Self::Status(status, message)
+    }
+}
+
+impl IntoResponse for MutationError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Status(status, message) => (status, message).into_response(),
+            Self::PreconditionFailed(current) => (
+                StatusCode::PRECONDITION_FAILED,
+                Json(serde_json::json!({
+                    "error": "Client was modified by another request",
+                    "revision": current.client.revision,
+                    "current": current,
+                })),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// 변경 요청의 기대 리비전 (본문 expected_revision > If-Match 헤더, 둘 다 없으면 검사 생략)
+fn expected_revision(headers: &HeaderMap, body: Option<i64>) -> Result<Option<i64>, (StatusCode, String)> {
+    if body.is_some() {
+        return Ok(body);
+    }
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+
+    let value = value.to_str().unwrap_or_default().trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    value
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .parse()
+        .map(Some)
+        .map_err(|_| (StatusCode::BAD_REQUEST, format!("Invalid If-Match revision: {}", value)))
+}
+
+/// 변경된 행이 없을 때: 클라이언트가 없으면 404, 있으면 리비전 불일치(412)
+async fn unmodified(state: &AppState, id: Uuid) -> MutationError {
+    match db::get_client_by_id(&state.pool, id).await {
+        Ok(Some(client)) => {
+            let config_drift = client.config_drift();
+            MutationError::PreconditionFailed(Box::new(db::ClientDetail { client, config_drift }))
+        }
+        Ok(None) => MutationError::Status(StatusCode::NOT_FOUND, "Client not found".to_string()),
+        Err(e) => MutationError::Status(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
 /// API Key 생성
-fn generate_api_key() -> String {
+pub(crate) fn generate_api_key() -> String {
     use rand::Rng;
     let mut rng = rand::thread_rng();
     let bytes: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
@@ -37,52 +111,224 @@ pub async fn register_client(
 
 /// 클라이언트 설정 업데이트
 /// PUT /api/clients/:id/config
+/// Header: If-Match: <revision> (또는 본문 expected_revision, 다르면 412)
 pub async fn update_client_config(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
+    headers: HeaderMap,
     Json(req): Json<UpdateClientConfigRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    // 클라이언트 존재 확인
-    let _client = db::get_client_by_id(&state.pool, id)
+) -> Result<Json<serde_json::Value>, MutationError> {
+    let expected = expected_revision(&headers, req.expected_revision)?;
+
+    // 미러는 클라이언트가 그대로 요청하므로 절대 URL이어야 함
+    if let Some(bad) = req
+        .config
+        .mirrors
+        .iter()
+        .flatten()
+        .find(|m| !(m.starts_with("http://") || m.starts_with("https://")))
+    {
+        return Err(MutationError::Status(
+            StatusCode::BAD_REQUEST,
+            format!("Mirror must be an http(s) URL: {}", bad),
+        ));
+    }
+
+    if let Some(product) = &req.config.product {
+        versions::validate_product(product)?;
+    }
+
+    // 보존 경로는 서비스 디렉토리 안의 상대 경로만 (클라이언트도 같은 규칙으로 거부)
+    if let Some(bad) = req.config.preserve_paths.iter().flatten().find(|p| {
+        let path = std::path::Path::new(p.as_str());
+        p.trim().is_empty()
+            || path.is_absolute()
+            || p.starts_with('/')
+            || path.components().any(|c| c == std::path::Component::ParentDir)
+    }) {
+        return Err(MutationError::Status(
+            StatusCode::BAD_REQUEST,
+            format!("Preserved path must be a relative path inside the service directory: {}", bad),
+        ));
+    }
+
+    if let Some(err) = req.config.health_checks.iter().flatten().find_map(|p| p.validate().err()) {
+        return Err(MutationError::Status(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid health check: {}", err),
+        ));
+    }
+
+    // 설정 업데이트 (리비전이 맞을 때만)
+    let revision = save_client_config(&state, id, &req.config, expected, req.changed_by.as_deref()).await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Config updated",
+        "client_id": id,
+        "revision": revision
+    })))
+}
+
+/// 설정 저장 (이전 설정은 이력에 기록) 후 보존 기간이 지난 이력 정리. 새 리비전 반환
+async fn save_client_config(
+    state: &AppState,
+    id: Uuid,
+    config: &db::ClientConfig,
+    expected: Option<i64>,
+    changed_by: Option<&str>,
+) -> Result<i64, MutationError> {
+    let Some(revision) = db::update_client_config(&state.pool, id, config, expected, changed_by)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Err(unmodified(state, id).await);
+    };
+
+    if let Some(days) = state.config.config_history_retention_days {
+        let before = chrono::Utc::now() - chrono::Duration::days(days.into());
+        if let Err(e) = db::prune_client_config_history(&state.pool, before).await {
+            tracing::warn!("Failed to prune config history: {}", e);
+        }
+    }
+    Ok(revision)
+}
+
+/// 클라이언트 설정 변경 이력 (최신순, 각 항목은 changed_at에 바뀌기 직전의 설정)
+/// GET /api/clients/:id/config/history
+/// Query: tz=<IANA 타임존> (로컬 표시 필드 추가)
+pub async fn get_client_config_history(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(tz): Query<TzQuery>,
+) -> Result<Localized<Vec<db::ClientConfigHistory>>, (StatusCode, String)> {
+    let tz = tz.parse()?;
+    db::get_client_by_id(&state.pool, id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
         .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
 
-    // 설정 업데이트
-    db::update_client_config(&state.pool, id, &req.config)
+    let history = db::list_client_config_history(&state.pool, id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+    Ok(Localized(history, tz))
+}
+
+/// 이전 설정으로 되돌리기 (되돌리기 전 설정도 새 이력 항목으로 기록)
+/// POST /api/clients/:id/config/rollback
+/// Header: If-Match: <revision> (또는 본문 expected_revision, 다르면 412)
+pub async fn rollback_client_config(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(req): Json<RollbackClientConfigRequest>,
+) -> Result<Json<serde_json::Value>, MutationError> {
+    let expected = expected_revision(&headers, req.expected_revision)?;
+
+    let entry = db::get_client_config_history(&state.pool, id, req.history_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Config history entry not found".to_string()))?;
+
+    let revision = save_client_config(&state, id, &entry.config.0, expected, req.changed_by.as_deref()).await?;
+
     Ok(Json(serde_json::json!({
-        "message": "Config updated",
-        "client_id": id
+        "message": "Config restored",
+        "client_id": id,
+        "history_id": entry.id,
+        "restored_from": entry.changed_at,
+        "revision": revision
     })))
 }
 
 /// 모든 클라이언트 조회
 /// GET /api/clients
+/// Query: tz=<IANA 타임존> (로컬 표시 필드 추가)
 pub async fn list_clients(
     State(state): State<AppState>,
-) -> Result<Json<Vec<db::Client>>, (StatusCode, String)> {
+    Query(tz): Query<TzQuery>,
+) -> Result<Localized<Vec<db::Client>>, (StatusCode, String)> {
+    let tz = tz.parse()?;
     let clients = db::get_all_clients(&state.pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(clients))
+    Ok(Localized(clients, tz))
 }
 
 /// 클라이언트 조회
 /// GET /api/clients/:id
+/// Query: tz=<IANA 타임존> (로컬 표시 필드 추가)
 pub async fn get_client(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-) -> Result<Json<db::Client>, (StatusCode, String)> {
+    Query(tz): Query<TzQuery>,
+) -> Result<Localized<db::ClientDetail>, (StatusCode, String)> {
+    let tz = tz.parse()?;
     let client = db::get_client_by_id(&state.pool, id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
         .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
 
-    Ok(Json(client))
+    let config_drift = client.config_drift();
+    Ok(Localized(db::ClientDetail { client, config_drift }, tz))
+}
+
+/// 클라이언트 삭제
+/// DELETE /api/clients/:id?force=true
+///
+/// 업데이트 중(status = updating)인 클라이언트는 force 없이 409로 거부한다. 삭제하면 API Key가
+/// 바로 무효가 되어 진행 중인 업데이트의 결과 보고도 거부된다.
+/// 업데이트 로그는 기록 당시 이름으로 남아 `/api/logs`에 " (deleted)"로 표시된다.
+/// 로그까지 지워야 하면 먼저 `POST /api/admin/purge-client-history`를 호출한다.
+pub async fn delete_client(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<db::ClientDeletionQuery>,
+) -> Result<Json<db::DeletedClient>, (StatusCode, String)> {
+    let deleted = db::delete_client(&state.pool, id, query.force)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let Some((client, retained_update_logs)) = deleted else {
+        let client = db::get_client_by_id(&state.pool, id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "Client {} is {} (target {}); pass ?force=true to delete it anyway",
+                client.name,
+                client.status,
+                client.target_version.as_deref().unwrap_or("none")
+            ),
+        ));
+    };
+    let forced = client.status == "updating";
+
+    tracing::info!(
+        "Client {} ({}) deleted{}, {} update logs retained",
+        client.name,
+        client.id,
+        if forced { " while updating" } else { "" },
+        retained_update_logs
+    );
+    state.webhook.emit(
+        "client.deleted",
+        serde_json::json!({
+            "client_id": client.id,
+            "client_name": client.name,
+            "retained_update_logs": retained_update_logs,
+            "forced": forced,
+        }),
+    );
+
+    Ok(Json(db::DeletedClient {
+        id: client.id,
+        name: client.name,
+        retained_update_logs,
+        forced,
+    }))
 }
 
 /// 클라이언트에 버전 배포 명령
@@ -90,28 +336,446 @@ pub async fn get_client(
 pub async fn deploy_to_client(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-    Json(req): Json<db::DeployRequest>,
+    Json(mut req): Json<db::DeployRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
     // 클라이언트 존재 확인
-    let _client = db::get_client_by_id(&state.pool, id)
+    let client = db::get_client_by_id(&state.pool, id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
         .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
 
+    if req.ticket.as_deref().is_some_and(|t| t.len() > MAX_TICKET_LEN) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("ticket must be at most {} characters", MAX_TICKET_LEN),
+        ));
+    }
+
+    // 별칭이면 지금 가리키는 버전으로 해석 (track이면 별칭을 타겟으로 저장)
+    let (resolved, alias) = aliases::resolve_version(&state, &req.version).await?;
+    if req.track && alias.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "track requires an alias version (alias:<name>)".to_string(),
+        ));
+    }
+    req.version = resolved;
+    let alias = alias.filter(|_| req.track);
+    let initiated_by = req
+        .initiated_by
+        .take()
+        .unwrap_or_else(|| actions::DEFAULT_INITIATOR.to_string());
+
     // 버전 존재 확인
-    let _version = db::get_version(&state.pool, &req.version)
+    let version = db::get_version(&state.pool, &req.version)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
         .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+    scan::ensure_deployable(&version)?;
+    let queued = queue_deploy(&state, client, &req, &version, alias.as_deref(), &initiated_by).await?;
 
-    // 타겟 버전 설정
-    db::set_client_target_version(&state.pool, id, &req.version)
+    Ok(Json(serde_json::json!({
+        "message": "Deploy command queued",
+        "client_id": id,
+        "action_id": queued.action_id,
+        "queue_position": queued.queue_position,
+        "superseded_version": queued.superseded_version,
+        "target_version": req.version,
+        "target_alias": alias,
+        "staged": req.staged,
+        "force_reinstall": req.force_reinstall,
+        "allow_downgrade": req.allow_downgrade,
+        "reason": req.reason,
+        "ticket": req.ticket,
+        "initiated_by": initiated_by
+    })))
+}
+/// 여러 클라이언트에 같은 버전 배포
+/// POST /api/deploy
+/// Body: {"version": "1.2.3", "client_ids": [...]} / {"version": "1.2.3", "status": "online"} / {"version": "1.2.3", "all": true}
+///
+/// 버전은 한 번만 해석/검증하고(비활성 버전은 409), 클라이언트마다 단일 배포와 같은 규칙으로
+/// 대기열에 추가한다. 클라이언트별로 거부된 경우는 전체를 실패시키지 않고 skipped에 이유와 함께 담는다.
+pub async fn bulk_deploy(
+    State(state): State<AppState>,
+    Json(mut req): Json<db::BulkDeployRequest>,
+) -> Result<Json<db::BulkDeployResponse>, (StatusCode, String)> {
+    if req.client_ids.is_none() && req.status.is_none() && req.role.is_none() && !req.all {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Specify client_ids, status, role or all=true".to_string(),
+        ));
+    }
+    if req.deploy.ticket.as_deref().is_some_and(|t| t.len() > MAX_TICKET_LEN) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("ticket must be at most {} characters", MAX_TICKET_LEN),
+        ));
+    }
+
+    let (resolved, alias) = aliases::resolve_version(&state, &req.deploy.version).await?;
+    if req.deploy.track && alias.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "track requires an alias version (alias:<name>)".to_string(),
+        ));
+    }
+    req.deploy.version = resolved;
+    let alias = alias.filter(|_| req.deploy.track);
+    let initiated_by = req
+        .deploy
+        .initiated_by
+        .take()
+        .unwrap_or_else(|| actions::DEFAULT_INITIATOR.to_string());
+
+    let version = db::get_version(&state.pool, &req.deploy.version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+    if !version.is_active {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Version {} is inactive", version.version),
+        ));
+    }
+    scan::ensure_deployable(&version)?;
+
+    let clients = db::get_all_clients(&state.pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut skipped = Vec::new();
+    if let Some(ids) = &req.client_ids {
+        for id in ids.iter().filter(|id| !clients.iter().any(|c| c.id == **id)) {
+            skipped.push(db::BulkDeploySkipped {
+                client_id: *id,
+                client_name: None,
+                reason: "Client not found".to_string(),
+            });
+        }
+    }
+    let targets = clients.into_iter().filter(|c| {
+        req.client_ids.as_ref().is_none_or(|ids| ids.contains(&c.id))
+            && req.status.as_ref().is_none_or(|s| *s == c.status)
+            && req.role.as_ref().is_none_or(|r| c.role.as_ref() == Some(r))
+    });
+
+    let mut queued = Vec::new();
+    for client in targets {
+        let (client_id, client_name) = (client.id, client.name.clone());
+        let already_installed = client.current_version.as_deref() == Some(req.deploy.version.as_str())
+            && client.target_version.is_none()
+            && !req.deploy.force_reinstall;
+        if already_installed {
+            skipped.push(db::BulkDeploySkipped {
+                client_id,
+                client_name: Some(client_name),
+                reason: format!("Already on {}", req.deploy.version),
+            });
+            continue;
+        }
+        match queue_deploy(&state, client, &req.deploy, &version, alias.as_deref(), &initiated_by).await {
+            Ok(deploy) => queued.push(db::BulkDeployQueued {
+                client_id,
+                client_name,
+                action_id: deploy.action_id,
+                queue_position: deploy.queue_position,
+                superseded_version: deploy.superseded_version,
+            }),
+            Err((_, reason)) => skipped.push(db::BulkDeploySkipped {
+                client_id,
+                client_name: Some(client_name),
+                reason,
+            }),
+        }
+    }
+
+    tracing::info!(
+        "Bulk deploy of {} by {}: {} queued, {} skipped",
+        req.deploy.version,
+        initiated_by,
+        queued.len(),
+        skipped.len()
+    );
+
+    Ok(Json(db::BulkDeployResponse {
+        target_version: req.deploy.version,
+        queued,
+        skipped,
+    }))
+}
+
+/// 대기열에 추가한 배포
+struct QueuedDeploy {
+    action_id: Uuid,
+    queue_position: usize,
+    superseded_version: Option<String>,
+}
+
+/// 검증된 버전의 배포를 클라이언트 작업 대기열에 추가 (단일/일괄 배포 공통)
+///
+/// 제품 불일치, 중복 배포, 버전 고정처럼 클라이언트별로 거부되는 경우는 에러로 돌려준다.
+async fn queue_deploy(
+    state: &AppState,
+    mut client: db::Client,
+    req: &db::DeployRequest,
+    version: &db::Version,
+    alias: Option<&str>,
+    initiated_by: &str,
+) -> Result<QueuedDeploy, (StatusCode, String)> {
+    // 다른 제품용으로 빌드된 버전 (같은 버전 번호를 쓰는 다른 서비스의 아티팩트 등)
+    if let Some(mismatch) = version.product_mismatch(&client) {
+        return Err((StatusCode::CONFLICT, mismatch));
+    }
+    if req.staged && version.is_image() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Version {} is a disk image and cannot be staged", version.version),
+        ));
+    }
+
+    // 같은 버전 배포가 이미 대기열에 있으면 중복 추가하지 않음
+    if db::has_open_deploy_action(&state.pool, client.id, &req.version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "Deploy of {} is already queued for this client; see GET /api/clients/{}/actions",
+                req.version, client.id
+            ),
+        ));
+    }
+
+    // 고정된 클라이언트 확인
+    if let Some(pinned) = client.pinned_version.as_deref().filter(|p| *p != req.version) {
+        if !req.override_pin {
+            return Err((
+                StatusCode::CONFLICT,
+                format!(
+                    "Client is pinned to {}; pass override_pin=true to deploy {}",
+                    pinned, req.version
+                ),
+            ));
+        }
+
+        db::set_client_pinned_version(&state.pool, client.id, None, None)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        tracing::warn!(
+            "Pin override: client {} ({}) unpinned from {} for deploy of {}",
+            client.name,
+            client.id,
+            pinned,
+            req.version
+        );
+        state.webhook.emit(
+            "client.pin_overridden",
+            serde_json::json!({
+                "client_id": client.id,
+                "client_name": client.name,
+                "pinned_version": pinned,
+                "version": req.version,
+                "reason": req.reason,
+                "ticket": req.ticket,
+                "initiated_by": initiated_by,
+            }),
+        );
+    }
+
+    // 진행 중인 다른 버전의 배포 대체 (진행 중인 업데이트 로그는 취소로 종료)
+    let superseded = match client.target_version.clone() {
+        Some(previous) if req.supersede && previous != req.version => {
+            let message = format!("Superseded by deploy of {}", req.version);
+            actions::clear_deploy(state, &mut client, &message).await?;
+            db::finish_deploy_action(&state.pool, client.id, "cancelled", Some(&message))
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            tracing::info!("Client {} ({}): deploy of {} {}", client.name, client.id, previous, message);
+            Some(previous)
+        }
+        _ => None,
+    };
+
+    // 작업 대기열에 추가 (앞선 작업이 없으면 바로 타겟 버전으로 전달)
+    let deploy = db::DeployPayload {
+        version: req.version.clone(),
+        staged: req.staged,
+        force_reinstall: req.force_reinstall,
+        allow_downgrade: req.allow_downgrade,
+        reason: req.reason.clone(),
+        ticket: req.ticket.clone(),
+        alias: alias.map(str::to_string),
+        initiated_by: Some(initiated_by.to_string()),
+    };
+    let payload = serde_json::to_value(&deploy).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let action = db::create_client_action(&state.pool, client.id, "deploy", &payload)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    actions::advance(state, &mut client).await?;
+    let position = actions::queue_position(state, client.id, action.id).await?;
+
+    state.webhook.emit(
+        "deploy.queued",
+        serde_json::json!({
+            "client_id": client.id,
+            "client_name": client.name,
+            "target_version": req.version,
+            "target_alias": alias,
+            "staged": req.staged,
+            "reason": req.reason,
+            "ticket": req.ticket,
+            "initiated_by": initiated_by,
+            "action_id": action.id,
+            "queue_position": position,
+            "superseded_version": superseded,
+            "severity": version.severity,
+            "urgent": version.is_security(),
+        }),
+    );
+
+    Ok(QueuedDeploy {
+        action_id: action.id,
+        queue_position: position,
+        superseded_version: superseded,
+    })
+}
+
+/// 스테이징된 배포 활성화
+/// POST /api/clients/:id/activate
+pub async fn activate_client_deploy(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let client = db::get_client_by_id(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+
+    let target_version = client
+        .target_version
+        .filter(|_| client.target_staged)
+        .ok_or((StatusCode::CONFLICT, "Client has no staged deploy".to_string()))?;
+
+    db::activate_client_target(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!("Client {} ({}): activation of {} requested", client.name, id, target_version);
 
     Ok(Json(serde_json::json!({
-        "message": "Deploy command queued",
+        "message": "Activation queued",
+        "client_id": id,
+        "target_version": target_version
+    })))
+}
+
+/// 진행 중인 배포 취소 (스테이징된 배포 포함)
+/// DELETE /api/clients/:id/deploy
+///
+/// 클라이언트는 다음 체크인에서 unstage 명령을 받아 스테이징 디렉토리를 정리한다.
+/// 대기열에 다음 배포가 있으면 그 배포가 새 타겟이 된다 (next_target_version).
+pub async fn cancel_client_deploy(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let mut client = db::get_client_by_id(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+
+    let target_version = client
+        .target_version
+        .clone()
+        .ok_or((StatusCode::CONFLICT, "Client has no pending deploy".to_string()))?;
+
+    let message = "Deploy cancelled by operator";
+    actions::clear_deploy(&state, &mut client, message).await?;
+    db::finish_deploy_action(&state.pool, id, "cancelled", Some(message))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    actions::advance(&state, &mut client).await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Deploy cancelled",
+        "client_id": id,
+        "target_version": target_version,
+        "next_target_version": client.target_version
+    })))
+}
+
+/// 클라이언트 버전 고정
+/// PUT /api/clients/:id/pin
+/// Header: If-Match: <revision> (또는 본문 expected_revision, 다르면 412)
+pub async fn pin_client(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(mut req): Json<PinRequest>,
+) -> Result<Json<serde_json::Value>, MutationError> {
+    let expected = expected_revision(&headers, req.expected_revision)?;
+
+    // 클라이언트 존재 확인
+    let client = db::get_client_by_id(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+
+    // 별칭이면 지금 가리키는 버전으로 고정
+    req.version = aliases::resolve_version(&state, &req.version).await?.0;
+
+    // 버전 존재 확인
+    db::get_version(&state.pool, &req.version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+
+    let Some(revision) = db::set_client_pinned_version(&state.pool, id, Some(&req.version), expected)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Err(unmodified(&state, id).await);
+    };
+
+    tracing::info!("Client {} ({}) pinned to {}", client.name, id, req.version);
+
+    Ok(Json(serde_json::json!({
+        "message": "Client pinned",
+        "client_id": id,
+        "pinned_version": req.version,
+        "revision": revision
+    })))
+}
+
+/// 클라이언트 버전 고정 해제
+/// DELETE /api/clients/:id/pin
+/// Header: If-Match: <revision> (다르면 412)
+pub async fn unpin_client(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, MutationError> {
+    let expected = expected_revision(&headers, None)?;
+
+    // 클라이언트 존재 확인
+    let client = db::get_client_by_id(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+
+    let Some(revision) = db::set_client_pinned_version(&state.pool, id, None, expected)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Err(unmodified(&state, id).await);
+    };
+
+    tracing::info!("Client {} ({}) unpinned", client.name, id);
+
+    Ok(Json(serde_json::json!({
+        "message": "Client unpinned",
         "client_id": id,
-        "target_version": req.version
+        "revision": revision
     })))
 }
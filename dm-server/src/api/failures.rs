@@ -0,0 +1,34 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+};
+
+use crate::db::{self, FailureQuery, FailureSignature};
+use crate::timefmt::{Localized, TzQuery};
+use crate::AppState;
+
+/// 한 번에 조회할 수 있는 최대 실패 유형 수
+const MAX_FAILURE_LIMIT: i64 = 1000;
+
+/// 실패 유형 조회 (많이 발생한 순)
+/// GET /api/failures?version=&limit=
+/// Query: tz=<IANA 타임존> (로컬 표시 필드 추가)
+pub async fn list_failures(
+    State(state): State<AppState>,
+    Query(query): Query<FailureQuery>,
+    Query(tz): Query<TzQuery>,
+) -> Result<Localized<Vec<FailureSignature>>, (StatusCode, String)> {
+    let tz = tz.parse()?;
+    if !(1..=MAX_FAILURE_LIMIT).contains(&query.limit) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("limit must be between 1 and {}", MAX_FAILURE_LIMIT),
+        ));
+    }
+
+    let signatures = db::list_failure_signatures(&state.pool, query.version.as_deref(), query.limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Localized(signatures, tz))
+}
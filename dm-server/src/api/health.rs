@@ -0,0 +1,23 @@
+use axum::{extract::State, Json};
+
+use crate::{leader, AppState};
+
+/// 서버 상태 (인스턴스 ID와 백그라운드 작업 리더 포함)
+/// GET /health
+pub async fn health(State(state): State<AppState>) -> Json<serde_json::Value> {
+    // DB에 닿지 않아도 프로세스는 살아 있으므로 200 유지 (리더는 null)
+    let task_leader = match leader::current_leader(&state.pool).await {
+        Ok(leader) => leader,
+        Err(e) => {
+            tracing::warn!("Failed to look up task leader: {}", e);
+            None
+        }
+    };
+
+    Json(serde_json::json!({
+        "status": "ok",
+        "instance_id": state.leadership.instance_id(),
+        "task_leader": task_leader,
+        "is_task_leader": state.leadership.is_leader(),
+    }))
+}
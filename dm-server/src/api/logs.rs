@@ -0,0 +1,34 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+};
+
+use crate::db::{self, UpdateLogEntry, UpdateLogQuery};
+use crate::timefmt::{Localized, TzQuery};
+use crate::AppState;
+
+/// 한 번에 조회할 수 있는 최대 로그 수
+const MAX_LOG_LIMIT: i64 = 1000;
+
+/// 업데이트 로그 조회 (최신순)
+/// GET /api/logs?client_id=&ticket=&initiated_by=&status=&install_strategy=&limit=
+/// Query: tz=<IANA 타임존> (로컬 표시 필드 추가)
+pub async fn list_update_logs(
+    State(state): State<AppState>,
+    Query(query): Query<UpdateLogQuery>,
+    Query(tz): Query<TzQuery>,
+) -> Result<Localized<Vec<UpdateLogEntry>>, (StatusCode, String)> {
+    let tz = tz.parse()?;
+    if !(1..=MAX_LOG_LIMIT).contains(&query.limit) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("limit must be between 1 and {}", MAX_LOG_LIMIT),
+        ));
+    }
+
+    let logs = db::list_update_logs(&state.pool, &query)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Localized(logs, tz))
+}
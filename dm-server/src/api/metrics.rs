@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+
+use super::reports;
+use crate::db::{self, PollIntervalQuery, PollIntervalStats};
+use crate::listener::ConnectionStats;
+use crate::AppState;
+
+/// 폴링 간격 분포 구간 상한 (초)
+const POLL_INTERVAL_BUCKETS: [i32; 8] = [10, 30, 60, 120, 300, 600, 1800, 3600];
+
+/// 연결 수준 통계 (새 연결 수, 재사용된 요청 수 등)
+/// GET /api/metrics/connections
+pub async fn get_connection_metrics(State(state): State<AppState>) -> Json<ConnectionStats> {
+    Json(state.connections.snapshot())
+}
+
+/// 실제 폴링 간격 분포 (힌트를 지정한 뒤 장비들이 실제로 늦췄는지 확인)
+/// GET /api/metrics/poll-intervals?window=1h
+///
+/// 클라이언트가 체크인에 보고한 직전 대기 시간 기준이라, 힌트를 받은 장비는 다음 체크인부터 반영된다.
+pub async fn get_poll_interval_metrics(
+    State(state): State<AppState>,
+    Query(query): Query<PollIntervalQuery>,
+) -> Result<Json<PollIntervalStats>, (StatusCode, String)> {
+    let window = reports::parse_window(&query.window).ok_or((
+        StatusCode::BAD_REQUEST,
+        format!(
+            "window must be <n>h or <n>d, at most {}d: {}",
+            reports::MAX_WINDOW_DAYS,
+            query.window
+        ),
+    ))?;
+
+    let window_start = Utc::now() - window;
+    let summary = db::poll_interval_summary(&state.pool, window_start)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let buckets = db::poll_interval_buckets(&state.pool, window_start, &POLL_INTERVAL_BUCKETS)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let next_poll_secs = state
+        .checkin_load
+        .poll_hint(&state.pool)
+        .await
+        .map(|h| h.next_poll_secs as u64);
+
+    Ok(Json(PollIntervalStats {
+        window_start,
+        summary,
+        buckets,
+        next_poll_secs,
+        shed_total: state.checkin_load.shed_total(),
+    }))
+}
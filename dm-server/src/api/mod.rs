@@ -1,9 +1,39 @@
+pub mod actions;
+pub mod admin;
+pub mod aliases;
 pub mod artifacts;
+pub mod attention;
+pub mod bootstrap;
+pub mod bundles;
 pub mod clients;
+pub mod failures;
+pub mod health;
+pub mod logs;
+pub mod metrics;
 pub mod polling;
+pub mod reports;
+pub mod search;
+pub mod settings;
+pub mod shares;
+pub mod surveys;
 pub mod versions;
 
+pub use actions::*;
+pub use admin::*;
+pub use aliases::*;
 pub use artifacts::*;
+pub use attention::*;
+pub use bootstrap::*;
+pub use bundles::*;
 pub use clients::*;
+pub use failures::*;
+pub use health::*;
+pub use logs::*;
+pub use metrics::*;
 pub use polling::*;
+pub use reports::*;
+pub use search::*;
+pub use settings::*;
+pub use shares::*;
+pub use surveys::*;
 pub use versions::*;
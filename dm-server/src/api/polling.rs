@@ -1,14 +1,40 @@
 use axum::{
     extract::State,
     http::{header::HeaderMap, StatusCode},
+    response::Response,
     Json,
 };
 
-use crate::db::{self, CheckinRequest, CheckinResponse, UpdateResultRequest};
+use chrono::{Duration, Utc};
+
+use super::{actions, artifacts, bootstrap, reports};
+
+use crate::db::{
+    self, ActionResultRequest, BatchCheckinEntry, BatchCheckinResult, CheckinRequest, CheckinResponse,
+    Client, ClientAction, ClientConfig, UpdateResultRequest,
+};
+use crate::etag;
+use crate::failure::{self, ClassifiedFailure};
+use crate::prefix::PublicPrefix;
+use crate::scan::ScanStatus;
+use crate::schedule::{self, CheckinSlot};
+use crate::transport::RequireTls;
 use crate::AppState;
 
+/// 배치 체크인 최대 항목 수
+const MAX_BATCH_SIZE: usize = 100;
+
+/// 서로 다른 인스턴스의 체크인을 다중 에이전트로 판단하는 기간
+const MULTI_AGENT_WINDOW_SECS: i64 = 300;
+
+/// 다중 에이전트 감지를 위해 기억하는 이전 인스턴스 수
+const RETIRED_INSTANCE_LIMIT: usize = 8;
+
+/// 결과 보고 Idempotency-Key 최대 길이 (더 길면 키 없이 처리)
+const MAX_REPORT_KEY_LEN: usize = 128;
+
 /// API Key 추출
-fn extract_api_key(headers: &HeaderMap) -> Option<String> {
+pub(crate) fn extract_api_key(headers: &HeaderMap) -> Option<String> {
     headers
         .get("X-API-Key")
         .and_then(|v| v.to_str().ok())
@@ -18,8 +44,13 @@ fn extract_api_key(headers: &HeaderMap) -> Option<String> {
 /// 클라이언트 체크인 (Polling)
 /// POST /api/checkin
 /// Header: X-API-Key
+///
+/// 처리 중인 체크인이 CHECKIN_SHED_IN_FLIGHT에 이르면 503 + Retry-After로 거부한다.
 pub async fn checkin(
+    _tls: RequireTls,
+    slot: CheckinSlot,
     State(state): State<AppState>,
+    PublicPrefix(prefix): PublicPrefix,
     headers: HeaderMap,
     Json(req): Json<CheckinRequest>,
 ) -> Result<Json<CheckinResponse>, (StatusCode, String)> {
@@ -27,78 +58,853 @@ pub async fn checkin(
     let api_key = extract_api_key(&headers)
         .ok_or((StatusCode::UNAUTHORIZED, "X-API-Key header required".to_string()))?;
 
+    let mut response = process_checkin(&state, &prefix, &api_key, req).await?;
+    response.next_poll_secs = schedule::next_poll_secs(&state, &slot).await;
+    Ok(Json(response))
+}
+
+/// 클라이언트 자신의 등록 정보 (역할, 고정, 대기 중인 배포)
+/// GET /api/clients/self
+/// Header: X-API-Key
+///
+/// 체크인과 달리 상태나 last_seen을 바꾸지 않으므로 현장 점검 도구에서 반복 호출해도 된다.
+/// 본문 해시 ETag를 붙이고 If-None-Match가 같으면 304를 반환한다.
+pub async fn get_client_self(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let api_key = extract_api_key(&headers)
+        .ok_or((StatusCode::UNAUTHORIZED, "X-API-Key header required".to_string()))?;
+
+    let client = db::get_client_self(&state.pool, &api_key)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::UNAUTHORIZED, "Invalid API key".to_string()))?;
+    etag::json(&headers, &client)
+}
+
+/// 게이트웨이 배치 체크인 (여러 장비를 대신하여 한 번에 체크인)
+/// POST /api/checkin/batch
+/// Body: [{api_key, current_version, status}, ...]
+pub async fn checkin_batch(
+    _tls: RequireTls,
+    slot: CheckinSlot,
+    State(state): State<AppState>,
+    PublicPrefix(prefix): PublicPrefix,
+    Json(entries): Json<Vec<BatchCheckinEntry>>,
+) -> Result<Json<Vec<BatchCheckinResult>>, (StatusCode, String)> {
+    if entries.len() > MAX_BATCH_SIZE {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Batch size {} exceeds limit of {}", entries.len(), MAX_BATCH_SIZE),
+        ));
+    }
+
+    let next_poll_secs = schedule::next_poll_secs(&state, &slot).await;
+    let mut results = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let req = CheckinRequest {
+            current_version: entry.current_version,
+            status: entry.status,
+            instance_id: None,
+            instance_started_at: None,
+            staged_version: None,
+            effective_config_hash: None,
+            effective_config: None,
+            role: None,
+            product: None,
+            state_tampered: false,
+            installed_checksum: None,
+            config_hash: None,
+            urgent_during_pause: false,
+            poll_interval_secs: None,
+        };
+
+        // 항목별로 독립 처리 (한 항목의 실패가 배치 전체를 실패시키지 않음)
+        let result = match process_checkin(&state, &prefix, &entry.api_key, req).await {
+            Ok(response) => BatchCheckinResult {
+                status: StatusCode::OK.as_u16(),
+                response: Some(CheckinResponse {
+                    next_poll_secs,
+                    ..response
+                }),
+                error: None,
+            },
+            Err((status, error)) => BatchCheckinResult {
+                status: status.as_u16(),
+                response: None,
+                error: Some(error),
+            },
+        };
+        results.push(result);
+    }
+
+    Ok(Json(results))
+}
+
+/// 체크인 처리 (단일/배치 공용)
+async fn process_checkin(
+    state: &AppState,
+    prefix: &str,
+    api_key: &str,
+    req: CheckinRequest,
+) -> Result<CheckinResponse, (StatusCode, String)> {
     // 클라이언트 조회
-    let client = db::get_client_by_api_key(&state.pool, &api_key)
+    let mut client = db::get_checkin_client(&state.pool, api_key, req.config_hash.as_deref())
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
         .ok_or((StatusCode::UNAUTHORIZED, "Invalid API key".to_string()))?;
 
-    // 체크인 업데이트
-    db::update_client_checkin(
+    // 다중 에이전트 감지 (체크인 기록 전, 이전 체크인 정보 기준)
+    let (warning, is_active_instance) = check_instance(state, &client, &req).await?;
+
+    // 체크인 시간대 기록 (SLA 보고의 오프라인 제외용, 시간대가 바뀔 때만)
+    let now = Utc::now();
+    if client.last_seen.map(|t| t.timestamp() / 3600) != Some(now.timestamp() / 3600) {
+        let retain_since = now - Duration::days(reports::MAX_WINDOW_DAYS);
+        db::record_client_presence(&state.pool, client.id, now, retain_since)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    // 체크인 업데이트 (설치된 아티팩트 체크섬 갱신)
+    client.current_checksum = db::update_client_checkin(
         &state.pool,
         client.id,
         req.current_version.as_deref(),
         &req.status,
+        req.role.as_deref(),
+        req.product.as_deref(),
+        req.poll_interval_secs,
     )
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    // 업데이트 필요 여부 확인
+    // 설치 상태 수정 감지 (처음 보고 시 웹훅)
+    if req.state_tampered != client.state_tampered_at.is_some() {
+        if req.state_tampered {
+            tracing::warn!(
+                "Client {} ({}): install state modified outside dm-client (reported version {})",
+                client.name,
+                client.id,
+                req.current_version.as_deref().unwrap_or("none")
+            );
+            state.webhook.emit(
+                "client.state_tampered",
+                serde_json::json!({
+                    "client_id": client.id,
+                    "client_name": client.name,
+                    "reported_version": req.current_version,
+                    "previous_version": client.current_version,
+                }),
+            );
+        }
+        db::set_client_state_tampered(&state.pool, client.id, req.state_tampered)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    // 설치 아티팩트 무결성 비교 (보고가 없거나 서버가 모르는 아티팩트면 알 수 없음)
+    let integrity_ok = match (req.installed_checksum.as_deref(), client.current_checksum.as_deref()) {
+        (Some(reported), Some(expected)) => Some(reported.eq_ignore_ascii_case(expected)),
+        _ => None,
+    };
+    if integrity_ok == Some(false) && client.integrity_ok != Some(false) {
+        tracing::warn!(
+            "Client {} ({}): installed artifact checksum mismatch for version {}",
+            client.name,
+            client.id,
+            client.current_version.as_deref().unwrap_or("none")
+        );
+        state.webhook.emit(
+            "client.integrity_mismatch",
+            serde_json::json!({
+                "client_id": client.id,
+                "client_name": client.name,
+                "version": req.current_version,
+                "expected_checksum": client.current_checksum,
+                "reported_checksum": req.installed_checksum,
+            }),
+        );
+    }
+    db::set_client_integrity(&state.pool, client.id, req.installed_checksum.as_deref(), integrity_ok)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // 실제 적용 설정 기록 (해시가 달라졌는데 본문이 없으면 재전송 요청)
+    let mut effective_config_requested = false;
+    if let Some(hash) = req.effective_config_hash.as_deref() {
+        match &req.effective_config {
+            Some(config) => {
+                db::set_client_effective_config(&state.pool, client.id, hash, config)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            }
+            None => {
+                effective_config_requested = client.effective_config_hash.as_deref() != Some(hash);
+            }
+        }
+    }
+
+    // 작업 대기열 진행 (맨 앞 배포는 타겟 버전으로, 재시작/롤백은 아래에서 전달)
+    let command = actions::advance(state, &mut client)
+        .await?
+        .filter(|action| action.action_type != "deploy");
+
+    // 버전도 대기 중인 작업도 없는 새 장비: 역할의 첫 설치 버전으로 배포
+    if command.is_none() && client.target_version.is_none() && req.current_version.is_none() {
+        let role = req.role.clone().or_else(|| client.role.clone());
+        bootstrap::assign_bootstrap(state, &mut client, role.as_deref()).await?;
+    }
+
+    // 별칭을 따라가는 클라이언트: 별칭이 옮겨졌으면 새 버전으로 재지정
+    if let Some(alias) = client.target_alias.clone() {
+        retarget_alias(state, &mut client, &alias, req.current_version.as_deref()).await?;
+    }
+
+    // 업데이트 필요 여부 확인 (재설치 요청이면 같은 버전이어도 진행)
     let needs_update = match (&client.target_version, &req.current_version) {
-        (Some(target), Some(current)) => target != current,
+        (Some(target), Some(current)) => target != current || client.target_force_reinstall,
         (Some(_), None) => true,
         _ => false,
     };
 
-    // 클라이언트 설정
-    let client_config = client.config.0.clone();
-    let config_option = if client_config.service_dir.is_some() || client_config.restart_command.is_some() {
-        Some(client_config)
+    // 클라이언트 설정 (클라이언트가 마지막으로 받은 설정과 같으면 생략)
+    let config_changed = client.config_hash.is_none() || req.config_hash != client.config_hash;
+    let config_option =
+        (config_changed && client.config.0.has_pushed_settings()).then(|| client.config.0.clone());
+
+    // 타겟과 다른 스테이징 버전은 폐기
+    let stale_staged = req
+        .staged_version
+        .as_deref()
+        .filter(|staged| client.target_version.as_deref() != Some(*staged));
+
+    // 현장에서 일시 정지한 장치는 업데이트를 내려보내지 않음 (pending 로그가 쌓이지 않도록)
+    // 단, 클라이언트 정책이 허용하면 보안 버전은 일시 중지 중에도 전달
+    let mut paused = req.status == "paused";
+    if paused && needs_update && req.urgent_during_pause && command.is_none() {
+        let security = match client.target_version.as_deref() {
+            Some(target) => db::get_version(&state.pool, target)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .is_some_and(|ver| ver.is_security()),
+            None => false,
+        };
+        if security {
+            tracing::info!(
+                "Client {} ({}): paused, delivering security update to {}",
+                client.name,
+                client.id,
+                client.target_version.as_deref().unwrap_or("?")
+            );
+            paused = false;
+        }
+    }
+    // 이미지를 쓰고 재부팅을 기다리는 장치 (결과는 재부팅 후 보고)
+    let rebooting = req.status == "rebooting";
+
+    let mut response = if let Some(command) = &command {
+        // 조사는 읽기 전용이라 일시 중지 중에도 전달
+        let held = if paused && command.action_type != "survey" {
+            Some("paused")
+        } else if rebooting {
+            Some("rebooting")
+        } else if !is_active_instance {
+            Some("inactive_instance")
+        } else {
+            None
+        };
+        deliver_command(state, &client, command, held, config_option).await?
+    } else if needs_update && paused {
+        tracing::info!(
+            "Client {} ({}): paused, holding update to {}",
+            client.name,
+            client.id,
+            client.target_version.as_deref().unwrap_or("?")
+        );
+        CheckinResponse {
+            note: Some(format!(
+                "paused: update to {} held until the device resumes",
+                client.target_version.as_deref().unwrap_or("?")
+            )),
+            ..CheckinResponse::none(config_option)
+        }
+    } else if needs_update && rebooting {
+        CheckinResponse {
+            note: Some(format!(
+                "rebooting: waiting for the device to boot {}",
+                client.target_version.as_deref().unwrap_or("?")
+            )),
+            ..CheckinResponse::none(config_option)
+        }
+    } else if needs_update && is_active_instance {
+        resolve_update(state, prefix, &client, &req, config_option).await?
+    } else if let Some(staged) = stale_staged {
+        tracing::info!(
+            "Client {} ({}): staged {} is no longer targeted, unstaging",
+            client.name,
+            client.id,
+            staged
+        );
+        CheckinResponse {
+            action: "unstage".to_string(),
+            target_version: Some(staged.to_string()),
+            config: config_option,
+            ..Default::default()
+        }
     } else {
-        None
+        CheckinResponse::none(config_option)
     };
+    response.warning = warning;
+    response.effective_config_requested = effective_config_requested;
+    if response.config.is_some() {
+        response.config_hash = client.config_hash;
+    }
 
-    if needs_update {
-        let target_version = client.target_version.unwrap();
-        
-        // 버전 정보 조회
-        let version = db::get_version(&state.pool, &target_version)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(response)
+}
 
-        if let Some(ver) = version {
-            // 업데이트 로그 생성
-            db::create_update_log(
-                &state.pool,
+/// 별칭을 따라가는 클라이언트의 타겟 재지정
+///
+/// 별칭이 가리키는 버전이 현재 설치 버전도, 지금 타겟도 아니면 타겟을 그 버전으로 바꾼다.
+/// 별칭이 삭제되었으면 기존 타겟을 그대로 둔다.
+async fn retarget_alias(
+    state: &AppState,
+    client: &mut db::Client,
+    alias: &str,
+    current_version: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    let Some(alias) = db::get_version_alias(&state.pool, alias)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Ok(());
+    };
+    if current_version == Some(alias.version.as_str())
+        || client.target_version.as_deref() == Some(alias.version.as_str())
+    {
+        return Ok(());
+    }
+
+    // 다른 제품용 버전으로는 따라가지 않음
+    if let Some(ver) = db::get_version(&state.pool, &alias.version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        if let Some(mismatch) = ver.product_mismatch(client) {
+            tracing::warn!("Not retargeting client {} to alias {}: {}", client.id, alias.name, mismatch);
+            return Ok(());
+        }
+    }
+
+    let reason = format!("alias {} moved to {}", alias.name, alias.version);
+    let initiated_by = format!("alias:{}", alias.name);
+    db::retarget_client_alias(&state.pool, client.id, &alias.version, &reason, &initiated_by)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    tracing::info!(
+        "Client {} ({}) retargeted to {} ({})",
+        client.name,
+        client.id,
+        alias.version,
+        reason
+    );
+
+    client.target_version = Some(alias.version);
+    client.target_staged = false;
+    client.target_force_reinstall = false;
+    client.target_allow_downgrade = false;
+    client.target_reason = Some(reason);
+    client.target_ticket = None;
+    client.target_initiated_by = Some(initiated_by);
+    Ok(())
+}
+
+/// 대기열 맨 앞의 재시작/롤백 전달 (결과 보고나 만료 전까지 다음 작업은 내려보내지 않음)
+async fn deliver_command(
+    state: &AppState,
+    client: &Client,
+    action: &ClientAction,
+    held: Option<&str>,
+    config_option: Option<ClientConfig>,
+) -> Result<CheckinResponse, (StatusCode, String)> {
+    if action.state == "delivered" {
+        let mut response = CheckinResponse::none(config_option);
+        response.note = Some(format!(
+            "awaiting_action_result: {} {} was delivered; waiting for its result",
+            action.action_type, action.id
+        ));
+        return Ok(response);
+    }
+    if let Some(held) = held {
+        let mut response = CheckinResponse::none(config_option);
+        response.note = Some(format!("{}: {} held until the device can run it", held, action.action_type));
+        return Ok(response);
+    }
+
+    db::mark_client_action_delivered(&state.pool, action.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    tracing::info!(
+        "Client {} ({}): delivering {} action {}",
+        client.name,
+        client.id,
+        action.action_type,
+        action.id
+    );
+
+    Ok(CheckinResponse {
+        action: action.action_type.clone(),
+        action_id: Some(action.id),
+        note: action.payload.0["reason"].as_str().map(str::to_string),
+        config: config_option,
+        survey: serde_json::from_value(action.payload.0["commands"].clone()).unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+/// 다중 에이전트 감지
+///
+/// 동일 API Key로 서로 다른 instance_id가 MULTI_AGENT_WINDOW_SECS 이내에 체크인하면
+/// 다중 에이전트 후보로 본다. 새 instance_id가 처음 나타나면 재시작(서비스 재시작, install-service,
+/// 자가 업데이트)으로 보고 바뀐 이전 인스턴스를 기억해 두며, 이미 다른 인스턴스로 바뀐 instance_id가
+/// 다시 체크인할 때만 경합으로 판단한다. 장비 시계는 비교하지 않으므로 시계가 틀어진 장비도 같은
+/// 기준으로 판단한다. 경합 중에는 가장 최근에 시작된 인스턴스만 업데이트를 받으며, 경합이 window
+/// 동안 감지되지 않으면 남은 인스턴스가 다시 업데이트를 받는다.
+///
+/// (경고 메시지, 업데이트 수신 여부) 반환
+async fn check_instance(
+    state: &AppState,
+    client: &Client,
+    req: &CheckinRequest,
+) -> Result<(Option<String>, bool), (StatusCode, String)> {
+    let Some(instance_id) = req.instance_id.as_deref() else {
+        return Ok((None, true));
+    };
+
+    let now = Utc::now();
+    let window = Duration::seconds(MULTI_AGENT_WINDOW_SECS);
+    let recently_seen = client.last_seen.is_some_and(|t| now - t < window);
+    let replaced = client
+        .last_instance_id
+        .as_deref()
+        .filter(|prev| *prev != instance_id);
+    let reappeared = client.retired_instance_ids.iter().any(|id| id == instance_id);
+    let conflict = recently_seen && replaced.is_some() && reappeared;
+
+    // 바뀐 이전 인스턴스를 기억 (지금 체크인한 인스턴스는 다시 살아 있으므로 제외)
+    let mut retired: Vec<String> = client
+        .retired_instance_ids
+        .iter()
+        .filter(|id| *id != instance_id && Some(id.as_str()) != replaced)
+        .cloned()
+        .collect();
+    retired.extend(replaced.map(str::to_string));
+    let excess = retired.len().saturating_sub(RETIRED_INSTANCE_LIMIT);
+    retired.drain(..excess);
+    let recently_flagged = client.multiple_agents_at.is_some_and(|t| now - t < window);
+    let contested = conflict || recently_flagged;
+
+    // 경합 중에는 가장 최근에 시작된 인스턴스가 우선
+    let is_active = if !contested {
+        true
+    } else {
+        match (
+            client.active_instance_id.as_deref(),
+            client.active_instance_started_at,
+            req.instance_started_at,
+        ) {
+            (None, _, _) => true,
+            (Some(active), _, _) if active == instance_id => true,
+            (Some(_), Some(active_started), Some(started)) => started >= active_started,
+            (Some(_), None, Some(_)) => true,
+            (Some(_), _, None) => false,
+        }
+    };
+
+    if conflict && !recently_flagged {
+        tracing::warn!(
+            "Client {} ({}): multiple agents detected (instances {} and {})",
+            client.name,
+            client.id,
+            client.last_instance_id.as_deref().unwrap_or("?"),
+            instance_id
+        );
+        state.webhook.emit(
+            "client.multiple_agents",
+            serde_json::json!({
+                "client_id": client.id,
+                "client_name": client.name,
+                "instance_ids": [client.last_instance_id, instance_id],
+            }),
+        );
+    }
+
+    db::record_client_instance(
+        &state.pool,
+        client.id,
+        instance_id,
+        req.instance_started_at,
+        &retired,
+        is_active,
+        conflict,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let warning = contested.then(|| {
+        let role = if is_active {
+            "this instance is the newest and receives updates"
+        } else {
+            "this instance is older and will not receive updates"
+        };
+        format!(
+            "multiple_agents: more than one dm-client is using this API key; {}",
+            role
+        )
+    });
+
+    Ok((warning, is_active))
+}
+
+/// 클라이언트에 내려줄 아티팩트 미러 (클라이언트 설정 → 역할별 → 기본 순)
+fn mirrors_for<'a>(state: &'a AppState, client: &'a Client) -> &'a [String] {
+    if let Some(mirrors) = &client.config.0.mirrors {
+        return mirrors;
+    }
+    let configured = &state.config.artifact_mirrors;
+    client
+        .role
+        .as_deref()
+        .and_then(|role| configured.get(role))
+        .or_else(|| configured.get("*"))
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// 타겟 버전 확인 후 업데이트 명령 생성
+async fn resolve_update(
+    state: &AppState,
+    prefix: &str,
+    client: &Client,
+    req: &CheckinRequest,
+    config_option: Option<ClientConfig>,
+) -> Result<CheckinResponse, (StatusCode, String)> {
+    let target_version = client.target_version.clone().unwrap_or_default();
+
+    // 고정된 버전과 다른 타겟은 차단
+    if let Some(pinned) = client.pinned_version.as_deref().filter(|p| *p != target_version) {
+        tracing::info!(
+            "Client {} ({}): target {} blocked_by_pin ({})",
+            client.name,
+            client.id,
+            target_version,
+            pinned
+        );
+        let mut response = CheckinResponse::none(config_option);
+        response.note = Some(format!(
+            "blocked_by_pin: pinned to {}, target {} ignored",
+            pinned, target_version
+        ));
+        return Ok(response);
+    }
+
+    // 버전 정보 조회
+    let version = db::get_version(&state.pool, &target_version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    match version {
+        // 배포 후 재검사에서 걸린 경우: 타겟은 유지하고 검사 통과까지 보류
+        Some(ver) if ver.is_active && ver.scan_status != ScanStatus::Clean.as_str() => {
+            tracing::info!(
+                "Client {} ({}): target {} held (scan {})",
+                client.name,
                 client.id,
+                target_version,
+                ver.scan_status
+            );
+            let mut response = CheckinResponse::none(config_option);
+            response.note = Some(format!(
+                "held_by_scan: {} scan is {}",
+                target_version, ver.scan_status
+            ));
+            Ok(response)
+        }
+        Some(ver) if ver.is_active => {
+            // 재태깅된 동일 빌드: 재설치 없이 배포 완료 처리
+            if !client.target_force_reinstall
+                && client.current_checksum.as_deref() == Some(ver.checksum.as_str())
+            {
+                return complete_noop_retag(state, client, req, &ver.checksum, config_option).await;
+            }
+
+            let staged_on_client = req.staged_version.as_deref() == Some(target_version.as_str());
+
+            // 스테이징 완료 후 활성화 대기
+            if client.target_staged && staged_on_client {
+                let mut response = CheckinResponse::none(config_option);
+                response.note = Some(format!("staged: {} is awaiting activation", target_version));
+                return Ok(response);
+            }
+
+            // 업데이트 로그 생성 (같은 타겟의 진행 중인 로그가 있으면 이어서 사용: 다운로드 중 재시작한
+            // 클라이언트의 체크인, 만료된 아티팩트 URL 갱신, 스테이징 후 활성화). 실패/완료로 닫힌
+            // 로그 뒤의 재시도만 새 로그가 된다.
+            let pending = db::get_pending_update_log(&state.pool, client.id, &target_version)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            if pending.is_none() {
+                db::create_update_log(
+                    &state.pool,
+                    client,
+                    req.current_version.as_deref(),
+                    &target_version,
+                )
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            }
+
+            let action = if client.target_staged {
+                "stage"
+            } else if staged_on_client {
+                "activate"
+            } else {
+                "update"
+            };
+
+            let artifact_url = artifacts::artifact_url(&state.config, prefix, &ver.version, client.id);
+            let mut artifact_urls = vec![artifact_url.clone()];
+            artifact_urls.extend(
+                mirrors_for(state, client)
+                    .iter()
+                    .map(|base| format!("{}/{}", base.trim_end_matches('/'), ver.artifact_path)),
+            );
+
+            Ok(CheckinResponse {
+                action: action.to_string(),
+                target_version: Some(target_version),
+                artifact_url: Some(artifact_url),
+                artifact_urls,
+                build_info: ver.build_info(),
+                scripts: ver.install_scripts().map(|s| s.hashes()),
+                deploy_type: ver.is_image().then(|| ver.deploy_type.clone()),
+                urgent: ver.is_security(),
+                checksum: Some(ver.checksum),
+                artifact_size: Some(ver.artifact_size),
+                signature: ver.signature.clone(),
+                config: config_option,
+                force_reinstall: client.target_force_reinstall,
+                allow_downgrade: client.target_allow_downgrade,
+                ..Default::default()
+            })
+        }
+        resolved => {
+            // 타겟 버전을 찾을 수 없거나 비활성화됨 → 타겟 클리어
+            let reason = if resolved.is_some() {
+                format!("Target version {} is inactive; deploy cancelled", target_version)
+            } else {
+                format!("Target version {} no longer exists; deploy cancelled", target_version)
+            };
+
+            tracing::warn!("Client {} ({}): {}", client.name, client.id, reason);
+
+            db::clear_client_target_version(&state.pool, client.id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            db::finish_deploy_action(&state.pool, client.id, "cancelled", Some(&reason))
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            let log = db::create_update_log(
+                &state.pool,
+                client,
                 req.current_version.as_deref(),
                 &target_version,
             )
             .await
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-            return Ok(Json(CheckinResponse {
-                action: "update".to_string(),
-                target_version: Some(target_version),
-                artifact_url: Some(format!("/api/artifacts/{}", ver.version)),
-                checksum: Some(ver.checksum),
-                config: config_option,
-            }));
+            db::update_log_status(&state.pool, log.id, "cancelled", Some(&reason))
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            state.webhook.emit(
+                "deploy.target_unresolvable",
+                serde_json::json!({
+                    "client_id": client.id,
+                    "client_name": client.name,
+                    "target_version": target_version,
+                    "reason": reason,
+                    "deploy_reason": client.target_reason,
+                    "ticket": client.target_ticket,
+                    "initiated_by": client.target_initiated_by,
+                }),
+            );
+
+            let mut response = CheckinResponse::none(config_option);
+            response.note = Some(reason);
+            Ok(response)
         }
     }
+}
 
-    Ok(Json(CheckinResponse {
-        action: "none".to_string(),
-        target_version: None,
-        artifact_url: None,
-        checksum: None,
-        config: config_option,
-    }))
+/// 타겟 버전이 현재 설치된 아티팩트와 체크섬이 같을 때 (같은 빌드를 새 버전으로 재태깅)
+///
+/// 다운로드/재시작 없이 current_version만 새 버전으로 올리고 배포를 완료로 기록한다.
+/// 재설치가 필요하면 배포 시 `force_reinstall: true`를 지정한다.
+async fn complete_noop_retag(
+    state: &AppState,
+    client: &Client,
+    req: &CheckinRequest,
+    checksum: &str,
+    config_option: Option<ClientConfig>,
+) -> Result<CheckinResponse, (StatusCode, String)> {
+    let target_version = client.target_version.clone().unwrap_or_default();
+    let previous = client.current_version.as_deref().or(req.current_version.as_deref());
+
+    tracing::warn!(
+        "Client {} ({}): target {} has the same checksum as installed {}; no-op retag, skipping reinstall",
+        client.name,
+        client.id,
+        target_version,
+        previous.unwrap_or("unknown")
+    );
+
+    db::complete_noop_retag(&state.pool, client.id, &target_version, checksum)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    db::finish_deploy_action(&state.pool, client.id, "completed", None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let log = db::create_update_log(&state.pool, client, previous, &target_version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    db::update_log_status(&state.pool, log.id, "completed", None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    db::set_update_log_skipped_reason(&state.pool, log.id, "noop_retag")
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    state.webhook.emit(
+        "deploy.noop_retag",
+        serde_json::json!({
+            "client_id": client.id,
+            "client_name": client.name,
+            "from_version": previous,
+            "target_version": target_version,
+            "checksum": checksum,
+            "reason": client.target_reason,
+            "ticket": client.target_ticket,
+            "initiated_by": client.target_initiated_by,
+        }),
+    );
+
+    let mut response = CheckinResponse::none(config_option);
+    response.note = Some(format!(
+        "noop_retag: {} is identical to the installed artifact; marked as installed without reinstall",
+        target_version
+    ));
+    Ok(response)
+}
+
+/// 장비가 검증한 체크섬과 업로드 체크섬 비교 (다르면 손상/변조 의심으로 웹훅 발송)
+async fn check_provenance(
+    state: &AppState,
+    client: &Client,
+    version: &str,
+    verified: &str,
+) -> Result<(), (StatusCode, String)> {
+    let Some(ver) = db::get_version(&state.pool, version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Ok(());
+    };
+    if ver.checksum == verified {
+        return Ok(());
+    }
+
+    tracing::error!(
+        "Client {} ({}): artifact {} verified as {}, uploaded as {}; possible corruption or tampering",
+        client.name,
+        client.id,
+        version,
+        verified,
+        ver.checksum
+    );
+    state.webhook.emit(
+        "artifact.provenance_mismatch",
+        serde_json::json!({
+            "client_id": client.id,
+            "client_name": client.name,
+            "version": version,
+            "uploaded_checksum": ver.checksum,
+            "verified_checksum": verified,
+            "uploaded_by": ver.uploaded_by,
+        }),
+    );
+    Ok(())
+}
+
+/// 실패 유형 집계 갱신 및 웹훅 발송 (원본 메시지 대신 fingerprint로 참조)
+async fn record_failure(
+    state: &AppState,
+    client: &Client,
+    version: &str,
+    classified: &ClassifiedFailure,
+    error_message: Option<&str>,
+    initiated_by: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    // 보안 버전 실패는 호출(page) 대상으로 구분할 수 있도록 심각도 포함
+    let severity = db::get_version(&state.pool, version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(|ver| ver.severity);
+    let occurrences = db::record_failure_signature(
+        &state.pool,
+        version,
+        &classified.fingerprint,
+        &classified.category,
+        error_message.unwrap_or_default(),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::warn!(
+        "Client {} failed update to {}: {} (fingerprint {}, {} occurrences)",
+        client.name,
+        version,
+        classified.category,
+        classified.fingerprint,
+        occurrences
+    );
+    state.webhook.emit(
+        "update.failed",
+        serde_json::json!({
+            "client_id": client.id,
+            "client_name": client.name,
+            "version": version,
+            "category": classified.category,
+            "fingerprint": classified.fingerprint,
+            "occurrences": occurrences,
+            "initiated_by": initiated_by,
+            "severity": severity,
+            "urgent": severity.as_deref() == Some("security"),
+        }),
+    );
+    Ok(())
 }
 
 /// 업데이트 결과 보고
 /// POST /api/update-result
-/// Header: X-API-Key
+/// Header: X-API-Key, Idempotency-Key (선택, 같은 키의 재전송은 무시)
+///
+/// 같은 버전의 진행 중인 업데이트 로그를 닫는다. 체크인에서 내려준 적 없는 설치(USB/apply)처럼
+/// 진행 중인 로그가 없으면 로그를 새로 만들어 기록한다 (대체된 시도 보고는 제외).
 pub async fn report_update_result(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -114,12 +920,209 @@ pub async fn report_update_result(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
         .ok_or((StatusCode::UNAUTHORIZED, "Invalid API key".to_string()))?;
 
-    if req.success {
+    // 실패 메시지 처리: 길이 제한, fingerprint, 원인 분류
+    let error_message = req
+        .error_message
+        .as_deref()
+        .map(|message| failure::truncate(message, state.config.error_message_max_len));
+    // 설치 전에 타겟이 바뀌어 중단된 시도 (실패로 세지 않음)
+    let superseded = !req.success && req.failure_reason.as_deref() == Some("superseded");
+    let classified = (!req.success && !superseded).then(|| {
+        failure::classify(
+            req.error_message.as_deref().unwrap_or_default(),
+            req.failure_reason.as_deref(),
+        )
+    });
+
+    // 결과 보고의 Idempotency-Key (첫 보고가 로그를 닫았거나 만들었으면 재전송은 무시)
+    let report_key = headers
+        .get("Idempotency-Key")
+        .and_then(|v| v.to_str().ok())
+        .filter(|key| !key.is_empty() && key.len() <= MAX_REPORT_KEY_LEN);
+
+    // 진행 중인 업데이트 로그 종료 (없으면 USB/apply 설치 등으로 보고 이력을 남기도록 새로 생성)
+    // 키는 상태를 바꾸기 전에 로그에 원자적으로 기록해, 동시에 재전송된 보고는 하나만 반영
+    let duplicate = || {
+        tracing::debug!("Client {}: duplicate result report for {}", client.name, req.version);
+        Ok(Json(serde_json::json!({
+            "message": "Update result already recorded",
+            "version": req.version
+        })))
+    };
+    let pending_log = db::get_pending_update_log(&state.pool, client.id, &req.version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let pending_log = match pending_log {
+        Some(log) => {
+            if let Some(key) = report_key {
+                if !db::claim_update_log_report_key(&state.pool, log.id, key)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                {
+                    return duplicate();
+                }
+            }
+            Some(log)
+        }
+        None if !superseded => {
+            let log = db::create_reported_update_log(
+                &state.pool,
+                &client,
+                client.current_version.as_deref(),
+                &req.version,
+                report_key,
+            )
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            match log {
+                Some(log) => Some(log),
+                None => return duplicate(),
+            }
+        }
+        None => None,
+    };
+
+    if let Some(log) = &pending_log {
+        let status = match (req.success, req.staged) {
+            (true, true) => "staged",
+            (true, false) => "completed",
+            (false, _) if superseded => "superseded",
+            (false, _) => "failed",
+        };
+        db::update_log_status(&state.pool, log.id, status, error_message.as_deref())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        if let Some(reason) = &req.skipped_reason {
+            db::set_update_log_skipped_reason(&state.pool, log.id, reason)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+
+        if let Some(classified) = &classified {
+            db::set_update_log_failure(&state.pool, log.id, &classified.category, &classified.fingerprint)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+
+        if let Some(checksum) = &req.verified_checksum {
+            db::set_update_log_verified_checksum(&state.pool, log.id, checksum)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+
+        if let Some(source) = &req.artifact_source {
+            db::set_update_log_artifact_source(&state.pool, log.id, source)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+
+        if let Some(strategy) = &req.install_strategy {
+            db::set_update_log_install_strategy(&state.pool, log.id, strategy)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+
+        if req.bootstrap {
+            db::set_update_log_bootstrap(&state.pool, log.id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+
+        // 헬스 체크 시각을 보내지 않는 클라이언트는 성공 보고 시각으로 대신
+        let healthy_at = req
+            .phases
+            .healthy_at
+            .or_else(|| (req.success && !req.staged).then(Utc::now));
+        db::set_update_log_phases(&state.pool, log.id, &req.phases, healthy_at)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    if let Some(verified) = &req.verified_checksum {
+        check_provenance(&state, &client, &req.version, verified).await?;
+    }
+
+    if let Some(classified) = &classified {
+        let initiated_by = pending_log.as_ref().and_then(|log| log.initiated_by.as_deref());
+        record_failure(&state, &client, &req.version, classified, error_message.as_deref(), initiated_by).await?;
+    }
+
+    if let Some(reason) = &req.skipped_reason {
+        tracing::info!("Client {} skipped install of {}: {}", client.name, req.version, reason);
+    }
+
+    if superseded {
+        // 클라이언트는 이미 새 타겟으로 진행 중이므로 상태와 배포 작업은 그대로 둠
+        tracing::info!(
+            "Client {} ({}): update to {} superseded before install",
+            client.name,
+            client.id,
+            req.version
+        );
+        return Ok(Json(serde_json::json!({
+            "message": "Superseded update recorded",
+            "version": req.version
+        })));
+    }
+
+    if req.success && req.staged {
+        // 스테이징 완료: 활성화 전까지 current_version 유지
+        sqlx::query(
+            r#"
+            UPDATE clients
+            SET status = 'staged', updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(client.id)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        Ok(Json(serde_json::json!({
+            "message": "Update staged",
+            "version": req.version
+        })))
+    } else if req.success && !targets_report(&client, &req.version) {
+        // 만료/취소된 배포의 늦은 성공 보고: 대기열의 다음 타겟은 유지
+        tracing::info!(
+            "Client {} ({}): late success for {} while targeting {}",
+            client.name,
+            client.id,
+            req.version,
+            client.target_version.as_deref().unwrap_or("?")
+        );
+        sqlx::query(
+            r#"
+            UPDATE clients
+            SET current_version = $2,
+                current_checksum = (SELECT checksum FROM versions WHERE version = $2),
+                status = 'online', updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(client.id)
+        .bind(&req.version)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        Ok(Json(serde_json::json!({
+            "message": "Update success recorded",
+            "version": req.version
+        })))
+    } else if req.success {
         // 성공: current_version 업데이트, target_version 클리어
         sqlx::query(
             r#"
             UPDATE clients
-            SET current_version = $2, target_version = NULL, status = 'online', updated_at = NOW()
+            SET current_version = $2,
+                current_checksum = (SELECT checksum FROM versions WHERE version = $2),
+                target_version = NULL, target_staged = FALSE, target_force_reinstall = FALSE,
+                target_allow_downgrade = FALSE,
+                target_reason = NULL, target_ticket = NULL, target_set_at = NULL, target_initiated_by = NULL,
+                status = 'online', updated_at = NOW()
             WHERE id = $1
             "#,
         )
@@ -128,6 +1131,7 @@ pub async fn report_update_result(
         .execute(&state.pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        finish_deploy(&state, client, "completed", None).await?;
 
         Ok(Json(serde_json::json!({
             "message": "Update success recorded",
@@ -147,10 +1151,147 @@ pub async fn report_update_result(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+        let targeted = client.target_version.as_deref() == Some(req.version.as_str());
+        if targeted {
+            db::set_deploy_action_error(&state.pool, client.id, error_message.as_deref().unwrap_or("update failed"))
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+
+        // 재시도해도 같은 결과인 실패는 타겟 해제: 이미지가 부팅되지 않아 이전 슬롯으로 돌아온 경우
+        // (재시도하면 재부팅이 반복됨), 클라이언트가 다운그레이드를 거부한 경우 (allow_downgrade로 다시 배포)
+        let permanent = match req.failure_reason.as_deref() {
+            Some("boot_fallback") => Some("did not boot"),
+            Some("downgrade_refused") => Some("is older than the installed version"),
+            _ => None,
+        };
+        if let (true, Some(why)) = (targeted, permanent) {
+            tracing::warn!(
+                "Client {} ({}): {} {}; deploy cancelled",
+                client.name,
+                client.id,
+                req.version,
+                why
+            );
+            db::clear_client_target_version(&state.pool, client.id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            finish_deploy(&state, client, "failed", error_message.as_deref()).await?;
+        } else {
+            // 대기열에 다음 작업이 있으면 실패한 배포를 재시도하지 않고 넘어감
+            let mut client = client;
+            actions::advance(&state, &mut client).await?;
+        }
+
         Ok(Json(serde_json::json!({
             "message": "Update failure recorded",
             "version": req.version,
-            "error": req.error_message
+            "error": error_message,
+            "failure_fingerprint": classified.map(|c| c.fingerprint),
         })))
     }
 }
+
+/// 보고된 버전이 지금 타겟이거나 타겟이 없는지 (아니면 이미 다음 배포로 넘어간 뒤의 보고)
+fn targets_report(client: &Client, version: &str) -> bool {
+    client.target_version.as_deref().is_none_or(|target| target == version)
+}
+
+/// 전달된 배포 작업 종료 후 대기열의 다음 작업을 타겟으로 (다음 체크인에서 바로 받도록)
+async fn finish_deploy(
+    state: &AppState,
+    client: Client,
+    action_state: &str,
+    error_message: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    db::finish_deploy_action(&state.pool, client.id, action_state, error_message)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut client = Client {
+        target_version: None,
+        target_staged: false,
+        ..client
+    };
+    actions::advance(state, &mut client).await?;
+    Ok(())
+}
+
+/// 재시작/롤백 결과 보고
+/// POST /api/action-result
+/// Header: X-API-Key
+///
+/// 같은 결과를 다시 보내면 그대로 성공으로 응답한다 (재시도 보고).
+pub async fn report_action_result(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<ActionResultRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let api_key = extract_api_key(&headers)
+        .ok_or((StatusCode::UNAUTHORIZED, "X-API-Key header required".to_string()))?;
+
+    let mut client = db::get_client_by_api_key(&state.pool, &api_key)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::UNAUTHORIZED, "Invalid API key".to_string()))?;
+
+    let action = db::get_client_action(&state.pool, client.id, req.action_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Action not found".to_string()))?;
+    if action.action_type == "deploy" {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Deploy results are reported via POST /api/update-result".to_string(),
+        ));
+    }
+    if action.action_type == "survey" && req.success {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Survey results are reported via POST /api/survey-result".to_string(),
+        ));
+    }
+
+    let result_state = if req.success { "completed" } else { "failed" };
+    let error_message = req
+        .error_message
+        .as_deref()
+        .map(|message| failure::truncate(message, state.config.error_message_max_len));
+
+    let recorded = db::finish_client_action(&state.pool, action.id, result_state, error_message.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !recorded && action.state != result_state {
+        return Err((StatusCode::CONFLICT, format!("Action is already {}", action.state)));
+    }
+
+    if recorded && !req.success {
+        tracing::warn!(
+            "Client {} ({}): {} action {} failed: {}",
+            client.name,
+            client.id,
+            action.action_type,
+            action.id,
+            error_message.as_deref().unwrap_or("unknown error")
+        );
+        state.webhook.emit(
+            "client.action_failed",
+            serde_json::json!({
+                "client_id": client.id,
+                "client_name": client.name,
+                "action_id": action.id,
+                "type": action.action_type,
+                "error": error_message,
+                "initiated_by": action.payload.0.get("initiated_by"),
+            }),
+        );
+    }
+
+    actions::advance(&state, &mut client).await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Action result recorded",
+        "action_id": action.id,
+        "state": result_state
+    })))
+}
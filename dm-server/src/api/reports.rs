@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+};
+use chrono::{Duration, Utc};
+
+use super::versions;
+use crate::db::{self, IntegrityReport, SlaQuery, SlaReport};
+use crate::timefmt::{Localized, TzQuery};
+use crate::AppState;
+
+/// SLA 보고 최대 기간 (체크인 시간대 기록도 이 기간만 보존)
+pub(crate) const MAX_WINDOW_DAYS: i64 = 90;
+
+/// 업데이트 SLA 보고 (제공부터 헬스 체크 통과까지 걸린 시간의 백분위)
+/// GET /api/reports/sla?version=&severity=&window=&target_secs=
+/// Query: tz=<IANA 타임존> (로컬 표시 필드 추가)
+pub async fn get_sla_report(
+    State(state): State<AppState>,
+    Query(query): Query<SlaQuery>,
+    Query(tz): Query<TzQuery>,
+) -> Result<Localized<SlaReport>, (StatusCode, String)> {
+    let tz = tz.parse()?;
+    let window = parse_window(&query.window).ok_or((
+        StatusCode::BAD_REQUEST,
+        format!(
+            "window must be <n>h or <n>d, at most {}d: {}",
+            MAX_WINDOW_DAYS, query.window
+        ),
+    ))?;
+    if query.target_secs <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "target_secs must be positive".to_string()));
+    }
+    if let Some(severity) = query.severity.as_deref() {
+        versions::validate_severity(severity)?;
+    }
+
+    let window_start = Utc::now() - window;
+    let stats = db::sla_stats(
+        &state.pool,
+        query.version.as_deref(),
+        query.severity.as_deref(),
+        window_start,
+        query.target_secs,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let within_target_ratio =
+        (stats.offered > 0).then(|| stats.within_target as f64 / stats.offered as f64);
+    Ok(Localized(
+        SlaReport {
+            version: query.version,
+            severity: query.severity,
+            window_start,
+            target_secs: query.target_secs,
+            within_target_ratio,
+            stats,
+        },
+        tz,
+    ))
+}
+
+/// 설치 아티팩트 무결성 보고 (체크인에서 보고한 체크섬과 배포 기록 비교)
+/// GET /api/reports/integrity
+/// Query: tz=<IANA 타임존> (로컬 표시 필드 추가)
+pub async fn get_integrity_report(
+    State(state): State<AppState>,
+    Query(tz): Query<TzQuery>,
+) -> Result<Localized<IntegrityReport>, (StatusCode, String)> {
+    let tz = tz.parse()?;
+    let (total, ok, mismatched, unknown) = db::count_integrity(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mismatches = db::get_integrity_clients(&state.pool, Some(false))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let unknown_clients = db::get_integrity_clients(&state.pool, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Localized(
+        IntegrityReport {
+            total,
+            ok,
+            mismatched,
+            unknown,
+            mismatches,
+            unknown_clients,
+        },
+        tz,
+    ))
+}
+
+/// 조회 기간 파싱 ("24h", "7d")
+pub(crate) fn parse_window(window: &str) -> Option<Duration> {
+    let window = window.trim();
+    let (count, unit) = window.split_at(window.len().checked_sub(1)?);
+    let count: i64 = count.parse().ok().filter(|&n| n > 0)?;
+    let duration = match unit {
+        "h" => Duration::hours(count),
+        "d" => Duration::days(count),
+        _ => return None,
+    };
+    (duration <= Duration::days(MAX_WINDOW_DAYS)).then_some(duration)
+}
@@ -0,0 +1,78 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+};
+
+use crate::db::{self, SearchGroup, SearchQuery, SearchResults};
+use crate::timefmt::{Localized, TzQuery};
+use crate::AppState;
+
+/// 검색어 최대 길이
+const MAX_QUERY_LEN: usize = 100;
+
+/// 카테고리별 최대 결과 수
+const RESULTS_PER_CATEGORY: usize = 10;
+
+/// LIKE 와일드카드 이스케이프
+fn escape_like(q: &str) -> String {
+    let mut escaped = String::with_capacity(q.len());
+    for c in q.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 상위 N개로 자르고 잘림 여부 기록
+fn truncate<T>(mut items: Vec<T>) -> SearchGroup<T> {
+    let truncated = items.len() > RESULTS_PER_CATEGORY;
+    items.truncate(RESULTS_PER_CATEGORY);
+    SearchGroup { items, truncated }
+}
+
+/// 클라이언트/버전/업데이트 로그 통합 검색
+/// GET /api/search?q=...
+/// Query: tz=<IANA 타임존> (로컬 표시 필드 추가)
+pub async fn search(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+    Query(tz): Query<TzQuery>,
+) -> Result<Localized<SearchResults>, (StatusCode, String)> {
+    let tz = tz.parse()?;
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "q must not be empty".to_string()));
+    }
+    if q.chars().count() > MAX_QUERY_LEN {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("q must be at most {} characters", MAX_QUERY_LEN),
+        ));
+    }
+
+    let pattern = escape_like(q);
+    // 잘림 여부 판단을 위해 하나 더 조회
+    let limit = RESULTS_PER_CATEGORY as i64 + 1;
+
+    let clients = db::search_clients(&state.pool, &pattern, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let versions = db::search_versions(&state.pool, &pattern, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let update_logs = db::search_update_logs(&state.pool, &pattern, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Localized(
+        SearchResults {
+            query: q.to_string(),
+            clients: truncate(clients),
+            versions: truncate(versions),
+            update_logs: truncate(update_logs),
+        },
+        tz,
+    ))
+}
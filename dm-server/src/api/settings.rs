@@ -0,0 +1,111 @@
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{Duration, Utc};
+
+use crate::db::{self, CheckinShedSettings, PollHint, ServerSettings, SetPollHintRequest};
+use crate::AppState;
+
+/// 폴링 간격 힌트 최대값 (하루, 클라이언트도 DM_POLL_HINT_MAX_SECS로 다시 제한)
+const MAX_POLL_HINT_SECS: i32 = 86400;
+/// 힌트 최대 유지 시간 (일주일, 해제를 잊어도 장비가 계속 느리게 폴링하지 않도록)
+const MAX_POLL_HINT_DURATION_SECS: i64 = 7 * 86400;
+const MAX_REASON_LEN: usize = 500;
+const MAX_SET_BY_LEN: usize = 128;
+
+/// 서버 설정 (전체 폴링 간격 힌트, 체크인 부하 분산 규칙)
+/// GET /api/settings
+pub async fn get_settings(State(state): State<AppState>) -> Result<Json<ServerSettings>, (StatusCode, String)> {
+    let poll_hint = db::get_poll_hint(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(ServerSettings {
+        poll_hint,
+        checkin_shed: CheckinShedSettings {
+            max_in_flight: state.config.checkin_shed_in_flight,
+            retry_after_secs: state.config.checkin_shed_retry_secs,
+            in_flight: state.checkin_load.in_flight(),
+        },
+    }))
+}
+
+/// 전체 폴링 간격 힌트 지정 (장애 중 장비의 폴링을 잠시 늦춤)
+/// PUT /api/settings/poll-hint
+/// Body: {"next_poll_secs": 600, "duration_secs": 3600, "reason": "db failover", "set_by": "ops"}
+///
+/// 유지 시간 동안 모든 체크인 응답에 next_poll_secs를 붙인다. 클라이언트는 다음 대기 한 번에만
+/// 적용하므로 힌트가 만료되거나 해제되면 다음 체크인부터 평소 간격으로 돌아간다.
+pub async fn set_poll_hint(
+    State(state): State<AppState>,
+    Json(req): Json<SetPollHintRequest>,
+) -> Result<Json<PollHint>, (StatusCode, String)> {
+    if !(1..=MAX_POLL_HINT_SECS).contains(&req.next_poll_secs) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("next_poll_secs must be 1-{}", MAX_POLL_HINT_SECS),
+        ));
+    }
+    if !(1..=MAX_POLL_HINT_DURATION_SECS).contains(&req.duration_secs) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("duration_secs must be 1-{}", MAX_POLL_HINT_DURATION_SECS),
+        ));
+    }
+    if req.reason.as_ref().is_some_and(|s| s.len() > MAX_REASON_LEN) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("reason must be at most {} characters", MAX_REASON_LEN),
+        ));
+    }
+    if req.set_by.as_ref().is_some_and(|s| s.len() > MAX_SET_BY_LEN) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("set_by must be at most {} characters", MAX_SET_BY_LEN),
+        ));
+    }
+
+    let expires_at = Utc::now() + Duration::seconds(req.duration_secs);
+    let hint = db::set_poll_hint(
+        &state.pool,
+        req.next_poll_secs,
+        expires_at,
+        req.reason.as_deref(),
+        req.set_by.as_deref(),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.checkin_load.invalidate_hint();
+
+    tracing::warn!(
+        "Poll hint set: next_poll_secs={} until {} ({})",
+        hint.next_poll_secs,
+        hint.expires_at,
+        hint.reason.as_deref().unwrap_or("no reason")
+    );
+    state.webhook.emit(
+        "settings.poll_hint_set",
+        serde_json::json!({
+            "next_poll_secs": hint.next_poll_secs,
+            "expires_at": hint.expires_at,
+            "reason": hint.reason,
+            "set_by": hint.set_by,
+        }),
+    );
+    Ok(Json(hint))
+}
+
+/// 전체 폴링 간격 힌트 해제
+/// DELETE /api/settings/poll-hint
+pub async fn clear_poll_hint(State(state): State<AppState>) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let cleared = db::clear_poll_hint(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.checkin_load.invalidate_hint();
+    if !cleared {
+        return Err((StatusCode::NOT_FOUND, "No active poll hint".to_string()));
+    }
+
+    tracing::info!("Poll hint cleared");
+    state.webhook.emit("settings.poll_hint_cleared", serde_json::json!({}));
+    Ok(Json(serde_json::json!({
+        "message": "Poll hint cleared"
+    })))
+}
@@ -0,0 +1,302 @@
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+    Json,
+};
+use chrono::{Duration, Utc};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use super::actions;
+use super::artifacts::{artifact_response, gzip_attachment, open_artifact};
+use super::clients::generate_api_key;
+use crate::bundle::{self, BundleManifest, BundleManifestEntry};
+use crate::db::{
+    self, CreateShareRequest, RenewShareRequest, ShareDownloadQuery, ShareLink, ShareLinkAudit, ShareLinkResponse,
+    Version,
+};
+use crate::prefix::PublicPrefix;
+use crate::scan;
+use crate::AppState;
+
+/// 공유 링크 최대 유효 기간
+const MAX_SHARE_TTL_DAYS: i64 = 30;
+
+/// 공유 링크 최대 다운로드 횟수
+const MAX_SHARE_DOWNLOADS: i32 = 100;
+
+/// 버전 아티팩트 공유 링크 생성 (서버 자격 증명 없이 받는 일회성 URL)
+/// POST /api/versions/:version/share
+/// Body: {"expires_in": 86400, "max_downloads": 1, "created_by": "alice"}
+pub async fn create_share_link(
+    State(state): State<AppState>,
+    PublicPrefix(prefix): PublicPrefix,
+    Path(version): Path<String>,
+    Json(req): Json<CreateShareRequest>,
+) -> Result<Json<ShareLinkResponse>, (StatusCode, String)> {
+    if req.expires_in <= 0 || req.expires_in > MAX_SHARE_TTL_DAYS * 86400 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("expires_in must be between 1 and {} seconds", MAX_SHARE_TTL_DAYS * 86400),
+        ));
+    }
+    if !(1..=MAX_SHARE_DOWNLOADS).contains(&req.max_downloads) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("max_downloads must be between 1 and {}", MAX_SHARE_DOWNLOADS),
+        ));
+    }
+
+    let ver = db::get_version(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+    if !ver.is_active {
+        return Err((StatusCode::CONFLICT, format!("Version {} is deactivated", version)));
+    }
+    scan::ensure_deployable(&ver)?;
+
+    let created_by = req.created_by.as_deref().unwrap_or(actions::DEFAULT_INITIATOR);
+    let expires_at = Utc::now() + Duration::seconds(req.expires_in);
+    let link = db::create_share_link(
+        &state.pool,
+        &generate_api_key(),
+        &ver.version,
+        created_by,
+        expires_at,
+        req.max_downloads,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!(
+        "Share link for {} created by {} (expires {}, {} downloads)",
+        link.version,
+        link.created_by,
+        link.expires_at,
+        link.max_downloads
+    );
+    let url = format!("{}/api/share/{}", prefix, link.token);
+    Ok(Json(ShareLinkResponse { link, url }))
+}
+
+/// 버전의 공유 링크와 다운로드/연장 기록 (남은 다운로드 횟수, 만료까지 남은 시간 포함)
+/// GET /api/versions/:version/shares
+pub async fn list_share_links(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+) -> Result<Json<Vec<ShareLinkAudit>>, (StatusCode, String)> {
+    let links = db::list_share_links(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let downloads = db::list_share_downloads(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let renewals = db::list_share_renewals(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let now = Utc::now();
+    let audits = links
+        .into_iter()
+        .map(|link| ShareLinkAudit {
+            remaining_downloads: link.remaining_downloads(),
+            expires_in_secs: link.expires_in_secs(now),
+            downloads: downloads.iter().filter(|d| d.token == link.token).cloned().collect(),
+            renewals: renewals.iter().filter(|r| r.token == link.token).cloned().collect(),
+            link,
+        })
+        .collect();
+    Ok(Json(audits))
+}
+
+/// 공유 링크로 아티팩트 다운로드 (만료/취소/횟수 초과면 410)
+/// GET /api/share/:token?bundle=true
+/// Query: bundle (manifest.json과 아티팩트를 담은 tar.gz, `dm-client apply --dir`로 설치)
+///
+/// Range는 지원하지 않으며 요청마다 다운로드 1회로 센다.
+pub async fn download_shared_artifact(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<ShareDownloadQuery>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    // 차감 전에 확인 (버전/파일이 없을 때 횟수를 쓰지 않도록)
+    let link = get_link(&state, &token).await?;
+    if let Some(reason) = link.unavailable_reason(Utc::now()) {
+        return Err((StatusCode::GONE, reason.to_string()));
+    }
+    let ver = shared_version(&state, &link).await?;
+    if query.bundle && ver.is_image() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Version {} is a disk image and cannot be bundled", ver.version),
+        ));
+    }
+    let (file, size) = open_artifact(&state.config, &ver).await?;
+
+    // 동시에 받는 경우를 위해 조건부로 차감 (그 사이 소진/만료되면 410)
+    let link = match db::consume_share_link(&state.pool, &token)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        Some(link) => link,
+        None => {
+            let link = get_link(&state, &token).await?;
+            let reason = link.unavailable_reason(Utc::now()).unwrap_or("Share link unavailable");
+            return Err((StatusCode::GONE, reason.to_string()));
+        }
+    };
+
+    // 다운로드 기록 (기록 실패로 다운로드를 막지 않음)
+    let remote_addr = connect_info.map(|ConnectInfo(addr)| addr.ip().to_string());
+    let user_agent = headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok());
+    if let Err(e) =
+        db::record_share_download(&state.pool, &token, query.bundle, remote_addr.as_deref(), user_agent).await
+    {
+        tracing::warn!("Failed to record share download of {}: {}", ver.version, e);
+    }
+    if let Err(e) = db::record_artifact_download(&state.pool, &ver.version, None, &ver.checksum).await {
+        tracing::warn!("Failed to record download of {}: {}", ver.version, e);
+    }
+    tracing::info!(
+        "Share link for {} by {} downloaded ({}/{})",
+        ver.version,
+        link.created_by,
+        link.download_count,
+        link.max_downloads
+    );
+
+    if !query.bundle {
+        return artifact_response(file, size, &ver, None).await;
+    }
+
+    // manifest.json을 붙인 tar.gz (USB 번들과 같은 형식의 기본 항목 하나)
+    drop(file);
+    let filename = format!("sam-dm-share_{}.tar.gz", ver.version);
+    let manifest = BundleManifest {
+        entries: vec![BundleManifestEntry::new(None, &ver)],
+    };
+    let artifact_dir = PathBuf::from(&state.config.artifact_dir);
+    gzip_attachment(&filename, move |writer| {
+        bundle::write_bundle(&manifest, &[ver], &artifact_dir, writer)
+    })
+}
+
+/// 공유 링크 취소 (이후 다운로드는 410)
+/// DELETE /api/share/:token
+pub async fn revoke_share_link(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Json<ShareLink>, (StatusCode, String)> {
+    let link = db::revoke_share_link(&state.pool, &token)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Share link not found".to_string()))?;
+    tracing::info!("Share link for {} revoked", link.version);
+    Ok(Json(link))
+}
+
+/// 공유 링크 만료 시각 연장 (연장 기록은 링크 목록의 renewals에 남음)
+/// POST /api/share/:token/renew
+/// Body: {"extend_by": 86400, "renewed_by": "alice"}
+///
+/// 현재 만료 시각(이미 만료되었으면 지금)부터 연장하며, 새 만료 시각은 지금부터 최대 30일이다.
+/// 취소되었거나 다운로드 횟수를 모두 쓴 링크는 409 (새 링크를 만든다).
+pub async fn renew_share_link(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(req): Json<RenewShareRequest>,
+) -> Result<Json<ShareLink>, (StatusCode, String)> {
+    let max_ttl = Duration::days(MAX_SHARE_TTL_DAYS);
+    if req.extend_by <= 0 || req.extend_by > max_ttl.num_seconds() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("extend_by must be between 1 and {} seconds", max_ttl.num_seconds()),
+        ));
+    }
+
+    let link = get_link(&state, &token).await?;
+    let now = Utc::now();
+    let expires_at = link.expires_at.max(now) + Duration::seconds(req.extend_by);
+    if expires_at > now + max_ttl {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Share links cannot be valid for more than {} days from now", MAX_SHARE_TTL_DAYS),
+        ));
+    }
+
+    let renewed_by = req.renewed_by.as_deref().unwrap_or(actions::DEFAULT_INITIATOR);
+    let Some(renewed) = db::renew_share_link(&state.pool, &token, expires_at, renewed_by)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        let link = get_link(&state, &token).await?;
+        let reason = if link.revoked_at.is_some() {
+            "Share link revoked"
+        } else {
+            "Share link download limit reached"
+        };
+        return Err((StatusCode::CONFLICT, format!("{}; create a new link instead", reason)));
+    };
+
+    tracing::info!(
+        "Share link for {} renewed by {} until {} (was {})",
+        renewed.version,
+        renewed_by,
+        renewed.expires_at,
+        link.expires_at
+    );
+    Ok(Json(renewed))
+}
+
+/// 만료 임박 공유 링크 알림 (share.expiring 웹훅, 링크마다 한 번. 연장하면 다시 알림)
+///
+/// 리더 인스턴스의 백그라운드 작업이 주기적으로 호출한다. 알림을 보낸 링크 수를 돌려준다.
+pub async fn warn_expiring_share_links(state: &AppState) -> anyhow::Result<usize> {
+    let window = state.config.share_expiry_warning_secs;
+    if window == 0 {
+        return Ok(0);
+    }
+    let links = db::claim_expiring_share_links(&state.pool, window as i64).await?;
+    let now = Utc::now();
+    for link in &links {
+        tracing::info!("Share link for {} by {} expires at {}", link.version, link.created_by, link.expires_at);
+        state.webhook.emit(
+            "share.expiring",
+            serde_json::json!({
+                "token": link.token,
+                "version": link.version,
+                "created_by": link.created_by,
+                "expires_at": link.expires_at,
+                "expires_in_secs": link.expires_in_secs(now),
+                "remaining_downloads": link.remaining_downloads(),
+            }),
+        );
+    }
+    Ok(links.len())
+}
+
+async fn get_link(state: &AppState, token: &str) -> Result<ShareLink, (StatusCode, String)> {
+    db::get_share_link(&state.pool, token)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Share link not found".to_string()))
+}
+
+/// 링크의 버전 (삭제/비활성화되었거나 검사를 통과하지 못했으면 410)
+async fn shared_version(state: &AppState, link: &ShareLink) -> Result<Version, (StatusCode, String)> {
+    let ver = db::get_version(&state.pool, &link.version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .filter(|ver| ver.is_active)
+        .ok_or((
+            StatusCode::GONE,
+            format!("Version {} is no longer available", link.version),
+        ))?;
+    scan::ensure_deployable(&ver).map_err(|(_, message)| (StatusCode::GONE, message))?;
+    Ok(ver)
+}
@@ -0,0 +1,212 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::HeaderMap, StatusCode},
+    Json,
+};
+use uuid::Uuid;
+
+use super::actions::{self, DEFAULT_INITIATOR};
+use super::polling::extract_api_key;
+use crate::db::{self, ClientSurvey, QueueSurveyRequest, SurveyQuery, SurveyResultRequest};
+use crate::AppState;
+
+/// 요청할 수 있는 조사 항목 (클라이언트에 컴파일된 허용 목록과 같은 이름)
+///
+/// 서버는 이름만 보내며, 무엇을 어떻게 실행할지는 클라이언트가 정한다.
+pub const SURVEY_PRESETS: [&str; 5] = ["system", "disks", "network", "lsblk", "ip_addr"];
+
+/// 업로드할 수 있는 조사 결과 최대 크기 (바이트)
+const MAX_SURVEY_BYTES: usize = 256 * 1024;
+
+/// 조회할 수 있는 최대 조사 결과 수
+const MAX_SURVEY_LIMIT: i64 = 100;
+
+/// 장비 현황 조사 요청 (작업 대기열에 추가, 체크인에서 전달)
+/// POST /api/clients/:id/survey
+/// Body: {"commands": ["system", "disks"], "reason": "...", "initiated_by": "..."}
+pub async fn queue_survey(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<QueueSurveyRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let mut client = db::get_client_by_id(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+
+    if req.commands.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "commands must not be empty".to_string()));
+    }
+    if let Some(unknown) = req.commands.iter().find(|c| !SURVEY_PRESETS.contains(&c.as_str())) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown survey command {:?} (expected one of {})", unknown, SURVEY_PRESETS.join(", ")),
+        ));
+    }
+    let mut commands = req.commands.clone();
+    commands.sort_by_key(|c| SURVEY_PRESETS.iter().position(|p| p == c));
+    commands.dedup();
+
+    let initiated_by = req.initiated_by.as_deref().unwrap_or(DEFAULT_INITIATOR);
+    let payload = serde_json::json!({
+        "commands": commands,
+        "reason": req.reason,
+        "initiated_by": initiated_by
+    });
+    let action = db::create_client_action(&state.pool, id, "survey", &payload)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    actions::advance(&state, &mut client).await?;
+    let position = actions::queue_position(&state, id, action.id).await?;
+
+    tracing::info!(
+        "Client {} ({}): survey {:?} queued at position {}",
+        client.name,
+        id,
+        commands,
+        position
+    );
+
+    Ok(Json(serde_json::json!({
+        "message": "Survey queued",
+        "client_id": id,
+        "action_id": action.id,
+        "commands": commands,
+        "queue_position": position,
+        "initiated_by": initiated_by
+    })))
+}
+
+/// 클라이언트의 조사 결과 (최근 것부터)
+/// GET /api/clients/:id/surveys?limit=20
+pub async fn list_client_surveys(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<SurveyQuery>,
+) -> Result<Json<Vec<ClientSurvey>>, (StatusCode, String)> {
+    if !(1..=MAX_SURVEY_LIMIT).contains(&query.limit) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("limit must be between 1 and {}", MAX_SURVEY_LIMIT),
+        ));
+    }
+    db::get_client_by_id(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Client not found".to_string()))?;
+
+    let surveys = db::list_client_surveys(&state.pool, id, query.limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(surveys))
+}
+
+/// 조사 결과 업로드 (클라이언트 → 서버, 같은 결과의 재전송은 성공으로 처리)
+/// POST /api/survey-result
+///
+/// 요청하지 않은 항목이 있거나 MAX_SURVEY_BYTES를 넘으면 거부한다.
+/// 모든 항목이 실패했으면 작업은 failed로 끝난다.
+pub async fn report_survey_result(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<SurveyResultRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let api_key = extract_api_key(&headers)
+        .ok_or((StatusCode::UNAUTHORIZED, "X-API-Key header required".to_string()))?;
+
+    let mut client = db::get_client_by_api_key(&state.pool, &api_key)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::UNAUTHORIZED, "Invalid API key".to_string()))?;
+
+    let action = db::get_client_action(&state.pool, client.id, req.action_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Action not found".to_string()))?;
+    if action.action_type != "survey" {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Action {} is a {}, not a survey", action.id, action.action_type),
+        ));
+    }
+    // 재전송이면 기록된 결과를 그대로 응답 (만료/취소된 뒤 도착한 결과는 저장하지 않음)
+    if !action.is_open() {
+        if matches!(action.state.as_str(), "completed" | "failed") {
+            return Ok(Json(serde_json::json!({
+                "message": "Survey result recorded",
+                "action_id": action.id,
+                "state": action.state
+            })));
+        }
+        return Err((StatusCode::CONFLICT, format!("Action is already {}", action.state)));
+    }
+
+    let commands: Vec<String> = serde_json::from_value(action.payload.0["commands"].clone()).unwrap_or_default();
+    if let Some(unexpected) = req
+        .results
+        .keys()
+        .chain(req.errors.keys())
+        .find(|name| !commands.contains(name))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("{:?} was not requested by this survey", unexpected),
+        ));
+    }
+
+    let results = serde_json::Value::Object(req.results);
+    let errors = serde_json::json!(req.errors);
+    let size = serde_json::to_vec(&results).map(|v| v.len()).unwrap_or_default()
+        + serde_json::to_vec(&errors).map(|v| v.len()).unwrap_or_default();
+    if size > MAX_SURVEY_BYTES {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Survey result is {} bytes (limit {})", size, MAX_SURVEY_BYTES),
+        ));
+    }
+
+    db::create_client_survey(
+        &state.pool,
+        client.id,
+        action.id,
+        &commands,
+        &results,
+        &errors,
+        size as i32,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let collected = results.as_object().map(|r| r.len()).unwrap_or_default();
+    let (result_state, error_message) = if collected == 0 && !req.errors.is_empty() {
+        let summary = req
+            .errors
+            .iter()
+            .map(|(name, error)| format!("{}: {}", name, error))
+            .collect::<Vec<_>>()
+            .join("; ");
+        ("failed", Some(summary))
+    } else {
+        ("completed", None)
+    };
+    db::finish_client_action(&state.pool, action.id, result_state, error_message.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!(
+        "Client {} ({}): survey {} collected {} of {} items ({} bytes)",
+        client.name,
+        client.id,
+        action.id,
+        collected,
+        commands.len(),
+        size
+    );
+    actions::advance(&state, &mut client).await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Survey result recorded",
+        "action_id": action.id,
+        "state": result_state
+    })))
+}
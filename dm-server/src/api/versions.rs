@@ -1,26 +1,49 @@
 use axum::{
-    extract::{Multipart, Path, State},
-    http::StatusCode,
+    extract::{Multipart, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
     Json,
 };
-use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
 
-use crate::db::{self, Version};
+use crate::changelog;
+use crate::etag;
+use crate::db::{self, NewVersion, UpdateVersionRequest, Version, VersionProvenance, VersionRemovalQuery};
+use crate::scan;
+use crate::upload::{self, UploadForm, ValidationReport};
+use crate::timefmt::{Localized, TzQuery};
 use crate::AppState;
 
 /// 버전 목록 조회
 /// GET /api/versions
+/// Query: metadata.<key>=<value> (metadata 최상위 키 필터, AND 조건)
+///        tz=<IANA 타임존> (로컬 표시 필드 추가)
 pub async fn list_versions(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Version>>, (StatusCode, String)> {
-    let versions = db::get_all_versions(&state.pool)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Localized<Vec<Version>>, (StatusCode, String)> {
+    let tz = TzQuery {
+        tz: params.get("tz").cloned(),
+    }
+    .parse()?;
+    let filter: serde_json::Map<String, serde_json::Value> = params
+        .into_iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix("metadata.")
+                .map(|k| (k.to_string(), serde_json::Value::String(value)))
+        })
+        .collect();
+
+    let versions = if filter.is_empty() {
+        db::get_all_versions(&state.pool).await
+    } else {
+        db::get_versions_by_metadata(&state.pool, &serde_json::Value::Object(filter)).await
+    }
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(versions))
+    Ok(Localized(versions, tz))
 }
 
 /// 버전 상세 조회
@@ -37,116 +60,433 @@ pub async fn get_version(
     Ok(Json(ver))
 }
 
+/// 제품 식별자 최대 길이
+const MAX_PRODUCT_LEN: usize = 64;
+
+/// 버전 심각도 (첫 값이 기본값)
+pub(crate) const SEVERITIES: [&str; 2] = ["normal", "security"];
+
 /// 새 버전 업로드
 /// POST /api/versions
-/// multipart form: version, artifact (file), release_notes (optional)
+/// Header: X-Uploaded-By (optional, 업로드 주체)
+/// multipart form: version, artifact (file), release_notes (optional, 4KB 이하 요약),
+/// changelog (optional, 긴 변경 이력 Markdown - 파일 또는 텍스트),
+/// git_commit (optional), build_time (optional, RFC3339),
+/// metadata (optional, JSON object), metadata.<key> (optional, text),
+/// checksum (optional, CI에서 계산한 SHA256 - 다르면 거부),
+/// pre_install_script / post_install_script (optional, 설치 전/후 셸 스크립트),
+/// deploy_type (optional, app | image, 기본 app),
+/// severity (optional, normal | security, 기본 normal),
+/// product (optional, 대상 제품 - 아티팩트의 `.dm-product`와 같은 값)
+///
+/// 검증 규칙은 POST /api/versions/validate와 같으며, 처음 실패한 규칙의 오류로 거부한다.
 pub async fn upload_version(
     State(state): State<AppState>,
-    mut multipart: Multipart,
+    headers: HeaderMap,
+    multipart: Multipart,
 ) -> Result<Json<Version>, (StatusCode, String)> {
-    let mut version_str: Option<String> = None;
-    let mut release_notes: Option<String> = None;
-    let mut file_data: Option<Vec<u8>> = None;
-    let mut file_name: Option<String> = None;
-
-    // Parse multipart form
-    while let Some(field) = multipart
-        .next_field()
-        .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
-    {
-        let name = field.name().unwrap_or("").to_string();
-
-        match name.as_str() {
-            "version" => {
-                version_str = Some(
-                    field
-                        .text()
-                        .await
-                        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
-                );
-            }
-            "release_notes" => {
-                release_notes = Some(
-                    field
-                        .text()
-                        .await
-                        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
-                );
-            }
-            "artifact" => {
-                file_name = field.file_name().map(|s| s.to_string());
-                file_data = Some(
-                    field
-                        .bytes()
-                        .await
-                        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
-                        .to_vec(),
-                );
+    let form = UploadForm::read(std::path::Path::new(&state.config.artifact_dir), &headers, multipart).await?;
+    let (report, upload) = upload::validate(&state, &form).await?;
+    if let Some(failure) = report.first_failure() {
+        return Err(failure);
+    }
+    let (Some(upload), Some(artifact)) = (upload, form.artifact) else {
+        return Err((StatusCode::BAD_REQUEST, "artifact file required".to_string()));
+    };
+
+    // Save file (파일 이름 없이 올린 아티팩트는 내용으로 판별한 형식의 확장자)
+    let extension = match &upload.original_filename {
+        Some(name) => super::artifacts::artifact_extension(name),
+        None => upload.detected_extension.map(str::to_string),
+    };
+    let artifact_filename = match extension {
+        Some(ext) => format!("{}.{}", upload.version, ext),
+        None => upload.version.clone(),
+    };
+    let artifact_path: PathBuf = [&state.config.artifact_dir, &artifact_filename]
+        .iter()
+        .collect();
+
+    // Save to database
+    let version = db::create_version(
+        &state.pool,
+        &NewVersion {
+            version: &upload.version,
+            artifact_path: &artifact_filename,
+            original_filename: upload.original_filename.as_deref(),
+            artifact_size: artifact.size as i64,
+            checksum: &upload.checksum,
+            release_notes: upload.release_notes.as_deref(),
+            git_commit: upload.git_commit.as_deref(),
+            build_time: upload.build_time,
+            metadata: &upload.metadata,
+            uploaded_by: upload.uploaded_by.as_deref(),
+            pre_install_script: upload.pre_install_script.as_deref(),
+            post_install_script: upload.post_install_script.as_deref(),
+            scan_status: scan::initial_status(&state).as_str(),
+            deploy_type: &upload.deploy_type,
+            has_changelog: upload.changelog.is_some(),
+            product: upload.product.as_deref(),
+            severity: &upload.severity,
+            signature: upload.signature.as_deref(),
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // 버전 행이 생긴 뒤 받아 둔 임시 파일을 제자리로 옮김 (실패하면 행도 지움)
+    if let Err(e) = artifact.file.persist(&artifact_path) {
+        tracing::error!("Failed to store artifact for {}: {}", version.version, e);
+        discard_upload(&state, &version.version, &[]).await;
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+    }
+
+    // 변경 이력은 행과 아티팩트가 자리 잡은 뒤에 저장 (행을 만들지 못한 업로드가 남기거나 덮어쓰지 않게)
+    if let Some(text) = &upload.changelog {
+        let changelog_path = std::path::Path::new(&state.config.artifact_dir).join(changelog::file_name(&version.version));
+        if let Err(e) = fs::write(&changelog_path, text).await {
+            tracing::error!("Failed to store changelog for {}: {}", version.version, e);
+            discard_upload(&state, &version.version, &[&artifact_path, &changelog_path]).await;
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    // 아티팩트 검사 (SCAN_COMMAND 설정 시, 끝날 때까지 배포 불가)
+    scan::spawn(state.clone(), version.version.clone(), version.artifact_path.clone());
+
+    Ok(Json(version))
+}
+
+/// 저장하다 실패한 업로드 정리 (이미 옮긴 파일과 버전 행 삭제)
+async fn discard_upload(state: &AppState, version: &str, files: &[&std::path::Path]) {
+    for path in files {
+        if let Err(e) = fs::remove_file(path).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("Failed to remove {} after storage error: {}", path.display(), e);
             }
-            _ => {}
         }
     }
+    if let Err(e) = db::delete_version(&state.pool, version).await {
+        tracing::warn!("Failed to remove version {} after storage error: {}", version, e);
+    }
+}
+
+/// 업로드 사전 검증 (DB와 아티팩트 디렉토리에 쓰지 않음, CI용)
+/// POST /api/versions/validate
+/// multipart form: POST /api/versions와 같음. artifact 대신 artifact_size, checksum, file_name만 보내면
+/// 파일 내용이 필요한 규칙(아카이브 내용, 제품 표식)은 skipped
+///
+/// 모든 규칙을 통과하면 200, 하나라도 실패하면 422 (본문은 둘 다 규칙별 결과)
+pub async fn validate_version_upload(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<ValidationReport>), (StatusCode, String)> {
+    let form = UploadForm::read(&std::env::temp_dir(), &headers, multipart).await?;
+    let (report, _) = upload::validate(&state, &form).await?;
+    let status = if report.valid {
+        StatusCode::OK
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY
+    };
+    Ok((status, Json(report)))
+}
+
+/// 버전 속성 변경 (심각도)
+/// PATCH /api/versions/:version
+/// Body: {"severity": "security"}
+pub async fn update_version(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+    Json(req): Json<UpdateVersionRequest>,
+) -> Result<Json<Version>, (StatusCode, String)> {
+    let Some(severity) = req.severity.as_deref().map(str::trim) else {
+        return Err((StatusCode::BAD_REQUEST, "Nothing to update".to_string()));
+    };
+    validate_severity(severity)?;
+
+    let ver = db::set_version_severity(&state.pool, &version, severity)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+    tracing::info!("Version {} severity set to {}", ver.version, ver.severity);
+    Ok(Json(ver))
+}
+
+/// 심각도 값 검증
+pub(crate) fn validate_severity(severity: &str) -> Result<(), (StatusCode, String)> {
+    if SEVERITIES.contains(&severity) {
+        return Ok(());
+    }
+    Err((
+        StatusCode::BAD_REQUEST,
+        format!("severity must be one of: {}", SEVERITIES.join(", ")),
+    ))
+}
+
+/// 제품 식별자 검증 (a-z, 0-9, '-', '.', '_')
+pub(crate) fn validate_product(product: &str) -> Result<(), (StatusCode, String)> {
+    let valid = !product.is_empty()
+        && product.len() <= MAX_PRODUCT_LEN
+        && product
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-._".contains(c));
+    if !valid {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "product must be 1-{} characters of a-z, 0-9, '-', '.', '_'",
+                MAX_PRODUCT_LEN
+            ),
+        ));
+    }
+    Ok(())
+}
 
-    let version_str =
-        version_str.ok_or((StatusCode::BAD_REQUEST, "version field required".to_string()))?;
-    let file_data =
-        file_data.ok_or((StatusCode::BAD_REQUEST, "artifact file required".to_string()))?;
+/// 버전 설치 스크립트 조회 (클라이언트가 체크인 응답의 해시로 검증, ETag/If-None-Match 지원)
+/// GET /api/versions/:version/scripts
+pub async fn get_version_scripts(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let ver = db::get_version(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
 
-    // Validate semver
-    semver::Version::parse(&version_str)
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid semver: {}", e)))?;
+    etag::json(&headers, &ver.install_scripts().unwrap_or_default())
+}
 
-    // Check if version already exists
-    if db::get_version(&state.pool, &version_str)
+/// 버전 변경 이력 (업로드 시 changelog로 받은 Markdown 원문, ETag/If-None-Match 지원)
+/// GET /api/versions/:version/changelog
+pub async fn get_version_changelog(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let ver = db::get_version(&state.pool, &version)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .is_some()
-    {
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+    if !ver.has_changelog {
         return Err((
-            StatusCode::CONFLICT,
-            format!("Version {} already exists", version_str),
+            StatusCode::NOT_FOUND,
+            format!("Version {} has no changelog", version),
         ));
     }
 
-    // Calculate checksum
-    let mut hasher = Sha256::new();
-    hasher.update(&file_data);
-    let checksum = format!("{:x}", hasher.finalize());
+    let path = std::path::Path::new(&state.config.artifact_dir).join(changelog::file_name(&ver.version));
+    let text = fs::read_to_string(&path)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, "Changelog file not found".to_string()))?;
 
-    // Save file
-    let file_ext = file_name
-        .as_ref()
-        .and_then(|n| n.rsplit('.').next())
-        .unwrap_or("tar.gz");
-    let artifact_filename = format!("{}.{}", version_str, file_ext);
-    let artifact_path: PathBuf = [&state.config.artifact_dir, &artifact_filename]
-        .iter()
-        .collect();
+    Ok(etag::respond(&headers, changelog::CONTENT_TYPE, text.into_bytes()))
+}
+
+/// 버전 출처 추적 (업로드 체크섬 → 서버 전송 체크섬 → 장비 검증 체크섬)
+/// GET /api/versions/:version/provenance
+/// Query: tz=<IANA 타임존> (로컬 표시 필드 추가)
+pub async fn get_version_provenance(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+    Query(tz): Query<TzQuery>,
+) -> Result<Localized<VersionProvenance>, (StatusCode, String)> {
+    let tz = tz.parse()?;
+    let ver = db::get_version(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
 
-    // Ensure artifact directory exists
-    fs::create_dir_all(&state.config.artifact_dir)
+    let mut clients = db::get_client_provenance(&state.pool, &version)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let mut file = fs::File::create(&artifact_path)
+    for client in &mut clients {
+        if client.served_checksum.as_ref().is_some_and(|c| *c != ver.checksum) {
+            client.mismatch.push("served");
+        }
+        if client.verified_checksum.as_ref().is_some_and(|c| *c != ver.checksum) {
+            client.mismatch.push("verified");
+        }
+    }
+    let mismatches = clients.iter().filter(|c| !c.mismatch.is_empty()).count();
+
+    Ok(Localized(
+        VersionProvenance {
+            build_info: ver.build_info(),
+            version: ver.version,
+            checksum: ver.checksum,
+            uploaded_by: ver.uploaded_by,
+            uploaded_at: ver.created_at,
+            clients,
+            mismatches,
+        },
+        tz,
+    ))
+}
+
+/// 아티팩트 재검사 (검사 명령 변경, 시그니처 갱신 후 등)
+/// POST /api/versions/:version/rescan
+pub async fn rescan_version(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if state.config.scan_command.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "SCAN_COMMAND is not configured".to_string(),
+        ));
+    }
+
+    let ver = db::get_version(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+
+    db::set_version_scan_pending(&state.pool, &version)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    file.write_all(&file_data)
+    scan::spawn(state.clone(), ver.version, ver.artifact_path);
+
+    Ok(Json(serde_json::json!({
+        "message": "Scan started",
+        "version": version,
+        "scan_status": scan::ScanStatus::Pending.as_str(),
+    })))
+}
+
+/// 버전 활성화
+/// POST /api/versions/:version/activate
+pub async fn activate_version(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    db::get_version(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+
+    db::set_version_active(&state.pool, &version, true)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    // Save to database
-    let version = db::create_version(
-        &state.pool,
-        &version_str,
-        &artifact_filename,
-        file_data.len() as i64,
-        &checksum,
-        release_notes.as_deref(),
-    )
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(serde_json::json!({
+        "message": "Version activated",
+        "version": version
+    })))
+}
 
-    Ok(Json(version))
+/// 버전 비활성화
+/// POST /api/versions/:version/deactivate?clear_targets=true
+pub async fn deactivate_version(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+    Query(query): Query<VersionRemovalQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    db::get_version(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+
+    db::set_version_active(&state.pool, &version, false)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let (orphaned, cleared) = handle_orphaned_targets(&state, &version, query.clear_targets).await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Version deactivated",
+        "version": version,
+        "orphaned_targets": orphaned,
+        "cleared_targets": cleared
+    })))
+}
+
+/// 버전 삭제 (아티팩트 파일 포함)
+/// DELETE /api/versions/:version?clear_targets=true
+pub async fn delete_version(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+    Query(query): Query<VersionRemovalQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let ver = db::get_version(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Version not found".to_string()))?;
+
+    // 별칭이 가리키는 버전은 별칭을 먼저 옮겨야 삭제 가능
+    let aliases = db::aliases_for_version(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !aliases.is_empty() {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Version {} is referenced by aliases: {}", version, aliases.join(", ")),
+        ));
+    }
+
+    db::delete_version(&state.pool, &version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let artifact_path = std::path::Path::new(&state.config.artifact_dir).join(&ver.artifact_path);
+    if let Err(e) = fs::remove_file(&artifact_path).await {
+        tracing::warn!("Failed to remove artifact {:?}: {}", artifact_path, e);
+    }
+    if ver.has_changelog {
+        let changelog_path = std::path::Path::new(&state.config.artifact_dir).join(changelog::file_name(&version));
+        if let Err(e) = fs::remove_file(&changelog_path).await {
+            tracing::warn!("Failed to remove changelog {:?}: {}", changelog_path, e);
+        }
+    }
+
+    let (orphaned, cleared) = handle_orphaned_targets(&state, &version, query.clear_targets).await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Version deleted",
+        "version": version,
+        "orphaned_targets": orphaned,
+        "cleared_targets": cleared
+    })))
+}
+
+/// 더 이상 배포할 수 없는 버전을 타겟으로 가진 클라이언트 처리
+/// (타겟 클라이언트 수, 클리어 여부) 반환
+///
+/// 클리어하면 이 버전의 대기 중인 배포 작업도 취소한다.
+async fn handle_orphaned_targets(
+    state: &AppState,
+    version: &str,
+    clear_targets: bool,
+) -> Result<(i64, bool), (StatusCode, String)> {
+    if clear_targets {
+        let reason = format!("Version {} is no longer deployable", version);
+        db::cancel_deploy_actions_for_version(&state.pool, version, &reason)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    let orphaned = db::count_clients_targeting(&state.pool, version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if orphaned == 0 {
+        return Ok((0, false));
+    }
+
+    if clear_targets {
+        db::clear_targets_for_version(&state.pool, version)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        tracing::info!("Cleared target {} from {} clients", version, orphaned);
+    } else {
+        tracing::warn!(
+            "Version {} is no longer deployable but still targeted by {} clients",
+            version,
+            orphaned
+        );
+    }
+
+    Ok((orphaned, clear_targets))
 }
@@ -0,0 +1,458 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::changelog;
+use crate::config::Config;
+
+/// 내보내기 아카이브 식별자
+pub const FORMAT: &str = "sam-dm-export";
+
+/// 내보내기 형식 버전 (테이블 구성이 바뀌면 증가)
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const CLIENTS_FILE: &str = "clients.json";
+const VERSIONS_FILE: &str = "versions.json";
+const UPDATE_LOGS_FILE: &str = "update_logs.json";
+const ARTIFACTS_DIR: &str = "artifacts";
+
+/// 내보내기 옵션
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportOptions {
+    /// API Key 원문과 웹훅 URL 포함
+    #[serde(default)]
+    pub include_secrets: bool,
+    /// 업데이트 로그 포함
+    #[serde(default)]
+    pub include_update_logs: bool,
+}
+
+/// 아카이브 manifest.json
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub format: String,
+    pub format_version: u32,
+    pub server_version: String,
+    pub exported_at: DateTime<Utc>,
+    pub includes_secrets: bool,
+    pub includes_update_logs: bool,
+    pub counts: ExportCounts,
+    pub settings: ExportSettings,
+    /// 아티팩트 파일이 없어 포함되지 않은 버전
+    #[serde(default)]
+    pub missing_artifacts: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ExportCounts {
+    pub clients: usize,
+    pub versions: usize,
+    pub update_logs: usize,
+    pub artifacts: usize,
+}
+
+/// 환경 변수로 관리되는 서버 설정 (가져오기 시 안내용)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ExportSettings {
+    pub webhook_configured: bool,
+    /// include_secrets일 때만 포함
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+}
+
+/// 내보낼 데이터 (아카이브 스트리밍 전에 모두 조회)
+pub struct ExportData {
+    pub manifest: ExportManifest,
+    clients: Vec<Value>,
+    versions: Vec<Value>,
+    update_logs: Option<Vec<Value>>,
+    /// 아티팩트와 변경 이력 파일 이름 (artifact_dir 기준)
+    artifacts: Vec<String>,
+}
+
+/// 테이블 전체를 JSON 행 목록으로 조회 (스키마 변경에도 모든 컬럼 포함)
+async fn dump_table(pool: &PgPool, sql: &str) -> Result<Vec<Value>> {
+    let rows: Vec<sqlx::types::Json<Value>> = sqlx::query_scalar(sql).fetch_all(pool).await?;
+    Ok(rows.into_iter().map(|row| row.0).collect())
+}
+
+/// 내보낼 데이터 수집
+pub async fn collect(pool: &PgPool, config: &Config, opts: &ExportOptions) -> Result<ExportData> {
+    let mut clients =
+        dump_table(pool, "SELECT to_jsonb(c) FROM clients c ORDER BY created_at").await?;
+    let versions =
+        dump_table(pool, "SELECT to_jsonb(v) FROM versions v ORDER BY created_at").await?;
+    let update_logs = if opts.include_update_logs {
+        Some(dump_table(pool, "SELECT to_jsonb(l) FROM update_logs l ORDER BY started_at").await?)
+    } else {
+        None
+    };
+
+    // API Key는 secrets 옵션이 없으면 해시만 포함
+    if !opts.include_secrets {
+        for client in clients.iter_mut().filter_map(Value::as_object_mut) {
+            if let Some(Value::String(key)) = client.remove("api_key") {
+                client.insert(
+                    "api_key_sha256".to_string(),
+                    Value::String(format!("{:x}", Sha256::digest(key.as_bytes()))),
+                );
+            }
+        }
+    }
+
+    let mut artifacts = Vec::new();
+    let mut missing_artifacts = Vec::new();
+    for version in &versions {
+        let name = version["version"].as_str().unwrap_or_default();
+        let path = version["artifact_path"].as_str().unwrap_or_default();
+        if Path::new(&config.artifact_dir).join(path).is_file() {
+            artifacts.push(path.to_string());
+        } else {
+            tracing::warn!("Export: artifact for {} not found ({})", name, path);
+            missing_artifacts.push(name.to_string());
+        }
+
+        if version["has_changelog"].as_bool().unwrap_or(false) {
+            let changelog = changelog::file_name(name);
+            if Path::new(&config.artifact_dir).join(&changelog).is_file() {
+                artifacts.push(changelog);
+            } else {
+                tracing::warn!("Export: changelog for {} not found ({})", name, changelog);
+            }
+        }
+    }
+
+    let manifest = ExportManifest {
+        format: FORMAT.to_string(),
+        format_version: FORMAT_VERSION,
+        server_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Utc::now(),
+        includes_secrets: opts.include_secrets,
+        includes_update_logs: opts.include_update_logs,
+        counts: ExportCounts {
+            clients: clients.len(),
+            versions: versions.len(),
+            update_logs: update_logs.as_ref().map_or(0, Vec::len),
+            artifacts: artifacts.len(),
+        },
+        settings: ExportSettings {
+            webhook_configured: config.webhook_url.is_some(),
+            webhook_url: config.webhook_url.clone().filter(|_| opts.include_secrets),
+        },
+        missing_artifacts,
+    };
+
+    Ok(ExportData {
+        manifest,
+        clients,
+        versions,
+        update_logs,
+        artifacts,
+    })
+}
+
+/// tar.gz 아카이브 작성 (blocking)
+pub fn write_archive<W: Write>(data: &ExportData, artifact_dir: &Path, writer: W) -> Result<W> {
+    let mut builder = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+
+    append_json(&mut builder, MANIFEST_FILE, &data.manifest)?;
+    append_json(&mut builder, CLIENTS_FILE, &data.clients)?;
+    append_json(&mut builder, VERSIONS_FILE, &data.versions)?;
+    if let Some(update_logs) = &data.update_logs {
+        append_json(&mut builder, UPDATE_LOGS_FILE, update_logs)?;
+    }
+
+    for name in &data.artifacts {
+        builder
+            .append_path_with_name(artifact_dir.join(name), Path::new(ARTIFACTS_DIR).join(name))
+            .with_context(|| format!("Failed to add artifact {}", name))?;
+    }
+
+    let mut writer = builder.into_inner()?.finish()?;
+    writer.flush()?;
+    Ok(writer)
+}
+
+pub(crate) fn append_json<W: Write, T: Serialize>(builder: &mut tar::Builder<W>, name: &str, value: &T) -> Result<()> {
+    append_bytes(builder, name, &serde_json::to_vec_pretty(value)?)
+}
+
+/// 메모리의 내용을 tar 항목으로 추가
+pub(crate) fn append_bytes<W: Write>(builder: &mut tar::Builder<W>, name: &str, bytes: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp() as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, name, bytes)?;
+    Ok(())
+}
+
+/// 가져오기 결과
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub clients: usize,
+    pub versions: usize,
+    pub update_logs: usize,
+    pub artifacts: usize,
+    /// API Key 없이 내보내져 새로 발급된 클라이언트 (이름, 새 Key)
+    pub regenerated_keys: Vec<(String, String)>,
+    pub settings: ExportSettings,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "🦊 가져오기 완료")?;
+        writeln!(f, "   클라이언트: {}", self.clients)?;
+        writeln!(f, "   버전: {} (아티팩트 {})", self.versions, self.artifacts)?;
+        writeln!(f, "   업데이트 로그: {}", self.update_logs)?;
+
+        if !self.regenerated_keys.is_empty() {
+            writeln!(
+                f,
+                "⚠️  API Key 없이 내보낸 아카이브입니다. 다음 클라이언트에 새 Key를 배포해야 합니다:"
+            )?;
+            for (name, key) in &self.regenerated_keys {
+                writeln!(f, "   {}: {}", name, key)?;
+            }
+        }
+
+        match &self.settings.webhook_url {
+            Some(url) => writeln!(f, "   WEBHOOK_URL={} (환경 변수로 설정하세요)", url)?,
+            None if self.settings.webhook_configured => {
+                writeln!(f, "   원본 서버는 WEBHOOK_URL을 사용했습니다 (아카이브에 미포함)")?
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// 내보내기 아카이브를 데이터베이스/아티팩트 디렉토리로 복원
+///
+/// 기존 데이터와 충돌(동일 ID, API Key, 버전, 아티팩트 파일)이 하나라도 있으면
+/// 아무것도 변경하지 않고 중단한다.
+pub async fn import(pool: &PgPool, artifact_dir: &str, file: &Path) -> Result<ImportReport> {
+    fs::create_dir_all(artifact_dir)?;
+
+    // 1. 아티팩트 디렉토리 안에 임시 추출 (같은 파일시스템에서 rename)
+    let staging = tempfile::TempDir::new_in(artifact_dir)?;
+    {
+        let archive = fs::File::open(file).with_context(|| format!("Cannot open {:?}", file))?;
+        tar::Archive::new(GzDecoder::new(archive))
+            .unpack(staging.path())
+            .context("Failed to extract export archive")?;
+    }
+
+    // 2. 형식 검증
+    let manifest: ExportManifest = read_json(staging.path(), MANIFEST_FILE)?;
+    if manifest.format != FORMAT {
+        anyhow::bail!("Not a Sam DM export archive (format: {})", manifest.format);
+    }
+    if manifest.format_version != FORMAT_VERSION {
+        anyhow::bail!(
+            "Unsupported export format version {} (this server supports {})",
+            manifest.format_version,
+            FORMAT_VERSION
+        );
+    }
+
+    let mut clients: Vec<Value> = read_json(staging.path(), CLIENTS_FILE)?;
+    let versions: Vec<Value> = read_json(staging.path(), VERSIONS_FILE)?;
+    let mut update_logs: Vec<Value> = if manifest.includes_update_logs {
+        read_json(staging.path(), UPDATE_LOGS_FILE)?
+    } else {
+        Vec::new()
+    };
+
+    if clients.len() != manifest.counts.clients
+        || versions.len() != manifest.counts.versions
+        || update_logs.len() != manifest.counts.update_logs
+    {
+        anyhow::bail!("Export archive is incomplete: row counts do not match manifest");
+    }
+
+    // client_name이 없던 서버에서 내보낸 로그는 아카이브의 클라이언트 이름으로 채움
+    for log in update_logs.iter_mut().filter_map(Value::as_object_mut) {
+        if log.get("client_name").is_some_and(Value::is_string) {
+            continue;
+        }
+        let name = clients
+            .iter()
+            .find(|c| c["id"] == log["client_id"])
+            .and_then(|c| c["name"].as_str())
+            .or_else(|| log["client_id"].as_str())
+            .unwrap_or_default()
+            .to_string();
+        log.insert("client_name".to_string(), Value::String(name));
+    }
+
+    // 3. 아티팩트 검증 (파일 존재 + 체크섬)
+    let mut artifacts: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for version in &versions {
+        let name = str_field(version, "version")?;
+        if manifest.missing_artifacts.iter().any(|m| m == name) {
+            anyhow::bail!("Version {} was exported without its artifact file", name);
+        }
+
+        let artifact_path = str_field(version, "artifact_path")?;
+        if Path::new(artifact_path).components().count() != 1 {
+            anyhow::bail!("Invalid artifact path for {}: {}", name, artifact_path);
+        }
+        let source = staging.path().join(ARTIFACTS_DIR).join(artifact_path);
+        let data = fs::read(&source).with_context(|| format!("Artifact for {} missing from archive", name))?;
+        if format!("{:x}", Sha256::digest(&data)) != str_field(version, "checksum")? {
+            anyhow::bail!("Checksum mismatch for artifact of {}", name);
+        }
+        artifacts.push((source, Path::new(artifact_dir).join(artifact_path)));
+
+        // 변경 이력은 선택 사항 (없으면 조회 시 404)
+        if version["has_changelog"].as_bool().unwrap_or(false) {
+            let changelog = changelog::file_name(name);
+            if Path::new(&changelog).components().count() != 1 {
+                anyhow::bail!("Invalid changelog path for {}: {}", name, changelog);
+            }
+            let source = staging.path().join(ARTIFACTS_DIR).join(&changelog);
+            if source.is_file() {
+                artifacts.push((source, Path::new(artifact_dir).join(changelog)));
+            } else {
+                tracing::warn!("Changelog for {} missing from archive", name);
+            }
+        }
+    }
+
+    // 4. 충돌 검사
+    let mut conflicts = Vec::new();
+    let client_ids = uuid_fields(&clients, "id")?;
+    let api_keys: Vec<String> = clients
+        .iter()
+        .filter_map(|c| c["api_key"].as_str().map(str::to_string))
+        .collect();
+    let existing: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM clients WHERE id = ANY($1) OR api_key = ANY($2)",
+    )
+    .bind(&client_ids)
+    .bind(&api_keys)
+    .fetch_all(pool)
+    .await?;
+    conflicts.extend(existing.into_iter().map(|name| format!("client {}", name)));
+
+    let version_names: Vec<String> = versions
+        .iter()
+        .filter_map(|v| v["version"].as_str().map(str::to_string))
+        .collect();
+    let existing: Vec<String> = sqlx::query_scalar(
+        "SELECT version FROM versions WHERE id = ANY($1) OR version = ANY($2)",
+    )
+    .bind(uuid_fields(&versions, "id")?)
+    .bind(&version_names)
+    .fetch_all(pool)
+    .await?;
+    conflicts.extend(existing.into_iter().map(|v| format!("version {}", v)));
+
+    let existing: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM update_logs WHERE id = ANY($1)")
+        .bind(uuid_fields(&update_logs, "id")?)
+        .fetch_one(pool)
+        .await?;
+    if existing > 0 {
+        conflicts.push(format!("{} update logs", existing));
+    }
+
+    for (_, target) in &artifacts {
+        if target.exists() {
+            conflicts.push(format!("artifact file {:?}", target));
+        }
+    }
+
+    if !conflicts.is_empty() {
+        anyhow::bail!(
+            "Import aborted, target already contains:\n  {}",
+            conflicts.join("\n  ")
+        );
+    }
+
+    // 5. API Key 없이 내보낸 클라이언트는 새 Key 발급
+    let mut report = ImportReport::default();
+    for client in clients.iter_mut().filter_map(Value::as_object_mut) {
+        client.remove("api_key_sha256");
+        if !client.contains_key("api_key") {
+            let key = crate::api::clients::generate_api_key();
+            let name = client.get("name").and_then(Value::as_str).unwrap_or_default();
+            report.regenerated_keys.push((name.to_string(), key.clone()));
+            client.insert("api_key".to_string(), Value::String(key));
+        }
+    }
+
+    // 6. 트랜잭션으로 복원 후 아티팩트 이동
+    let mut tx = pool.begin().await?;
+    insert_rows(&mut tx, "clients", &clients).await?;
+    insert_rows(&mut tx, "versions", &versions).await?;
+    insert_rows(&mut tx, "update_logs", &update_logs).await?;
+
+    let mut moved: Vec<&Path> = Vec::new();
+    for (source, target) in &artifacts {
+        if let Err(e) = fs::rename(source, target) {
+            for path in moved {
+                let _ = fs::remove_file(path);
+            }
+            return Err(e).with_context(|| format!("Failed to move artifact to {:?}", target));
+        }
+        moved.push(target);
+    }
+
+    tx.commit().await?;
+
+    report.clients = clients.len();
+    report.versions = versions.len();
+    report.update_logs = update_logs.len();
+    report.artifacts = artifacts.len();
+    report.settings = manifest.settings;
+    Ok(report)
+}
+
+/// JSON 행 목록을 테이블에 삽입 (행 타입 기준으로 컬럼 매핑)
+async fn insert_rows(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    table: &str,
+    rows: &[Value],
+) -> Result<()> {
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let sql = format!(
+        "INSERT INTO {table} SELECT * FROM jsonb_populate_recordset(NULL::{table}, $1)"
+    );
+    sqlx::query(&sql)
+        .bind(sqlx::types::Json(rows))
+        .execute(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to restore {}", table))?;
+    Ok(())
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(dir: &Path, name: &str) -> Result<T> {
+    let file = fs::File::open(dir.join(name)).with_context(|| format!("{} missing from archive", name))?;
+    serde_json::from_reader(io::BufReader::new(file)).with_context(|| format!("Invalid {}", name))
+}
+
+fn str_field<'a>(row: &'a Value, field: &str) -> Result<&'a str> {
+    row[field]
+        .as_str()
+        .with_context(|| format!("Row is missing field '{}'", field))
+}
+
+fn uuid_fields(rows: &[Value], field: &str) -> Result<Vec<uuid::Uuid>> {
+    rows.iter()
+        .map(|row| Ok(str_field(row, field)?.parse()?))
+        .collect()
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+use flate2::{write::GzEncoder, Compression};
+
+use crate::archive::{append_bytes, append_json};
+use crate::changelog;
+use crate::db::{BuildInfo, Version};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const ARTIFACTS_DIR: &str = "artifacts";
+const SCRIPTS_DIR: &str = "scripts";
+const CHANGELOGS_DIR: &str = "changelogs";
+
+/// USB 번들 생성 요청
+#[derive(Debug, Deserialize)]
+pub struct BundleRequest {
+    pub entries: Vec<BundleEntryRequest>,
+}
+
+/// 번들 항목 요청
+#[derive(Debug, Deserialize)]
+pub struct BundleEntryRequest {
+    /// 대상 역할/그룹 (없으면 역할이 맞는 항목이 없을 때 쓰는 기본 항목)
+    #[serde(default)]
+    pub group: Option<String>,
+    /// 버전 (없으면 해당 역할 클라이언트들의 타겟/현재 버전 중 가장 많은 버전)
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// 다중 항목 manifest.json (dm-client `apply --dir`가 역할로 항목 선택)
+#[derive(Debug, Serialize)]
+pub struct BundleManifest {
+    pub entries: Vec<BundleManifestEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BundleManifestEntry {
+    #[serde(rename = "match")]
+    pub matcher: BundleMatch,
+    pub version: String,
+    pub checksum: String,
+    /// 아티팩트 ed25519 서명 (base64, 업로드 시 받은 경우)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// 번들 루트 기준 아티팩트 경로
+    pub artifact: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_notes: Option<String>,
+    /// 번들 루트 기준 변경 이력 경로 (Markdown)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub build_info: Option<BuildInfo>,
+    /// 번들 루트 기준 설치 전 스크립트 경로
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_install_script: Option<String>,
+    /// 번들 루트 기준 설치 후 스크립트 경로
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_install_script: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BundleMatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+impl BundleManifestEntry {
+    pub fn new(group: Option<String>, version: &Version) -> Self {
+        Self {
+            matcher: BundleMatch { group },
+            version: version.version.clone(),
+            checksum: version.checksum.clone(),
+            signature: version.signature.clone(),
+            artifact: format!("{}/{}", ARTIFACTS_DIR, version.artifact_path),
+            release_notes: version.release_notes.clone(),
+            changelog: version.has_changelog.then(|| changelog_path(&version.version)),
+            build_info: version.build_info(),
+            pre_install_script: version
+                .pre_install_script
+                .as_ref()
+                .map(|_| script_path(&version.version, "pre_install")),
+            post_install_script: version
+                .post_install_script
+                .as_ref()
+                .map(|_| script_path(&version.version, "post_install")),
+        }
+    }
+}
+
+/// 번들 안의 스크립트 경로
+fn script_path(version: &str, phase: &str) -> String {
+    format!("{}/{}/{}.sh", SCRIPTS_DIR, version, phase)
+}
+
+/// 번들 안의 변경 이력 경로
+fn changelog_path(version: &str) -> String {
+    format!("{}/{}.md", CHANGELOGS_DIR, version)
+}
+
+/// 번들 tar.gz 작성 (blocking). 같은 버전의 아티팩트는 한 번만 포함
+pub fn write_bundle<W: Write>(
+    manifest: &BundleManifest,
+    versions: &[Version],
+    artifact_dir: &Path,
+    writer: W,
+) -> Result<()> {
+    let mut builder = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+
+    append_json(&mut builder, MANIFEST_FILE, manifest)?;
+
+    let mut added: Vec<&str> = Vec::new();
+    for version in versions {
+        if added.contains(&version.artifact_path.as_str()) {
+            continue;
+        }
+        builder
+            .append_path_with_name(
+                artifact_dir.join(&version.artifact_path),
+                Path::new(ARTIFACTS_DIR).join(&version.artifact_path),
+            )
+            .with_context(|| format!("Failed to add artifact for {}", version.version))?;
+        added.push(&version.artifact_path);
+
+        if version.has_changelog {
+            builder
+                .append_path_with_name(
+                    artifact_dir.join(changelog::file_name(&version.version)),
+                    changelog_path(&version.version),
+                )
+                .with_context(|| format!("Failed to add changelog for {}", version.version))?;
+        }
+
+        for (phase, script) in [
+            ("pre_install", &version.pre_install_script),
+            ("post_install", &version.post_install_script),
+        ] {
+            if let Some(script) = script {
+                append_bytes(&mut builder, &script_path(&version.version, phase), script.as_bytes())?;
+            }
+        }
+    }
+
+    builder.into_inner()?.finish()?.flush()?;
+    Ok(())
+}
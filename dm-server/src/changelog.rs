@@ -0,0 +1,49 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::path::Path;
+
+use crate::db;
+
+/// 릴리즈 노트(요약) 최대 크기 (바이트). 버전 목록 응답마다 실리므로 짧게 유지
+pub const MAX_RELEASE_NOTES_LEN: usize = 4096;
+
+/// 변경 이력 파일 최대 크기 (바이트)
+pub const MAX_CHANGELOG_LEN: usize = 4 * 1024 * 1024;
+
+/// 변경 이력 응답 Content-Type
+pub const CONTENT_TYPE: &str = "text/markdown; charset=utf-8";
+
+/// 변경 이력 파일 이름 (artifact_dir 기준)
+pub fn file_name(version: &str) -> String {
+    format!("{}.changelog.md", version)
+}
+
+/// 한도를 넘는 릴리즈 노트의 요약 (한도 안의 마지막 줄까지)
+pub fn summarize(notes: &str) -> String {
+    if notes.len() <= MAX_RELEASE_NOTES_LEN {
+        return notes.to_string();
+    }
+    let marker = "\n…";
+    let mut end = MAX_RELEASE_NOTES_LEN - marker.len();
+    while !notes.is_char_boundary(end) {
+        end -= 1;
+    }
+    let head = &notes[..end];
+    let head = head.rfind('\n').map_or(head, |i| &head[..i]);
+    format!("{}{}", head.trim_end(), marker)
+}
+
+/// 한도를 넘는 기존 릴리즈 노트를 변경 이력 파일로 옮기고 요약만 남김 (서버 시작 시)
+///
+/// 이미 변경 이력 파일이 있는 버전은 파일을 덮어쓰지 않고 노트만 줄인다.
+pub async fn migrate_oversized_notes(pool: &PgPool, artifact_dir: &Path) -> Result<usize> {
+    let oversized = db::get_oversized_release_notes(pool, MAX_RELEASE_NOTES_LEN as i32).await?;
+    for (version, notes, has_changelog) in &oversized {
+        if !has_changelog {
+            tokio::fs::write(artifact_dir.join(file_name(version)), notes).await?;
+        }
+        db::move_release_notes_to_changelog(pool, version, &summarize(notes)).await?;
+        tracing::info!("Moved release notes of {} ({} bytes) to changelog", version, notes.len());
+    }
+    Ok(oversized.len())
+}
@@ -1,7 +1,7 @@
 pub mod models;
 
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use sqlx::{postgres::PgPoolOptions, PgPool};
 use uuid::Uuid;
 
@@ -38,24 +38,91 @@ pub async fn register_client(pool: &PgPool, name: &str, api_key: &str, config: O
     Ok(client)
 }
 
-/// 클라이언트 설정 업데이트
-pub async fn update_client_config(pool: &PgPool, client_id: Uuid, config: &ClientConfig) -> Result<()> {
+/// 클라이언트 설정 업데이트. 새 리비전 반환
+///
+/// 변경 직전의 설정은 같은 문장에서 설정 이력에 기록한다.
+/// `expected_revision`이 현재 리비전과 다르거나 클라이언트가 없으면 None (호출자가 구분)
+pub async fn update_client_config(
+    pool: &PgPool,
+    client_id: Uuid,
+    config: &ClientConfig,
+    expected_revision: Option<i64>,
+    changed_by: Option<&str>,
+) -> Result<Option<i64>> {
     let config_json = serde_json::to_value(config)?;
-    
-    sqlx::query(
+
+    let revision = sqlx::query_scalar(
         r#"
-        UPDATE clients
-        SET config = $2, updated_at = $3
-        WHERE id = $1
+        WITH prior AS (
+            SELECT id, config FROM clients
+            WHERE id = $1 AND ($4::BIGINT IS NULL OR revision = $4)
+            FOR UPDATE
+        ),
+        updated AS (
+            UPDATE clients c
+            SET config = $2, updated_at = $3, revision = c.revision + 1
+            FROM prior
+            WHERE c.id = prior.id
+            RETURNING c.revision
+        ),
+        history AS (
+            INSERT INTO client_config_history (id, client_id, config, changed_by, changed_at)
+            SELECT $5, prior.id, prior.config, $6, $3
+            FROM prior, updated
+        )
+        SELECT revision FROM updated
         "#,
     )
     .bind(client_id)
     .bind(config_json)
     .bind(Utc::now())
-    .execute(pool)
+    .bind(expected_revision)
+    .bind(Uuid::new_v4())
+    .bind(changed_by)
+    .fetch_optional(pool)
     .await?;
 
-    Ok(())
+    Ok(revision)
+}
+
+/// 클라이언트 설정 변경 이력 (최신순)
+pub async fn list_client_config_history(pool: &PgPool, client_id: Uuid) -> Result<Vec<ClientConfigHistory>> {
+    let history = sqlx::query_as::<_, ClientConfigHistory>(
+        r#"
+        SELECT * FROM client_config_history
+        WHERE client_id = $1
+        ORDER BY changed_at DESC
+        "#,
+    )
+    .bind(client_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(history)
+}
+
+/// 클라이언트의 설정 이력 항목 조회
+pub async fn get_client_config_history(
+    pool: &PgPool,
+    client_id: Uuid,
+    history_id: Uuid,
+) -> Result<Option<ClientConfigHistory>> {
+    let entry = sqlx::query_as::<_, ClientConfigHistory>(
+        "SELECT * FROM client_config_history WHERE id = $1 AND client_id = $2",
+    )
+    .bind(history_id)
+    .bind(client_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(entry)
+}
+
+/// 보존 기간이 지난 설정 이력 삭제. 삭제된 항목 수 반환
+pub async fn prune_client_config_history(pool: &PgPool, before: DateTime<Utc>) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM client_config_history WHERE changed_at < $1")
+        .bind(before)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
 }
 
 /// API Key로 클라이언트 조회
@@ -67,6 +134,61 @@ pub async fn get_client_by_api_key(pool: &PgPool, api_key: &str) -> Result<Optio
     Ok(client)
 }
 
+/// 체크인용 클라이언트 조회 (API Key)
+///
+/// 실제 적용 설정(effective_config)은 읽지 않는다. 서버 설정(config)은 해시가 클라이언트가
+/// 마지막으로 받은 `known_config_hash`와 다를 때만 전체를 읽고, 같으면 체크인에서 쓰는
+/// product/mirrors만 읽는다.
+pub async fn get_checkin_client(
+    pool: &PgPool,
+    api_key: &str,
+    known_config_hash: Option<&str>,
+) -> Result<Option<Client>> {
+    let client = sqlx::query_as::<_, Client>(
+        r#"
+        SELECT id, name, api_key, current_version, target_version, last_seen, status,
+               created_at, updated_at, pinned_version, last_instance_id, active_instance_id,
+               active_instance_started_at, multiple_agents_at, retired_instance_ids, target_staged, role,
+               effective_config_hash, effective_config_at, current_checksum, target_force_reinstall,
+               target_allow_downgrade,
+               target_reason, target_ticket, state_tampered_at, revision, target_alias, target_set_at,
+               product, target_initiated_by, reported_checksum, integrity_ok, integrity_checked_at,
+               integrity_ok_at, integrity_mismatch_at,
+               CASE WHEN md5(config::text) = $2
+                   THEN jsonb_build_object('product', config->'product', 'mirrors', config->'mirrors')
+                   ELSE config
+               END AS config,
+               md5(config::text) AS config_hash
+        FROM clients
+        WHERE api_key = $1
+        "#,
+    )
+    .bind(api_key)
+    .bind(known_config_hash)
+    .fetch_optional(pool)
+    .await?;
+    Ok(client)
+}
+
+/// API Key로 클라이언트 자신의 정보 조회 (장비에 보여줄 열만, 체크인 기록은 남기지 않음)
+pub async fn get_client_self(pool: &PgPool, api_key: &str) -> Result<Option<ClientSelf>> {
+    let client = sqlx::query_as::<_, ClientSelf>(
+        r#"
+        SELECT id, name, status, COALESCE(role, config->>'role') AS role,
+               current_version, target_version, target_set_at, target_alias, target_staged,
+               target_version IS NOT NULL AND target_version IS DISTINCT FROM current_version
+                   AS update_pending,
+               pinned_version, last_seen
+        FROM clients
+        WHERE api_key = $1
+        "#,
+    )
+    .bind(api_key)
+    .fetch_optional(pool)
+    .await?;
+    Ok(client)
+}
+
 /// 클라이언트 ID로 조회
 pub async fn get_client_by_id(pool: &PgPool, id: Uuid) -> Result<Option<Client>> {
     let client = sqlx::query_as::<_, Client>("SELECT * FROM clients WHERE id = $1")
@@ -90,147 +212,1985 @@ pub async fn update_client_checkin(
     client_id: Uuid,
     current_version: Option<&str>,
     status: &str,
-) -> Result<()> {
-    sqlx::query(
+    role: Option<&str>,
+    product: Option<&str>,
+    poll_interval_secs: Option<i32>,
+) -> Result<Option<String>> {
+    // 보고된 버전의 아티팩트가 기록된 체크섬과 같으면 (재태깅된 동일 빌드) 버전 문자열 유지
+    let current_checksum: Option<String> = sqlx::query_scalar(
         r#"
-        UPDATE clients
-        SET current_version = COALESCE($2, current_version),
+        WITH reported AS (
+            SELECT (SELECT checksum FROM versions WHERE version = $2) AS checksum
+        )
+        UPDATE clients c
+        SET current_version = CASE
+                WHEN $2 IS NULL THEN c.current_version
+                WHEN r.checksum IS NOT NULL AND r.checksum = c.current_checksum THEN c.current_version
+                ELSE $2
+            END,
+            current_checksum = CASE
+                WHEN $2 IS NULL THEN c.current_checksum
+                WHEN r.checksum IS NOT NULL AND r.checksum = c.current_checksum THEN c.current_checksum
+                WHEN $2 = c.current_version THEN COALESCE(c.current_checksum, r.checksum)
+                ELSE r.checksum
+            END,
             status = $3,
             last_seen = $4,
-            updated_at = $4
-        WHERE id = $1
+            updated_at = $4,
+            role = COALESCE($5, c.role),
+            product = COALESCE($6, c.product),
+            poll_interval_secs = COALESCE($7, c.poll_interval_secs)
+        FROM reported r
+        WHERE c.id = $1
+        RETURNING c.current_checksum
         "#,
     )
     .bind(client_id)
     .bind(current_version)
     .bind(status)
     .bind(Utc::now())
+    .bind(role)
+    .bind(product)
+    .bind(poll_interval_secs)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(current_checksum)
+}
+
+/// 클라이언트 실제 적용 설정 저장
+pub async fn set_client_effective_config(
+    pool: &PgPool,
+    client_id: Uuid,
+    hash: &str,
+    config: &serde_json::Value,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE clients
+        SET effective_config = $2, effective_config_hash = $3, effective_config_at = $4
+        WHERE id = $1
+        "#,
+    )
+    .bind(client_id)
+    .bind(sqlx::types::Json(config))
+    .bind(hash)
+    .bind(Utc::now())
     .execute(pool)
     .await?;
 
     Ok(())
 }
 
-/// 클라이언트 타겟 버전 설정
-pub async fn set_client_target_version(
+/// 설치 상태 수정 여부 기록 (처음 보고된 시각 유지, 정상 보고 시 해제)
+pub async fn set_client_state_tampered(pool: &PgPool, client_id: Uuid, tampered: bool) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE clients
+        SET state_tampered_at = CASE WHEN $2 THEN COALESCE(state_tampered_at, NOW()) ELSE NULL END
+        WHERE id = $1
+        "#,
+    )
+    .bind(client_id)
+    .bind(tampered)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 체크인에서 보고한 설치 체크섬과 비교 결과 기록 (ok가 None이면 알 수 없음)
+pub async fn set_client_integrity(
     pool: &PgPool,
     client_id: Uuid,
-    target_version: &str,
+    reported_checksum: Option<&str>,
+    ok: Option<bool>,
 ) -> Result<()> {
     sqlx::query(
         r#"
         UPDATE clients
-        SET target_version = $2, updated_at = $3
+        SET reported_checksum = $2, integrity_ok = $3, integrity_checked_at = NOW(),
+            integrity_ok_at = CASE WHEN $3 THEN NOW() ELSE integrity_ok_at END,
+            integrity_mismatch_at = CASE WHEN NOT $3 THEN COALESCE(integrity_mismatch_at, NOW()) ELSE NULL END
         WHERE id = $1
         "#,
     )
     .bind(client_id)
-    .bind(target_version)
-    .bind(Utc::now())
+    .bind(reported_checksum)
+    .bind(ok)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 설치 체크섬이 배포한 버전과 다른 클라이언트
+pub async fn get_integrity_mismatch_clients(pool: &PgPool) -> Result<Vec<AttentionClient>> {
+    let clients = sqlx::query_as::<_, AttentionClient>(
+        r#"
+        SELECT id, name, status, current_version, target_version, last_seen,
+               integrity_mismatch_at AS since, 'integrity_mismatch' AS reason
+        FROM clients
+        WHERE integrity_ok = FALSE
+        ORDER BY integrity_mismatch_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(clients)
+}
+
+/// 무결성 상태별 클라이언트 (ok가 None이면 알 수 없는 클라이언트)
+pub async fn get_integrity_clients(pool: &PgPool, ok: Option<bool>) -> Result<Vec<IntegrityClient>> {
+    let clients = sqlx::query_as::<_, IntegrityClient>(
+        r#"
+        SELECT id, name, status, current_version, current_checksum AS expected_checksum,
+               reported_checksum, integrity_mismatch_at AS mismatch_since,
+               integrity_ok_at AS last_ok_at, last_seen
+        FROM clients
+        WHERE integrity_ok IS NOT DISTINCT FROM $1
+        ORDER BY integrity_mismatch_at ASC NULLS LAST, name
+        "#,
+    )
+    .bind(ok)
+    .fetch_all(pool)
+    .await?;
+    Ok(clients)
+}
+
+/// 무결성 상태별 클라이언트 수 (전체, 일치, 불일치, 알 수 없음)
+pub async fn count_integrity(pool: &PgPool) -> Result<(i64, i64, i64, i64)> {
+    let counts = sqlx::query_as(
+        r#"
+        SELECT COUNT(*),
+               COUNT(*) FILTER (WHERE integrity_ok),
+               COUNT(*) FILTER (WHERE NOT integrity_ok),
+               COUNT(*) FILTER (WHERE integrity_ok IS NULL)
+        FROM clients
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(counts)
+}
+
+/// 설치 상태가 외부에서 수정된 클라이언트
+pub async fn get_state_tampered_clients(pool: &PgPool) -> Result<Vec<AttentionClient>> {
+    let clients = sqlx::query_as::<_, AttentionClient>(
+        r#"
+        SELECT id, name, status, current_version, target_version, last_seen,
+               state_tampered_at AS since, 'state_tampered' AS reason
+        FROM clients
+        WHERE state_tampered_at IS NOT NULL
+        ORDER BY state_tampered_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(clients)
+}
+
+/// 데몬 인스턴스 정보 기록
+pub async fn record_client_instance(
+    pool: &PgPool,
+    client_id: Uuid,
+    instance_id: &str,
+    started_at: Option<DateTime<Utc>>,
+    retired_instance_ids: &[String],
+    becomes_active: bool,
+    multiple_agents: bool,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE clients
+        SET last_instance_id = $2,
+            retired_instance_ids = $4,
+            active_instance_id = CASE WHEN $5 THEN $2 ELSE active_instance_id END,
+            active_instance_started_at = CASE WHEN $5 THEN $3 ELSE active_instance_started_at END,
+            multiple_agents_at = CASE WHEN $6 THEN NOW() ELSE multiple_agents_at END
+        WHERE id = $1
+        "#,
+    )
+    .bind(client_id)
+    .bind(instance_id)
+    .bind(started_at)
+    .bind(retired_instance_ids)
+    .bind(becomes_active)
+    .bind(multiple_agents)
     .execute(pool)
     .await?;
 
     Ok(())
 }
 
-/// 버전 생성
-pub async fn create_version(
+/// 다중 에이전트가 감지된 클라이언트 (since 이후)
+pub async fn get_multiple_agent_clients(pool: &PgPool, since: DateTime<Utc>) -> Result<Vec<AttentionClient>> {
+    let clients = sqlx::query_as::<_, AttentionClient>(
+        r#"
+        SELECT id, name, status, current_version, target_version, last_seen, multiple_agents_at AS since
+        FROM clients
+        WHERE multiple_agents_at >= $1
+        ORDER BY multiple_agents_at DESC
+        "#,
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+    Ok(clients)
+}
+
+/// 업데이트 실패 상태의 클라이언트 (최근 실패의 분류와 fingerprint 포함)
+pub async fn get_failed_clients(pool: &PgPool) -> Result<Vec<AttentionClient>> {
+    let clients = sqlx::query_as::<_, AttentionClient>(
+        r#"
+        SELECT c.id, c.name, c.status, c.current_version, c.target_version, c.last_seen,
+               c.updated_at AS since, l.failure_reason AS reason, l.failure_fingerprint
+        FROM clients c
+        LEFT JOIN LATERAL (
+            SELECT failure_reason, failure_fingerprint FROM update_logs
+            WHERE client_id = c.id AND status = 'failed'
+            ORDER BY started_at DESC
+            LIMIT 1
+        ) l ON TRUE
+        WHERE c.status = 'error'
+        ORDER BY c.updated_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(clients)
+}
+
+/// 파일시스템 장애가 의심되는 클라이언트 (degraded 상태 또는 최근 업데이트가 FS 오류로 실패)
+pub async fn get_hardware_suspect_clients(pool: &PgPool) -> Result<Vec<AttentionClient>> {
+    let clients = sqlx::query_as::<_, AttentionClient>(
+        r#"
+        SELECT c.id, c.name, c.status, c.current_version, c.target_version, c.last_seen,
+               COALESCE(l.completed_at, c.updated_at) AS since,
+               l.failure_reason AS reason
+        FROM clients c
+        LEFT JOIN LATERAL (
+            SELECT failure_reason, completed_at FROM update_logs
+            WHERE client_id = c.id
+            ORDER BY started_at DESC
+            LIMIT 1
+        ) l ON TRUE
+        WHERE c.status = 'degraded'
+           OR l.failure_reason IN ('fs_read_only', 'disk_full', 'io_error')
+        ORDER BY since DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(clients)
+}
+
+/// 장비가 검증한 체크섬이 업로드 체크섬과 다른 클라이언트 (최근 업데이트 기준)
+pub async fn get_provenance_mismatch_clients(pool: &PgPool) -> Result<Vec<AttentionClient>> {
+    let clients = sqlx::query_as::<_, AttentionClient>(
+        r#"
+        SELECT c.id, c.name, c.status, c.current_version, c.target_version, c.last_seen,
+               COALESCE(l.completed_at, l.started_at) AS since,
+               'checksum_mismatch' AS reason
+        FROM clients c
+        JOIN LATERAL (
+            SELECT to_version, verified_checksum, started_at, completed_at FROM update_logs
+            WHERE client_id = c.id AND verified_checksum IS NOT NULL
+            ORDER BY started_at DESC
+            LIMIT 1
+        ) l ON TRUE
+        JOIN versions v ON v.version = l.to_version
+        WHERE l.verified_checksum <> v.checksum
+        ORDER BY since DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(clients)
+}
+
+/// 업데이트 로그에 실패 분류와 fingerprint 기록
+pub async fn set_update_log_failure(pool: &PgPool, log_id: Uuid, reason: &str, fingerprint: &str) -> Result<()> {
+    sqlx::query("UPDATE update_logs SET failure_reason = $2, failure_fingerprint = $3 WHERE id = $1")
+        .bind(log_id)
+        .bind(reason)
+        .bind(fingerprint)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 실패 유형 집계 갱신 (버전별 fingerprint). 갱신된 누적 횟수 반환
+pub async fn record_failure_signature(
     pool: &PgPool,
     version: &str,
-    artifact_path: &str,
-    artifact_size: i64,
-    checksum: &str,
-    release_notes: Option<&str>,
-) -> Result<Version> {
-    let ver = sqlx::query_as::<_, Version>(
+    fingerprint: &str,
+    category: &str,
+    message: &str,
+) -> Result<i64> {
+    let (count,): (i64,) = sqlx::query_as(
         r#"
-        INSERT INTO versions (id, version, artifact_path, artifact_size, checksum, release_notes, is_active, created_at)
-        VALUES ($1, $2, $3, $4, $5, $6, true, $7)
-        RETURNING *
+        INSERT INTO failure_signatures (version, fingerprint, category, example_message)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (version, fingerprint) DO UPDATE
+        SET count = failure_signatures.count + 1, last_seen = NOW()
+        RETURNING count
         "#,
     )
-    .bind(Uuid::new_v4())
     .bind(version)
-    .bind(artifact_path)
-    .bind(artifact_size)
-    .bind(checksum)
-    .bind(release_notes)
-    .bind(Utc::now())
+    .bind(fingerprint)
+    .bind(category)
+    .bind(message)
     .fetch_one(pool)
     .await?;
-
-    Ok(ver)
+    Ok(count)
 }
 
-/// 버전 조회
-pub async fn get_version(pool: &PgPool, version: &str) -> Result<Option<Version>> {
-    let ver = sqlx::query_as::<_, Version>("SELECT * FROM versions WHERE version = $1")
-        .bind(version)
-        .fetch_optional(pool)
-        .await?;
-    Ok(ver)
+/// 실패 유형 목록 (많이 발생한 순, 영향받은 클라이언트 수 포함)
+pub async fn list_failure_signatures(pool: &PgPool, version: Option<&str>, limit: i64) -> Result<Vec<FailureSignature>> {
+    let signatures = sqlx::query_as::<_, FailureSignature>(
+        r#"
+        SELECT s.version, s.fingerprint, s.category, s.example_message, s.count,
+               s.first_seen, s.last_seen,
+               (SELECT COUNT(DISTINCT l.client_id) FROM update_logs l
+                WHERE l.to_version = s.version AND l.failure_fingerprint = s.fingerprint) AS affected_clients
+        FROM failure_signatures s
+        WHERE $1::text IS NULL OR s.version = $1
+        ORDER BY s.count DESC, s.last_seen DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(version)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(signatures)
 }
 
-/// 모든 버전 조회
-pub async fn get_all_versions(pool: &PgPool) -> Result<Vec<Version>> {
-    let versions =
-        sqlx::query_as::<_, Version>("SELECT * FROM versions ORDER BY created_at DESC")
-            .fetch_all(pool)
-            .await?;
-    Ok(versions)
+/// 클라이언트 타겟 버전 설정 (staged면 스테이징 후 활성화 대기)
+///
+/// `alias`가 있으면 클라이언트가 그 별칭을 따라가도록 기록 (없으면 따라가기 해제)
+pub async fn set_client_target_version(pool: &PgPool, client_id: Uuid, deploy: &DeployPayload) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE clients
+        SET target_version = $2, target_staged = $3, target_force_reinstall = $4,
+            target_reason = $5, target_ticket = $6, updated_at = $7, target_alias = $8,
+            target_set_at = $7, target_initiated_by = $9, target_allow_downgrade = $10
+        WHERE id = $1
+        "#,
+    )
+    .bind(client_id)
+    .bind(&deploy.version)
+    .bind(deploy.staged)
+    .bind(deploy.force_reinstall)
+    .bind(&deploy.reason)
+    .bind(&deploy.ticket)
+    .bind(Utc::now())
+    .bind(&deploy.alias)
+    .bind(&deploy.initiated_by)
+    .bind(deploy.allow_downgrade)
+    .execute(pool)
+    .await?;
+
+    Ok(())
 }
 
-/// 업데이트 로그 생성
-pub async fn create_update_log(
+/// 재태깅된 동일 빌드 배포 완료 처리 (재설치 없이 버전 문자열만 갱신)
+pub async fn complete_noop_retag(
     pool: &PgPool,
     client_id: Uuid,
-    from_version: Option<&str>,
-    to_version: &str,
-) -> Result<UpdateLog> {
-    let log = sqlx::query_as::<_, UpdateLog>(
+    version: &str,
+    checksum: &str,
+) -> Result<()> {
+    sqlx::query(
         r#"
-        INSERT INTO update_logs (id, client_id, from_version, to_version, status, started_at)
-        VALUES ($1, $2, $3, $4, 'pending', $5)
-        RETURNING *
+        UPDATE clients
+        SET current_version = $2, current_checksum = $3, target_version = NULL,
+            target_staged = FALSE, target_force_reinstall = FALSE,
+            target_allow_downgrade = FALSE,
+            target_reason = NULL, target_ticket = NULL, target_set_at = NULL, target_initiated_by = NULL,
+            updated_at = $4
+        WHERE id = $1
         "#,
     )
-    .bind(Uuid::new_v4())
     .bind(client_id)
-    .bind(from_version)
-    .bind(to_version)
+    .bind(version)
+    .bind(checksum)
     .bind(Utc::now())
-    .fetch_one(pool)
+    .execute(pool)
     .await?;
 
-    Ok(log)
+    Ok(())
 }
 
-/// 업데이트 로그 상태 업데이트
-pub async fn update_log_status(
+/// 클라이언트 고정 버전 설정 (None이면 해제). 새 리비전 반환
+///
+/// `expected_revision`이 현재 리비전과 다르거나 클라이언트가 없으면 None (호출자가 구분)
+pub async fn set_client_pinned_version(
     pool: &PgPool,
-    log_id: Uuid,
-    status: &str,
-    error_message: Option<&str>,
-) -> Result<()> {
-    let completed_at = if status == "completed" || status == "failed" || status == "rolled_back" {
-        Some(Utc::now())
-    } else {
-        None
-    };
+    client_id: Uuid,
+    pinned_version: Option<&str>,
+    expected_revision: Option<i64>,
+) -> Result<Option<i64>> {
+    let revision = sqlx::query_scalar(
+        r#"
+        UPDATE clients
+        SET pinned_version = $2, updated_at = $3, revision = revision + 1
+        WHERE id = $1 AND ($4::BIGINT IS NULL OR revision = $4)
+        RETURNING revision
+        "#,
+    )
+    .bind(client_id)
+    .bind(pinned_version)
+    .bind(Utc::now())
+    .bind(expected_revision)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(revision)
+}
 
+/// 스테이징 배포 활성화 허용 (target_staged 해제)
+pub async fn activate_client_target(pool: &PgPool, client_id: Uuid) -> Result<()> {
     sqlx::query(
         r#"
-        UPDATE update_logs
-        SET status = $2, error_message = $3, completed_at = $4
+        UPDATE clients
+        SET target_staged = FALSE, updated_at = $2
         WHERE id = $1
         "#,
     )
-    .bind(log_id)
-    .bind(status)
-    .bind(error_message)
-    .bind(completed_at)
+    .bind(client_id)
+    .bind(Utc::now())
     .execute(pool)
     .await?;
 
     Ok(())
 }
+
+/// 클라이언트 타겟 버전 클리어
+pub async fn clear_client_target_version(pool: &PgPool, client_id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE clients
+        SET target_version = NULL, target_staged = FALSE, target_force_reinstall = FALSE,
+            target_allow_downgrade = FALSE,
+            target_reason = NULL, target_ticket = NULL, target_alias = NULL, target_set_at = NULL,
+            target_initiated_by = NULL, updated_at = $2
+        WHERE id = $1
+        "#,
+    )
+    .bind(client_id)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 특정 버전을 타겟으로 가진 클라이언트 수
+pub async fn count_clients_targeting(pool: &PgPool, version: &str) -> Result<i64> {
+    let count: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM clients WHERE target_version = $1")
+            .bind(version)
+            .fetch_one(pool)
+            .await?;
+    Ok(count)
+}
+
+/// 특정 버전을 타겟으로 가진 모든 클라이언트의 타겟 클리어
+pub async fn clear_targets_for_version(pool: &PgPool, version: &str) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE clients
+        SET target_version = NULL, target_staged = FALSE, target_force_reinstall = FALSE,
+            target_allow_downgrade = FALSE,
+            target_reason = NULL, target_ticket = NULL, target_alias = NULL, target_set_at = NULL,
+            target_initiated_by = NULL, updated_at = $2
+        WHERE target_version = $1
+        "#,
+    )
+    .bind(version)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// 클라이언트 작업 추가 (대기열 끝)
+pub async fn create_client_action(
+    pool: &PgPool,
+    client_id: Uuid,
+    action_type: &str,
+    payload: &serde_json::Value,
+) -> Result<ClientAction> {
+    let action = sqlx::query_as::<_, ClientAction>(
+        r#"
+        INSERT INTO client_actions (id, client_id, action_type, payload, state, created_at)
+        VALUES ($1, $2, $3, $4, 'pending', $5)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(client_id)
+    .bind(action_type)
+    .bind(sqlx::types::Json(payload))
+    .bind(Utc::now())
+    .fetch_one(pool)
+    .await?;
+
+    Ok(action)
+}
+
+/// 클라이언트 작업 목록 (전달 순서). `all`이면 끝난 작업도 최근 100건까지 포함
+pub async fn list_client_actions(pool: &PgPool, client_id: Uuid, all: bool) -> Result<Vec<ClientAction>> {
+    let actions = sqlx::query_as::<_, ClientAction>(
+        r#"
+        SELECT * FROM (
+            SELECT * FROM client_actions
+            WHERE client_id = $1 AND ($2 OR state IN ('pending', 'delivered'))
+            ORDER BY created_at DESC
+            LIMIT 100
+        ) recent
+        ORDER BY created_at
+        "#,
+    )
+    .bind(client_id)
+    .bind(all)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(actions)
+}
+
+/// 클라이언트 작업 조회
+pub async fn get_client_action(pool: &PgPool, client_id: Uuid, action_id: Uuid) -> Result<Option<ClientAction>> {
+    let action = sqlx::query_as::<_, ClientAction>("SELECT * FROM client_actions WHERE id = $1 AND client_id = $2")
+        .bind(action_id)
+        .bind(client_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(action)
+}
+
+/// 대기열 맨 앞 작업 (가장 오래된 pending/delivered)과 그 뒤에 기다리는 작업 수
+pub async fn head_client_action(pool: &PgPool, client_id: Uuid) -> Result<Option<(ClientAction, i64)>> {
+    let open = sqlx::query_as::<_, ClientAction>(
+        r#"
+        SELECT * FROM client_actions
+        WHERE client_id = $1 AND state IN ('pending', 'delivered')
+        ORDER BY created_at, id
+        "#,
+    )
+    .bind(client_id)
+    .fetch_all(pool)
+    .await?;
+
+    let waiting = open.len() as i64 - 1;
+    Ok(open.into_iter().next().map(|head| (head, waiting)))
+}
+
+/// 같은 버전의 배포가 이미 대기열에 있는지
+pub async fn has_open_deploy_action(pool: &PgPool, client_id: Uuid, version: &str) -> Result<bool> {
+    let exists = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM client_actions
+            WHERE client_id = $1 AND action_type = 'deploy'
+              AND state IN ('pending', 'delivered') AND payload->>'version' = $2
+        )
+        "#,
+    )
+    .bind(client_id)
+    .bind(version)
+    .fetch_one(pool)
+    .await?;
+    Ok(exists)
+}
+
+/// 작업 전달 기록
+pub async fn mark_client_action_delivered(pool: &PgPool, action_id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE client_actions
+        SET state = 'delivered', delivered_at = $2
+        WHERE id = $1 AND state = 'pending'
+        "#,
+    )
+    .bind(action_id)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 작업 종료 (completed/failed/cancelled/expired). 이미 끝난 작업이면 false
+pub async fn finish_client_action(
+    pool: &PgPool,
+    action_id: Uuid,
+    state: &str,
+    error_message: Option<&str>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE client_actions
+        SET state = $2, error_message = $3, completed_at = $4
+        WHERE id = $1 AND state IN ('pending', 'delivered')
+        "#,
+    )
+    .bind(action_id)
+    .bind(state)
+    .bind(error_message)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// 전달된(타겟 버전에 반영된) 배포 작업 종료
+pub async fn finish_deploy_action(
+    pool: &PgPool,
+    client_id: Uuid,
+    state: &str,
+    error_message: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE client_actions
+        SET state = $2, error_message = $3, completed_at = $4
+        WHERE client_id = $1 AND action_type = 'deploy' AND state = 'delivered'
+        "#,
+    )
+    .bind(client_id)
+    .bind(state)
+    .bind(error_message)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 전달된 배포 작업의 마지막 실패 기록 (뒤에 작업이 기다리면 재시도하지 않고 넘어가는 기준)
+pub async fn set_deploy_action_error(pool: &PgPool, client_id: Uuid, error_message: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE client_actions
+        SET error_message = $2
+        WHERE client_id = $1 AND action_type = 'deploy' AND state = 'delivered'
+        "#,
+    )
+    .bind(client_id)
+    .bind(error_message)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 특정 버전의 대기 중인 배포 작업 모두 취소 (버전 삭제/비활성화 시 타겟 클리어와 함께)
+pub async fn cancel_deploy_actions_for_version(pool: &PgPool, version: &str, reason: &str) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE client_actions
+        SET state = 'cancelled', error_message = $2, completed_at = $3
+        WHERE action_type = 'deploy' AND state IN ('pending', 'delivered') AND payload->>'version' = $1
+        "#,
+    )
+    .bind(version)
+    .bind(reason)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// 역할별 대표 버전 (해당 역할 클라이언트의 타겟/현재 버전 중 가장 많은 버전)
+pub async fn get_role_version(pool: &PgPool, role: &str) -> Result<Option<String>> {
+    let version = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT COALESCE(target_version, current_version) AS version
+        FROM clients
+        WHERE role = $1 AND COALESCE(target_version, current_version) IS NOT NULL
+        GROUP BY version
+        ORDER BY COUNT(*) DESC, version DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(role)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(version)
+}
+
+/// 버전 생성
+pub async fn create_version(pool: &PgPool, new: &NewVersion<'_>) -> Result<Version> {
+    let ver = sqlx::query_as::<_, Version>(
+        r#"
+        INSERT INTO versions (id, version, artifact_path, artifact_size, checksum, release_notes, is_active, created_at,
+                              git_commit, build_time, metadata, original_filename, uploaded_by,
+                              pre_install_script, post_install_script, scan_status, deploy_type, has_changelog, product,
+                              severity, signature)
+        VALUES ($1, $2, $3, $4, $5, $6, true, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(new.version)
+    .bind(new.artifact_path)
+    .bind(new.artifact_size)
+    .bind(new.checksum)
+    .bind(new.release_notes)
+    .bind(Utc::now())
+    .bind(new.git_commit)
+    .bind(new.build_time)
+    .bind(new.metadata)
+    .bind(new.original_filename)
+    .bind(new.uploaded_by)
+    .bind(new.pre_install_script)
+    .bind(new.post_install_script)
+    .bind(new.scan_status)
+    .bind(new.deploy_type)
+    .bind(new.has_changelog)
+    .bind(new.product)
+    .bind(new.severity)
+    .bind(new.signature)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(ver)
+}
+
+/// 버전 심각도 변경
+pub async fn set_version_severity(pool: &PgPool, version: &str, severity: &str) -> Result<Option<Version>> {
+    let ver = sqlx::query_as::<_, Version>("UPDATE versions SET severity = $2 WHERE version = $1 RETURNING *")
+        .bind(version)
+        .bind(severity)
+        .fetch_optional(pool)
+        .await?;
+    Ok(ver)
+}
+
+/// 버전 조회
+pub async fn get_version(pool: &PgPool, version: &str) -> Result<Option<Version>> {
+    let ver = sqlx::query_as::<_, Version>("SELECT * FROM versions WHERE version = $1")
+        .bind(version)
+        .fetch_optional(pool)
+        .await?;
+    Ok(ver)
+}
+
+/// 버전 활성화 상태 변경
+pub async fn set_version_active(pool: &PgPool, version: &str, is_active: bool) -> Result<()> {
+    sqlx::query("UPDATE versions SET is_active = $2 WHERE version = $1")
+        .bind(version)
+        .bind(is_active)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 검사 시작 (결과 초기화). 버전이 없으면 false
+pub async fn set_version_scan_pending(pool: &PgPool, version: &str) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE versions
+        SET scan_status = 'pending', scan_output = NULL, scan_duration_ms = NULL, scanned_at = NULL
+        WHERE version = $1
+        "#,
+    )
+    .bind(version)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 검사 결과 기록
+pub async fn set_version_scan_result(
+    pool: &PgPool,
+    version: &str,
+    status: &str,
+    output: &str,
+    duration_ms: i64,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE versions
+        SET scan_status = $2, scan_output = $3, scan_duration_ms = $4, scanned_at = NOW()
+        WHERE version = $1
+        "#,
+    )
+    .bind(version)
+    .bind(status)
+    .bind(output)
+    .bind(duration_ms)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 한도(바이트)를 넘는 릴리즈 노트 (version, release_notes, has_changelog)
+pub async fn get_oversized_release_notes(pool: &PgPool, max_len: i32) -> Result<Vec<(String, String, bool)>> {
+    let rows = sqlx::query_as::<_, (String, String, bool)>(
+        r#"
+        SELECT version, release_notes, has_changelog
+        FROM versions
+        WHERE octet_length(release_notes) > $1
+        "#,
+    )
+    .bind(max_len)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// 릴리즈 노트를 요약으로 바꾸고 변경 이력 파일 표시
+pub async fn move_release_notes_to_changelog(pool: &PgPool, version: &str, summary: &str) -> Result<()> {
+    sqlx::query("UPDATE versions SET release_notes = $2, has_changelog = true WHERE version = $1")
+        .bind(version)
+        .bind(summary)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 버전 삭제
+pub async fn delete_version(pool: &PgPool, version: &str) -> Result<()> {
+    sqlx::query("DELETE FROM versions WHERE version = $1")
+        .bind(version)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 모든 버전 조회
+pub async fn get_all_versions(pool: &PgPool) -> Result<Vec<Version>> {
+    let versions =
+        sqlx::query_as::<_, Version>("SELECT * FROM versions ORDER BY created_at DESC")
+            .fetch_all(pool)
+            .await?;
+    Ok(versions)
+}
+
+/// metadata가 주어진 객체를 포함하는 버전 조회 (JSONB @>)
+pub async fn get_versions_by_metadata(pool: &PgPool, filter: &serde_json::Value) -> Result<Vec<Version>> {
+    let versions = sqlx::query_as::<_, Version>(
+        "SELECT * FROM versions WHERE metadata @> $1 ORDER BY created_at DESC",
+    )
+    .bind(filter)
+    .fetch_all(pool)
+    .await?;
+    Ok(versions)
+}
+
+/// 업데이트 로그 생성 (클라이언트의 대기 중인 배포 사유/티켓/시작 주체 기록)
+pub async fn create_update_log(
+    pool: &PgPool,
+    client: &Client,
+    from_version: Option<&str>,
+    to_version: &str,
+) -> Result<UpdateLog> {
+    let log = sqlx::query_as::<_, UpdateLog>(
+        r#"
+        INSERT INTO update_logs
+            (id, client_id, client_name, from_version, to_version, status, started_at, offered_at,
+             reason, ticket, initiated_by)
+        VALUES ($1, $2, $9, $3, $4, 'pending', $5, $5, $6, $7, $8)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(client.id)
+    .bind(from_version)
+    .bind(to_version)
+    .bind(Utc::now())
+    .bind(&client.target_reason)
+    .bind(&client.target_ticket)
+    .bind(&client.target_initiated_by)
+    .bind(&client.name)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(log)
+}
+
+/// 업데이트 로그 조회 (최신순, 클라이언트/티켓/상태 필터)
+///
+/// 삭제된 클라이언트의 로그도 포함하며, 기록 당시 이름에 " (deleted)"를 붙여 표시한다.
+pub async fn list_update_logs(pool: &PgPool, query: &UpdateLogQuery) -> Result<Vec<UpdateLogEntry>> {
+    let logs = sqlx::query_as::<_, UpdateLogEntry>(
+        r#"
+        SELECT l.*,
+               COALESCE(c.name, l.client_name || ' (deleted)') AS display_name,
+               l.client_id IS NULL AS client_deleted
+        FROM update_logs l
+        LEFT JOIN clients c ON c.id = l.client_id
+        WHERE ($1::uuid IS NULL OR l.client_id = $1)
+          AND ($2::text IS NULL OR l.ticket = $2)
+          AND ($3::text IS NULL OR l.status = $3)
+          AND ($5::text IS NULL OR l.initiated_by = $5)
+          AND ($6::text IS NULL OR l.install_strategy = $6)
+        ORDER BY l.started_at DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(query.client_id)
+    .bind(&query.ticket)
+    .bind(&query.status)
+    .bind(query.limit)
+    .bind(&query.initiated_by)
+    .bind(&query.install_strategy)
+    .fetch_all(pool)
+    .await?;
+    Ok(logs)
+}
+
+/// 업데이트 로그에 장비가 검증한 체크섬 기록
+pub async fn set_update_log_verified_checksum(pool: &PgPool, log_id: Uuid, checksum: &str) -> Result<()> {
+    sqlx::query("UPDATE update_logs SET verified_checksum = $2 WHERE id = $1")
+        .bind(log_id)
+        .bind(checksum)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 업데이트 로그에 단계별 시각 기록 (보고되지 않은 단계는 유지)
+pub async fn set_update_log_phases(
+    pool: &PgPool,
+    log_id: Uuid,
+    phases: &UpdatePhaseTimes,
+    healthy_at: Option<DateTime<Utc>>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE update_logs
+        SET download_started_at = COALESCE($2, download_started_at),
+            download_completed_at = COALESCE($3, download_completed_at),
+            install_started_at = COALESCE($4, install_started_at),
+            restarted_at = COALESCE($5, restarted_at),
+            healthy_at = COALESCE($6, healthy_at)
+        WHERE id = $1
+        "#,
+    )
+    .bind(log_id)
+    .bind(phases.download_started_at)
+    .bind(phases.download_completed_at)
+    .bind(phases.install_started_at)
+    .bind(phases.restarted_at)
+    .bind(healthy_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 클라이언트가 체크인한 시간대 기록 (1시간 단위), 보존 기간이 지난 기록은 삭제
+pub async fn record_client_presence(
+    pool: &PgPool,
+    client_id: Uuid,
+    at: DateTime<Utc>,
+    retain_since: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO client_presence (client_id, hour)
+        VALUES ($1, date_trunc('hour', $2::TIMESTAMPTZ))
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(client_id)
+    .bind(at)
+    .execute(pool)
+    .await?;
+
+    sqlx::query("DELETE FROM client_presence WHERE client_id = $1 AND hour < $2")
+        .bind(client_id)
+        .bind(retain_since)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 제공부터 헬스 체크 통과까지 걸린 시간 분포
+///
+/// 체크인 기록 시간대가 기간(체크인 기록이 시작된 이후)의 절반에 못 미치는 클라이언트는 제외한다.
+/// 취소된 배포는 집계하지 않는다.
+pub async fn sla_stats(
+    pool: &PgPool,
+    version: Option<&str>,
+    severity: Option<&str>,
+    since: DateTime<Utc>,
+    target_secs: i64,
+) -> Result<SlaStats> {
+    let stats = sqlx::query_as::<_, SlaStats>(
+        r#"
+        WITH span AS (
+            SELECT tracked_from,
+                   GREATEST(CEIL(EXTRACT(EPOCH FROM NOW() - tracked_from) / 3600), 1)::BIGINT AS hours
+            FROM (
+                SELECT GREATEST(
+                    date_trunc('hour', $2::TIMESTAMPTZ),
+                    COALESCE((SELECT MIN(hour) FROM client_presence), date_trunc('hour', $2::TIMESTAMPTZ))
+                ) AS tracked_from
+            ) b
+        ),
+        presence AS (
+            SELECT p.client_id, COUNT(*) AS hours
+            FROM client_presence p, span s
+            WHERE p.hour >= s.tracked_from
+            GROUP BY p.client_id
+        ),
+        logs AS (
+            SELECT EXTRACT(EPOCH FROM l.healthy_at - l.offered_at)::DOUBLE PRECISION AS secs,
+                   COALESCE(p.hours, 0) * 2 >= s.hours AS online
+            FROM update_logs l
+            CROSS JOIN span s
+            LEFT JOIN presence p ON p.client_id = l.client_id
+            WHERE l.offered_at >= $2
+              AND l.status NOT IN ('cancelled', 'superseded')
+              AND ($1::TEXT IS NULL OR l.to_version = $1)
+              AND ($4::TEXT IS NULL OR EXISTS (
+                  SELECT 1 FROM versions v WHERE v.version = l.to_version AND v.severity = $4
+              ))
+        )
+        SELECT
+            COUNT(*) FILTER (WHERE online) AS offered,
+            COUNT(secs) FILTER (WHERE online) AS completed,
+            COUNT(*) FILTER (WHERE online AND secs <= $3) AS within_target,
+            COUNT(*) FILTER (WHERE NOT online) AS excluded_offline,
+            percentile_cont(0.5) WITHIN GROUP (ORDER BY secs) FILTER (WHERE online) AS p50_secs,
+            percentile_cont(0.9) WITHIN GROUP (ORDER BY secs) FILTER (WHERE online) AS p90_secs,
+            percentile_cont(0.95) WITHIN GROUP (ORDER BY secs) FILTER (WHERE online) AS p95_secs,
+            percentile_cont(0.99) WITHIN GROUP (ORDER BY secs) FILTER (WHERE online) AS p99_secs,
+            MAX(secs) FILTER (WHERE online) AS max_secs
+        FROM logs
+        "#,
+    )
+    .bind(version)
+    .bind(since)
+    .bind(target_secs as f64)
+    .bind(severity)
+    .fetch_one(pool)
+    .await?;
+    Ok(stats)
+}
+
+/// 업데이트 로그에 아티팩트를 받은 곳 기록
+pub async fn set_update_log_artifact_source(pool: &PgPool, log_id: Uuid, source: &str) -> Result<()> {
+    sqlx::query("UPDATE update_logs SET artifact_source = $2 WHERE id = $1")
+        .bind(log_id)
+        .bind(source)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 업데이트 로그에 장비가 트리를 설치한 방법 기록
+pub async fn set_update_log_install_strategy(pool: &PgPool, log_id: Uuid, strategy: &str) -> Result<()> {
+    sqlx::query("UPDATE update_logs SET install_strategy = $2 WHERE id = $1")
+        .bind(log_id)
+        .bind(strategy)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 첫 설치로 기록
+pub async fn set_update_log_bootstrap(pool: &PgPool, log_id: Uuid) -> Result<()> {
+    sqlx::query("UPDATE update_logs SET bootstrap = TRUE WHERE id = $1")
+        .bind(log_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 아티팩트 다운로드 기록 (전송한 체크섬 헤더)
+pub async fn record_artifact_download(
+    pool: &PgPool,
+    version: &str,
+    client_id: Option<Uuid>,
+    served_checksum: &str,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO artifact_downloads (id, version, client_id, served_checksum, downloaded_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(version)
+    .bind(client_id)
+    .bind(served_checksum)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 공유 링크 생성
+pub async fn create_share_link(
+    pool: &PgPool,
+    token: &str,
+    version: &str,
+    created_by: &str,
+    expires_at: DateTime<Utc>,
+    max_downloads: i32,
+) -> Result<ShareLink> {
+    let link = sqlx::query_as::<_, ShareLink>(
+        r#"
+        INSERT INTO share_links (token, version, created_by, created_at, expires_at, max_downloads)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(token)
+    .bind(version)
+    .bind(created_by)
+    .bind(Utc::now())
+    .bind(expires_at)
+    .bind(max_downloads)
+    .fetch_one(pool)
+    .await?;
+    Ok(link)
+}
+
+/// 공유 링크 조회
+pub async fn get_share_link(pool: &PgPool, token: &str) -> Result<Option<ShareLink>> {
+    let link = sqlx::query_as::<_, ShareLink>("SELECT * FROM share_links WHERE token = $1")
+        .bind(token)
+        .fetch_optional(pool)
+        .await?;
+    Ok(link)
+}
+
+/// 공유 링크 다운로드 1회 차감 (만료/취소/횟수 초과면 None)
+///
+/// 조건 확인과 증가를 한 문장으로 처리해 동시에 받아도 최대 횟수를 넘지 않는다.
+pub async fn consume_share_link(pool: &PgPool, token: &str) -> Result<Option<ShareLink>> {
+    let link = sqlx::query_as::<_, ShareLink>(
+        r#"
+        UPDATE share_links
+        SET download_count = download_count + 1
+        WHERE token = $1 AND revoked_at IS NULL AND expires_at > NOW()
+          AND download_count < max_downloads
+        RETURNING *
+        "#,
+    )
+    .bind(token)
+    .fetch_optional(pool)
+    .await?;
+    Ok(link)
+}
+
+/// 공유 링크 취소 (이미 취소된 링크는 처음 취소 시각 유지)
+pub async fn revoke_share_link(pool: &PgPool, token: &str) -> Result<Option<ShareLink>> {
+    let link = sqlx::query_as::<_, ShareLink>(
+        r#"
+        UPDATE share_links
+        SET revoked_at = COALESCE(revoked_at, NOW())
+        WHERE token = $1
+        RETURNING *
+        "#,
+    )
+    .bind(token)
+    .fetch_optional(pool)
+    .await?;
+    Ok(link)
+}
+
+/// 공유 링크 다운로드 기록
+pub async fn record_share_download(
+    pool: &PgPool,
+    token: &str,
+    bundle: bool,
+    remote_addr: Option<&str>,
+    user_agent: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO share_downloads (id, token, bundle, remote_addr, user_agent, downloaded_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(token)
+    .bind(bundle)
+    .bind(remote_addr)
+    .bind(user_agent)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 버전의 공유 링크 (최근 생성 순)
+pub async fn list_share_links(pool: &PgPool, version: &str) -> Result<Vec<ShareLink>> {
+    let links = sqlx::query_as::<_, ShareLink>(
+        "SELECT * FROM share_links WHERE version = $1 ORDER BY created_at DESC",
+    )
+    .bind(version)
+    .fetch_all(pool)
+    .await?;
+    Ok(links)
+}
+
+/// 공유 링크 만료 시각 연장 (연장 기록 추가, 만료 임박 알림 초기화)
+///
+/// 취소되었거나 다운로드 횟수를 모두 쓴 링크는 연장하지 않는다 (None).
+pub async fn renew_share_link(
+    pool: &PgPool,
+    token: &str,
+    expires_at: DateTime<Utc>,
+    renewed_by: &str,
+) -> Result<Option<ShareLink>> {
+    let mut tx = pool.begin().await?;
+    let previous: Option<DateTime<Utc>> =
+        sqlx::query_scalar("SELECT expires_at FROM share_links WHERE token = $1 FOR UPDATE")
+            .bind(token)
+            .fetch_optional(&mut *tx)
+            .await?;
+    let Some(previous) = previous else {
+        return Ok(None);
+    };
+
+    let link = sqlx::query_as::<_, ShareLink>(
+        r#"
+        UPDATE share_links
+        SET expires_at = $2, expiry_warned_at = NULL
+        WHERE token = $1 AND revoked_at IS NULL AND download_count < max_downloads
+        RETURNING *
+        "#,
+    )
+    .bind(token)
+    .bind(expires_at)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(link) = link else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO share_link_renewals (id, token, previous_expires_at, expires_at, renewed_by, renewed_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(token)
+    .bind(previous)
+    .bind(expires_at)
+    .bind(renewed_by)
+    .bind(Utc::now())
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Some(link))
+}
+
+/// 버전의 공유 링크 연장 기록 (오래된 순)
+pub async fn list_share_renewals(pool: &PgPool, version: &str) -> Result<Vec<ShareRenewal>> {
+    let renewals = sqlx::query_as::<_, ShareRenewal>(
+        r#"
+        SELECT r.token, r.previous_expires_at, r.expires_at, r.renewed_by, r.renewed_at
+        FROM share_link_renewals r
+        JOIN share_links l ON l.token = r.token
+        WHERE l.version = $1
+        ORDER BY r.renewed_at
+        "#,
+    )
+    .bind(version)
+    .fetch_all(pool)
+    .await?;
+    Ok(renewals)
+}
+
+/// 만료 임박 알림 대상 공유 링크를 알림 보냄으로 표시하고 반환
+///
+/// `window` 안에 만료되고 아직 받을 수 있는 링크 중, 유효 기간이 `window`보다 길었던
+/// 링크만 대상이다 (짧은 링크는 만들자마자 알림이 가지 않도록).
+pub async fn claim_expiring_share_links(pool: &PgPool, window_secs: i64) -> Result<Vec<ShareLink>> {
+    let links = sqlx::query_as::<_, ShareLink>(
+        r#"
+        UPDATE share_links
+        SET expiry_warned_at = NOW()
+        WHERE revoked_at IS NULL AND expiry_warned_at IS NULL
+          AND download_count < max_downloads
+          AND expires_at > NOW()
+          AND expires_at <= NOW() + make_interval(secs => $1)
+          AND expires_at - created_at > make_interval(secs => $1)
+        RETURNING *
+        "#,
+    )
+    .bind(window_secs as f64)
+    .fetch_all(pool)
+    .await?;
+    Ok(links)
+}
+
+/// 버전의 공유 링크 다운로드 기록 (오래된 순)
+pub async fn list_share_downloads(pool: &PgPool, version: &str) -> Result<Vec<ShareDownload>> {
+    let downloads = sqlx::query_as::<_, ShareDownload>(
+        r#"
+        SELECT d.token, d.bundle, d.remote_addr, d.user_agent, d.downloaded_at
+        FROM share_downloads d
+        JOIN share_links l ON l.token = d.token
+        WHERE l.version = $1
+        ORDER BY d.downloaded_at
+        "#,
+    )
+    .bind(version)
+    .fetch_all(pool)
+    .await?;
+    Ok(downloads)
+}
+
+/// 버전의 클라이언트별 출처 기록 (마지막 다운로드와 마지막 업데이트 보고)
+pub async fn get_client_provenance(pool: &PgPool, version: &str) -> Result<Vec<ClientProvenance>> {
+    let rows = sqlx::query_as::<_, ClientProvenance>(
+        r#"
+        SELECT c.id AS client_id, c.name AS client_name,
+               COALESCE(d.downloads, 0) AS downloads,
+               d.downloaded_at AS last_downloaded_at,
+               d.served_checksum,
+               l.verified_checksum,
+               l.status AS update_status,
+               l.completed_at AS reported_at
+        FROM clients c
+        LEFT JOIN LATERAL (
+            SELECT served_checksum, downloaded_at, COUNT(*) OVER () AS downloads
+            FROM artifact_downloads
+            WHERE version = $1 AND client_id = c.id
+            ORDER BY downloaded_at DESC
+            LIMIT 1
+        ) d ON TRUE
+        LEFT JOIN LATERAL (
+            SELECT verified_checksum, status, completed_at FROM update_logs
+            WHERE to_version = $1 AND client_id = c.id
+            ORDER BY started_at DESC
+            LIMIT 1
+        ) l ON TRUE
+        WHERE d.downloaded_at IS NOT NULL OR l.status IS NOT NULL
+        ORDER BY c.name
+        "#,
+    )
+    .bind(version)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// 진행 중인 로그 없이 받은 결과 보고의 업데이트 로그 생성 (USB/apply 설치, 만료된 배포의 늦은 보고)
+///
+/// 체크인에서 내려준 적이 없어 offered_at은 비워 두고, 배포 사유와 시작 주체는 같은 버전을
+/// 타겟으로 가진 경우에만 복사한다.
+pub async fn create_reported_update_log(
+    pool: &PgPool,
+    client: &Client,
+    from_version: Option<&str>,
+    to_version: &str,
+    report_key: Option<&str>,
+) -> Result<Option<UpdateLog>> {
+    let targeted = client.target_version.as_deref() == Some(to_version);
+    let log = sqlx::query_as::<_, UpdateLog>(
+        r#"
+        INSERT INTO update_logs
+            (id, client_id, client_name, from_version, to_version, status, started_at,
+             reason, ticket, initiated_by, report_key)
+        VALUES ($1, $2, $8, $3, $4, 'pending', NOW(), $5, $6, $7, $9)
+        ON CONFLICT (client_id, report_key) WHERE report_key IS NOT NULL DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(client.id)
+    .bind(from_version)
+    .bind(to_version)
+    .bind(client.target_reason.as_ref().filter(|_| targeted))
+    .bind(client.target_ticket.as_ref().filter(|_| targeted))
+    .bind(client.target_initiated_by.as_ref().filter(|_| targeted))
+    .bind(&client.name)
+    .bind(report_key)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(log)
+}
+
+/// 업데이트 로그에 결과 보고의 Idempotency-Key 기록 (다른 보고가 먼저 기록했으면 false)
+pub async fn claim_update_log_report_key(pool: &PgPool, log_id: Uuid, report_key: &str) -> Result<bool> {
+    let claimed = sqlx::query("UPDATE update_logs SET report_key = $2 WHERE id = $1 AND report_key IS NULL")
+        .bind(log_id)
+        .bind(report_key)
+        .execute(pool)
+        .await;
+    match claimed {
+        Ok(result) => Ok(result.rows_affected() == 1),
+        // 같은 키가 이 클라이언트의 다른 로그에 이미 있음
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 진행 중인 업데이트 로그 조회 (클라이언트 + 대상 버전)
+pub async fn get_pending_update_log(
+    pool: &PgPool,
+    client_id: Uuid,
+    to_version: &str,
+) -> Result<Option<UpdateLog>> {
+    let log = sqlx::query_as::<_, UpdateLog>(
+        r#"
+        SELECT * FROM update_logs
+        WHERE client_id = $1 AND to_version = $2
+          AND status IN ('pending', 'downloading', 'installing', 'staged')
+        ORDER BY started_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(client_id)
+    .bind(to_version)
+    .fetch_optional(pool)
+    .await?;
+    Ok(log)
+}
+
+/// 업데이트 로그에 건너뜀 사유 기록
+pub async fn set_update_log_skipped_reason(pool: &PgPool, log_id: Uuid, reason: &str) -> Result<()> {
+    sqlx::query("UPDATE update_logs SET skipped_reason = $2 WHERE id = $1")
+        .bind(log_id)
+        .bind(reason)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 업데이트 로그 상태 업데이트
+pub async fn update_log_status(
+    pool: &PgPool,
+    log_id: Uuid,
+    status: &str,
+    error_message: Option<&str>,
+) -> Result<()> {
+    let completed_at = if matches!(status, "completed" | "failed" | "rolled_back" | "cancelled" | "superseded") {
+        Some(Utc::now())
+    } else {
+        None
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE update_logs
+        SET status = $2, error_message = $3, completed_at = $4
+        WHERE id = $1
+        "#,
+    )
+    .bind(log_id)
+    .bind(status)
+    .bind(error_message)
+    .bind(completed_at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 클라이언트 검색 (이름 부분 일치, ID 접두사)
+/// pattern은 LIKE 와일드카드가 이스케이프된 검색어
+pub async fn search_clients(pool: &PgPool, pattern: &str, limit: i64) -> Result<Vec<ClientSearchHit>> {
+    let hits = sqlx::query_as::<_, ClientSearchHit>(
+        r#"
+        SELECT id, name, status, current_version, last_seen
+        FROM clients
+        WHERE name ILIKE '%' || $1 || '%' OR id::text ILIKE $1 || '%'
+        ORDER BY name
+        LIMIT $2
+        "#,
+    )
+    .bind(pattern)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(hits)
+}
+
+/// 버전 검색 (버전 문자열, 릴리즈 노트)
+pub async fn search_versions(pool: &PgPool, pattern: &str, limit: i64) -> Result<Vec<VersionSearchHit>> {
+    let hits = sqlx::query_as::<_, VersionSearchHit>(
+        r#"
+        SELECT version, is_active, LEFT(release_notes, 200) AS release_notes, created_at
+        FROM versions
+        WHERE version ILIKE '%' || $1 || '%' OR release_notes ILIKE '%' || $1 || '%'
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(pattern)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(hits)
+}
+
+/// 업데이트 로그 검색 (에러 메시지)
+pub async fn search_update_logs(pool: &PgPool, pattern: &str, limit: i64) -> Result<Vec<UpdateLogSearchHit>> {
+    let hits = sqlx::query_as::<_, UpdateLogSearchHit>(
+        r#"
+        SELECT l.id, l.client_id, COALESCE(c.name, l.client_name || ' (deleted)') AS client_name,
+               l.to_version, l.status, LEFT(l.error_message, 200) AS error_message, l.started_at
+        FROM update_logs l
+        LEFT JOIN clients c ON c.id = l.client_id
+        WHERE l.error_message ILIKE '%' || $1 || '%'
+        ORDER BY l.started_at DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(pattern)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(hits)
+}
+
+/// advisory lock을 잡고 있는 연결의 application_name
+///
+/// 32비트 이하 키는 pg_locks에서 classid 0, objid 키, objsubid 1로 표시된다.
+pub async fn advisory_lock_holder(pool: &PgPool, key: i64) -> Result<Option<String>> {
+    let holder = sqlx::query_scalar(
+        r#"
+        SELECT a.application_name
+        FROM pg_locks l
+        JOIN pg_stat_activity a ON a.pid = l.pid
+        WHERE l.locktype = 'advisory' AND l.granted
+          AND l.classid = 0 AND l.objid = $1::BIGINT::OID AND l.objsubid = 1
+        LIMIT 1
+        "#,
+    )
+    .bind(key)
+    .fetch_optional(pool)
+    .await?;
+    Ok(holder)
+}
+
+/// 버전 별칭 목록 (따라가는 클라이언트 수 포함)
+pub async fn list_version_aliases(pool: &PgPool) -> Result<Vec<VersionAlias>> {
+    let aliases = sqlx::query_as::<_, VersionAlias>(
+        r#"
+        SELECT a.*, (SELECT COUNT(*) FROM clients c WHERE c.target_alias = a.name) AS tracking_clients
+        FROM version_aliases a
+        ORDER BY a.name
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(aliases)
+}
+
+/// 역할별 첫 설치 버전 목록
+pub async fn list_bootstrap_versions(pool: &PgPool) -> Result<Vec<BootstrapVersion>> {
+    let versions = sqlx::query_as::<_, BootstrapVersion>("SELECT * FROM bootstrap_versions ORDER BY role")
+        .fetch_all(pool)
+        .await?;
+    Ok(versions)
+}
+
+/// 장비 역할의 첫 설치 버전 (역할에 없으면 "*")
+pub async fn get_bootstrap_version(pool: &PgPool, role: Option<&str>) -> Result<Option<BootstrapVersion>> {
+    let version = sqlx::query_as::<_, BootstrapVersion>(
+        r#"
+        SELECT * FROM bootstrap_versions
+        WHERE role = $1 OR role = '*'
+        ORDER BY role = '*'
+        LIMIT 1
+        "#,
+    )
+    .bind(role.unwrap_or("*"))
+    .fetch_optional(pool)
+    .await?;
+    Ok(version)
+}
+
+/// 첫 설치 버전 지정 (이전 버전 반환)
+pub async fn set_bootstrap_version(
+    pool: &PgPool,
+    role: &str,
+    version: &str,
+    set_by: Option<&str>,
+) -> Result<Option<String>> {
+    let previous = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+        WITH prior AS (
+            SELECT version FROM bootstrap_versions WHERE role = $1 FOR UPDATE
+        ),
+        upsert AS (
+            INSERT INTO bootstrap_versions (role, version, set_by, updated_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (role) DO UPDATE
+            SET version = EXCLUDED.version, set_by = EXCLUDED.set_by, updated_at = EXCLUDED.updated_at
+        )
+        SELECT (SELECT version FROM prior)
+        "#,
+    )
+    .bind(role)
+    .bind(version)
+    .bind(set_by)
+    .bind(Utc::now())
+    .fetch_one(pool)
+    .await?;
+    Ok(previous)
+}
+
+/// 첫 설치 버전 해제
+pub async fn delete_bootstrap_version(pool: &PgPool, role: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM bootstrap_versions WHERE role = $1")
+        .bind(role)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 유효한 폴링 간격 힌트 (만료되었으면 None)
+pub async fn get_poll_hint(pool: &PgPool) -> Result<Option<PollHint>> {
+    let hint = sqlx::query_as::<_, PollHint>(
+        r#"
+        SELECT poll_hint_secs AS next_poll_secs, poll_hint_expires_at AS expires_at,
+               poll_hint_reason AS reason, poll_hint_set_by AS set_by, updated_at
+        FROM server_settings
+        WHERE poll_hint_secs IS NOT NULL AND poll_hint_expires_at > NOW()
+        "#,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(hint)
+}
+
+/// 폴링 간격 힌트 지정 (이전 힌트는 덮어씀)
+pub async fn set_poll_hint(
+    pool: &PgPool,
+    next_poll_secs: i32,
+    expires_at: DateTime<Utc>,
+    reason: Option<&str>,
+    set_by: Option<&str>,
+) -> Result<PollHint> {
+    let hint = sqlx::query_as::<_, PollHint>(
+        r#"
+        INSERT INTO server_settings (id, poll_hint_secs, poll_hint_expires_at, poll_hint_reason, poll_hint_set_by, updated_at)
+        VALUES (TRUE, $1, $2, $3, $4, $5)
+        ON CONFLICT (id) DO UPDATE
+        SET poll_hint_secs = EXCLUDED.poll_hint_secs,
+            poll_hint_expires_at = EXCLUDED.poll_hint_expires_at,
+            poll_hint_reason = EXCLUDED.poll_hint_reason,
+            poll_hint_set_by = EXCLUDED.poll_hint_set_by,
+            updated_at = EXCLUDED.updated_at
+        RETURNING poll_hint_secs AS next_poll_secs, poll_hint_expires_at AS expires_at,
+                  poll_hint_reason AS reason, poll_hint_set_by AS set_by, updated_at
+        "#,
+    )
+    .bind(next_poll_secs)
+    .bind(expires_at)
+    .bind(reason)
+    .bind(set_by)
+    .bind(Utc::now())
+    .fetch_one(pool)
+    .await?;
+    Ok(hint)
+}
+
+/// 폴링 간격 힌트 해제 (유효한 힌트가 있었으면 true)
+pub async fn clear_poll_hint(pool: &PgPool) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE server_settings
+        SET poll_hint_secs = NULL, poll_hint_expires_at = NULL, poll_hint_reason = NULL,
+            poll_hint_set_by = NULL, updated_at = $1
+        WHERE poll_hint_secs IS NOT NULL AND poll_hint_expires_at > $1
+        "#,
+    )
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 기간 안에 체크인한 클라이언트의 폴링 간격 요약
+pub async fn poll_interval_summary(pool: &PgPool, since: DateTime<Utc>) -> Result<PollIntervalSummary> {
+    let summary = sqlx::query_as::<_, PollIntervalSummary>(
+        r#"
+        SELECT COUNT(*) AS clients,
+               COUNT(poll_interval_secs) AS reporting,
+               percentile_cont(0.5) WITHIN GROUP (ORDER BY poll_interval_secs) AS p50_secs,
+               percentile_cont(0.9) WITHIN GROUP (ORDER BY poll_interval_secs) AS p90_secs,
+               MAX(poll_interval_secs) AS max_secs
+        FROM clients
+        WHERE last_seen >= $1
+        "#,
+    )
+    .bind(since)
+    .fetch_one(pool)
+    .await?;
+    Ok(summary)
+}
+
+/// 기간 안에 체크인한 클라이언트의 폴링 간격 구간별 수 (`bounds`는 오름차순 구간 상한)
+pub async fn poll_interval_buckets(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+    bounds: &[i32],
+) -> Result<Vec<PollIntervalBucket>> {
+    // 구간 상한 배열의 인덱스로 묶음 (어느 상한보다도 크면 마지막 구간)
+    let counts: Vec<(i32, i64)> = sqlx::query_as(
+        r#"
+        SELECT COALESCE(
+                   (SELECT MIN(i) FROM generate_subscripts($2::INT[], 1) i WHERE c.poll_interval_secs <= ($2::INT[])[i]),
+                   cardinality($2::INT[]) + 1
+               ) AS bucket,
+               COUNT(*)
+        FROM clients c
+        WHERE c.last_seen >= $1 AND c.poll_interval_secs IS NOT NULL
+        GROUP BY 1
+        "#,
+    )
+    .bind(since)
+    .bind(bounds)
+    .fetch_all(pool)
+    .await?;
+
+    let buckets = bounds
+        .iter()
+        .map(|&b| Some(b))
+        .chain([None])
+        .enumerate()
+        .map(|(i, le_secs)| PollIntervalBucket {
+            le_secs,
+            count: counts
+                .iter()
+                .find(|(bucket, _)| *bucket as usize == i + 1)
+                .map_or(0, |(_, count)| *count),
+        })
+        .collect();
+    Ok(buckets)
+}
+
+/// 버전 별칭 조회
+pub async fn get_version_alias(pool: &PgPool, name: &str) -> Result<Option<VersionAlias>> {
+    let alias = sqlx::query_as::<_, VersionAlias>(
+        r#"
+        SELECT a.*, (SELECT COUNT(*) FROM clients c WHERE c.target_alias = a.name) AS tracking_clients
+        FROM version_aliases a
+        WHERE a.name = $1
+        "#,
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+    Ok(alias)
+}
+
+/// 별칭 생성 또는 이동 (이동 기록을 같은 문장에서 남김). 이전 버전과 따라가는 클라이언트 수 반환
+pub async fn set_version_alias(
+    pool: &PgPool,
+    name: &str,
+    version: &str,
+    moved_by: Option<&str>,
+) -> Result<(Option<String>, i64)> {
+    let moved = sqlx::query_as::<_, (Option<String>, i64)>(
+        r#"
+        WITH prior AS (
+            SELECT version FROM version_aliases WHERE name = $1 FOR UPDATE
+        ),
+        tracking AS (
+            SELECT COUNT(*) AS n FROM clients WHERE target_alias = $1
+        ),
+        upsert AS (
+            INSERT INTO version_aliases (name, version, moved_at, moved_by, created_at)
+            VALUES ($1, $2, $3, $4, $3)
+            ON CONFLICT (name) DO UPDATE
+            SET version = EXCLUDED.version, moved_at = EXCLUDED.moved_at, moved_by = EXCLUDED.moved_by
+            RETURNING name
+        ),
+        moves AS (
+            INSERT INTO version_alias_moves (id, name, from_version, to_version, moved_by, moved_at, tracking_clients)
+            SELECT $5, $1, (SELECT version FROM prior), $2, $4, $3, (SELECT n FROM tracking)
+            FROM upsert
+        )
+        SELECT (SELECT version FROM prior), (SELECT n FROM tracking)
+        "#,
+    )
+    .bind(name)
+    .bind(version)
+    .bind(Utc::now())
+    .bind(moved_by)
+    .bind(Uuid::new_v4())
+    .fetch_one(pool)
+    .await?;
+    Ok(moved)
+}
+
+/// 별칭 이동 기록 (최신순)
+pub async fn list_version_alias_moves(pool: &PgPool, name: &str) -> Result<Vec<VersionAliasMove>> {
+    let moves = sqlx::query_as::<_, VersionAliasMove>(
+        "SELECT * FROM version_alias_moves WHERE name = $1 ORDER BY moved_at DESC",
+    )
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+    Ok(moves)
+}
+
+/// 별칭 삭제 (이동 기록은 유지). 삭제 여부 반환
+pub async fn delete_version_alias(pool: &PgPool, name: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM version_aliases WHERE name = $1")
+        .bind(name)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 특정 버전을 가리키는 별칭 이름
+pub async fn aliases_for_version(pool: &PgPool, version: &str) -> Result<Vec<String>> {
+    let names = sqlx::query_scalar("SELECT name FROM version_aliases WHERE version = $1 ORDER BY name")
+        .bind(version)
+        .fetch_all(pool)
+        .await?;
+    Ok(names)
+}
+
+/// 별칭을 따라가는 클라이언트의 타겟을 별칭의 현재 버전으로 재지정 (시작 주체는 `initiated_by`)
+pub async fn retarget_client_alias(
+    pool: &PgPool,
+    client_id: Uuid,
+    version: &str,
+    reason: &str,
+    initiated_by: &str,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE clients
+        SET target_version = $2, target_staged = FALSE, target_force_reinstall = FALSE,
+            target_allow_downgrade = FALSE,
+            target_reason = $3, target_ticket = NULL, updated_at = $4, target_set_at = $4,
+            target_initiated_by = $5
+        WHERE id = $1
+        "#,
+    )
+    .bind(client_id)
+    .bind(version)
+    .bind(reason)
+    .bind(Utc::now())
+    .bind(initiated_by)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 조사 결과 저장 (같은 작업의 결과가 이미 있으면 무시)
+pub async fn create_client_survey(
+    pool: &PgPool,
+    client_id: Uuid,
+    action_id: Uuid,
+    commands: &[String],
+    results: &serde_json::Value,
+    errors: &serde_json::Value,
+    size_bytes: i32,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO client_surveys (id, client_id, action_id, commands, results, errors, size_bytes, collected_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (action_id) DO NOTHING
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(client_id)
+    .bind(action_id)
+    .bind(commands)
+    .bind(sqlx::types::Json(results))
+    .bind(sqlx::types::Json(errors))
+    .bind(size_bytes)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 클라이언트의 조사 결과 (최근 것부터 `limit`건)
+pub async fn list_client_surveys(pool: &PgPool, client_id: Uuid, limit: i64) -> Result<Vec<ClientSurvey>> {
+    let surveys = sqlx::query_as::<_, ClientSurvey>(
+        r#"
+        SELECT * FROM client_surveys
+        WHERE client_id = $1
+        ORDER BY collected_at DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(client_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(surveys)
+}
+
+/// 클라이언트 삭제 (업데이트 로그는 client_id가 NULL이 되어 기록 당시 이름으로 남고,
+/// 작업/설정 이력/조사 결과 등 클라이언트 상태는 함께 삭제된다)
+///
+/// 삭제 전 남아 있던 업데이트 로그 수를 돌려준다. 클라이언트가 없거나, force 없이
+/// 업데이트 중(status = updating)이면 삭제하지 않고 None (호출자가 다시 조회해 구분).
+pub async fn delete_client(pool: &PgPool, id: Uuid, force: bool) -> Result<Option<(Client, i64)>> {
+    let mut tx = pool.begin().await?;
+    let retained: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM update_logs WHERE client_id = $1")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let client = sqlx::query_as::<_, Client>(
+        "DELETE FROM clients WHERE id = $1 AND ($2 OR status <> 'updating') RETURNING *",
+    )
+    .bind(id)
+    .bind(force)
+    .fetch_optional(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(client.map(|client| (client, retained)))
+}
+
+/// 현재 클라이언트의 업데이트 로그와 다운로드 기록 삭제
+pub async fn purge_client_history(pool: &PgPool, client_id: Uuid) -> Result<PurgeClientHistoryResponse> {
+    let mut tx = pool.begin().await?;
+    let update_logs = sqlx::query("DELETE FROM update_logs WHERE client_id = $1")
+        .bind(client_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    let artifact_downloads = sqlx::query("DELETE FROM artifact_downloads WHERE client_id = $1")
+        .bind(client_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+
+    Ok(PurgeClientHistoryResponse {
+        update_logs,
+        artifact_downloads,
+    })
+}
+
+/// 삭제된 클라이언트의 업데이트 로그를 기록 당시 이름으로 삭제
+///
+/// 같은 이름으로 삭제된 클라이언트가 여럿이면 모두 삭제된다. 다운로드 기록은 삭제 시
+/// client_id가 해제되어 이름으로 찾을 수 없으므로 대상이 아니다.
+pub async fn purge_deleted_client_history(pool: &PgPool, client_name: &str) -> Result<PurgeClientHistoryResponse> {
+    let update_logs = sqlx::query("DELETE FROM update_logs WHERE client_id IS NULL AND client_name = $1")
+        .bind(client_name)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    Ok(PurgeClientHistoryResponse {
+        update_logs,
+        artifact_downloads: 0,
+    })
+}
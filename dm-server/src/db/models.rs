@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::FromRow;
 use uuid::Uuid;
 
@@ -20,6 +21,122 @@ pub struct ClientConfig {
     pub health_check_timeout: Option<i32>,
     #[serde(default)]
     pub rollback_on_failure: Option<bool>,
+    /// 클라이언트 역할/그룹 (DM_CLIENT_ROLE이 없을 때 사용, USB 번들 항목 선택용)
+    #[serde(default)]
+    pub role: Option<String>,
+    /// 백업에서 제외할 glob 패턴 (예: node_modules, .next/cache). DM_BACKUP_EXCLUDE가 없을 때 사용
+    #[serde(default)]
+    pub backup_exclude: Option<Vec<String>>,
+    /// 설치와 롤백에서 그대로 둘 서비스 디렉토리 안의 상대 경로 (예: .env, data, uploads). DM_PRESERVE_PATHS가 없을 때 사용
+    #[serde(default)]
+    pub preserve_paths: Option<Vec<String>>,
+    /// 제외된 경로가 있는 백업으로 롤백한 뒤 클라이언트의 DM_INSTALL_COMMAND 실행
+    #[serde(default)]
+    pub rollback_regenerate: Option<bool>,
+    /// 아티팩트 미러 base URL (`<base>/<아티팩트 파일>`, 서버 다음으로 순서대로 시도). ARTIFACT_MIRRORS보다 우선
+    #[serde(default)]
+    pub mirrors: Option<Vec<String>>,
+    /// 배포 대상 제품 (DM_EXPECTED_PRODUCT가 없을 때 사용, 다른 제품의 버전은 배포/설치 거부)
+    #[serde(default)]
+    pub product: Option<String>,
+    /// 헬스 체크 프로브 (모두 통과해야 정상). 클라이언트 로컬 헬스 체크 설정이 없을 때 사용하며,
+    /// 없으면 health_check_url/health_check_timeout을 http 프로브로 사용
+    #[serde(default)]
+    pub health_checks: Option<Vec<HealthProbe>>,
+}
+
+impl ClientConfig {
+    /// 체크인 응답으로 클라이언트에 내려보낼 항목이 있는지
+    pub fn has_pushed_settings(&self) -> bool {
+        self.service_dir.is_some()
+            || self.restart_command.is_some()
+            || self.role.is_some()
+            || self.backup_exclude.is_some()
+            || self.preserve_paths.is_some()
+            || self.rollback_regenerate.is_some()
+            || self.product.is_some()
+            || self.health_checks.is_some()
+            || self.health_check_url.is_some()
+            || self.health_check_timeout.is_some()
+            || self.pre_update_script.is_some()
+            || self.post_update_script.is_some()
+            || self.rollback_on_failure.is_some()
+    }
+}
+
+/// 헬스 체크 프로브 (`{"type": "tcp", "host": "127.0.0.1", "port": 5000, "timeout_secs": 3}`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthProbe {
+    #[serde(flatten)]
+    pub kind: HealthProbeKind,
+    /// 프로브별 제한 시간 (없으면 클라이언트 기본값)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// 헬스 체크 프로브 종류
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HealthProbeKind {
+    /// HTTP GET 응답 상태 (expected_status가 없으면 2xx)
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected_status: Option<u16>,
+    },
+    /// TCP 포트 연결
+    Tcp { host: String, port: u16 },
+    /// 프로세스 실행 여부 (이름 또는 pidfile 중 하나)
+    Process {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pidfile: Option<String>,
+    },
+    /// 하트비트 파일이 max_age_secs 안에 수정되었는지
+    FileAge { path: String, max_age_secs: u64 },
+    /// 셸 명령 종료 코드
+    Command { command: String },
+}
+
+impl HealthProbe {
+    /// 클라이언트가 실행할 수 있는 프로브인지 검증
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.kind {
+            HealthProbeKind::Http { url, .. } => {
+                if !url.starts_with("http://") {
+                    return Err(format!(
+                        "http probe supports plain http:// URLs only (use a command probe for https): {}",
+                        url
+                    ));
+                }
+            }
+            HealthProbeKind::Tcp { host, port } => {
+                if host.is_empty() || *port == 0 {
+                    return Err(format!("tcp probe needs a host and a non-zero port: {}:{}", host, port));
+                }
+            }
+            HealthProbeKind::Process { name, pidfile } => {
+                if name.is_some() == pidfile.is_some() {
+                    return Err("process probe needs exactly one of name or pidfile".to_string());
+                }
+            }
+            HealthProbeKind::FileAge { path, .. } => {
+                if path.is_empty() {
+                    return Err("file_age probe needs a path".to_string());
+                }
+            }
+            HealthProbeKind::Command { command } => {
+                if command.trim().is_empty() {
+                    return Err("command probe needs a command".to_string());
+                }
+            }
+        }
+        if self.timeout_secs == Some(0) {
+            return Err("probe timeout_secs must be positive".to_string());
+        }
+        Ok(())
+    }
 }
 
 /// 등록된 클라이언트 (타겟 서버)
@@ -30,12 +147,154 @@ pub struct Client {
     pub api_key: String,
     pub current_version: Option<String>,
     pub target_version: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
     pub last_seen: Option<DateTime<Utc>>,
-    pub status: String, // "online", "offline", "updating", "error"
+    pub status: String, // "online", "offline", "updating", "staged", "degraded", "paused", "error"
+    #[serde(with = "crate::timefmt::rfc3339")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "crate::timefmt::rfc3339")]
     pub updated_at: DateTime<Utc>,
     #[sqlx(default)]
     pub config: sqlx::types::Json<ClientConfig>,
+    #[sqlx(default)]
+    pub pinned_version: Option<String>,
+    #[sqlx(default)]
+    pub last_instance_id: Option<String>,
+    #[sqlx(default)]
+    pub active_instance_id: Option<String>,
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub active_instance_started_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub multiple_agents_at: Option<DateTime<Utc>>,
+    /// 다른 인스턴스로 바뀐 이전 인스턴스 (오래된 것부터, 다중 에이전트 감지용)
+    #[sqlx(default)]
+    #[serde(skip)]
+    pub retired_instance_ids: Vec<String>,
+    /// 타겟 버전을 스테이징만 하고 활성화 대기
+    #[sqlx(default)]
+    pub target_staged: bool,
+    /// 클라이언트가 마지막으로 보고한 실제 적용 설정
+    #[sqlx(default)]
+    pub effective_config: Option<sqlx::types::Json<serde_json::Value>>,
+    /// 클라이언트가 보고한 역할/그룹
+    #[sqlx(default)]
+    pub role: Option<String>,
+    #[sqlx(default)]
+    pub effective_config_hash: Option<String>,
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub effective_config_at: Option<DateTime<Utc>>,
+    /// 현재 설치된 아티팩트의 체크섬
+    #[sqlx(default)]
+    pub current_checksum: Option<String>,
+    /// 체크섬이 같아도 타겟 버전을 재설치
+    #[sqlx(default)]
+    pub target_force_reinstall: bool,
+    /// 설치된 버전보다 낮은 타겟 허용
+    #[sqlx(default)]
+    pub target_allow_downgrade: bool,
+    /// 대기 중인 배포의 사유
+    #[sqlx(default)]
+    pub target_reason: Option<String>,
+    /// 대기 중인 배포의 변경 요청(티켓) 번호
+    #[sqlx(default)]
+    pub target_ticket: Option<String>,
+    /// 설치 상태가 외부에서 수정되었다고 처음 보고된 시각
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub state_tampered_at: Option<DateTime<Utc>>,
+    /// 관리자 설정(config, 버전 고정) 리비전 (변경 요청의 If-Match / expected_revision과 비교)
+    #[sqlx(default)]
+    pub revision: i64,
+    /// 따라가는 버전 별칭 (`track: true` 배포, 별칭이 옮겨지면 타겟도 바뀜)
+    #[sqlx(default)]
+    pub target_alias: Option<String>,
+    /// 타겟 버전이 지정된 시각 (배포 또는 별칭 재지정)
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub target_set_at: Option<DateTime<Utc>>,
+    /// 클라이언트가 보고한 제품
+    #[sqlx(default)]
+    pub product: Option<String>,
+    /// 대기 중인 배포를 시작한 주체 (운영자 또는 "alias:<이름>")
+    #[sqlx(default)]
+    pub target_initiated_by: Option<String>,
+    /// 마지막 체크인에서 장비가 보고한 설치 아티팩트 체크섬
+    #[sqlx(default)]
+    pub reported_checksum: Option<String>,
+    /// 보고된 체크섬이 현재 버전의 체크섬과 일치하는지 (모르면 null)
+    #[sqlx(default)]
+    pub integrity_ok: Option<bool>,
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub integrity_checked_at: Option<DateTime<Utc>>,
+    /// 마지막으로 일치가 확인된 시각
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub integrity_ok_at: Option<DateTime<Utc>>,
+    /// 불일치가 처음 확인된 시각 (일치하거나 알 수 없게 되면 null)
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub integrity_mismatch_at: Option<DateTime<Utc>>,
+    /// 서버 설정(config)의 해시 (체크인 조회에서만 채움)
+    #[sqlx(default)]
+    #[serde(skip)]
+    pub config_hash: Option<String>,
+}
+
+impl Client {
+    /// 배포 대상 제품 (클라이언트 보고 > 서버 설정)
+    pub fn product(&self) -> Option<&str> {
+        self.product.as_deref().or(self.config.0.product.as_deref())
+    }
+
+    /// 의도한 설정(config)과 실제 적용 설정(effective_config)의 차이 여부
+    ///
+    /// 의도한 설정에 값이 있고 클라이언트가 같은 키를 보고한 항목만 비교한다.
+    /// 클라이언트가 보고하지 않은 키는 지원하지 않는 설정으로 보고 무시한다.
+    pub fn config_drift(&self) -> Option<bool> {
+        let effective = self.effective_config.as_ref()?.0.as_object()?;
+        let intended = serde_json::to_value(&self.config.0).ok()?;
+
+        let drift = intended.as_object()?.iter().any(|(key, value)| {
+            !value.is_null() && effective.get(key).is_some_and(|actual| actual != value)
+        });
+        Some(drift)
+    }
+}
+
+/// 장비가 조회하는 자신의 등록 정보 (GET /api/clients/self)
+///
+/// API Key, 설정, 배포 사유/티켓 등 운영 정보는 포함하지 않는다.
+#[derive(Debug, Serialize, FromRow)]
+pub struct ClientSelf {
+    pub id: Uuid,
+    pub name: String,
+    pub status: String,
+    /// 역할/그룹 (클라이언트 보고 > 서버 설정)
+    pub role: Option<String>,
+    pub current_version: Option<String>,
+    pub target_version: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub target_set_at: Option<DateTime<Utc>>,
+    pub target_alias: Option<String>,
+    pub target_staged: bool,
+    /// 설치 대기 중인 타겟이 있음
+    pub update_pending: bool,
+    pub pinned_version: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// 클라이언트 상세 (의도한 설정과 실제 설정 비교 포함)
+#[derive(Debug, Serialize)]
+pub struct ClientDetail {
+    #[serde(flatten)]
+    pub client: Client,
+    /// effective_config가 없으면 null
+    pub config_drift: Option<bool>,
 }
 
 /// 버전 정보
@@ -46,22 +305,318 @@ pub struct Version {
     pub artifact_path: String,    // 파일 경로
     pub artifact_size: i64,       // 파일 크기 (bytes)
     pub checksum: String,         // SHA256 해시
+    /// 릴리즈 노트 요약 (4KB 이하, 전체 내용은 변경 이력 파일)
     pub release_notes: Option<String>,
     pub is_active: bool,          // 배포 가능 여부
+    #[serde(with = "crate::timefmt::rfc3339")]
     pub created_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub git_commit: Option<String>,
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub build_time: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    pub metadata: sqlx::types::Json<serde_json::Value>,
+    /// 업로드된 원본 파일 이름
+    #[sqlx(default)]
+    pub original_filename: Option<String>,
+    /// 업로드한 주체 (X-Uploaded-By, 예: CI 파이프라인 이름)
+    #[sqlx(default)]
+    pub uploaded_by: Option<String>,
+    /// 설치 전 스크립트 (내용은 `GET /api/versions/:version/scripts`로 조회)
+    #[sqlx(default)]
+    #[serde(skip_serializing, default)]
+    pub pre_install_script: Option<String>,
+    /// 설치 후 스크립트
+    #[sqlx(default)]
+    #[serde(skip_serializing, default)]
+    pub post_install_script: Option<String>,
+    /// 아티팩트 검사 상태 (pending / clean / infected / error). clean일 때만 배포 가능
+    #[sqlx(default)]
+    pub scan_status: String,
+    /// 검사 명령 출력 (앞/뒤만 유지)
+    #[sqlx(default)]
+    pub scan_output: Option<String>,
+    #[sqlx(default)]
+    pub scan_duration_ms: Option<i64>,
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub scanned_at: Option<DateTime<Utc>>,
+    /// 배포 유형 (app / image). image는 클라이언트가 A/B 슬롯에 쓰고 재부팅
+    #[sqlx(default)]
+    pub deploy_type: String,
+    /// 긴 변경 이력 여부 (내용은 `GET /api/versions/:version/changelog`로 조회)
+    #[sqlx(default)]
+    pub has_changelog: bool,
+    /// 대상 제품 (업로드 시 선언, 아티팩트의 `.dm-product`와 같은 값)
+    #[sqlx(default)]
+    pub product: Option<String>,
+    /// 심각도 (normal / security). security는 체크인 응답에 `urgent: true`
+    #[sqlx(default)]
+    pub severity: String,
+    /// 아티팩트 ed25519 서명 (base64, 클라이언트가 DM_UPDATE_PUBLIC_KEY로 검증)
+    #[sqlx(default)]
+    pub signature: Option<String>,
+}
+
+impl Version {
+    /// 디스크 이미지 배포 여부
+    pub fn is_image(&self) -> bool {
+        self.deploy_type == "image"
+    }
+
+    /// 보안 수정 버전 여부
+    pub fn is_security(&self) -> bool {
+        self.severity == "security"
+    }
+
+    /// 클라이언트 제품과 다르면 거부 사유 (둘 다 선언하지 않았으면 검사하지 않음)
+    pub fn product_mismatch(&self, client: &Client) -> Option<String> {
+        if self.product.as_deref() == client.product() {
+            return None;
+        }
+        Some(format!(
+            "Version {} is built for product {} but client {} expects {}",
+            self.version,
+            self.product.as_deref().unwrap_or("(none)"),
+            client.name,
+            client.product().unwrap_or("(none)")
+        ))
+    }
+
+    /// 다운로드 시 표시할 파일 이름 (원본 이름이 없으면 저장 파일 이름)
+    pub fn download_filename(&self) -> &str {
+        self.original_filename.as_deref().unwrap_or(&self.artifact_path)
+    }
+
+    /// 클라이언트에 전달할 빌드 정보
+    pub fn build_info(&self) -> Option<BuildInfo> {
+        let metadata = match &self.metadata.0 {
+            serde_json::Value::Object(map) if !map.is_empty() => Some(self.metadata.0.clone()),
+            _ => None,
+        };
+
+        if self.git_commit.is_none() && self.build_time.is_none() && metadata.is_none() {
+            return None;
+        }
+
+        Some(BuildInfo {
+            git_commit: self.git_commit.clone(),
+            build_time: self.build_time,
+            metadata,
+        })
+    }
+
+    /// 설치 스크립트 내용 (없으면 None)
+    pub fn install_scripts(&self) -> Option<InstallScripts> {
+        let scripts = InstallScripts {
+            pre_install: self.pre_install_script.clone(),
+            post_install: self.post_install_script.clone(),
+        };
+        (!scripts.is_empty()).then_some(scripts)
+    }
+}
+
+/// 버전별 설치 스크립트 (체크인 응답에는 SHA256, 스크립트 API에는 내용)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstallScripts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_install: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_install: Option<String>,
+}
+
+impl InstallScripts {
+    pub fn is_empty(&self) -> bool {
+        self.pre_install.is_none() && self.post_install.is_none()
+    }
+
+    /// 각 스크립트 내용의 SHA256
+    pub fn hashes(&self) -> Self {
+        let hash = |script: &String| format!("{:x}", Sha256::digest(script.as_bytes()));
+        Self {
+            pre_install: self.pre_install.as_ref().map(hash),
+            post_install: self.post_install.as_ref().map(hash),
+        }
+    }
+}
+
+/// 새 버전 생성 파라미터
+#[derive(Debug)]
+pub struct NewVersion<'a> {
+    pub version: &'a str,
+    pub artifact_path: &'a str,
+    pub original_filename: Option<&'a str>,
+    pub artifact_size: i64,
+    pub checksum: &'a str,
+    pub release_notes: Option<&'a str>,
+    pub git_commit: Option<&'a str>,
+    pub build_time: Option<DateTime<Utc>>,
+    pub metadata: &'a serde_json::Value,
+    pub uploaded_by: Option<&'a str>,
+    pub pre_install_script: Option<&'a str>,
+    pub post_install_script: Option<&'a str>,
+    pub scan_status: &'a str,
+    pub deploy_type: &'a str,
+    pub has_changelog: bool,
+    pub product: Option<&'a str>,
+    pub severity: &'a str,
+    pub signature: Option<&'a str>,
+}
+
+/// 버전 속성 변경 요청
+#[derive(Debug, Deserialize)]
+pub struct UpdateVersionRequest {
+    /// normal / security
+    #[serde(default)]
+    pub severity: Option<String>,
+}
+
+/// 버전 빌드 정보 (출처 추적용)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    #[serde(
+        with = "crate::timefmt::rfc3339::option",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub build_time: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
 }
 
 /// 업데이트 기록
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct UpdateLog {
     pub id: Uuid,
-    pub client_id: Uuid,
+    /// 클라이언트가 삭제되면 None (로그는 client_name으로 남음)
+    pub client_id: Option<Uuid>,
+    /// 로그 생성 시점의 클라이언트 이름 (조회 응답은 UpdateLogEntry.client_name 사용)
+    #[sqlx(default)]
+    #[serde(skip)]
+    pub client_name: String,
     pub from_version: Option<String>,
     pub to_version: String,
-    pub status: String, // "pending", "downloading", "installing", "completed", "failed", "rolled_back"
+    pub status: String, // "pending", "downloading", "installing", "staged", "completed", "failed", "rolled_back", "cancelled", "superseded"
     pub error_message: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
     pub started_at: DateTime<Utc>,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
     pub completed_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    pub skipped_reason: Option<String>,
+    /// 분류된 실패 원인 (예: "fs_read_only", "disk_full", "io_error")
+    #[sqlx(default)]
+    pub failure_reason: Option<String>,
+    /// 운영자가 남긴 배포 사유
+    #[sqlx(default)]
+    pub reason: Option<String>,
+    /// 배포와 연결된 변경 요청(티켓) 번호
+    #[sqlx(default)]
+    pub ticket: Option<String>,
+    /// 배포를 시작한 주체 (운영자 또는 "alias:<이름>")
+    #[sqlx(default)]
+    pub initiated_by: Option<String>,
+    /// 장비가 설치 전에 계산한 아티팩트 체크섬
+    #[sqlx(default)]
+    pub verified_checksum: Option<String>,
+    /// 실패 메시지 fingerprint (`GET /api/failures` 참고)
+    #[sqlx(default)]
+    pub failure_fingerprint: Option<String>,
+    /// 아티팩트를 받은 곳 (서버 경로 또는 미러 URL)
+    #[sqlx(default)]
+    pub artifact_source: Option<String>,
+    /// 장비가 트리를 설치한 방법 (rename 또는 copy, 이전 버전 클라이언트와 스테이징 활성화는 없음)
+    #[sqlx(default)]
+    pub install_strategy: Option<String>,
+    /// 체크인 응답으로 업데이트를 제공한 시각
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub offered_at: Option<DateTime<Utc>>,
+    /// 이하 클라이언트가 결과 보고에 포함한 단계별 시각
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub download_started_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub download_completed_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub install_started_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub restarted_at: Option<DateTime<Utc>>,
+    /// 헬스 체크 통과 시각 (클라이언트가 보내지 않으면 성공 보고 시각)
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub healthy_at: Option<DateTime<Utc>>,
+    /// 빈 장비의 첫 설치 (백업/롤백 없이 설치)
+    #[sqlx(default)]
+    pub bootstrap: bool,
+}
+
+/// 실패 유형 조회 필터
+/// Query: version=&limit=
+#[derive(Debug, Deserialize)]
+pub struct FailureQuery {
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default = "default_log_limit")]
+    pub limit: i64,
+}
+
+/// 버전별 실패 유형 (정규화된 메시지 기준으로 묶음)
+#[derive(Debug, FromRow, Serialize)]
+pub struct FailureSignature {
+    pub version: String,
+    pub fingerprint: String,
+    pub category: String,
+    /// 처음 보고된 메시지 (잘린 상태로 저장)
+    pub example_message: String,
+    /// 누적 실패 보고 수
+    pub count: i64,
+    /// 이 실패를 겪은 클라이언트 수
+    pub affected_clients: i64,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub first_seen: DateTime<Utc>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub last_seen: DateTime<Utc>,
+}
+
+/// 업데이트 로그 조회 필터
+/// Query: client_id=&ticket=&initiated_by=&status=&install_strategy=&limit=
+#[derive(Debug, Deserialize)]
+pub struct UpdateLogQuery {
+    #[serde(default)]
+    pub client_id: Option<Uuid>,
+    #[serde(default)]
+    pub ticket: Option<String>,
+    #[serde(default)]
+    pub initiated_by: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub install_strategy: Option<String>,
+    #[serde(default = "default_log_limit")]
+    pub limit: i64,
+}
+
+fn default_log_limit() -> i64 {
+    100
+}
+
+/// 업데이트 로그 조회 결과 (클라이언트 이름 포함)
+#[derive(Debug, FromRow, Serialize)]
+pub struct UpdateLogEntry {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub log: UpdateLog,
+    /// 현재 클라이언트 이름 (삭제된 클라이언트면 기록 당시 이름 + " (deleted)")
+    #[sqlx(rename = "display_name")]
+    pub client_name: String,
+    pub client_deleted: bool,
 }
 
 /// 클라이언트 체크인 요청
@@ -69,20 +624,133 @@ pub struct UpdateLog {
 pub struct CheckinRequest {
     pub current_version: Option<String>,
     pub status: String,
+    /// 데몬 시작 시 생성되는 인스턴스 ID
+    #[serde(default)]
+    pub instance_id: Option<String>,
+    /// 데몬 인스턴스 시작 시각
+    #[serde(with = "crate::timefmt::rfc3339::option", default)]
+    pub instance_started_at: Option<DateTime<Utc>>,
+    /// 클라이언트에 스테이징되어 활성화 대기 중인 버전
+    #[serde(default)]
+    pub staged_version: Option<String>,
+    /// 실제 적용 설정의 해시 (매 체크인)
+    #[serde(default)]
+    pub effective_config_hash: Option<String>,
+    /// 실제 적용 설정 전체 (변경 시 또는 서버 요청 시)
+    #[serde(default)]
+    pub effective_config: Option<serde_json::Value>,
+    /// 클라이언트 역할/그룹
+    #[serde(default)]
+    pub role: Option<String>,
+    /// 클라이언트가 기대하는 제품 (DM_EXPECTED_PRODUCT 또는 서버 설정)
+    #[serde(default)]
+    pub product: Option<String>,
+    /// 설치 상태(.dm-version 등)가 dm-client 밖에서 수정됨
+    #[serde(default)]
+    pub state_tampered: bool,
+    /// current_version으로 설치한 아티팩트의 SHA256 (이전 버전 클라이언트나 모르는 경우 없음)
+    #[serde(default)]
+    pub installed_checksum: Option<String>,
+    /// 마지막으로 받은 서버 설정의 해시 (같으면 응답에서 설정 생략)
+    #[serde(default)]
+    pub config_hash: Option<String>,
+    /// 일시 중지 중이어도 보안 업데이트는 받음 (클라이언트 로컬 정책)
+    #[serde(default)]
+    pub urgent_during_pause: bool,
+    /// 이 체크인 전에 실제로 기다린 시간 (초, 힌트/백오프 반영, 폴링 간격 분포용)
+    #[serde(default)]
+    pub poll_interval_secs: Option<i32>,
 }
 
 /// 클라이언트 체크인 응답
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub struct CheckinResponse {
-    pub action: String, // "none", "update"
+    pub action: String, // "none", "update", "stage", "activate", "unstage", "restart", "rollback", "survey"
+    /// 재시작/롤백 작업 ID (결과를 POST /api/action-result로 보고)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_id: Option<Uuid>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub target_version: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub artifact_url: Option<String>,
+    /// 다운로드 시도 순서 (artifact_url, 미러...). 미러 URL은 그대로 사용
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub artifact_urls: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub checksum: Option<String>,
+    /// 아티팩트 크기 (bytes, 클라이언트가 다운로드 전 여유 공간 확인에 사용)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact_size: Option<i64>,
+    /// 아티팩트 ed25519 서명 (base64, 업로드 시 받은 경우)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub config: Option<ClientConfig>,
+    /// config의 해시 (다음 체크인의 config_hash로 보내면 바뀌기 전까지 config 생략)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// 타겟 버전의 빌드 정보
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_info: Option<BuildInfo>,
+    /// 클라이언트가 로그로 남길 안내 메시지
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// 클라이언트가 경고로 남길 메시지 (예: 다중 에이전트 감지)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    /// 서버에 저장된 설정 해시와 달라 전체 설정을 다시 보내달라는 요청
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub effective_config_requested: bool,
+    /// 동일한 아티팩트가 설치되어 있어도 다시 설치
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub force_reinstall: bool,
+    /// 설치된 버전보다 낮은 타겟도 설치 (클라이언트는 기본적으로 다운그레이드를 거부)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub allow_downgrade: bool,
+    /// 타겟 버전 설치 스크립트의 SHA256 (내용은 스크립트 API로 받아 검증)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scripts: Option<InstallScripts>,
+    /// 배포 유형 (디스크 이미지일 때만 "image")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deploy_type: Option<String>,
+    /// 보안 수정 버전 (클라이언트는 다음 폴링 간격을 줄이고, 정책이 허용하면 일시 중지 중에도 설치)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub urgent: bool,
+    /// 수집할 조사 항목 (action이 "survey"일 때, 클라이언트는 자체 허용 목록에 있는 항목만 실행)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub survey: Vec<String>,
+    /// 다음 폴링까지 기다릴 시간 (장애 중 힌트/부하 분산, 클라이언트는 다음 대기 한 번에만 적용)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_poll_secs: Option<u64>,
+}
+
+impl CheckinResponse {
+    /// 수행할 작업 없음
+    pub fn none(config: Option<ClientConfig>) -> Self {
+        Self {
+            action: "none".to_string(),
+            config,
+            ..Default::default()
+        }
+    }
+}
+
+/// 배치 체크인 항목 (게이트웨이가 장비별 API Key 보유)
+#[derive(Debug, Deserialize)]
+pub struct BatchCheckinEntry {
+    pub api_key: String,
+    pub current_version: Option<String>,
+    pub status: String,
+}
+
+/// 배치 체크인 항목별 결과 (요청 순서 유지)
+#[derive(Debug, Serialize)]
+pub struct BatchCheckinResult {
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<CheckinResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 /// 새 클라이언트 등록 요청
@@ -97,6 +765,35 @@ pub struct RegisterClientRequest {
 #[derive(Debug, Deserialize)]
 pub struct UpdateClientConfigRequest {
     pub config: ClientConfig,
+    /// 마지막으로 읽은 리비전 (다르면 412, If-Match 헤더로도 전달 가능)
+    #[serde(default)]
+    pub expected_revision: Option<i64>,
+    /// 변경한 사람 (설정 이력에 기록)
+    #[serde(default)]
+    pub changed_by: Option<String>,
+}
+
+/// 클라이언트 설정 롤백 요청
+#[derive(Debug, Deserialize)]
+pub struct RollbackClientConfigRequest {
+    /// 복원할 이력 항목 (`GET /api/clients/:id/config/history`의 id)
+    pub history_id: Uuid,
+    /// 마지막으로 읽은 리비전 (다르면 412, If-Match 헤더로도 전달 가능)
+    #[serde(default)]
+    pub expected_revision: Option<i64>,
+    #[serde(default)]
+    pub changed_by: Option<String>,
+}
+
+/// 클라이언트 설정 변경 이력 (changed_at에 변경되기 직전의 설정)
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ClientConfigHistory {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub config: sqlx::types::Json<ClientConfig>,
+    pub changed_by: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub changed_at: DateTime<Utc>,
 }
 
 /// 새 클라이언트 등록 응답
@@ -110,7 +807,344 @@ pub struct RegisterClientResponse {
 /// 버전 배포 명령
 #[derive(Debug, Deserialize)]
 pub struct DeployRequest {
+    /// 버전 또는 "alias:<이름>" (호출 시점에 해석)
     pub version: String,
+    /// 별칭 배포에서 타겟을 별칭으로 유지 (별칭이 옮겨지면 다음 체크인에서 재지정)
+    #[serde(default)]
+    pub track: bool,
+    /// 고정된 클라이언트에 배포 (고정 해제)
+    #[serde(default)]
+    pub override_pin: bool,
+    /// 스테이징만 수행하고 활성화는 별도 명령으로 진행
+    #[serde(default)]
+    pub staged: bool,
+    /// 설치된 아티팩트와 체크섬이 같아도 재설치
+    #[serde(default)]
+    pub force_reinstall: bool,
+    /// 설치된 버전보다 낮은 버전으로 되돌리기 (없으면 클라이언트가 다운그레이드를 거부)
+    #[serde(default)]
+    pub allow_downgrade: bool,
+    /// 배포 사유 (업데이트 로그와 웹훅에 기록, 클라이언트에는 전달하지 않음)
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// 변경 요청(티켓) 번호 (예: "OPS-1234")
+    #[serde(default)]
+    pub ticket: Option<String>,
+    /// 배포한 사람 (생략하면 "api", 업데이트 로그와 웹훅에 기록)
+    #[serde(default)]
+    pub initiated_by: Option<String>,
+    /// 진행 중인 다른 버전의 배포를 취소하고 대체 (다운로드 중인 클라이언트는 설치 전에 중단)
+    #[serde(default)]
+    pub supersede: bool,
+}
+
+/// 여러 클라이언트 일괄 배포 요청
+/// Body: {"version": "1.2.3", "client_ids": [...]} 또는 {"version": "1.2.3", "status": "online"} 또는 {"version": "1.2.3", "all": true}
+///
+/// 대상 조건(client_ids, status, role, all)이 하나 이상 있어야 하고, 여러 개면 모두 만족하는 클라이언트가 대상이다.
+/// 나머지 필드는 단일 배포와 같다.
+#[derive(Debug, Deserialize)]
+pub struct BulkDeployRequest {
+    #[serde(flatten)]
+    pub deploy: DeployRequest,
+    #[serde(default)]
+    pub client_ids: Option<Vec<Uuid>>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub role: Option<String>,
+    /// 모든 클라이언트
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// 일괄 배포 결과
+#[derive(Debug, Serialize)]
+pub struct BulkDeployResponse {
+    /// 해석된 버전 (별칭이면 지금 가리키는 버전)
+    pub target_version: String,
+    pub queued: Vec<BulkDeployQueued>,
+    pub skipped: Vec<BulkDeploySkipped>,
+}
+
+/// 일괄 배포에서 대기열에 추가된 클라이언트
+#[derive(Debug, Serialize)]
+pub struct BulkDeployQueued {
+    pub client_id: Uuid,
+    pub client_name: String,
+    pub action_id: Uuid,
+    pub queue_position: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub superseded_version: Option<String>,
+}
+
+/// 일괄 배포에서 건너뛴 클라이언트 (이미 그 버전, 고정, 중복 배포, 없는 ID 등)
+#[derive(Debug, Serialize)]
+pub struct BulkDeploySkipped {
+    pub client_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    pub reason: String,
+}
+
+/// 클라이언트 버전 고정 요청
+#[derive(Debug, Deserialize)]
+pub struct PinRequest {
+    /// 버전 또는 "alias:<이름>" (호출 시점의 버전으로 고정)
+    pub version: String,
+    /// 마지막으로 읽은 리비전 (다르면 412, If-Match 헤더로도 전달 가능)
+    #[serde(default)]
+    pub expected_revision: Option<i64>,
+}
+
+/// 클라이언트 작업 대기열 항목 (배포/재시작/롤백, 오래된 것부터 하나씩 전달)
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ClientAction {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    /// "deploy", "restart", "rollback", "survey"
+    pub action_type: String,
+    /// 배포면 DeployPayload, 조사면 {"commands": [...], "reason": ...}, 그 외에는 {"reason": ...}
+    pub payload: sqlx::types::Json<serde_json::Value>,
+    /// "pending", "delivered", "completed", "failed", "cancelled", "expired"
+    pub state: String,
+    pub error_message: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub delivered_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl ClientAction {
+    /// 대기 중이거나 결과를 기다리는 작업
+    pub fn is_open(&self) -> bool {
+        matches!(self.state.as_str(), "pending" | "delivered")
+    }
+
+    /// 배포 작업의 타겟 (배포가 아니거나 내용이 깨졌으면 None)
+    pub fn deploy(&self) -> Option<DeployPayload> {
+        if self.action_type != "deploy" {
+            return None;
+        }
+        serde_json::from_value(self.payload.0.clone()).ok()
+    }
+}
+
+/// 배포 작업 내용 (전달될 때 clients.target_* 컬럼에 그대로 기록)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployPayload {
+    pub version: String,
+    #[serde(default)]
+    pub staged: bool,
+    #[serde(default)]
+    pub force_reinstall: bool,
+    #[serde(default)]
+    pub allow_downgrade: bool,
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub ticket: Option<String>,
+    /// 따라갈 별칭 (track 배포)
+    #[serde(default)]
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub initiated_by: Option<String>,
+}
+
+/// 작업 대기열 조회 조건
+#[derive(Debug, Deserialize)]
+pub struct ClientActionQuery {
+    /// 끝난 작업도 포함 (최근 100건)
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// 재시작/롤백 작업 추가 요청 (배포는 POST /api/clients/:id/deploy)
+#[derive(Debug, Deserialize)]
+pub struct QueueActionRequest {
+    /// "restart" 또는 "rollback"
+    #[serde(rename = "type")]
+    pub action_type: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// 요청한 사람 (생략하면 "api")
+    #[serde(default)]
+    pub initiated_by: Option<String>,
+}
+
+/// 클라이언트의 재시작/롤백 결과 보고
+#[derive(Debug, Deserialize)]
+pub struct ActionResultRequest {
+    pub action_id: Uuid,
+    pub success: bool,
+    #[serde(default)]
+    pub error_message: Option<String>,
+}
+
+/// 버전 별칭
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct VersionAlias {
+    pub name: String,
+    pub version: String,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub moved_at: DateTime<Utc>,
+    pub moved_by: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub created_at: DateTime<Utc>,
+    /// 이 별칭을 따라가는 클라이언트 수
+    #[sqlx(default)]
+    pub tracking_clients: i64,
+}
+
+/// 별칭 이동 기록
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct VersionAliasMove {
+    pub id: Uuid,
+    pub name: String,
+    /// 생성이면 null
+    pub from_version: Option<String>,
+    pub to_version: String,
+    pub moved_by: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub moved_at: DateTime<Utc>,
+    /// 이동 시점에 별칭을 따라가던 클라이언트 수
+    pub tracking_clients: i64,
+}
+
+/// 별칭 상세 (이동 기록 포함, 최신순)
+#[derive(Debug, Serialize)]
+pub struct VersionAliasDetail {
+    #[serde(flatten)]
+    pub alias: VersionAlias,
+    pub moves: Vec<VersionAliasMove>,
+}
+
+/// 별칭 생성/이동 요청
+#[derive(Debug, Deserialize)]
+pub struct SetAliasRequest {
+    pub version: String,
+    /// 옮긴 사람 (이동 기록에 남김)
+    #[serde(default)]
+    pub moved_by: Option<String>,
+}
+
+/// 역할별 첫 설치 버전 ("*"는 역할이 없거나 따로 지정되지 않은 역할)
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct BootstrapVersion {
+    pub role: String,
+    pub version: String,
+    pub set_by: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 첫 설치 버전 지정 요청
+#[derive(Debug, Deserialize)]
+pub struct SetBootstrapRequest {
+    /// 버전 또는 "alias:<이름>" (호출 시점의 버전으로 지정)
+    pub version: String,
+    /// 지정한 사람
+    #[serde(default)]
+    pub set_by: Option<String>,
+}
+
+/// 전체 장비 폴링 간격 힌트 (장애 중 체크인 부하를 줄이기 위한 임시 설정)
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct PollHint {
+    /// 체크인 응답의 next_poll_secs (클라이언트는 다음 대기 한 번에만 적용)
+    pub next_poll_secs: i32,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub expires_at: DateTime<Utc>,
+    pub reason: Option<String>,
+    pub set_by: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 폴링 간격 힌트 지정 요청
+#[derive(Debug, Deserialize)]
+pub struct SetPollHintRequest {
+    pub next_poll_secs: i32,
+    /// 힌트를 유지할 시간 (초, 지나면 평소 간격으로 돌아감)
+    #[serde(default = "default_poll_hint_duration_secs")]
+    pub duration_secs: i64,
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub set_by: Option<String>,
+}
+
+fn default_poll_hint_duration_secs() -> i64 {
+    3600
+}
+
+/// 서버 설정 (GET /api/settings)
+#[derive(Debug, Serialize)]
+pub struct ServerSettings {
+    /// 유효한 폴링 간격 힌트 (없거나 만료되었으면 null)
+    pub poll_hint: Option<PollHint>,
+    /// 체크인 부하 분산 규칙 (환경 변수로만 변경)
+    pub checkin_shed: CheckinShedSettings,
+}
+
+/// 체크인 부하 분산 규칙 (CHECKIN_SHED_IN_FLIGHT, CHECKIN_SHED_RETRY_SECS)
+#[derive(Debug, Serialize)]
+pub struct CheckinShedSettings {
+    /// 동시에 처리 중인 체크인이 이 수에 이르면 503 + Retry-After (0이면 사용 안 함)
+    pub max_in_flight: usize,
+    /// Retry-After 값이자, 한도의 절반을 넘었을 때 응답에 붙이는 next_poll_secs
+    pub retry_after_secs: u64,
+    /// 이 인스턴스에서 지금 처리 중인 체크인 수
+    pub in_flight: usize,
+}
+
+/// 폴링 간격 분포 조회 기간
+/// Query: window=
+#[derive(Debug, Deserialize)]
+pub struct PollIntervalQuery {
+    /// 마지막 체크인 기준 조회 기간 (예: "1h", "1d")
+    #[serde(default = "default_poll_interval_window")]
+    pub window: String,
+}
+
+fn default_poll_interval_window() -> String {
+    "1h".to_string()
+}
+
+/// 폴링 간격 요약 (클라이언트가 체크인에 보고한 직전 대기 시간)
+#[derive(Debug, FromRow, Serialize)]
+pub struct PollIntervalSummary {
+    /// 기간 안에 체크인한 클라이언트 수
+    pub clients: i64,
+    /// 그중 대기 시간을 보고한 클라이언트 수 (이전 버전 클라이언트와 배치 체크인은 보고하지 않음)
+    pub reporting: i64,
+    pub p50_secs: Option<f64>,
+    pub p90_secs: Option<f64>,
+    pub max_secs: Option<i32>,
+}
+
+/// 실제 폴링 간격 분포
+#[derive(Debug, Serialize)]
+pub struct PollIntervalStats {
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub window_start: DateTime<Utc>,
+    #[serde(flatten)]
+    pub summary: PollIntervalSummary,
+    /// 구간별 클라이언트 수 (le_secs 이하, 마지막 구간은 le_secs가 null)
+    pub buckets: Vec<PollIntervalBucket>,
+    /// 지금 유효한 전체 폴링 간격 힌트 (없으면 null)
+    pub next_poll_secs: Option<u64>,
+    /// 이 인스턴스가 503으로 돌려보낸 체크인 수 (시작 이후)
+    pub shed_total: u64,
+}
+
+/// 폴링 간격 분포 구간
+#[derive(Debug, Serialize)]
+pub struct PollIntervalBucket {
+    pub le_secs: Option<i32>,
+    pub count: i64,
 }
 
 /// 업데이트 결과 보고
@@ -119,4 +1153,489 @@ pub struct UpdateResultRequest {
     pub version: String,
     pub success: bool,
     pub error_message: Option<String>,
+    /// 클라이언트가 설치를 건너뛴 이유 (예: "already_installed")
+    #[serde(default)]
+    pub skipped_reason: Option<String>,
+    /// 클라이언트가 실제로 계산한 아티팩트 체크섬
+    #[serde(default)]
+    pub verified_checksum: Option<String>,
+    /// 스테이징 완료 보고 (아직 활성화되지 않음)
+    #[serde(default)]
+    pub staged: bool,
+    /// 클라이언트가 분류한 실패 원인
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+    /// 아티팩트를 받은 곳 (서버 경로 또는 미러 URL)
+    #[serde(default)]
+    pub artifact_source: Option<String>,
+    /// 추출한 트리를 설치한 방법 (rename 또는 copy)
+    #[serde(default)]
+    pub install_strategy: Option<String>,
+    /// 단계별 시각 (이전 버전 클라이언트는 보내지 않음)
+    #[serde(default)]
+    pub phases: UpdatePhaseTimes,
+    /// 빈 장비의 첫 설치 (백업/롤백 없이 설치)
+    #[serde(default)]
+    pub bootstrap: bool,
+}
+
+/// 클라이언트가 기록한 업데이트 단계별 시각
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdatePhaseTimes {
+    #[serde(default)]
+    pub download_started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub download_completed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub install_started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub restarted_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub healthy_at: Option<DateTime<Utc>>,
+}
+
+/// SLA 보고 필터
+/// Query: version=&window=&target_secs=
+#[derive(Debug, Deserialize)]
+pub struct SlaQuery {
+    #[serde(default)]
+    pub version: Option<String>,
+    /// 버전 심각도로 제한 (예: security - 보안 패치 적용 시간)
+    #[serde(default)]
+    pub severity: Option<String>,
+    /// 제공 시각 기준 조회 기간 (예: "24h", "7d")
+    #[serde(default = "default_sla_window")]
+    pub window: String,
+    /// 목표 시간 (제공부터 헬스 체크 통과까지, 초)
+    #[serde(default = "default_sla_target_secs")]
+    pub target_secs: i64,
+}
+
+fn default_sla_window() -> String {
+    "7d".to_string()
+}
+
+fn default_sla_target_secs() -> i64 {
+    600
+}
+
+/// 제공부터 헬스 체크 통과까지 걸린 시간 분포 (초)
+#[derive(Debug, FromRow, Serialize)]
+pub struct SlaStats {
+    /// 집계 대상 업데이트 수 (대부분 오프라인이던 클라이언트 제외)
+    pub offered: i64,
+    /// 헬스 체크까지 완료된 수
+    pub completed: i64,
+    /// 목표 시간 안에 완료된 수
+    pub within_target: i64,
+    /// 기간의 절반 이상 오프라인이라 제외된 업데이트 수
+    pub excluded_offline: i64,
+    pub p50_secs: Option<f64>,
+    pub p90_secs: Option<f64>,
+    pub p95_secs: Option<f64>,
+    pub p99_secs: Option<f64>,
+    pub max_secs: Option<f64>,
+}
+
+/// SLA 보고
+#[derive(Debug, Serialize)]
+pub struct SlaReport {
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub window_start: DateTime<Utc>,
+    pub target_secs: i64,
+    /// within_target / offered (집계 대상이 없으면 null)
+    pub within_target_ratio: Option<f64>,
+    #[serde(flatten)]
+    pub stats: SlaStats,
+}
+
+/// 설치 아티팩트 무결성 보고
+#[derive(Debug, Serialize)]
+pub struct IntegrityReport {
+    pub total: i64,
+    pub ok: i64,
+    pub mismatched: i64,
+    /// 체크섬을 보고하지 않았거나 서버에 없는 버전을 실행 중
+    pub unknown: i64,
+    /// 불일치 클라이언트 (오래된 불일치부터)
+    pub mismatches: Vec<IntegrityClient>,
+    /// 확인할 수 없는 클라이언트
+    pub unknown_clients: Vec<IntegrityClient>,
+}
+
+/// 무결성 보고의 클라이언트
+#[derive(Debug, FromRow, Serialize)]
+pub struct IntegrityClient {
+    pub id: Uuid,
+    pub name: String,
+    pub status: String,
+    pub current_version: Option<String>,
+    /// 서버가 설치했다고 기록한 아티팩트 체크섬
+    pub expected_checksum: Option<String>,
+    pub reported_checksum: Option<String>,
+    /// 불일치가 처음 확인된 시각
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub mismatch_since: Option<DateTime<Utc>>,
+    /// 마지막으로 일치가 확인된 시각 (한 번도 없으면 null)
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub last_ok_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// 일회성 공유 링크 (외부 설치 기사용 아티팩트 다운로드)
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ShareLink {
+    pub token: String,
+    pub version: String,
+    pub created_by: String,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub expires_at: DateTime<Utc>,
+    pub max_downloads: i32,
+    pub download_count: i32,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// 만료 임박 알림(share.expiring)을 보낸 시각 (연장하면 초기화)
+    #[sqlx(default)]
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub expiry_warned_at: Option<DateTime<Utc>>,
+}
+
+impl ShareLink {
+    /// 남은 다운로드 횟수
+    pub fn remaining_downloads(&self) -> i32 {
+        (self.max_downloads - self.download_count).max(0)
+    }
+
+    /// 만료까지 남은 시간 (초, 이미 만료되었으면 0)
+    pub fn expires_in_secs(&self, now: DateTime<Utc>) -> i64 {
+        (self.expires_at - now).num_seconds().max(0)
+    }
+
+    /// 더 이상 받을 수 없는 이유 (받을 수 있으면 None)
+    pub fn unavailable_reason(&self, now: DateTime<Utc>) -> Option<&'static str> {
+        if self.revoked_at.is_some() {
+            Some("Share link revoked")
+        } else if self.expires_at <= now {
+            Some("Share link expired")
+        } else if self.download_count >= self.max_downloads {
+            Some("Share link download limit reached")
+        } else {
+            None
+        }
+    }
+}
+
+/// 공유 링크 생성 요청
+#[derive(Debug, Deserialize)]
+pub struct CreateShareRequest {
+    /// 유효 기간 (초)
+    pub expires_in: i64,
+    /// 최대 다운로드 횟수 (기본 1)
+    #[serde(default = "default_share_downloads")]
+    pub max_downloads: i32,
+    /// 링크를 만든 운영자 (없으면 "api")
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+fn default_share_downloads() -> i32 {
+    1
+}
+
+/// 공유 링크 생성 응답
+#[derive(Debug, Serialize)]
+pub struct ShareLinkResponse {
+    #[serde(flatten)]
+    pub link: ShareLink,
+    /// 다운로드 경로 (외부 접두사 포함, 서버 주소를 앞에 붙여 전달)
+    pub url: String,
+}
+
+/// 공유 링크 다운로드 기록
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ShareDownload {
+    #[serde(skip)]
+    pub token: String,
+    /// manifest.json을 포함한 tar.gz로 받았는지
+    pub bundle: bool,
+    pub remote_addr: Option<String>,
+    pub user_agent: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub downloaded_at: DateTime<Utc>,
+}
+
+/// 버전의 공유 링크와 다운로드/연장 기록 (감사용)
+#[derive(Debug, Serialize)]
+pub struct ShareLinkAudit {
+    #[serde(flatten)]
+    pub link: ShareLink,
+    pub remaining_downloads: i32,
+    /// 만료까지 남은 시간 (초, 만료되었으면 0)
+    pub expires_in_secs: i64,
+    pub downloads: Vec<ShareDownload>,
+    pub renewals: Vec<ShareRenewal>,
+}
+
+/// 공유 링크 연장 요청
+#[derive(Debug, Deserialize)]
+pub struct RenewShareRequest {
+    /// 연장할 시간 (초, 현재 만료 시각 기준. 이미 만료되었으면 지금부터)
+    pub extend_by: i64,
+    /// 연장한 운영자 (없으면 "api")
+    #[serde(default)]
+    pub renewed_by: Option<String>,
+}
+
+/// 공유 링크 연장 기록
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ShareRenewal {
+    #[serde(skip)]
+    pub token: String,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub previous_expires_at: DateTime<Utc>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub expires_at: DateTime<Utc>,
+    pub renewed_by: String,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub renewed_at: DateTime<Utc>,
+}
+
+/// 공유 링크 다운로드 옵션
+#[derive(Debug, Default, Deserialize)]
+pub struct ShareDownloadQuery {
+    /// manifest.json과 아티팩트를 담은 tar.gz로 받기
+    #[serde(default)]
+    pub bundle: bool,
+}
+
+/// 버전 비활성화/삭제 옵션
+#[derive(Debug, Default, Deserialize)]
+pub struct VersionRemovalQuery {
+    /// 해당 버전을 타겟으로 가진 클라이언트의 target_version 클리어
+    #[serde(default)]
+    pub clear_targets: bool,
+}
+
+/// 검색 요청
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+}
+
+/// 검색 결과 (카테고리별)
+#[derive(Debug, Serialize)]
+pub struct SearchResults {
+    pub query: String,
+    pub clients: SearchGroup<ClientSearchHit>,
+    pub versions: SearchGroup<VersionSearchHit>,
+    pub update_logs: SearchGroup<UpdateLogSearchHit>,
+}
+
+/// 카테고리별 검색 결과 (상위 N개)
+#[derive(Debug, Serialize)]
+pub struct SearchGroup<T> {
+    pub items: Vec<T>,
+    /// 결과가 N개를 초과하여 잘렸는지 여부
+    pub truncated: bool,
+}
+
+/// 클라이언트 검색 결과
+#[derive(Debug, FromRow, Serialize)]
+pub struct ClientSearchHit {
+    pub id: Uuid,
+    pub name: String,
+    pub status: String,
+    pub current_version: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// 버전 검색 결과
+#[derive(Debug, FromRow, Serialize)]
+pub struct VersionSearchHit {
+    pub version: String,
+    pub is_active: bool,
+    pub release_notes: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// 업데이트 로그 검색 결과
+#[derive(Debug, FromRow, Serialize)]
+pub struct UpdateLogSearchHit {
+    pub id: Uuid,
+    pub client_id: Option<Uuid>,
+    /// 삭제된 클라이언트면 기록 당시 이름 + " (deleted)"
+    pub client_name: String,
+    pub to_version: String,
+    pub status: String,
+    pub error_message: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub started_at: DateTime<Utc>,
+}
+
+/// 주의가 필요한 클라이언트 목록 (카테고리별)
+#[derive(Debug, Serialize)]
+pub struct AttentionReport {
+    /// 동일 API Key로 여러 데몬이 동작 중
+    pub multiple_agents: Vec<AttentionClient>,
+    /// 마지막 업데이트 실패
+    pub update_failed: Vec<AttentionClient>,
+    /// 파일시스템 장애 (읽기 전용, 디스크 부족, I/O 오류) - 하드웨어 점검 필요
+    pub hardware_suspect: Vec<AttentionClient>,
+    /// 장비가 검증한 체크섬이 업로드 체크섬과 다름 - 손상 또는 변조 의심
+    pub provenance_mismatch: Vec<AttentionClient>,
+    /// 설치 상태가 수동으로 수정됨 - 서버의 버전 정보를 신뢰할 수 없음
+    pub state_tampered: Vec<AttentionClient>,
+    /// 체크인에서 보고한 설치 체크섬이 배포한 버전과 다름
+    pub integrity_mismatch: Vec<AttentionClient>,
+}
+
+/// 주의가 필요한 클라이언트
+#[derive(Debug, FromRow, Serialize)]
+pub struct AttentionClient {
+    pub id: Uuid,
+    pub name: String,
+    pub status: String,
+    pub current_version: Option<String>,
+    pub target_version: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub last_seen: Option<DateTime<Utc>>,
+    /// 해당 상태가 마지막으로 감지된 시각
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub since: Option<DateTime<Utc>>,
+    /// 분류된 원인 (해당하는 경우)
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// 최근 실패의 fingerprint (`GET /api/failures`로 같은 실패를 겪은 장비 확인)
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_fingerprint: Option<String>,
+}
+
+/// 버전 출처 추적 (업로드 → 서버 전송 → 장비 검증)
+#[derive(Debug, Serialize)]
+pub struct VersionProvenance {
+    pub version: String,
+    /// 업로드 시 계산한 체크섬 (기준값)
+    pub checksum: String,
+    pub uploaded_by: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub uploaded_at: DateTime<Utc>,
+    pub build_info: Option<BuildInfo>,
+    pub clients: Vec<ClientProvenance>,
+    /// 불일치가 있는 클라이언트 수
+    pub mismatches: usize,
+}
+
+/// 클라이언트별 출처 추적
+#[derive(Debug, FromRow, Serialize)]
+pub struct ClientProvenance {
+    pub client_id: Uuid,
+    pub client_name: String,
+    pub downloads: i64,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub last_downloaded_at: Option<DateTime<Utc>>,
+    /// 마지막 다운로드에서 전송한 체크섬 헤더
+    pub served_checksum: Option<String>,
+    /// 장비가 설치 전에 계산한 체크섬
+    pub verified_checksum: Option<String>,
+    pub update_status: Option<String>,
+    #[serde(with = "crate::timefmt::rfc3339::option")]
+    pub reported_at: Option<DateTime<Utc>>,
+    /// 업로드 체크섬과 다른 단계 ("served", "verified")
+    #[sqlx(skip)]
+    pub mismatch: Vec<&'static str>,
+}
+
+/// 장비 현황 조사 요청
+#[derive(Debug, Deserialize)]
+pub struct QueueSurveyRequest {
+    /// 수집 항목 (이름이 정해진 항목만, 예: ["system", "disks"])
+    pub commands: Vec<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// 요청한 사람 (생략하면 "api")
+    #[serde(default)]
+    pub initiated_by: Option<String>,
+}
+
+/// 클라이언트의 조사 결과 업로드
+#[derive(Debug, Deserialize)]
+pub struct SurveyResultRequest {
+    pub action_id: Uuid,
+    /// 항목별 결과
+    #[serde(default)]
+    pub results: serde_json::Map<String, serde_json::Value>,
+    /// 수집하지 못한 항목과 사유
+    #[serde(default)]
+    pub errors: std::collections::BTreeMap<String, String>,
+}
+
+/// 조사 결과 조회 조건
+#[derive(Debug, Deserialize)]
+pub struct SurveyQuery {
+    #[serde(default = "default_survey_limit")]
+    pub limit: i64,
+}
+
+fn default_survey_limit() -> i64 {
+    20
+}
+
+/// 저장된 조사 결과
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ClientSurvey {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub action_id: Uuid,
+    pub commands: Vec<String>,
+    pub results: sqlx::types::Json<serde_json::Value>,
+    pub errors: sqlx::types::Json<serde_json::Value>,
+    /// 업로드된 결과 크기 (바이트)
+    pub size_bytes: i32,
+    #[serde(with = "crate::timefmt::rfc3339")]
+    pub collected_at: DateTime<Utc>,
+}
+
+/// 클라이언트 삭제 결과 (업데이트 로그는 client_id만 해제되어 남음)
+#[derive(Debug, Serialize)]
+pub struct DeletedClient {
+    pub id: Uuid,
+    pub name: String,
+    /// 이름과 함께 보존된 업데이트 로그 수
+    pub retained_update_logs: i64,
+    /// 업데이트 중인 클라이언트를 force로 삭제했는지
+    pub forced: bool,
+}
+
+/// 클라이언트 삭제 옵션
+#[derive(Debug, Default, Deserialize)]
+pub struct ClientDeletionQuery {
+    /// 업데이트 중(status = updating)이어도 삭제
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// 클라이언트 이력 삭제 요청 (client_id 또는 삭제된 클라이언트의 client_name 중 하나)
+#[derive(Debug, Deserialize)]
+pub struct PurgeClientHistoryRequest {
+    #[serde(default)]
+    pub client_id: Option<Uuid>,
+    #[serde(default)]
+    pub client_name: Option<String>,
+}
+
+/// 클라이언트 이력 삭제 결과 (삭제된 행 수)
+#[derive(Debug, Default, Serialize)]
+pub struct PurgeClientHistoryResponse {
+    pub update_logs: u64,
+    pub artifact_downloads: u64,
 }
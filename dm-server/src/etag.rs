@@ -0,0 +1,53 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// 본문 해시 기반 강한 ETag (`"<sha256 앞 32자>"`)
+pub fn for_body(body: &[u8]) -> String {
+    let digest = format!("{:x}", Sha256::digest(body));
+    format!("\"{}\"", &digest[..32])
+}
+
+/// If-None-Match가 이 ETag와 일치하는지 (목록, 약한 비교 `W/`, `*` 지원)
+pub fn matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// ETag를 붙인 응답 (요청의 If-None-Match와 같으면 본문 없이 304)
+///
+/// 클라이언트가 매번 재검증하도록 `Cache-Control: no-cache`를 함께 보낸다.
+pub fn respond(headers: &HeaderMap, content_type: &'static str, body: Vec<u8>) -> Response {
+    let etag = for_body(&body);
+    let not_modified = matches(headers, &etag);
+
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        Response::new(Body::from(body))
+    };
+    let response_headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response_headers.insert(header::ETAG, value);
+    }
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    if !not_modified {
+        response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    }
+    response
+}
+
+/// JSON 응답에 ETag 적용
+pub fn json<T: Serialize>(headers: &HeaderMap, value: &T) -> Result<Response, (StatusCode, String)> {
+    let body = serde_json::to_vec(value).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(respond(headers, "application/json", body))
+}
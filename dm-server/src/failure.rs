@@ -0,0 +1,125 @@
+use sha2::{Digest, Sha256};
+
+/// 잘린 메시지의 앞/뒤 사이에 넣는 표시
+const TRUNCATION_MARKER: &str = "\n... [truncated] ...\n";
+
+/// 서버에서 분류한 실패 결과
+#[derive(Debug, Clone)]
+pub struct ClassifiedFailure {
+    /// 정규화된 메시지 해시 (같은 문제를 묶는 키)
+    pub fingerprint: String,
+    /// 실패 분류 (클라이언트가 보고한 원인 우선)
+    pub category: String,
+}
+
+/// 실패 메시지 분류 (fingerprint, 원인)
+pub fn classify(message: &str, client_reason: Option<&str>) -> ClassifiedFailure {
+    ClassifiedFailure {
+        fingerprint: fingerprint(message),
+        category: client_reason
+            .map(str::to_string)
+            .unwrap_or_else(|| category(message).to_string()),
+    }
+}
+
+/// 최대 길이(바이트)를 넘으면 앞/뒤를 남기고 가운데를 생략
+///
+/// 스택 트레이스는 보통 앞(에러 종류)과 뒤(최초 원인)가 중요하다.
+pub fn truncate(message: &str, max_len: usize) -> String {
+    if message.len() <= max_len {
+        return message.to_string();
+    }
+    let keep = max_len.saturating_sub(TRUNCATION_MARKER.len()) / 2;
+    let head = floor_char_boundary(message, keep);
+    let tail = ceil_char_boundary(message, message.len() - keep);
+    format!("{}{}{}", &message[..head], TRUNCATION_MARKER, &message[tail..])
+}
+
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+/// 정규화된 메시지의 해시 (16자)
+///
+/// 장비마다 다른 값(숫자, 경로의 임시 디렉토리, 해시, UUID)을 지워 같은 문제가 같은 값을 갖게 한다.
+pub fn fingerprint(message: &str) -> String {
+    let digest = Sha256::digest(normalize(message).as_bytes());
+    format!("{:x}", digest)[..16].to_string()
+}
+
+/// fingerprint용 정규화
+fn normalize(message: &str) -> String {
+    message
+        .split_whitespace()
+        .map(|token| {
+            let token = token.to_lowercase();
+            let trimmed = token.trim_matches(|c: char| !c.is_ascii_alphanumeric() && c != '/' && c != '.');
+            if trimmed.contains("/tmp/") || trimmed.contains("/.tmp") {
+                "<tmp>".to_string()
+            } else if trimmed.len() >= 8 && trimmed.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+                "<hex>".to_string()
+            } else {
+                collapse_digits(&token)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 연속된 숫자를 '#' 하나로
+fn collapse_digits(token: &str) -> String {
+    let mut out = String::with_capacity(token.len());
+    let mut in_digits = false;
+    for c in token.chars() {
+        if c.is_ascii_digit() {
+            if !in_digits {
+                out.push('#');
+            }
+            in_digits = true;
+        } else {
+            out.push(c);
+            in_digits = false;
+        }
+    }
+    out
+}
+
+/// 메시지 기반 실패 분류 (클라이언트가 원인을 보고하지 않은 경우)
+pub fn category(message: &str) -> &'static str {
+    let message = message.to_lowercase();
+    let has = |needles: &[&str]| needles.iter().any(|n| message.contains(n));
+
+    if has(&["read-only file system"]) {
+        "fs_read_only"
+    } else if has(&["no space left"]) {
+        "disk_full"
+    } else if has(&["timed out", "timeout"]) {
+        "timed_out"
+    } else if has(&["backup failed"]) {
+        "backup"
+    } else if has(&["install script"]) {
+        "install_script"
+    } else if has(&["checksum"]) {
+        "checksum_mismatch"
+    } else if has(&["download", "stalled", "connection", "http"]) {
+        "download"
+    } else if has(&["extract", "archive", "gzip", "tar"]) {
+        "extract"
+    } else if has(&["health check"]) {
+        "health_check"
+    } else if has(&["restart"]) {
+        "restart"
+    } else {
+        "other"
+    }
+}
@@ -0,0 +1,100 @@
+use sqlx::postgres::PgConnectOptions;
+use sqlx::{ConnectOptions, Connection, PgConnection, PgPool};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db;
+
+/// 백그라운드 작업 리더 잠금 키 (Postgres advisory lock, "samd")
+const TASK_LOCK_KEY: i64 = 0x7361_6d64;
+
+/// 여러 서버 인스턴스 중 백그라운드 작업을 실행할 리더
+///
+/// 리더는 전용 DB 연결에서 advisory lock을 잡고 있는 인스턴스다. 프로세스가 죽으면
+/// 연결과 함께 잠금이 풀리고, 다른 인스턴스가 다음 heartbeat에 잠금을 가져간다.
+/// 백그라운드 작업은 매 주기 `is_leader()`를 확인해 리더일 때만 실행한다.
+pub struct Leadership {
+    instance_id: String,
+    leader: AtomicBool,
+}
+
+impl Leadership {
+    pub fn new(instance_id: String) -> Self {
+        Self {
+            instance_id,
+            leader: AtomicBool::new(false),
+        }
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// 이 인스턴스가 현재 잠금을 잡고 있는지
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+
+    fn set_leader(&self, leader: bool) {
+        if self.leader.swap(leader, Ordering::Relaxed) != leader {
+            if leader {
+                tracing::info!("Instance {} is now the task leader", self.instance_id);
+            } else {
+                tracing::warn!("Instance {} is no longer the task leader", self.instance_id);
+            }
+        }
+    }
+}
+
+/// 리더 선출 heartbeat 시작
+///
+/// 매 주기 리더는 잠금 연결이 살아 있는지 확인하고, 리더가 아니면 잠금을 시도한다.
+/// 잠금 연결의 application_name은 인스턴스 ID (다른 인스턴스가 `current_leader`로 조회).
+pub fn spawn(database_url: String, leadership: Arc<Leadership>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut conn: Option<PgConnection> = None;
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match heartbeat(&database_url, &leadership, &mut conn).await {
+                Ok(leader) => leadership.set_leader(leader),
+                Err(e) => {
+                    tracing::warn!("Task leader heartbeat failed: {}", e);
+                    // 연결을 버려 잡고 있던 잠금도 해제 (다른 인스턴스가 이어받음)
+                    conn = None;
+                    leadership.set_leader(false);
+                }
+            }
+        }
+    });
+}
+
+async fn heartbeat(
+    database_url: &str,
+    leadership: &Leadership,
+    conn: &mut Option<PgConnection>,
+) -> anyhow::Result<bool> {
+    let conn = match conn {
+        Some(conn) => conn,
+        None => {
+            let options: PgConnectOptions = database_url.parse()?;
+            conn.insert(options.application_name(leadership.instance_id()).connect().await?)
+        }
+    };
+
+    if leadership.is_leader() {
+        conn.ping().await?;
+        return Ok(true);
+    }
+    let acquired = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+        .bind(TASK_LOCK_KEY)
+        .fetch_one(&mut *conn)
+        .await?;
+    Ok(acquired)
+}
+
+/// 현재 리더 인스턴스 ID (어느 인스턴스도 잠금을 잡고 있지 않으면 None)
+pub async fn current_leader(pool: &PgPool) -> anyhow::Result<Option<String>> {
+    db::advisory_lock_holder(pool, TASK_LOCK_KEY).await
+}
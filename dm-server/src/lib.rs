@@ -0,0 +1,163 @@
+//! Sam DM Server 라이브러리 (바이너리와 통합 테스트가 함께 사용)
+
+pub mod api;
+pub mod archive;
+pub mod bundle;
+pub mod changelog;
+pub mod config;
+pub mod db;
+pub mod etag;
+pub mod failure;
+pub mod leader;
+pub mod listener;
+pub mod prefix;
+pub mod scan;
+pub mod schedule;
+pub mod tasks;
+pub mod timefmt;
+pub mod transport;
+pub mod upload;
+pub mod webhook;
+
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{delete, get, post, put},
+    Router,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tower_http::cors::{Any, CorsLayer};
+use tower_http::trace::TraceLayer;
+
+use config::Config;
+use leader::Leadership;
+use listener::ConnectionMetrics;
+use schedule::CheckinLoad;
+use webhook::Webhook;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: PgPool,
+    pub config: Arc<Config>,
+    pub webhook: Arc<Webhook>,
+    pub connections: Arc<ConnectionMetrics>,
+    /// 백그라운드 작업 리더 선출 상태 (`leader::spawn`으로 시작)
+    pub leadership: Arc<Leadership>,
+    /// 처리 중인 체크인 수와 폴링 간격 힌트 캐시
+    pub checkin_load: Arc<CheckinLoad>,
+}
+
+impl AppState {
+    pub fn new(config: Config, pool: PgPool) -> Self {
+        Self {
+            pool,
+            webhook: Arc::new(Webhook::new(config.webhook_url.clone())),
+            leadership: Arc::new(Leadership::new(config.instance_id.clone())),
+            config: Arc::new(config),
+            connections: Arc::new(ConnectionMetrics::default()),
+            checkin_load: Arc::new(CheckinLoad::default()),
+        }
+    }
+}
+
+/// API 라우터
+pub fn router(state: AppState) -> Router {
+    // CORS 설정
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    // 업로드 본문 크기 제한 (MAX_ARTIFACT_BYTES + 다른 필드 여유분)
+    let upload_limit = DefaultBodyLimit::max(upload::body_limit(&state.config));
+
+    // 라우터 설정
+    let app = Router::new()
+        // 관리 API
+        .route("/api/clients", get(api::list_clients).post(api::register_client))
+        .route("/api/clients/self", get(api::get_client_self))
+        .route("/api/clients/:id", get(api::get_client).delete(api::delete_client))
+        .route("/api/clients/:id/config", put(api::update_client_config))
+        .route("/api/clients/:id/config/history", get(api::get_client_config_history))
+        .route("/api/clients/:id/config/rollback", post(api::rollback_client_config))
+        .route("/api/deploy", post(api::bulk_deploy))
+        .route(
+            "/api/clients/:id/deploy",
+            post(api::deploy_to_client).delete(api::cancel_client_deploy),
+        )
+        .route(
+            "/api/clients/:id/actions",
+            get(api::list_client_actions).post(api::queue_client_action),
+        )
+        .route("/api/clients/:id/actions/:action_id", delete(api::cancel_client_action))
+        .route("/api/clients/:id/survey", post(api::queue_survey))
+        .route("/api/clients/:id/surveys", get(api::list_client_surveys))
+        .route("/api/clients/:id/activate", post(api::activate_client_deploy))
+        .route("/api/clients/:id/pin", put(api::pin_client).delete(api::unpin_client))
+        .route(
+            "/api/versions",
+            get(api::list_versions).post(api::upload_version).layer(upload_limit),
+        )
+        .route("/api/versions/validate", post(api::validate_version_upload).layer(upload_limit))
+        .route("/api/versions/:version", get(api::get_version).patch(api::update_version).delete(api::delete_version))
+        .route("/api/versions/:version/provenance", get(api::get_version_provenance))
+        .route("/api/versions/:version/scripts", get(api::get_version_scripts))
+        .route("/api/versions/:version/changelog", get(api::get_version_changelog))
+        .route("/api/versions/:version/activate", post(api::activate_version))
+        .route("/api/versions/:version/deactivate", post(api::deactivate_version))
+        .route("/api/versions/:version/rescan", post(api::rescan_version))
+        .route("/api/versions/:version/share", post(api::create_share_link))
+        .route("/api/versions/:version/shares", get(api::list_share_links))
+        .route("/api/aliases", get(api::list_aliases))
+        .route(
+            "/api/aliases/:name",
+            get(api::get_alias).put(api::set_alias).delete(api::delete_alias),
+        )
+        .route("/api/bootstrap", get(api::list_bootstrap_versions))
+        .route(
+            "/api/bootstrap/:role",
+            put(api::set_bootstrap_version).delete(api::delete_bootstrap_version),
+        )
+        .route("/api/artifacts/:version", get(api::download_artifact))
+        .route("/api/bundles", post(api::create_bundle))
+        .route(
+            "/api/share/:token",
+            get(api::download_shared_artifact).delete(api::revoke_share_link),
+        )
+        .route("/api/share/:token/renew", post(api::renew_share_link))
+        .route("/api/logs", get(api::list_update_logs))
+        .route("/api/failures", get(api::list_failures))
+        .route("/api/reports/sla", get(api::get_sla_report))
+        .route("/api/reports/integrity", get(api::get_integrity_report))
+        .route("/api/search", get(api::search))
+        .route("/api/attention", get(api::get_attention))
+        .route("/api/metrics/connections", get(api::get_connection_metrics))
+        .route("/api/metrics/poll-intervals", get(api::get_poll_interval_metrics))
+        .route("/api/settings", get(api::get_settings))
+        .route(
+            "/api/settings/poll-hint",
+            put(api::set_poll_hint).delete(api::clear_poll_hint),
+        )
+        .route("/api/admin/export", get(api::export_state))
+        .route("/api/admin/purge-client-history", post(api::purge_client_history))
+        // 클라이언트 Polling API
+        .route("/api/checkin", post(api::checkin))
+        .route("/api/checkin/batch", post(api::checkin_batch))
+        .route("/api/update-result", post(api::report_update_result))
+        .route("/api/action-result", post(api::report_action_result))
+        .route("/api/survey-result", post(api::report_survey_result))
+        // Health check
+        .route("/health", get(api::health));
+
+    // 리버스 프록시가 접두사를 떼지 않고 전달하는 경우 (BASE_PATH=/dm)
+    let base_path = state.config.base_path.clone();
+    let app = if base_path.is_empty() {
+        app
+    } else {
+        Router::new().nest(&base_path, app)
+    };
+
+    app.layer(cors)
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
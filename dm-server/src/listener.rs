@@ -0,0 +1,137 @@
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request},
+    Router,
+};
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use serde::Serialize;
+use socket2::{SockRef, TcpKeepalive};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower::Service;
+
+use crate::config::Config;
+
+/// 연결 수준 통계 (GET /api/metrics/connections)
+///
+/// 새 연결 수(`accepted_total`)가 곧 TCP/TLS 핸드셰이크 수이므로,
+/// 체크인 수 대비 이 값이 작을수록 연결이 재사용되고 있다는 뜻이다.
+#[derive(Debug, Default)]
+pub struct ConnectionMetrics {
+    accepted: AtomicU64,
+    closed: AtomicU64,
+    requests: AtomicU64,
+    reused_requests: AtomicU64,
+    http2_requests: AtomicU64,
+}
+
+/// 연결 통계 스냅샷
+#[derive(Debug, Serialize)]
+pub struct ConnectionStats {
+    pub accepted_total: u64,
+    pub active: u64,
+    pub closed_total: u64,
+    pub requests_total: u64,
+    /// 이미 요청을 처리한 연결에서 들어온 요청 수 (핸드셰이크 없이 처리됨)
+    pub reused_requests_total: u64,
+    pub http2_requests_total: u64,
+    pub requests_per_connection: f64,
+}
+
+impl ConnectionMetrics {
+    pub fn snapshot(&self) -> ConnectionStats {
+        let accepted = self.accepted.load(Ordering::Relaxed);
+        let closed = self.closed.load(Ordering::Relaxed);
+        let requests = self.requests.load(Ordering::Relaxed);
+        ConnectionStats {
+            accepted_total: accepted,
+            active: accepted.saturating_sub(closed),
+            closed_total: closed,
+            requests_total: requests,
+            reused_requests_total: self.reused_requests.load(Ordering::Relaxed),
+            http2_requests_total: self.http2_requests.load(Ordering::Relaxed),
+            requests_per_connection: if accepted == 0 {
+                0.0
+            } else {
+                requests as f64 / accepted as f64
+            },
+        }
+    }
+}
+
+/// HTTP 서버 실행 (keep-alive 설정 및 연결 통계 포함)
+///
+/// `axum::serve`는 keep-alive/HTTP2 설정을 노출하지 않으므로 hyper 연결을 직접 관리한다.
+/// HTTP/1은 keep-alive 연결에서 다음 요청을 `HTTP_IDLE_TIMEOUT_SECS`까지 기다리고,
+/// HTTP/2(h2c)는 PING으로 연결 상태를 확인한다.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    config: &Config,
+    metrics: Arc<ConnectionMetrics>,
+) -> anyhow::Result<()> {
+    let mut builder = Builder::new(TokioExecutor::new());
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .keep_alive(true)
+        // 요청 사이 대기에도 적용되므로 유휴 연결 제한 시간 역할을 한다
+        .header_read_timeout(Duration::from_secs(config.http_idle_timeout_secs));
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .keep_alive_interval(Duration::from_secs(config.http2_keepalive_interval_secs))
+        .keep_alive_timeout(Duration::from_secs(config.http2_keepalive_timeout_secs));
+    let builder = Arc::new(builder);
+    let keepalive = TcpKeepalive::new().with_time(Duration::from_secs(config.tcp_keepalive_secs));
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // fd 고갈 등 일시적 오류: 잠시 쉬고 계속
+                tracing::warn!("Failed to accept connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let _ = stream.set_nodelay(true);
+        if let Err(e) = SockRef::from(&stream).set_tcp_keepalive(&keepalive) {
+            tracing::debug!("Failed to set TCP keepalive for {}: {}", peer, e);
+        }
+        metrics.accepted.fetch_add(1, Ordering::Relaxed);
+
+        let builder = builder.clone();
+        let metrics = metrics.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let served = Arc::new(AtomicU64::new(0));
+            let service = hyper::service::service_fn({
+                let metrics = metrics.clone();
+                move |req: Request<Incoming>| {
+                    metrics.requests.fetch_add(1, Ordering::Relaxed);
+                    if served.fetch_add(1, Ordering::Relaxed) > 0 {
+                        metrics.reused_requests.fetch_add(1, Ordering::Relaxed);
+                    }
+                    if req.version() == hyper::Version::HTTP_2 {
+                        metrics.http2_requests.fetch_add(1, Ordering::Relaxed);
+                    }
+                    // 신뢰하는 프록시 판단용 (X-Forwarded-Prefix)
+                    let mut req = req.map(Body::new);
+                    req.extensions_mut().insert(ConnectInfo(peer));
+                    // Router는 항상 준비 상태라 poll_ready 없이 호출해도 된다
+                    app.clone().call(req)
+                }
+            });
+
+            if let Err(e) = builder.serve_connection(TokioIo::new(stream), service).await {
+                tracing::debug!("Connection from {} closed with error: {}", peer, e);
+            }
+            metrics.closed.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+}
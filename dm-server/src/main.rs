@@ -1,27 +1,35 @@
-mod api;
-mod config;
-mod db;
-
-use axum::{
-    routing::{get, post, put},
-    Router,
-};
+use clap::{Parser, Subcommand};
 use sqlx::PgPool;
-use std::sync::Arc;
-use tower_http::cors::{Any, CorsLayer};
-use tower_http::trace::TraceLayer;
+use std::path::PathBuf;
+use std::time::Duration;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use config::Config;
+use dm_server::config::Config;
+use dm_server::{archive, changelog, db, leader, listener, tasks, AppState};
 
-#[derive(Clone)]
-pub struct AppState {
-    pub pool: PgPool,
-    pub config: Arc<Config>,
+#[derive(Parser)]
+#[command(name = "dm-server", version, about = "🦊 Sam DM Server")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// API 서버 실행 (기본)
+    Serve,
+
+    /// 내보내기 아카이브(GET /api/admin/export)를 이 서버로 가져오기
+    Import {
+        /// 아카이브 파일 경로 (.tar.gz)
+        file: PathBuf,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
     // 로깅 초기화
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
@@ -35,50 +43,55 @@ async fn main() -> anyhow::Result<()> {
 
     // 설정 로드
     let config = Config::from_env()?;
-    tracing::info!("Starting DM Server on {}", config.server_addr());
 
     // 데이터베이스 연결
     let pool = db::create_pool(&config.database_url).await?;
     tracing::info!("Connected to database");
 
+    match cli.command.unwrap_or(Commands::Serve) {
+        Commands::Serve => serve(config, pool).await,
+        Commands::Import { file } => {
+            let report = archive::import(&pool, &config.artifact_dir, &file).await?;
+            print!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+async fn serve(config: Config, pool: PgPool) -> anyhow::Result<()> {
+    tracing::info!("Starting DM Server on {}", config.server_addr());
+
     // 아티팩트 디렉토리 생성
     tokio::fs::create_dir_all(&config.artifact_dir).await?;
     tracing::info!("Artifact directory: {}", config.artifact_dir);
 
-    let state = AppState {
-        pool,
-        config: Arc::new(config.clone()),
-    };
-
-    // CORS 설정
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
-    // 라우터 설정
-    let app = Router::new()
-        // 관리 API
-        .route("/api/clients", get(api::list_clients).post(api::register_client))
-        .route("/api/clients/:id", get(api::get_client))
-        .route("/api/clients/:id/config", put(api::update_client_config))
-        .route("/api/clients/:id/deploy", post(api::deploy_to_client))
-        .route("/api/versions", get(api::list_versions).post(api::upload_version))
-        .route("/api/versions/:version", get(api::get_version))
-        .route("/api/artifacts/:version", get(api::download_artifact))
-        // 클라이언트 Polling API
-        .route("/api/checkin", post(api::checkin))
-        .route("/api/update-result", post(api::report_update_result))
-        // Health check
-        .route("/health", get(|| async { "OK" }))
-        .layer(cors)
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+    // 한도를 넘는 기존 릴리즈 노트는 변경 이력 파일로 이동
+    changelog::migrate_oversized_notes(&pool, std::path::Path::new(&config.artifact_dir)).await?;
+
+    if let Some(url) = &config.webhook_url {
+        tracing::info!("Webhook URL: {}", url);
+    }
+
+    if config.require_tls_clients && config.trusted_proxies.is_empty() {
+        tracing::warn!("REQUIRE_TLS_CLIENTS=true without TRUSTED_PROXIES: every checkin will be rejected");
+    }
+
+    let state = AppState::new(config.clone(), pool);
+    tracing::info!("Instance ID: {}", config.instance_id);
+    leader::spawn(
+        config.database_url.clone(),
+        state.leadership.clone(),
+        Duration::from_secs(config.leader_heartbeat_secs),
+    );
+    tasks::spawn(state.clone());
+    let connections = state.connections.clone();
+
+    let app = dm_server::router(state);
 
     // 서버 시작
     let listener = tokio::net::TcpListener::bind(config.server_addr()).await?;
     tracing::info!("🦊 Sam DM Server is running!");
-    axum::serve(listener, app).await?;
+    listener::serve(listener, app, &config, connections).await?;
 
     Ok(())
 }
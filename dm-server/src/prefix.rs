@@ -0,0 +1,65 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::request::Parts,
+};
+use ipnet::IpNet;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::AppState;
+
+/// 응답에 담는 경로의 외부 접두사 (예: "/dm", 루트면 "")
+///
+/// 신뢰하는 프록시(TRUSTED_PROXIES)가 보낸 `X-Forwarded-Prefix`가 있으면 그 값을,
+/// 없으면 BASE_PATH를 사용한다. 접두사를 떼고 전달하는 인그레스는 헤더로,
+/// 그대로 전달하는 인그레스는 BASE_PATH로 맞춘다.
+#[derive(Debug, Clone)]
+pub struct PublicPrefix(pub String);
+
+#[async_trait]
+impl FromRequestParts<AppState> for PublicPrefix {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let forwarded = peer
+            .filter(|ip| is_trusted(&state.config.trusted_proxies, *ip))
+            .and_then(|_| parts.headers.get("X-Forwarded-Prefix"))
+            .and_then(|v| v.to_str().ok())
+            .and_then(normalize);
+
+        Ok(Self(forwarded.unwrap_or_else(|| state.config.base_path.clone())))
+    }
+}
+
+pub(crate) fn is_trusted(proxies: &[IpNet], ip: IpAddr) -> bool {
+    // IPv4 매핑 IPv6 주소(::ffff:a.b.c.d)도 IPv4 목록과 비교
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    };
+    proxies.iter().any(|net| net.contains(&ip))
+}
+
+/// 경로 접두사 정리: 앞에 '/'를 붙이고 끝의 '/'는 제거 (루트는 "")
+///
+/// 경로로 쓸 수 없는 값(공백, 제어 문자, `..`, 쿼리)은 None
+pub fn normalize(prefix: &str) -> Option<String> {
+    let trimmed = prefix.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        return Some(String::new());
+    }
+    let valid = trimmed.split('/').all(|segment| {
+        !segment.is_empty()
+            && segment != ".."
+            && segment != "."
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c))
+    });
+    valid.then(|| format!("/{}", trimmed))
+}
@@ -0,0 +1,135 @@
+use axum::http::StatusCode;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+use crate::db::{self, Version};
+use crate::{failure, AppState};
+
+/// 저장할 검사 출력 최대 길이 (바이트)
+const MAX_OUTPUT_LEN: usize = 4096;
+
+/// 검사 결과
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanStatus {
+    Pending,
+    Clean,
+    Infected,
+    Error,
+}
+
+impl ScanStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Clean => "clean",
+            Self::Infected => "infected",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// 업로드 직후 상태 (검사 명령이 없으면 바로 clean)
+pub fn initial_status(state: &AppState) -> ScanStatus {
+    if state.config.scan_command.is_some() {
+        ScanStatus::Pending
+    } else {
+        ScanStatus::Clean
+    }
+}
+
+/// 검사를 통과하지 않은 버전의 배포 차단 (409)
+pub fn ensure_deployable(ver: &Version) -> Result<(), (StatusCode, String)> {
+    if ver.scan_status == ScanStatus::Clean.as_str() {
+        return Ok(());
+    }
+    Err((
+        StatusCode::CONFLICT,
+        format!(
+            "Version {} cannot be deployed: artifact scan is {}",
+            ver.version, ver.scan_status
+        ),
+    ))
+}
+
+/// 백그라운드에서 아티팩트 검사 실행 (SCAN_COMMAND가 없으면 아무 것도 하지 않음)
+///
+/// 종료 코드 0은 clean, 1은 infected(clamscan/clamdscan 관례), 그 외와 타임아웃은 error.
+pub fn spawn(state: AppState, version: String, artifact_path: String) {
+    let Some(template) = state.config.scan_command.clone() else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let path = Path::new(&state.config.artifact_dir).join(&artifact_path);
+        let command = template.replace("{file}", &shell_quote(&path.to_string_lossy()));
+        let timeout = Duration::from_secs(state.config.scan_timeout_secs);
+
+        let started = Instant::now();
+        let (status, output) = run(&command, timeout).await;
+        let duration_ms = started.elapsed().as_millis() as i64;
+        let output = failure::truncate(output.trim(), MAX_OUTPUT_LEN);
+
+        tracing::info!(
+            "Scan of {} finished: {} ({} ms)",
+            version,
+            status.as_str(),
+            duration_ms
+        );
+        if let Err(e) =
+            db::set_version_scan_result(&state.pool, &version, status.as_str(), &output, duration_ms)
+                .await
+        {
+            tracing::error!("Failed to record scan result for {}: {}", version, e);
+            return;
+        }
+
+        state.webhook.emit(
+            &format!("version.scan_{}", status.as_str()),
+            serde_json::json!({
+                "version": version,
+                "scan_status": status.as_str(),
+                "scan_output": output,
+                "scan_duration_ms": duration_ms,
+            }),
+        );
+    });
+}
+
+async fn run(command: &str, timeout: Duration) -> (ScanStatus, String) {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+
+    match tokio::time::timeout(timeout, child).await {
+        Err(_) => (
+            ScanStatus::Error,
+            format!("scan timed out after {}s", timeout.as_secs()),
+        ),
+        Ok(Err(e)) => (ScanStatus::Error, format!("failed to run scan command: {}", e)),
+        Ok(Ok(output)) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            let status = match output.status.code() {
+                Some(0) => ScanStatus::Clean,
+                Some(1) => ScanStatus::Infected,
+                _ => {
+                    if text.trim().is_empty() {
+                        text = format!("scan command exited with {}", output.status);
+                    }
+                    ScanStatus::Error
+                }
+            };
+            (status, text)
+        }
+    }
+}
+
+/// 셸 명령에 넣을 수 있도록 작은따옴표로 감싸기
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
@@ -0,0 +1,115 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::db::{self, PollHint};
+use crate::AppState;
+
+/// 전체 폴링 간격 힌트를 다시 읽는 주기 (다른 인스턴스에서 바꾼 힌트도 이 안에 반영)
+const HINT_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// 체크인 부하 상태와 폴링 간격 힌트 캐시 (인스턴스별)
+///
+/// 체크인마다 설정 테이블을 읽지 않도록 힌트를 잠시 캐시한다.
+#[derive(Debug, Default)]
+pub struct CheckinLoad {
+    in_flight: AtomicUsize,
+    shed: AtomicU64,
+    hint: Mutex<Option<(Instant, Option<PollHint>)>>,
+}
+
+impl CheckinLoad {
+    /// 지금 처리 중인 체크인 수
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// 503으로 돌려보낸 체크인 수 (시작 이후)
+    pub fn shed_total(&self) -> u64 {
+        self.shed.load(Ordering::Relaxed)
+    }
+
+    /// 힌트를 바꾼 뒤 이 인스턴스가 바로 새 값을 쓰도록 캐시 비움
+    pub fn invalidate_hint(&self) {
+        *self.hint.lock().unwrap() = None;
+    }
+
+    /// 유효한 전체 폴링 간격 힌트 (읽지 못하면 힌트 없이 체크인을 계속 처리)
+    pub async fn poll_hint(&self, pool: &sqlx::PgPool) -> Option<PollHint> {
+        if let Some((read_at, hint)) = self.hint.lock().unwrap().as_ref() {
+            if read_at.elapsed() < HINT_CACHE_TTL {
+                return hint.clone().filter(|h| h.expires_at > chrono::Utc::now());
+            }
+        }
+        let hint = match db::get_poll_hint(pool).await {
+            Ok(hint) => hint,
+            Err(e) => {
+                tracing::warn!("Failed to read poll hint: {}", e);
+                return None;
+            }
+        };
+        *self.hint.lock().unwrap() = Some((Instant::now(), hint.clone()));
+        hint
+    }
+}
+
+/// 처리 중인 체크인 자리 (CHECKIN_SHED_IN_FLIGHT에 이르면 503 + Retry-After로 거부)
+///
+/// 핸들러가 끝나 값이 drop되면 자리를 반납한다.
+#[derive(Debug)]
+pub struct CheckinSlot {
+    load: Arc<CheckinLoad>,
+    /// 이 요청을 포함한 처리 중인 체크인 수
+    in_flight: usize,
+}
+
+impl Drop for CheckinSlot {
+    fn drop(&mut self) {
+        self.load.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for CheckinSlot {
+    type Rejection = Response;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let load = state.checkin_load.clone();
+        let in_flight = load.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        let slot = Self { load, in_flight };
+
+        let limit = state.config.checkin_shed_in_flight;
+        if limit > 0 && in_flight > limit {
+            slot.load.shed.fetch_add(1, Ordering::Relaxed);
+            let retry_after = state.config.checkin_shed_retry_secs;
+            tracing::debug!("Shedding checkin ({} in flight, limit {})", in_flight, limit);
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                format!("Server busy: retry checkin in {}s", retry_after),
+            )
+                .into_response());
+        }
+        Ok(slot)
+    }
+}
+
+/// 체크인 응답에 붙일 다음 폴링 간격 (전체 힌트와 부하 분산 규칙 중 긴 값, 둘 다 없으면 None)
+///
+/// 처리 중인 체크인이 CHECKIN_SHED_IN_FLIGHT의 절반을 넘으면 거부 전에 미리 간격을 늘린다.
+pub async fn next_poll_secs(state: &AppState, slot: &CheckinSlot) -> Option<u64> {
+    let hint = state
+        .checkin_load
+        .poll_hint(&state.pool)
+        .await
+        .map(|h| h.next_poll_secs as u64);
+    let limit = state.config.checkin_shed_in_flight;
+    let shed = (limit > 0 && slot.in_flight * 2 > limit).then_some(state.config.checkin_shed_retry_secs);
+    hint.max(shed)
+}
@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+use crate::api;
+use crate::AppState;
+
+/// 주기 작업 실행 간격
+const TASK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 주기 백그라운드 작업 시작 (리더 인스턴스에서만 실행, `leader::spawn` 참고)
+///
+/// - 만료 임박 공유 링크 알림 (SHARE_EXPIRY_WARNING_SECS)
+pub fn spawn(state: AppState) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(TASK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if !state.leadership.is_leader() {
+                continue;
+            }
+            match api::warn_expiring_share_links(&state).await {
+                Ok(0) => {}
+                Ok(count) => tracing::debug!("Sent {} share link expiry warnings", count),
+                Err(e) => tracing::warn!("Share link expiry check failed: {}", e),
+            }
+        }
+    });
+}
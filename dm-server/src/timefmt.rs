@@ -0,0 +1,143 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// API 타임스탬프 직렬화 (RFC3339, UTC `Z` 표기 고정)
+///
+/// 역직렬화는 오프셋이 명시된 RFC3339만 허용하고 UTC로 변환한다.
+/// 오프셋 없는(naive) 값은 거부한다.
+pub mod rfc3339 {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::format_utc(value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_utc(&s).map_err(serde::de::Error::custom)
+    }
+
+    /// Option<DateTime<Utc>> 용
+    pub mod option {
+        use chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<DateTime<Utc>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(v) => super::serialize(v, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<DateTime<Utc>>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|s| super::super::parse_utc(&s).map_err(serde::de::Error::custom))
+                .transpose()
+        }
+    }
+}
+
+/// UTC RFC3339 문자열로 변환
+pub fn format_utc(value: &DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// RFC3339 문자열 파싱 (오프셋 필수, UTC로 변환)
+pub fn parse_utc(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| format!("invalid RFC3339 timestamp '{}': {}", s, e))
+}
+
+/// 리포트 조회 시 로컬 표시용 타임존 지정
+/// Query: tz=Asia/Seoul
+#[derive(Debug, Default, Deserialize)]
+pub struct TzQuery {
+    #[serde(default)]
+    pub tz: Option<String>,
+}
+
+impl TzQuery {
+    /// IANA 타임존 이름 파싱
+    pub fn parse(&self) -> Result<Option<Tz>, (StatusCode, String)> {
+        self.tz
+            .as_deref()
+            .map(|name| {
+                name.parse::<Tz>()
+                    .map_err(|_| (StatusCode::BAD_REQUEST, format!("Unknown time zone: {}", name)))
+            })
+            .transpose()
+    }
+}
+
+/// UTC 필드 옆에 로컬 표시 문자열(`<field>_local`)을 추가하는 응답
+///
+/// 타임존이 없으면 원본 그대로 직렬화한다.
+pub struct Localized<T>(pub T, pub Option<Tz>);
+
+impl<T: Serialize> IntoResponse for Localized<T> {
+    fn into_response(self) -> Response {
+        let Localized(body, tz) = self;
+        let Some(tz) = tz else {
+            return Json(body).into_response();
+        };
+
+        match serde_json::to_value(&body) {
+            Ok(mut value) => {
+                add_local_display(&mut value, tz);
+                Json(value).into_response()
+            }
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+}
+
+/// UTC 타임스탬프 문자열 필드마다 `<field>_local` 표시 문자열 추가 (재귀)
+fn add_local_display(value: &mut Value, tz: Tz) {
+    match value {
+        Value::Object(map) => {
+            let locals: Vec<(String, String)> = map
+                .iter()
+                .filter_map(|(key, v)| {
+                    let s = v.as_str()?;
+                    // 정규화된 UTC 값만 대상 (메타데이터 등 임의 문자열 제외)
+                    if !s.ends_with('Z') {
+                        return None;
+                    }
+                    let dt = parse_utc(s).ok()?;
+                    Some((
+                        format!("{}_local", key),
+                        dt.with_timezone(&tz).format("%Y-%m-%d %H:%M:%S %Z").to_string(),
+                    ))
+                })
+                .collect();
+
+            // metadata는 사용자 데이터이므로 변경하지 않음
+            for (key, v) in map.iter_mut() {
+                if key != "metadata" {
+                    add_local_display(v, tz);
+                }
+            }
+            map.extend(locals.into_iter().map(|(k, v)| (k, Value::String(v))));
+        }
+        Value::Array(items) => {
+            for v in items {
+                add_local_display(v, tz);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,47 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, StatusCode},
+};
+use std::net::SocketAddr;
+
+use crate::prefix;
+use crate::AppState;
+
+/// 체크인이 HTTPS로 들어왔는지 확인 (REQUIRE_TLS_CLIENTS=true일 때만 검사)
+///
+/// 서버는 평문 HTTP로 수신하므로 TLS는 앞단 프록시가 종료한다. 신뢰하는 프록시(TRUSTED_PROXIES)가
+/// `X-Forwarded-Proto: https`를 보낸 요청만 받고, 나머지(직접 접속, 평문 HTTP)는 403으로 거부한다.
+#[derive(Debug, Clone, Copy)]
+pub struct RequireTls;
+
+#[async_trait]
+impl FromRequestParts<AppState> for RequireTls {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        if !state.config.require_tls_clients {
+            return Ok(Self);
+        }
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        // 프록시를 여러 번 거치면 "https, http"처럼 쌓이므로 첫 값(클라이언트가 접속한 프로토콜)
+        let https = peer
+            .filter(|ip| prefix::is_trusted(&state.config.trusted_proxies, *ip))
+            .and_then(|_| parts.headers.get("X-Forwarded-Proto"))
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"));
+
+        if !https {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "HTTPS required: this server only accepts checkins over TLS (set DM_SERVER_URL to https://)"
+                    .to_string(),
+            ));
+        }
+        Ok(Self)
+    }
+}
@@ -0,0 +1,844 @@
+use axum::{
+    extract::{
+        multipart::{Field, MultipartError},
+        Multipart,
+    },
+    http::{HeaderMap, StatusCode},
+};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+
+use crate::api::artifacts::{artifact_extension, sanitize_file_name};
+use crate::api::versions::{validate_product, validate_severity, SEVERITIES};
+use crate::changelog;
+use crate::config::Config;
+use crate::db;
+use crate::AppState;
+
+/// 업로드 주체 최대 길이 (versions.uploaded_by 컬럼 크기)
+const MAX_UPLOADED_BY_LEN: usize = 128;
+
+/// 설치 스크립트 최대 크기 (바이트)
+const MAX_SCRIPT_LEN: usize = 64 * 1024;
+
+/// 배포 유형 (app: 서비스 디렉토리에 설치하는 아카이브, image: A/B 슬롯에 쓰는 디스크 이미지)
+const DEPLOY_TYPES: [&str; 2] = ["app", "image"];
+
+/// 클라이언트가 풀 수 있는 애플리케이션 아카이브 확장자 (tar + gzip/zstd, 압축하지 않은 tar, zip)
+const ARCHIVE_EXTENSIONS: [&str; 6] = ["tar.gz", "tgz", "tar.zst", "tzst", "tar", "zip"];
+
+/// 아티팩트 최상위의 제품 식별 파일 (`dm-client package --product`가 작성)
+const PRODUCT_FILE: &str = ".dm-product";
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const ZIP_MAGIC: [u8; 4] = *b"PK\x03\x04";
+const ZIP_EMPTY_MAGIC: [u8; 4] = *b"PK\x05\x06";
+const XZ_MAGIC: [u8; 6] = [0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const BZIP2_MAGIC: [u8; 3] = *b"BZh";
+/// tar 첫 헤더의 ustar magic 위치
+const USTAR_MAGIC_OFFSET: usize = 257;
+
+/// 받는 중인 아티팩트 임시 파일 이름 접두사
+const SPOOL_PREFIX: &str = ".upload-";
+
+/// 아티팩트 외 필드(변경 이력, 스크립트 등)에 허용하는 요청 본문 여유분
+const FORM_OVERHEAD_BYTES: usize = changelog::MAX_CHANGELOG_LEN + 1024 * 1024;
+
+/// 업로드 요청 본문 최대 크기 (아티팩트 제한 + 다른 필드 여유분)
+pub fn body_limit(config: &Config) -> usize {
+    usize::try_from(config.max_artifact_bytes)
+        .unwrap_or(usize::MAX)
+        .saturating_add(FORM_OVERHEAD_BYTES)
+}
+
+/// 업로드 multipart 필드 (검증 전 원본)
+#[derive(Debug, Default)]
+pub struct UploadForm {
+    pub uploaded_by: Option<String>,
+    pub version: Option<String>,
+    pub release_notes: Option<String>,
+    pub changelog: Option<Vec<u8>>,
+    pub git_commit: Option<String>,
+    pub build_time: Option<String>,
+    pub checksum: Option<String>,
+    pub pre_install_script: Option<String>,
+    pub post_install_script: Option<String>,
+    pub deploy_type: Option<String>,
+    pub severity: Option<String>,
+    pub product: Option<String>,
+    /// `metadata`(JSON, 키 None)와 `metadata.<key>`(텍스트), 받은 순서대로
+    pub metadata: Vec<(Option<String>, String)>,
+    pub artifact: Option<SpooledArtifact>,
+    pub file_name: Option<String>,
+    /// 아티팩트 없이 검증할 때 선언하는 크기 (POST /api/versions/validate)
+    pub artifact_size: Option<String>,
+    /// 아티팩트 ed25519 서명 (base64)
+    pub signature: Option<String>,
+}
+
+impl UploadForm {
+    /// 요청 헤더와 multipart 필드 읽기 (값 검증은 `validate`에서)
+    ///
+    /// 아티팩트는 메모리에 모으지 않고 `spool_dir`의 임시 파일로 바로 쓴다.
+    pub async fn read(
+        spool_dir: &Path,
+        headers: &HeaderMap,
+        mut multipart: Multipart,
+    ) -> Result<Self, (StatusCode, String)> {
+        let mut form = Self {
+            uploaded_by: headers
+                .get("X-Uploaded-By")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+            ..Default::default()
+        };
+
+        while let Some(field) = multipart.next_field().await.map_err(field_error)? {
+            let name = field.name().unwrap_or("").to_string();
+            match name.as_str() {
+                "artifact" => {
+                    form.file_name = field.file_name().map(str::to_string);
+                    form.artifact = Some(SpooledArtifact::receive(spool_dir, field).await?);
+                }
+                "changelog" => form.changelog = Some(field.bytes().await.map_err(field_error)?.to_vec()),
+                "metadata" => form.metadata.push((None, field.text().await.map_err(field_error)?)),
+                key if key.starts_with("metadata.") => {
+                    let key = key["metadata.".len()..].to_string();
+                    form.metadata.push((Some(key), field.text().await.map_err(field_error)?));
+                }
+                "version" | "release_notes" | "git_commit" | "build_time" | "checksum" | "pre_install_script"
+                | "post_install_script" | "deploy_type" | "severity" | "product" | "file_name" | "artifact_size" | "signature" => {
+                    let text = field.text().await.map_err(field_error)?;
+                    let slot = match name.as_str() {
+                        "version" => &mut form.version,
+                        "release_notes" => &mut form.release_notes,
+                        "git_commit" => &mut form.git_commit,
+                        "build_time" => &mut form.build_time,
+                        "checksum" => &mut form.checksum,
+                        "pre_install_script" => &mut form.pre_install_script,
+                        "post_install_script" => &mut form.post_install_script,
+                        "deploy_type" => &mut form.deploy_type,
+                        "severity" => &mut form.severity,
+                        "product" => &mut form.product,
+                        "file_name" => &mut form.file_name,
+                        "signature" => &mut form.signature,
+                        _ => &mut form.artifact_size,
+                    };
+                    *slot = Some(text);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(form)
+    }
+}
+
+/// 디스크에 받아 둔 아티팩트 (drop되면 임시 파일 삭제)
+#[derive(Debug)]
+pub struct SpooledArtifact {
+    pub file: NamedTempFile,
+    pub size: u64,
+    /// 받으면서 계산한 SHA256 (hex)
+    pub checksum: String,
+}
+
+impl SpooledArtifact {
+    /// multipart 필드를 조각 단위로 임시 파일에 쓰면서 크기와 SHA256 계산
+    async fn receive(dir: &Path, mut field: Field<'_>) -> Result<Self, (StatusCode, String)> {
+        let internal = |e: std::io::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+        tokio::fs::create_dir_all(dir).await.map_err(internal)?;
+        let file = tempfile::Builder::new()
+            .prefix(SPOOL_PREFIX)
+            .tempfile_in(dir)
+            .map_err(internal)?;
+        let mut out = tokio::fs::File::from_std(file.as_file().try_clone().map_err(internal)?);
+
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        while let Some(chunk) = field.chunk().await.map_err(field_error)? {
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+            out.write_all(&chunk).await.map_err(internal)?;
+        }
+        out.flush().await.map_err(internal)?;
+
+        Ok(Self {
+            file,
+            size,
+            checksum: format!("{:x}", hasher.finalize()),
+        })
+    }
+
+    /// 아카이브 형식 확인과 `.dm-product` 읽기 (파일을 처음부터 다시 읽음). (형식의 확장자, 제품) 반환
+    async fn inspect(&self) -> Result<(&'static str, Option<String>), String> {
+        let file = self
+            .file
+            .reopen()
+            .map_err(|e| format!("Unreadable artifact: {}", e))?;
+        tokio::task::spawn_blocking(move || inspect_archive(file))
+            .await
+            .map_err(|e| format!("Archive inspection failed: {}", e))?
+    }
+
+    /// 디스크 이미지 압축 형식 확인 (파일 앞부분만 읽음). 형식의 확장자 반환
+    async fn inspect_image(&self) -> Result<&'static str, String> {
+        let file = self
+            .file
+            .reopen()
+            .map_err(|e| format!("Unreadable artifact: {}", e))?;
+        tokio::task::spawn_blocking(move || inspect_image(file))
+            .await
+            .map_err(|e| format!("Image inspection failed: {}", e))?
+    }
+}
+
+/// multipart 읽기 실패 (본문 크기 제한을 넘으면 413)
+fn field_error(e: MultipartError) -> (StatusCode, String) {
+    (e.status(), e.body_text())
+}
+
+/// 규칙 결과
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleStatus {
+    Pass,
+    Fail,
+    /// 판단에 필요한 값이 없음 (예: 아티팩트 없이 검증)
+    Skipped,
+}
+
+/// 검증 규칙 하나의 결과
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleResult {
+    pub rule: &'static str,
+    pub status: RuleStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// 실제 업로드가 이 규칙으로 거부될 때의 응답 코드
+    #[serde(skip)]
+    pub code: StatusCode,
+}
+
+/// 업로드 검증 결과 (POST /api/versions/validate 응답)
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    /// "artifact" (파일 포함) 또는 "metadata" (크기와 체크섬만)
+    pub mode: &'static str,
+    pub version: Option<String>,
+    pub checksum: Option<String>,
+    pub artifact_size: Option<u64>,
+    pub rules: Vec<RuleResult>,
+}
+
+impl ValidationReport {
+    /// 첫 번째 실패 규칙 (실제 업로드의 오류 응답)
+    pub fn first_failure(&self) -> Option<(StatusCode, String)> {
+        self.rules
+            .iter()
+            .find(|r| r.status == RuleStatus::Fail)
+            .map(|r| (r.code, r.message.clone().unwrap_or_default()))
+    }
+}
+
+/// 검증을 통과한 업로드 (저장에 쓰는 값)
+#[derive(Debug)]
+pub struct ValidatedUpload {
+    pub version: String,
+    pub uploaded_by: Option<String>,
+    pub release_notes: Option<String>,
+    pub changelog: Option<String>,
+    pub git_commit: Option<String>,
+    pub build_time: Option<DateTime<Utc>>,
+    pub checksum: String,
+    pub original_filename: Option<String>,
+    pub pre_install_script: Option<String>,
+    pub post_install_script: Option<String>,
+    pub deploy_type: String,
+    pub severity: String,
+    pub product: Option<String>,
+    pub signature: Option<String>,
+    pub metadata: serde_json::Value,
+    /// 내용으로 판별한 형식의 확장자 (파일 이름 없이 올린 아티팩트의 저장 이름에 사용)
+    pub detected_extension: Option<&'static str>,
+}
+
+#[derive(Default)]
+struct Rules(Vec<RuleResult>);
+
+impl Rules {
+    fn check(&mut self, rule: &'static str, code: StatusCode, result: Result<(), String>) -> bool {
+        let passed = result.is_ok();
+        self.0.push(RuleResult {
+            rule,
+            status: if passed { RuleStatus::Pass } else { RuleStatus::Fail },
+            message: result.err(),
+            code,
+        });
+        passed
+    }
+
+    fn skip(&mut self, rule: &'static str, reason: &str) {
+        self.0.push(RuleResult {
+            rule,
+            status: RuleStatus::Skipped,
+            message: Some(reason.to_string()),
+            code: StatusCode::OK,
+        });
+    }
+}
+
+/// 업로드 규칙 검사 (실제 업로드와 사전 검증이 함께 사용, DB와 파일은 읽기만 함)
+///
+/// 모든 규칙을 끝까지 검사해 결과를 모으며, 아티팩트가 있고 모두 통과했을 때만 저장할 값을 돌려준다.
+pub async fn validate(
+    state: &AppState,
+    form: &UploadForm,
+) -> Result<(ValidationReport, Option<ValidatedUpload>), (StatusCode, String)> {
+    let mut rules = Rules::default();
+    let text = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+
+    let uploaded_by = text(&form.uploaded_by);
+    rules.check(
+        "uploaded_by",
+        StatusCode::BAD_REQUEST,
+        match &uploaded_by {
+            Some(u) if u.len() > MAX_UPLOADED_BY_LEN => Err(format!(
+                "X-Uploaded-By must be at most {} characters",
+                MAX_UPLOADED_BY_LEN
+            )),
+            _ => Ok(()),
+        },
+    );
+
+    let release_notes = form.release_notes.clone().filter(|t| !t.trim().is_empty());
+    rules.check(
+        "release_notes",
+        StatusCode::UNPROCESSABLE_ENTITY,
+        match &release_notes {
+            Some(notes) if notes.len() > changelog::MAX_RELEASE_NOTES_LEN => Err(format!(
+                "release_notes must be at most {} bytes (got {}); upload the full text as the changelog field",
+                changelog::MAX_RELEASE_NOTES_LEN,
+                notes.len()
+            )),
+            _ => Ok(()),
+        },
+    );
+
+    let mut changelog_text = None;
+    rules.check(
+        "changelog",
+        StatusCode::UNPROCESSABLE_ENTITY,
+        match &form.changelog {
+            Some(data) if data.len() > changelog::MAX_CHANGELOG_LEN => Err(format!(
+                "changelog must be at most {} bytes",
+                changelog::MAX_CHANGELOG_LEN
+            )),
+            Some(data) => match String::from_utf8(data.clone()) {
+                Ok(text) => {
+                    changelog_text = Some(text).filter(|t| !t.trim().is_empty());
+                    Ok(())
+                }
+                Err(_) => Err("changelog must be UTF-8 text".to_string()),
+            },
+            None => Ok(()),
+        },
+    );
+
+    let deploy_type = text(&form.deploy_type).unwrap_or_else(|| DEPLOY_TYPES[0].to_string());
+    let is_image = deploy_type == "image";
+    rules.check(
+        "deploy_type",
+        StatusCode::BAD_REQUEST,
+        if DEPLOY_TYPES.contains(&deploy_type.as_str()) {
+            Ok(())
+        } else {
+            Err(format!("deploy_type must be one of: {}", DEPLOY_TYPES.join(", ")))
+        },
+    );
+
+    let pre_install_script = form.pre_install_script.clone().filter(|t| !t.trim().is_empty());
+    let post_install_script = form.post_install_script.clone().filter(|t| !t.trim().is_empty());
+    let oversized_script = [
+        ("pre_install_script", &form.pre_install_script),
+        ("post_install_script", &form.post_install_script),
+    ]
+    .into_iter()
+    .find(|(_, script)| script.as_ref().is_some_and(|s| s.len() > MAX_SCRIPT_LEN));
+    rules.check(
+        "install_scripts",
+        StatusCode::BAD_REQUEST,
+        if let Some((name, _)) = oversized_script {
+            Err(format!("{} must be at most {} bytes", name, MAX_SCRIPT_LEN))
+        } else if is_image && (pre_install_script.is_some() || post_install_script.is_some()) {
+            // 이미지는 재부팅 전후로 나뉘어 설치 스크립트를 실행할 시점이 없음
+            Err("Install scripts are not supported for image versions".to_string())
+        } else {
+            Ok(())
+        },
+    );
+
+    let severity = text(&form.severity).unwrap_or_else(|| SEVERITIES[0].to_string());
+    rules.check(
+        "severity",
+        StatusCode::BAD_REQUEST,
+        validate_severity(&severity).map_err(|(_, message)| message),
+    );
+
+    // 서명은 형식만 확인 (검증은 공개 키를 가진 장비가 DM_UPDATE_PUBLIC_KEY로)
+    let signature = text(&form.signature);
+    rules.check(
+        "signature",
+        StatusCode::BAD_REQUEST,
+        match &signature {
+            Some(signature) => match base64::engine::general_purpose::STANDARD.decode(signature) {
+                Ok(bytes) if bytes.len() == 64 => Ok(()),
+                _ => Err("signature must be a base64-encoded 64-byte ed25519 signature".to_string()),
+            },
+            None => Ok(()),
+        },
+    );
+
+    let product = text(&form.product);
+    rules.check(
+        "product",
+        StatusCode::BAD_REQUEST,
+        match &product {
+            Some(product) => validate_product(product).map_err(|(_, message)| message),
+            None => Ok(()),
+        },
+    );
+
+    let mut metadata = serde_json::Map::new();
+    rules.check("metadata", StatusCode::BAD_REQUEST, {
+        let mut result = Ok(());
+        for (key, value) in &form.metadata {
+            match key {
+                Some(key) => {
+                    metadata.insert(key.clone(), serde_json::Value::String(value.clone()));
+                }
+                None => match serde_json::from_str::<serde_json::Value>(value) {
+                    Ok(serde_json::Value::Object(map)) => metadata.extend(map),
+                    Ok(_) => result = Err("metadata must be a JSON object".to_string()),
+                    Err(e) => result = Err(format!("Invalid metadata JSON: {}", e)),
+                },
+            }
+        }
+        result
+    });
+
+    let version = form.version.clone();
+    let semver_ok = rules.check(
+        "version",
+        StatusCode::BAD_REQUEST,
+        match &version {
+            Some(v) => semver::Version::parse(v)
+                .map(|_| ())
+                .map_err(|e| format!("Invalid semver: {}", e)),
+            None => Err("version field required".to_string()),
+        },
+    );
+
+    let mut build_time = None;
+    rules.check(
+        "build_time",
+        StatusCode::BAD_REQUEST,
+        match text(&form.build_time) {
+            Some(t) => DateTime::parse_from_rfc3339(&t)
+                .map(|dt| build_time = Some(dt.with_timezone(&Utc)))
+                .map_err(|e| format!("Invalid build_time: {}", e)),
+            None => Ok(()),
+        },
+    );
+
+    match version.as_deref().filter(|_| semver_ok) {
+        Some(v) => {
+            let exists = db::get_version(&state.pool, v)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .is_some();
+            rules.check(
+                "version_available",
+                StatusCode::CONFLICT,
+                if exists { Err(format!("Version {} already exists", v)) } else { Ok(()) },
+            );
+        }
+        None => rules.skip("version_available", "version is missing or invalid"),
+    }
+
+    // 아티팩트 또는 선언된 크기
+    let expected_checksum = text(&form.checksum).map(|c| c.to_lowercase());
+    let declared_size = text(&form.artifact_size);
+    let artifact = form.artifact.as_ref();
+    let mut artifact_size = artifact.map(|a| a.size);
+    rules.check(
+        "artifact",
+        StatusCode::BAD_REQUEST,
+        match (artifact, &declared_size) {
+            (Some(_), _) => Ok(()),
+            (None, Some(size)) => size
+                .parse::<u64>()
+                .map(|size| artifact_size = Some(size))
+                .map_err(|_| format!("artifact_size must be a byte count, got {:?}", size)),
+            (None, None) => Err("artifact file required".to_string()),
+        },
+    );
+
+    let max = state.config.max_artifact_bytes;
+    match artifact_size {
+        Some(size) => {
+            rules.check(
+                "artifact_size",
+                StatusCode::PAYLOAD_TOO_LARGE,
+                if size > max {
+                    Err(format!("Artifact is {} bytes (limit {})", size, max))
+                } else {
+                    Ok(())
+                },
+            );
+        }
+        None => rules.skip("artifact_size", "artifact size unknown"),
+    }
+
+    let checksum = match artifact {
+        Some(spooled) => {
+            let checksum = spooled.checksum.clone();
+            // CI에서 보낸 체크섬과 비교 (전송 중 손상 감지)
+            rules.check(
+                "checksum",
+                StatusCode::BAD_REQUEST,
+                match &expected_checksum {
+                    Some(expected) if *expected != checksum => Err(format!(
+                        "Checksum mismatch: received artifact is {}, expected {}",
+                        checksum, expected
+                    )),
+                    _ => Ok(()),
+                },
+            );
+            Some(checksum)
+        }
+        None => {
+            let valid = expected_checksum
+                .as_deref()
+                .is_some_and(|c| c.len() == 64 && c.chars().all(|ch| ch.is_ascii_hexdigit()));
+            rules.check(
+                "checksum",
+                StatusCode::BAD_REQUEST,
+                if valid {
+                    Ok(())
+                } else {
+                    Err("checksum (SHA256 hex) is required when validating without the artifact".to_string())
+                },
+            );
+            expected_checksum.clone().filter(|_| valid)
+        }
+    };
+
+    // 클라이언트가 설치할 수 있는 아카이브인지 (이름과 내용)
+    let original_filename = form.file_name.as_deref().and_then(sanitize_file_name);
+    let mut archive_product = None;
+    let mut detected_extension = None;
+    if is_image {
+        match artifact {
+            Some(spooled) => {
+                let result = spooled.inspect_image().await.map(|ext| detected_extension = Some(ext));
+                rules.check("archive_format", StatusCode::UNPROCESSABLE_ENTITY, result);
+            }
+            None => rules.skip("archive_format", "artifact contents not uploaded"),
+        }
+    } else {
+        let extension = original_filename.as_deref().and_then(artifact_extension);
+        let result = match (&extension, artifact) {
+            (Some(ext), _) if !ARCHIVE_EXTENSIONS.contains(&ext.as_str()) => Some(Err(format!(
+                "Unrecognized archive extension .{} (expected one of: {})",
+                ext,
+                ARCHIVE_EXTENSIONS.map(|e| format!(".{}", e)).join(", ")
+            ))),
+            (_, Some(spooled)) => Some(spooled.inspect().await.map(|(ext, product)| {
+                detected_extension = Some(ext);
+                archive_product = product;
+            })),
+            (Some(_), None) => Some(Ok(())),
+            (None, None) => {
+                rules.skip("archive_format", "no file_name or artifact contents to check");
+                None
+            }
+        };
+        if let Some(result) = result {
+            rules.check("archive_format", StatusCode::UNPROCESSABLE_ENTITY, result);
+        }
+    }
+
+    // 선언한 제품과 아티팩트의 `.dm-product`가 같아야 장비가 설치함
+    match (&product, artifact) {
+        _ if is_image => rules.skip("product_marker", "disk image versions have no product marker"),
+        (None, _) => rules.skip("product_marker", "no product declared"),
+        (Some(_), None) => rules.skip("product_marker", "artifact contents not uploaded"),
+        (Some(expected), Some(_)) => {
+            rules.check(
+                "product_marker",
+                StatusCode::UNPROCESSABLE_ENTITY,
+                match &archive_product {
+                    Some(found) if found == expected => Ok(()),
+                    Some(found) => Err(format!(
+                        "Artifact {} declares product {}, but the upload declares {}",
+                        PRODUCT_FILE, found, expected
+                    )),
+                    None => Err(format!(
+                        "Artifact has no {} for product {} (build it with `dm-client package --product {}`)",
+                        PRODUCT_FILE, expected, expected
+                    )),
+                },
+            );
+        }
+    }
+
+    let valid = rules.0.iter().all(|r| r.status != RuleStatus::Fail);
+    let report = ValidationReport {
+        valid,
+        mode: if artifact.is_some() { "artifact" } else { "metadata" },
+        version: version.clone(),
+        checksum: checksum.clone(),
+        artifact_size,
+        rules: rules.0,
+    };
+
+    let upload = match (valid, artifact, version, checksum) {
+        (true, Some(_), Some(version), Some(checksum)) => Some(ValidatedUpload {
+            version,
+            uploaded_by,
+            release_notes,
+            changelog: changelog_text,
+            git_commit: text(&form.git_commit),
+            build_time,
+            checksum,
+            original_filename,
+            pre_install_script,
+            post_install_script,
+            deploy_type,
+            severity,
+            product,
+            signature,
+            metadata: serde_json::Value::Object(metadata),
+            detected_extension,
+        }),
+        _ => None,
+    };
+
+    Ok((report, upload))
+}
+
+/// tar.gz/tar.zst/tar/zip 아티팩트를 끝까지 읽어 형식 확인, (형식의 확장자, 콘텐츠 루트의 `.dm-product`) 반환
+///
+/// 형식은 클라이언트와 같이 시작 바이트로 판별한다.
+/// 루트는 클라이언트 설치와 같다 (최상위 항목이 디렉토리 하나뿐이면 그 디렉토리).
+fn inspect_archive(file: File) -> Result<(&'static str, Option<String>), String> {
+    let mut data = BufReader::new(file);
+    let head = data.fill_buf().map_err(|e| format!("Unreadable artifact: {}", e))?;
+    let mut index = ArchiveIndex::default();
+    if head.starts_with(&ZIP_MAGIC) || head.starts_with(&ZIP_EMPTY_MAGIC) {
+        inspect_zip(data, &mut index)?;
+        return Ok(("zip", index.product()));
+    }
+    let (extension, reader): (&'static str, Box<dyn Read>) = if head.starts_with(&ZSTD_MAGIC) {
+        let decoder = zstd::Decoder::with_buffer(data).map_err(|e| format!("Invalid zstd archive: {}", e))?;
+        ("tar.zst", Box::new(decoder))
+    } else if head.starts_with(&GZIP_MAGIC) {
+        ("tar.gz", Box::new(GzDecoder::new(data)))
+    } else if head.get(USTAR_MAGIC_OFFSET..USTAR_MAGIC_OFFSET + 5) == Some(b"ustar") {
+        ("tar", Box::new(data))
+    } else {
+        return Err("Artifact is not a tar.gz, tar.zst, tar or zip archive".to_string());
+    };
+
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive.entries().map_err(|e| format!("Unreadable tar archive: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Unreadable tar archive: {}", e))?;
+        let path = entry
+            .path()
+            .map_err(|e| format!("Invalid archive path: {}", e))?
+            .into_owned();
+        if let Some(marker) = index.add(&path, entry.header().entry_type().is_dir()) {
+            index.read_marker(marker, &mut entry)?;
+        }
+    }
+    Ok((extension, index.product()))
+}
+
+/// 디스크 이미지는 원본 그대로이거나 gzip이어야 함 (형식의 확장자 `img` 또는 `img.gz` 반환)
+///
+/// 클라이언트는 gzip만 풀어서 슬롯에 쓰므로, 다른 압축 형식은 압축된 채로 슬롯에 쓰이게 된다.
+fn inspect_image(file: File) -> Result<&'static str, String> {
+    let mut data = BufReader::new(file);
+    let head = data.fill_buf().map_err(|e| format!("Unreadable artifact: {}", e))?;
+    let compressed: [(&[u8], &str); 5] = [
+        (&ZSTD_MAGIC, "zstd"),
+        (&XZ_MAGIC, "xz"),
+        (&BZIP2_MAGIC, "bzip2"),
+        (&ZIP_MAGIC, "zip"),
+        (&ZIP_EMPTY_MAGIC, "zip"),
+    ];
+    match compressed.iter().find(|(magic, _)| head.starts_with(magic)) {
+        Some((_, format)) => Err(format!(
+            "Disk image is {} compressed; upload a raw or gzip-compressed image",
+            format
+        )),
+        None if head.starts_with(&GZIP_MAGIC) => Ok("img.gz"),
+        None => Ok("img"),
+    }
+}
+
+/// zip 아티팩트의 항목 목록과 `.dm-product` 읽기 (항목 CRC는 끝까지 읽을 때 확인됨)
+fn inspect_zip(data: BufReader<File>, index: &mut ArchiveIndex) -> Result<(), String> {
+    let mut archive = zip::ZipArchive::new(data).map_err(|e| format!("Unreadable zip archive: {}", e))?;
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Unreadable zip archive: {}", e))?;
+        let path = PathBuf::from(entry.name());
+        let is_dir = entry.is_dir();
+        if let Some(marker) = index.add(&path, is_dir) {
+            index.read_marker(marker, &mut entry)?;
+        } else {
+            std::io::copy(&mut entry, &mut std::io::sink())
+                .map_err(|e| format!("Corrupt zip entry {:?}: {}", path, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// 아카이브 항목에서 모은 최상위 이름과 `.dm-product` 후보
+#[derive(Debug, Default)]
+struct ArchiveIndex {
+    /// 최상위 이름 → 디렉토리인지
+    top_level: HashMap<String, bool>,
+    markers: HashMap<PathBuf, String>,
+}
+
+impl ArchiveIndex {
+    /// 항목 하나 기록. 콘텐츠 루트의 `.dm-product`일 수 있으면 정리한 경로 반환
+    fn add(&mut self, path: &Path, is_dir: bool) -> Option<PathBuf> {
+        let parts: PathBuf = path.components().filter(|c| matches!(c, Component::Normal(_))).collect();
+        let first = parts.components().next()?;
+        let is_dir = is_dir || parts.components().count() > 1;
+        *self
+            .top_level
+            .entry(first.as_os_str().to_string_lossy().into_owned())
+            .or_default() |= is_dir;
+        (parts.file_name().is_some_and(|name| name == PRODUCT_FILE) && parts.components().count() <= 2).then_some(parts)
+    }
+
+    fn read_marker(&mut self, parts: PathBuf, reader: &mut impl Read) -> Result<(), String> {
+        let mut content = String::new();
+        reader
+            .read_to_string(&mut content)
+            .map_err(|e| format!("Unreadable {}: {}", PRODUCT_FILE, e))?;
+        self.markers.insert(parts, content.trim().to_string());
+        Ok(())
+    }
+
+    /// 콘텐츠 루트(최상위 항목이 디렉토리 하나뿐이면 그 디렉토리)의 제품
+    fn product(mut self) -> Option<String> {
+        let root = match self.top_level.iter().collect::<Vec<_>>().as_slice() {
+            [(dir, true)] => PathBuf::from(dir),
+            _ => PathBuf::new(),
+        };
+        self.markers.remove(&root.join(PRODUCT_FILE)).filter(|p| !p.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Seek, Write};
+
+    fn file(bytes: &[u8]) -> File {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(bytes).unwrap();
+        file.rewind().unwrap();
+        file
+    }
+
+    fn image(bytes: &[u8]) -> Result<&'static str, String> {
+        inspect_image(file(bytes))
+    }
+
+    /// `.dm-product`와 `app.txt`가 든 tar
+    fn tar(product: &str) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in [(PRODUCT_FILE, product), ("app.txt", "v1")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, content.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn archive_format_is_detected_from_the_content() {
+        let plain = tar("pos");
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gz.write_all(&plain).unwrap();
+        let gz = gz.finish().unwrap();
+        let zst = zstd::encode_all(&plain[..], 0).unwrap();
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip.start_file(PRODUCT_FILE, zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"pos").unwrap();
+        let zip = zip.finish().unwrap().into_inner();
+
+        for (bytes, extension) in [(&plain, "tar"), (&gz, "tar.gz"), (&zst, "tar.zst"), (&zip, "zip")] {
+            assert_eq!(
+                inspect_archive(file(bytes)),
+                Ok((extension, Some("pos".to_string()))),
+                "{}",
+                extension
+            );
+        }
+    }
+
+    #[test]
+    fn other_content_is_not_an_archive() {
+        assert_eq!(
+            inspect_archive(file(b"not an archive at all")),
+            Err("Artifact is not a tar.gz, tar.zst, tar or zip archive".to_string())
+        );
+    }
+
+    #[test]
+    fn raw_and_gzip_images_are_accepted() {
+        assert_eq!(image(&[0u8; 512]), Ok("img"));
+        assert_eq!(image(b""), Ok("img"));
+        assert_eq!(image(&[0x1f, 0x8b, 0x08, 0x00]), Ok("img.gz"));
+    }
+
+    #[test]
+    fn other_compression_is_rejected() {
+        for (magic, format) in [
+            (&ZSTD_MAGIC[..], "zstd"),
+            (&XZ_MAGIC[..], "xz"),
+            (&BZIP2_MAGIC[..], "bzip2"),
+            (&ZIP_MAGIC[..], "zip"),
+            (&ZIP_EMPTY_MAGIC[..], "zip"),
+        ] {
+            let mut bytes = magic.to_vec();
+            bytes.extend_from_slice(&[0u8; 64]);
+            assert_eq!(
+                image(&bytes),
+                Err(format!("Disk image is {} compressed; upload a raw or gzip-compressed image", format))
+            );
+        }
+    }
+}
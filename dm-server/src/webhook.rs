@@ -0,0 +1,47 @@
+use chrono::Utc;
+use serde_json::Value;
+
+/// 외부 시스템으로 이벤트를 전달하는 Webhook 발송기
+pub struct Webhook {
+    url: Option<String>,
+    client: reqwest::Client,
+}
+
+impl Webhook {
+    pub fn new(url: Option<String>) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// 이벤트 발송 (fire-and-forget)
+    /// WEBHOOK_URL이 설정되지 않은 경우 아무 것도 하지 않음
+    pub fn emit(&self, event: &str, data: Value) {
+        let Some(url) = self.url.clone() else {
+            return;
+        };
+
+        let client = self.client.clone();
+        let body = serde_json::json!({
+            "event": event,
+            "timestamp": Utc::now(),
+            "data": data,
+        });
+        let event = event.to_string();
+
+        tokio::spawn(async move {
+            match client.post(&url).json(&body).send().await {
+                Ok(resp) if !resp.status().is_success() => {
+                    tracing::warn!("Webhook {} rejected: {}", event, resp.status());
+                }
+                Ok(_) => {
+                    tracing::debug!("Webhook {} delivered", event);
+                }
+                Err(e) => {
+                    tracing::warn!("Webhook {} failed: {}", event, e);
+                }
+            }
+        });
+    }
+}